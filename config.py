@@ -10,8 +10,10 @@ TYPESCRIPT_OUTPUT_FILE = "typescript.js"
 TEST_DIRECTORY = "tests"
 TYPESCRIPT_TEST_OUTPUT_FILE = "test.js"
 RUST_DIRECTORY = "rust"
+RUST_FEATURES = "" # Space-separated Cargo features to build with. Use "validation" to catch NaN/infinite values in the physics, or "release-lean" for a smaller wasm to ship.
 RUST_OUTPUT_SUBDIRECTORY = "pkg"
 RUST_OUTPUT_D_TS_FILE = "rust.d.ts"
 RUST_OUTPUT_JS_FILE = "rust.js"
 RUST_OUTPUT_WASM_FILE = "rust_bg.wasm"
 RUST_FINAL_WASM_FILE = "rust.wasm"
+ASSET_MANIFEST_FILE = "manifest.json" # Within the site directory. Asset versions in it are updated to match the files on every build.
@@ -10,6 +10,8 @@ from os.path import join, getmtime, exists
 import os
 import re
 import textwrap
+import json
+import hashlib
 
 import config
 import server
@@ -161,13 +163,35 @@ def fix_wasm_declaration_file(path):
 	with open(path, "w") as output:
 		output.write(before + after)
 
+def update_asset_manifest(directory, manifest_file):
+	"""
+	Sets the version of every asset in the manifest to a hash of its file (so changed files get new URLs, and browsers don't use old cached copies).
+
+	Returns the names of any assets whose files couldn't be found.
+	"""
+	path = join(directory, manifest_file)
+	with open(path, "r") as source:
+		manifest = json.load(source)
+	missing = []
+	for (name, entry) in manifest.get("assets", {}).items():
+		file_path = join(directory, entry.get("url", name).split("?")[0])
+		if not exists(file_path):
+			missing.append(name)
+			continue
+		with open(file_path, "rb") as asset:
+			entry["version"] = hashlib.sha1(asset.read()).hexdigest()[:10]
+	with open(path, "w") as output:
+		json.dump(manifest, output, indent="\t")
+		output.write("\n")
+	return missing
+
 
 
 
 start = time.time()
 print("▶ Running Rust tests...")
 call_cli(
-	"RUST_BACKTRACE=1 cargo test --color=always",
+	f"RUST_BACKTRACE=1 cargo test --color=always --features={config.RUST_FEATURES!r}",
 	directory=config.RUST_DIRECTORY,
 	max_hang=15.0, # A little higher because need to compile several packages.
 )
@@ -180,7 +204,7 @@ print(f"✓ Rust tests done in {time.time() - start:.3f}")
 start = time.time()
 print("▶ Compiling Rust to WASM + JS...")
 call_cli(
-	"wasm-pack build --target no-modules -- --color=always",
+	f"wasm-pack build --target no-modules -- --color=always --features={config.RUST_FEATURES!r}",
 	directory=config.RUST_DIRECTORY,
 )
 print(f"✓ Done compiling Rust in {time.time() - start:.3f} seconds. Copying out results...")
@@ -221,6 +245,16 @@ else:
 
 
 
+start = time.time()
+print("▶ Updating asset manifest versions...")
+for name in update_asset_manifest(config.SITE_DIRECTORY, config.ASSET_MANIFEST_FILE):
+	print(f"  Couldn't find the file for asset {name!r}!")
+print(f"✓ Asset manifest updated in {time.time() - start:.3f} seconds.")
+
+
+
+
+
 start = time.time()
 print("▶ Checking QUnit test files...")
 download("https://code.jquery.com/qunit/qunit-2.11.3.css", join(config.SITE_DIRECTORY, "qunit-2.11.3.css"))
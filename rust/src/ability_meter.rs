@@ -0,0 +1,177 @@
+/// Something notable that happened to an AbilityMeter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum AbilityMeterEvent {
+	/// The meter ran out.
+	Emptied,
+	/// The meter filled back up.
+	Filled,
+}
+
+/// A resource that abilities use up and that refills over time (like stamina, or a cooldown).
+///
+/// Abilities either spend a chunk at once with `try_consume()` (like a dash) or drain it while active with `drain()` (like bullet time).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbilityMeter {
+	/// How much is available.
+	current : f32,
+	/// The most there can be.
+	max : f32,
+	/// How fast it refills (per second).
+	regen_rate : f32,
+	/// How long after being used before it starts refilling (in seconds).
+	regen_delay : f32,
+	/// How long since it was last used (in seconds).
+	since_used : f32,
+	/// The events since `take_events()` was last called.
+	events : Vec<AbilityMeterEvent>,
+}
+
+impl AbilityMeter {
+	/// Creates a full meter that starts refilling as soon as it's used.
+	pub fn new(max : f32, regen_rate : f32) -> AbilityMeter {
+		AbilityMeter {
+			current : max,
+			max,
+			regen_rate,
+			regen_delay : 0.0,
+			since_used : 0.0,
+			events : Vec::new(),
+		}
+	}
+
+	/// Makes the meter wait some time after being used before it starts refilling.
+	pub fn with_regen_delay(mut self, delay : f32) -> AbilityMeter {
+		self.regen_delay = delay;
+		self
+	}
+
+	/// How much is available.
+	pub fn get_current(&self) -> f32 {
+		self.current
+	}
+
+	/// The most there can be.
+	pub fn get_max(&self) -> f32 {
+		self.max
+	}
+
+	/// How full it is as a value from 0 to 1.
+	pub fn get_fraction(&self) -> f32 {
+		if self.max <= 0.0 {
+			return 0.0;
+		}
+		self.current / self.max
+	}
+
+	/// The fraction to show on a HUD meter, or None when full (so the meter can hide itself when there's nothing to show).
+	pub fn get_display_fraction(&self) -> Option<f32> {
+		if self.is_full() { None } else { Some(self.get_fraction()) }
+	}
+
+	/// Whether there's nothing left.
+	pub fn is_empty(&self) -> bool {
+		self.current <= 0.0
+	}
+
+	/// Whether it's completely full.
+	pub fn is_full(&self) -> bool {
+		self.max <= self.current
+	}
+
+	/// Whether there's enough to spend some amount.
+	pub fn can_consume(&self, amount : f32) -> bool {
+		amount <= self.current
+	}
+
+	/// Spends some amount, but only if there's enough. Returns whether it was spent.
+	pub fn try_consume(&mut self, amount : f32) -> bool {
+		if !self.can_consume(amount) {
+			return false;
+		}
+		self.spend(amount);
+		true
+	}
+
+	/// Spends as much of some amount as possible (as in when draining the meter over time). Returns how much was actually spent.
+	pub fn drain(&mut self, amount : f32) -> f32 {
+		let spent = amount.max(0.0).min(self.current);
+		self.spend(spent);
+		spent
+	}
+
+	/// Refills it completely.
+	pub fn refill(&mut self) {
+		if !self.is_full() {
+			self.current = self.max;
+			self.events.push(AbilityMeterEvent::Filled);
+		}
+	}
+
+	/// Refills over time (once the regen delay has passed).
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		self.since_used += elapsed_seconds;
+		if self.is_full() || self.since_used < self.regen_delay {
+			return;
+		}
+		self.current = (self.current + self.regen_rate * elapsed_seconds).min(self.max);
+		if self.is_full() {
+			self.events.push(AbilityMeterEvent::Filled);
+		}
+	}
+
+	/// Gets the events since this was last called.
+	pub fn take_events(&mut self) -> Vec<AbilityMeterEvent> {
+		std::mem::take(&mut self.events)
+	}
+
+	/// Removes some amount, noting if that emptied it.
+	fn spend(&mut self, amount : f32) {
+		if amount <= 0.0 {
+			return;
+		}
+		let was_empty = self.is_empty();
+		self.current = (self.current - amount).max(0.0);
+		self.since_used = 0.0;
+		if self.is_empty() && !was_empty {
+			self.events.push(AbilityMeterEvent::Emptied);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_ability_meter {
+	use super::*;
+
+	#[test]
+	fn consuming() {
+		let mut meter = AbilityMeter::new(3.0, 1.0);
+		assert_eq!(meter.get_display_fraction(), None);
+		assert!(meter.try_consume(2.0));
+		assert!(!meter.try_consume(2.0));
+		assert_eq!(meter.get_current(), 1.0);
+		assert_eq!(meter.drain(5.0), 1.0);
+		assert!(meter.is_empty());
+		assert_eq!(meter.take_events(), vec![AbilityMeterEvent::Emptied]);
+		assert_eq!(meter.drain(5.0), 0.0);
+		assert!(meter.take_events().is_empty());
+	}
+
+	#[test]
+	fn regenerating() {
+		let mut meter = AbilityMeter::new(2.0, 1.0).with_regen_delay(0.5);
+		meter.drain(2.0);
+		meter.take_events();
+		meter.update(0.25);
+		assert_eq!(meter.get_current(), 0.0); // Still waiting.
+		meter.update(0.5);
+		assert_eq!(meter.get_current(), 0.5);
+		assert_eq!(meter.get_display_fraction(), Some(0.25));
+		meter.update(10.0);
+		assert!(meter.is_full());
+		assert_eq!(meter.take_events(), vec![AbilityMeterEvent::Filled]);
+		meter.update(1.0);
+		assert!(meter.take_events().is_empty());
+	}
+}
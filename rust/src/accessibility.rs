@@ -0,0 +1,215 @@
+use crate::color::Color;
+use crate::palette::PaletteKind;
+
+/// The setting name for turning off flashes (the damage vignette flash and hit flashes).
+pub const REDUCE_FLASHING_SETTING : &str = "reduceFlashing";
+/// The setting name for the high-contrast palette.
+pub const HIGH_CONTRAST_SETTING : &str = "highContrast";
+/// The setting name for making hold actions (pulling toward a track) work with a tap instead.
+pub const HOLD_TOGGLE_SETTING : &str = "holdToggle";
+/// The setting name for which set of colors the HUD and debug overlays use.
+pub const PALETTE_SETTING : &str = "palette";
+/// The setting name for how big text is drawn.
+pub const TEXT_SCALE_SETTING : &str = "textScale";
+/// The setting name for how big the HUD and its text are drawn (on top of the scaling from the virtual resolution).
+pub const UI_SCALE_SETTING : &str = "uiScale";
+/// The smallest the HUD can be scaled to.
+const MIN_UI_SCALE : f32 = 0.5;
+/// The largest the HUD can be scaled to.
+const MAX_UI_SCALE : f32 = 2.0;
+/// The smallest text can be scaled to.
+const MIN_TEXT_SCALE : f32 = 0.5;
+/// The largest text can be scaled to.
+const MAX_TEXT_SCALE : f32 = 3.0;
+/// What tiles are blended toward in high-contrast mode (so things in front stand out more).
+pub const HIGH_CONTRAST_TILE_TINT : Color = Color { red : 0, green : 0, blue : 0, alpha : 255 };
+/// How much tiles are blended toward their high-contrast tint.
+pub const HIGH_CONTRAST_TILE_AMOUNT : f32 = 0.45;
+/// What enemies are blended toward in high-contrast mode.
+pub const HIGH_CONTRAST_ENEMY_TINT : Color = Color { red : 255, green : 64, blue : 64, alpha : 255 };
+/// How much enemies are blended toward their high-contrast tint.
+pub const HIGH_CONTRAST_ENEMY_AMOUNT : f32 = 0.35;
+
+/// Options that make the game easier to see and play. Saved between visits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilitySettings {
+	/// Whether flashes are turned off.
+	pub reduce_flashing : bool,
+	/// Whether tiles are darkened and enemies are tinted so they're easier to pick out.
+	pub high_contrast : bool,
+	/// Whether press-and-hold actions are toggled by a tap instead.
+	pub hold_toggle : bool,
+	/// How big overlay text is drawn (1 is normal).
+	pub text_scale : f32,
+	/// How big the HUD and its text are drawn (1 is normal).
+	pub ui_scale : f32,
+	/// Which set of colors the HUD and debug overlays use.
+	pub palette : PaletteKind,
+}
+
+impl AccessibilitySettings {
+	/// Creates an instance with everything at its default.
+	pub fn new() -> AccessibilitySettings {
+		AccessibilitySettings {
+			reduce_flashing : false,
+			high_contrast : false,
+			hold_toggle : false,
+			text_scale : 1.0,
+			ui_scale : 1.0,
+			palette : PaletteKind::Standard,
+		}
+	}
+
+	/// Sets a single setting from text. Nothing changes if the name or value isn't valid.
+	pub fn set(&mut self, name : &str, value : &str) -> Result<(), String> {
+		let parse_bool = |value : &str| match value {
+			"true" => Ok(true),
+			"false" => Ok(false),
+			_ => Err(format!("Setting {:?} must be true or false, not {:?}", name, value)),
+		};
+		match name {
+			REDUCE_FLASHING_SETTING => { self.reduce_flashing = parse_bool(value)?; },
+			HIGH_CONTRAST_SETTING => { self.high_contrast = parse_bool(value)?; },
+			HOLD_TOGGLE_SETTING => { self.hold_toggle = parse_bool(value)?; },
+			TEXT_SCALE_SETTING => {
+				match value.parse::<f32>() {
+					Ok(scale) if MIN_TEXT_SCALE <= scale && scale <= MAX_TEXT_SCALE => { self.text_scale = scale; },
+					_ => { return Err(format!("Setting {:?} must be a number from {} to {}, not {:?}", name, MIN_TEXT_SCALE, MAX_TEXT_SCALE, value)); },
+				}
+			},
+			UI_SCALE_SETTING => {
+				match value.parse::<f32>() {
+					Ok(scale) if MIN_UI_SCALE <= scale && scale <= MAX_UI_SCALE => { self.ui_scale = scale; },
+					_ => { return Err(format!("Setting {:?} must be a number from {} to {}, not {:?}", name, MIN_UI_SCALE, MAX_UI_SCALE, value)); },
+				}
+			},
+			PALETTE_SETTING => {
+				match PaletteKind::from_name(value) {
+					Some(kind) => { self.palette = kind; },
+					None => { return Err(format!("There's no palette named {:?}", value)); },
+				}
+			},
+			_ => { return Err(format!("There's no accessibility setting named {:?}", name)); },
+		}
+		Ok(())
+	}
+
+	/// Converts to text for saving (one `name=value` per line).
+	pub fn to_save_string(&self) -> String {
+		format!(
+			"{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}",
+			REDUCE_FLASHING_SETTING, self.reduce_flashing,
+			HIGH_CONTRAST_SETTING, self.high_contrast,
+			HOLD_TOGGLE_SETTING, self.hold_toggle,
+			TEXT_SCALE_SETTING, self.text_scale,
+			UI_SCALE_SETTING, self.ui_scale,
+			PALETTE_SETTING, self.palette.name(),
+		)
+	}
+
+	/// Gets how much to scale the HUD by (in screen pixels per HUD pixel), given how many screen pixels a world unit takes up at normal zoom.
+	pub fn ui_pixel_scale(&self, pixel_scale : f32) -> f32 {
+		self.ui_scale * pixel_scale
+	}
+
+	/// Gets how big overlay text is drawn (1 is the browser's normal size), given how many screen pixels a world unit takes up at normal zoom.
+	pub fn text_pixel_scale(&self, pixel_scale : f32) -> f32 {
+		self.text_scale * self.ui_pixel_scale(pixel_scale)
+	}
+
+	/// Replaces all settings with ones from `to_save_string()`. Anything not mentioned goes back to its default. If anything's wrong with the text, then nothing is changed.
+	pub fn load_save_string(&mut self, text : &str) -> Result<(), String> {
+		let mut loaded = AccessibilitySettings::new();
+		for line in text.lines() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			match line.find('=') {
+				Some(split) => { loaded.set(&line[..split], &line[split+1..])?; },
+				None => { return Err(format!("Missing '=' in {:?}", line)); },
+			}
+		}
+		*self = loaded;
+		Ok(())
+	}
+}
+
+/// Turns a button that normally has to be held into one that's tapped on and off.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct HoldToggle {
+	/// Whether it's acting as if the button is held.
+	held : bool,
+	/// Whether the real button was down last update (so only new presses count).
+	was_down : bool,
+}
+
+impl HoldToggle {
+	/// Creates an instance that isn't held.
+	pub fn new() -> HoldToggle {
+		HoldToggle {
+			held : false,
+			was_down : false,
+		}
+	}
+
+	/// Takes the real button's state. Returns whether to act as if it's held.
+	pub fn update(&mut self, down : bool) -> bool {
+		if down && !self.was_down {
+			self.held = !self.held;
+		}
+		self.was_down = down;
+		self.held
+	}
+
+	/// Stops acting as if it's held (as in when whatever it was held for happened).
+	pub fn release(&mut self) {
+		self.held = false;
+	}
+}
+
+#[cfg(test)]
+mod tests_accessibility {
+	use super::*;
+
+	#[test]
+	fn settings() {
+		let mut settings = AccessibilitySettings::new();
+		assert!(settings.set(HIGH_CONTRAST_SETTING, "true").is_ok());
+		assert!(settings.set(TEXT_SCALE_SETTING, "1.5").is_ok());
+		assert!(settings.set(TEXT_SCALE_SETTING, "10").is_err());
+		assert!(settings.set(REDUCE_FLASHING_SETTING, "yes").is_err());
+		assert!(settings.set("shiny", "true").is_err());
+		assert!(settings.set(PALETTE_SETTING, "redGreenSafe").is_ok());
+		assert!(settings.set(PALETTE_SETTING, "sepia").is_err());
+		assert!(settings.set(UI_SCALE_SETTING, "1.25").is_ok());
+		assert!(settings.set(UI_SCALE_SETTING, "0").is_err());
+		assert_eq!(settings.text_scale, 1.5);
+		// Both scale with the virtual resolution.
+		assert_eq!(settings.ui_pixel_scale(2.0), 2.5);
+		assert_eq!(settings.text_pixel_scale(2.0), 3.75);
+
+		let mut loaded = AccessibilitySettings::new();
+		assert!(loaded.load_save_string(&settings.to_save_string()).is_ok());
+		assert_eq!(loaded, settings);
+		assert!(loaded.load_save_string("holdToggle=true\nnonsense").is_err());
+		assert_eq!(loaded, settings); // Unchanged.
+		assert!(loaded.load_save_string("").is_ok());
+		assert_eq!(loaded, AccessibilitySettings::new());
+	}
+
+	#[test]
+	fn hold_toggle() {
+		let mut toggle = HoldToggle::new();
+		assert!(!toggle.update(false));
+		assert!(toggle.update(true));
+		assert!(toggle.update(false)); // Stays held after letting go.
+		assert!(toggle.update(false));
+		assert!(!toggle.update(true)); // Tapping again lets go.
+		assert!(!toggle.update(true));
+		assert!(!toggle.update(false));
+		assert!(toggle.update(true));
+		toggle.release();
+		assert!(!toggle.update(true)); // Still the same press.
+	}
+}
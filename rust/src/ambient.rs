@@ -0,0 +1,507 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::instanced_buffer::*;
+use crate::random::Random;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::bounds2::Bounds2;
+use crate::geo::consts::EPSILON;
+use crate::tiled::{TiledFile, TiledPoint};
+use crate::origin_rebase::ShiftOrigin;
+use crate::pool::{Pool, PoolStats};
+use crate::spatial_hash::{SpatialHash, DynamicKind};
+use crate::scheduler::Clock;
+
+/// The Tiled point type that marks a spot ambient things come from. The point's name is the kind: "birds", "leaves", or "drips".
+pub const AMBIENT_POINT_TYPE : &str = "ambient";
+/// The seed for placing ambient things. They use their own generator so they never change what the simulation does.
+const AMBIENT_SEED : u32 = 0x0A3B_1E17;
+/// How far outside of the camera's view a spawner still makes things (in pixels).
+const SPAWN_MARGIN : f32 = 96.0;
+/// How far outside of the camera's view things are removed (in pixels). Bigger than `SPAWN_MARGIN` so new things aren't removed straight away.
+const DESPAWN_MARGIN : f32 = 192.0;
+/// The most ambient things that can exist at once.
+const MAX_ENTITIES : usize = 256;
+/// How close a player has to get before perched birds fly off.
+const BIRD_FLEE_RADIUS : f32 = 48.0;
+/// How close a player has to be for fleeing birds to keep steering away from them (past this they keep flying the way they were going).
+const BIRD_WARY_RADIUS : f32 = 192.0;
+/// How fast fleeing birds try to fly.
+const BIRD_FLEE_SPEED : f32 = 160.0;
+/// How quickly fleeing birds can change their velocity.
+const BIRD_FLEE_ACCELERATION : f32 = 480.0;
+/// How fast leaves fall (along gravity).
+const LEAF_FALL_SPEED : f32 = 20.0;
+/// How fast leaves sway side to side (at the peak of a sway).
+const LEAF_SWAY_SPEED : f32 = 18.0;
+/// How many radians per second leaves go through their sway.
+const LEAF_SWAY_RATE : f32 = 2.5;
+/// How long leaves last (in seconds).
+const LEAF_LIFETIME : f32 = 8.0;
+/// How long drips last (in seconds).
+const DRIP_LIFETIME : f32 = 1.5;
+/// How big drips are when checking whether they've landed on something that moves (in pixels).
+const DRIP_RADIUS : f32 = 1.0;
+/// The depth ambient things are drawn at (behind pickups).
+const AMBIENT_DEPTH : f32 = -0.05;
+
+/// The kinds of ambient things.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AmbientKind {
+	/// Perch until the player gets close, then fly off.
+	Birds,
+	/// Drift down, swaying.
+	Leaves,
+	/// Fall straight down.
+	Drips,
+}
+
+impl AmbientKind {
+	/// Gets the kind a Tiled point's name refers to.
+	pub fn from_name(name : &str) -> Option<AmbientKind> {
+		match name {
+			"birds" => Some(AmbientKind::Birds),
+			"leaves" => Some(AmbientKind::Leaves),
+			"drips" => Some(AmbientKind::Drips),
+			_ => None,
+		}
+	}
+}
+
+/// A spot ambient things come from.
+#[derive(Debug, Clone)]
+pub struct AmbientSpawner {
+	/// What it makes.
+	pub kind : AmbientKind,
+	/// Where it is (the middle of the span things come from).
+	pub position : Vec2,
+	/// How wide of a span things come from (centered on the position).
+	pub width : f32,
+	/// How many things are made per second (for leaves and drips).
+	pub rate : f32,
+	/// How many birds perch here.
+	pub count : usize,
+	/// How long until the next thing is made (in seconds).
+	timer : f32,
+}
+
+impl AmbientSpawner {
+	/// Creates an instance from a Tiled point. Uses the point's properties:
+	/// * "width": How wide of a span things come from (defaults to 0).
+	/// * "rate": How many leaves or drips are made per second (defaults to 1).
+	/// * "count": How many birds perch there (defaults to 3).
+	pub fn from_point(point : &TiledPoint) -> Result<AmbientSpawner, String> {
+		let kind = match AmbientKind::from_name(&point.name) {
+			Some(kind) => kind,
+			None => { return Err(format!("Unknown kind {:?}", point.name)); },
+		};
+		let width = match point.get_property("width") {
+			Some(text) => match text.parse::<f32>() {
+				Ok(width) if 0.0 <= width => width,
+				_ => { return Err(format!("Bad width {:?}", text)); },
+			},
+			None => 0.0,
+		};
+		let rate = match point.get_property("rate") {
+			Some(text) => match text.parse::<f32>() {
+				Ok(rate) if 0.0 < rate => rate,
+				_ => { return Err(format!("Bad rate {:?}", text)); },
+			},
+			None => 1.0,
+		};
+		let count = match point.get_property("count") {
+			Some(text) => match text.parse::<usize>() {
+				Ok(count) => count,
+				Err(_) => { return Err(format!("Bad count {:?}", text)); },
+			},
+			None => 3,
+		};
+		Ok(AmbientSpawner::new(kind, &point.position, width, rate, count))
+	}
+
+	/// Creates an instance.
+	pub fn new(kind : AmbientKind, position : &Vec2, width : f32, rate : f32, count : usize) -> AmbientSpawner {
+		AmbientSpawner {
+			kind,
+			position : *position,
+			width,
+			rate,
+			count,
+			timer : 0.0,
+		}
+	}
+}
+
+/// A single ambient thing.
+#[derive(Debug, Clone)]
+pub struct AmbientEntity {
+	/// What it is.
+	pub kind : AmbientKind,
+	/// The index of the spawner that made it.
+	pub spawner : usize,
+	/// Where it is.
+	pub position : Vec2,
+	/// How fast it's moving.
+	pub velocity : Vec2,
+	/// How long it's existed (in seconds).
+	pub age : f32,
+	/// Where in its sway it started (for leaves).
+	phase : f32,
+	/// Whether it's flying off (for birds).
+	pub fleeing : bool,
+}
+
+impl AmbientEntity {
+	/// Creates a blank instance (for the pool to hand out later).
+	fn spare() -> AmbientEntity {
+		AmbientEntity {
+			kind : AmbientKind::Drips,
+			spawner : 0,
+			position : Vec2::zero(),
+			velocity : Vec2::zero(),
+			age : 0.0,
+			phase : 0.0,
+			fleeing : false,
+		}
+	}
+}
+
+/// All of the ambient things: purely decorative, so they're simulated cheaply and only near the camera.
+pub struct Ambient {
+	/// Where things come from.
+	spawners : Vec<AmbientSpawner>,
+	/// Everything that currently exists (recycled, and all made up front since there's a cap anyway).
+	entities : Pool<AmbientEntity>,
+	/// Used to place things.
+	random : Random,
+	/// Which time things move on.
+	clock : Clock,
+}
+
+impl Ambient {
+	/// Creates an instance with nothing in it.
+	pub fn new() -> Ambient {
+		Ambient {
+			spawners : Vec::new(),
+			entities : Pool::new("ambient", MAX_ENTITIES, AmbientEntity::spare),
+			random : Random::new(AMBIENT_SEED),
+			clock : Clock::Simulation,
+		}
+	}
+
+	/// Sets which time things move on (simulation time by default, so they stop while paused).
+	pub fn set_clock(&mut self, clock : Clock) {
+		self.clock = clock;
+	}
+
+	/// Gets which time things move on.
+	pub fn get_clock(&self) -> Clock {
+		self.clock
+	}
+
+	/// Replaces all spawners with the ones in a Tiled file (and removes everything they made). Returns why any couldn't be loaded.
+	pub fn load_from(&mut self, file : &TiledFile) -> Vec<String> {
+		self.spawners.clear();
+		self.entities.clear();
+		let mut errors = Vec::new();
+		for point in file.get_points() {
+			if AMBIENT_POINT_TYPE != point.r#type {
+				continue;
+			}
+			match AmbientSpawner::from_point(point) {
+				Ok(spawner) => { self.add(spawner); },
+				Err(error) => errors.push(format!("Couldn't load ambient point {:?} due to: {}", point.name, error)),
+			}
+		}
+		errors
+	}
+
+	/// Adds a spawner. Returns its index.
+	pub fn add(&mut self, spawner : AmbientSpawner) -> usize {
+		self.spawners.push(spawner);
+		self.spawners.len() - 1
+	}
+
+	/// Gets everything that currently exists.
+	pub fn get_entities<'a>(&'a self) -> &'a [AmbientEntity] {
+		self.entities.as_slice()
+	}
+
+	/// Gets how full the entity pool is.
+	pub fn pool_stats(&self) -> PoolStats {
+		self.entities.stats()
+	}
+
+	/// Moves forward in time, given everything that moves (the dynamic entity hash), gravity, and what the camera can see.
+	///
+	/// Spawners only make things when they're near the view, and things far outside of it are removed, so this costs about the same no matter how big the map is.
+	/// Birds only come back once their perch is empty, just out of view (so they never pop in on screen), and no player is right next to it.
+	/// Drips splash (and are removed) when they land on anything that moves.
+	pub fn update(&mut self, elapsed_seconds : f32, dynamic : &SpatialHash, gravity : &Vec2, view : &Bounds2) {
+		if 0.0 >= elapsed_seconds {
+			return;
+		}
+		let spawn_area = expand(view, SPAWN_MARGIN);
+		let despawn_area = expand(view, DESPAWN_MARGIN);
+		let down = if EPSILON < gravity.length() { gravity.norm() } else { Vec2::new(0.0, -1.0) };
+
+		for index in 0..self.spawners.len() {
+			let spawner = &self.spawners[index];
+			if !spawn_area.overlaps_point(&spawner.position) {
+				continue;
+			}
+			match spawner.kind {
+				AmbientKind::Birds => {
+					let player_near = !dynamic.nearest(&spawner.position, 1, BIRD_FLEE_RADIUS * 2.0, Some(DynamicKind::Player)).is_empty();
+					if player_near || view.overlaps_point(&spawner.position) || self.entities.as_slice().iter().any(|entity| index == entity.spawner) {
+						continue;
+					}
+					for _ in 0..spawner.count {
+						self.spawn(index, Vec2::zero());
+					}
+				},
+				AmbientKind::Leaves | AmbientKind::Drips => {
+					let interval = 1.0 / spawner.rate;
+					self.spawners[index].timer -= elapsed_seconds;
+					while 0.0 >= self.spawners[index].timer {
+						let jitter = self.random.range(0.5, 1.5);
+						self.spawners[index].timer += interval * jitter;
+						let velocity = if AmbientKind::Leaves == self.spawners[index].kind { down.scale(LEAF_FALL_SPEED) } else { Vec2::zero() };
+						self.spawn(index, velocity);
+					}
+				},
+			}
+		}
+
+		for entity in self.entities.as_mut_slice() {
+			entity.age += elapsed_seconds;
+			match entity.kind {
+				AmbientKind::Birds => {
+					if !entity.fleeing && !dynamic.nearest(&entity.position, 1, BIRD_FLEE_RADIUS, Some(DynamicKind::Player)).is_empty() {
+						entity.fleeing = true;
+					}
+					if entity.fleeing {
+						// Steer away from the closest player and up.
+						let away = match dynamic.nearest(&entity.position, 1, BIRD_WARY_RADIUS, Some(DynamicKind::Player)).first() {
+							Some(threat) => entity.position - threat.position,
+							None => entity.velocity,
+						};
+						let away = if EPSILON < away.length() { away.norm() } else { Vec2::zero() };
+						let desired = (away - down).norm().scale(BIRD_FLEE_SPEED);
+						let mut steering = desired - entity.velocity;
+						let max_change = BIRD_FLEE_ACCELERATION * elapsed_seconds;
+						if max_change < steering.length() {
+							steering = steering.norm().scale(max_change);
+						}
+						entity.velocity += steering;
+					}
+				},
+				AmbientKind::Leaves => {
+					let sway = (entity.age * LEAF_SWAY_RATE + entity.phase).sin() * LEAF_SWAY_SPEED;
+					entity.velocity = down.scale(LEAF_FALL_SPEED) + down.ortho().scale(sway);
+				},
+				AmbientKind::Drips => {
+					entity.velocity += gravity.scale(elapsed_seconds);
+				},
+			}
+			entity.position += entity.velocity.scale(elapsed_seconds);
+		}
+
+		self.entities.retain(|entity| {
+			let expired = match entity.kind {
+				AmbientKind::Birds => false,
+				AmbientKind::Leaves => LEAF_LIFETIME <= entity.age,
+				AmbientKind::Drips => DRIP_LIFETIME <= entity.age || dynamic.any_within(&entity.position, DRIP_RADIUS, None),
+			};
+			!expired && despawn_area.overlaps_point(&entity.position)
+		});
+	}
+
+	/// Removes everything (spawners stay, so things come back as the view moves around).
+	pub fn clear(&mut self) {
+		self.entities.clear();
+	}
+
+	/// Makes a thing somewhere along a spawner's span (if there's room for it).
+	fn spawn(&mut self, spawner_index : usize, velocity : Vec2) {
+		if MAX_ENTITIES <= self.entities.len() {
+			return;
+		}
+		let spawner = &self.spawners[spawner_index];
+		let half_width = spawner.width / 2.0;
+		let offset = if 0.0 < half_width { self.random.range(-half_width, half_width) } else { 0.0 };
+		let (kind, position) = (spawner.kind, Vec2::new(spawner.position.x + offset, spawner.position.y));
+		let phase = self.random.range(0.0, std::f32::consts::PI * 2.0);
+		let entity = self.entities.spawn();
+		entity.kind = kind;
+		entity.spawner = spawner_index;
+		entity.position = position;
+		entity.velocity = velocity;
+		entity.age = 0.0;
+		entity.phase = phase;
+		entity.fleeing = false;
+	}
+}
+
+/// Gets a copy of some bounds grown by a margin on every side.
+fn expand(bounds : &Bounds2, margin : f32) -> Bounds2 {
+	Bounds2::from_points(
+		&Vec2::new(bounds.x_min() - margin, bounds.y_min() - margin),
+		&Vec2::new(bounds.x_max() + margin, bounds.y_max() + margin),
+	)
+}
+
+impl ShiftOrigin for Ambient {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for spawner in &mut self.spawners {
+			spawner.position += shift;
+		}
+		for entity in self.entities.as_mut_slice() {
+			entity.position += shift;
+		}
+	}
+}
+
+/// Draws the ambient things (one small shape per thing, shaped and colored by its kind).
+pub struct AmbientDisplay {
+	/// What birds are drawn in.
+	birds : InstancedBuffer,
+	/// What leaves are drawn in.
+	leaves : InstancedBuffer,
+	/// What drips are drawn in.
+	drips : InstancedBuffer,
+}
+
+impl AmbientDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> AmbientDisplay {
+		AmbientDisplay {
+			birds : AmbientDisplay::make_buffer(&vec![
+				Vec3::new(-3.0, 1.0, AMBIENT_DEPTH),
+				Vec3::new(0.0, -1.0, AMBIENT_DEPTH),
+				Vec3::new(3.0, 1.0, AMBIENT_DEPTH),
+				Vec3::new(0.0, 0.0, AMBIENT_DEPTH),
+			]),
+			leaves : AmbientDisplay::make_buffer(&vec![
+				Vec3::new(0.0, 1.5, AMBIENT_DEPTH),
+				Vec3::new(-1.5, 0.0, AMBIENT_DEPTH),
+				Vec3::new(0.0, -1.5, AMBIENT_DEPTH),
+				Vec3::new(1.5, 0.0, AMBIENT_DEPTH),
+			]),
+			drips : AmbientDisplay::make_buffer(&vec![
+				Vec3::new(-0.5, 1.5, AMBIENT_DEPTH),
+				Vec3::new(-0.5, -1.5, AMBIENT_DEPTH),
+				Vec3::new(0.5, -1.5, AMBIENT_DEPTH),
+				Vec3::new(0.5, 1.5, AMBIENT_DEPTH),
+			]),
+		}
+	}
+
+	/// Creates a buffer that draws copies of a shape.
+	fn make_buffer(shape : &Vec<Vec3>) -> InstancedBuffer {
+		let mut template = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		template.add_polygon(shape, &Color::new(255, 255, 255, 255));
+		InstancedBuffer::new(DisplayBufferType::SOLIDS, template)
+	}
+
+	/// Redraws everything.
+	pub fn redraw(&mut self, ambient : &Ambient) {
+		let bird_color = Color::new(60, 60, 72, 255);
+		let leaf_color = Color::new(196, 132, 48, 255);
+		let drip_color = Color::new(128, 180, 255, 200);
+		let mut birds = Vec::new();
+		let mut leaves = Vec::new();
+		let mut drips = Vec::new();
+		for entity in ambient.get_entities() {
+			let position = Vec3::new(entity.position.x, entity.position.y, 0.0);
+			match entity.kind {
+				AmbientKind::Birds => birds.push(Instance::new(&position, &bird_color)),
+				AmbientKind::Leaves => leaves.push(Instance::new(&position, &leaf_color)),
+				AmbientKind::Drips => drips.push(Instance::new(&position, &drip_color)),
+			}
+		}
+		self.birds.update(&birds);
+		self.leaves.update(&leaves);
+		self.drips.update(&drips);
+	}
+}
+
+#[cfg(test)]
+mod tests_ambient {
+	use super::*;
+
+	/// Makes a dynamic entity hash with just a player in it.
+	fn player_at(position : &Vec2) -> SpatialHash {
+		let mut dynamic = SpatialHash::new();
+		dynamic.insert(DynamicKind::Player, 0, position, 8.0);
+		dynamic
+	}
+
+	#[test]
+	fn spawning_and_despawning() {
+		let gravity = Vec2::new(0.0, -800.0);
+		let view = Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 200.0, 200.0);
+		let far_away = player_at(&Vec2::new(1000.0, 1000.0));
+		let mut ambient = Ambient::new();
+		let birds = ambient.add(AmbientSpawner::new(AmbientKind::Birds, &Vec2::new(150.0, 0.0), 20.0, 1.0, 3));
+		ambient.add(AmbientSpawner::new(AmbientKind::Drips, &Vec2::new(0.0, 50.0), 0.0, 10.0, 0));
+		ambient.add(AmbientSpawner::new(AmbientKind::Leaves, &Vec2::new(5000.0, 0.0), 0.0, 10.0, 0)); // Too far from the view to do anything.
+
+		// Birds perch just out of view, and drips come out (and fall away).
+		for _ in 0..10 {
+			ambient.update(0.1, &far_away, &gravity, &view);
+		}
+		let count = |ambient : &Ambient, kind : AmbientKind| ambient.get_entities().iter().filter(|entity| kind == entity.kind).count();
+		assert_eq!(count(&ambient, AmbientKind::Birds), 3);
+		assert!(0 < count(&ambient, AmbientKind::Drips) && count(&ambient, AmbientKind::Drips) < 10);
+		assert_eq!(count(&ambient, AmbientKind::Leaves), 0);
+
+		// Perched birds stay put until the player gets close, then fly off (up and away) until they're out of range and removed.
+		ambient.update(0.5, &far_away, &gravity, &view);
+		assert!(ambient.get_entities().iter().filter(|entity| birds == entity.spawner).all(|entity| !entity.fleeing && 0.0 == entity.position.y));
+		let player = player_at(&Vec2::new(130.0, 0.0));
+		ambient.update(0.5, &player, &gravity, &view);
+		assert!(ambient.get_entities().iter().filter(|entity| birds == entity.spawner).all(|entity| entity.fleeing && 0.0 < entity.position.y));
+		for _ in 0..100 {
+			ambient.update(0.1, &player, &gravity, &view);
+		}
+		assert_eq!(count(&ambient, AmbientKind::Birds), 0);
+
+		// They don't come back while their perch is in view.
+		let centered = Bounds2::from_centered_rect(&Vec2::new(150.0, 0.0), 200.0, 200.0);
+		ambient.update(0.1, &far_away, &gravity, &centered);
+		assert_eq!(count(&ambient, AmbientKind::Birds), 0);
+		ambient.update(0.1, &far_away, &gravity, &view);
+		assert_eq!(count(&ambient, AmbientKind::Birds), 3);
+	}
+
+	#[test]
+	fn bounded() {
+		let gravity = Vec2::new(0.0, 0.0);
+		let view = Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 200.0, 200.0);
+		let mut ambient = Ambient::new();
+		ambient.add(AmbientSpawner::new(AmbientKind::Leaves, &Vec2::new(0.0, 0.0), 50.0, 1000.0, 0));
+		ambient.update(1.0, &SpatialHash::new(), &gravity, &view);
+		assert_eq!(ambient.get_entities().len(), MAX_ENTITIES);
+
+		// Things expire, and moving the view away removes the rest.
+		ambient.update(LEAF_LIFETIME, &SpatialHash::new(), &gravity, &Bounds2::from_centered_rect(&Vec2::new(5000.0, 0.0), 200.0, 200.0));
+		assert!(ambient.get_entities().is_empty());
+	}
+
+	#[test]
+	fn drips_splash() {
+		let gravity = Vec2::new(0.0, -800.0);
+		let view = Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 200.0, 200.0);
+		let mut ambient = Ambient::new();
+		ambient.add(AmbientSpawner::new(AmbientKind::Drips, &Vec2::new(0.0, 50.0), 0.0, 10.0, 0));
+		let player = player_at(&Vec2::new(0.0, 0.0));
+		let mut dripped = 0;
+		for _ in 0..60 {
+			ambient.update(1.0 / 60.0, &player, &gravity, &view);
+			dripped = dripped.max(ambient.get_entities().len());
+			assert!(ambient.get_entities().iter().all(|drip| 0.0 < drip.position.y)); // None get past the player.
+		}
+		assert!(0 < dripped);
+	}
+}
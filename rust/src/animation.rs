@@ -0,0 +1,324 @@
+use crate::geo::vec2::*;
+use crate::tiled::TiledPolyline;
+use crate::tween::Easing;
+use crate::origin_rebase::ShiftOrigin;
+use crate::scheduler::{Clock, FrameTime};
+
+/// How fast things move along paths made from Tiled polylines (in pixels per second), unless the polyline has a "speed" property.
+const DEFAULT_PATH_SPEED : f32 = 60.0;
+
+/// Something that can be smoothly blended between two values.
+pub trait Interpolate : Clone {
+	/// Blends from this to the other value. The progress goes from 0 (all this) to 1 (all other).
+	fn interpolate(&self, other : &Self, progress : f32) -> Self;
+}
+
+impl Interpolate for f32 {
+	fn interpolate(&self, other : &f32, progress : f32) -> f32 {
+		self + (other - self) * progress
+	}
+}
+
+impl Interpolate for Vec2 {
+	fn interpolate(&self, other : &Vec2, progress : f32) -> Vec2 {
+		self + (other - self) * progress
+	}
+}
+
+/// A value at a point in time.
+#[derive(Debug, Clone)]
+pub struct Keyframe<T> {
+	/// When the value is reached (in seconds from the start of the track).
+	pub time : f32,
+	/// The value.
+	pub value : T,
+	/// The curve to follow when heading from this key to the next one.
+	pub easing : Easing,
+}
+
+/// What happens once a track reaches its last key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackEnd {
+	/// Hold the last value.
+	Stop,
+	/// Start over from the beginning.
+	Loop,
+}
+
+/// A series of keyframes that can be sampled at any time.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+	/// The keys (sorted by time).
+	keys : Vec<Keyframe<T>>,
+	/// What happens after the last key.
+	end : TrackEnd,
+}
+
+impl<T : Interpolate> Track<T> {
+	/// Creates a track with no keys.
+	pub fn new(end : TrackEnd) -> Track<T> {
+		Track {
+			keys : Vec::new(),
+			end,
+		}
+	}
+
+	/// Adds a key (keeping them sorted by time).
+	pub fn add_key(&mut self, time : f32, value : T, easing : Easing) {
+		let index = self.keys.iter().position(|key| time < key.time).unwrap_or(self.keys.len());
+		self.keys.insert(index, Keyframe { time, value, easing });
+	}
+
+	/// Gets all the keys.
+	pub fn get_keys<'a>(&'a self) -> &'a Vec<Keyframe<T>> {
+		&self.keys
+	}
+
+	/// What happens after the last key.
+	pub fn get_end(&self) -> TrackEnd {
+		self.end
+	}
+
+	/// How long the track is (the time of the last key).
+	pub fn get_duration(&self) -> f32 {
+		self.keys.last().map(|key| key.time).unwrap_or(0.0)
+	}
+
+	/// Gets the value at a point in time. Returns None if there are no keys.
+	pub fn sample(&self, time : f32) -> Option<T> {
+		let first = self.keys.first()?;
+		let duration = self.get_duration();
+		let mut time = time;
+		if TrackEnd::Loop == self.end && 0.0 < duration {
+			time = time.rem_euclid(duration);
+		}
+		if time <= first.time {
+			return Some(first.value.clone());
+		}
+		for index in 1..self.keys.len() {
+			let next = &self.keys[index];
+			if time <= next.time {
+				let previous = &self.keys[index - 1];
+				let span = next.time - previous.time;
+				if 0.0 >= span {
+					return Some(next.value.clone());
+				}
+				let progress = previous.easing.apply((time - previous.time) / span);
+				return Some(previous.value.interpolate(&next.value, progress));
+			}
+		}
+		Some(self.keys.last().unwrap().value.clone())
+	}
+}
+
+impl Track<Vec2> {
+	/// Makes a track that moves through a series of points at some speed, waiting at each point for its delay (if any).
+	pub fn from_path(points : &Vec<Vec2>, speed : f32, delays : &Vec<f32>, easing : Easing, end : TrackEnd) -> Track<Vec2> {
+		let mut track = Track::new(end);
+		let mut time = 0.0;
+		for (index, point) in points.iter().enumerate() {
+			if 0 < index {
+				time += (point - &points[index - 1]).length() / speed.max(1.0);
+			}
+			track.add_key(time, point.clone(), easing);
+			let delay = delays.get(index).cloned().unwrap_or(0.0);
+			if 0.0 < delay {
+				time += delay;
+				track.add_key(time, point.clone(), easing);
+			}
+		}
+		track
+	}
+
+	/// Makes a track from a Tiled polyline. These properties are used (if present):
+	///
+	/// * "speed": How fast to move (in pixels per second).
+	/// * "delays": A comma separated list of how long to wait at each point (in seconds).
+	/// * "easing": The easing name (see `Easing::from_name()`) to use between points.
+	/// * "end": What to do at the end: "stop", "loop" (go back to the first point), or "pingPong" (go back through every point).
+	///
+	/// The default end is only used if the polyline doesn't have an "end" property.
+	pub fn from_polyline(polyline : &TiledPolyline, default_end : &str) -> Result<Track<Vec2>, String> {
+		let speed = match polyline.get_property("speed") {
+			Some(text) => text.trim().parse::<f32>().map_err(|_| format!("Couldn't parse speed {:?}", text))?,
+			None => DEFAULT_PATH_SPEED,
+		};
+		let mut delays = Vec::new();
+		if let Some(text) = polyline.get_property("delays") {
+			for piece in text.split(',') {
+				delays.push(piece.trim().parse::<f32>().map_err(|_| format!("Couldn't parse delay {:?}", piece))?);
+			}
+		}
+		let easing = match polyline.get_property("easing") {
+			Some(name) => Easing::from_name(name).ok_or_else(|| format!("Unknown easing {:?}", name))?,
+			None => Easing::QuadInOut,
+		};
+		let mut points = polyline.points.clone();
+		let end = match polyline.get_property("end").unwrap_or(default_end) {
+			"stop" => TrackEnd::Stop,
+			"loop" => {
+				if let Some(first) = points.first().cloned() {
+					points.push(first);
+				}
+				TrackEnd::Loop
+			},
+			"pingPong" => {
+				// Head back through every point, waiting at each one again (except the first, since that wait happens when looping).
+				let count = points.len();
+				delays.resize(count, 0.0);
+				for index in (0..count.saturating_sub(1)).rev() {
+					points.push(points[index]);
+					delays.push(if 0 == index { 0.0 } else { delays[index] });
+				}
+				TrackEnd::Loop
+			},
+			other => { return Err(format!("Unknown end {:?}", other)); },
+		};
+		Ok(Track::from_path(&points, speed, &delays, easing, end))
+	}
+}
+
+/// Plays a track forward in time.
+#[derive(Debug, Clone)]
+pub struct TrackPlayer<T> {
+	/// The track being played.
+	track : Track<T>,
+	/// How far into the track playback is (in seconds).
+	time : f32,
+	/// Which time playback moves forward on when ticked (see `tick()`).
+	clock : Clock,
+}
+
+impl<T : Interpolate> TrackPlayer<T> {
+	/// Starts playing a track from the beginning.
+	pub fn new(track : Track<T>) -> TrackPlayer<T> {
+		TrackPlayer {
+			track,
+			time : 0.0,
+			clock : Clock::Simulation,
+		}
+	}
+
+	/// Makes playback move forward on some clock when ticked (it's on simulation time otherwise).
+	pub fn with_clock(mut self, clock : Clock) -> TrackPlayer<T> {
+		self.clock = clock;
+		self
+	}
+
+	/// Moves playback forward by however much time passed on its clock. Returns the new value (or None if the track is empty).
+	pub fn tick(&mut self, time : &FrameTime) -> Option<T> {
+		self.update(time.seconds(self.clock))
+	}
+
+	/// Moves playback forward. Returns the new value (or None if the track is empty).
+	pub fn update(&mut self, elapsed_seconds : f32) -> Option<T> {
+		self.time += elapsed_seconds;
+		self.value()
+	}
+
+	/// Gets the current value (or None if the track is empty).
+	pub fn value(&self) -> Option<T> {
+		self.track.sample(self.time)
+	}
+
+	/// Gets how far into the track playback is (in seconds).
+	pub fn get_time(&self) -> f32 {
+		self.time
+	}
+
+	/// Jumps to some point in the track (in seconds from the start).
+	pub fn set_time(&mut self, time : f32) {
+		self.time = time;
+	}
+
+	/// Whether the track has finished playing. Looping tracks never finish.
+	pub fn is_done(&self) -> bool {
+		TrackEnd::Stop == self.track.get_end() && self.track.get_duration() <= self.time
+	}
+}
+
+impl<T : ShiftOrigin> ShiftOrigin for TrackPlayer<T> {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for key in &mut self.track.keys {
+			key.value.shift_origin(shift);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_animation {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+	use crate::geo::consts::EPSILON;
+
+	#[test]
+	fn sampling() {
+		let mut track : Track<f32> = Track::new(TrackEnd::Stop);
+		assert!(track.sample(0.0).is_none());
+		track.add_key(2.0, 10.0, Easing::QuadIn);
+		track.add_key(0.0, 0.0, Easing::Linear); // Out of order on purpose.
+		track.add_key(3.0, 0.0, Easing::Linear);
+		assert_eq!(track.get_duration(), 3.0);
+		assert_eq!(track.sample(-1.0), Some(0.0));
+		assert_eq!(track.sample(1.0), Some(5.0));
+		assert_eq!(track.sample(2.0), Some(10.0));
+		assert_eq!(track.sample(2.5), Some(7.5));
+		assert_eq!(track.sample(10.0), Some(0.0));
+	}
+
+	#[test]
+	fn looping() {
+		let mut track : Track<f32> = Track::new(TrackEnd::Loop);
+		track.add_key(0.0, 0.0, Easing::Linear);
+		track.add_key(1.0, 4.0, Easing::Linear);
+		assert_eq!(track.sample(1.25), Some(1.0));
+		let mut player = TrackPlayer::new(track);
+		player.update(100.0);
+		assert!(!player.is_done());
+	}
+
+	#[test]
+	fn clocks() {
+		let mut track : Track<f32> = Track::new(TrackEnd::Stop);
+		track.add_key(0.0, 0.0, Easing::Linear);
+		track.add_key(1.0, 4.0, Easing::Linear);
+		let paused = FrameTime { real_seconds : 0.25, simulated_seconds : 0.0, interpolation : 1.0 };
+		assert_eq!(TrackPlayer::new(track.clone()).tick(&paused), Some(0.0));
+		assert_eq!(TrackPlayer::new(track).with_clock(Clock::Real).tick(&paused), Some(1.0));
+	}
+
+	#[test]
+	fn paths_with_delays() {
+		let points = vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(10.0, 20.0)];
+		let track = Track::from_path(&points, 10.0, &vec![0.0, 2.0], Easing::Linear, TrackEnd::Stop);
+		assert!((track.get_duration() - 5.0).abs() < EPSILON);
+		assert_vec2_about_eq!(track.sample(0.5).unwrap(), Vec2::new(5.0, 0.0));
+		assert_vec2_about_eq!(track.sample(2.0).unwrap(), Vec2::new(10.0, 0.0)); // Waiting.
+		assert_vec2_about_eq!(track.sample(4.0).unwrap(), Vec2::new(10.0, 10.0));
+		let mut player = TrackPlayer::new(track);
+		player.update(5.0);
+		assert!(player.is_done());
+	}
+
+	#[test]
+	fn polylines() {
+		use crate::tiled::TiledStringProp;
+		let mut polyline = TiledPolyline {
+			points : vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)],
+			name : String::new(),
+			r#type : String::from("platform"),
+			properties : vec![
+				TiledStringProp { name : String::from("speed"), value : String::from("5") },
+				TiledStringProp { name : String::from("easing"), value : String::from("linear") },
+			],
+		};
+		let track = Track::from_polyline(&polyline, "pingPong").unwrap();
+		assert_eq!(track.get_end(), TrackEnd::Loop);
+		assert!((track.get_duration() - 4.0).abs() < EPSILON);
+		assert_vec2_about_eq!(track.sample(3.0).unwrap(), Vec2::new(5.0, 0.0));
+		assert_vec2_about_eq!(track.sample(4.5).unwrap(), Vec2::new(2.5, 0.0));
+
+		polyline.properties.push(TiledStringProp { name : String::from("end"), value : String::from("nonsense") });
+		assert!(Track::from_polyline(&polyline, "stop").is_err());
+	}
+}
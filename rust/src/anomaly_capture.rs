@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+
+use crate::geo::vec2::*;
+use crate::geo::collision_system::*;
+use crate::keyboard::*;
+use crate::gamepad::*;
+use crate::json::*;
+
+/// How many input frames to keep around for captures.
+const INPUT_HISTORY_LENGTH : usize = 60;
+/// The most captures to keep. Later ones are dropped, since the first is usually the interesting one.
+const MAX_CAPTURES : usize = 16;
+/// How far from the player to look for obstacles to include in a capture.
+const NEARBY_OBSTACLE_DISTANCE : f32 = 32.0;
+
+/// The kinds of physics problems that get captured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnomalyKind {
+	/// The physics loop ran out of iterations before using up all the movement.
+	IterationMax,
+	/// A position or velocity became NaN or infinite.
+	InvalidState,
+	/// The collider ended an update overlapping the collision geometry.
+	Penetration,
+}
+
+impl AnomalyKind {
+	/// The name used in the captured JSON.
+	pub fn name(&self) -> &'static str {
+		match self {
+			AnomalyKind::IterationMax => "iteration_max",
+			AnomalyKind::InvalidState => "invalid_state",
+			AnomalyKind::Penetration => "penetration",
+		}
+	}
+}
+
+/// A problem detected during an update.
+#[derive(Debug, Clone)]
+pub struct PhysicsAnomaly {
+	/// What went wrong.
+	pub kind : AnomalyKind,
+	/// Some extra human-readable information.
+	pub details : String,
+}
+
+/// The inputs used during a single update.
+#[derive(Debug, Clone)]
+pub struct InputFrame {
+	/// The game time of the update.
+	pub time : f32,
+	/// How long the update was.
+	pub elapsed_seconds : f32,
+	/// The directional input (keys and main stick combined).
+	pub direction : Vec2,
+	/// Whether jump was held.
+	pub jump : bool,
+	/// Whether the track button was held.
+	pub track : bool,
+}
+
+impl InputFrame {
+	/// Reads the current inputs.
+	pub fn read(time : f32, elapsed_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad) -> InputFrame {
+		let mut direction = gamepad.direction();
+		if keyboard.is_down(Key::UP)    { direction.y += 1.0; }
+		if keyboard.is_down(Key::LEFT)  { direction.x -= 1.0; }
+		if keyboard.is_down(Key::DOWN)  { direction.y -= 1.0; }
+		if keyboard.is_down(Key::RIGHT) { direction.x += 1.0; }
+		InputFrame {
+			time,
+			elapsed_seconds,
+			direction,
+			jump : gamepad.is_down(Button::A) || keyboard.is_down(Key::UP),
+			track : gamepad.is_down(Button::R) || keyboard.is_down(Key::SPACE),
+		}
+	}
+}
+
+/// Keeps a short history of inputs and records a snapshot of the relevant state whenever a physics anomaly happens.
+///
+/// Each capture is a JSON object, so they can be pulled out (via `lib.rs`) and attached to bug reports.
+pub struct AnomalyCapture {
+	/// The most recent input frames (oldest first).
+	inputs : VecDeque<InputFrame>,
+	/// The captures so far (as JSON objects).
+	captures : Vec<String>,
+}
+
+impl AnomalyCapture {
+	/// Creates an instance with no history.
+	pub fn new() -> AnomalyCapture {
+		AnomalyCapture {
+			inputs : VecDeque::with_capacity(INPUT_HISTORY_LENGTH),
+			captures : Vec::new(),
+		}
+	}
+
+	/// Adds an input frame to the history (dropping the oldest if full).
+	pub fn record_input(&mut self, frame : InputFrame) {
+		if INPUT_HISTORY_LENGTH <= self.inputs.len() {
+			self.inputs.pop_front();
+		}
+		self.inputs.push_back(frame);
+	}
+
+	/// Replaces the input history (as in when time has been rewound, so the inputs that were undone shouldn't be in it).
+	pub fn replace_inputs<I : Iterator<Item = InputFrame>>(&mut self, frames : I) {
+		self.inputs.clear();
+		for frame in frames {
+			self.record_input(frame);
+		}
+	}
+
+	/// Records a snapshot for an anomaly.
+	pub fn capture(&mut self, anomaly : &PhysicsAnomaly, time : f32, position : &Vec2, velocity : &Vec2, collision : &CollisionSystem) {
+		if MAX_CAPTURES <= self.captures.len() {
+			return;
+		}
+		let mut obstacles = Vec::new();
+		if position.x.is_finite() && position.y.is_finite() {
+			for index in collision.find_obstacles_near(position, NEARBY_OBSTACLE_DISTANCE) {
+				obstacles.push(json_obstacle(&collision.obstacles[index]));
+			}
+		}
+		let inputs : Vec<String> = self.inputs.iter().map(|frame| format!(
+			"{{\"time\":{},\"elapsed\":{},\"direction\":{},\"jump\":{},\"track\":{}}}",
+			json_number(frame.time),
+			json_number(frame.elapsed_seconds),
+			json_vec2(&frame.direction),
+			frame.jump,
+			frame.track,
+		)).collect();
+		self.captures.push(format!(
+			"{{\"kind\":{},\"details\":{},\"time\":{},\"position\":{},\"velocity\":{},\"obstacles\":[{}],\"inputs\":[{}]}}",
+			json_string(anomaly.kind.name()),
+			json_string(&anomaly.details),
+			json_number(time),
+			json_vec2(position),
+			json_vec2(velocity),
+			obstacles.join(","),
+			inputs.join(","),
+		));
+	}
+
+	/// How many captures there are.
+	pub fn capture_count(&self) -> usize {
+		self.captures.len()
+	}
+
+	/// Gets all captures as a JSON array.
+	pub fn to_json(&self) -> String {
+		format!("[{}]", self.captures.join(","))
+	}
+
+	/// Removes all captures (but keeps the input history).
+	pub fn clear(&mut self) {
+		self.captures.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests_anomaly_capture {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+
+	#[test]
+	fn json_escaping() {
+		assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+		assert_eq!(json_number(std::f32::NAN), "null");
+		assert_eq!(json_vec2(&Vec2::new(1.5, -2.0)), "[1.5,-2]");
+	}
+
+	#[test]
+	fn capture_contents() {
+		let mut capture = AnomalyCapture::new();
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(4.0, 0.0))));
+		collision.add_obstacle(CircleObstacle::Point(Vec2::new(1000.0, 0.0)));
+		for index in 0..(INPUT_HISTORY_LENGTH + 5) {
+			capture.record_input(InputFrame {
+				time : index as f32,
+				elapsed_seconds : 1.0,
+				direction : Vec2::new(0.0, 0.0),
+				jump : false,
+				track : false,
+			});
+		}
+		capture.capture(
+			&PhysicsAnomaly { kind : AnomalyKind::Penetration, details : String::from("test") },
+			1.0,
+			&Vec2::new(1.0, 1.0),
+			&Vec2::new(std::f32::NAN, 0.0),
+			&collision,
+		);
+		assert_eq!(capture.capture_count(), 1);
+		let json = capture.to_json();
+		assert!(json.starts_with("[{\"kind\":\"penetration\",\"details\":\"test\",\"time\":1,\"position\":[1,1],\"velocity\":[null,0],\"obstacles\":[{\"type\":\"segment\",\"start\":[0,0],\"end\":[4,0]}],\"inputs\":[{\"time\":5,"));
+		assert_eq!(json.matches("\"jump\"").count(), INPUT_HISTORY_LENGTH);
+
+		capture.clear();
+		assert_eq!(capture.to_json(), "[]");
+	}
+}
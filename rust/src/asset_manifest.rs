@@ -0,0 +1,131 @@
+/// The asset manifest: a JSON file mapping asset names to where they're actually served from (and a version to bust caches with).
+///
+/// Everything the game loads (images, maps, definitions, ghosts) is asked for by name and goes through `asset_url()`, so files can be renamed or moved without code changes.
+/// Names that aren't in the manifest are used as URLs directly (so things still work without one).
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::static_singletons::get_asset_manifest;
+
+/// Where the manifest is loaded from (relative to the page).
+pub const ASSET_MANIFEST_URL : &str = "manifest.json";
+
+/// Where an asset is, as written in the manifest.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AssetEntry {
+	/// Where it's served from. Defaults to its name.
+	pub url : Option<String>,
+	/// Changes whenever the file does (like a hash of it), so browsers don't use an old cached copy. Empty to not add one.
+	#[serde(default)]
+	pub version : String,
+}
+
+/// The file's layout.
+#[derive(Deserialize)]
+struct JsonManifest {
+	/// Every known asset, by name.
+	#[serde(default)]
+	assets : HashMap<String, AssetEntry>,
+	/// The names of assets to load before the game starts (in order).
+	#[serde(default)]
+	preload : Vec<String>,
+}
+
+/// All known assets, and which to load up front.
+#[derive(Debug, Clone)]
+pub struct AssetManifest {
+	/// Every known asset, by name.
+	assets : HashMap<String, AssetEntry>,
+	/// The names of assets to load before the game starts (in order).
+	preload : Vec<String>,
+}
+
+impl AssetManifest {
+	/// Creates an empty instance (so every name is its own URL).
+	pub fn new() -> AssetManifest {
+		AssetManifest {
+			assets : HashMap::new(),
+			preload : Vec::new(),
+		}
+	}
+
+	/// Reads in a manifest file's contents.
+	pub fn from_json(text : &str) -> Result<AssetManifest, String> {
+		let json : JsonManifest = serde_json::from_str(text).map_err(|error| format!("Couldn't read asset manifest: {}", error))?;
+		for name in &json.preload {
+			if !json.assets.contains_key(name) {
+				return Err(format!("Preloaded asset {:?} isn't in the manifest", name));
+			}
+		}
+		Ok(AssetManifest {
+			assets : json.assets,
+			preload : json.preload,
+		})
+	}
+
+	/// Gets the entry for an asset (if it's in the manifest).
+	pub fn get(&self, name : &str) -> Option<&AssetEntry> {
+		self.assets.get(name)
+	}
+
+	/// Gets the URL to load an asset from, with its version on the end (if it has one). Names not in the manifest are returned as is.
+	pub fn resolve(&self, name : &str) -> String {
+		let entry = match self.assets.get(name) {
+			Some(entry) => entry,
+			None => { return name.to_string(); },
+		};
+		let url = entry.url.as_deref().unwrap_or(name);
+		if entry.version.is_empty() {
+			url.to_string()
+		} else {
+			format!("{}{}v={}", url, if url.contains('?') { '&' } else { '?' }, entry.version)
+		}
+	}
+
+	/// Gets the names of the assets to load before the game starts.
+	pub fn get_preload(&self) -> &Vec<String> {
+		&self.preload
+	}
+
+	/// Gets the URLs of the assets to load before the game starts, as a JSON array (for the loading screen).
+	pub fn preload_urls_json(&self) -> String {
+		let urls : Vec<String> = self.preload.iter().map(|name| self.resolve(name)).collect();
+		serde_json::to_string(&urls).unwrap_or_else(|error| crate::fatal!("Couldn't write out the preload URLs: {}", error))
+	}
+}
+
+/// Gets the URL to load an asset from (see `AssetManifest::resolve()`).
+pub fn asset_url(name : &str) -> String {
+	get_asset_manifest().resolve(name)
+}
+
+#[cfg(test)]
+mod tests_asset_manifest {
+	use super::*;
+
+	#[test]
+	fn resolving() {
+		let manifest = AssetManifest::from_json(r#"{
+			"assets" : {
+				"player.png" : { "url" : "sprites/robot.png", "version" : "1a2b" },
+				"room.json" : { "version" : "99" },
+				"enemies.json" : { "url" : "data/enemies.json?lang=en", "version" : "7" },
+				"prompts.png" : { "url" : "ui/prompts.png" }
+			},
+			"preload" : ["player.png", "room.json"]
+		}"#).unwrap();
+		assert_eq!(manifest.resolve("player.png"), "sprites/robot.png?v=1a2b"); // Renamed.
+		assert_eq!(manifest.resolve("room.json"), "room.json?v=99");
+		assert_eq!(manifest.resolve("enemies.json"), "data/enemies.json?lang=en&v=7");
+		assert_eq!(manifest.resolve("prompts.png"), "ui/prompts.png");
+		assert_eq!(manifest.resolve("uploads/ghost.bin"), "uploads/ghost.bin"); // Unknown names are left alone.
+		assert_eq!(manifest.get_preload(), &vec![String::from("player.png"), String::from("room.json")]);
+		assert_eq!(manifest.preload_urls_json(), r#"["sprites/robot.png?v=1a2b","room.json?v=99"]"#);
+
+		assert!(AssetManifest::from_json(r#"{ "assets" : {}, "preload" : ["missing.png"] }"#).is_err());
+		assert!(AssetManifest::from_json("not json").is_err());
+		assert_eq!(AssetManifest::new().resolve("room.json"), "room.json");
+	}
+}
@@ -0,0 +1,200 @@
+/// How long there needs to be no input before the demo starts (in seconds).
+pub const ATTRACT_IDLE_SECONDS : f32 = 30.0;
+/// The replay that the demo plays (bundled in, so it's always available).
+pub const DEMO_REPLAY : &str = include_str!("../../site/demo.replay");
+/// What's written in a replay for the space bar (as replays are split on whitespace).
+const SPACE_KEY_NAME : &str = "Space";
+
+/// A single change in a key's state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayEvent {
+	/// When it happens (in simulated seconds from the start of the replay).
+	pub time : f32,
+	/// The real key (as in a keyboard event's `key`).
+	pub key : String,
+	/// Whether the key is pressed (or released).
+	pub down : bool,
+}
+
+/// A recording of key presses that can be played back through a Keyboard.
+#[derive(Debug, Clone)]
+pub struct InputReplay {
+	/// The events, in the order they happen.
+	events : Vec<ReplayEvent>,
+}
+
+impl InputReplay {
+	/// Reads a replay from text: one event per line, written as `<seconds> down|up <key>`. Blank lines and lines starting with `#` are skipped.
+	pub fn parse(text : &str) -> Result<InputReplay, String> {
+		let mut events : Vec<ReplayEvent> = Vec::new();
+		for (index, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let parts : Vec<&str> = line.split_whitespace().collect();
+			if 3 != parts.len() {
+				return Err(format!("Line {} should be '<seconds> down|up <key>' but is {:?}", index + 1, line));
+			}
+			let time = match parts[0].parse::<f32>() {
+				Ok(time) if 0.0 <= time => time,
+				_ => { return Err(format!("Line {} has an invalid time: {:?}", index + 1, parts[0])); },
+			};
+			if events.last().map_or(false, |last| time < last.time) {
+				return Err(format!("Line {} goes back in time", index + 1));
+			}
+			let down = match parts[1] {
+				"down" => true,
+				"up" => false,
+				other => { return Err(format!("Line {} should say 'down' or 'up' but says {:?}", index + 1, other)); },
+			};
+			let key = if SPACE_KEY_NAME == parts[2] { " " } else { parts[2] };
+			events.push(ReplayEvent { time, key : key.to_string(), down });
+		}
+		Ok(InputReplay { events })
+	}
+
+	/// Gets all the events, in order.
+	pub fn get_events<'a>(&'a self) -> &'a Vec<ReplayEvent> {
+		&self.events
+	}
+
+	/// How long the replay lasts (i.e. when the last event happens).
+	pub fn get_duration(&self) -> f32 {
+		self.events.last().map_or(0.0, |event| event.time)
+	}
+}
+
+/// A change in whether the demo is playing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttractModeChange {
+	/// Nothing happened for long enough, so the demo started.
+	Started,
+	/// The demo finished, or was interrupted by input.
+	Stopped,
+}
+
+/// Plays a demo replay once the game has gone without input for a while, and stops it as soon as there's input again.
+///
+/// This only tracks timing and which replayed events are due. The Game resets the room and routes the events into its Keyboard.
+pub struct AttractMode {
+	/// What to play.
+	replay : InputReplay,
+	/// How long to wait without input before playing.
+	idle_limit : f32,
+	/// How long there's been no input (in real seconds).
+	idle_seconds : f32,
+	/// Whether there's been input since the last update.
+	had_input : bool,
+	/// Where the demo is (in simulated seconds), or None if it's not playing.
+	playhead : Option<f32>,
+	/// The index of the next event to hand out.
+	next_event : usize,
+}
+
+impl AttractMode {
+	/// Creates an instance that isn't playing.
+	pub fn new(replay : InputReplay, idle_limit : f32) -> AttractMode {
+		AttractMode {
+			replay,
+			idle_limit,
+			idle_seconds : 0.0,
+			had_input : false,
+			playhead : None,
+			next_event : 0,
+		}
+	}
+
+	/// Whether the demo is playing.
+	pub fn is_active(&self) -> bool {
+		self.playhead.is_some()
+	}
+
+	/// Notes that a player did something. Returns whether the demo is playing (in which case the input should only stop it, and not be used).
+	pub fn note_input(&mut self) -> bool {
+		self.had_input = true;
+		self.idle_seconds = 0.0;
+		self.is_active()
+	}
+
+	/// Moves the idle timer forward, starting or stopping the demo as needed.
+	pub fn update(&mut self, real_seconds : f32) -> Option<AttractModeChange> {
+		let had_input = self.had_input;
+		self.had_input = false;
+		if let Some(playhead) = self.playhead {
+			if had_input || self.replay.get_duration() < playhead {
+				self.playhead = None;
+				self.idle_seconds = 0.0;
+				return Some(AttractModeChange::Stopped);
+			}
+			return None;
+		}
+		if had_input {
+			return None;
+		}
+		self.idle_seconds += real_seconds;
+		if self.idle_limit <= self.idle_seconds && !self.replay.get_events().is_empty() {
+			self.playhead = Some(0.0);
+			self.next_event = 0;
+			return Some(AttractModeChange::Started);
+		}
+		None
+	}
+
+	/// Moves the demo forward by a simulation step, giving the events that are now due. Gives nothing if the demo isn't playing.
+	pub fn step(&mut self, simulated_seconds : f32) -> &[ReplayEvent] {
+		let playhead = match &mut self.playhead {
+			Some(playhead) => playhead,
+			None => { return &[]; },
+		};
+		*playhead += simulated_seconds;
+		let start = self.next_event;
+		let events = self.replay.get_events();
+		while self.next_event < events.len() && events[self.next_event].time <= *playhead {
+			self.next_event += 1;
+		}
+		&events[start..self.next_event]
+	}
+}
+
+#[cfg(test)]
+mod tests_attract_mode {
+	use super::*;
+
+	#[test]
+	fn parsing() {
+		let replay = InputReplay::parse("# A comment.\n0 down ArrowRight\n\n0.5 down Space\n1.25 up ArrowRight\n").unwrap();
+		assert_eq!(replay.get_events().len(), 3);
+		assert_eq!(replay.get_events()[1], ReplayEvent { time : 0.5, key : String::from(" "), down : true });
+		assert_eq!(replay.get_duration(), 1.25);
+		assert!(InputReplay::parse("1 down a\n0.5 up a").is_err());
+		assert!(InputReplay::parse("1 sideways a").is_err());
+		assert!(InputReplay::parse("-1 down a").is_err());
+		assert!(InputReplay::parse("1 down").is_err());
+		assert!(InputReplay::parse(DEMO_REPLAY).is_ok());
+	}
+
+	#[test]
+	fn idling_and_playing() {
+		let replay = InputReplay::parse("0 down d\n0.2 up d\n").unwrap();
+		let mut attract = AttractMode::new(replay, 1.0);
+		assert_eq!(attract.update(0.6), None);
+		assert!(!attract.note_input()); // Resets the timer.
+		assert_eq!(attract.update(0.6), None);
+		assert_eq!(attract.update(0.6), None);
+		assert_eq!(attract.update(0.6), Some(AttractModeChange::Started));
+		assert!(attract.is_active());
+
+		assert_eq!(attract.step(0.1).len(), 1);
+		assert_eq!(attract.step(0.05).len(), 0);
+		assert_eq!(attract.step(0.05).len(), 1);
+		assert_eq!(attract.update(0.1), None);
+		assert_eq!(attract.step(0.1).len(), 0);
+		assert_eq!(attract.update(0.1), Some(AttractModeChange::Stopped)); // Ran out.
+		assert_eq!(attract.step(0.1).len(), 0);
+
+		assert_eq!(attract.update(1.0), Some(AttractModeChange::Started));
+		assert!(attract.note_input());
+		assert_eq!(attract.update(0.1), Some(AttractModeChange::Stopped));
+	}
+}
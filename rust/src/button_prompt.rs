@@ -0,0 +1,311 @@
+use crate::geo::vec2::*;
+use crate::color::*;
+use crate::display_text::*;
+use crate::keyboard::*;
+use crate::gamepad::*;
+use crate::texture_atlas::AtlasRegion;
+use crate::tiled::TiledPoint;
+use crate::origin_rebase::ShiftOrigin;
+use crate::asset_manifest::asset_url;
+
+/// The image with every prompt glyph: keyboard key caps along the top row, and gamepad buttons along the bottom. Each action has one column (in `PromptAction::ALL` order).
+pub const GLYPH_SHEET_URL : &str = "prompts.png";
+/// How wide and tall each glyph is (in pixels).
+const GLYPH_SIZE : u32 = 16;
+/// How wide the glyph sheet is (in pixels).
+const GLYPH_SHEET_WIDTH : u32 = 128;
+/// How tall the glyph sheet is (in pixels).
+const GLYPH_SHEET_HEIGHT : u32 = 32;
+/// The Tiled point type for a prompt shown while the player is near it.
+pub const PROMPT_POINT_TYPE : &str = "prompt";
+/// How close the player has to be to a prompt point to see it (if it doesn't say).
+const DEFAULT_PROMPT_RADIUS : f32 = 48.0;
+/// How far above its point a prompt is drawn.
+const PROMPT_HEIGHT : f32 = 20.0;
+/// The color of prompt text.
+const PROMPT_COLOR : Color = Color { red : 255, green : 255, blue : 255, alpha : 255 };
+
+/// Something the player can do, which prompts can show the button for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptAction {
+	Jump,
+	Track,
+	DroneCommand,
+	BulletTime,
+	Rewind,
+	Menu,
+	Accept,
+}
+
+impl PromptAction {
+	/// Every action, in the order their glyphs are on the sheet.
+	pub const ALL : [PromptAction; 7] = [
+		PromptAction::Jump,
+		PromptAction::Track,
+		PromptAction::DroneCommand,
+		PromptAction::BulletTime,
+		PromptAction::Rewind,
+		PromptAction::Menu,
+		PromptAction::Accept,
+	];
+
+	/// Gets the action with some name (as used in prompt text, like "{jump}").
+	pub fn from_name(name : &str) -> Option<PromptAction> {
+		match name {
+			"jump" => Some(PromptAction::Jump),
+			"track" => Some(PromptAction::Track),
+			"drone" => Some(PromptAction::DroneCommand),
+			"bulletTime" => Some(PromptAction::BulletTime),
+			"rewind" => Some(PromptAction::Rewind),
+			"menu" => Some(PromptAction::Menu),
+			"accept" => Some(PromptAction::Accept),
+			_ => None,
+		}
+	}
+
+	/// Gets the keyboard key that does it.
+	pub fn key(&self) -> Key {
+		match self {
+			PromptAction::Jump => Key::UP,
+			PromptAction::Track => Key::SPACE,
+			PromptAction::DroneCommand => Key::DRONE_COMMAND,
+			PromptAction::BulletTime => Key::BULLET_TIME,
+			PromptAction::Rewind => Key::REWIND,
+			PromptAction::Menu => Key::MENU,
+			PromptAction::Accept => Key::ACCEPT,
+		}
+	}
+
+	/// Gets the gamepad button that does it.
+	pub fn button(&self) -> Button {
+		match self {
+			PromptAction::Jump => Button::A,
+			PromptAction::Track => Button::R,
+			PromptAction::DroneCommand => Button::X,
+			PromptAction::BulletTime => Button::L,
+			PromptAction::Rewind => Button::Y,
+			PromptAction::Menu => Button::START,
+			PromptAction::Accept => Button::A,
+		}
+	}
+
+	/// Gets whether it's being done on either device.
+	pub fn is_down(&self, keyboard : &Keyboard, gamepad : &Gamepad) -> bool {
+		keyboard.is_down(self.key()) || gamepad.is_down(self.button())
+	}
+
+	/// Gets where its glyph for some device is on the glyph sheet (from the bottom left, like all other texture positions).
+	pub fn glyph_region(&self, device : InputDevice) -> AtlasRegion {
+		let column = PromptAction::ALL.iter().position(|action| action == self).unwrap() as u32;
+		AtlasRegion {
+			x : column * GLYPH_SIZE,
+			y : match device {
+				InputDevice::Keyboard => GLYPH_SHEET_HEIGHT - GLYPH_SIZE,
+				InputDevice::Gamepad => 0,
+			},
+			width : GLYPH_SIZE,
+			height : GLYPH_SIZE,
+		}
+	}
+}
+
+/// A piece of a prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptPart {
+	/// Plain text.
+	Text(String),
+	/// The glyph for an action's button.
+	Glyph(PromptAction),
+}
+
+/// Splits prompt text into its parts. Action names in braces (like "Press {jump} to jump") become glyphs.
+pub fn parse_prompt(text : &str) -> Result<Vec<PromptPart>, String> {
+	let mut parts = Vec::new();
+	let mut rest = text;
+	while let Some(start) = rest.find('{') {
+		let end = match rest[start..].find('}') {
+			Some(end) => start + end,
+			None => { return Err(format!("Missing '}}' in {:?}", text)); },
+		};
+		if 0 < start {
+			parts.push(PromptPart::Text(rest[..start].to_string()));
+		}
+		let name = &rest[start+1..end];
+		match PromptAction::from_name(name) {
+			Some(action) => parts.push(PromptPart::Glyph(action)),
+			None => { return Err(format!("There's no action named {:?}", name)); },
+		}
+		rest = &rest[end+1..];
+	}
+	if !rest.is_empty() {
+		parts.push(PromptPart::Text(rest.to_string()));
+	}
+	Ok(parts)
+}
+
+/// Makes the HTML for a prompt, with glyphs for some device. Glyphs are cut out of the glyph sheet with CSS, so they sit in line with the text and grow with the UI scale.
+pub fn prompt_html(parts : &[PromptPart], device : InputDevice) -> String {
+	let mut html = String::new();
+	for part in parts {
+		match part {
+			PromptPart::Text(text) => {
+				for character in text.chars() {
+					match character {
+						'&' => html.push_str("&amp;"),
+						'<' => html.push_str("&lt;"),
+						'>' => html.push_str("&gt;"),
+						_ => html.push(character),
+					}
+				}
+			},
+			PromptPart::Glyph(action) => {
+				let region = action.glyph_region(device);
+				// CSS measures from the top left.
+				let top = GLYPH_SHEET_HEIGHT - region.y - region.height;
+				html.push_str(&format!(
+					concat!(
+						"<span style=\"display:inline-block;vertical-align:middle;image-rendering:pixelated;",
+						"width:calc({}px * var(--ui-scale, 1));height:calc({}px * var(--ui-scale, 1));",
+						"background:url({}) calc(-{}px * var(--ui-scale, 1)) calc(-{}px * var(--ui-scale, 1)) / calc({}px * var(--ui-scale, 1)) auto no-repeat\"></span>",
+					),
+					region.width, region.height, asset_url(GLYPH_SHEET_URL), region.x, top, GLYPH_SHEET_WIDTH,
+				));
+			},
+		}
+	}
+	html
+}
+
+/// A line of HUD text with button glyphs in it, that switches glyphs to match whichever device the player is using.
+pub struct ButtonPrompt {
+	/// What's shown.
+	parts : Vec<PromptPart>,
+	/// What it's drawn in.
+	text : DisplayText,
+	/// The device the glyphs are currently for (None until the first update).
+	device : Option<InputDevice>,
+}
+
+impl ButtonPrompt {
+	/// Creates a hidden instance from prompt text (see `parse_prompt()`).
+	pub fn new(text : &str) -> Result<ButtonPrompt, String> {
+		let parts = parse_prompt(text)?;
+		let mut display = DisplayText::new_text_point(Vec2::zero(), 0.5, 1.0, CssLength::CharWidth(20.0), CssLength::CharHeight(1.5), &PROMPT_COLOR, TextAlignment::CENTER, "");
+		display.hide();
+		Ok(ButtonPrompt {
+			parts,
+			text : display,
+			device : None,
+		})
+	}
+
+	/// Shows it with its bottom center at some position (in HUD pixels from the center of the view), with glyphs for some device.
+	pub fn show(&mut self, position : &Vec2, device : InputDevice) {
+		if Some(device) != self.device {
+			self.device = Some(device);
+			self.text.set_text(&prompt_html(&self.parts, device));
+		}
+		self.text.set_text_point_position(position, 0.5, 1.0, CssLength::CharWidth(20.0), CssLength::CharHeight(1.5));
+		self.text.show();
+	}
+
+	/// Hides it.
+	pub fn hide(&mut self) {
+		self.text.hide();
+	}
+}
+
+impl Drop for ButtonPrompt {
+	fn drop(&mut self) {
+		self.text.hide(); // DisplayText elements stick around, so at least keep them out of the way.
+	}
+}
+
+/// A prompt that shows up over a spot in the map while the player is near it.
+pub struct PromptPoint {
+	/// Where it is.
+	pub position : Vec2,
+	/// How close the player has to be to see it.
+	pub radius : f32,
+	/// What's shown.
+	prompt : ButtonPrompt,
+}
+
+impl PromptPoint {
+	/// Creates an instance from a Tiled point. Uses the properties:
+	/// * "text": What to show (see `parse_prompt()`). Required.
+	/// * "radius": How close the player has to be to see it (defaults to 48).
+	pub fn from_point(point : &TiledPoint) -> Result<PromptPoint, String> {
+		let text = match point.get_property("text") {
+			Some(text) => text,
+			None => { return Err(String::from("Missing \"text\" property")); },
+		};
+		let radius = match point.get_property("radius") {
+			Some(text) => match text.parse::<f32>() {
+				Ok(radius) => radius,
+				Err(_) => { return Err(format!("Bad radius {:?}", text)); },
+			},
+			None => DEFAULT_PROMPT_RADIUS,
+		};
+		Ok(PromptPoint {
+			position : point.position,
+			radius,
+			prompt : ButtonPrompt::new(text)?,
+		})
+	}
+
+	/// Shows or hides the prompt depending on whether the player is near. The view offset converts a position in the world to HUD pixels from the center of the view.
+	pub fn update(&mut self, player : &Vec2, device : InputDevice, to_view_offset : &dyn Fn(&Vec2) -> Vec2) {
+		if self.radius < (player - self.position).length() {
+			self.prompt.hide();
+			return;
+		}
+		let offset = to_view_offset(&(self.position + Vec2::new(0.0, PROMPT_HEIGHT)));
+		self.prompt.show(&offset, device);
+	}
+
+	/// Hides the prompt.
+	pub fn hide(&mut self) {
+		self.prompt.hide();
+	}
+}
+
+impl ShiftOrigin for PromptPoint {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+	}
+}
+
+#[cfg(test)]
+mod tests_button_prompt {
+	use super::*;
+
+	#[test]
+	fn parsing() {
+		assert_eq!(parse_prompt("Press {jump} to jump").unwrap(), vec![
+			PromptPart::Text(String::from("Press ")),
+			PromptPart::Glyph(PromptAction::Jump),
+			PromptPart::Text(String::from(" to jump")),
+		]);
+		assert_eq!(parse_prompt("{drone}{track}").unwrap(), vec![PromptPart::Glyph(PromptAction::DroneCommand), PromptPart::Glyph(PromptAction::Track)]);
+		assert!(parse_prompt("Press {dance}").is_err());
+		assert!(parse_prompt("Press {jump").is_err());
+	}
+
+	#[test]
+	fn glyphs() {
+		// Keyboard glyphs are along the top of the sheet, and gamepad ones along the bottom.
+		assert_eq!(PromptAction::Rewind.glyph_region(InputDevice::Keyboard), AtlasRegion { x : 64, y : 16, width : 16, height : 16 });
+		assert_eq!(PromptAction::Jump.glyph_region(InputDevice::Gamepad), AtlasRegion { x : 0, y : 0, width : 16, height : 16 });
+		for action in PromptAction::ALL.iter() {
+			let region = action.glyph_region(InputDevice::Gamepad);
+			assert!(region.x + region.width <= GLYPH_SHEET_WIDTH);
+		}
+
+		let parts = parse_prompt("<{rewind}>").unwrap();
+		let keyboard = prompt_html(&parts, InputDevice::Keyboard);
+		assert!(keyboard.starts_with("&lt;<span") && keyboard.ends_with("</span>&gt;"));
+		assert!(keyboard.contains("calc(-64px * var(--ui-scale, 1)) calc(-0px"));
+		assert!(prompt_html(&parts, InputDevice::Gamepad).contains("calc(-64px * var(--ui-scale, 1)) calc(-16px"));
+	}
+}
@@ -0,0 +1,139 @@
+/// Reads values out of a packed (little-endian) byte buffer, in order.
+///
+/// Every read fails with a description of what was being read (instead of panicking) when the buffer is too short or malformed.
+pub struct ByteReader<'a> {
+	/// The bytes being read.
+	bytes : &'a [u8],
+	/// Where the next read starts.
+	offset : usize,
+}
+
+impl<'a> ByteReader<'a> {
+	/// Creates an instance that starts reading at the beginning of the given bytes.
+	pub fn new(bytes : &'a [u8]) -> ByteReader<'a> {
+		ByteReader {
+			bytes,
+			offset : 0,
+		}
+	}
+
+	/// Gets where the next read starts.
+	pub fn get_offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Whether everything has been read.
+	pub fn is_done(&self) -> bool {
+		self.bytes.len() <= self.offset
+	}
+
+	/// Takes the next `count` bytes.
+	pub fn read_bytes(&mut self, count : usize, what : &str) -> Result<&'a [u8], String> {
+		let end = self.offset + count;
+		if self.bytes.len() < end {
+			return Err(format!("Ran out of data reading {} at byte {} (needed {} bytes, had {})", what, self.offset, count, self.bytes.len() - self.offset));
+		}
+		let bytes = &self.bytes[self.offset..end];
+		self.offset = end;
+		Ok(bytes)
+	}
+
+	/// Reads a single byte.
+	pub fn read_u8(&mut self, what : &str) -> Result<u8, String> {
+		Ok(self.read_bytes(1, what)?[0])
+	}
+
+	/// Reads a byte as a boolean (anything but zero is true).
+	pub fn read_bool(&mut self, what : &str) -> Result<bool, String> {
+		Ok(0 != self.read_u8(what)?)
+	}
+
+	/// Reads a 16-bit unsigned integer.
+	pub fn read_u16(&mut self, what : &str) -> Result<u16, String> {
+		let bytes = self.read_bytes(2, what)?;
+		Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+	}
+
+	/// Reads a 32-bit unsigned integer.
+	pub fn read_u32(&mut self, what : &str) -> Result<u32, String> {
+		let bytes = self.read_bytes(4, what)?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	/// Reads a 32-bit float.
+	pub fn read_f32(&mut self, what : &str) -> Result<f32, String> {
+		Ok(f32::from_bits(self.read_u32(what)?))
+	}
+
+	/// Reads a 64-bit float.
+	pub fn read_f64(&mut self, what : &str) -> Result<f64, String> {
+		let bytes = self.read_bytes(8, what)?;
+		let mut value = [0u8; 8];
+		value.copy_from_slice(bytes);
+		Ok(f64::from_le_bytes(value))
+	}
+
+	/// Reads a count (a 32-bit unsigned integer), failing if there aren't at least `item_size` bytes left per item (so garbage can't trigger huge allocations).
+	pub fn read_count(&mut self, item_size : usize, what : &str) -> Result<usize, String> {
+		let count = self.read_u32(what)? as usize;
+		let remaining = self.bytes.len() - self.offset;
+		if remaining < count.saturating_mul(item_size) {
+			return Err(format!("Count of {} for {} at byte {} is more than the data left ({} bytes)", count, what, self.offset - 4, remaining));
+		}
+		Ok(count)
+	}
+
+	/// Reads a UTF-8 string (prefixed by its length in bytes).
+	pub fn read_string(&mut self, what : &str) -> Result<String, String> {
+		let length = self.read_count(1, what)?;
+		let bytes = self.read_bytes(length, what)?;
+		match std::str::from_utf8(bytes) {
+			Ok(text) => Ok(text.to_string()),
+			Err(_) => Err(format!("Invalid UTF-8 in {} before byte {}", what, self.offset)),
+		}
+	}
+
+	/// Reads a list of 32-bit floats (prefixed by how many there are).
+	pub fn read_f32_list(&mut self, what : &str) -> Result<Vec<f32>, String> {
+		let count = self.read_count(4, what)?;
+		let mut values = Vec::with_capacity(count);
+		for _ in 0..count {
+			values.push(self.read_f32(what)?);
+		}
+		Ok(values)
+	}
+
+	/// Reads a list of 32-bit unsigned integers (prefixed by how many there are).
+	pub fn read_u32_list(&mut self, what : &str) -> Result<Vec<u32>, String> {
+		let count = self.read_count(4, what)?;
+		let mut values = Vec::with_capacity(count);
+		for _ in 0..count {
+			values.push(self.read_u32(what)?);
+		}
+		Ok(values)
+	}
+}
+
+#[cfg(test)]
+mod tests_byte_reader {
+	use super::*;
+
+	#[test]
+	fn reading() {
+		let mut bytes = vec![1u8, 0x34, 0x12];
+		bytes.extend_from_slice(&2u32.to_le_bytes());
+		bytes.extend_from_slice(b"hi");
+		bytes.extend_from_slice(&1.5f32.to_le_bytes());
+		bytes.extend_from_slice(&(-0.25f64).to_le_bytes());
+		bytes.extend_from_slice(&1000u32.to_le_bytes()); // A count far bigger than what's left.
+		let mut reader = ByteReader::new(&bytes);
+		assert_eq!(reader.read_bool("flag"), Ok(true));
+		assert_eq!(reader.read_u16("short"), Ok(0x1234));
+		assert_eq!(reader.read_string("name"), Ok(String::from("hi")));
+		assert_eq!(reader.read_f32("value"), Ok(1.5));
+		assert_eq!(reader.read_f64("wide value"), Ok(-0.25));
+		assert!(reader.read_u32_list("list").is_err());
+		assert!(reader.is_done());
+		assert!(reader.read_u8("past the end").is_err());
+	}
+}
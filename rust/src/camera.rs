@@ -1,27 +1,153 @@
 use crate::externals::*;
 use crate::geo::vec2::*;
 use crate::geo::vec3::*;
-use crate::geo::mat4::*;
 use crate::geo::bounds2::*;
+use crate::geo::world_vec2::*;
+use crate::display_buffer::{get_render_origin, set_render_origin};
+use crate::origin_rebase::ShiftOrigin;
+use crate::render_view::*;
 
 /// What percent of the screen is reserved (tracked positions aren't allowed in it).
 const TRACK_MARGIN_PERCENT : f32 = 0.5;
+/// How far the camera can get from the render origin before the origin is moved to it (when rendering relative to the camera).
+const REBASE_DISTANCE : f32 = 4096.0;
+
+/// How a virtual resolution is scaled up to fill the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VirtualScaling {
+	/// Only whole-number scales, so every virtual pixel is drawn the same size (the crispest, but it can leave wide borders). Screens smaller than the virtual resolution still scale down to fit.
+	Integer,
+	/// Whatever scale fills the most of the screen (while keeping the aspect ratio).
+	Fit,
+}
+
+impl VirtualScaling {
+	/// Gets the scaling with some name ("integer" or "fit").
+	pub fn from_name(name : &str) -> Option<VirtualScaling> {
+		match name {
+			"integer" => Some(VirtualScaling::Integer),
+			"fit" => Some(VirtualScaling::Fit),
+			_ => None,
+		}
+	}
+}
+
+/// A fixed view size (in world units at normal zoom) that's scaled up to fill the screen, with letterboxing around whatever's left over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualResolution {
+	/// How wide the view is.
+	pub width : u32,
+	/// How tall the view is.
+	pub height : u32,
+	/// How it's scaled up.
+	pub scaling : VirtualScaling,
+}
+
+impl VirtualResolution {
+	/// Creates an instance.
+	pub fn new(width : u32, height : u32, scaling : VirtualScaling) -> VirtualResolution {
+		VirtualResolution { width : width.max(1), height : height.max(1), scaling }
+	}
+
+	/// Works out how to show it in an area some number of pixels big. Gives the scale and the size (in pixels) of the part that's drawn into.
+	pub fn fit(&self, width : u32, height : u32) -> (f32, u32, u32) {
+		let exact = (width as f32 / self.width as f32).min(height as f32 / self.height as f32);
+		let scale = match self.scaling {
+			VirtualScaling::Integer if 1.0 <= exact => exact.floor(),
+			_ => exact,
+		};
+		(
+			scale,
+			((self.width  as f32 * scale).round() as u32).max(1).min(width),
+			((self.height as f32 * scale).round() as u32).max(1).min(height),
+		)
+	}
+}
+
+/// Where a view is actually drawn, after letterboxing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewLayout {
+	/// The region of the screen drawn into.
+	pub rect : ViewRect,
+	/// How wide that is in pixels.
+	pub width : u32,
+	/// How tall that is in pixels.
+	pub height : u32,
+	/// How many pixels a world unit takes up at normal zoom.
+	pub scale : f32,
+}
+
+/// Works out where a view is drawn inside a region of a screen of some size (in pixels). With a virtual resolution it's centered in the region (on whole pixels), otherwise it's the whole region.
+pub fn layout_view(viewport : &ViewRect, screen_width : u32, screen_height : u32, resolution : Option<&VirtualResolution>) -> ViewLayout {
+	let (width, height) = viewport.pixel_size(screen_width, screen_height);
+	let resolution = match resolution {
+		Some(resolution) => resolution,
+		None => { return ViewLayout { rect : viewport.clone(), width, height, scale : 1.0 }; },
+	};
+	let (scale, used_width, used_height) = resolution.fit(width, height);
+	let screen_width = screen_width.max(1) as f32;
+	let screen_height = screen_height.max(1) as f32;
+	let left = (viewport.left * screen_width).round() + ((width - used_width) / 2) as f32;
+	let bottom = (viewport.bottom * screen_height).round() + ((height - used_height) / 2) as f32;
+	ViewLayout {
+		rect : ViewRect::new(left / screen_width, bottom / screen_height, used_width as f32 / screen_width, used_height as f32 / screen_height),
+		width : used_width,
+		height : used_height,
+		scale,
+	}
+}
+
+/// Where a camera was looking (see `Camera::snapshot()`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraSnapshot {
+	/// The center of the view.
+	pub center : Vec2,
+	/// How zoomed in it was.
+	pub zoom : f32,
+}
 
 pub struct Camera {
 	pub center : Vec3,
+	/// How much bigger to draw everything (1.0 is normal size).
+	zoom : f32,
 	screen_width : u32,
 	screen_height : u32,
+	/// The part of the screen this draws into (the main render view).
+	viewport : ViewRect,
+	/// Whether the render origin follows the camera around, so what's drawn near it keeps its precision no matter how far from the world origin it is.
+	relative_rendering : bool,
+	/// The fixed view size that's scaled up to fill the viewport (if any). Otherwise a world unit is a pixel at normal zoom.
+	virtual_resolution : Option<VirtualResolution>,
 }
 
 impl Camera {
 	pub fn new() -> Camera {
 		Camera {
 			center: Vec3::zero(),
+			zoom: 1.0,
 			screen_width: 1,
 			screen_height: 1,
+			viewport: ViewRect::FULL,
+			relative_rendering: cfg!(feature = "f64-world"),
+			virtual_resolution: None,
 		}
 	}
 
+	/// Turns camera-relative rendering on or off. When off, the render origin stays at the world origin.
+	pub fn set_relative_rendering(&mut self, enabled : bool) {
+		self.relative_rendering = enabled;
+		if !enabled {
+			set_render_origin(&WorldVec2::zero());
+		}
+		self.set_transform();
+	}
+
+	/// Gets whether camera-relative rendering is on.
+	pub fn is_relative_rendering(&self) -> bool {
+		self.relative_rendering
+	}
+
 	/// Resizes the screen.
 	pub fn resize(&mut self, width : u32, height : u32) {
 		self.screen_width = width;
@@ -29,44 +155,126 @@ impl Camera {
 		self.set_transform();
 	}
 
+	/// Changes the part of the screen this draws into (as in for split-screen).
+	pub fn set_viewport(&mut self, viewport : &ViewRect) {
+		if *viewport == self.viewport {
+			return;
+		}
+		self.viewport = viewport.clone();
+		self.set_transform();
+	}
+
+	/// Changes the virtual resolution (or turns it off with None).
+	pub fn set_virtual_resolution(&mut self, resolution : Option<VirtualResolution>) {
+		if resolution == self.virtual_resolution {
+			return;
+		}
+		self.virtual_resolution = resolution;
+		self.set_transform();
+	}
+
+	/// Gets the virtual resolution (if any).
+	pub fn get_virtual_resolution(&self) -> Option<&VirtualResolution> {
+		self.virtual_resolution.as_ref()
+	}
+
+	/// Gets where the view is actually drawn (letterboxed inside the viewport when there's a virtual resolution).
+	pub fn layout(&self) -> ViewLayout {
+		layout_view(&self.viewport, self.screen_width, self.screen_height, self.virtual_resolution.as_ref())
+	}
+
+	/// Gets where the view would be drawn if it covered the whole screen (as in for overlays that ignore split-screen).
+	pub fn screen_layout(&self) -> ViewLayout {
+		layout_view(&ViewRect::FULL, self.screen_width, self.screen_height, self.virtual_resolution.as_ref())
+	}
+
+	/// Gets how many pixels a world unit takes up at normal zoom.
+	pub fn get_pixel_scale(&self) -> f32 {
+		self.layout().scale
+	}
+
 	fn set_transform(&mut self) {
-		let mut display = Mat4::new();
-		let mut translation = &self.center * -1.0;
-		// Keep things pixel perfect even with odd widths/heights
-		if 1 == self.screen_width  % 2 { translation.x -= 0.5; }
-		if 1 == self.screen_height % 2 { translation.y -= 0.5; }
-		display.scale_before(&Vec3::new(
-			2.0 / (self.screen_width  as f32),
-			2.0 / (self.screen_height as f32),
-			1.0,
-		)).translate_before(&translation);
-		setDisplayTransform(display.export());
-	}
-
-	/// Gets the size of the screen.
+		let center = WorldVec2::from_vec2(&Vec2::new(self.center.x, self.center.y));
+		if self.relative_rendering {
+			let offset = center.relative_to(&get_render_origin());
+			if REBASE_DISTANCE < offset.x.abs() || REBASE_DISTANCE < offset.y.abs() {
+				set_render_origin(&WorldVec2::new(center.x.floor(), center.y.floor()));
+			}
+		}
+		// Everything drawn is already moved by the render origin, so only the rest needs doing here.
+		let offset = center.relative_to(&get_render_origin());
+		let layout = self.layout();
+		let display = view_transform(&offset, self.center.z, self.zoom * layout.scale, layout.width, layout.height);
+		let rect = &layout.rect;
+		setRenderView(MAIN_VIEW, rect.left, rect.bottom, rect.width, rect.height, display.export());
+	}
+
+	/// Gets the size of the view in world units at normal zoom (the virtual resolution if there is one, otherwise the size of the part of the screen this draws into).
 	pub fn size(&self) -> Vec3 {
+		let layout = self.layout();
+		Vec3::new(layout.width as f32 / layout.scale, layout.height as f32 / layout.scale, 0.0)
+	}
+
+	/// Gets the size of the whole screen.
+	pub fn screen_size(&self) -> Vec3 {
 		Vec3::new(self.screen_width as f32, self.screen_height as f32, 0.0)
 	}
 
 	/// Gets the game world bounds.
 	pub fn bounds(&self) -> Bounds2 {
-		Bounds2::from_centered_rect(&Vec2::new(self.center.x, self.center.y), self.screen_width as f32, self.screen_height as f32)
+		let size = self.size();
+		Bounds2::from_centered_rect(&Vec2::new(self.center.x, self.center.y), size.x / self.zoom, size.y / self.zoom)
+	}
+
+	/// Gets how much bigger everything is drawn (1.0 is normal size).
+	pub fn get_zoom(&self) -> f32 {
+		self.zoom
+	}
+
+	/// Moves the camera to look at a position with a given zoom (ignoring the tracking margins).
+	pub fn set_view(&mut self, center : &Vec2, zoom : f32) {
+		self.center.x = center.x;
+		self.center.y = center.y;
+		self.zoom = zoom;
+		self.set_transform();
+	}
+
+	/// Captures where the camera is looking.
+	pub fn snapshot(&self) -> CameraSnapshot {
+		CameraSnapshot {
+			center : Vec2::new(self.center.x, self.center.y),
+			zoom : self.zoom,
+		}
+	}
+
+	/// Looks where a snapshot was looking.
+	pub fn restore(&mut self, snapshot : &CameraSnapshot) {
+		self.set_view(&snapshot.center, snapshot.zoom);
 	}
 
 	/// Converts a (cartesian) position on the screen to a position in game.
 	pub fn to_game_space(&self, screen_position : &Vec3) -> Vec3 {
+		let layout = self.layout();
+		let corner = layout.rect.pixel_top_left(self.screen_width, self.screen_height);
+		let zoom = self.zoom * layout.scale;
 		Vec3 {
-			x: screen_position.x - ((self.screen_width  / 2) as f32) + self.center.x,
-			y:-screen_position.y + ((self.screen_height / 2) as f32) + self.center.y,
+			x: (screen_position.x - corner.x - ((layout.width  / 2) as f32)) / zoom + self.center.x,
+			y:(-screen_position.y + corner.y + ((layout.height / 2) as f32)) / zoom + self.center.y,
 			z: self.center.z,
 		}
 	}
 
+	/// Gets how far a position in game is from the center of the view, in pixels at the virtual resolution (or screen pixels without one).
+	pub fn to_view_offset(&self, position : &Vec2) -> Vec2 {
+		Vec2::new((position.x - self.center.x) * self.zoom, (position.y - self.center.y) * self.zoom)
+	}
+
 	/// Track the given location with this camera.
 	pub fn track_position(&mut self, position : &Vec2) {
 		let percent = (1.0 - TRACK_MARGIN_PERCENT) / 2.0;
-		let max_x_distance = (self.screen_width  as f32) * percent;
-		let max_y_distance = (self.screen_height as f32) * percent;
+		let size = self.size();
+		let max_x_distance = size.x * percent;
+		let max_y_distance = size.y * percent;
 		let mut changed = false;
 		if (self.center.x - position.x).abs() > max_x_distance {
 			if self.center.x < position.x {
@@ -91,3 +299,45 @@ impl Camera {
 		}
 	}
 }
+
+impl ShiftOrigin for Camera {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.center.x += shift.x;
+		self.center.y += shift.y;
+		self.set_transform();
+	}
+}
+
+#[cfg(test)]
+mod tests_camera {
+	use super::*;
+
+	#[test]
+	fn virtual_resolution_layout() {
+		let integer = VirtualResolution::new(480, 270, VirtualScaling::Integer);
+		// A 1080p screen fits exactly four times over.
+		let layout = layout_view(&ViewRect::FULL, 1920, 1080, Some(&integer));
+		assert_eq!((layout.width, layout.height, layout.scale), (1920, 1080, 4.0));
+		// Anything in between rounds down to a whole scale and letterboxes the rest (centered on whole pixels).
+		let layout = layout_view(&ViewRect::FULL, 1280, 800, Some(&integer));
+		assert_eq!((layout.width, layout.height, layout.scale), (960, 540, 2.0));
+		let corner = layout.rect.pixel_top_left(1280, 800);
+		assert_eq!((corner.x, corner.y), (160.0, 130.0));
+		assert_eq!(layout.rect.pixel_size(1280, 800), (960, 540));
+		// Fitting fills one whole axis instead.
+		let fit = VirtualResolution::new(480, 270, VirtualScaling::Fit);
+		let layout = layout_view(&ViewRect::FULL, 1280, 800, Some(&fit));
+		assert_eq!((layout.width, layout.height), (1280, 720));
+		// Screens smaller than the virtual resolution scale down either way.
+		let layout = layout_view(&ViewRect::FULL, 240, 200, Some(&integer));
+		assert_eq!((layout.width, layout.height, layout.scale), (240, 135, 0.5));
+		// Letterboxing happens inside the viewport.
+		let layout = layout_view(&ViewRect::new(0.5, 0.0, 0.5, 1.0), 1920, 1080, Some(&integer));
+		assert_eq!((layout.width, layout.height, layout.scale), (960, 540, 2.0));
+		let corner = layout.rect.pixel_top_left(1920, 1080);
+		assert_eq!((corner.x, corner.y), (960.0, 270.0));
+		// Without one, it's just the viewport.
+		let layout = layout_view(&ViewRect::FULL, 800, 600, None);
+		assert_eq!((layout.rect, layout.width, layout.height, layout.scale), (ViewRect::FULL, 800, 600, 1.0));
+	}
+}
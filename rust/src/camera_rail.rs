@@ -0,0 +1,182 @@
+use crate::geo::vec2::*;
+use crate::tiled::{TiledFile, TiledPolyline};
+use crate::tween::{Easing, Tween};
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled polyline type for a path the camera can be sent along (as in from dialogue). The polyline's name is the rail's name.
+pub const CAMERA_RAIL_TYPE : &str = "camera_rail";
+/// How long the camera takes to get back to the player after reaching the end of a rail (in seconds).
+const RETURN_SECONDS : f32 = 0.75;
+
+/// A named path for the camera.
+#[derive(Debug, Clone)]
+pub struct CameraRail {
+	/// The name it's asked for by.
+	pub name : String,
+	/// The points along it (at least two).
+	points : Vec<Vec2>,
+	/// How far along the rail each point is (so it can be travelled at a steady speed).
+	distances : Vec<f32>,
+}
+
+impl CameraRail {
+	/// Creates an instance from a Tiled polyline.
+	pub fn from_polyline(polyline : &TiledPolyline) -> Result<CameraRail, String> {
+		CameraRail::new(&polyline.name, &polyline.points)
+	}
+
+	/// Creates an instance following some points.
+	pub fn new(name : &str, points : &Vec<Vec2>) -> Result<CameraRail, String> {
+		if points.len() < 2 {
+			return Err(String::from("It needs at least two points"));
+		}
+		let mut distances = vec![0.0];
+		for pair in points.windows(2) {
+			let last = distances[distances.len() - 1];
+			distances.push(last + (pair[1] - pair[0]).length());
+		}
+		Ok(CameraRail {
+			name : name.to_string(),
+			points : points.clone(),
+			distances,
+		})
+	}
+
+	/// Gets the whole length of the rail.
+	pub fn length(&self) -> f32 {
+		self.distances[self.distances.len() - 1]
+	}
+
+	/// Gets the spot some fraction (from 0 to 1) of the way along the rail (by distance).
+	pub fn position_at(&self, fraction : f32) -> Vec2 {
+		let target = fraction.max(0.0).min(1.0) * self.length();
+		for index in 1..self.points.len() {
+			if target <= self.distances[index] {
+				let span = self.distances[index] - self.distances[index - 1];
+				let along = if 0.0 < span { (target - self.distances[index - 1]) / span } else { 1.0 };
+				return self.points[index - 1] + (self.points[index] - self.points[index - 1]).scale(along);
+			}
+		}
+		self.points[self.points.len() - 1]
+	}
+}
+
+/// All of the camera rails in the room.
+pub struct CameraRails {
+	/// Every rail (in the order they were loaded).
+	rails : Vec<CameraRail>,
+}
+
+impl CameraRails {
+	/// Creates an instance with no rails.
+	pub fn new() -> CameraRails {
+		CameraRails {
+			rails : Vec::new(),
+		}
+	}
+
+	/// Replaces all rails with the ones in a Tiled file. Returns why any couldn't be loaded.
+	pub fn load_from(&mut self, file : &TiledFile) -> Vec<String> {
+		self.rails.clear();
+		let mut errors = Vec::new();
+		for polyline in file.get_polylines() {
+			if CAMERA_RAIL_TYPE != polyline.r#type {
+				continue;
+			}
+			match CameraRail::from_polyline(polyline) {
+				Ok(rail) => { self.rails.push(rail); },
+				Err(error) => errors.push(format!("Couldn't load camera rail {:?} due to: {}", polyline.name, error)),
+			}
+		}
+		errors
+	}
+
+	/// Gets a rail by name.
+	pub fn get(&self, name : &str) -> Option<&CameraRail> {
+		self.rails.iter().find(|rail| rail.name == name)
+	}
+}
+
+impl ShiftOrigin for CameraRails {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for rail in &mut self.rails {
+			for point in &mut rail.points {
+				*point += shift;
+			}
+		}
+	}
+}
+
+/// Moves the camera along a rail, then eases it back to whatever it normally follows.
+#[derive(Debug, Clone)]
+pub struct RailMove {
+	/// The rail being travelled.
+	rail : CameraRail,
+	/// How far along the rail the camera is (from 0 to 1).
+	progress : Tween,
+	/// How far back to the followed spot the camera is once it's off the end of the rail (from 0 to 1).
+	returning : Tween,
+}
+
+impl RailMove {
+	/// Starts travelling a rail over some seconds.
+	pub fn new(rail : &CameraRail, seconds : f32, easing : Easing) -> RailMove {
+		RailMove {
+			rail : rail.clone(),
+			progress : Tween::new(0.0, 1.0, seconds, easing),
+			returning : Tween::new(0.0, 1.0, RETURN_SECONDS, Easing::QuadInOut),
+		}
+	}
+
+	/// Moves forward in time. Returns where the camera should be centered, given where it would be if it were following normally.
+	pub fn update(&mut self, elapsed_seconds : f32, followed : &Vec2) -> Vec2 {
+		if !self.progress.is_done() {
+			return self.rail.position_at(self.progress.update(elapsed_seconds));
+		}
+		// Blend toward the followed spot (which is likely still moving), so there's no jump at the end.
+		let end = self.rail.position_at(1.0);
+		end + (followed - end).scale(self.returning.update(elapsed_seconds))
+	}
+
+	/// Whether it's done (and the camera is back to following).
+	pub fn is_done(&self) -> bool {
+		self.progress.is_done() && self.returning.is_done()
+	}
+}
+
+impl ShiftOrigin for RailMove {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for point in &mut self.rail.points {
+			*point += shift;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_camera_rail {
+	use super::*;
+
+	/// Gets a position as a tuple (so it can be compared).
+	fn xy(position : Vec2) -> (f32, f32) {
+		(position.x, position.y)
+	}
+
+	#[test]
+	fn travelling() {
+		let rail = CameraRail::new("pan", &vec![Vec2::new(0.0, 0.0), Vec2::new(30.0, 0.0), Vec2::new(30.0, 10.0)]).unwrap();
+		assert_eq!(rail.length(), 40.0);
+		assert_eq!(xy(rail.position_at(0.5)), (20.0, 0.0));
+		assert_eq!(xy(rail.position_at(0.875)), (30.0, 5.0));
+		assert!(CameraRail::new("short", &vec![Vec2::new(0.0, 0.0)]).is_err());
+
+		// Travels the rail, then eases back to the followed spot.
+		let followed = Vec2::new(30.0, 110.0);
+		let mut travel = RailMove::new(&rail, 2.0, Easing::Linear);
+		assert_eq!(xy(travel.update(1.0, &followed)), (20.0, 0.0));
+		assert_eq!(xy(travel.update(1.0, &followed)), (30.0, 10.0));
+		assert!(!travel.is_done());
+		assert_eq!(xy(travel.update(RETURN_SECONDS / 2.0, &followed)), (30.0, 60.0));
+		assert_eq!(xy(travel.update(RETURN_SECONDS, &followed)), (30.0, 110.0));
+		assert!(travel.is_done());
+	}
+}
@@ -0,0 +1,203 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::tween::*;
+use crate::tiled::TiledFile;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled rectangle type for a region that keeps the camera inside of it (while still following the player).
+pub const CAMERA_LIMIT_TYPE : &str = "cameraLimit";
+/// The Tiled rectangle type for a region that holds the camera still at its center (like a boss arena).
+pub const CAMERA_LOCK_TYPE : &str = "cameraLock";
+/// How long the camera takes to move between regions (in seconds).
+const REGION_BLEND_SECONDS : f32 = 0.5;
+
+/// How a camera region constrains the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraRegionKind {
+	/// Follows the player (without the usual margins), but never shows anything outside the region.
+	Limit,
+	/// Looks at the region's center.
+	Lock,
+}
+
+/// An area of the map that takes over the camera while the player is in it.
+#[derive(Debug, Clone)]
+pub struct CameraRegion {
+	/// The area covered.
+	pub bounds : Bounds2,
+	/// How it constrains the camera.
+	pub kind : CameraRegionKind,
+}
+
+impl CameraRegion {
+	/// Gets where the camera should look while following a position in this region, with a view of some size (in world units).
+	pub fn target(&self, position : &Vec2, view_size : &Vec2) -> Vec2 {
+		match self.kind {
+			CameraRegionKind::Limit => clamp_center(position, view_size, &self.bounds),
+			CameraRegionKind::Lock => Vec2::new(
+				(self.bounds.x_min() + self.bounds.x_max()) / 2.0,
+				(self.bounds.y_min() + self.bounds.y_max()) / 2.0,
+			),
+		}
+	}
+}
+
+/// Moves the center of a view of some size so the view stays inside some bounds. Along any axis where the view is bigger than the bounds, it's centered on them instead.
+pub fn clamp_center(center : &Vec2, view_size : &Vec2, bounds : &Bounds2) -> Vec2 {
+	let clamp = |value : f32, size : f32, min : f32, max : f32| {
+		if max - min <= size {
+			(min + max) / 2.0
+		} else {
+			value.max(min + size / 2.0).min(max - size / 2.0)
+		}
+	};
+	Vec2::new(
+		clamp(center.x, view_size.x, bounds.x_min(), bounds.x_max()),
+		clamp(center.y, view_size.y, bounds.y_min(), bounds.y_max()),
+	)
+}
+
+/// All of the camera regions in the level, and the blending between them as the player moves around.
+pub struct CameraRegions {
+	/// Every region (in the order they were loaded). The first one containing the player is used.
+	regions : Vec<CameraRegion>,
+	/// The index of the region the player is in (if any).
+	active : Option<usize>,
+	/// Where the camera was looking when the active region last changed.
+	blend_from : Vec2,
+	/// How far the camera has moved from `blend_from` to where the active region (or the default following) wants it, from 0 to 1.
+	blend : Tween,
+	/// Where the camera looked after the last update.
+	last : Vec2,
+	/// Whether the next update should jump straight to its target.
+	snap_next : bool,
+}
+
+impl CameraRegions {
+	/// Creates an instance with no regions.
+	pub fn new() -> CameraRegions {
+		CameraRegions {
+			regions : Vec::new(),
+			active : None,
+			blend_from : Vec2::zero(),
+			blend : Tween::hold(1.0),
+			last : Vec2::zero(),
+			snap_next : true,
+		}
+	}
+
+	/// Replaces all regions with the ones in a Tiled file. The next update jumps straight to where the camera should be.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.regions.clear();
+		for region in file.get_regions() {
+			let kind = if CAMERA_LIMIT_TYPE == region.r#type {
+				CameraRegionKind::Limit
+			} else if CAMERA_LOCK_TYPE == region.r#type {
+				CameraRegionKind::Lock
+			} else {
+				continue;
+			};
+			self.add(&region.bounds, kind);
+		}
+		self.active = None;
+		self.snap();
+	}
+
+	/// Adds a region. Returns its index.
+	pub fn add(&mut self, bounds : &Bounds2, kind : CameraRegionKind) -> usize {
+		self.regions.push(CameraRegion { bounds : bounds.clone(), kind });
+		self.regions.len() - 1
+	}
+
+	/// Gets all regions.
+	pub fn get_regions<'a>(&'a self) -> &'a Vec<CameraRegion> {
+		&self.regions
+	}
+
+	/// Makes the next update jump straight to its target (as in after the camera was moved some other way).
+	pub fn snap(&mut self) {
+		self.snap_next = true;
+	}
+
+	/// Finds the region (if any) that takes over the camera at a position.
+	pub fn find(&self, position : &Vec2) -> Option<usize> {
+		self.regions.iter().position(|region| region.bounds.overlaps_point(position))
+	}
+
+	/// Moves forward in time, giving where the camera should look.
+	/// The position is what's being followed, `followed` is where the default following put the camera, and the view size is in world units.
+	/// Returns None once outside of every region and done blending (so the default following is left alone).
+	pub fn update(&mut self, elapsed_seconds : f32, position : &Vec2, followed : &Vec2, view_size : &Vec2) -> Option<Vec2> {
+		let region = self.find(position);
+		if region != self.active || self.snap_next {
+			self.active = region;
+			self.blend_from = self.last.clone();
+			self.blend = if self.snap_next {
+				Tween::hold(1.0)
+			} else {
+				Tween::new(0.0, 1.0, REGION_BLEND_SECONDS, Easing::QuadInOut)
+			};
+			self.snap_next = false;
+		}
+		let amount = self.blend.update(elapsed_seconds);
+		let target = match region {
+			Some(index) => self.regions[index].target(position, view_size),
+			None => followed.clone(),
+		};
+		if region.is_none() && self.blend.is_done() {
+			self.last = target;
+			return None;
+		}
+		self.last = self.blend_from + (target - self.blend_from) * amount;
+		Some(self.last.clone())
+	}
+}
+
+impl ShiftOrigin for CameraRegions {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for region in &mut self.regions {
+			region.bounds.shift_origin(shift);
+		}
+		self.blend_from += shift;
+		self.last += shift;
+	}
+}
+
+#[cfg(test)]
+mod tests_camera_region {
+	use super::*;
+
+	#[test]
+	fn clamping() {
+		let bounds = Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(400.0, 100.0));
+		let view = Vec2::new(200.0, 150.0);
+		let center = clamp_center(&Vec2::new(20.0, 0.0), &view, &bounds);
+		// Pushed in from the left edge, and centered vertically since the view is taller than the region.
+		assert_eq!((center.x, center.y), (100.0, 50.0));
+		let center = clamp_center(&Vec2::new(250.0, 90.0), &view, &bounds);
+		assert_eq!((center.x, center.y), (250.0, 50.0));
+	}
+
+	#[test]
+	fn blends_between_regions() {
+		let mut regions = CameraRegions::new();
+		regions.add(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 100.0)), CameraRegionKind::Lock);
+		let view = Vec2::new(50.0, 50.0);
+		// Outside of every region just follows.
+		assert!(regions.update(0.1, &Vec2::new(-50.0, 0.0), &Vec2::new(-40.0, 0.0), &view).is_none());
+		// Going in eases over to the region.
+		let start = regions.update(0.0, &Vec2::new(10.0, 10.0), &Vec2::new(10.0, 10.0), &view).unwrap();
+		assert_eq!((start.x, start.y), (-40.0, 0.0));
+		let middle = regions.update(REGION_BLEND_SECONDS / 2.0, &Vec2::new(10.0, 10.0), &Vec2::new(10.0, 10.0), &view).unwrap();
+		assert!(-40.0 < middle.x && middle.x < 50.0);
+		let end = regions.update(REGION_BLEND_SECONDS, &Vec2::new(10.0, 10.0), &Vec2::new(10.0, 10.0), &view).unwrap();
+		assert_eq!((end.x, end.y), (50.0, 50.0));
+		// Leaving eases back to following, then lets go.
+		assert!(regions.update(0.1, &Vec2::new(150.0, 50.0), &Vec2::new(140.0, 50.0), &view).is_some());
+		assert!(regions.update(REGION_BLEND_SECONDS, &Vec2::new(150.0, 50.0), &Vec2::new(140.0, 50.0), &view).is_none());
+		// Snapping skips the blend.
+		regions.snap();
+		let snapped = regions.update(0.0, &Vec2::new(10.0, 10.0), &Vec2::new(10.0, 10.0), &view).unwrap();
+		assert_eq!((snapped.x, snapped.y), (50.0, 50.0));
+	}
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crate::geo::vec2::*;
+use crate::tiled::{TiledFile, TiledPoint};
+use crate::world_flags::*;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled point type that starts a challenge when the player touches it. The point's name is the challenge's name.
+pub const CHALLENGE_START_TYPE : &str = "challengeStart";
+/// The Tiled point type that finishes a challenge when the player touches it. The point's name is the challenge's name.
+pub const CHALLENGE_END_TYPE : &str = "challengeEnd";
+/// How close the player has to be to a start or end point to trigger it (if it doesn't say).
+const DEFAULT_TRIGGER_RADIUS : f32 = 12.0;
+/// The kind of pickup given for finishing a challenge (if it doesn't say).
+const DEFAULT_REWARD_KIND : &str = "coin";
+
+/// Gets the world flag that's set once a challenge is finished.
+pub fn challenge_flag(name : &str) -> String {
+	format!("challenge.{}", name)
+}
+
+/// Where a challenge is in its run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengeState {
+	/// Waiting for the player to touch the start.
+	Ready,
+	/// Counting down, with some seconds left.
+	Running(f32),
+	/// Finished (in this session or an earlier one), so it won't start again.
+	Completed,
+}
+
+/// Something that happened to a challenge during an update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengeChange {
+	/// The player touched the start.
+	Started,
+	/// The player reached the end in time.
+	Completed,
+	/// Time ran out.
+	Failed,
+}
+
+/// A timed run from a start point to an end point.
+#[derive(Debug, Clone)]
+pub struct ChallengeRoom {
+	/// The name (shared by its start and end points).
+	pub name : String,
+	/// Where the run starts.
+	pub start : Vec2,
+	/// Where the run ends.
+	pub end : Vec2,
+	/// How close the player has to be to the start or end to trigger it.
+	pub radius : f32,
+	/// How long the player has to get from the start to the end (in seconds).
+	pub seconds : f32,
+	/// The kind of pickup given for finishing.
+	pub reward : String,
+	/// Where the run is.
+	state : ChallengeState,
+	/// Whether the player has left the start since it last failed (so it doesn't start straight back up).
+	armed : bool,
+}
+
+impl ChallengeRoom {
+	/// Creates an instance from its start and end Tiled points. Uses the start point's properties:
+	/// * "seconds": How long the player has. Required.
+	/// * "radius": How close the player has to be to the start or end to trigger them (defaults to 12).
+	/// * "reward": The kind of pickup given for finishing (defaults to "coin").
+	pub fn from_points(start : &TiledPoint, end : &TiledPoint) -> Result<ChallengeRoom, String> {
+		let seconds = match start.get_property("seconds") {
+			Some(text) => match text.parse::<f32>() {
+				Ok(seconds) if 0.0 < seconds => seconds,
+				_ => { return Err(format!("Bad seconds {:?}", text)); },
+			},
+			None => { return Err(String::from("Missing \"seconds\" property")); },
+		};
+		let radius = match start.get_property("radius") {
+			Some(text) => match text.parse::<f32>() {
+				Ok(radius) => radius,
+				Err(_) => { return Err(format!("Bad radius {:?}", text)); },
+			},
+			None => DEFAULT_TRIGGER_RADIUS,
+		};
+		Ok(ChallengeRoom {
+			name : start.name.clone(),
+			start : start.position,
+			end : end.position,
+			radius,
+			seconds,
+			reward : start.get_property("reward").unwrap_or(DEFAULT_REWARD_KIND).to_string(),
+			state : ChallengeState::Ready,
+			armed : true,
+		})
+	}
+
+	/// Gets where the run is.
+	pub fn get_state(&self) -> ChallengeState {
+		self.state
+	}
+
+	/// Marks it as finished (as in when it was finished in an earlier session).
+	pub fn mark_completed(&mut self) {
+		self.state = ChallengeState::Completed;
+	}
+
+	/// Moves forward in time with the player at some position.
+	pub fn update(&mut self, elapsed_seconds : f32, position : &Vec2) -> Option<ChallengeChange> {
+		match self.state {
+			ChallengeState::Ready => {
+				let touching = (position - self.start).length() <= self.radius;
+				if !touching {
+					self.armed = true;
+					return None;
+				}
+				if !self.armed {
+					return None;
+				}
+				self.state = ChallengeState::Running(self.seconds);
+				Some(ChallengeChange::Started)
+			},
+			ChallengeState::Running(remaining) => {
+				if (position - self.end).length() <= self.radius {
+					self.state = ChallengeState::Completed;
+					return Some(ChallengeChange::Completed);
+				}
+				let remaining = remaining - elapsed_seconds;
+				if 0.0 >= remaining {
+					self.state = ChallengeState::Ready;
+					self.armed = false;
+					return Some(ChallengeChange::Failed);
+				}
+				self.state = ChallengeState::Running(remaining);
+				None
+			},
+			ChallengeState::Completed => None,
+		}
+	}
+}
+
+/// All of the challenges in the room. Only one can run at a time.
+pub struct Challenges {
+	/// Every challenge (in the order their start points were loaded).
+	challenges : Vec<ChallengeRoom>,
+	/// The index of the one that's running (if any).
+	active : Option<usize>,
+}
+
+impl Challenges {
+	/// Creates an instance with no challenges.
+	pub fn new() -> Challenges {
+		Challenges {
+			challenges : Vec::new(),
+			active : None,
+		}
+	}
+
+	/// Replaces all challenges with the ones in a Tiled file, pairing start and end points by name. Ones whose flag is already set start out completed.
+	/// Returns why any couldn't be loaded.
+	pub fn load_from(&mut self, file : &TiledFile, flags : &WorldFlags) -> Vec<String> {
+		let mut ends : HashMap<&str, &TiledPoint> = HashMap::new();
+		for point in file.get_points() {
+			if CHALLENGE_END_TYPE == point.r#type {
+				ends.insert(&point.name, point);
+			}
+		}
+		self.challenges.clear();
+		self.active = None;
+		let mut errors = Vec::new();
+		for point in file.get_points() {
+			if CHALLENGE_START_TYPE != point.r#type {
+				continue;
+			}
+			let result = match ends.remove(point.name.as_str()) {
+				Some(end) => ChallengeRoom::from_points(point, end),
+				None => Err(String::from("There's no end point with the same name")),
+			};
+			match result {
+				Ok(challenge) => { self.add(challenge, flags); },
+				Err(error) => errors.push(format!("Couldn't load challenge {:?} due to: {}", point.name, error)),
+			}
+		}
+		for name in ends.keys() {
+			errors.push(format!("Challenge end {:?} has no start point", name));
+		}
+		errors
+	}
+
+	/// Adds a challenge (completed if its flag is already set). Returns its index.
+	pub fn add(&mut self, mut challenge : ChallengeRoom, flags : &WorldFlags) -> usize {
+		if flags.get_bool(&challenge_flag(&challenge.name)) {
+			challenge.mark_completed();
+		}
+		self.challenges.push(challenge);
+		self.challenges.len() - 1
+	}
+
+	/// Gets all challenges.
+	pub fn get_challenges<'a>(&'a self) -> &'a Vec<ChallengeRoom> {
+		&self.challenges
+	}
+
+	/// Gets the one that's running (if any).
+	pub fn get_active(&self) -> Option<&ChallengeRoom> {
+		self.active.map(|index| &self.challenges[index])
+	}
+
+	/// Gets how many seconds the running challenge has left (if one is running).
+	pub fn get_remaining(&self) -> Option<f32> {
+		match self.get_active()?.get_state() {
+			ChallengeState::Running(remaining) => Some(remaining),
+			_ => None,
+		}
+	}
+
+	/// Moves forward in time with the player at some position. Returns the index of the challenge that changed (if any) and how.
+	pub fn update(&mut self, elapsed_seconds : f32, position : &Vec2) -> Option<(usize, ChallengeChange)> {
+		if let Some(index) = self.active {
+			let change = self.challenges[index].update(elapsed_seconds, position)?;
+			self.active = None;
+			return Some((index, change));
+		}
+		for (index, challenge) in self.challenges.iter_mut().enumerate() {
+			if let Some(change) = challenge.update(elapsed_seconds, position) {
+				self.active = Some(index);
+				return Some((index, change));
+			}
+		}
+		None
+	}
+}
+
+impl ShiftOrigin for Challenges {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for challenge in &mut self.challenges {
+			challenge.start += shift;
+			challenge.end += shift;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_challenge_room {
+	use super::*;
+
+	fn challenge(name : &str) -> ChallengeRoom {
+		ChallengeRoom {
+			name : name.to_string(),
+			start : Vec2::new(0.0, 0.0),
+			end : Vec2::new(100.0, 0.0),
+			radius : 10.0,
+			seconds : 2.0,
+			reward : String::from("coin"),
+			state : ChallengeState::Ready,
+			armed : true,
+		}
+	}
+
+	#[test]
+	fn running() {
+		let mut flags = WorldFlags::new();
+		let mut challenges = Challenges::new();
+		challenges.add(challenge("first"), &flags);
+		assert_eq!(challenges.update(0.1, &Vec2::new(50.0, 0.0)), None);
+		assert_eq!(challenges.update(0.1, &Vec2::new(5.0, 0.0)), Some((0, ChallengeChange::Started)));
+		assert_eq!(challenges.update(0.5, &Vec2::new(50.0, 0.0)), None);
+		assert_eq!(challenges.get_remaining(), Some(1.5));
+
+		// Running out of time fails it, and it can't start again until the player leaves the start.
+		assert_eq!(challenges.update(1.5, &Vec2::new(2.0, 0.0)), Some((0, ChallengeChange::Failed)));
+		assert_eq!(challenges.get_remaining(), None);
+		assert_eq!(challenges.update(0.1, &Vec2::new(2.0, 0.0)), None);
+		assert_eq!(challenges.update(0.1, &Vec2::new(20.0, 0.0)), None);
+		assert_eq!(challenges.update(0.1, &Vec2::new(2.0, 0.0)), Some((0, ChallengeChange::Started)));
+
+		// Reaching the end in time finishes it for good.
+		assert_eq!(challenges.update(1.0, &Vec2::new(95.0, 0.0)), Some((0, ChallengeChange::Completed)));
+		assert_eq!(challenges.update(0.1, &Vec2::new(0.0, 0.0)), None);
+		assert_eq!(challenges.get_challenges()[0].get_state(), ChallengeState::Completed);
+
+		// Ones finished in earlier sessions start out that way.
+		flags.set(&challenge_flag("second"), FlagValue::Bool(true));
+		let second = challenges.add(challenge("second"), &flags);
+		assert_eq!(challenges.get_challenges()[second].get_state(), ChallengeState::Completed);
+	}
+}
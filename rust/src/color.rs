@@ -6,7 +6,8 @@ pub trait ColorExportable {
 }
 
 /// A class for storing colors.
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
 	pub red : ColorMagnitude,
 	pub green : ColorMagnitude,
@@ -22,6 +23,20 @@ impl Color {
 	pub fn to_css(&self) -> String {
 		format!("rgba({}, {}, {}, {})", self.red, self.green, self.blue, (self.alpha as f32) / 255.0)
 	}
+
+	/// Parses a hex color like "#ff00ff" (or "#80ff00ff" with alpha first, like Tiled writes). The "#" is optional.
+	pub fn from_hex(text : &str) -> Result<Color, String> {
+		let digits = text.trim().trim_start_matches('#');
+		let value = match u32::from_str_radix(digits, 16) {
+			Ok(value) => value,
+			Err(_) => { return Err(format!("Invalid hex color {:?}", text)); },
+		};
+		match digits.len() {
+			6 => Ok(Color::new((value >> 16) as u8, (value >> 8) as u8, value as u8, 255)),
+			8 => Ok(Color::new((value >> 16) as u8, (value >> 8) as u8, value as u8, (value >> 24) as u8)),
+			_ => Err(format!("Hex color {:?} needs 6 or 8 digits", text)),
+		}
+	}
 }
 
 impl ColorExportable for Color {
@@ -64,4 +79,18 @@ impl ColorExportable for TexturePositionAsColor {
 		output.push(y_pieces[0]);
 		output.push(y_pieces[1]);
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests_color {
+	use super::*;
+
+	#[test]
+	fn hex() {
+		let magenta = Color::from_hex("#ff00ff").unwrap();
+		assert_eq!((magenta.red, magenta.green, magenta.blue, magenta.alpha), (255, 0, 255, 255));
+		let faded = Color::from_hex("80102030").unwrap();
+		assert_eq!((faded.red, faded.green, faded.blue, faded.alpha), (0x10, 0x20, 0x30, 0x80));
+		assert!(Color::from_hex("#ff00").is_err());
+		assert!(Color::from_hex("#gg00ff").is_err());
+	}
+}
@@ -0,0 +1,53 @@
+use crate::color::Color;
+use crate::geo::vec2::*;
+
+/// How far the camera will zoom out to keep both players in view. Past this (the split threshold), the screen splits in two.
+pub const COOP_MIN_ZOOM : f32 = 0.5;
+/// How much room to leave between each player and the edge of the screen (in pixels, before zooming).
+const COOP_FRAME_MARGIN : f32 = 64.0;
+/// What the second player's sprite is tinted toward, so the two can be told apart.
+pub const SECOND_PLAYER_TINT : Color = Color { red : 96, green : 200, blue : 255, alpha : 255 };
+/// How strongly the second player's sprite is tinted.
+pub const SECOND_PLAYER_TINT_AMOUNT : f32 = 0.5;
+
+/// Where the camera should look to show both players (see `frame_players()`).
+#[derive(Debug, Clone)]
+pub struct CoopFraming {
+	/// The center of the view.
+	pub center : Vec2,
+	/// How zoomed in to be.
+	pub zoom : f32,
+	/// Whether the players were too far apart to fit (so the view is just on the first player).
+	pub split : bool,
+}
+
+/// Works out how to frame two players on a screen of some size (in pixels): centered between them, and zoomed out (no further than `COOP_MIN_ZOOM`, and no closer than `max_zoom`) so both fit with a margin.
+/// If they can't fit, the view stays on the first player at the furthest zoom and `split` is set.
+pub fn frame_players(first : &Vec2, second : &Vec2, screen : &Vec2, max_zoom : f32) -> CoopFraming {
+	let width = (first.x - second.x).abs() + 2.0 * COOP_FRAME_MARGIN;
+	let height = (first.y - second.y).abs() + 2.0 * COOP_FRAME_MARGIN;
+	let zoom = max_zoom.min(screen.x / width).min(screen.y / height);
+	if zoom < COOP_MIN_ZOOM {
+		return CoopFraming { center : first.clone(), zoom : COOP_MIN_ZOOM, split : true };
+	}
+	CoopFraming { center : (first + second) * 0.5, zoom, split : false }
+}
+
+#[cfg(test)]
+mod tests_coop {
+	use super::*;
+
+	#[test]
+	fn framing() {
+		let screen = Vec2::new(640.0, 480.0);
+		// Close together is centered between them at normal size.
+		let close = frame_players(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 0.0), &screen, 1.0);
+		assert_eq!((close.center.x, close.center.y, close.zoom, close.split), (50.0, 0.0, 1.0, false));
+		// Further apart zooms out to fit.
+		let far = frame_players(&Vec2::new(0.0, 0.0), &Vec2::new(0.0, 832.0), &screen, 1.0);
+		assert_eq!((far.center.y, far.zoom, far.split), (416.0, 0.5, false));
+		// Too far to fit stays on the first player.
+		let split = frame_players(&Vec2::new(10.0, 20.0), &Vec2::new(2000.0, 0.0), &screen, 1.0);
+		assert_eq!((split.center.x, split.center.y, split.zoom, split.split), (10.0, 20.0, COOP_MIN_ZOOM, true));
+	}
+}
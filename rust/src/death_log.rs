@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::world_vec2::*;
+
+/// The most deaths kept (the oldest are dropped first), so the saved log doesn't grow forever.
+const MAX_DEATH_RECORDS : usize = 500;
+/// How big the heatmap's cells are (in pixels).
+pub const HEATMAP_CELL_SIZE : f32 = 16.0;
+/// The depth to draw the heatmap at (in front of the world, behind the HUD).
+const HEATMAP_DEPTH : f32 = -0.7;
+/// How many points make up each heatmap circle.
+const HEATMAP_CIRCLE_POINTS : i32 = 12;
+/// The color of cells with the fewest deaths.
+const HEATMAP_COOL_COLOR : (u8, u8, u8) = (255, 230, 0);
+/// The color of the cell with the most deaths.
+const HEATMAP_HOT_COLOR : (u8, u8, u8) = (255, 0, 0);
+
+/// Where and how the player died.
+#[derive(Debug, Clone)]
+pub struct DeathRecord {
+	/// The URL of the room it happened in.
+	pub room : String,
+	/// Where the player was (in map coordinates).
+	pub position : Vec2,
+	/// What did it (as in "enemy" or "projectile").
+	pub cause : String,
+}
+
+/// All the player's deaths, saved locally so they build up over many play sessions (for tuning difficult spots).
+pub struct DeathLog {
+	/// The deaths, oldest first.
+	records : Vec<DeathRecord>,
+	/// Whether there's been a change since the last save.
+	dirty : bool,
+}
+
+impl DeathLog {
+	/// Creates an empty instance.
+	pub fn new() -> DeathLog {
+		DeathLog {
+			records : Vec::new(),
+			dirty : false,
+		}
+	}
+
+	/// Adds a death (dropping the oldest if full).
+	pub fn record(&mut self, room : &str, position : &Vec2, cause : &str) {
+		if MAX_DEATH_RECORDS <= self.records.len() {
+			self.records.remove(0);
+		}
+		self.records.push(DeathRecord {
+			room : room.to_string(),
+			position : position.clone(),
+			cause : cause.to_string(),
+		});
+		self.dirty = true;
+	}
+
+	/// Gets all the deaths, oldest first.
+	pub fn get_records<'a>(&'a self) -> &'a Vec<DeathRecord> {
+		&self.records
+	}
+
+	/// Removes all deaths.
+	pub fn clear(&mut self) {
+		self.records.clear();
+		self.dirty = true;
+	}
+
+	/// Returns whether there's been a change since the last call (so it should be saved).
+	pub fn take_dirty(&mut self) -> bool {
+		std::mem::replace(&mut self.dirty, false)
+	}
+
+	/// Converts to text for saving (one `x,y,cause,room` per line; the room goes last as it's a URL, which might have commas).
+	pub fn to_save_string(&self) -> String {
+		let lines : Vec<String> = self.records.iter().map(|record| format!("{},{},{},{}", record.position.x, record.position.y, record.cause, record.room)).collect();
+		lines.join("\n")
+	}
+
+	/// Replaces all deaths with ones from `to_save_string()`. If anything's wrong with the text, then nothing is changed.
+	pub fn load_save_string(&mut self, text : &str) -> Result<(), String> {
+		let mut records = Vec::new();
+		for line in text.lines() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			let parts : Vec<&str> = line.splitn(4, ',').collect();
+			if 4 != parts.len() {
+				return Err(format!("Expected 'x,y,cause,room' but got {:?}", line));
+			}
+			let x = parts[0].parse::<f32>().map_err(|_| format!("Bad x in {:?}", line))?;
+			let y = parts[1].parse::<f32>().map_err(|_| format!("Bad y in {:?}", line))?;
+			records.push(DeathRecord { room : parts[3].to_string(), position : Vec2::new(x, y), cause : parts[2].to_string() });
+		}
+		let skip = records.len().saturating_sub(MAX_DEATH_RECORDS);
+		self.records = records.split_off(skip);
+		self.dirty = false;
+		Ok(())
+	}
+}
+
+/// A single heatmap cell.
+#[derive(Debug, Clone)]
+pub struct HeatCell {
+	/// The center of the cell.
+	pub center : Vec2,
+	/// How many deaths happened in it.
+	pub count : u32,
+}
+
+/// Groups the deaths in a room into grid cells. The cells are sorted by position (bottom to top, then left to right).
+pub fn build_heatmap(records : &[DeathRecord], room : &str, cell_size : f32) -> Vec<HeatCell> {
+	let mut counts : HashMap<(i32, i32), u32> = HashMap::new();
+	for record in records.iter().filter(|record| room == record.room) {
+		let cell = ((record.position.x / cell_size).floor() as i32, (record.position.y / cell_size).floor() as i32);
+		*counts.entry(cell).or_insert(0) += 1;
+	}
+	let mut cells : Vec<(i32, i32)> = counts.keys().cloned().collect();
+	cells.sort_by_key(|(x, y)| (*y, *x));
+	cells.iter().map(|cell| HeatCell {
+		center : Vec2::new((cell.0 as f32 + 0.5) * cell_size, (cell.1 as f32 + 0.5) * cell_size),
+		count : counts[cell],
+	}).collect()
+}
+
+/// A debug overlay that draws the deaths in the current room as a heatmap: one circle per cell, bigger and redder the more deaths it has.
+pub struct DeathHeatmapDisplay {
+	/// What the heatmap is drawn in.
+	display : DisplayBuffer,
+	/// Whether it's showing.
+	visible : bool,
+}
+
+impl DeathHeatmapDisplay {
+	/// Creates a hidden instance.
+	pub fn new() -> DeathHeatmapDisplay {
+		let mut display = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		display.hide();
+		DeathHeatmapDisplay {
+			display,
+			visible : false,
+		}
+	}
+
+	/// Whether it's showing.
+	pub fn is_visible(&self) -> bool {
+		self.visible
+	}
+
+	/// Shows or hides the heatmap.
+	pub fn set_visible(&mut self, visible : bool) {
+		self.visible = visible;
+		if visible {
+			self.display.show();
+		} else {
+			self.display.hide();
+		}
+	}
+
+	/// Redraws the heatmap for a room. The records are in map coordinates, so the origin is where the world's origin is in them (see `origin_rebase`).
+	pub fn redraw(&mut self, log : &DeathLog, room : &str, origin : &WorldVec2) {
+		let cells = build_heatmap(log.get_records(), room, HEATMAP_CELL_SIZE);
+		let most = cells.iter().map(|cell| cell.count).max().unwrap_or(1) as f32;
+		let mut editor = self.display.make_editor();
+		editor.clear();
+		for cell in cells {
+			let heat = cell.count as f32 / most;
+			let mix = |cool : u8, hot : u8| (cool as f32 + (hot as f32 - cool as f32) * heat) as u8;
+			let color = Color::new(
+				mix(HEATMAP_COOL_COLOR.0, HEATMAP_HOT_COLOR.0),
+				mix(HEATMAP_COOL_COLOR.1, HEATMAP_HOT_COLOR.1),
+				mix(HEATMAP_COOL_COLOR.2, HEATMAP_HOT_COLOR.2),
+				(96.0 + 128.0 * heat) as u8,
+			);
+			let radius = HEATMAP_CELL_SIZE * (0.2 + 0.3 * heat);
+			let center = WorldVec2::from_vec2(&cell.center).relative_to(origin);
+			editor.add_circle(Vec3::new(center.x, center.y, HEATMAP_DEPTH), radius, HEATMAP_CIRCLE_POINTS, &color);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_death_log {
+	use super::*;
+
+	#[test]
+	fn saving() {
+		let mut log = DeathLog::new();
+		log.record("room.json", &Vec2::new(10.0, 20.5), "enemy");
+		log.record("maps/a,b.json", &Vec2::new(-3.0, 4.0), "projectile");
+		assert!(log.take_dirty());
+		assert!(!log.take_dirty());
+
+		let mut loaded = DeathLog::new();
+		assert!(loaded.load_save_string(&log.to_save_string()).is_ok());
+		assert_eq!(loaded.to_save_string(), log.to_save_string());
+		let record = &loaded.get_records()[1];
+		assert_eq!(record.room, "maps/a,b.json");
+		assert_eq!(record.cause, "projectile");
+		assert_eq!(record.position.x, -3.0);
+		assert!(loaded.load_save_string("1,2,enemy").is_err());
+		assert!(loaded.load_save_string("x,2,enemy,room.json").is_err());
+		assert_eq!(loaded.get_records().len(), 2); // Unchanged.
+	}
+
+	#[test]
+	fn heatmap() {
+		let mut log = DeathLog::new();
+		log.record("room.json", &Vec2::new(1.0, 1.0), "enemy");
+		log.record("room.json", &Vec2::new(15.0, 2.0), "enemy");
+		log.record("room.json", &Vec2::new(17.0, 1.0), "enemy");
+		log.record("other.json", &Vec2::new(1.0, 1.0), "enemy");
+		let cells = build_heatmap(log.get_records(), "room.json", 16.0);
+		let summary : Vec<(f32, f32, u32)> = cells.iter().map(|cell| (cell.center.x, cell.center.y, cell.count)).collect();
+		assert_eq!(summary, vec![(8.0, 8.0, 2), (24.0, 8.0, 1)]);
+		assert!(build_heatmap(log.get_records(), "missing.json", 16.0).is_empty());
+	}
+}
@@ -0,0 +1,195 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::instanced_buffer::*;
+use crate::random::Random;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::bounds2::Bounds2;
+use crate::origin_rebase::ShiftOrigin;
+use crate::pool::{Pool, PoolStats};
+use crate::scheduler::Clock;
+
+/// The seed for throwing debris around. It uses its own generator so it never changes what the simulation does.
+const DEBRIS_SEED : u32 = 0x0DEB_0215;
+/// How many pieces a tile breaks into.
+const PIECES_PER_SHATTER : usize = 4;
+/// How many pieces to make up front (enough for a few tiles breaking at once).
+const DEBRIS_POOL_SIZE : usize = 32;
+/// How long a piece lasts (in seconds).
+const DEBRIS_LIFETIME : f32 = 0.8;
+/// How fast pieces are thrown up out of the tile (in pixels per second).
+const DEBRIS_POP_SPEED : f32 = 160.0;
+/// How fast pieces are thrown out sideways from the tile's center (in pixels per second, at the tile's edge).
+const DEBRIS_SPREAD_SPEED : f32 = 90.0;
+/// How far in front of the map pieces are drawn.
+const DEBRIS_DEPTH : f32 = -0.06;
+
+/// A bit of a broken tile flying away.
+#[derive(Debug, Clone)]
+pub struct DebrisPiece {
+	/// Where it is.
+	pub position : Vec2,
+	/// How fast it's moving (in pixels per second).
+	velocity : Vec2,
+	/// How long it has been around (in seconds).
+	age : f32,
+}
+
+impl DebrisPiece {
+	/// Creates a blank instance for the pool to hand out.
+	fn spare() -> DebrisPiece {
+		DebrisPiece {
+			position : Vec2::new(0.0, 0.0),
+			velocity : Vec2::new(0.0, 0.0),
+			age : 0.0,
+		}
+	}
+
+	/// How far through its life it is (from 0 to 1).
+	pub fn get_age_fraction(&self) -> f32 {
+		(self.age / DEBRIS_LIFETIME).min(1.0)
+	}
+}
+
+/// The pieces of any tiles that were broken. Purely decorative, so it isn't part of snapshots or replays.
+pub struct Debris {
+	/// The pieces in flight.
+	pieces : Pool<DebrisPiece>,
+	/// Where the pieces' speeds come from.
+	random : Random,
+	/// Which time the pieces move on.
+	clock : Clock,
+}
+
+impl Debris {
+	/// Creates an instance with nothing in flight.
+	pub fn new() -> Debris {
+		Debris {
+			pieces : Pool::new("debris", DEBRIS_POOL_SIZE, DebrisPiece::spare),
+			random : Random::new(DEBRIS_SEED),
+			clock : Clock::Simulation,
+		}
+	}
+
+	/// Sets which time the pieces move on (simulation time by default, so they hang in the air while paused).
+	pub fn set_clock(&mut self, clock : Clock) {
+		self.clock = clock;
+	}
+
+	/// Gets which time the pieces move on.
+	pub fn get_clock(&self) -> Clock {
+		self.clock
+	}
+
+	/// Removes every piece (like when the room changes).
+	pub fn clear(&mut self) {
+		self.pieces.clear();
+	}
+
+	/// Breaks the area a tile covers into pieces, thrown out from its center and up (away from gravity).
+	pub fn shatter(&mut self, bounds : &Bounds2, up : &Vec2) {
+		let center = Vec2::new((bounds.x_min() + bounds.x_max()) / 2.0, (bounds.y_min() + bounds.y_max()) / 2.0);
+		let quarter = Vec2::new((bounds.x_max() - bounds.x_min()) / 4.0, (bounds.y_max() - bounds.y_min()) / 4.0);
+		let half_size = 2.0 * quarter.x.max(quarter.y);
+		for index in 0..PIECES_PER_SHATTER {
+			// One piece per quarter of the tile, each thrown away from the center.
+			let corner = Vec2::new(if 0 == index % 2 { -1.0 } else { 1.0 }, if 2 > index { -1.0 } else { 1.0 });
+			let offset = Vec2::new(corner.x * quarter.x, corner.y * quarter.y);
+			let spread = if 0.0 < half_size { offset.scale(DEBRIS_SPREAD_SPEED / half_size) } else { Vec2::new(0.0, 0.0) };
+			let velocity = up.scale(DEBRIS_POP_SPEED * self.random.range(0.75, 1.25)) + spread;
+			let piece = self.pieces.spawn();
+			piece.position = center + offset;
+			piece.velocity = velocity;
+			piece.age = 0.0;
+		}
+	}
+
+	/// Moves every piece forward in time under gravity, removing ones that are done.
+	pub fn update(&mut self, elapsed_seconds : f32, gravity : &Vec2) {
+		self.pieces.retain(|piece| {
+			piece.velocity += gravity.scale(elapsed_seconds);
+			piece.position += piece.velocity.scale(elapsed_seconds);
+			piece.age += elapsed_seconds;
+			piece.age < DEBRIS_LIFETIME
+		});
+	}
+
+	/// Gets every piece in flight.
+	pub fn get_pieces(&self) -> &[DebrisPiece] {
+		self.pieces.as_slice()
+	}
+
+	/// Gets how full the piece pool is.
+	pub fn pool_stats(&self) -> PoolStats {
+		self.pieces.stats()
+	}
+}
+
+impl ShiftOrigin for Debris {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for piece in self.pieces.as_mut_slice() {
+			piece.position += shift;
+		}
+	}
+}
+
+/// Draws the debris.
+pub struct DebrisDisplay {
+	/// What the pieces are drawn in.
+	pieces : InstancedBuffer,
+}
+
+impl DebrisDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> DebrisDisplay {
+		let mut template = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		template.add_polygon(&vec![
+			Vec3::new(-2.0, -2.0, DEBRIS_DEPTH),
+			Vec3::new(2.0, -1.5, DEBRIS_DEPTH),
+			Vec3::new(1.5, 2.0, DEBRIS_DEPTH),
+			Vec3::new(-1.5, 1.5, DEBRIS_DEPTH),
+		], &Color::new(255, 255, 255, 255));
+		DebrisDisplay {
+			pieces : InstancedBuffer::new(DisplayBufferType::SOLIDS, template),
+		}
+	}
+
+	/// Redraws everything. Pieces fade out as they get older.
+	pub fn redraw(&mut self, debris : &Debris) {
+		let instances : Vec<Instance> = debris.get_pieces().iter().map(|piece| {
+			let alpha = (255.0 * (1.0 - piece.get_age_fraction())) as u8;
+			Instance::new(&Vec3::new(piece.position.x, piece.position.y, 0.0), &Color::new(140, 110, 84, alpha))
+		}).collect();
+		self.pieces.update(&instances);
+	}
+}
+
+#[cfg(test)]
+mod tests_debris {
+	use super::*;
+
+	#[test]
+	fn shattering() {
+		let mut debris = Debris::new();
+		let up = Vec2::new(0.0, 1.0);
+		debris.shatter(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0)), &up);
+		assert_eq!(debris.get_pieces().len(), PIECES_PER_SHATTER);
+
+		// Pieces start inside the tile and fly up and away from its middle.
+		for piece in debris.get_pieces() {
+			assert!(0.0 < piece.position.x && piece.position.x < 16.0);
+			assert!(0.0 < piece.position.y && piece.position.y < 16.0);
+			assert!(0.0 < piece.velocity.y);
+			assert_eq!(piece.velocity.x < 0.0, piece.position.x < 8.0);
+		}
+
+		// Then fall under gravity, and are gone once they've lived out their time.
+		let gravity = Vec2::new(0.0, -800.0);
+		debris.update(DEBRIS_LIFETIME / 2.0, &gravity);
+		assert_eq!(debris.get_pieces().len(), PIECES_PER_SHATTER);
+		assert!(debris.get_pieces().iter().all(|piece| piece.velocity.y < DEBRIS_POP_SPEED));
+		debris.update(DEBRIS_LIFETIME / 2.0, &gravity);
+		assert!(debris.get_pieces().is_empty());
+		assert_eq!(debris.pool_stats().peak, PIECES_PER_SHATTER);
+	}
+}
@@ -0,0 +1,170 @@
+/// A quick self-test of the link between the WASM and the JavaScript side, so integration problems show up right at startup (instead of as a mysterious failure later).
+
+use serde::Serialize;
+
+use crate::externals::*;
+use crate::color::{ColorExportable, TexturePositionAsColor};
+use crate::static_singletons::is_browser_little_endian;
+
+/// Every function the WASM calls on the JavaScript `GAME` object (dotted names are on a child object).
+const REQUIRED_BINDINGS : [&str; 44] = [
+	"createDrawBuffer", "deleteDrawBuffer", "setDisplayBuffer", "setDisplayBufferWide", "setDisplayBufferTransform", "setDisplayTransform",
+	"setRenderView", "removeRenderView", "setDisplayBufferViews", "setDisplayBufferVisibility", "setDisplayBufferAlpha", "setDisplayBufferTint",
+	"setDisplayBufferFrozen", "setDisplayBufferBlendMode", "setDisplayBufferDepthTest", "supportsInstancing", "setDisplayBufferInstances",
+	"createDrawTexture", "deleteDrawTexture", "setDrawTextureFromURL", "setDrawTextureOptions", "setDrawTextureSize", "blitImageIntoTexture", "setDrawBufferTexture",
+	"text.addTextPoint", "text.positionTextPoint", "text.addTextArea", "text.positionTextArea", "text.setText", "text.setTextVisibility",
+	"text.setTextScale", "text.setLayout", "text.setFontStack", "text.measureTextWidth",
+	"startTiledFileLoad", "startEnemyDefinitionsLoad", "startGhostLoad",
+	"audio.playSound", "audio.playMusic", "audio.stopMusic", "audio.setMusicStemVolume", "audio.setMusicVolume",
+	"storeSaveData", "loadSaveData",
+];
+
+/// The texture position round-tripped by the endianness check. Every byte is different, so any swapping shows up.
+const ENDIAN_CHECK_POSITION : (u16, u16) = (0x1234, 0xABCD);
+
+/// The outcome of one self-test check.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticCheck {
+	/// What was checked.
+	pub name : &'static str,
+	/// Whether it worked.
+	pub passed : bool,
+	/// What went wrong (empty if it passed).
+	pub details : String,
+}
+
+impl DiagnosticCheck {
+	/// Creates a check that passed if there's no problem, or failed with the problem.
+	pub fn new(name : &'static str, problem : Option<String>) -> DiagnosticCheck {
+		DiagnosticCheck {
+			name,
+			passed : problem.is_none(),
+			details : problem.unwrap_or_default(),
+		}
+	}
+
+	/// Describes the check as a JSON object.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(self).unwrap_or_else(|error| crate::fatal!("Couldn't write out a diagnostic check: {}", error))
+	}
+}
+
+/// The outcome of every self-test check.
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+	/// Each check, in the order they were run.
+	pub checks : Vec<DiagnosticCheck>,
+}
+
+impl DiagnosticReport {
+	/// Whether every check passed.
+	pub fn passed(&self) -> bool {
+		self.checks.iter().all(|check| check.passed)
+	}
+
+	/// Describes the report as a JSON object (with whether everything passed, and every check).
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(&ReportJson { passed : self.passed(), checks : &self.checks }).unwrap_or_else(|error| crate::fatal!("Couldn't write out a diagnostic report: {}", error))
+	}
+}
+
+/// How a report is written out as JSON.
+#[derive(Serialize)]
+struct ReportJson<'a> {
+	/// Whether every check passed.
+	passed : bool,
+	/// Each check.
+	checks : &'a [DiagnosticCheck],
+}
+
+/// Runs every check. Must be run after `setup()`, as it needs the browser's endianness.
+pub fn run_diagnostics() -> DiagnosticReport {
+	DiagnosticReport {
+		checks : vec![
+			DiagnosticCheck::new("bindings", check_bindings()),
+			DiagnosticCheck::new("draw buffer", check_draw_buffer()),
+			DiagnosticCheck::new("draw texture", check_draw_texture()),
+			DiagnosticCheck::new("endianness", check_endianness(is_browser_little_endian())),
+		],
+	}
+}
+
+/// Checks that every function the WASM calls exists on the JavaScript side.
+fn check_bindings() -> Option<String> {
+	let missing : Vec<&str> = REQUIRED_BINDINGS.iter().copied().filter(|name| !hasGameFunction(name)).collect();
+	if missing.is_empty() {
+		None
+	} else {
+		Some(format!("Missing: {}", missing.join(", ")))
+	}
+}
+
+/// Checks that a display buffer can be made and deleted (and only deleted once).
+fn check_draw_buffer() -> Option<String> {
+	let id = createDrawBuffer(0); // A SOLIDS buffer.
+	if 0 > id {
+		return Some(format!("Creating gave an invalid ID ({})", id));
+	}
+	if !deleteDrawBuffer(id) {
+		return Some(format!("Couldn't delete buffer {}", id));
+	}
+	if deleteDrawBuffer(id) {
+		return Some(format!("Buffer {} could be deleted twice", id));
+	}
+	None
+}
+
+/// Checks that a texture can be made and deleted.
+fn check_draw_texture() -> Option<String> {
+	let id = createDrawTexture();
+	if 0 > id {
+		return Some(format!("Creating gave an invalid ID ({})", id));
+	}
+	if !deleteDrawTexture(id) {
+		return Some(format!("Couldn't delete texture {}", id));
+	}
+	None
+}
+
+/// Checks that a texture position packed into a color (which is done in the browser's byte order) reads back as the same position.
+fn check_endianness(little_endian : bool) -> Option<String> {
+	let (x, y) = ENDIAN_CHECK_POSITION;
+	let mut bytes = Vec::new();
+	TexturePositionAsColor::new(x, y).raw_export(&mut bytes);
+	if 4 != bytes.len() {
+		return Some(format!("Packed into {} bytes instead of 4", bytes.len()));
+	}
+	let read = |first : usize, second : usize| -> u16 {
+		if little_endian { u16::from_le_bytes([bytes[first], bytes[second]]) } else { u16::from_be_bytes([bytes[first], bytes[second]]) }
+	};
+	let (read_x, read_y) = (read(0, 1), read(2, 3));
+	if (x, y) != (read_x, read_y) {
+		return Some(format!("Packed ({:#06x}, {:#06x}) but read back ({:#06x}, {:#06x}) as {} endian", x, y, read_x, read_y, if little_endian { "little" } else { "big" }));
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests_diagnostics {
+	use super::*;
+
+	#[test]
+	fn endianness() {
+		// Packing follows the browser's setting, so reading it back the same way works, and the other way doesn't.
+		let little_endian = is_browser_little_endian();
+		assert_eq!(check_endianness(little_endian), None);
+		assert!(check_endianness(!little_endian).unwrap().contains("read back"));
+	}
+
+	#[test]
+	fn report_json() {
+		let report = DiagnosticReport {
+			checks : vec![
+				DiagnosticCheck::new("bindings", None),
+				DiagnosticCheck::new("draw buffer", Some(String::from("Couldn't delete buffer 3"))),
+			],
+		};
+		assert!(!report.passed());
+		assert_eq!(report.to_json(), "{\"passed\":false,\"checks\":[{\"name\":\"bindings\",\"passed\":true,\"details\":\"\"},{\"name\":\"draw buffer\",\"passed\":false,\"details\":\"Couldn't delete buffer 3\"}]}");
+	}
+}
@@ -2,9 +2,54 @@ use crate::externals::*;
 use crate::geo::vec3::*;
 use crate::geo::vec2::*;
 use crate::geo::mat4::*;
+use crate::geo::world_vec2::*;
 use crate::color::*;
 use crate::display_texture::DisplayTexture;
+use crate::origin_rebase::ShiftOrigin;
+use crate::render_view::ViewMask;
 use std::f32::consts::PI;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+thread_local! {
+	/// The world position that's drawn at the display's origin. Everything is moved by this at the last moment, so positions reaching the GPU stay small.
+	static RENDER_ORIGIN : Cell<WorldVec2> = Cell::new(WorldVec2::zero());
+	/// The transform of every live DisplayBuffer (keyed by its ID), so they can all be redone when the render origin moves.
+	static TRANSFORMS : RefCell<HashMap<DrawBufferID, Mat4>> = RefCell::new(HashMap::new());
+}
+
+/// Gets the world position that's drawn at the display's origin.
+pub fn get_render_origin() -> WorldVec2 {
+	RENDER_ORIGIN.with(|origin| origin.get())
+}
+
+/// Moves the world position that's drawn at the display's origin, updating every live DisplayBuffer to match.
+///
+/// The camera's transform needs to be moved by the same amount (see Camera).
+pub fn set_render_origin(origin : &WorldVec2) {
+	if get_render_origin() == *origin {
+		return;
+	}
+	RENDER_ORIGIN.with(|current| current.set(*origin));
+	TRANSFORMS.with(|transforms| {
+		for (id, transform) in transforms.borrow().iter() {
+			setDisplayBufferTransform(*id, relative_transform(transform).export());
+		}
+	});
+}
+
+/// Gets a buffer's transform once it's been moved relative to the render origin.
+/// This is the boundary where world positions become f32 values, so the subtraction is done at world precision first.
+fn relative_transform(transform : &Mat4) -> Mat4 {
+	let origin = get_render_origin();
+	let mut result = transform.clone();
+	if !origin.is_zero() {
+		let translation = transform.get_translation();
+		let relative = WorldVec2::from_vec2(&Vec2::new(translation.x, translation.y)).relative_to(&origin);
+		result.set_translation(&Vec3::new(relative.x, relative.y, translation.z));
+	}
+	result
+}
 
 #[derive(PartialEq)]
 pub enum DisplayBufferType {
@@ -15,34 +60,113 @@ pub enum DisplayBufferType {
 
 //impl Eq for DisplayBufferType {}
 
-pub struct DisplayBuffer {
-	id : DrawBufferID, // The reference to the external JS buffer.
+/// How a buffer's pixels are combined with what's already been drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+	/// Covers what's under it (by its alpha). The default.
+	Alpha = 0,
+	/// Adds to what's under it (for glows).
+	Additive = 1,
+	/// Darkens what's under it by its color (for lighting).
+	Multiply = 2,
+}
+
+/// How many vertices a quad (like an image) uses.
+const QUAD_VERTICES : usize = 4;
+/// How many indices a quad (like an image) uses.
+const QUAD_INDICES : usize = 6;
+/// How many color values each vertex uses.
+const COLOR_VALUES_PER_VERTEX : usize = 4;
+
+/// An image to copy into an IMAGES type DisplayBuffer (see `DisplayBufferEditor::add_images()`).
+#[derive(Debug, Clone)]
+pub struct ImageQuad {
+	/// Where the image is in its texture.
+	pub source_position : Vec2,
+	/// The image's size.
+	pub size : Vec2,
+	/// Where its bottom left corner is drawn.
+	pub destination : Vec3,
+}
+
+/// The largest number of vertices that can be drawn with narrow (16 bit) indices. Past this, the wide index upload is used.
+/// The biggest 16 bit index is left out, as WebGL2 always treats it as the primitive restart index (which would drop whatever uses it).
+pub const MAX_NARROW_VERTICES : usize = DrawIndex::MAX as usize;
+
+/// The raw geometry behind a DisplayBuffer: what it's made of, and how to build it up.
+///
+/// Indices are always kept wide (32 bit), and only narrowed when they're uploaded (if they fit). So a buffer can hold more than 65k vertices without its index math silently wrapping around.
+/// It's kept apart from the display (which needs the JS side) so it can be tested.
+pub struct DisplayGeometry {
 	vertices : Vec<DrawCoord>, // A vector of raw vertex values.
 	colors : Vec<ColorMagnitude>, // A vector of raw vertex values.
-	indices : Vec<DrawIndex>, // A vector of raw vertex index values.
-	transform : Mat4, // The transform matrix to apply to the buffers at render time.
+	indices : Vec<WideDrawIndex>, // A vector of raw vertex index values.
 	type_ : DisplayBufferType, // What sort of drawing this wll do.
 }
 
-
-impl DisplayBuffer {
-	pub fn new(type_ : DisplayBufferType) -> DisplayBuffer {
-		DisplayBuffer {
-			id : createDrawBuffer(
-				match type_ {
-					DisplayBufferType::SOLIDS => 0,
-					DisplayBufferType::LINES => 1,
-					DisplayBufferType::IMAGES => 2,
-				}
-			),
+impl DisplayGeometry {
+	/// Creates an empty instance.
+	pub fn new(type_ : DisplayBufferType) -> DisplayGeometry {
+		DisplayGeometry {
 			vertices : Vec::new(),
 			colors : Vec::new(),
 			indices : Vec::new(),
-			transform : Mat4::new(),
-			type_ : type_,
+			type_,
 		}
 	}
 
+	/// Gets how many vertices are stored.
+	pub fn vertex_count(&self) -> usize {
+		self.vertices.len() / 3
+	}
+
+	/// Gets the stored indices.
+	pub fn get_indices<'a>(&'a self) -> &'a Vec<WideDrawIndex> {
+		&self.indices
+	}
+
+	/// Gets the indices as 16 bit values, or None if there are too many vertices for that.
+	pub fn narrow_indices(&self) -> Option<Vec<DrawIndex>> {
+		if MAX_NARROW_VERTICES < self.vertex_count() {
+			return None;
+		}
+		Some(self.indices.iter().map(|index| *index as DrawIndex).collect())
+	}
+
+	/// Sends everything to a JS-side buffer, using narrow indices when they fit and wide ones otherwise.
+	fn upload(&self, id : DrawBufferID) {
+		match self.narrow_indices() {
+			Some(indices) => setDisplayBuffer(id, &self.vertices, &self.colors, &indices),
+			None => setDisplayBufferWide(id, &self.vertices, &self.colors, &self.indices),
+		}
+	}
+
+	/// Clears out all stored geometry.
+	pub fn clear(&mut self) {
+		self.vertices.clear();
+		self.colors.clear();
+		self.indices.clear();
+	}
+
+	/// Clears out all stored geometry, and gives back the memory it used.
+	fn release(&mut self) {
+		self.vertices = Vec::new();
+		self.colors = Vec::new();
+		self.indices = Vec::new();
+	}
+
+	/// Sets aside room for some number of quads beyond what's already stored.
+	pub fn reserve_quads(&mut self, quads : usize) {
+		self.vertices.reserve(3 * QUAD_VERTICES * quads);
+		self.colors.reserve(COLOR_VALUES_PER_VERTEX * QUAD_VERTICES * quads);
+		self.indices.reserve(QUAD_INDICES * quads);
+	}
+
+	/// Gets the index the next stored vertex will have.
+	fn next_index(&self) -> WideDrawIndex {
+		self.vertex_count() as WideDrawIndex
+	}
+
 	/// Stores a vertex.
 	fn store_vertex(&mut self, position : &Vec3, color : &dyn ColorExportable) {
 		self.vertices.push(position.x);
@@ -52,41 +176,297 @@ impl DisplayBuffer {
 		color.raw_export(&mut self.colors);
 	}
 
+	/// Adds a triangle.
+	pub fn add_triangle(&mut self, points : [Vec3; 3], color : &Color) {
+		let index = self.next_index();
+
+		self.store_vertex(&points[0], color);
+		self.indices.push(index + 0);
+
+		self.store_vertex(&points[1], color);
+		self.indices.push(index + 1);
+
+		self.store_vertex(&points[2], color);
+		self.indices.push(index + 2);
+	}
+
+	/// Adds a triangle strip where every vertex has its own color.
+	/// Panics if this isn't called on a SOLIDS type, or if there isn't one color per point.
+	pub fn add_triangle_strip(&mut self, points : &Vec<Vec3>, colors : &Vec<Color>) {
+		if DisplayBufferType::SOLIDS != self.type_ {
+			crate::fatal!("Can only call add_triangle_strip() on a SOLIDS type DisplayBuffer!");
+		}
+		crate::lean_assert!(points.len() == colors.len(), "Need one color per point ({} vs {}) in add_triangle_strip()", points.len(), colors.len());
+
+		let start = self.next_index();
+		for index in 0..points.len() {
+			self.store_vertex(&points[index], &colors[index]);
+		}
+		for index in 2..(points.len() as WideDrawIndex) {
+			self.indices.push(start + index - 2);
+			self.indices.push(start + index - 1);
+			self.indices.push(start + index);
+		}
+	}
+
+	/// Adds a polygon. This will either be a line loop or a filled shape.
+	/// Only convex polygons are guaranteed to be filled everywhere.
+	pub fn add_polygon(&mut self, points : &Vec<Vec3>, color : &Color) {
+		let start = self.next_index();
+
+		// Always add all the points.
+		for point in points {
+			self.store_vertex(point, color);
+		}
+
+		let length = points.len() as WideDrawIndex;
+		match self.type_ {
+			DisplayBufferType::SOLIDS => {
+				// Creates a triangle fan centered around the first point.
+				for index in 2..length {
+					self.indices.push(start + 0);
+					self.indices.push(start + index - 1);
+					self.indices.push(start + index);
+				}
+			},
+			DisplayBufferType::LINES => {
+				// Just draws all of the lines separately.
+				for index in 0..length-1 {
+					self.indices.push(start + index);
+					self.indices.push(start + index + 1);
+				}
+				self.indices.push(start + length - 1);
+				self.indices.push(start + 0);
+			},
+			DisplayBufferType::IMAGES => crate::fatal!("DisplayBuffers of type IMAGES cannot use add_polygon()"),
+		}
+	}
+
+	/// Adds a series of lines.
+	/// Panics if this is called on a SOLID type.
+	pub fn add_lines(&mut self, points : Vec<Vec3>, color : &Color) {
+		if DisplayBufferType::LINES != self.type_ {
+			crate::fatal!("Can only call add_lines() on a LINES type DisplayBuffer!");
+		}
+
+		let start_index = self.next_index();
+		self.store_vertex(&points[0], color);
+		for index in 1..points.len() {
+			self.store_vertex(&points[index], color);
+			self.indices.push(start_index + (index as WideDrawIndex) - 1);
+			self.indices.push(start_index + (index as WideDrawIndex));
+		}
+	}
+
+	/// Adds an image (a part of the buffer's texture) with its bottom left corner at some position.
+	/// Panics if this isn't called on an IMAGES type.
+	pub fn add_image(&mut self, source_position : &Vec2, size : &Vec2, destination_position : &Vec3) {
+		if DisplayBufferType::IMAGES != self.type_ {
+			crate::fatal!("Can only call add_image() on a IMAGES type DisplayBuffer!");
+		}
+		self.store_image(source_position, size, destination_position);
+	}
+
+	/// Adds a bunch of images at once, setting aside room for all of them first.
+	/// Panics if this isn't called on an IMAGES type.
+	pub fn add_images(&mut self, images : &[ImageQuad]) {
+		if DisplayBufferType::IMAGES != self.type_ {
+			crate::fatal!("Can only call add_images() on a IMAGES type DisplayBuffer!");
+		}
+		self.reserve_quads(images.len());
+		for image in images {
+			self.store_image(&image.source_position, &image.size, &image.destination);
+		}
+	}
+
+	/// Adds a copy of another geometry (of the same type), moved by an offset.
+	/// For SOLIDS and LINES the copy's colors are multiplied by a color. IMAGES store texture positions in place of colors, so they can't be tinted this way (the color is ignored).
+	pub fn add_copy(&mut self, template : &DisplayGeometry, offset : &Vec3, color : &Color) {
+		crate::lean_assert!(template.type_ == self.type_, "Can only add_copy() geometry of the same type");
+		let start = self.next_index();
+		for position in template.vertices.chunks(3) {
+			self.vertices.push(position[0] + offset.x);
+			self.vertices.push(position[1] + offset.y);
+			self.vertices.push(position[2] + offset.z);
+		}
+		if DisplayBufferType::IMAGES == self.type_ {
+			self.colors.extend_from_slice(&template.colors);
+		} else {
+			let factors = [color.red, color.green, color.blue, color.alpha];
+			for (index, value) in template.colors.iter().enumerate() {
+				let factor = factors[index % COLOR_VALUES_PER_VERTEX] as u16;
+				self.colors.push(((*value as u16) * factor / 255) as ColorMagnitude);
+			}
+		}
+		self.indices.extend(template.indices.iter().map(|index| start + index));
+	}
+
+	/// Stores an image's vertices and indices (without checking the type).
+	fn store_image(&mut self, source_position : &Vec2, size : &Vec2, destination_position : &Vec3) {
+		let start_index = self.next_index();
+		let mut texture_position = TexturePositionAsColor::new(
+			source_position.x as u16,
+			source_position.y as u16,
+		);
+		let mut position = destination_position.clone();
+		self.store_vertex(&position, &texture_position);
+		position.x         += size.x;
+		texture_position.x += size.x as u16;
+		self.store_vertex(&position, &texture_position);
+		position.y         += size.y;
+		texture_position.y += size.y as u16;
+		self.store_vertex(&position, &texture_position);
+		position.x         -= size.x;
+		texture_position.x -= size.x as u16;
+		self.store_vertex(&position, &texture_position);
+
+		self.indices.push(start_index + 0);
+		self.indices.push(start_index + 1);
+		self.indices.push(start_index + 2);
+
+		self.indices.push(start_index + 0);
+		self.indices.push(start_index + 2);
+		self.indices.push(start_index + 3);
+	}
+}
+
+pub struct DisplayBuffer {
+	id : DrawBufferID, // The reference to the external JS buffer.
+	geometry : DisplayGeometry, // What's drawn.
+	frozen : bool, // Whether the contents can't change (and so only live on the JS side).
+}
+
+
+impl DisplayBuffer {
+	pub fn new(type_ : DisplayBufferType) -> DisplayBuffer {
+		let buffer = DisplayBuffer {
+			id : createDrawBuffer(
+				match type_ {
+					DisplayBufferType::SOLIDS => 0,
+					DisplayBufferType::LINES => 1,
+					DisplayBufferType::IMAGES => 2,
+				}
+			),
+			geometry : DisplayGeometry::new(type_),
+			frozen : false,
+		};
+		TRANSFORMS.with(|transforms| transforms.borrow_mut().insert(buffer.id, Mat4::new()));
+		if !get_render_origin().is_zero() {
+			setDisplayBufferTransform(buffer.id, relative_transform(&Mat4::new()).export());
+		}
+		buffer
+	}
+
+	/// Creates an instance with room for some number of quads (like images) already set aside, so building it up doesn't keep reallocating.
+	pub fn with_capacity(type_ : DisplayBufferType, quads : usize) -> DisplayBuffer {
+		let mut buffer = DisplayBuffer::new(type_);
+		buffer.geometry.reserve_quads(quads);
+		buffer
+	}
+
 	/// Makes sure the buffer is shown.
 	pub fn show(&mut self) {
-		assert!(setDisplayBufferVisibility(self.id, true), "Couldn't set visibiltiy of display buffer {}", self.id);
+		crate::lean_assert!(setDisplayBufferVisibility(self.id, true), "Couldn't set visibiltiy of display buffer {}", self.id);
 	}
 
 	/// Makes sure the buffer is hidden.
 	pub fn hide(&mut self) {
-		assert!(setDisplayBufferVisibility(self.id, false), "Couldn't set visibiltiy of display buffer {}", self.id);
+		crate::lean_assert!(setDisplayBufferVisibility(self.id, false), "Couldn't set visibiltiy of display buffer {}", self.id);
+	}
+
+	/// Sets how opaque the whole buffer is drawn (from 0 for invisible to 1 for normal).
+	pub fn set_alpha(&mut self, alpha : f32) {
+		crate::lean_assert!(setDisplayBufferAlpha(self.id, alpha), "Couldn't set alpha of display buffer {}", self.id);
+	}
+
+	/// Blends every pixel in the buffer toward a color (ignoring its alpha) by some amount (from 0 for normal to 1 for solid color).
+	pub fn set_tint(&mut self, color : &Color, amount : f32) {
+		assert!(
+			setDisplayBufferTint(self.id, color.red as f32 / 255.0, color.green as f32 / 255.0, color.blue as f32 / 255.0, amount),
+			"Couldn't set tint of display buffer {}", self.id
+		);
+	}
+
+	/// Sets how the buffer's pixels are combined with what's already been drawn.
+	///
+	/// Buffers that don't use `BlendMode::Alpha` are drawn after all the ones that do (still in creation order amongst themselves), so whatever they blend onto is already there. They also don't hide anything behind them.
+	pub fn set_blend_mode(&mut self, mode : BlendMode) {
+		crate::lean_assert!(setDisplayBufferBlendMode(self.id, mode as i32), "Couldn't set blend mode of display buffer {}", self.id);
+	}
+
+	/// Sets whether things drawn closer can hide this buffer (on by default).
+	/// Turning it off draws the buffer over everything (after all the buffers that have it on).
+	pub fn set_depth_test(&mut self, enabled : bool) {
+		crate::lean_assert!(setDisplayBufferDepthTest(self.id, enabled), "Couldn't set depth testing of display buffer {}", self.id);
+	}
+
+	/// Sets which render views the buffer is drawn in (see `render_view`). It starts out in all of them.
+	pub fn set_views(&mut self, views : ViewMask) {
+		crate::lean_assert!(setDisplayBufferViews(self.id, views), "Couldn't set views of display buffer {}", self.id);
 	}
 
 	/// Sets the transform for this buffer.
+	/// It's in world space, and gets moved relative to the render origin before it's sent along.
 	pub fn set_transform(&mut self, transform : &Mat4) {
-		self.transform = transform.clone();
-		setDisplayBufferTransform(self.id, self.transform.export());
+		TRANSFORMS.with(|transforms| transforms.borrow_mut().insert(self.id, transform.clone()));
+		setDisplayBufferTransform(self.id, relative_transform(transform).export());
 	}
 
 	/// Gets a copy of the transform buffer.
 	pub fn get_transform(&self) -> Mat4 {
-		return self.transform.clone();
+		TRANSFORMS.with(|transforms| transforms.borrow().get(&self.id).cloned().unwrap_or_else(Mat4::new))
 	}
 
 	/// Sets the associated texture.
 	///
 	/// Since this will happen infrequently, it's done immediately rather than being put off until the next update() call.
 	pub fn set_texture(&mut self, texture : &DisplayTexture) { // TODO: Could store this as an Rc<RefCell<DisplayTexture>> so the texture would be guaranteed to be kept until all associated buffers are deleted?
-		if DisplayBufferType::IMAGES != self.type_ {
-			panic!("Can only call set_texture() on a IMAGES type DisplayBuffer!");
+		if DisplayBufferType::IMAGES != self.geometry.type_ {
+			crate::fatal!("Can only call set_texture() on a IMAGES type DisplayBuffer!");
 		}
 
 		let texture_id = texture.get_id();
-		assert!(setDrawBufferTexture(self.id, texture_id), "Couldn't set display buffer {} to use texture {}", self.id, texture_id);
+		crate::lean_assert!(setDrawBufferTexture(self.id, texture_id), "Couldn't set display buffer {} to use texture {}", self.id, texture_id);
+	}
+
+	/// Marks the contents as never changing again. They're re-uploaded for static drawing, and the copies kept here are dropped (to save memory).
+	///
+	/// Things like the transform, visibility, and alpha can still change. Editing the contents requires a `thaw()` first.
+	pub fn freeze(&mut self) {
+		if self.frozen {
+			return;
+		}
+		self.frozen = true;
+		crate::lean_assert!(setDisplayBufferFrozen(self.id, true), "Couldn't freeze display buffer {}", self.id);
+		self.geometry.upload(self.id);
+		self.geometry.release();
+	}
+
+	/// Allows the contents to change again. Since the old contents weren't kept, this starts out empty (so whatever was drawn needs to be rebuilt).
+	pub fn thaw(&mut self) {
+		if !self.frozen {
+			return;
+		}
+		self.frozen = false;
+		crate::lean_assert!(setDisplayBufferFrozen(self.id, false), "Couldn't thaw display buffer {}", self.id);
+		self.geometry.upload(self.id);
+	}
+
+	/// Whether the contents are frozen.
+	pub fn is_frozen(&self) -> bool {
+		self.frozen
+	}
+
+	/// Gets the ID the JS side knows it by.
+	pub fn get_id(&self) -> DrawBufferID {
+		self.id
 	}
 
 	/// Creates a editor object to batch changing the buffer.
+	///
+	/// Panics if the buffer is frozen.
 	pub fn make_editor<'a>(&'a mut self) -> DisplayBufferEditor<'a> {
+		crate::lean_assert!(!self.frozen, "Can't edit frozen display buffer {} (thaw it first)", self.id);
 		DisplayBufferEditor {
 			target: self,
 			dirty: false,
@@ -94,12 +474,23 @@ impl DisplayBuffer {
 	}
 }
 
+impl ShiftOrigin for DisplayBuffer {
+	/// Moves the whole buffer along with the world (without touching its contents).
+	fn shift_origin(&mut self, shift : &Vec2) {
+		let mut transform = self.get_transform();
+		let translation = transform.get_translation();
+		transform.set_translation(&Vec3::new(translation.x + shift.x, translation.y + shift.y, translation.z));
+		self.set_transform(&transform);
+	}
+}
+
 impl Drop for DisplayBuffer {
 	/// Remove the buffer.
 	/// The TypeScript side of things will re-use it later.
 	/// Using TypeScript for that to keep the DisplayBuffer::new() calls simple.
 	fn drop(&mut self) {
-		assert!(deleteDrawBuffer(self.id), "Couldn't delete draw buffer {}", self.id);
+		TRANSFORMS.with(|transforms| transforms.borrow_mut().remove(&self.id));
+		crate::lean_assert!(deleteDrawBuffer(self.id), "Couldn't delete draw buffer {}", self.id);
 	}
 }
 
@@ -116,60 +507,32 @@ pub struct DisplayBufferEditor<'a> {
 impl<'a> DisplayBufferEditor<'a> {
 	/// Clears out all stored geometry.
 	pub fn clear(&mut self) {
-		self.target.vertices.clear();
-		self.target.colors.clear();
-		self.target.indices.clear();
+		self.target.geometry.clear();
 		self.dirty = true;
 	}
 
+	/// Sets aside room for some number of quads (like images), so adding a lot of them doesn't keep reallocating.
+	pub fn reserve_quads(&mut self, quads : usize) {
+		self.target.geometry.reserve_quads(quads);
+	}
+
 	/// Adds a triangle.
 	pub fn add_triangle(&mut self, points : [Vec3; 3], color : &Color) {
-		let index : u16 = (self.target.vertices.len() / 3) as u16;
-
-		self.target.store_vertex(&points[0], color);
-		self.target.indices.push(index + 0);
-
-		self.target.store_vertex(&points[1], color);
-		self.target.indices.push(index + 1);
-
-		self.target.store_vertex(&points[2], color);
-		self.target.indices.push(index + 2);
+		self.target.geometry.add_triangle(points, color);
+		self.dirty = true;
+	}
 
+	/// Adds a triangle strip where every vertex has its own color.
+	/// Panics if this isn't called on a SOLIDS type, or if there isn't one color per point.
+	pub fn add_triangle_strip(&mut self, points : &Vec<Vec3>, colors : &Vec<Color>) {
+		self.target.geometry.add_triangle_strip(points, colors);
 		self.dirty = true;
 	}
 
 	/// Adds a polygon. This will either be a line loop or a filled shape.
 	/// Only convex polygons are guaranteed to be filled everywhere.
 	pub fn add_polygon(&mut self, points : &Vec<Vec3>, color : &Color) {
-		let start : u16 = (self.target.vertices.len() / 3) as u16;
-
-		// Always add all the points.
-		for point in points {
-			self.target.store_vertex(point, color);
-		}
-
-		let length = points.len() as u16;
-		match self.target.type_ {
-			DisplayBufferType::SOLIDS => {
-				// Creates a triangle fan centered around the first point.
-				for index in 2..length {
-					self.target.indices.push(start + 0);
-					self.target.indices.push(start + index - 1);
-					self.target.indices.push(start + index);
-				}
-			},
-			DisplayBufferType::LINES => {
-				// Just draws all of the lines separately.
-				for index in 0..length-1 {
-					self.target.indices.push(start + index);
-					self.target.indices.push(start + index + 1);
-				}
-				self.target.indices.push(start + length - 1);
-				self.target.indices.push(start + 0);
-			},
-			DisplayBufferType::IMAGES => panic!("DisplayBuffers of type IMAGES cannot use add_polygon()"),
-		}
-
+		self.target.geometry.add_polygon(points, color);
 		self.dirty = true;
 	}
 
@@ -193,50 +556,27 @@ impl<'a> DisplayBufferEditor<'a> {
 	/// Adds a series of lines.
 	/// Panics if this is called on a SOLID type.
 	pub fn add_lines(&mut self, points : Vec<Vec3>, color : &Color) {
-		if DisplayBufferType::LINES != self.target.type_ {
-			panic!("Can only call add_lines() on a LINES type DisplayBuffer!");
-		}
-
-		let start_index : u16 = (self.target.vertices.len() / 3) as u16;
-		self.target.store_vertex(&points[0], color);
-		for index in 1..points.len() {
-			self.target.store_vertex(&points[index], color);
-			self.target.indices.push(start_index + (index as u16) - 1);
-			self.target.indices.push(start_index + (index as u16));
-		}
+		self.target.geometry.add_lines(points, color);
 		self.dirty = true;
 	}
 
+	/// Adds an image (a part of the buffer's texture) with its bottom left corner at some position.
+	/// Panics if this isn't called on an IMAGES type.
 	pub fn add_image(&mut self, source_position : &Vec2, size : &Vec2, destination_position : &Vec3) {
-		if DisplayBufferType::IMAGES != self.target.type_ {
-			panic!("Can only call add_image() on a IMAGES type DisplayBuffer!");
-		}
-
-		let start_index : u16 = (self.target.vertices.len() / 3) as u16;
-		let mut texture_position = TexturePositionAsColor::new(
-			source_position.x as u16,
-			source_position.y as u16,
-		);
-		let mut position = destination_position.clone();
-		self.target.store_vertex(&position, &texture_position);
-		position.x         += size.x;
-		texture_position.x += size.x as u16;
-		self.target.store_vertex(&position, &texture_position);
-		position.y         += size.y;
-		texture_position.y += size.y as u16;
-		self.target.store_vertex(&position, &texture_position);
-		position.x         -= size.x;
-		texture_position.x -= size.x as u16;
-		self.target.store_vertex(&position, &texture_position);
-
-		self.target.indices.push(start_index + 0);
-		self.target.indices.push(start_index + 1);
-		self.target.indices.push(start_index + 2);
+		self.target.geometry.add_image(source_position, size, destination_position);
+		self.dirty = true;
+	}
 
-		self.target.indices.push(start_index + 0);
-		self.target.indices.push(start_index + 2);
-		self.target.indices.push(start_index + 3);
+	/// Adds a bunch of images at once, setting aside room for all of them first.
+	/// Panics if this isn't called on an IMAGES type.
+	pub fn add_images(&mut self, images : &[ImageQuad]) {
+		self.target.geometry.add_images(images);
+		self.dirty = true;
+	}
 
+	/// Adds a copy of some geometry (of the same type), moved by an offset and multiplied by a color (see `DisplayGeometry::add_copy()`).
+	pub fn add_copy(&mut self, template : &DisplayGeometry, offset : &Vec3, color : &Color) {
+		self.target.geometry.add_copy(template, offset, color);
 		self.dirty = true;
 	}
 }
@@ -245,7 +585,127 @@ impl Drop for DisplayBufferEditor<'_> {
 	/// Attempts to update the associated DisplayBuffer's displayed geometry.
 	fn drop(&mut self) {
 		if self.dirty {
-			setDisplayBuffer(self.target.id, &self.target.vertices, &self.target.colors, &self.target.indices);
+			self.target.geometry.upload(self.target.id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_display_buffer {
+	use super::*;
+
+	#[test]
+	fn oversized_layer() {
+		// A 160x128 tile layer is 20480 quads, which is 81920 vertices: too many for 16 bit indices.
+		let mut geometry = DisplayGeometry::new(DisplayBufferType::IMAGES);
+		let mut tiles = Vec::new();
+		for y in 0..128 {
+			for x in 0..160 {
+				tiles.push(ImageQuad {
+					source_position : Vec2::new(0.0, 0.0),
+					size : Vec2::new(16.0, 16.0),
+					destination : Vec3::new((x * 16) as f32, (y * 16) as f32, 0.0),
+				});
+			}
+		}
+		geometry.add_images(&tiles[..100]);
+		assert_eq!(geometry.narrow_indices().unwrap()[6..12], [4, 5, 6, 4, 6, 7]);
+		geometry.add_images(&tiles[100..]);
+		assert_eq!(geometry.vertex_count(), 81920);
+		assert!(geometry.narrow_indices().is_none());
+		// The last quad's indices point past the 16 bit limit, rather than wrapping back around.
+		let indices = geometry.get_indices();
+		assert_eq!(indices[indices.len() - 6..], [81916, 81917, 81918, 81916, 81918, 81919]);
+
+		geometry.clear();
+		geometry.add_images(&tiles[..16383]);
+		let red = Color::new(255, 0, 0, 255);
+		let triangle = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+		geometry.add_triangle(triangle.clone(), &red); // Exactly the most that fit.
+		assert_eq!(geometry.vertex_count(), 65535);
+		assert_eq!(geometry.narrow_indices().unwrap().last(), Some(&65534));
+		// The next vertex would need 65535, which WebGL2 always reads as a primitive restart.
+		geometry.add_triangle(triangle, &red);
+		assert!(geometry.narrow_indices().is_none());
+	}
+}
+
+#[cfg(test)]
+mod tests_display_freeze {
+	use super::*;
+
+	#[test]
+	fn released_geometry() {
+		// Freezing hands the contents off to the JS side and drops the copy kept here, memory included.
+		let mut geometry = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		geometry.reserve_quads(8);
+		geometry.add_triangle([Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)], &Color::new(255, 0, 0, 255));
+		assert_eq!(geometry.vertex_count(), 3);
+		geometry.release();
+		assert_eq!(geometry.vertex_count(), 0);
+		assert_eq!(geometry.vertices.capacity(), 0);
+		assert_eq!(geometry.colors.capacity(), 0);
+		assert_eq!(geometry.indices.capacity(), 0);
+		// Thawing starts out empty, and building back up works the same as a new buffer.
+		geometry.add_triangle([Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)], &Color::new(255, 0, 0, 255));
+		assert_eq!(geometry.get_indices(), &vec![0, 1, 2]);
+	}
+}
+
+#[cfg(test)]
+mod tests_display_bulk {
+	use super::*;
+
+	#[test]
+	fn bulk_images() {
+		let images : Vec<ImageQuad> = (0..5).map(|index| ImageQuad {
+			source_position : Vec2::new(16.0 * index as f32, 0.0),
+			size : Vec2::new(16.0, 16.0),
+			destination : Vec3::new(0.0, 16.0 * index as f32, 0.0),
+		}).collect();
+		// Adding them all at once is the same as adding them one at a time.
+		let mut one_at_a_time = DisplayGeometry::new(DisplayBufferType::IMAGES);
+		for image in images.iter() {
+			one_at_a_time.add_image(&image.source_position, &image.size, &image.destination);
+		}
+		let mut bulk = DisplayGeometry::new(DisplayBufferType::IMAGES);
+		bulk.add_images(&images);
+		assert_eq!(bulk.vertices, one_at_a_time.vertices);
+		assert_eq!(bulk.colors, one_at_a_time.colors);
+		assert_eq!(bulk.indices, one_at_a_time.indices);
+		// And it only needed one allocation per vector to do it.
+		assert_eq!(bulk.vertices.capacity(), bulk.vertices.len());
+		assert_eq!(bulk.colors.capacity(), bulk.colors.len());
+		assert_eq!(bulk.indices.capacity(), bulk.indices.len());
+	}
+
+	#[test]
+	fn reserved_quads() {
+		let mut geometry = DisplayGeometry::new(DisplayBufferType::IMAGES);
+		geometry.reserve_quads(10);
+		let vertices = geometry.vertices.as_ptr();
+		for index in 0..10 {
+			geometry.add_image(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0), &Vec3::new(16.0 * index as f32, 0.0, 0.0));
+		}
+		assert_eq!(geometry.vertex_count(), 40);
+		assert_eq!(geometry.indices.len(), 60);
+		assert_eq!(vertices, geometry.vertices.as_ptr()); // Never had to move.
+	}
+}
+
+#[cfg(test)]
+mod tests_blend_mode {
+	use super::*;
+
+	#[test]
+	fn matches_display() {
+		// The modes are passed to the JS side by number, so they have to line up with its enum.
+		let source = include_str!("../../typescript/display.ts");
+		let start = source.find("export enum BlendMode {").unwrap();
+		let end = start + source[start..].find('}').unwrap();
+		let declaration = &source[start..end];
+		for (name, mode) in [("ALPHA", BlendMode::Alpha), ("ADDITIVE", BlendMode::Additive), ("MULTIPLY", BlendMode::Multiply)].iter() {
+			assert!(declaration.contains(&format!("{} = {},", name, *mode as i32)), "BlendMode::{:?} doesn't match display.ts", mode);
 		}
 	}
 }
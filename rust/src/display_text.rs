@@ -1,6 +1,8 @@
 use crate::externals::*;
 use crate::geo::vec2::*;
 use crate::color::*;
+use crate::text_layout::*;
+use crate::render_view::ViewRect;
 
 #[derive(Debug, Copy, Clone)]
 pub enum TextAlignment {
@@ -39,7 +41,8 @@ impl CssLength {
 		match self {
 			CssLength::CharWidth(magnitude) => format!("{}em", magnitude),
 			CssLength::CharHeight(magnitude) => format!("{}ex", 2.0 * magnitude),
-			CssLength::Pixels(magnitude) => format!("{}px", magnitude),
+			// HUD pixels, which grow with the UI scale (see `DisplayText::set_layout_for_all()`).
+			CssLength::Pixels(magnitude) => format!("calc({}px * var(--ui-scale, 1))", magnitude),
 		}
 	}
 }
@@ -118,6 +121,31 @@ impl DisplayText {
 		setTextValues(self.id, &self.color.to_css(), self.alignment.to_css(), &self.contents);
 	}
 
+	/// Sets how big all text is drawn (1 is normal). Text sized in `CssLength::CharWidth` or `CssLength::CharHeight` grows with it.
+	pub fn set_scale_for_all(scale : f32) {
+		setDisplayTextScale(scale);
+	}
+
+	/// Fits all text to the part of the screen the game is drawn in (fractions of the screen, from the bottom left), with some UI scale. Text point positions and pixel sizes are multiplied by the scale.
+	pub fn set_layout_for_all(rect : &ViewRect, ui_scale : f32) {
+		setDisplayTextLayout(rect.left, rect.bottom, rect.width, rect.height, ui_scale);
+	}
+
+	/// Switches all text to a locale (a BCP 47 tag like "ja" or "zh-TW"), using its font stack and line breaking rules.
+	pub fn set_locale_for_all(locale : &str) {
+		setDisplayTextFontStack(locale, font_stack_for_locale(locale));
+	}
+
+	/// Measures how wide some (plain) text is drawn with the current font stack and scale (in pixels).
+	pub fn measure_width(text : &str) -> f32 {
+		measureDisplayTextWidth(text)
+	}
+
+	/// Splits plain text into lines that fit in a given width (in pixels) when drawn with the current font stack. See `text_layout::wrap_text()`.
+	pub fn wrap(text : &str, max_width : f32) -> Vec<String> {
+		wrap_text(text, max_width, measureDisplayTextWidth)
+	}
+
 	/// Makes sure the text is shown.
 	pub fn show(&mut self) {
 		setDisplayTextVisibility(self.id, true);
@@ -1,4 +1,62 @@
 use crate::externals::*;
+use crate::color::Color;
+use crate::asset_manifest::asset_url;
+
+/// The image property with a color to make transparent (as a hex color, like "#ff00ff").
+pub const COLOR_KEY_PROPERTY : &str = "colorKey";
+/// The image property with how far off (per channel, from 0 to 255) a pixel can be from the color key and still be made transparent.
+pub const COLOR_KEY_TOLERANCE_PROPERTY : &str = "colorKeyTolerance";
+/// The image property with whether to premultiply the color by the alpha ("true" or "false").
+pub const PREMULTIPLY_ALPHA_PROPERTY : &str = "premultiplyAlpha";
+
+/// Changes to make to an image as it's loaded into a texture.
+#[derive(Clone)]
+pub struct TextureOptions {
+	/// A background color to make fully transparent (as lots of free tilesets use magenta for this).
+	pub color_key : Option<Color>,
+	/// How far off (per channel) a pixel can be from the color key and still be made transparent.
+	pub color_key_tolerance : u8,
+	/// Whether to premultiply the colors by the alpha (which blends partly transparent edges better).
+	pub premultiply_alpha : bool,
+}
+
+impl TextureOptions {
+	/// Creates an instance that leaves images as is.
+	pub fn new() -> TextureOptions {
+		TextureOptions {
+			color_key : None,
+			color_key_tolerance : 0,
+			premultiply_alpha : false,
+		}
+	}
+
+	/// Reads the options from properties (as in a Tiled tileset's). Anything missing is left as is.
+	pub fn from_properties<'a, F : Fn(&str) -> Option<&'a str>>(get : F) -> Result<TextureOptions, String> {
+		let mut options = TextureOptions::new();
+		if let Some(text) = get(COLOR_KEY_PROPERTY) {
+			options.color_key = Some(Color::from_hex(text)?);
+		}
+		if let Some(text) = get(COLOR_KEY_TOLERANCE_PROPERTY) {
+			options.color_key_tolerance = match text.trim().parse::<u8>() {
+				Ok(tolerance) => tolerance,
+				Err(_) => { return Err(format!("Invalid {} {:?} (should be from 0 to 255)", COLOR_KEY_TOLERANCE_PROPERTY, text)); },
+			};
+		}
+		if let Some(text) = get(PREMULTIPLY_ALPHA_PROPERTY) {
+			options.premultiply_alpha = match text.trim() {
+				"true" => true,
+				"false" => false,
+				_ => { return Err(format!("Invalid {} {:?} (should be true or false)", PREMULTIPLY_ALPHA_PROPERTY, text)); },
+			};
+		}
+		Ok(options)
+	}
+
+	/// Whether these actually change anything.
+	pub fn is_default(&self) -> bool {
+		self.color_key.is_none() && !self.premultiply_alpha
+	}
+}
 
 pub struct DisplayTexture {
 	id : DrawTextureID, // The reference to the external JS buffer.
@@ -12,9 +70,24 @@ impl DisplayTexture {
 		}
 	}
 
-	/// Loads in the texture information from the given URL.
+	/// Loads in the texture information from the given URL (or asset name, see `asset_manifest`).
 	pub fn load_from_url(&mut self, url : &str) {
-		assert!(setDrawTextureFromURL(self.id, url), "Couldn't start loading url {:?} into draw texture {}", url, self.id)
+		crate::lean_assert!(setDrawTextureFromURL(self.id, &asset_url(url)), "Couldn't start loading url {:?} into draw texture {}", url, self.id)
+	}
+
+	/// Makes this a fully transparent texture of a given size (for copying other images into).
+	pub fn set_blank(&mut self, width : u32, height : u32) {
+		crate::lean_assert!(setDrawTextureSize(self.id, width, height), "Couldn't resize draw texture {}", self.id);
+	}
+
+	/// Loads in the texture information from the given URL, changing it as it loads.
+	pub fn load_with_options(&mut self, url : &str, options : &TextureOptions) {
+		let key = options.color_key.clone().unwrap_or_else(|| Color::new(0, 0, 0, 0));
+		assert!(
+			setDrawTextureOptions(self.id, options.color_key.is_some(), key.red, key.green, key.blue, options.color_key_tolerance, options.premultiply_alpha),
+			"Couldn't set the options on draw texture {}", self.id
+		);
+		self.load_from_url(url);
 	}
 
 	/// Gets the raw low-level ID. Don't use this unless you're calling from `DisplayBuffer.set_texture()`.
@@ -27,6 +100,26 @@ impl DisplayTexture {
 impl Drop for DisplayTexture {
 	/// Remove the texture.
 	fn drop(&mut self) {
-		assert!(deleteDrawTexture(self.id), "Couldn't delete draw texture {}", self.id);
+		crate::lean_assert!(deleteDrawTexture(self.id), "Couldn't delete draw texture {}", self.id);
+	}
+}
+
+#[cfg(test)]
+mod tests_display_texture {
+	use super::*;
+
+	#[test]
+	fn options_from_properties() {
+		let properties = vec![("colorKey", "#ff00ff"), ("colorKeyTolerance", "8"), ("premultiplyAlpha", "true")];
+		let get = |name : &str| properties.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+		let options = TextureOptions::from_properties(get).unwrap();
+		assert_eq!(options.color_key.as_ref().map(|color| (color.red, color.green, color.blue)), Some((255, 0, 255)));
+		assert_eq!(options.color_key_tolerance, 8);
+		assert!(options.premultiply_alpha);
+		assert!(!options.is_default());
+
+		assert!(TextureOptions::from_properties(|_| None).unwrap().is_default());
+		assert!(TextureOptions::from_properties(|name| if "colorKeyTolerance" == name { Some("300") } else { None }).is_err());
+		assert!(TextureOptions::from_properties(|name| if "premultiplyAlpha" == name { Some("yes") } else { None }).is_err());
 	}
 }
@@ -0,0 +1,355 @@
+use std::f32::consts::PI;
+
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::transform2d::Transform2D;
+use crate::geo::consts::EPSILON;
+use crate::nav_grid::NavGrid;
+use crate::pickups::Pickups;
+use crate::interpolation::InterpolatedPosition;
+use crate::origin_rebase::ShiftOrigin;
+
+/// Where the drone hovers relative to the player when following.
+const FOLLOW_OFFSET : Vec2 = Vec2 { x : -12.0, y : 20.0 };
+/// The drone's top speed (in pixels per second).
+const MAX_SPEED : f32 = 160.0;
+/// How quickly the drone can change its velocity (in pixels per second squared).
+const MAX_ACCELERATION : f32 = 600.0;
+/// How far from its final target the drone starts slowing down.
+const ARRIVE_DISTANCE : f32 = 24.0;
+/// How close the drone needs to get to a path point before moving on to the next one.
+const WAYPOINT_REACH : f32 = 6.0;
+/// How often the drone re-plans its path (in seconds).
+const REPATH_SECONDS : f32 = 0.5;
+/// How far the target can move before the drone re-plans early.
+const REPATH_DISTANCE : f32 = 16.0;
+/// How far from the player the drone will go to fetch something.
+const FETCH_RANGE : f32 = 160.0;
+/// How close the drone needs to get to a pickup to grab it.
+const GRAB_DISTANCE : f32 = 6.0;
+/// How close the drone needs to get to the player to hand something over.
+const DELIVER_DISTANCE : f32 = 12.0;
+/// How close a secret needs to be for the drone to point it out.
+const SECRET_SENSE_DISTANCE : f32 = 64.0;
+
+/// The drone's body size (in pixels).
+const BODY_RADIUS : f32 = 4.0;
+/// How far the drone bobs up and down while hovering.
+const BOB_DISTANCE : f32 = 1.5;
+/// How fast the drone bobs (in cycles per second).
+const BOB_RATE : f32 = 1.5;
+/// How fast the drone's light blinks (in blinks per second).
+const BLINK_RATE : f32 = 2.0;
+/// The depth to draw the drone at (just in front of the player).
+const DRONE_DEPTH : f32 = -0.05;
+/// The depth to draw secret highlights at.
+const HIGHLIGHT_DEPTH : f32 = -0.85;
+
+/// What the drone is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DroneTask {
+	/// Hovering near the player.
+	Follow,
+	/// Going to get a pickup (by index).
+	Fetch(usize),
+	/// Bringing a pickup (by index) back to the player.
+	Return(usize),
+}
+
+/// Something that happened during an update that the owner needs to act on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DroneAction {
+	/// Nothing of note.
+	Nothing,
+	/// A pickup (by index) was grabbed and is now being carried.
+	PickedUp(usize),
+	/// A pickup (by index) was handed to the player.
+	Delivered(usize),
+}
+
+/// The drone's decision making and movement.
+///
+/// Kept apart from the display so it can be tested.
+pub struct DroneBrain {
+	/// Where the drone is.
+	position : Vec2,
+	/// How fast the drone is moving.
+	velocity : Vec2,
+	/// What the drone is doing.
+	task : DroneTask,
+	/// The points still to go through to reach the target.
+	path : Vec<Vec2>,
+	/// The target the current path was planned for.
+	path_target : Vec2,
+	/// How long until the path is re-planned.
+	repath_timer : f32,
+}
+
+impl DroneBrain {
+	/// Creates an instance following the player from the given position.
+	pub fn new(position : &Vec2) -> DroneBrain {
+		DroneBrain {
+			position : position.clone(),
+			velocity : Vec2::zero(),
+			task : DroneTask::Follow,
+			path : Vec::new(),
+			path_target : position.clone(),
+			repath_timer : 0.0,
+		}
+	}
+
+	/// Where the drone is.
+	pub fn get_position(&self) -> Vec2 {
+		self.position
+	}
+
+	/// What the drone is doing.
+	pub fn get_task(&self) -> DroneTask {
+		self.task
+	}
+
+	/// Gets the pickup being carried (if any).
+	pub fn get_carried(&self) -> Option<usize> {
+		match self.task {
+			DroneTask::Return(index) => Some(index),
+			_ => None,
+		}
+	}
+
+	/// Finds which of some points (like secrets) are close enough to the drone to point out.
+	pub fn find_nearby(&self, points : &Vec<Vec2>) -> Vec<usize> {
+		let mut found = Vec::new();
+		for (index, point) in points.iter().enumerate() {
+			if (point - self.position).length() <= SECRET_SENSE_DISTANCE {
+				found.push(index);
+			}
+		}
+		found
+	}
+
+	/// Moves the drone and works through its current task.
+	/// The command input (whether it was just pressed) sends the drone to fetch the nearest pickup (when it isn't already busy).
+	pub fn update(&mut self, elapsed_seconds : f32, player_position : &Vec2, command : bool, nav : &NavGrid, pickups : &Pickups) -> DroneAction {
+		if command && DroneTask::Follow == self.task {
+			if let Some(index) = pickups.find_nearest(player_position, FETCH_RANGE) {
+				self.task = DroneTask::Fetch(index);
+				self.repath_timer = 0.0;
+			}
+		}
+
+		// Work out where to go, and whether the current task is done.
+		let mut action = DroneAction::Nothing;
+		let target = match self.task {
+			DroneTask::Follow => player_position + FOLLOW_OFFSET,
+			DroneTask::Fetch(index) => {
+				let item = &pickups.get_items()[index];
+				if item.collected {
+					self.task = DroneTask::Follow;
+					player_position + FOLLOW_OFFSET
+				} else if (item.position - self.position).length() <= GRAB_DISTANCE {
+					self.task = DroneTask::Return(index);
+					action = DroneAction::PickedUp(index);
+					player_position.clone()
+				} else {
+					item.position
+				}
+			},
+			DroneTask::Return(index) => {
+				if (player_position - self.position).length() <= DELIVER_DISTANCE {
+					self.task = DroneTask::Follow;
+					action = DroneAction::Delivered(index);
+					player_position + FOLLOW_OFFSET
+				} else {
+					player_position.clone()
+				}
+			},
+		};
+
+		// Re-plan every so often (or if the target moved a lot), so the drone goes around walls.
+		self.repath_timer -= elapsed_seconds;
+		if 0.0 >= self.repath_timer || REPATH_DISTANCE < (target - self.path_target).length() {
+			self.path = nav.find_path(&self.position, &target).unwrap_or_else(|| vec![target]);
+			self.path_target = target;
+			self.repath_timer = REPATH_SECONDS;
+		}
+		while 1 < self.path.len() && (self.path[0] - self.position).length() <= WAYPOINT_REACH {
+			self.path.remove(0);
+		}
+		let waypoint = self.path.first().cloned().unwrap_or(target);
+
+		// Steer toward the waypoint, slowing down when getting close to the end.
+		let offset = waypoint - self.position;
+		let distance = offset.length();
+		let mut desired = Vec2::zero();
+		if EPSILON < distance {
+			let mut speed = MAX_SPEED;
+			if self.path.len() <= 1 {
+				speed *= (distance / ARRIVE_DISTANCE).min(1.0);
+			}
+			desired = offset.set_length(speed);
+		}
+		let mut steering = desired - self.velocity;
+		let max_change = MAX_ACCELERATION * elapsed_seconds;
+		if max_change < steering.length() {
+			steering = steering.set_length(max_change);
+		}
+		self.velocity += steering;
+		self.position += self.velocity * elapsed_seconds;
+		action
+	}
+}
+
+impl ShiftOrigin for DroneBrain {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+		self.path.shift_origin(shift);
+		self.path_target += shift;
+	}
+}
+
+/// A small flying companion that follows the player, points out secrets, and fetches pickups.
+pub struct Drone {
+	/// What the drone is doing.
+	brain : DroneBrain,
+	/// How long the drone has existed (for animating).
+	time : f32,
+	/// Where the drone was for the last two updates (so it can be drawn smoothly between them).
+	display_position : InterpolatedPosition,
+	/// The drone itself.
+	body : DisplayBuffer,
+	/// The rings around nearby secrets.
+	highlights : DisplayBuffer,
+}
+
+impl Drone {
+	/// Creates a drone at some position.
+	pub fn new(position : &Vec2) -> Drone {
+		Drone {
+			brain : DroneBrain::new(position),
+			time : 0.0,
+			display_position : InterpolatedPosition::new(position),
+			body : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+			highlights : DisplayBuffer::new(DisplayBufferType::LINES),
+		}
+	}
+
+	/// Gets the size of the drone's body.
+	pub fn get_radius(&self) -> f32 {
+		BODY_RADIUS
+	}
+
+	/// Gets the decision making part of the drone.
+	pub fn get_brain<'a>(&'a self) -> &'a DroneBrain {
+		&self.brain
+	}
+
+	/// Updates the drone's behavior. See DroneBrain::update().
+	pub fn update(&mut self, elapsed_seconds : f32, player_position : &Vec2, command : bool, nav : &NavGrid, pickups : &Pickups) -> DroneAction {
+		self.time += elapsed_seconds;
+		let action = self.brain.update(elapsed_seconds, player_position, command, nav, pickups);
+		self.display_position.push(&self.brain.get_position());
+		action
+	}
+
+	/// Moves the drone's body to where it should be drawn (bobbing up and down), given how far between the last two updates to draw things (from 0 to 1).
+	pub fn sync_display(&mut self, interpolation : f32) {
+		let position = self.display_position.get(interpolation);
+		let bob = (self.time * BOB_RATE * 2.0 * PI).sin() * BOB_DISTANCE;
+		self.body.set_transform(&Transform2D::new(&Vec2::new(position.x, position.y + bob)).to_mat4());
+	}
+
+	/// Redraws the drone (with its hover animation) and rings around any of the secrets it's close to.
+	pub fn redraw(&mut self, secrets : &Vec<Vec2>) {
+		{
+			let blink_on = 0.5 > (self.time * BLINK_RATE).fract();
+			let mut editor = self.body.make_editor();
+			editor.clear();
+			editor.add_circle(Vec3::new(0.0, 0.0, DRONE_DEPTH), BODY_RADIUS, 8, &Color::new(160, 160, 180, 255));
+			editor.add_polygon(&vec![
+				Vec3::new(-BODY_RADIUS * 1.5, BODY_RADIUS, DRONE_DEPTH),
+				Vec3::new( BODY_RADIUS * 1.5, BODY_RADIUS, DRONE_DEPTH),
+				Vec3::new( BODY_RADIUS * 1.5, BODY_RADIUS + 1.0, DRONE_DEPTH),
+				Vec3::new(-BODY_RADIUS * 1.5, BODY_RADIUS + 1.0, DRONE_DEPTH),
+			], &Color::new(80, 80, 90, 255));
+			let light = if blink_on { Color::new(0, 255, 160, 255) } else { Color::new(0, 90, 60, 255) };
+			editor.add_circle(Vec3::new(0.0, 0.0, DRONE_DEPTH - 0.001), BODY_RADIUS / 2.0, 6, &light);
+		}
+		let mut editor = self.highlights.make_editor();
+		editor.clear();
+		let pulse = 10.0 + 2.0 * (self.time * 4.0).sin();
+		for index in self.brain.find_nearby(secrets) {
+			let center = &secrets[index];
+			let mut points = Vec::with_capacity(13);
+			for step in 0..13 {
+				let angle = (step as f32) * 2.0 * PI / 12.0;
+				points.push(Vec3::new(center.x + angle.cos() * pulse, center.y + angle.sin() * pulse, HIGHLIGHT_DEPTH));
+			}
+			editor.add_lines(points, &Color::new(0, 255, 160, 255));
+		}
+	}
+}
+
+impl ShiftOrigin for Drone {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.brain.shift_origin(shift);
+		self.display_position.shift_origin(shift);
+	}
+}
+
+#[cfg(test)]
+mod tests_drone_brain {
+	use super::*;
+	use crate::geo::bounds2::Bounds2;
+
+	/// Runs the drone for a while, returning every action that wasn't Nothing.
+	fn run(brain : &mut DroneBrain, steps : usize, player : &Vec2, command : bool, nav : &NavGrid, pickups : &Pickups) -> Vec<DroneAction> {
+		let mut actions = Vec::new();
+		for _ in 0..steps {
+			let action = brain.update(1.0 / 60.0, player, command, nav, pickups);
+			if DroneAction::Nothing != action {
+				actions.push(action);
+			}
+		}
+		actions
+	}
+
+	#[test]
+	fn follows_around_walls() {
+		let mut nav = NavGrid::new(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(160.0, 160.0)), 8.0);
+		nav.block_rect(&Bounds2::from_points(&Vec2::new(72.0, 0.0), &Vec2::new(88.0, 120.0)));
+		let pickups = Pickups::new();
+		let mut brain = DroneBrain::new(&Vec2::new(20.0, 20.0));
+		let player = Vec2::new(140.0, 20.0);
+		let mut highest = 0.0f32;
+		for _ in 0..600 {
+			brain.update(1.0 / 60.0, &player, false, &nav, &pickups);
+			highest = highest.max(brain.get_position().y);
+		}
+		// Had to go over the wall to get there.
+		assert!(120.0 < highest);
+		assert!(((player + FOLLOW_OFFSET) - brain.get_position()).length() < 2.0);
+	}
+
+	#[test]
+	fn fetches() {
+		let nav = NavGrid::new(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(200.0, 200.0)), 8.0);
+		let mut pickups = Pickups::new();
+		let index = pickups.add("scrap", &Vec2::new(100.0, 20.0));
+		let player = Vec2::new(20.0, 20.0);
+		let mut brain = DroneBrain::new(&(player + FOLLOW_OFFSET));
+		assert!(run(&mut brain, 1, &player, true, &nav, &pickups).is_empty());
+		assert_eq!(brain.get_task(), DroneTask::Fetch(index));
+		let actions = run(&mut brain, 600, &player, false, &nav, &pickups);
+		assert_eq!(actions, vec![DroneAction::PickedUp(index), DroneAction::Delivered(index)]);
+		assert_eq!(brain.get_task(), DroneTask::Follow);
+	}
+
+	#[test]
+	fn senses_secrets() {
+		let brain = DroneBrain::new(&Vec2::new(0.0, 0.0));
+		let secrets = vec![Vec2::new(10.0, 0.0), Vec2::new(1000.0, 0.0)];
+		assert_eq!(brain.find_nearby(&secrets), vec![0]);
+	}
+}
@@ -0,0 +1,849 @@
+use std::collections::HashMap;
+
+use crate::color::*;
+use crate::palette::*;
+use crate::display_buffer::*;
+use crate::display_texture::DisplayTexture;
+use crate::texture_atlas::*;
+use crate::health::Health;
+use crate::knockback::Knockback;
+use crate::projectiles::ProjectileConfig;
+use crate::tiled::TiledFile;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::consts::EPSILON;
+use crate::geo::collision_system::CollisionSystem;
+use crate::spatial_hash::{SpatialHash, DynamicKind};
+use crate::origin_rebase::ShiftOrigin;
+use crate::update_lod::*;
+use crate::geo::bounds2::Bounds2;
+
+/// The Tiled point type that marks where an enemy spawns. The point's "definition" property names the enemy definition to use.
+const ENEMY_POINT_TYPE : &str = "enemy";
+/// The Tiled point property holding the name of the enemy definition.
+const DEFINITION_PROPERTY : &str = "definition";
+/// How close the player needs to be for enemies with the chase behavior to go after them.
+const CHASE_RANGE : f32 = 128.0;
+/// How close the player needs to be for enemies with the shoot behavior to fire at them.
+const SHOOT_RANGE : f32 = 160.0;
+/// How far away enemies look for a player to go after (the furthest any behavior cares about).
+const AGGRO_RANGE : f32 = SHOOT_RANGE;
+/// How much a surface's normal needs to point up to count as ground (rather than a wall).
+const GROUND_DOT : f32 = 0.7;
+/// How long an enemy waits between hurting the player by touching them (in seconds).
+const CONTACT_DAMAGE_COOLDOWN : f32 = 1.0;
+/// The depth to draw enemies at (just behind the player).
+const ENEMY_DEPTH : f32 = 0.05;
+/// How many points make up an enemy's circle (when it has no sprite).
+const ENEMY_CIRCLE_POINTS : i32 = 10;
+
+/// Something an enemy can do. Definitions can combine several of these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnemyBehavior {
+	/// Walks back and forth, turning around at walls.
+	Patrol,
+	/// Heads toward the player when they're close.
+	Chase,
+	/// Ignores gravity (and so can move in any direction).
+	Fly,
+	/// Fires projectiles at the player when they're close.
+	Shoot,
+}
+
+impl EnemyBehavior {
+	/// Gets a behavior from its name in an enemy definition file.
+	pub fn from_name(name : &str) -> Option<EnemyBehavior> {
+		match name {
+			"patrol" => Some(EnemyBehavior::Patrol),
+			"chase" => Some(EnemyBehavior::Chase),
+			"fly" => Some(EnemyBehavior::Fly),
+			"shoot" => Some(EnemyBehavior::Shoot),
+			_ => None,
+		}
+	}
+}
+
+/// A part of an image to draw an enemy with.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpriteRegion {
+	/// The image's URL.
+	pub url : String,
+	/// The bottom left corner of the region in the image (in pixels).
+	pub position : Vec2,
+	/// The size of the region (in pixels).
+	pub size : Vec2,
+}
+
+/// Something an enemy might drop when defeated.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnemyDrop {
+	/// The kind of pickup dropped.
+	pub kind : String,
+	/// How likely it is to be dropped (from 0 to 1).
+	pub chance : f32,
+}
+
+/// Describes a kind of enemy. Every enemy spawned from a definition starts out the same.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnemyDefinition {
+	/// The name that spawn points use to refer to this.
+	pub name : String,
+	/// How fast it moves (in pixels per second).
+	pub speed : f32,
+	/// How much health it starts with.
+	pub health : f32,
+	/// How big it is (in pixels).
+	pub radius : f32,
+	/// How much damage it does when touching the player.
+	pub damage : f32,
+	/// How fast it gets pushed away when hit (in pixels per second).
+	pub knockback_speed : f32,
+	/// How long it stops doing anything after being hit (in seconds).
+	pub hit_stun : f32,
+	/// What it does.
+	pub behaviors : Vec<EnemyBehavior>,
+	/// The name of the projectile it fires (see `ProjectileConfig::from_name()`).
+	pub projectile : String,
+	/// How long it waits between shots (in seconds).
+	pub fire_interval : f32,
+	/// How far out of view it has to be to be updated less often (or not at all).
+	#[cfg_attr(feature = "serialize", serde(default = "default_enemy_lod"))]
+	pub lod : LodSettings,
+	/// What to draw it with (if None, it's drawn as a circle).
+	pub sprite : Option<SpriteRegion>,
+	/// What it might drop when defeated.
+	pub drops : Vec<EnemyDrop>,
+}
+
+/// Gets the update LOD that enemies use unless their definition says otherwise: slowed down a little out of view, and frozen once they're far enough away that nothing they do could matter yet.
+fn default_enemy_lod() -> LodSettings {
+	LodSettings::new(Some(256.0), Some(1024.0))
+}
+
+/// Parses a number from a definition property.
+fn parse_number(name : &str, value : &str) -> Result<f32, String> {
+	value.trim().parse::<f32>().map_err(|_| format!("Couldn't parse {} {:?}", name, value))
+}
+
+impl EnemyDefinition {
+	/// Creates a definition with the default stats.
+	pub fn new(name : &str) -> EnemyDefinition {
+		EnemyDefinition {
+			name : name.to_string(),
+			speed : 30.0,
+			health : 1.0,
+			radius : 6.0,
+			damage : 10.0,
+			knockback_speed : 160.0,
+			hit_stun : 0.4,
+			behaviors : Vec::new(),
+			projectile : String::from("bolt"),
+			fire_interval : 2.0,
+			lod : default_enemy_lod(),
+			sprite : None,
+			drops : Vec::new(),
+		}
+	}
+
+	/// Whether this has a given behavior.
+	pub fn has_behavior(&self, behavior : EnemyBehavior) -> bool {
+		self.behaviors.contains(&behavior)
+	}
+
+	/// Sets a value from the definition file. All values come in as strings (lists are comma separated):
+	///
+	/// * "speed", "health", "radius", "damage", "knockback" (speed), "hitStun" (in seconds), and "fireInterval" (in seconds): Numbers.
+	/// * "projectile": The name of the projectile it shoots (see `ProjectileConfig::from_name()`).
+	/// * "behaviors": A list of behavior names (see `EnemyBehavior::from_name()`).
+	/// * "sprite": The URL of the image to draw it with.
+	/// * "spriteRegion": The x, y, width, and height of the part of the image to use.
+	/// * "drops": A list of "kind:chance" pairs (the chance defaults to 1 if left off).
+	/// * "lod": How far out of view it's updated less often and frozen (see `LodSettings::from_property()`).
+	pub fn set_property(&mut self, name : &str, value : &str) -> Result<(), String> {
+		match name {
+			"speed" => { self.speed = parse_number(name, value)?; },
+			"health" => { self.health = parse_number(name, value)?; },
+			"radius" => { self.radius = parse_number(name, value)?; },
+			"damage" => { self.damage = parse_number(name, value)?; },
+			"knockback" => { self.knockback_speed = parse_number(name, value)?; },
+			"hitStun" => { self.hit_stun = parse_number(name, value)?; },
+			"fireInterval" => { self.fire_interval = parse_number(name, value)?; },
+			"projectile" => {
+				if ProjectileConfig::from_name(value).is_none() {
+					return Err(format!("Unknown projectile {:?}", value));
+				}
+				self.projectile = value.to_string();
+			},
+			"behaviors" => {
+				let mut behaviors = Vec::new();
+				for piece in value.split(',').map(str::trim).filter(|piece| !piece.is_empty()) {
+					behaviors.push(EnemyBehavior::from_name(piece).ok_or_else(|| format!("Unknown behavior {:?}", piece))?);
+				}
+				self.behaviors = behaviors;
+			},
+			"sprite" => {
+				let sprite = self.sprite.get_or_insert_with(|| SpriteRegion {
+					url : String::new(),
+					position : Vec2::zero(),
+					size : Vec2::new(16.0, 16.0),
+				});
+				sprite.url = value.to_string();
+			},
+			"spriteRegion" => {
+				let mut values = Vec::new();
+				for piece in value.split(',') {
+					values.push(parse_number(name, piece)?);
+				}
+				if 4 != values.len() {
+					return Err(format!("Expected 4 values for {} but got {:?}", name, value));
+				}
+				let sprite = self.sprite.get_or_insert_with(|| SpriteRegion {
+					url : String::new(),
+					position : Vec2::zero(),
+					size : Vec2::zero(),
+				});
+				sprite.position = Vec2::new(values[0], values[1]);
+				sprite.size = Vec2::new(values[2], values[3]);
+			},
+			"drops" => {
+				let mut drops = Vec::new();
+				for piece in value.split(',').map(str::trim).filter(|piece| !piece.is_empty()) {
+					let mut parts = piece.splitn(2, ':');
+					let kind = parts.next().unwrap().trim().to_string();
+					let chance = match parts.next() {
+						Some(text) => parse_number("drop chance", text)?,
+						None => 1.0,
+					};
+					drops.push(EnemyDrop { kind, chance });
+				}
+				self.drops = drops;
+			},
+			"lod" => { self.lod = LodSettings::from_property(value)?; },
+			_ => { return Err(format!("Unknown property {:?}", name)); },
+		}
+		Ok(())
+	}
+}
+
+/// All the known enemy definitions (by name).
+pub struct EnemyDefinitions {
+	/// The definitions.
+	definitions : HashMap<String, EnemyDefinition>,
+	/// Whether the definition file has finished loading.
+	loaded : bool,
+}
+
+impl EnemyDefinitions {
+	/// Creates an instance with no definitions.
+	pub fn new() -> EnemyDefinitions {
+		EnemyDefinitions {
+			definitions : HashMap::new(),
+			loaded : false,
+		}
+	}
+
+	/// Sets a value on a definition, creating it if it doesn't exist yet (see `EnemyDefinition::set_property()`).
+	pub fn set_property(&mut self, definition : &str, name : &str, value : &str) -> Result<(), String> {
+		self.definitions.entry(definition.to_string())
+			.or_insert_with(|| EnemyDefinition::new(definition))
+			.set_property(name, value)
+	}
+
+	/// Gets a definition by name.
+	pub fn get(&self, name : &str) -> Option<&EnemyDefinition> {
+		self.definitions.get(name)
+	}
+
+	/// Marks the definitions as completely loaded.
+	pub fn finish_loading(&mut self) {
+		self.loaded = true;
+	}
+
+	/// Whether the definitions are completely loaded.
+	pub fn is_loaded(&self) -> bool {
+		self.loaded
+	}
+}
+
+/// A single enemy in the level.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Enemy {
+	/// What kind of enemy this is.
+	pub definition : EnemyDefinition,
+	/// Where its center is.
+	pub position : Vec2,
+	/// How fast it's falling (flying enemies ignore this).
+	pub fall_velocity : Vec2,
+	/// Which way it's walking along the ground (-1 or 1).
+	pub facing : f32,
+	/// How much more damage it can take.
+	pub health : Health,
+	/// How long until it can hurt the player by touching them again (in seconds).
+	pub contact_cooldown : f32,
+	/// Getting pushed back (and stunned) after being hit.
+	pub knockback : Knockback,
+	/// How long until it can shoot again (in seconds).
+	pub fire_cooldown : f32,
+	/// How often it's being updated (based on how far out of view it is).
+	#[cfg_attr(feature = "serialize", serde(default = "UpdateLod::new"))]
+	pub lod : UpdateLod,
+}
+
+impl Enemy {
+	/// Creates an enemy at full health.
+	pub fn new(definition : &EnemyDefinition, position : &Vec2) -> Enemy {
+		Enemy {
+			definition : definition.clone(),
+			position : position.clone(),
+			fall_velocity : Vec2::zero(),
+			facing : 1.0,
+			health : Health::new(definition.health),
+			contact_cooldown : 0.0,
+			knockback : Knockback::new(),
+			fire_cooldown : definition.fire_interval,
+			lod : UpdateLod::new(),
+		}
+	}
+
+	/// Moves the enemy according to its behaviors, going after the target (a player) if there is one. Returns the shot it wants to fire (if any).
+	pub fn update(&mut self, elapsed_seconds : f32, target : Option<&Vec2>, gravity : &Vec2, collision : &CollisionSystem) -> Option<EnemyShot> {
+		self.contact_cooldown = (self.contact_cooldown - elapsed_seconds).max(0.0);
+		self.fire_cooldown = (self.fire_cooldown - elapsed_seconds).max(0.0);
+		let up = if EPSILON < gravity.length() { gravity.norm().scale(-1.0) } else { Vec2::new(0.0, 1.0) };
+		let across = Vec2::new(up.y, -up.x);
+		let flying = self.definition.has_behavior(EnemyBehavior::Fly);
+		let to_player = target.map(|target| target - self.position).unwrap_or_else(Vec2::zero);
+		let chasing = self.definition.has_behavior(EnemyBehavior::Chase) && target.is_some() && to_player.length() < CHASE_RANGE;
+
+		let mut velocity = Vec2::zero();
+		if self.knockback.is_stunned() {
+			// Does nothing while stunned (other than falling).
+		} else if chasing && flying {
+			if EPSILON < to_player.length() {
+				velocity = to_player.norm() * self.definition.speed;
+			}
+		} else {
+			if chasing {
+				let side = to_player.dot(&across);
+				if EPSILON < side.abs() {
+					self.facing = side.signum();
+				}
+			}
+			if chasing || self.definition.has_behavior(EnemyBehavior::Patrol) {
+				velocity = across * (self.facing * self.definition.speed);
+			}
+		}
+		if !flying {
+			self.fall_velocity += gravity * elapsed_seconds;
+			velocity += self.fall_velocity;
+		}
+
+		let movement = velocity * elapsed_seconds + self.knockback.update(elapsed_seconds);
+		let mut final_position = self.position + movement;
+		for deflection in collision.collide_circle(&self.position, self.definition.radius, &movement) {
+			final_position = deflection.final_position;
+			for normal in &deflection.normals {
+				let dot = normal.dot(&up);
+				if GROUND_DOT <= dot {
+					self.fall_velocity = Vec2::zero();
+				} else if -GROUND_DOT < dot && 0.0 > normal.dot(&across) * self.facing {
+					self.facing = -self.facing; // Hit a wall, so turn around.
+				}
+			}
+		}
+		self.position = final_position;
+
+		let target = match target {
+			Some(target) => target,
+			None => { return None; },
+		};
+		if !self.definition.has_behavior(EnemyBehavior::Shoot) || self.knockback.is_stunned() || 0.0 < self.fire_cooldown || SHOOT_RANGE <= (target - self.position).length() {
+			return None;
+		}
+		self.fire_cooldown = self.definition.fire_interval;
+		Some(EnemyShot {
+			projectile : self.definition.projectile.clone(),
+			position : self.position,
+			target : *target,
+		})
+	}
+}
+
+impl ShiftOrigin for Enemy {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+	}
+}
+
+/// A projectile that an enemy wants to fire.
+#[derive(Debug, Clone)]
+pub struct EnemyShot {
+	/// The name of the projectile.
+	pub projectile : String,
+	/// Where to fire it from.
+	pub position : Vec2,
+	/// What it's being aimed at.
+	pub target : Vec2,
+}
+
+/// An enemy touching the player.
+#[derive(Debug, Clone)]
+pub struct ContactHit {
+	/// How much damage it does.
+	pub damage : f32,
+	/// Where the enemy is (so the player can be knocked away from it).
+	pub source : Vec2,
+}
+
+/// What happened when the player stomped on enemies.
+#[derive(Debug, Clone)]
+pub struct StompResult {
+	/// Whether any enemy was stomped on.
+	pub hit : bool,
+	/// The enemies that were defeated by it.
+	pub defeated : Vec<Enemy>,
+}
+
+/// Where an enemy should spawn once its definition is available.
+#[derive(Debug, Clone)]
+pub struct EnemySpawn {
+	/// The name of the definition to use.
+	pub definition : String,
+	/// Where to spawn it.
+	pub position : Vec2,
+}
+
+impl ShiftOrigin for EnemySpawn {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+	}
+}
+
+/// Spawns and updates all the enemies in the level.
+pub struct Enemies {
+	/// Spawns that are still waiting on their definitions to load.
+	pending : Vec<EnemySpawn>,
+	/// The living enemies.
+	enemies : Vec<Enemy>,
+}
+
+impl Enemies {
+	/// Creates an instance with no enemies.
+	pub fn new() -> Enemies {
+		Enemies {
+			pending : Vec::new(),
+			enemies : Vec::new(),
+		}
+	}
+
+	/// Removes all enemies and queues up the spawns in a Tiled file.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.pending.clear();
+		self.enemies.clear();
+		for point in file.get_points() {
+			if ENEMY_POINT_TYPE == point.r#type {
+				let definition = point.get_property(DEFINITION_PROPERTY).unwrap_or(&point.name);
+				self.add_spawn(definition, &point.position);
+			}
+		}
+	}
+
+	/// Queues up an enemy to spawn once its definition is available.
+	pub fn add_spawn(&mut self, definition : &str, position : &Vec2) {
+		self.pending.push(EnemySpawn {
+			definition : definition.to_string(),
+			position : position.clone(),
+		});
+	}
+
+	/// Spawns every queued enemy whose definition is available.
+	/// Once the definitions are completely loaded, any spawns with unknown definitions are dropped and their names returned.
+	pub fn spawn_pending(&mut self, definitions : &EnemyDefinitions) -> Vec<String> {
+		let mut unknown = Vec::new();
+		let mut waiting = Vec::new();
+		for spawn in self.pending.drain(..) {
+			match definitions.get(&spawn.definition) {
+				Some(definition) if definitions.is_loaded() => { self.enemies.push(Enemy::new(definition, &spawn.position)); },
+				_ if definitions.is_loaded() => { unknown.push(spawn.definition); },
+				_ => { waiting.push(spawn); },
+			}
+		}
+		self.pending = waiting;
+		unknown
+	}
+
+	/// Gets all living enemies.
+	pub fn get_enemies<'a>(&'a self) -> &'a Vec<Enemy> {
+		&self.enemies
+	}
+
+	/// Replaces all living enemies (as in when restoring a snapshot). Spawns still waiting on definitions are left alone.
+	pub fn restore(&mut self, enemies : &[Enemy]) {
+		self.enemies = enemies.to_vec();
+	}
+
+	/// Moves all enemies, each going after whichever player (in the dynamic entity hash) is closest to it. Returns the shots they want to fire.
+	/// Enemies far outside of the view are updated less often (or not at all), as their definition's LOD settings say.
+	pub fn update(&mut self, elapsed_seconds : f32, dynamic : &SpatialHash, gravity : &Vec2, collision : &CollisionSystem, view : &Bounds2) -> Vec<EnemyShot> {
+		let mut shots = Vec::new();
+		for enemy in &mut self.enemies {
+			let elapsed_seconds = match enemy.lod.step(&enemy.definition.lod, distance_outside(view, &enemy.position), elapsed_seconds) {
+				Some(seconds) => seconds,
+				None => { continue; },
+			};
+			let target = dynamic.nearest(&enemy.position, 1, AGGRO_RANGE, Some(DynamicKind::Player)).first().map(|entry| entry.position);
+			shots.extend(enemy.update(elapsed_seconds, target.as_ref(), gravity, collision));
+		}
+		shots
+	}
+
+	/// Finds the enemies touching a circle (the player's body) that can hurt it, restarting their cooldowns.
+	pub fn take_contact_hits(&mut self, position : &Vec2, radius : f32) -> Vec<ContactHit> {
+		let mut hits = Vec::new();
+		for enemy in &mut self.enemies {
+			if 0.0 < enemy.contact_cooldown {
+				continue;
+			}
+			if (enemy.position - position).length() < enemy.definition.radius + radius {
+				hits.push(ContactHit {
+					damage : enemy.definition.damage,
+					source : enemy.position,
+				});
+				enemy.contact_cooldown = CONTACT_DAMAGE_COOLDOWN;
+			}
+		}
+		hits
+	}
+
+	/// Damages every enemy that a falling circle (the player's feet) lands on top of.
+	/// Stomped enemies can't hurt the player by touching them until their contact cooldown is over.
+	pub fn stomp(&mut self, position : &Vec2, radius : f32, velocity : &Vec2, up : &Vec2, damage : f32) -> StompResult {
+		let mut result = StompResult { hit : false, defeated : Vec::new() };
+		if 0.0 <= velocity.dot(up) {
+			return result;
+		}
+		let mut index = 0;
+		while index < self.enemies.len() {
+			let enemy = &mut self.enemies[index];
+			let offset = position - enemy.position;
+			if 0.0 < enemy.contact_cooldown || offset.length() >= enemy.definition.radius + radius || 0.0 >= offset.dot(up) {
+				index += 1;
+				continue;
+			}
+			result.hit = true;
+			enemy.contact_cooldown = CONTACT_DAMAGE_COOLDOWN;
+			match self.damage(index, damage, position) {
+				Some(defeated) => { result.defeated.push(defeated); },
+				None => { index += 1; },
+			}
+		}
+		result
+	}
+
+	/// Damages an enemy, knocking it away from where the damage came from. If that defeats it, it's removed and returned.
+	pub fn damage(&mut self, index : usize, amount : f32, source : &Vec2) -> Option<Enemy> {
+		let enemy = &mut self.enemies[index];
+		if enemy.health.damage(amount) {
+			return Some(self.enemies.remove(index));
+		}
+		let fallback = Vec2::new(enemy.facing, 0.0);
+		enemy.knockback.apply(source, &enemy.position, &fallback, enemy.definition.knockback_speed, enemy.definition.hit_stun);
+		None
+	}
+}
+
+impl ShiftOrigin for Enemies {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.pending.shift_origin(shift);
+		self.enemies.shift_origin(shift);
+	}
+}
+
+/// Draws the enemies.
+pub struct EnemyDisplay {
+	/// What enemies without sprites are drawn in.
+	shapes : DisplayBuffer,
+	/// Where the sprites get packed, so enemies using different images can all be drawn together.
+	atlas : TextureAtlas,
+	/// What enemies with atlas-packed sprites are drawn in.
+	atlas_sprites : DisplayBuffer,
+	/// What enemies with atlas-packed sprites that were just hit are drawn in (tinted).
+	atlas_flash : DisplayBuffer,
+	/// What enemies with sprites that didn't fit in the atlas are drawn in (by image URL). Enemies that were just hit are drawn in the second (tinted) buffer.
+	sprites : HashMap<String, (DisplayTexture, DisplayBuffer, DisplayBuffer)>,
+	/// Whether enemies that were just hit are drawn flashing.
+	flashing : bool,
+	/// The color enemies are blended toward (when not flashing), and how much.
+	tint : (Color, f32),
+	/// The color of enemies without sprites.
+	shape_color : Color,
+}
+
+impl EnemyDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> EnemyDisplay {
+		let atlas = TextureAtlas::new(ATLAS_SIZE);
+		let mut atlas_sprites = DisplayBuffer::new(DisplayBufferType::IMAGES);
+		atlas_sprites.set_texture(atlas.get_texture());
+		let mut atlas_flash = DisplayBuffer::new(DisplayBufferType::IMAGES);
+		atlas_flash.set_texture(atlas.get_texture());
+		atlas_flash.set_tint(&Color::new(255, 255, 255, 255), 1.0);
+		EnemyDisplay {
+			shapes : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+			atlas,
+			atlas_sprites,
+			atlas_flash,
+			sprites : HashMap::new(),
+			flashing : true,
+			tint : (Color::new(255, 255, 255, 255), 0.0),
+			shape_color : Palette::new(PaletteKind::Standard).get(PaletteRole::Danger),
+		}
+	}
+
+	/// Sets the color of enemies without sprites (on the next redraw).
+	pub fn set_shape_color(&mut self, color : &Color) {
+		self.shape_color = color.clone();
+	}
+
+	/// Sets whether enemies that were just hit are drawn flashing.
+	pub fn set_flashing(&mut self, flashing : bool) {
+		self.flashing = flashing;
+	}
+
+	/// Sets the color enemies are blended toward (when not flashing), and how much (from 0 to 1).
+	pub fn set_tint(&mut self, color : &Color, amount : f32) {
+		self.tint = (color.clone(), amount);
+		self.shapes.set_tint(color, amount);
+		self.atlas_sprites.set_tint(color, amount);
+		for (_, buffer, _) in self.sprites.values_mut() {
+			buffer.set_tint(color, amount);
+		}
+	}
+
+	/// Redraws all the enemies.
+	pub fn redraw(&mut self, enemies : &Enemies) {
+		let mut shapes = self.shapes.make_editor();
+		shapes.clear();
+		let mut atlas_sprites = self.atlas_sprites.make_editor();
+		atlas_sprites.clear();
+		let mut atlas_flash = self.atlas_flash.make_editor();
+		atlas_flash.clear();
+		for (_, buffer, flash_buffer) in self.sprites.values_mut() {
+			buffer.make_editor().clear();
+			flash_buffer.make_editor().clear();
+		}
+		for enemy in enemies.get_enemies() {
+			let flash = if self.flashing { enemy.knockback.get_flash() } else { 0.0 };
+			match &enemy.definition.sprite {
+				Some(sprite) if !sprite.url.is_empty() => {
+					let position = Vec3::new(enemy.position.x - sprite.size.x / 2.0, enemy.position.y - sprite.size.y / 2.0, ENEMY_DEPTH);
+					if let Some(region) = self.atlas.add_region(&sprite.url, &sprite.position, &sprite.size) {
+						let target = if 0.5 < flash { &mut atlas_flash } else { &mut atlas_sprites };
+						target.add_image(&region.get_position(), &region.get_size(), &position);
+						continue;
+					}
+					// The atlas is full, so fall back to drawing straight from the image.
+					let tint = &self.tint;
+					let (_, buffer, flash_buffer) = self.sprites.entry(sprite.url.clone()).or_insert_with(|| {
+						let mut texture = DisplayTexture::new();
+						texture.load_from_url(&sprite.url);
+						let mut buffer = DisplayBuffer::new(DisplayBufferType::IMAGES);
+						buffer.set_texture(&texture);
+						buffer.set_tint(&tint.0, tint.1);
+						let mut flash_buffer = DisplayBuffer::new(DisplayBufferType::IMAGES);
+						flash_buffer.set_texture(&texture);
+						flash_buffer.set_tint(&Color::new(255, 255, 255, 255), 1.0);
+						(texture, buffer, flash_buffer)
+					});
+					let target = if 0.5 < flash { flash_buffer } else { buffer };
+					target.make_editor().add_image(&sprite.position, &sprite.size, &position);
+				},
+				_ => {
+					// Flashes toward white.
+					let base = &self.shape_color;
+					let blend = |value : u8| value + ((255 - value) as f32 * flash) as u8;
+					let color = Color::new(blend(base.red), blend(base.green), blend(base.blue), 255);
+					shapes.add_circle(Vec3::new(enemy.position.x, enemy.position.y, ENEMY_DEPTH), enemy.definition.radius, ENEMY_CIRCLE_POINTS, &color);
+				},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_enemies {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	/// Gets a view around the origin (so enemies there are fully updated).
+	fn near_origin() -> Bounds2 {
+		Bounds2::from_centered_rect(&Vec2::zero(), 400.0, 300.0)
+	}
+
+	#[test]
+	fn definitions() {
+		let mut definitions = EnemyDefinitions::new();
+		assert!(definitions.set_property("crawler", "speed", "40").is_ok());
+		assert!(definitions.set_property("crawler", "behaviors", "patrol, chase").is_ok());
+		assert!(definitions.set_property("crawler", "drops", "scrap:0.5,health").is_ok());
+		assert!(definitions.set_property("crawler", "spriteRegion", "16,0,16,8").is_ok());
+		assert!(definitions.set_property("crawler", "behaviors", "dance").is_err());
+		assert!(definitions.set_property("crawler", "nonsense", "1").is_err());
+		assert!(definitions.set_property("crawler", "projectile", "seeker").is_ok());
+		assert!(definitions.set_property("crawler", "projectile", "banana").is_err());
+		let crawler = definitions.get("crawler").unwrap();
+		assert_eq!(crawler.speed, 40.0);
+		assert_eq!(crawler.behaviors, vec![EnemyBehavior::Patrol, EnemyBehavior::Chase]);
+		assert_eq!(crawler.drops, vec![
+			EnemyDrop { kind : "scrap".to_string(), chance : 0.5 },
+			EnemyDrop { kind : "health".to_string(), chance : 1.0 },
+		]);
+		assert_eq!(crawler.sprite.as_ref().unwrap().size.x, 16.0);
+		assert_eq!(crawler.projectile, "seeker");
+		assert!(definitions.get("flyer").is_none());
+	}
+
+	#[test]
+	fn shooting() {
+		let mut definition = EnemyDefinition::new("turret");
+		definition.set_property("behaviors", "shoot").unwrap();
+		definition.set_property("fireInterval", "1").unwrap();
+		let mut enemy = Enemy::new(&definition, &Vec2::zero());
+		let collision = CollisionSystem::new();
+		let near = Vec2::new(50.0, 0.0);
+		let mut shots = 0;
+		for _ in 0..30 {
+			shots += enemy.update(0.1, Some(&near), &Vec2::zero(), &collision).iter().count();
+		}
+		assert_eq!(shots, 3); // Waits a full interval before the first shot.
+		assert!(enemy.update(5.0, Some(&Vec2::new(1000.0, 0.0)), &Vec2::zero(), &collision).is_none()); // Too far away.
+		assert!(enemy.update(5.0, None, &Vec2::zero(), &collision).is_none());
+	}
+
+	#[test]
+	fn targets_nearest_player() {
+		let mut definitions = EnemyDefinitions::new();
+		definitions.set_property("turret", "behaviors", "shoot").unwrap();
+		definitions.set_property("turret", "fireInterval", "1").unwrap();
+		definitions.finish_loading();
+		let mut enemies = Enemies::new();
+		enemies.add_spawn("turret", &Vec2::zero());
+		enemies.spawn_pending(&definitions);
+		let mut dynamic = SpatialHash::new();
+		dynamic.insert(DynamicKind::Player, 0, &Vec2::new(-120.0, 0.0), 4.0);
+		dynamic.insert(DynamicKind::Player, 1, &Vec2::new(60.0, 0.0), 4.0);
+		dynamic.insert(DynamicKind::Pickup, 0, &Vec2::new(5.0, 0.0), 4.0); // Not something to shoot at.
+		let shots = enemies.update(1.0, &dynamic, &Vec2::zero(), &CollisionSystem::new(), &near_origin());
+		assert_eq!(shots.len(), 1);
+		assert_eq!(shots[0].target.x, 60.0);
+	}
+
+	#[test]
+	fn spawning() {
+		let mut definitions = EnemyDefinitions::new();
+		let mut enemies = Enemies::new();
+		enemies.add_spawn("crawler", &Vec2::new(0.0, 0.0));
+		enemies.add_spawn("ghost", &Vec2::new(10.0, 0.0));
+		definitions.set_property("crawler", "health", "3").unwrap();
+		assert!(enemies.spawn_pending(&definitions).is_empty());
+		assert!(enemies.get_enemies().is_empty()); // Waits until loading is done.
+		definitions.finish_loading();
+		assert_eq!(enemies.spawn_pending(&definitions), vec!["ghost".to_string()]);
+		assert_eq!(enemies.get_enemies().len(), 1);
+		assert!(enemies.damage(0, 2.0, &Vec2::new(-5.0, 0.0)).is_none());
+		assert!(enemies.get_enemies()[0].knockback.is_stunned());
+		assert!(0.0 < enemies.get_enemies()[0].knockback.get_velocity().x); // Pushed away from the hit.
+		assert!(enemies.damage(0, 2.0, &Vec2::new(-5.0, 0.0)).is_some());
+		assert!(enemies.get_enemies().is_empty());
+	}
+
+	#[test]
+	fn patrols_between_walls() {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-50.0, 0.0), &Vec2::new(50.0, 0.0))));
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-50.0, 0.0), &Vec2::new(-50.0, 50.0))));
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(50.0, 0.0), &Vec2::new(50.0, 50.0))));
+		let mut definition = EnemyDefinition::new("crawler");
+		definition.set_property("behaviors", "patrol").unwrap();
+		let mut enemy = Enemy::new(&definition, &Vec2::new(0.0, 20.0));
+		let gravity = Vec2::new(0.0, -800.0);
+		let far_away = Vec2::new(1000.0, 1000.0);
+		let mut turned = false;
+		for _ in 0..200 {
+			enemy.update(1.0 / 30.0, Some(&far_away), &gravity, &collision);
+			turned |= 0.0 > enemy.facing;
+			assert!(-50.0 < enemy.position.x && enemy.position.x < 50.0);
+		}
+		assert!(turned);
+		assert!((enemy.position.y - definition.radius).abs() < 0.5); // Standing on the floor.
+	}
+
+	#[test]
+	fn contact_damage_cooldown() {
+		let mut enemies = Enemies::new();
+		let mut definitions = EnemyDefinitions::new();
+		definitions.set_property("crawler", "damage", "5").unwrap();
+		definitions.finish_loading();
+		enemies.add_spawn("crawler", &Vec2::new(0.0, 0.0));
+		enemies.spawn_pending(&definitions);
+		let hits = enemies.take_contact_hits(&Vec2::new(8.0, 0.0), 4.0);
+		assert_eq!(hits.len(), 1);
+		assert_eq!(hits[0].damage, 5.0);
+		assert_eq!(hits[0].source.x, 0.0);
+		assert!(enemies.take_contact_hits(&Vec2::new(8.0, 0.0), 4.0).is_empty());
+		assert!(enemies.take_contact_hits(&Vec2::new(80.0, 0.0), 4.0).is_empty());
+	}
+
+	#[test]
+	fn stomping() {
+		let mut enemies = Enemies::new();
+		let mut definitions = EnemyDefinitions::new();
+		definitions.set_property("crawler", "health", "2").unwrap();
+		definitions.finish_loading();
+		enemies.add_spawn("crawler", &Vec2::new(0.0, 0.0));
+		enemies.spawn_pending(&definitions);
+		let up = Vec2::new(0.0, 1.0);
+		let above = Vec2::new(0.0, 8.0);
+		assert!(!enemies.stomp(&above, 4.0, &Vec2::new(0.0, 10.0), &up, 1.0).hit); // Going up.
+		assert!(!enemies.stomp(&Vec2::new(0.0, -8.0), 4.0, &Vec2::new(0.0, -10.0), &up, 1.0).hit); // Below.
+		let first = enemies.stomp(&above, 4.0, &Vec2::new(0.0, -10.0), &up, 1.0);
+		assert!(first.hit && first.defeated.is_empty());
+		assert!(enemies.take_contact_hits(&above, 4.0).is_empty()); // Doesn't hurt right after being stomped.
+		enemies.update(CONTACT_DAMAGE_COOLDOWN, &SpatialHash::new(), &Vec2::zero(), &CollisionSystem::new(), &near_origin());
+		let landing = enemies.get_enemies()[0].position + above;
+		let second = enemies.stomp(&landing, 4.0, &Vec2::new(0.0, -10.0), &up, 1.0);
+		assert_eq!(second.defeated.len(), 1);
+		assert!(enemies.get_enemies().is_empty());
+	}
+
+	#[test]
+	fn far_away_updates_less() {
+		let mut definitions = EnemyDefinitions::new();
+		definitions.set_property("drifter", "behaviors", "patrol, fly").unwrap();
+		definitions.set_property("statue", "behaviors", "patrol, fly").unwrap();
+		definitions.set_property("statue", "lod", "none, 100").unwrap();
+		definitions.finish_loading();
+		let mut enemies = Enemies::new();
+		enemies.add_spawn("drifter", &Vec2::new(1000.0, 0.0));
+		enemies.add_spawn("statue", &Vec2::new(1000.0, 0.0));
+		enemies.add_spawn("drifter", &Vec2::new(0.0, 0.0));
+		enemies.spawn_pending(&definitions);
+		let (dynamic, collision) = (SpatialHash::new(), CollisionSystem::new());
+		for _ in 0..8 {
+			enemies.update(0.1, &dynamic, &Vec2::zero(), &collision, &near_origin());
+		}
+		// Out of view but not too far catches up on the time every few steps, while in view moves every step. Both end up moving the same.
+		let moved : Vec<f32> = enemies.get_enemies().iter().map(|enemy| enemy.position.x.abs() % 1000.0).collect();
+		assert_eq!(enemies.get_enemies()[0].lod.get_level(), UpdateLevel::Reduced);
+		assert!((moved[0] - moved[2]).abs() < 0.01);
+		// Far enough away (for its definition) doesn't move at all.
+		assert_eq!(enemies.get_enemies()[1].lod.get_level(), UpdateLevel::Frozen);
+		assert_eq!(moved[1], 0.0);
+	}
+}
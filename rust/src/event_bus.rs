@@ -0,0 +1,44 @@
+/// Something that happened in the game that other systems might care about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+	/// An objective (by name) was completed.
+	ObjectiveCompleted(String),
+	/// A pickup (by kind) was collected.
+	PickupCollected(String),
+	/// A secret area (by index) was found.
+	SecretFound(usize),
+	/// An enemy (by definition name) was defeated.
+	EnemyDefeated(String),
+	/// A timed challenge (by name) was finished in time.
+	ChallengeCompleted(String),
+	/// The player was in a fight (an enemy shot at them, touched them, or was stomped).
+	Combat,
+}
+
+/// A queue of GameEvents.
+///
+/// Systems post events as things happen, and the Game hands them out to everything that listens once per update.
+/// This keeps systems from needing references to each other.
+pub struct EventBus {
+	/// The events posted since the last `drain()`.
+	queue : Vec<GameEvent>,
+}
+
+impl EventBus {
+	/// Creates an empty bus.
+	pub fn new() -> EventBus {
+		EventBus {
+			queue : Vec::new(),
+		}
+	}
+
+	/// Adds an event to the queue.
+	pub fn post(&mut self, event : GameEvent) {
+		self.queue.push(event);
+	}
+
+	/// Takes all the queued events (in the order they were posted).
+	pub fn drain(&mut self) -> Vec<GameEvent> {
+		std::mem::take(&mut self.queue)
+	}
+}
@@ -6,8 +6,11 @@ pub type ColorMagnitude = u8;
 pub type DrawBufferID = i32;
 pub type DrawCoord = f32;
 pub type DrawIndex = u16;
+/// Indices for buffers with too many vertices for DrawIndex (see `setDisplayBufferWide()`).
+pub type WideDrawIndex = u32;
 pub type DrawTextureID = i32;
 pub type DrawTextID = i32;
+pub type RenderViewID = u32;
 
 #[wasm_bindgen]
 extern {
@@ -15,6 +18,18 @@ extern {
 	#[wasm_bindgen(js_namespace=console, js_name=log)]
 	fn _log(message : &str);
 
+	/// Gets a high resolution timestamp (in milliseconds).
+	#[wasm_bindgen(js_namespace=performance, js_name=now)]
+	fn _performanceNow() -> f64;
+
+	/// Gets the wall clock time (in milliseconds since 1970, UTC).
+	#[wasm_bindgen(js_namespace=Date, js_name=now)]
+	fn _dateNow() -> f64;
+
+	/// Checks whether a function exists on the JavaScript side (with dotted names for ones on child objects). Errors if this check itself is missing.
+	#[wasm_bindgen(catch, js_namespace=GAME, js_name=hasGameFunction)]
+	fn _hasGameFunction(name : &str) -> Result<bool, JsValue>;
+
 	#[wasm_bindgen(js_namespace=GAME, js_name=exportExample)]
 	pub fn customCall(number : i32);
 
@@ -27,15 +42,50 @@ extern {
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBuffer)]
 	fn _setDisplayBuffer(id : DrawBufferID, vertices : Vec<DrawCoord>, colors : Vec<ColorMagnitude>, indices : Vec<DrawIndex>) -> bool;
 
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferWide)]
+	fn _setDisplayBufferWide(id : DrawBufferID, vertices : Vec<DrawCoord>, colors : Vec<ColorMagnitude>, indices : Vec<WideDrawIndex>) -> bool;
+
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferTransform)]
 	fn _setDisplayBufferTransform(id : DrawBufferID, matrix : Vec<DrawCoord>) -> bool;
 
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayTransform)]
 	pub fn setDisplayTransform(matrix : Vec<DrawCoord>);
 
+	/// Creates or updates a render view: a region of the canvas (in fractions of its size, from the bottom left) drawn with its own transform.
+	#[wasm_bindgen(js_namespace=GAME, js_name=setRenderView)]
+	pub fn setRenderView(view : RenderViewID, left : f32, bottom : f32, width : f32, height : f32, matrix : Vec<DrawCoord>);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=removeRenderView)]
+	pub fn removeRenderView(view : RenderViewID) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferViews)]
+	pub fn setDisplayBufferViews(id : DrawBufferID, views : u32) -> bool;
+
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferVisibility)]
 	pub fn setDisplayBufferVisibility(id : DrawBufferID, visibility : bool) -> bool;
 
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferAlpha)]
+	pub fn setDisplayBufferAlpha(id : DrawBufferID, alpha : f32) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferTint)]
+	pub fn setDisplayBufferTint(id : DrawBufferID, red : f32, green : f32, blue : f32, amount : f32) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferFrozen)]
+	pub fn setDisplayBufferFrozen(id : DrawBufferID, frozen : bool) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferBlendMode)]
+	pub fn setDisplayBufferBlendMode(id : DrawBufferID, mode : i32) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferDepthTest)]
+	pub fn setDisplayBufferDepthTest(id : DrawBufferID, enabled : bool) -> bool;
+
+	/// Whether display buffers can be drawn instanced (see `setDisplayBufferInstances()`).
+	#[wasm_bindgen(js_namespace=GAME, js_name=supportsInstancing)]
+	pub fn supportsInstancing() -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDisplayBufferInstances)]
+	fn _setDisplayBufferInstances(id : DrawBufferID, offsets : Vec<DrawCoord>, colors : Vec<ColorMagnitude>) -> bool;
+
 	#[wasm_bindgen(js_namespace=GAME, js_name=createDrawTexture)]
 	pub fn createDrawTexture() -> DrawTextureID;
 
@@ -45,6 +95,15 @@ extern {
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDrawTextureFromURL)]
 	pub fn setDrawTextureFromURL(id : DrawTextureID, url : &str) -> bool;
 
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDrawTextureOptions)]
+	pub fn setDrawTextureOptions(id : DrawTextureID, use_color_key : bool, key_red : u8, key_green : u8, key_blue : u8, key_tolerance : u8, premultiply_alpha : bool) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=setDrawTextureSize)]
+	pub fn setDrawTextureSize(id : DrawTextureID, width : u32, height : u32) -> bool;
+
+	#[wasm_bindgen(js_namespace=GAME, js_name=blitImageIntoTexture)]
+	pub fn blitImageIntoTexture(id : DrawTextureID, url : &str, source_x : u32, source_y : u32, width : u32, height : u32, destination_x : u32, destination_y : u32) -> bool;
+
 	#[wasm_bindgen(js_namespace=GAME, js_name=setDrawBufferTexture)]
 	pub fn setDrawBufferTexture(bufferId : DrawBufferID, textureId : DrawTextureID) -> bool;
 
@@ -67,27 +126,148 @@ extern {
 	#[wasm_bindgen(js_namespace=GAME, js_name="text.setTextVisibility")]
 	pub fn setDisplayTextVisibility(id : DrawTextID, visible : bool);
 
+	#[wasm_bindgen(js_namespace=GAME, js_name="text.setTextScale")]
+	pub fn setDisplayTextScale(scale : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="text.setLayout")]
+	pub fn setDisplayTextLayout(left : f32, bottom : f32, width : f32, height : f32, ui_scale : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="text.setFontStack")]
+	pub fn setDisplayTextFontStack(locale : &str, font_stack : &str);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="text.measureTextWidth")]
+	pub fn measureDisplayTextWidth(text : &str) -> f32;
+
 
 	#[wasm_bindgen(js_namespace=GAME, js_name="startTiledFileLoad")]
-	pub fn startTiledFileLoad(url : &str);
+	pub fn startTiledFileLoad(name : &str, url : &str);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="startEnemyDefinitionsLoad")]
+	pub fn startEnemyDefinitionsLoad(name : &str, url : &str);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="startGhostLoad")]
+	pub fn startGhostLoad(name : &str, url : &str);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="audio.playSound")]
+	pub fn playSound(name : &str, url : &str, volume : f32, reverb : f32, muffled : bool);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="audio.playMusic")]
+	pub fn playMusic(name : &str, url : &str, stem_name : &str, stem_url : &str, fade_seconds : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="audio.stopMusic")]
+	pub fn stopMusic(fade_seconds : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="audio.setMusicStemVolume")]
+	pub fn setMusicStemVolume(volume : f32, fade_seconds : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="audio.setMusicVolume")]
+	pub fn setMusicVolume(volume : f32, fade_seconds : f32);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="storeSaveData")]
+	pub fn storeSaveData(key : &str, data : &str);
+
+	#[wasm_bindgen(js_namespace=GAME, js_name="loadSaveData")]
+	pub fn loadSaveData(key : &str) -> String;
 }
 
 #[allow(non_snake_case)] // To keep with TypeScript's naming conventions, don't mess with this.
 pub fn setDisplayBuffer(id : DrawBufferID, vertices : &Vec<DrawCoord>, colors : &Vec<ColorMagnitude>, indices : &Vec<DrawIndex>) {
 	if !_setDisplayBuffer(id, vertices.clone(), colors.clone(), indices.clone()) {
-		panic!("No such display buffer {}", id);
+		crate::fatal!("No such display buffer {}", id);
+	}
+}
+
+/// Like `setDisplayBuffer()`, but with 32 bit indices (for buffers with more vertices than 16 bits can index).
+#[allow(non_snake_case)] // To keep with TypeScript's naming conventions, don't mess with this.
+pub fn setDisplayBufferWide(id : DrawBufferID, vertices : &Vec<DrawCoord>, colors : &Vec<ColorMagnitude>, indices : &Vec<WideDrawIndex>) {
+	if !_setDisplayBufferWide(id, vertices.clone(), colors.clone(), indices.clone()) {
+		crate::fatal!("No such display buffer {}", id);
+	}
+}
+
+/// Makes a display buffer draw once per instance. There are three offset values and four color values per instance.
+#[allow(non_snake_case)] // To keep with TypeScript's naming conventions, don't mess with this.
+pub fn setDisplayBufferInstances(id : DrawBufferID, offsets : Vec<DrawCoord>, colors : Vec<ColorMagnitude>) {
+	if !_setDisplayBufferInstances(id, offsets, colors) {
+		crate::fatal!("No such display buffer {}", id);
 	}
 }
 
 #[allow(non_snake_case)] // To keep with TypeScript's naming conventions, don't mess with this.
 pub fn setDisplayBufferTransform(id : DrawBufferID, matrix : Vec<DrawCoord>) {
 	if !_setDisplayBufferTransform(id, matrix) {
-		panic!("No such display buffer {}", id);
+		crate::fatal!("No such display buffer {}", id);
 	}
 }
 
+/// Logs a formatted message to the console, but only in builds that keep debug output. With the `release-lean` feature, the branch is constant false, so the formatting code gets dropped from the build.
+#[macro_export]
+macro_rules! debug_log {
+	( $($message : tt)+ ) => {
+		if !cfg!(feature = "release-lean") {
+			$crate::externals::log(&format!( $($message)+ ));
+		}
+	};
+}
+
+/// Prints a formatted message to stdout (which only shows up when running tests). Dropped from the build like `debug_log!()`.
+#[macro_export]
+macro_rules! debug_println {
+	( $($message : tt)+ ) => {
+		if !cfg!(feature = "release-lean") {
+			println!( $($message)+ );
+		}
+	};
+}
+
+/// Panics with a formatted message. With the `release-lean` feature, it panics without the message, so none of the formatting code ends up in the build.
+#[macro_export]
+macro_rules! fatal {
+	( $($message : tt)+ ) => {
+		if cfg!(feature = "release-lean") {
+			panic!()
+		} else {
+			panic!( $($message)+ )
+		}
+	};
+}
+
+/// Asserts that something is true, with a formatted message if it isn't. With the `release-lean` feature, the message is dropped like in `fatal!()` (the condition is still checked).
+#[macro_export]
+macro_rules! lean_assert {
+	( $condition : expr, $($message : tt)+ ) => {
+		if !($condition) {
+			$crate::fatal!( $($message)+ );
+		}
+	};
+}
+
+/// Whether a function exists on the JavaScript `GAME` object (dotted names are on a child object). False if that can't even be checked.
+#[allow(non_snake_case)] // To keep with TypeScript's naming conventions, don't mess with this.
+pub fn hasGameFunction(name : &str) -> bool {
+	_hasGameFunction(name).unwrap_or(false)
+}
+
 pub fn log(message : &str) {
 	if !cfg!(test) {
 		_log(message);
 	}
 }
+
+/// Gets the wall clock time in seconds (since 1970, UTC). Only meant for timestamps shown to the player.
+pub fn wall_clock_seconds() -> f64 {
+	if cfg!(test) {
+		0.0
+	} else {
+		_dateNow() / 1000.0
+	}
+}
+
+/// Gets the current time in seconds (from some arbitrary starting point). Only meant for measuring how long things take.
+pub fn now_seconds() -> f64 {
+	if cfg!(test) {
+		0.0
+	} else {
+		_performanceNow() / 1000.0
+	}
+}
@@ -1,4 +1,5 @@
 use crate::externals::*;
+use crate::debug_log;
 use crate::color::*;
 use crate::camera::*;
 use crate::mouse::*;
@@ -8,11 +9,116 @@ use crate::display_text::*;
 use crate::tiled::*;
 use crate::tiled_display::*;
 use crate::tiled_geometry::*;
+use crate::tile_behavior::*;
 use crate::player::Player;
+use crate::rope::Rope;
+use crate::water::Water;
+use crate::tuning::Tuning;
+use crate::screen_effects::ScreenEffects;
+use crate::meter_bar::MeterBar;
+use crate::event_bus::*;
+use crate::objectives::*;
+use crate::photo_mode::PhotoMode;
+use crate::time_control::*;
+use crate::anomaly_capture::*;
+use crate::nav_grid::NavGrid;
+use crate::pickups::*;
+use crate::drone::*;
+use crate::enemies::*;
+use crate::projectiles::*;
+use crate::secrets::Secrets;
+use crate::challenge_room::*;
+use crate::ambient::*;
+use crate::spatial_hash::*;
+use crate::hitboxes::HitboxKind;
+use crate::debris::*;
+use crate::sound::*;
+use crate::music::*;
+use crate::camera_rail::*;
+use crate::tween::Easing;
+use crate::camera_region::CameraRegions;
+use crate::animation::*;
+use crate::moving_platform::MovingPlatform;
+use crate::shadows::Shadows;
+use crate::ability_meter::*;
+use crate::random::Random;
+use crate::room_transition::*;
+use crate::world_flags::*;
+use crate::button_prompt::*;
+use crate::save_slots::*;
+use crate::asset_manifest::asset_url;
+use crate::scheduler::*;
+use crate::map_queries;
+use crate::attract_mode::*;
+use crate::accessibility::*;
+use crate::palette::*;
+use crate::death_log::*;
+use crate::origin_rebase::*;
+use crate::snapshot::GameSnapshot;
+use crate::rewind::*;
+use crate::ghost::*;
+use crate::coop::*;
+use crate::scene::*;
+use crate::pause_menu::*;
+use crate::title::*;
+use crate::render_view::*;
+use crate::tile_chunks::CHUNK_TILES;
+use crate::work_queue::WorkQueue;
+
+use generational_arena::Index;
 
 use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
 use crate::geo::line_segment::*;
 use crate::geo::collision_system::*;
+use crate::geo::edge_set::EdgeSet;
+use crate::geo::consts::set_tolerances;
+use crate::geo::world_vec2::*;
+
+/// How far above the player's center the kick charge meter is drawn.
+const KICK_METER_HEIGHT : f32 = 14.0;
+/// How far above the player's center the bullet time meter is drawn.
+const BULLET_TIME_METER_HEIGHT : f32 = 19.0;
+/// How far above the player's center the stamina meter is drawn (so it's below them).
+const STAMINA_METER_HEIGHT : f32 = -14.0;
+/// How far above each player's center their health meter is drawn (only in co-op).
+const HEALTH_METER_HEIGHT : f32 = 24.0;
+/// How much a contact's normal needs to point up for the player to count as standing on a moving platform.
+const PLATFORM_STAND_DOT : f32 = 0.7;
+/// How big the cells in the drone's navigation grid are (in pixels).
+const NAV_CELL_SIZE : f32 = 16.0;
+/// How much open space to leave around the level's geometry in the navigation grid.
+const NAV_PADDING : f32 = 64.0;
+/// How close the player needs to get to a pickup to collect it.
+const PICKUP_COLLECT_RADIUS : f32 = 8.0;
+/// The seed for the game's random numbers (fixed so runs can be repeated).
+const RANDOM_SEED : u32 = 0x5EED_1234;
+/// The file that enemy definitions are loaded from.
+const ENEMY_DEFINITIONS_URL : &str = "enemies.json";
+/// The key the death log is saved under.
+const DEATH_LOG_SAVE_KEY : &str = "deaths";
+/// The key the accessibility settings are saved under.
+const ACCESSIBILITY_SAVE_KEY : &str = "accessibility";
+/// The key the gamepad calibration is saved under.
+const GAMEPAD_CALIBRATION_SAVE_KEY : &str = "gamepadCalibration";
+/// Where the first player's half of the screen is in split-screen co-op.
+const SPLIT_FIRST_VIEW : ViewRect = ViewRect { left : 0.0, bottom : 0.0, width : 0.5, height : 1.0 };
+/// Where the second player's half of the screen is in split-screen co-op.
+const SPLIT_SECOND_VIEW : ViewRect = ViewRect { left : 0.5, bottom : 0.0, width : 0.5, height : 1.0 };
+/// Where the minimap is drawn (the top right corner).
+const MINIMAP_RECT : ViewRect = ViewRect { left : 0.77, bottom : 0.72, width : 0.21, height : 0.26 };
+/// How zoomed in the minimap is.
+const MINIMAP_ZOOM : f32 = 0.15;
+/// The gray that the world is blended toward while rewinding (so it looks washed out).
+const REWIND_TINT : Color = Color { red : 128, green : 128, blue : 128, alpha : 255 };
+/// How much the world is blended toward the rewind tint.
+const REWIND_TINT_AMOUNT : f32 = 0.6;
+/// How long loading a room can take each update (in seconds), so a big map loads over several updates instead of freezing the page. About half of a 60 FPS frame.
+const ROOM_LOAD_BUDGET_SECONDS : f64 = 0.008;
+/// How many collision edges are added per loading step.
+const ROOM_LOAD_EDGE_BATCH : usize = 512;
+/// The systems that aren't needed while in photo mode (as everything they update is hidden).
+const PHOTO_MODE_DISABLED_SYSTEMS : [&str; 7] = ["objective_markers", "kick_meter", "bullet_time_meter", "stamina_meter", "coop_meters", "prompts", "challenge_timer"];
 
 pub struct Game {
 	camera : Camera,
@@ -31,103 +137,2409 @@ pub struct Game {
 
 	#[allow(dead_code)] // This should be stored, so it's clear where the instructional text comes from...
 	tiled_file : SharedTiledFile,
+	/// The file of the room being loaded in (see `room_load`).
+	room_load_file : SharedTiledFile,
+	/// What's left of loading in a room. The simulation waits until it's done.
+	room_load : WorkQueue<Game>,
+	/// Shows how far along loading a room is.
+	room_load_text : DisplayText,
 	tiled_display : TiledDisplay,
 	tiled_geometry : TiledGeometry,
+	tile_behaviors : TileBehaviors,
+	ropes : Vec<Rope>,
+	waters : Vec<Water>,
+	platforms : Vec<MovingPlatform>,
+	/// The platform the player was standing on at the end of the last update (if any).
+	riding_platform : Option<usize>,
+	/// The camera move being played (if any).
+	cutscene : Option<TrackPlayer<Vec2>>,
+	/// The paths the camera can be sent along (as in from dialogue).
+	camera_rails : CameraRails,
+	/// The camera's trip along one of those (if it's on one).
+	rail_move : Option<RailMove>,
+	nav_grid : NavGrid,
+	pickups : Pickups,
+	pickup_display : PickupDisplay,
+	drone : Drone,
+	secrets : Secrets,
+	/// The areas that change how sounds are heard.
+	sound_zones : SoundZones,
+	/// Decides what music plays.
+	music : Music,
+	/// The timed challenges in the room.
+	challenges : Challenges,
+	/// What the room was like when the running challenge started (so it can be put back if the challenge is failed).
+	challenge_snapshot : Option<GameSnapshot>,
+	/// Shows how long the running challenge has left.
+	challenge_timer : DisplayText,
+	/// The decorative birds, leaves, and drips near the camera.
+	ambient : Ambient,
+	ambient_display : AmbientDisplay,
+	/// Where everything that moves (players, enemies, projectiles, and pickups) was as of the last rebuild, for finding what's near what.
+	dynamic : SpatialHash,
+	/// The pieces of any tiles that were broken.
+	debris : Debris,
+	debris_display : DebrisDisplay,
+	/// The parts of the map that take over the camera while the player is in them.
+	camera_regions : CameraRegions,
+	enemy_definitions : EnemyDefinitions,
+	enemies : Enemies,
+	enemy_display : EnemyDisplay,
+	projectiles : Projectiles,
+	projectile_display : ProjectileDisplay,
+	/// How long until the player can fire again (in seconds).
+	shot_cooldown : f32,
+	doors : Vec<Door>,
+	/// Whether the player has stepped away from every door since arriving in the room (so they aren't sent straight back).
+	doors_armed : bool,
+	/// The move to another room that's playing (if any).
+	room_transition : Option<RoomTransition>,
+	flags : WorldFlags,
+	switches : Vec<FlagSwitch>,
+	/// Which save slot is being played, and what's in each.
+	save_slots : SaveSlots,
+	/// How long the active slot has been played since its metadata was last saved (in seconds).
+	unsaved_playtime : f64,
+	/// The button prompts that show up near spots in the room.
+	prompt_points : Vec<PromptPoint>,
+
+	tuning : Tuning,
+	screen_effects : ScreenEffects,
+	kick_meter : MeterBar,
+	bullet_time : AbilityMeter,
+	bullet_time_meter : MeterBar,
+	/// Whether bullet time ran out and is waiting for its button to be let go before it can start again.
+	bullet_time_locked : bool,
+	/// Shows the player's stamina (see `Player::stamina`).
+	stamina_meter : MeterBar,
+	/// Whether the stamina meter is showing the player as exhausted (in the danger color).
+	stamina_meter_exhausted : bool,
+	shadows : Shadows,
+
+	random : Random,
+	events : EventBus,
+	quests : QuestLog,
+	objective_display : ObjectiveDisplay,
+
+	photo_mode : PhotoMode,
+	time_control : TimeControl,
+	anomaly_capture : AnomalyCapture,
+	/// Plays the demo replay when nobody's playing.
+	attract_mode : AttractMode,
+	/// The device the player last used (so prompts can show matching buttons).
+	input_device : InputDevice,
+	/// Shown while the demo is playing.
+	demo_text : DisplayText,
+	/// Options that make the game easier to see and play.
+	accessibility : AccessibilitySettings,
+	/// Measuring where the first gamepad's sticks rest (if that's happening).
+	calibrator : Option<GamepadCalibrator>,
+	/// Shows how calibrating the gamepad is going.
+	calibration_text : DisplayText,
+	/// Where the player has died (across play sessions).
+	death_log : DeathLog,
+	/// The debug overlay showing the death log.
+	death_heatmap : DeathHeatmapDisplay,
+	/// Whether the death heatmap's toggle key was down last update (so holding it doesn't flicker on and off).
+	death_heatmap_toggle_held : bool,
+
+	/// Decides what order everything runs in during an update.
+	scheduler : Scheduler<Game>,
+	/// Whether photo mode was turned on or off during this update.
+	photo_mode_toggled : bool,
+	/// Where the player was before they moved during this simulation step.
+	previous_player_position : Vec2,
+	/// Where the second player (if any) was before they moved during this simulation step.
+	previous_second_player_position : Vec2,
+	/// Moves everything back toward the origin when the player gets far from it (and keeps track of how far that's been).
+	origin_rebase : OriginRebaser,
+	/// The snapshot kept by the quick save debug command.
+	quick_save : Option<GameSnapshot>,
+	/// The recent history to rewind through.
+	rewind : RewindHistory<GameSnapshot>,
+	/// Whether time is currently being rolled back.
+	rewinding : bool,
+	/// How much real time has gone by toward going back another snapshot (in seconds).
+	rewind_timer : f32,
+	/// Records the current run in the room, to race against later.
+	ghost_recorder : GhostRecorder,
+	/// The ghost being raced (only shown in the room it was recorded in).
+	ghost : Option<GhostRun>,
+	/// The last run that was finished.
+	last_run : Option<GhostRun>,
+	/// Shows the ghost and how the current run compares to it.
+	ghost_display : GhostDisplay,
+	/// The second (local) player, while co-op is on.
+	second_player : Option<Player>,
+	/// The second player's keys.
+	second_keyboard : Keyboard,
+	/// The second player's gamepad (the second one connected).
+	second_gamepad : Gamepad,
+	/// When the last update started (in seconds, from `now_seconds()`). Input that came in after this belongs to the next one.
+	input_time : f64,
+	/// The second player's kick charge meter.
+	second_kick_meter : MeterBar,
+	/// Each player's health meter (only shown in co-op).
+	health_meters : [MeterBar; 2],
+	/// Which scene is active (and what's under it).
+	scenes : SceneStack,
+	/// The menu shown while paused.
+	pause_menu : PauseMenu,
+	/// Whether the menu button was down last update (so holding it doesn't open and close the menu over and over).
+	menu_button_held : bool,
+	/// Moves the camera around behind the title menu.
+	title_pan : Option<TrackPlayer<Vec2>>,
+	/// The second player's half of the screen (only while co-op is split).
+	second_view : Option<RenderView>,
+	/// A small zoomed out view of the world around the player (while turned on).
+	minimap : Option<RenderView>,
+}
+
+/// Adds the edges of a rectangle as obstacles.
+fn add_rect_obstacles(collision : &mut CollisionSystem, rect : &Bounds2, user_data : Option<usize>) -> Vec<Index> {
+	add_polygon_obstacles(collision, &vec![
+		Vec2::new(rect.x_min(), rect.y_min()),
+		Vec2::new(rect.x_max(), rect.y_min()),
+		Vec2::new(rect.x_max(), rect.y_max()),
+		Vec2::new(rect.x_min(), rect.y_max()),
+	], user_data)
+}
+
+/// Adds the top of a rectangle as a one-way platform (only solid when landed on from above).
+fn add_platform_obstacle(collision : &mut CollisionSystem, rect : &Bounds2) -> Index {
+	let index = collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
+		&Vec2::new(rect.x_min(), rect.y_max()),
+		&Vec2::new(rect.x_max(), rect.y_max()),
+	)));
+	collision.set_one_way(index, Some(Vec2::new(0.0, 1.0)));
+	index
+}
+
+/// Adds the edges of a polygon as obstacles.
+fn add_polygon_obstacles(collision : &mut CollisionSystem, polygon : &Vec<Vec2>, user_data : Option<usize>) -> Vec<Index> {
+	let mut indices = Vec::with_capacity(polygon.len());
+	for index in 0..polygon.len() {
+		let mut next_index = index+1;
+		if next_index >= polygon.len() { next_index = 0; }
+		let obstacle = CircleObstacle::LineSegment(LineSegment::new(
+			&polygon[index],
+			&polygon[next_index],
+		));
+		indices.push(match user_data {
+			Some(data) => collision.add_obstacle_with_user_data(obstacle, data),
+			None => collision.add_obstacle(obstacle),
+		});
+	}
+	indices
 }
 
 
 impl Game {
 	pub fn new() -> Game {
-		log("Setting up WASM game!");
+		debug_log!("Setting up WASM game!");
+		let palette = Palette::new(PaletteKind::Standard); // Swapped for the saved one once everything exists.
 
 		let description = DisplayText::new_text_area(
 			0.80,
 			0.05,
 			0.95,
 			0.95,
-			&Color::new(0, 255, 0, 255),
+			&palette.get(PaletteRole::HudPrimary),
 			TextAlignment::JUSTIFY,
 			"Hit the arrow keys or WASD to move around.<br>Click to show mouse button tracking.",
 		);
 
+		let mut demo_text = DisplayText::new_text_area(
+			0.05,
+			0.05,
+			0.15,
+			0.95,
+			&Color::new(255, 255, 255, 255),
+			TextAlignment::CENTER,
+			"DEMO",
+		);
+		demo_text.hide();
+		let mut challenge_timer = DisplayText::new_text_area(
+			0.15,
+			0.35,
+			0.25,
+			0.65,
+			&palette.get(PaletteRole::HudPrimary),
+			TextAlignment::CENTER,
+			"",
+		);
+		challenge_timer.hide();
+		let mut room_load_text = DisplayText::new_text_area(
+			0.85,
+			0.35,
+			0.95,
+			0.65,
+			&palette.get(PaletteRole::HudPrimary),
+			TextAlignment::CENTER,
+			"",
+		);
+		room_load_text.hide();
+		let mut calibration_text = DisplayText::new_text_area(
+			0.05,
+			0.05,
+			0.15,
+			0.95,
+			&palette.get(PaletteRole::HudPrimary),
+			TextAlignment::CENTER,
+			"",
+		);
+		calibration_text.hide();
+		let replay = match InputReplay::parse(DEMO_REPLAY) {
+			Ok(replay) => replay,
+			Err(error) => {
+				log(&format!("Couldn't read the demo replay due to: {}", error));
+				InputReplay::parse("").unwrap()
+			},
+		};
+
+		let tuning = Tuning::new();
+		set_tolerances(&tuning.geometry_tolerances);
+		let mut collision = CollisionSystem::new();
+		collision.set_iteration_budget(tuning.collision_iteration_budget, tuning.iteration_escape);
+		let bullet_time = AbilityMeter::new(tuning.bullet_time_max_seconds, tuning.bullet_time_regen_rate).with_regen_delay(tuning.bullet_time_regen_delay);
+		let rewind = RewindHistory::new(tuning.rewind_max_seconds, FIXED_STEP_SECONDS, REWIND_SNAPSHOT_STEPS);
+
 		let mut tiled_file = SharedTiledFile::new();
-		assert!(tiled_file.load("room.json").is_ok(), "Couldn't start loading 'room.json'!");
+		crate::lean_assert!(tiled_file.load("room.json").is_ok(), "Couldn't start loading 'room.json'!");
+		startEnemyDefinitionsLoad(ENEMY_DEFINITIONS_URL, &asset_url(ENEMY_DEFINITIONS_URL));
+		let mut accessibility = AccessibilitySettings::new();
+		if let Err(error) = accessibility.load_save_string(&loadSaveData(ACCESSIBILITY_SAVE_KEY)) {
+			log(&format!("Couldn't load the saved accessibility settings due to: {}", error));
+		}
+		let mut gamepad_calibration = GamepadCalibration::new();
+		if let Err(error) = gamepad_calibration.load_save_string(&loadSaveData(GAMEPAD_CALIBRATION_SAVE_KEY)) {
+			log(&format!("Couldn't load the saved gamepad calibration due to: {}", error));
+		}
+		let mut gamepad = Gamepad::new();
+		gamepad.set_calibration(&gamepad_calibration);
+		let mut death_log = DeathLog::new();
+		if let Err(error) = death_log.load_save_string(&loadSaveData(DEATH_LOG_SAVE_KEY)) {
+			log(&format!("Couldn't load the saved death log due to: {}", error));
+		}
+		let save_slots = SaveSlots::load(&mut BrowserSaveStore);
+		let mut flags = WorldFlags::new();
+		if let Err(error) = flags.load_save_string(&loadSaveData(&save_slots.key(WORLD_FLAGS_SLOT_KEY))) {
+			log(&format!("Couldn't load the saved world flags due to: {}", error));
+		}
 
-		Game {
+		let mut game = Game {
 			camera: Camera::new(),
 			mouse: Mouse::new(),
 			keyboard: Keyboard::new(),
-			gamepad: Gamepad::new(),
+			gamepad,
 			elapsed: 0.0,
 
-			collision : CollisionSystem::new(),
+			collision,
 
 			description,
 
-			player : Player::new(),
+			player : Player::new(&tuning),
 
 			tiled_file,
+			room_load_file : SharedTiledFile::new(),
+			room_load : WorkQueue::new().with_progress(Game::show_room_load_progress),
+			room_load_text,
 			tiled_display : TiledDisplay::new(),
 			tiled_geometry : TiledGeometry::new(),
+			tile_behaviors : TileBehaviors::new(),
+			ropes : Vec::new(),
+			waters : Vec::new(),
+			platforms : Vec::new(),
+			riding_platform : None,
+			cutscene : None,
+			camera_rails : CameraRails::new(),
+			rail_move : None,
+			nav_grid : NavGrid::empty(),
+			pickups : Pickups::new(),
+			pickup_display : PickupDisplay::new(),
+			drone : Drone::new(&Vec2::zero()),
+			secrets : Secrets::new(),
+			sound_zones : SoundZones::new(),
+			music : Music::new(),
+			challenges : Challenges::new(),
+			challenge_snapshot : None,
+			challenge_timer,
+			ambient : Ambient::new(),
+			ambient_display : AmbientDisplay::new(),
+			dynamic : SpatialHash::new(),
+			debris : Debris::new(),
+			debris_display : DebrisDisplay::new(),
+			camera_regions : CameraRegions::new(),
+			enemy_definitions : EnemyDefinitions::new(),
+			enemies : Enemies::new(),
+			enemy_display : EnemyDisplay::new(),
+			projectiles : Projectiles::new(),
+			projectile_display : ProjectileDisplay::new(),
+			shot_cooldown : 0.0,
+			doors : Vec::new(),
+			doors_armed : false,
+			room_transition : None,
+			flags,
+			switches : Vec::new(),
+			save_slots,
+			unsaved_playtime : 0.0,
+			prompt_points : Vec::new(),
+
+			tuning,
+			screen_effects : ScreenEffects::new(),
+			kick_meter : MeterBar::new(&palette.get(PaletteRole::KickMeter)),
+			bullet_time,
+			bullet_time_meter : MeterBar::new(&palette.get(PaletteRole::BulletTimeMeter)),
+			bullet_time_locked : false,
+			stamina_meter : MeterBar::new(&palette.get(PaletteRole::StaminaMeter)),
+			stamina_meter_exhausted : false,
+			shadows : Shadows::new(),
+
+			random : Random::new(RANDOM_SEED),
+			events : EventBus::new(),
+			quests : QuestLog::new(),
+			objective_display : ObjectiveDisplay::new(),
+
+			photo_mode : PhotoMode::new(),
+			time_control : TimeControl::new(),
+			anomaly_capture : AnomalyCapture::new(),
+			attract_mode : AttractMode::new(replay, ATTRACT_IDLE_SECONDS),
+			input_device : InputDevice::Keyboard,
+			demo_text,
+			accessibility,
+			calibrator : None,
+			calibration_text,
+			death_log,
+			death_heatmap : DeathHeatmapDisplay::new(),
+			death_heatmap_toggle_held : false,
+
+			scheduler : Game::make_scheduler(),
+			photo_mode_toggled : false,
+			previous_player_position : Vec2::zero(),
+			previous_second_player_position : Vec2::zero(),
+			origin_rebase : OriginRebaser::new(),
+			quick_save : None,
+			rewind,
+			rewinding : false,
+			rewind_timer : 0.0,
+			ghost_recorder : GhostRecorder::new(FIXED_STEP_SECONDS),
+			ghost : None,
+			last_run : None,
+			ghost_display : GhostDisplay::new(),
+			second_player : None,
+			second_keyboard : Keyboard::new_second_player(),
+			second_gamepad : Gamepad::new(),
+			input_time : now_seconds(),
+			second_kick_meter : MeterBar::new(&palette.get(PaletteRole::KickMeter)),
+			health_meters : [MeterBar::new(&palette.get(PaletteRole::Danger)), MeterBar::new(&palette.get(PaletteRole::Danger))],
+			scenes : SceneStack::new(Scene::Title),
+			pause_menu : PauseMenu::new(&palette.get(PaletteRole::HudPrimary)),
+			menu_button_held : false,
+			title_pan : None,
+			second_view : None,
+			minimap : None,
+		};
+		game.apply_accessibility();
+		game.show_title(); // The title map is the first room, which is already loading.
+		game
+	}
+
+	/// Starts building a room from its (now loaded) file.
+	///
+	/// Big maps take too long to build in one go, so the work is queued up in steps that run a little each update (see `update_room_load()`).
+	pub fn handle_tiled_file_loaded(&mut self, _url : &str, tiled_file : SharedTiledFile) {
+		self.room_load_file = tiled_file;
+		self.room_load.clear(); // Anything left from a room that was replaced before it finished.
+		self.queue_room_load_step("reset", Game::load_room_reset);
+		self.queue_room_load_step("regions", Game::load_room_regions);
+		self.queue_room_load_step("display", |game, file| game.tiled_display.load_from(file, &game.secrets));
+		self.queue_room_load_step("geometry", |game, file| game.tiled_geometry.load_from(file));
+		let mut edges = None;
+		self.room_load.push("collision edges", move |game : &mut Game| {
+			// Neighboring shapes share edges, so only add each outside edge once.
+			let edges = edges.get_or_insert_with(|| {
+				let mut edges = EdgeSet::new();
+				for rect in game.tiled_geometry.get_collision_rects() {
+					edges.add_rect(rect);
+				}
+				for polygon in game.tiled_geometry.get_collision_polygons() {
+					edges.add_polygon(&polygon);
+				}
+				let kept = edges.get_edges();
+				debug_log!("Adding {} collision edges ({} duplicates skipped, {} shared inside edges removed)", kept.len(), edges.get_duplicate_count(), edges.get_inside_count());
+				kept.into_iter()
+			});
+			for edge in edges.by_ref().take(ROOM_LOAD_EDGE_BATCH) {
+				game.collision.add_obstacle(CircleObstacle::LineSegment(edge));
+			}
+			0 == edges.len()
+		});
+		self.queue_room_load_step("tile behaviors", Game::load_room_tile_behaviors);
+		self.queue_room_load_step("points", Game::load_room_points);
+		self.queue_room_load_step("paths", Game::load_room_paths);
+		self.queue_room_load_step("actors", Game::load_room_actors);
+	}
+
+	/// Queues a step of loading a room that works on the room's file.
+	fn queue_room_load_step(&mut self, name : &'static str, step : fn(&mut Game, &mut TiledFile)) {
+		self.room_load.push_once(name, move |game : &mut Game| {
+			let mut shared = game.room_load_file.clone();
+			let file = shared.get();
+			match file {
+				Some(mut file) => step(game, &mut file),
+				None => log(&format!("Couldn't get the room's file to load {}", name)),
+			}
+		});
+	}
+
+	/// Runs as much of loading a room as fits in this update.
+	fn update_room_load(&mut self) {
+		if self.room_load.is_empty() {
+			return;
+		}
+		// The queue is taken out while it runs, since its steps need the whole game.
+		let mut room_load = std::mem::replace(&mut self.room_load, WorkQueue::new());
+		room_load.run(self, ROOM_LOAD_BUDGET_SECONDS, &now_seconds);
+		self.room_load = room_load;
+	}
+
+	/// Shows how far along loading a room is, and wraps it up once it's done.
+	fn show_room_load_progress(&mut self, progress : f32) {
+		if progress < 1.0 {
+			self.room_load_text.set_text(&format!("Loading {}%", (100.0 * progress).floor()));
+			self.room_load_text.show();
+			return;
+		}
+		self.room_load_text.hide();
+		// The new room is ready, so it can be shown.
+		if let Some(transition) = &mut self.room_transition {
+			self.camera.set_view(&self.player.position, 1.0);
+			transition.finish_swap();
+		}
+	}
+
+	/// Clears out the last room, and places the player in the new one.
+	fn load_room_reset(&mut self, file : &mut TiledFile) {
+		// Start from scratch, in case this is replacing another room.
+		let mut collision = CollisionSystem::new();
+		collision.take_audit_from(&mut self.collision);
+		self.collision = collision;
+		self.collision.set_iteration_budget(self.tuning.collision_iteration_budget, self.tuning.iteration_escape);
+		self.tiled_geometry = TiledGeometry::new();
+		self.tiled_geometry.set_debug_palette(&Palette::new(self.accessibility.palette));
+		// Maps always load in at map coordinates. Shifts are whole chunks, so the tiles stay on the same pixels.
+		let chunk_size = file.get_tile_layers().first().map_or(1.0, |layer| layer.get_size().x / (layer.get_width().max(1) as f32) * (CHUNK_TILES as f32));
+		self.origin_rebase.reset(chunk_size);
+		self.rewind.clear();
+
+		// Go to the point the transition asked for (if any), or else the map's default one.
+		let requested = self.room_transition.as_ref().and_then(|transition| transition.get_spawn()).map(|name| name.to_string());
+		match find_spawn(file.get_points(), requested.as_deref()) {
+			Some(point) => {
+				if let Some(name) = &requested {
+					if *name != point.name {
+						log(&format!("Couldn't find spawn point {:?} in map {:?} (using {:?})", name, file.get_url(), point.name));
+					}
+				}
+				debug_log!("Spawning at {:?}: {:?}", point.name, point.position);
+				self.player.place(&point.position);
+			},
+			None => { log(&format!("Map {:?} has no points to spawn at", file.get_url())); },
+		}
+
+		// Drop everything whose condition doesn't currently hold. Doors are kept, as they check their conditions whenever they're touched.
+		let flags = &self.flags;
+		let url = file.get_url().to_string();
+		file.retain_points(|point| {
+			if DOOR_POINT_TYPE == point.r#type {
+				return true;
+			}
+			match Condition::from_point(point) {
+				Ok(condition) => condition.map_or(true, |condition| condition.holds(flags)),
+				Err(error) => {
+					log(&format!("Couldn't read the condition on {:?} in map {:?} due to: {}", point.name, url, error));
+					true
+				},
+			}
+		});
+		if self.death_heatmap.is_visible() {
+			self.death_heatmap.redraw(&self.death_log, &url, &self.origin_rebase.get_offset());
+		}
+	}
+
+	/// Loads the parts of a room that are just areas in it.
+	fn load_room_regions(&mut self, file : &mut TiledFile) {
+		self.secrets.load_from(file);
+		self.sound_zones.load_from(file);
+		self.music.load_from(file);
+		self.camera_regions.load_from(file);
+	}
+
+	/// Adds the obstacles (beyond the plain collision edges) and water from a room's geometry.
+	fn load_room_tile_behaviors(&mut self, _file : &mut TiledFile) {
+		for rect in self.tiled_geometry.get_platform_rects() {
+			add_platform_obstacle(&mut self.collision, rect);
+		}
+		self.tile_behaviors.clear();
+		for tile in self.tiled_geometry.get_behavior_tiles() {
+			let maybe_id = self.tile_behaviors.add(tile);
+			if maybe_id.is_none() {
+				log(&format!("Unknown tile behavior {:?} at ({},{}) in layer {}", tile.behavior, tile.x, tile.y, tile.layer_index));
+			}
+			let mut indices = Vec::new();
+			for rect in &tile.collision_rects {
+				indices.extend(add_rect_obstacles(&mut self.collision, rect, maybe_id));
+			}
+			for polygon in &tile.collision_polygons {
+				indices.extend(add_polygon_obstacles(&mut self.collision, polygon, maybe_id));
+			}
+			if let Some(id) = maybe_id {
+				for index in indices {
+					self.tile_behaviors.add_obstacle(id, index);
+				}
+			}
+		}
+
+		self.waters.clear();
+		for rect in self.tiled_geometry.get_water_rects() {
+			self.waters.push(Water::new(rect));
 		}
 	}
 
-	pub fn handle_tiled_file_loaded(&mut self, _url : &str, mut tiled_file : SharedTiledFile) {
-		let file = tiled_file.get().unwrap();
-		log(&format!("Point[0]: {:?}", file.get_points()[0].position));
-		self.player.position = file.get_points()[0].position;
-		self.tiled_display.load_from(&file);
-		self.tiled_geometry.load_from(&file);
+	/// Loads everything in a room that comes from its points.
+	fn load_room_points(&mut self, file : &mut TiledFile) {
+		let url = file.get_url().to_string();
+		self.quests.load_from(file);
+		self.objective_display.update_panel(&self.quests);
+		self.start_ghost_run(&url);
+
+		self.ropes.clear();
+		for point in file.get_points() {
+			if "rope" == point.name {
+				self.ropes.push(Rope::new(&point.position, &Color::new(128, 128, 128, 255)));
+			} else if "vine" == point.name {
+				self.ropes.push(Rope::new(&point.position, &Color::new(0, 160, 0, 255)));
+			}
+		}
+
+		self.doors.clear();
+		self.doors_armed = false;
+		self.switches.clear();
+		self.prompt_points.clear();
+		for point in file.get_points() {
+			if DOOR_POINT_TYPE == point.r#type {
+				match Door::from_point(point) {
+					Ok(door) => { self.doors.push(door); },
+					Err(error) => { log(&format!("Couldn't load door {:?} in map {:?} due to: {}", point.name, file.get_url(), error)); },
+				}
+			} else if SWITCH_POINT_TYPE == point.r#type {
+				match FlagSwitch::from_point(point) {
+					Ok(switch) => { self.switches.push(switch); },
+					Err(error) => { log(&format!("Couldn't load switch {:?} in map {:?} due to: {}", point.name, file.get_url(), error)); },
+				}
+			} else if PROMPT_POINT_TYPE == point.r#type {
+				match PromptPoint::from_point(point) {
+					Ok(prompt) => { self.prompt_points.push(prompt); },
+					Err(error) => { log(&format!("Couldn't load prompt {:?} in map {:?} due to: {}", point.name, file.get_url(), error)); },
+				}
+			}
+		}
+
+		for error in self.challenges.load_from(file, &self.flags) {
+			log(&format!("{} (in map {:?})", error, file.get_url()));
+		}
+		self.challenge_snapshot = None;
+
+		for error in self.ambient.load_from(file) {
+			log(&format!("{} (in map {:?})", error, file.get_url()));
+		}
+		self.dynamic.clear();
+		self.debris.clear();
+		self.debris_display.redraw(&self.debris);
+	}
+
+	/// Loads everything in a room that comes from its polylines (and saves that the player got there).
+	fn load_room_paths(&mut self, file : &mut TiledFile) {
+		self.platforms.clear();
+		self.riding_platform = None;
+		self.cutscene = None;
+		self.rail_move = None;
+		for error in self.camera_rails.load_from(file) {
+			log(&format!("{} (in map {:?})", error, file.get_url()));
+		}
+		self.title_pan = match title_pan(file) {
+			Ok(track) => track.map(|track| TrackPlayer::new(track).with_clock(Clock::Real)), // The title screen never runs the simulation.
+			Err(error) => {
+				log(&format!("Couldn't load the title camera path in map {:?} due to: {}", file.get_url(), error));
+				None
+			},
+		};
+		if Scene::Playing == self.scenes.current() {
+			storeSaveData(&self.save_slots.key(CONTINUE_SLOT_KEY), file.get_url());
+			self.save_progress();
+		}
+		for polyline in file.get_polylines() {
+			if "platform" == polyline.r#type {
+				match MovingPlatform::from_polyline(polyline, &mut self.collision) {
+					Ok(platform) => { self.platforms.push(platform); },
+					Err(error) => { log(&format!("Couldn't load platform {:?} in map {:?} due to: {}", polyline.name, file.get_url(), error)); },
+				}
+			} else if "camera" == polyline.r#type && self.cutscene.is_none() {
+				let cutscene = Track::from_polyline(polyline, "stop").and_then(|track| {
+					let clock = match polyline.get_property("clock") {
+						Some(name) => Clock::from_name(name).ok_or_else(|| format!("Unknown clock {:?}", name))?,
+						None => Clock::Simulation,
+					};
+					Ok(TrackPlayer::new(track).with_clock(clock))
+				});
+				match cutscene {
+					Ok(cutscene) => { self.cutscene = Some(cutscene); },
+					Err(error) => { log(&format!("Couldn't load camera path {:?} in map {:?} due to: {}", polyline.name, file.get_url(), error)); },
+				}
+			}
+		}
+
+	}
+
+	/// Loads everything in a room that moves around on its own.
+	fn load_room_actors(&mut self, file : &mut TiledFile) {
+		self.build_nav_grid();
+		self.pickups.load_from(file);
+		self.pickup_display.redraw(&self.pickups);
+		self.drone = Drone::new(&self.player.position);
+		self.enemies.load_from(file);
+		self.projectiles.clear();
+		self.spawn_enemies();
+
+		self.player.gravity_acceleration.y = -800.0;
+		if let Some(second) = &mut self.second_player {
+			second.place(&self.player.position);
+			second.gravity_acceleration = self.player.gravity_acceleration;
+		}
+	}
+
+	/// Starts loading the room a transition is moving to. If it can't, then the transition just reveals the current room again.
+	fn load_room(&mut self, url : &str) {
+		let mut tiled_file = SharedTiledFile::new();
+		if tiled_file.load(url).is_ok() {
+			self.tiled_file = tiled_file;
+		} else {
+			log(&format!("Couldn't start loading room {:?}", url));
+			if let Some(transition) = &mut self.room_transition {
+				transition.finish_swap();
+			}
+		}
+	}
+
+
+	/// Sets a value on an enemy definition, logging if it's not valid.
+	pub fn set_enemy_definition_property(&mut self, definition : &str, name : &str, value : &str) {
+		if let Err(error) = self.enemy_definitions.set_property(definition, name, value) {
+			log(&format!("Couldn't set {:?} on enemy definition {:?} due to: {}", name, definition, error));
+		}
+	}
+
+	/// Spawns any enemies that were waiting on the enemy definitions.
+	pub fn handle_enemy_definitions_loaded(&mut self, _url : &str) {
+		self.enemy_definitions.finish_loading();
+		self.spawn_enemies();
+	}
+
+	/// Spawns all enemies that have their definitions available, logging any that don't exist.
+	fn spawn_enemies(&mut self) {
+		for name in self.enemies.spawn_pending(&self.enemy_definitions) {
+			log(&format!("Unknown enemy definition {:?} (the spawn was skipped)", name));
+		}
+	}
+
+	/// Rebuilds the drone's navigation grid so it covers the level's geometry (plus some padding) and avoids everything solid.
+	fn build_nav_grid(&mut self) {
+		let mut bounds : Option<Bounds2> = None;
+		let mut include = |point : &Vec2| {
+			match &mut bounds {
+				Some(existing) => {
+					existing.expand_to_x(point.x);
+					existing.expand_to_y(point.y);
+				},
+				None => bounds = Some(Bounds2::from_points(point, point)),
+			}
+		};
+		include(&self.player.position);
+		for rect in self.tiled_geometry.get_collision_rects() {
+			include(&Vec2::new(rect.x_min(), rect.y_min()));
+			include(&Vec2::new(rect.x_max(), rect.y_max()));
+		}
+		for polygon in self.tiled_geometry.get_collision_polygons() {
+			for point in &polygon {
+				include(point);
+			}
+		}
+		let bounds = bounds.unwrap();
+		self.nav_grid = NavGrid::new(&Bounds2::from_points(
+			&Vec2::new(bounds.x_min() - NAV_PADDING, bounds.y_min() - NAV_PADDING),
+			&Vec2::new(bounds.x_max() + NAV_PADDING, bounds.y_max() + NAV_PADDING),
+		), NAV_CELL_SIZE);
 		for rect in self.tiled_geometry.get_collision_rects() {
-			self.collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
-				&Vec2::new(rect.x_min(), rect.y_min()),
-				&Vec2::new(rect.x_max(), rect.y_min()),
-			)));
-			self.collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
-				&Vec2::new(rect.x_min(), rect.y_max()),
-				&Vec2::new(rect.x_max(), rect.y_max()),
-			)));
-
-			self.collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
-				&Vec2::new(rect.x_min(), rect.y_min()),
-				&Vec2::new(rect.x_min(), rect.y_max()),
-			)));
-			self.collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
-				&Vec2::new(rect.x_max(), rect.y_min()),
-				&Vec2::new(rect.x_max(), rect.y_max()),
-			)));
+			self.nav_grid.block_rect(rect);
 		}
 		for polygon in self.tiled_geometry.get_collision_polygons() {
-			for index in 0..polygon.len() {
-				let mut next_index = index+1;
-				if next_index >= polygon.len() { next_index = 0; }
-				self.collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(
-					&polygon[index],
-					&polygon[next_index],
-				)));
+			self.nav_grid.block_polygon(&polygon);
+		}
+		for tile in self.tiled_geometry.get_behavior_tiles() {
+			for rect in &tile.collision_rects {
+				self.nav_grid.block_rect(rect);
+			}
+			for polygon in &tile.collision_polygons {
+				self.nav_grid.block_polygon(polygon);
 			}
 		}
+	}
 
-		self.player.gravity_acceleration.y = -800.0;
+	/// Registers all of the game's systems (everything that runs during an update).
+	fn make_scheduler() -> Scheduler<Game> {
+		let systems : [(&str, Phase, i32, System<Game>); 44] = [
+			("scenes", Phase::Input, -20, Game::update_scenes),
+			("attract_mode", Phase::Input, -10, Game::update_attract_mode),
+			("photo_mode", Phase::Input, 0, Game::update_photo_mode),
+			("gamepad_calibration", Phase::Input, 5, Game::update_gamepad_calibration),
+			("time_control", Phase::Input, 10, Game::update_time_control),
+			("rewind", Phase::Input, 20, Game::update_rewind),
+
+			("demo_replay", Phase::Simulation, -10, Game::update_demo_replay),
+			("rewind_history", Phase::Simulation, -5, Game::update_rewind_history),
+			("platforms", Phase::Simulation, 0, Game::update_platforms),
+			("player", Phase::Simulation, 10, Game::update_player),
+			("second_player", Phase::Simulation, 15, Game::update_second_player),
+			("tile_behaviors", Phase::Simulation, 20, Game::update_tile_behaviors),
+			("waters", Phase::Simulation, 30, Game::update_waters),
+			("ropes", Phase::Simulation, 40, Game::update_ropes),
+			("dynamic_hash", Phase::Simulation, 45, Game::update_dynamic_hash),
+			("enemies", Phase::Simulation, 50, Game::update_enemies),
+			("player_shots", Phase::Simulation, 55, Game::update_player_shots),
+			("projectiles", Phase::Simulation, 60, Game::update_projectiles),
+			("pickups", Phase::Simulation, 70, Game::update_pickups),
+			("secrets", Phase::Simulation, 80, Game::update_secrets),
+			("challenges", Phase::Simulation, 90, Game::update_challenges),
+
+			("damage_effects", Phase::PostSimulation, 0, Game::update_damage_effects),
+			("shadows", Phase::PostSimulation, 10, Game::update_shadows),
+			("ghost", Phase::PostSimulation, 15, Game::update_ghost),
+			("events", Phase::PostSimulation, 20, Game::update_events),
+			("switches", Phase::PostSimulation, 30, Game::update_switches),
+			("doors", Phase::PostSimulation, 40, Game::update_doors),
+			("origin_rebase", Phase::PostSimulation, 50, Game::update_origin_rebase),
+
+			("interpolated_displays", Phase::RenderSync, -10, Game::update_interpolated_displays),
+			("camera", Phase::RenderSync, 0, Game::update_camera),
+			("map_chunks", Phase::RenderSync, 5, Game::update_map_chunks),
+			("ambient", Phase::RenderSync, 7, Game::update_ambient),
+			("debris", Phase::RenderSync, 8, Game::update_debris),
+			("room_transition", Phase::RenderSync, 10, Game::update_room_transition),
+			("screen_effects", Phase::RenderSync, 20, Game::update_screen_effects),
+			("objective_markers", Phase::RenderSync, 30, Game::update_objective_markers),
+			("prompts", Phase::RenderSync, 35, Game::update_prompts),
+			("kick_meter", Phase::RenderSync, 40, Game::update_kick_meter),
+			("coop_meters", Phase::RenderSync, 45, Game::update_coop_meters),
+			("bullet_time_meter", Phase::RenderSync, 50, Game::update_bullet_time_meter),
+			("stamina_meter", Phase::RenderSync, 55, Game::update_stamina_meter),
+			("challenge_timer", Phase::RenderSync, 57, Game::update_challenge_timer),
+			("death_heatmap", Phase::RenderSync, 60, Game::update_death_heatmap),
+			("music", Phase::RenderSync, 70, Game::update_music),
+		];
+		let mut scheduler = Scheduler::new();
+		for (name, phase, priority, system) in systems.iter() {
+			crate::lean_assert!(scheduler.add(name, *phase, *priority, *system).is_ok(), "Couldn't register the {:?} system!", name);
+		}
+		scheduler
 	}
 
 	pub fn update(&mut self, elapsed_seconds : f32) {
-		self.elapsed += elapsed_seconds;
+		self.update_room_load();
+		if Scene::Playing == self.scenes.current() {
+			self.unsaved_playtime += elapsed_seconds as f64;
+		}
+		// Input that came in since the last update is split up between the steps by when it happened (about a step's worth of real time each), with the last step taking the rest.
+		// Anything not taken (like when fewer steps run than the real time covers) waits for the next update, rather than being applied between steps where nothing would see it.
+		// A press stays down (and just pressed) until a step has run with it, so even a tap that starts and ends within an update that has no steps isn't lost.
+		let input_start = self.input_time;
+		let input_end = now_seconds().max(input_start);
+		self.input_time = input_end;
+		let input_cutoff = |step : u32| (input_start + (FIXED_STEP_SECONDS as f64) * ((step + 1) as f64)).min(input_end);
+		self.process_input_events(input_cutoff(0));
+		self.run_phase(Phase::Input, &FrameTime { real_seconds : elapsed_seconds, simulated_seconds : 0.0, interpolation : 1.0 });
+
+		// The simulation always moves in fixed steps. Leftover time waits for the next update.
+		let steps = self.take_simulation_steps(elapsed_seconds);
+		let step_time = FrameTime { real_seconds : elapsed_seconds, simulated_seconds : FIXED_STEP_SECONDS, interpolation : 1.0 };
+		for step in 0..steps {
+			if 0 < step {
+				self.process_input_events(if step + 1 == steps { input_end } else { input_cutoff(step) });
+			}
+			self.elapsed += FIXED_STEP_SECONDS;
+			self.run_phase(Phase::Simulation, &step_time);
+			self.run_phase(Phase::PostSimulation, &step_time);
+			self.end_input_step();
+		}
+		// While the simulation is held still, the input phase is all that sees the presses, so they're let go after each update.
+		if 0 == steps && (self.simulation_held() || self.time_control.is_paused()) {
+			self.end_input_step();
+		}
+
+		// Then everything's drawn part way between the last two steps, so motion is smooth no matter how often this is called.
+		self.run_phase(Phase::RenderSync, &FrameTime {
+			real_seconds : elapsed_seconds,
+			simulated_seconds : (steps as f32) * FIXED_STEP_SECONDS,
+			interpolation : self.time_control.get_interpolation(),
+		});
+
+		if let Some(frame) = self.collision.finish_audit_frame() {
+			log(&frame.describe());
+		}
+	}
+
+	/// Applies the queued key and button changes (from every player's devices) that happened by some time.
+	fn process_input_events(&mut self, time : f64) {
+		self.keyboard.process_until(time);
+		self.second_keyboard.process_until(time);
+		self.gamepad.process_until(time);
+		self.second_gamepad.process_until(time);
+	}
+
+	/// Lets go of the presses (from every player's devices) that a step has now seen.
+	fn end_input_step(&mut self) {
+		self.keyboard.end_step();
+		self.second_keyboard.end_step();
+		self.gamepad.end_step();
+		self.second_gamepad.end_step();
+	}
+
+	/// Runs all the enabled systems in a phase.
+	fn run_phase(&mut self, phase : Phase, time : &FrameTime) {
+		for system in self.scheduler.get_systems(phase) {
+			system(self, time);
+		}
+	}
+
+	/// Checks whether something is holding the simulation still (so no steps will run no matter how much time passes).
+	fn simulation_held(&self) -> bool {
+		// Nothing moves in a half-built room.
+		if !self.room_load.is_empty() {
+			return true;
+		}
+		// The world is held still while the screen is covered for a room transition.
+		if self.room_transition.as_ref().map_or(false, |transition| transition.blocks_simulation()) {
+			return true;
+		}
+		// Menus hold everything still underneath them.
+		if !self.scenes.current().runs_simulation() {
+			return true;
+		}
+		// Rewinding moves time itself (see `update_rewind()`).
+		self.rewinding
+	}
+
+	/// Works out how many fixed steps the simulation should move forward this update.
+	fn take_simulation_steps(&mut self, elapsed_seconds : f32) -> u32 {
+		if self.simulation_held() {
+			return 0;
+		}
+		let mut scale = 1.0;
+		if !self.time_control.is_paused() {
+			// Bullet time slows the simulation (by feeding it less time), and runs on real time so it drains at the same rate regardless.
+			let wants_bullet_time = self.keyboard.is_down(Key::BULLET_TIME) || self.gamepad.is_down(Button::L);
+			if !wants_bullet_time {
+				self.bullet_time_locked = false;
+			}
+			let bullet_time_active = wants_bullet_time && !self.bullet_time_locked && 0.0 < self.bullet_time.drain(elapsed_seconds);
+			self.bullet_time.update(elapsed_seconds);
+			for event in self.bullet_time.take_events() {
+				if AbilityMeterEvent::Emptied == event {
+					self.bullet_time_locked = true;
+				}
+			}
+			if bullet_time_active {
+				scale = self.tuning.bullet_time_scale;
+			}
+		}
+		self.time_control.take_steps(elapsed_seconds * scale)
+	}
+
+	/// Starts the demo once the title screen has sat idle for a while, and goes back to the title screen once it's done (or someone does something).
+	fn update_attract_mode(&mut self, time : &FrameTime) {
+		let change = match self.attract_mode.update(time.real_seconds) {
+			Some(change) => change,
+			None => { return; },
+		};
+		// Whatever keys were down belong to whoever was using the menu (or to the demo).
+		self.keyboard.release_all();
+		if AttractModeChange::Started == change {
+			self.pause_menu.close();
+			self.scenes.push(Scene::Demo);
+			self.apply_scene_systems();
+			self.demo_text.show();
+			self.load_room(START_ROOM_URL); // The replay is of a new game.
+		} else {
+			self.demo_text.hide();
+			self.load_slot_flags(); // Forget anything the demo did.
+			self.show_title();
+			self.load_room(TITLE_MAP_URL);
+		}
+	}
+
+	/// Feeds the demo's key presses into the keyboard (once per step, so the replay lines up with the simulation).
+	fn update_demo_replay(&mut self, time : &FrameTime) {
+		if self.tiled_file.get().is_none() {
+			return; // Wait for the room to finish restarting.
+		}
+		for event in self.attract_mode.step(time.simulated_seconds) {
+			if event.down {
+				self.keyboard.on_down(event.key.clone());
+			} else {
+				self.keyboard.on_up(event.key.clone());
+			}
+		}
+	}
+
+	/// Turns photo mode on and off, pausing the simulation and hiding the HUD while it's on.
+	fn update_photo_mode(&mut self, time : &FrameTime) {
+		let camera_center = Vec2::new(self.camera.center.x, self.camera.center.y);
+		self.photo_mode_toggled = self.photo_mode.update(time.real_seconds, &self.keyboard, &self.gamepad, &camera_center);
+		if self.photo_mode_toggled {
+			let active = self.photo_mode.is_active();
+			self.set_hud_visible(!active);
+			self.apply_scene_systems();
+			if active {
+				self.time_control.pause();
+			} else {
+				self.time_control.resume();
+			}
+		}
+	}
+
+	/// Turns off whatever systems the active scene (and photo mode) don't want running.
+	fn apply_scene_systems(&mut self) {
+		let mut disabled = self.scenes.current().disabled_systems().to_vec();
+		if self.photo_mode.is_active() {
+			disabled.extend_from_slice(&PHOTO_MODE_DISABLED_SYSTEMS);
+		}
+		self.scheduler.apply_scene(&disabled);
+		self.music.set_ducked(Scene::Paused == self.scenes.current());
+	}
+
+	/// Moves between scenes: pausing when the menu button's pressed, running the pause menu, and starting from the title screen.
+	fn update_scenes(&mut self, time : &FrameTime) {
+		let menu_down = self.keyboard.is_down(Key::MENU) || self.gamepad.is_down(Button::START);
+		let menu_pressed = menu_down && !self.menu_button_held;
+		self.menu_button_held = menu_down;
+		match self.scenes.current() {
+			Scene::Playing => {
+				// Photo mode already holds the game still, and has its own way out.
+				if menu_pressed && !self.photo_mode.is_active() {
+					self.scenes.push(Scene::Paused);
+					self.pause_menu.open(MenuPage::Main, false, &self.accessibility);
+					self.apply_scene_systems();
+				}
+			},
+			Scene::Paused => {
+				let action = if menu_pressed {
+					Some(PauseAction::Resume)
+				} else {
+					self.pause_menu.update(time.real_seconds, &self.keyboard, &self.gamepad, &self.accessibility, &self.camera.center, &self.camera.size())
+				};
+				match action {
+					Some(PauseAction::Resume) => {
+						self.pause_menu.close();
+						self.scenes.pop();
+						self.apply_scene_systems();
+					},
+					Some(PauseAction::Quit) => {
+						self.save_progress();
+						self.show_title();
+						self.load_room(TITLE_MAP_URL);
+					},
+					Some(PauseAction::ChangeSetting(name, value)) => {
+						self.set_accessibility_setting(name, &value);
+						self.pause_menu.refresh(&self.accessibility);
+					},
+					Some(PauseAction::CalibrateGamepad) => {
+						self.start_gamepad_calibration();
+					},
+					_ => {},
+				}
+			},
+			Scene::Demo => {}, // Any input stops the demo (see `update_attract_mode()`).
+			Scene::Title => {
+				match self.pause_menu.update(time.real_seconds, &self.keyboard, &self.gamepad, &self.accessibility, &self.camera.center, &self.camera.size()) {
+					Some(PauseAction::Start) => {
+						// A new game forgets everything the last one in its slot did (the menu asks first if there's a save to lose).
+						self.save_slots.delete(&mut BrowserSaveStore, self.save_slots.get_active());
+						self.flags = WorldFlags::new();
+						self.unsaved_playtime = 0.0;
+						self.save_progress();
+						self.start_playing(START_ROOM_URL);
+					},
+					Some(PauseAction::Continue) => {
+						self.start_playing(&loadSaveData(&self.save_slots.key(CONTINUE_SLOT_KEY)));
+					},
+					Some(PauseAction::SelectSlot(slot)) => {
+						self.save_slots.set_active(&mut BrowserSaveStore, slot);
+						self.load_slot_flags();
+						self.refresh_slot_menu();
+					},
+					Some(PauseAction::CopySlot(slot)) => {
+						self.save_slots.copy(&mut BrowserSaveStore, self.save_slots.get_active(), slot);
+						self.refresh_slot_menu();
+					},
+					Some(PauseAction::DeleteSlot(slot)) => {
+						self.save_slots.delete(&mut BrowserSaveStore, slot);
+						if slot == self.save_slots.get_active() {
+							self.load_slot_flags();
+						}
+						self.refresh_slot_menu();
+					},
+					Some(PauseAction::ChangeSetting(name, value)) => {
+						self.set_accessibility_setting(name, &value);
+						self.pause_menu.refresh(&self.accessibility);
+					},
+					Some(PauseAction::CalibrateGamepad) => {
+						self.start_gamepad_calibration();
+					},
+					_ => {},
+				}
+			},
+		}
+	}
+
+	/// Goes to the title scene (dropping any scenes over it), with the title menu over the map.
+	fn show_title(&mut self) {
+		self.scenes.unwind_to(Scene::Title);
+		self.apply_scene_systems();
+		self.set_hud_visible(false);
+		self.pause_menu.open(MenuPage::Title, self.can_continue(), &self.accessibility);
+		self.refresh_slot_menu();
+	}
+
+	/// Whether the active save slot has a room to continue from.
+	fn can_continue(&self) -> bool {
+		!loadSaveData(&self.save_slots.key(CONTINUE_SLOT_KEY)).is_empty()
+	}
+
+	/// Shows what's in each save slot in the menu.
+	fn refresh_slot_menu(&mut self) {
+		let summaries = (0..SAVE_SLOT_COUNT).map(|slot| self.save_slots.summary(slot)).collect();
+		self.pause_menu.set_slots(summaries, self.save_slots.get_active(), self.can_continue(), &self.accessibility);
+	}
+
+	/// Replaces the world flags with the ones saved in the active slot.
+	fn load_slot_flags(&mut self) {
+		self.flags = WorldFlags::new();
+		if let Err(error) = self.flags.load_save_string(&loadSaveData(&self.save_slots.key(WORLD_FLAGS_SLOT_KEY))) {
+			log(&format!("Couldn't load the saved world flags due to: {}", error));
+		}
+	}
+
+	/// Saves the active slot's progress: its world flags, plus how long it's been played, how complete it is, and when.
+	fn save_progress(&mut self) {
+		let mut store = BrowserSaveStore;
+		store.store(&self.save_slots.key(WORLD_FLAGS_SLOT_KEY), &self.flags.to_save_string());
+		let played = std::mem::replace(&mut self.unsaved_playtime, 0.0);
+		self.save_slots.record(&mut store, played, completion(&self.flags), wall_clock_seconds());
+	}
+
+	/// Leaves the title scene, loading a room to play.
+	fn start_playing(&mut self, url : &str) {
+		self.pause_menu.close();
+		self.keyboard.release_all();
+		self.scenes.push(Scene::Playing);
+		self.apply_scene_systems();
+		self.set_hud_visible(true);
+		self.load_room(url);
+	}
+
+	/// Handles the debug time control keys.
+	fn update_time_control(&mut self, _time : &FrameTime) {
+		self.time_control.handle_input(&self.keyboard);
+	}
+
+	/// Rolls time back while the rewind button is held, going through the recent history at the same speed it was recorded.
+	/// The simulation doesn't run while rewinding, and picks up from wherever it stopped once the button is let go.
+	fn update_rewind(&mut self, time : &FrameTime) {
+		let held = self.keyboard.is_down(Key::REWIND) || self.gamepad.is_down(Button::Y);
+		let allowed = !self.photo_mode.is_active() && self.room_transition.is_none();
+		let rewinding = held && allowed;
+		if rewinding != self.rewinding {
+			self.rewinding = rewinding;
+			self.rewind_timer = 0.0;
+			if !rewinding {
+				// The undone steps' inputs shouldn't show up in any later anomaly capture.
+				self.anomaly_capture.replace_inputs(self.rewind.inputs().cloned());
+			}
+			self.apply_world_tint();
+		}
+		if !self.rewinding {
+			return;
+		}
+		self.rewind_timer += time.real_seconds;
+		let period = (self.rewind.get_interval() as f32) * FIXED_STEP_SECONDS;
+		while period <= self.rewind_timer {
+			self.rewind_timer -= period;
+			let snapshot = match self.rewind.pop() {
+				Some(snapshot) => snapshot,
+				None => { // Ran out of history, so hold on the oldest moment.
+					self.rewind_timer = 0.0;
+					break;
+				},
+			};
+			if let Err(error) = self.restore(&snapshot) {
+				log(&format!("Couldn't rewind: {}", error));
+				self.rewind.clear();
+			}
+		}
+	}
+
+	/// Adds to the rewind history: a snapshot every few steps, and the inputs for every step.
+	fn update_rewind_history(&mut self, time : &FrameTime) {
+		if self.rewind.is_snapshot_due() {
+			if let Some(snapshot) = self.snapshot() {
+				self.rewind.push(snapshot);
+			}
+		}
+		self.rewind.record_input(InputFrame::read(self.elapsed, time.simulated_seconds, &self.keyboard, &self.gamepad));
+	}
+
+	/// Moves the platforms, carrying along the player if they're standing on one.
+	fn update_platforms(&mut self, time : &FrameTime) {
+		let mut platform_movements = Vec::with_capacity(self.platforms.len());
+		for platform in &mut self.platforms {
+			platform_movements.push(platform.update(time.simulated_seconds, &mut self.collision));
+		}
+		if let Some(index) = self.riding_platform {
+			self.player.position += platform_movements[index];
+		}
+	}
+
+	/// Moves the player, then handles everything they touched.
+	fn update_player(&mut self, time : &FrameTime) {
+		self.previous_player_position = self.player.position;
+		self.anomaly_capture.record_input(InputFrame::read(self.elapsed, time.simulated_seconds, &self.keyboard, &self.gamepad));
+		self.player.update(self.elapsed, time.simulated_seconds, &self.keyboard, &self.gamepad, &self.collision, &self.tiled_geometry, &self.tuning);
+		for anomaly in self.player.take_anomalies() {
+			log(&format!("Physics anomaly: {:?}", anomaly));
+			self.anomaly_capture.capture(&anomaly, self.elapsed, &self.player.position, &self.player.get_velocity(), &self.collision);
+		}
+		let contacts = self.player.take_contacts();
+		let up = self.player.get_up();
+		self.riding_platform = self.platforms.iter().position(|platform| contacts.iter().any(|contact| platform.owns(contact.source) && PLATFORM_STAND_DOT <= contact.normal.dot(&up)));
+		self.tile_behaviors.dispatch_contacts(&contacts, &mut self.collision, &mut self.player);
+		let bumps = self.player.take_head_bumps();
+		self.tile_behaviors.dispatch_bumps(&bumps, &mut self.collision, &mut self.player);
+	}
+
+	/// Moves the behavior tiles forward in time, showing any changes.
+	fn update_tile_behaviors(&mut self, time : &FrameTime) {
+		let mut occupants = vec![(self.player.position, self.player.get_radius())];
+		if let Some(second) = &self.second_player {
+			occupants.push((second.position, second.get_radius()));
+		}
+		self.tile_behaviors.update(time.simulated_seconds, &occupants, &mut self.collision);
+		self.show_tile_behavior_changes();
+		let mut pickups_changed = false;
+		for effect in self.tile_behaviors.take_effects() {
+			match effect {
+				TileEffect::Drop { kind, position, up } => {
+					self.pickups.drop(&kind, &position, &(up * self.tuning.loot_pop_speed));
+					pickups_changed = true;
+				},
+				TileEffect::Shatter { bounds, up } => {
+					self.debris.shatter(&bounds, &up);
+					let center = Vec2::new((bounds.x_min() + bounds.x_max()) / 2.0, (bounds.y_min() + bounds.y_max()) / 2.0);
+					self.play_sound("blockBreak.wav", Some(&center));
+				},
+			}
+		}
+		if pickups_changed {
+			self.pickup_display.redraw(&self.pickups);
+		}
+	}
+
+	/// Redraws the behavior tiles whose visuals changed.
+	fn show_tile_behavior_changes(&mut self) {
+		for id in self.tile_behaviors.take_display_changes() {
+			let (layer_index, x, y) = self.tile_behaviors.get_location(id);
+			let state = self.tile_behaviors.get_state(id);
+			self.tiled_display.set_tile_visible(layer_index, x, y, state.visible);
+			self.tiled_display.set_tile_offset(layer_index, x, y, &state.offset);
+		}
+	}
+
+	/// Moves the water's surfaces, splashing wherever the players or projectiles went in or out.
+	fn update_waters(&mut self, time : &FrameTime) {
+		if self.waters.is_empty() {
+			return;
+		}
+		let mut movements = self.projectiles.movements(time.simulated_seconds);
+		movements.push((self.previous_player_position, self.player.position));
+		if let Some(second) = &self.second_player {
+			movements.push((self.previous_second_player_position, second.position));
+		}
+		for water in &mut self.waters {
+			water.splash_crossings(&movements, time.simulated_seconds);
+			water.update(time.simulated_seconds);
+		}
+	}
+
+	/// Moves the ropes.
+	fn update_ropes(&mut self, time : &FrameTime) {
+		for rope in &mut self.ropes {
+			if rope.update(time.simulated_seconds, &self.player.gravity_acceleration, &self.collision) {
+				let anomaly = PhysicsAnomaly {
+					kind : AnomalyKind::IterationMax,
+					details : format!("A rope point ran out of collision iterations (budget: {})", self.tuning.collision_iteration_budget),
+				};
+				log(&format!("Physics anomaly: {:?}", anomaly));
+				self.anomaly_capture.capture(&anomaly, self.elapsed, &rope.simulation.get_points()[0], &Vec2::zero(), &self.collision);
+			}
+		}
+	}
+
+	/// Rebuilds the dynamic entity hash once the players have moved, but before anything that looks for them.
+	fn update_dynamic_hash(&mut self, _time : &FrameTime) {
+		self.rebuild_dynamic_hash();
+	}
+
+	/// Refills the dynamic entity hash from wherever everything that moves is now.
+	fn rebuild_dynamic_hash(&mut self) {
+		self.dynamic.clear();
+		self.dynamic.insert(DynamicKind::Player, 0, &self.player.position, self.player.get_radius());
+		if let Some(second) = &self.second_player {
+			self.dynamic.insert(DynamicKind::Player, 1, &second.position, second.get_radius());
+		}
+		for (index, enemy) in self.enemies.get_enemies().iter().enumerate() {
+			self.dynamic.insert(DynamicKind::Enemy, index, &enemy.position, enemy.definition.radius);
+		}
+		for (index, projectile) in self.projectiles.get_projectiles().iter().enumerate() {
+			self.dynamic.insert(DynamicKind::Projectile, index, &projectile.position, projectile.config.radius);
+		}
+		for (index, item) in self.pickups.get_items().iter().enumerate() {
+			if !item.collected {
+				self.dynamic.insert(DynamicKind::Pickup, index, &item.position, DROP_RADIUS);
+			}
+		}
+	}
+
+	/// Moves the second player (if there is one) with their own inputs.
+	/// They come back next to the first player when they run out of health, or when they get too far away for the camera to show both (the split threshold).
+	fn update_second_player(&mut self, time : &FrameTime) {
+		let second = match &mut self.second_player {
+			Some(second) => second,
+			None => return,
+		};
+		if second.health.is_dead() {
+			second.health.reset();
+			second.place(&self.player.position);
+		}
+		self.previous_second_player_position = second.position;
+		second.update(self.elapsed, time.simulated_seconds, &self.second_keyboard, &self.second_gamepad, &self.collision, &self.tiled_geometry, &self.tuning);
+		for anomaly in second.take_anomalies() {
+			log(&format!("Physics anomaly (second player): {:?}", anomaly));
+		}
+		let contacts = second.take_contacts();
+		self.tile_behaviors.dispatch_contacts(&contacts, &mut self.collision, second);
+		let bumps = second.take_head_bumps();
+		self.tile_behaviors.dispatch_bumps(&bumps, &mut self.collision, second);
+	}
+
+	/// Moves the enemies (firing any shots they want), then handles the player landing on them or running into them.
+	fn update_enemies(&mut self, time : &FrameTime) {
+		let up = self.player.get_up();
+		let view = self.visible_bounds();
+		for shot in self.enemies.update(time.simulated_seconds, &self.dynamic, &self.player.gravity_acceleration, &self.collision, &view) {
+			if let Some(config) = ProjectileConfig::from_name(&shot.projectile) {
+				self.projectiles.fire(&config, &shot.position, &config.aim(&shot.position, &shot.target, &up));
+				self.play_sound("enemyShot.wav", Some(&shot.position));
+				self.events.post(GameEvent::Combat);
+			}
+		}
+		let (feet, feet_radius) = self.player.get_hitbox(HitboxKind::Feet);
+		let stomp = self.enemies.stomp(&feet, feet_radius, &self.player.get_velocity(), &up, self.tuning.stomp_damage);
+		if stomp.hit {
+			self.player.bounce(self.tuning.stomp_bounce_speed);
+			self.events.post(GameEvent::Combat);
+		}
+		for enemy in &stomp.defeated {
+			self.handle_enemy_defeated(enemy);
+		}
+		let (body, body_radius) = self.player.get_hitbox(HitboxKind::Body);
+		for hit in self.enemies.take_contact_hits(&body, body_radius) {
+			self.events.post(GameEvent::Combat);
+			if self.player.health.damage(hit.damage) {
+				self.record_death("enemy");
+			}
+			self.player.knockback.apply(&hit.source, &self.player.position, &up, self.tuning.player_knockback_speed, self.tuning.player_hit_stun_seconds);
+		}
+		if let Some(second) = &mut self.second_player {
+			let up = second.get_up();
+			let (body, body_radius) = second.get_hitbox(HitboxKind::Body);
+			for hit in self.enemies.take_contact_hits(&body, body_radius) {
+				second.health.damage(hit.damage);
+				second.knockback.apply(&hit.source, &second.position, &up, self.tuning.player_knockback_speed, self.tuning.player_hit_stun_seconds);
+			}
+		}
+		self.enemy_display.redraw(&self.enemies);
+	}
+
+	/// Lets everyone know an enemy was defeated, and throws out whatever it drops.
+	fn handle_enemy_defeated(&mut self, enemy : &Enemy) {
+		let up = self.player.get_up();
+		self.events.post(GameEvent::EnemyDefeated(enemy.definition.name.clone()));
+		self.play_sound("enemyDefeated.wav", Some(&enemy.position));
+		for drop in &enemy.definition.drops {
+			if self.random.chance(drop.chance) {
+				let across = Vec2::new(up.y, -up.x);
+				let velocity = up * self.tuning.loot_pop_speed + across * self.random.range(-self.tuning.loot_pop_spread, self.tuning.loot_pop_spread);
+				self.pickups.drop(&drop.kind, &enemy.position, &velocity);
+			}
+		}
+	}
+
+	/// Fires the player's shots (in the way they're facing) while the fire button is held, as often as the cooldown allows.
+	fn update_player_shots(&mut self, time : &FrameTime) {
+		self.shot_cooldown = (self.shot_cooldown - time.simulated_seconds).max(0.0);
+		let firing = self.keyboard.is_down(Key::FIRE) || self.gamepad.is_down(Button::B);
+		if !firing || 0.0 < self.shot_cooldown {
+			return;
+		}
+		let config = match ProjectileConfig::from_name("shot") {
+			Some(config) => config,
+			None => { return; },
+		};
+		let up = self.player.get_up();
+		let across = Vec2::new(up.y, -up.x);
+		let direction = if self.player.is_aiming_right() { across } else { across * -1.0 };
+		let (body, body_radius) = self.player.get_hitbox(HitboxKind::Body);
+		let position = body + direction * (body_radius + config.radius);
+		self.projectiles.fire_friendly(&config, &position, &direction);
+		self.shot_cooldown = self.tuning.player_shot_cooldown;
+		self.play_sound("playerShot.wav", None);
+		self.events.post(GameEvent::Combat);
+	}
+
+	/// Moves the projectiles, hurting the player with any enemy shots that reach them (and the enemies with any of the player's).
+	fn update_projectiles(&mut self, time : &FrameTime) {
+		let up = self.player.get_up();
+		let (body, body_radius) = self.player.get_hitbox(HitboxKind::Body);
+		let enemies : Vec<(Vec2, f32)> = self.enemies.get_enemies().iter().map(|enemy| (enemy.position, enemy.definition.radius)).collect();
+		let projectile_hits = self.projectiles.update(time.simulated_seconds, &self.player.gravity_acceleration, &body, body_radius, &enemies, &self.collision);
+		// Defeated enemies are removed, so go through them from last to first to keep the indices right.
+		let mut enemy_hits = Vec::new();
+		for hit in projectile_hits {
+			match hit.target {
+				ProjectileTarget::Player => {
+					if self.player.health.damage(hit.damage) {
+						self.record_death("projectile");
+					}
+					self.player.knockback.apply(&hit.source, &self.player.position, &up, self.tuning.player_knockback_speed, self.tuning.player_hit_stun_seconds);
+				},
+				ProjectileTarget::Enemy(index) => { enemy_hits.push((index, hit)); },
+			}
+		}
+		if !enemy_hits.is_empty() {
+			enemy_hits.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+			let mut defeated = Vec::new();
+			for (index, hit) in enemy_hits {
+				if defeated.contains(&index) {
+					continue; // Already gone (from an earlier shot this step).
+				}
+				self.events.post(GameEvent::Combat);
+				if let Some(enemy) = self.enemies.damage(index, hit.damage, &hit.source) {
+					defeated.push(index);
+					self.handle_enemy_defeated(&enemy);
+				}
+			}
+			self.enemy_display.redraw(&self.enemies);
+		}
+		self.projectile_display.redraw(&self.projectiles);
+	}
+
+	/// Moves the drone and the pickups, collecting any that were delivered or touched.
+	fn update_pickups(&mut self, time : &FrameTime) {
+		let drone_command = self.keyboard.just_pressed(Key::DRONE_COMMAND) || self.gamepad.just_pressed(Button::X);
+		let mut pickups_changed = false;
+		if let DroneAction::Delivered(index) = self.drone.update(time.simulated_seconds, &self.player.position, drone_command, &self.nav_grid, &self.pickups) {
+			self.pickups.collect(index, &mut self.events);
+			pickups_changed = true;
+		}
+		if let Some(index) = self.drone.get_brain().get_carried() {
+			self.pickups.set_position(index, &self.drone.get_brain().get_position());
+			pickups_changed = true;
+		}
+		pickups_changed |= self.pickups.update(time.simulated_seconds, &self.player.gravity_acceleration, &self.dynamic, &self.collision);
+		pickups_changed |= self.pickups.collect_touching(&self.player.position, PICKUP_COLLECT_RADIUS, &mut self.events);
+		if let Some(second) = &self.second_player {
+			pickups_changed |= self.pickups.collect_touching(&second.position, PICKUP_COLLECT_RADIUS, &mut self.events); // Loose pickups fly to either player, so either can collect them.
+		}
+		if pickups_changed {
+			self.pickup_display.redraw(&self.pickups);
+		}
+		self.drone.redraw(&self.secrets.get_hidden_centers());
+	}
+
+	/// Reveals any secrets the player found.
+	fn update_secrets(&mut self, time : &FrameTime) {
+		for secret in self.secrets.check_entered(&self.player.position, &mut self.events) {
+			self.tiled_display.reveal_secret(secret);
+		}
+		self.tiled_display.update(time.simulated_seconds);
+	}
+
+	/// Starts, finishes, and fails timed challenges as the player reaches their start and end points.
+	/// Finishing one drops its reward and sets its flag. Failing one puts the room back the way it was when the challenge started.
+	fn update_challenges(&mut self, time : &FrameTime) {
+		let (index, change) = match self.challenges.update(time.simulated_seconds, &self.player.position) {
+			Some(found) => found,
+			None => { return; },
+		};
+		let challenge = self.challenges.get_challenges()[index].clone();
+		match change {
+			ChallengeChange::Started => {
+				debug_log!("Started challenge {:?} ({}s)", challenge.name, challenge.seconds);
+				self.challenge_snapshot = self.snapshot();
+			},
+			ChallengeChange::Completed => {
+				debug_log!("Completed challenge {:?}", challenge.name);
+				self.challenge_snapshot = None;
+				self.pickups.drop(&challenge.reward, &challenge.end, &(self.player.get_up() * self.tuning.loot_pop_speed));
+				self.pickup_display.redraw(&self.pickups);
+				self.events.post(GameEvent::ChallengeCompleted(challenge.name));
+			},
+			ChallengeChange::Failed => {
+				debug_log!("Failed challenge {:?}", challenge.name);
+				if let Some(snapshot) = self.challenge_snapshot.take() {
+					self.reset_room(&snapshot);
+				}
+			},
+		}
+	}
+
+	/// Flashes the screen if the player got hurt.
+	fn update_damage_effects(&mut self, _time : &FrameTime) {
+		if 0.0 < self.player.health.take_damage_taken() && !self.accessibility.reduce_flashing {
+			self.screen_effects.on_damage(&self.tuning);
+		}
+	}
+
+	/// Redraws the shadows under the player and the drone.
+	fn update_shadows(&mut self, _time : &FrameTime) {
+		let casters = [
+			(self.player.position, self.player.get_radius()),
+			(self.drone.get_brain().get_position(), self.drone.get_radius()),
+		];
+		self.shadows.redraw(&casters, &self.collision, &self.player.gravity_acceleration);
+	}
+
+	/// Hands out all of the events posted during the step.
+	fn update_events(&mut self, _time : &FrameTime) {
+		self.quests.check_reached(&self.player.position, &mut self.events);
+		let mut quests_changed = false;
+		for event in self.events.drain() {
+			let sound = match &event {
+				GameEvent::PickupCollected(_) => Some("pickup.wav"),
+				GameEvent::SecretFound(_) => Some("secret.wav"),
+				GameEvent::ChallengeCompleted(_) => Some("challengeCompleted.wav"),
+				_ => None,
+			};
+			if let Some(sound) = sound {
+				self.play_sound(sound, None);
+			}
+			self.music.handle_event(&event);
+			if self.quests.handle_event(&event) {
+				quests_changed = true;
+				self.mark_ghost_split();
+			}
+			self.flags.handle_event(&event);
+		}
+		if quests_changed {
+			self.objective_display.update_panel(&self.quests);
+		}
+	}
+
+	/// Plays a sound effect as the player would hear it (see `SoundZones::mix()`). Sounds without a source are the player's own.
+	fn play_sound(&self, name : &str, source : Option<&Vec2>) {
+		play_sound(name, &self.sound_zones.mix(&self.player.position, source, &self.collision));
+	}
+
+	/// Records where the player is for the current run, and moves the ghost being raced (if any) along with it.
+	fn update_ghost(&mut self, _time : &FrameTime) {
+		if !self.ghost_recorder.is_recording() {
+			return;
+		}
+		self.ghost_recorder.record(&self.origin_rebase.to_map(&self.player.position));
+		let steps = self.ghost_recorder.get_steps();
+		if let Some(position) = self.race_ghost().and_then(|ghost| ghost.get_position(steps)) {
+			let position = self.origin_rebase.from_map(&position);
+			self.ghost_display.place(&position);
+		}
+	}
+
+	/// Presses any switches the player is touching, and saves the world flags if they changed.
+	fn update_switches(&mut self, _time : &FrameTime) {
+		for switch in &self.switches {
+			switch.press_if_touching(&self.player.position, &mut self.flags);
+		}
+		if self.flags.take_dirty() {
+			self.save_progress();
+		}
+	}
+
+	/// Starts a room transition if the player is touching an open door (and has stepped off of every door since arriving).
+	fn update_doors(&mut self, _time : &FrameTime) {
+		if self.room_transition.is_some() {
+			return;
+		}
+		let touching = self.doors.iter().find(|door| door.is_touching(&self.player.position));
+		match touching {
+			Some(door) => {
+				if self.doors_armed && door.is_open(&self.flags) {
+					self.room_transition = Some(door.start_transition(self.tuning.room_transition_seconds));
+				}
+			},
+			None => { self.doors_armed = true; },
+		}
+	}
+
+	/// Moves the whole world back toward the origin once the player gets too far from it.
+	/// Runs after everything else in the step, so nothing sees half of the world moved.
+	fn update_origin_rebase(&mut self, _time : &FrameTime) {
+		let shift = match self.origin_rebase.check(&self.player.position) {
+			Some(shift) => shift,
+			None => return,
+		};
+		self.shift_world(&shift);
+		let offset = self.origin_rebase.get_offset();
+		debug_log!("Moved the world origin to ({}, {}) in map coordinates", offset.x, offset.y);
+	}
+
+	/// Moves everything in the world by some amount (see `origin_rebase`).
+	fn shift_world(&mut self, shift : &Vec2) {
+		self.player.shift_origin(shift);
+		self.previous_player_position.shift_origin(shift);
+		self.previous_second_player_position.shift_origin(shift);
+		self.collision.shift_origin(shift);
+		self.tiled_display.shift_origin(shift);
+		self.tiled_geometry.shift_origin(shift);
+		self.tile_behaviors.shift_origin(shift);
+		self.ropes.shift_origin(shift);
+		self.waters.shift_origin(shift);
+		self.platforms.shift_origin(shift);
+		self.cutscene.shift_origin(shift);
+		self.camera_rails.shift_origin(shift);
+		self.rail_move.shift_origin(shift);
+		self.title_pan.shift_origin(shift);
+		self.nav_grid.shift_origin(shift);
+		self.pickups.shift_origin(shift);
+		self.drone.shift_origin(shift);
+		self.secrets.shift_origin(shift);
+		self.sound_zones.shift_origin(shift);
+		self.challenges.shift_origin(shift);
+		self.ambient.shift_origin(shift);
+		self.debris.shift_origin(shift);
+		self.camera_regions.shift_origin(shift);
+		self.enemies.shift_origin(shift);
+		self.projectiles.shift_origin(shift);
+		self.doors.shift_origin(shift);
+		self.switches.shift_origin(shift);
+		self.prompt_points.shift_origin(shift);
+		self.quests.shift_origin(shift);
+		self.photo_mode.shift_origin(shift);
+		self.camera.shift_origin(shift);
+		self.ghost_display.shift_origin(shift);
+		self.second_player.shift_origin(shift);
+		self.rebuild_dynamic_hash(); // Its cells depend on where things are, so it's simpler to refill it than shift it.
+
+		// These are only redrawn when something changes, so redo them now.
+		self.enemy_display.redraw(&self.enemies);
+		self.projectile_display.redraw(&self.projectiles);
+		self.pickup_display.redraw(&self.pickups);
+		self.ambient_display.redraw(&self.ambient);
+		self.debris_display.redraw(&self.debris);
+		self.drone.redraw(&self.secrets.get_hidden_centers());
+		if self.death_heatmap.is_visible() {
+			self.redraw_death_heatmap();
+		}
+	}
+
+	/// Moves everything that's drawn with a transform to part way between their last two steps.
+	fn update_interpolated_displays(&mut self, time : &FrameTime) {
+		self.player.sync_display(time.interpolation);
+		if let Some(second) = &mut self.second_player {
+			second.sync_display(time.interpolation);
+		}
+		self.drone.sync_display(time.interpolation);
+		self.ghost_display.sync_display(time.interpolation);
+		for platform in &mut self.platforms {
+			platform.sync_display(time.interpolation);
+		}
+	}
+
+	/// Points the camera at whatever it should be showing: the title pan, the photo mode view, the cutscene, a camera rail, or the player (or both players in co-op, splitting the screen if they're too far apart).
+	/// It follows where the player is drawn (not where they are), so it moves as smoothly as they do. The minimap does too.
+	fn update_camera(&mut self, time : &FrameTime) {
+		let player_position = self.player.get_display_position(time.interpolation);
+		let mut split = false;
+		let mut following = false;
+		if Scene::Title == self.scenes.current() {
+			if let Some(center) = self.title_pan.as_mut().and_then(|pan| pan.tick(time)) {
+				self.camera.set_view(&center, 1.0);
+			}
+		} else if self.photo_mode.is_active() {
+			self.camera.set_view(&self.photo_mode.get_center(), self.photo_mode.get_zoom());
+		} else if let Some(cutscene) = &mut self.cutscene {
+			if let Some(center) = cutscene.tick(time) {
+				self.camera.set_view(&center, 1.0);
+			}
+			if cutscene.is_done() {
+				self.cutscene = None;
+				self.camera.set_view(&player_position, 1.0);
+			}
+		} else if let Some(rail_move) = &mut self.rail_move {
+			let center = rail_move.update(time.simulated_seconds, &player_position);
+			self.camera.set_view(&center, 1.0);
+			if rail_move.is_done() {
+				self.rail_move = None;
+			}
+		} else if let Some(second) = &self.second_player {
+			let screen = self.camera.screen_size();
+			let scale = self.camera.get_pixel_scale();
+			let second_position = second.get_display_position(time.interpolation);
+			let framing = frame_players(&player_position, &second_position, &Vec2::new(screen.x / scale, screen.y / scale), 1.0);
+			split = framing.split;
+			if split {
+				self.camera.set_viewport(&SPLIT_FIRST_VIEW);
+				self.camera.set_view(&player_position, 1.0);
+				self.second_view.get_or_insert_with(|| RenderView::new(SECOND_PLAYER_VIEW, &SPLIT_SECOND_VIEW)).update(&second_position, scale, &screen);
+			} else {
+				self.camera.set_viewport(&ViewRect::FULL);
+				self.camera.set_view(&framing.center, framing.zoom);
+			}
+		} else {
+			if self.photo_mode_toggled {
+				self.camera.set_view(&player_position, 1.0);
+			}
+			self.camera.track_position(&player_position);
+			// Camera regions override the default following (easing between each other as the player moves around).
+			let size = self.camera.size();
+			let zoom = self.camera.get_zoom();
+			let followed = self.camera.snapshot().center;
+			if let Some(center) = self.camera_regions.update(time.simulated_seconds, &player_position, &followed, &Vec2::new(size.x / zoom, size.y / zoom)) {
+				self.camera.set_view(&center, zoom);
+			}
+			following = true;
+		}
+		if !following {
+			// The camera was put somewhere else, so the regions shouldn't ease away from wherever they last had it.
+			self.camera_regions.snap();
+		}
+		if !split && self.second_view.is_some() {
+			self.second_view = None;
+			self.camera.set_viewport(&ViewRect::FULL);
+		}
+		let screen = self.camera.screen_size();
+		if let Some(minimap) = &mut self.minimap {
+			minimap.update(&player_position, MINIMAP_ZOOM, &screen);
+		}
+	}
+
+	/// Builds the parts of the map that are coming into view (and drops the ones far out of it).
+	fn update_map_chunks(&mut self, time : &FrameTime) {
+		let view = self.visible_bounds();
+		self.tiled_display.update_view(&view, time.real_seconds);
+	}
+
+	/// Gets the area covering everything that's on screen (in every view, including the minimap).
+	fn visible_bounds(&self) -> Bounds2 {
+		let mut view = self.camera.bounds();
+		for extra in self.second_view.iter().chain(self.minimap.iter()) {
+			let bounds = extra.bounds();
+			view.expand_to_x(bounds.x_min());
+			view.expand_to_x(bounds.x_max());
+			view.expand_to_y(bounds.y_min());
+			view.expand_to_y(bounds.y_max());
+		}
+		view
+	}
+
+	/// Moves the ambient decorations forward (on their clock, which is simulated time unless changed, so they stop when the game's paused) around what the camera can see.
+	/// They're purely decorative, so they run here instead of in the simulation and aren't part of snapshots or replays.
+	fn update_ambient(&mut self, time : &FrameTime) {
+		let seconds = time.seconds(self.ambient.get_clock());
+		if 0.0 >= seconds {
+			return;
+		}
+		self.ambient.update(seconds, &self.dynamic, &self.player.gravity_acceleration, &self.camera.bounds());
+		self.ambient_display.redraw(&self.ambient);
+	}
+
+	/// Moves the pieces of broken tiles forward (on their clock, like the ambient decorations).
+	fn update_debris(&mut self, time : &FrameTime) {
+		let seconds = time.seconds(self.debris.get_clock());
+		if 0.0 >= seconds || self.debris.get_pieces().is_empty() {
+			return;
+		}
+		self.debris.update(seconds, &self.player.gravity_acceleration);
+		self.debris_display.redraw(&self.debris);
+	}
+
+	/// Fades the music's intensity stem in or out (on simulated time, so combat doesn't wear off while paused), and sends on any music changes.
+	fn update_music(&mut self, time : &FrameTime) {
+		self.music.update(time.simulated_seconds);
+		for command in self.music.take_commands() {
+			send_music_command(&command);
+		}
+	}
+
+	/// Moves the room transition forward (on real time), starting the swap once the screen is covered.
+	/// This runs after the camera moves so the cover is drawn where the screen actually is.
+	fn update_room_transition(&mut self, time : &FrameTime) {
+		let mut swap_url = None;
+		let mut transition_cover = None;
+		if let Some(transition) = &mut self.room_transition {
+			let coverage = transition.update(time.real_seconds);
+			if transition.take_swap_request() {
+				swap_url = Some(transition.get_target_url().to_string());
+			}
+			if !transition.is_done() {
+				transition_cover = Some((transition.get_style(), coverage));
+			}
+		}
+		if let Some(url) = swap_url {
+			self.load_room(&url);
+		}
+		match transition_cover {
+			Some((style, coverage)) => { self.screen_effects.set_transition(Some(style), coverage); },
+			None => {
+				self.room_transition = None;
+				self.screen_effects.set_transition(None, 0.0);
+			},
+		}
+	}
+
+	/// Keeps the screen effects covering the screen.
+	fn update_screen_effects(&mut self, time : &FrameTime) {
+		self.screen_effects.update(time, self.player.health.get_fraction(), &self.tuning, &self.camera.center, &self.camera.size());
+	}
+
+	/// Moves the markers pointing at off-screen objectives.
+	fn update_objective_markers(&mut self, _time : &FrameTime) {
+		self.objective_display.update_markers(&self.quests, &self.camera.bounds());
+	}
+
+	/// Shows the kick charge above the player.
+	fn update_kick_meter(&mut self, time : &FrameTime) {
+		self.kick_meter.update(self.player.get_kick_charge(self.elapsed, &self.tuning), &(self.player.get_display_position(time.interpolation) + Vec2::new(0.0, KICK_METER_HEIGHT)));
+	}
+
+	/// Shows the second player's kick charge, and each player's health, above them (only in co-op).
+	fn update_coop_meters(&mut self, time : &FrameTime) {
+		let second = match &self.second_player {
+			Some(second) => second,
+			None => return,
+		};
+		let first_position = self.player.get_display_position(time.interpolation);
+		let second_position = second.get_display_position(time.interpolation);
+		self.second_kick_meter.update(second.get_kick_charge(self.elapsed, &self.tuning), &(second_position + Vec2::new(0.0, KICK_METER_HEIGHT)));
+		self.health_meters[0].update(Some(self.player.health.get_fraction()), &(first_position + Vec2::new(0.0, HEALTH_METER_HEIGHT)));
+		self.health_meters[1].update(Some(second.health.get_fraction()), &(second_position + Vec2::new(0.0, HEALTH_METER_HEIGHT)));
+	}
+
+	/// Shows the button prompts the player is near, with glyphs for whichever device they're using.
+	fn update_prompts(&mut self, time : &FrameTime) {
+		let player_position = self.player.get_display_position(time.interpolation);
+		let camera = &self.camera;
+		let ui_scale = self.accessibility.ui_scale;
+		let to_view_offset = |position : &Vec2| camera.to_view_offset(position) * (1.0 / ui_scale);
+		for prompt in &mut self.prompt_points {
+			prompt.update(&player_position, self.input_device, &to_view_offset);
+		}
+	}
+
+	/// Shows the bullet time meter above the player.
+	fn update_bullet_time_meter(&mut self, time : &FrameTime) {
+		self.bullet_time_meter.update(self.bullet_time.get_display_fraction(), &(self.player.get_display_position(time.interpolation) + Vec2::new(0.0, BULLET_TIME_METER_HEIGHT)));
+	}
+
+	/// Shows how long the running challenge has left (hidden when none is running).
+	fn update_challenge_timer(&mut self, _time : &FrameTime) {
+		match self.challenges.get_remaining() {
+			Some(remaining) => {
+				self.challenge_timer.set_text(&format!("{:.1}s", remaining.max(0.0)));
+				self.challenge_timer.show();
+			},
+			None => self.challenge_timer.hide(),
+		}
+	}
+
+	/// Shows the stamina meter below the player, in the danger color while they're exhausted.
+	fn update_stamina_meter(&mut self, time : &FrameTime) {
+		let exhausted = self.player.stamina.is_exhausted();
+		if exhausted != self.stamina_meter_exhausted {
+			self.stamina_meter_exhausted = exhausted;
+			self.apply_stamina_meter_color();
+		}
+		self.stamina_meter.update(self.player.stamina.get_meter().get_display_fraction(), &(self.player.get_display_position(time.interpolation) + Vec2::new(0.0, STAMINA_METER_HEIGHT)));
+	}
+
+	/// Colors the stamina meter for the current palette (and whether the player is exhausted).
+	fn apply_stamina_meter_color(&mut self) {
+		let role = if self.stamina_meter_exhausted { PaletteRole::Danger } else { PaletteRole::StaminaMeter };
+		self.stamina_meter.set_color(&Palette::new(self.accessibility.palette).get(role));
+	}
+
+	/// Adds where the player is to the death log.
+	fn record_death(&mut self, cause : &str) {
+		if Scene::Demo == self.scenes.current() {
+			return; // Only real deaths are worth keeping.
+		}
+		let room = self.tiled_file.get().map_or(String::new(), |file| file.get_url().to_string());
+		self.death_log.record(&room, &self.origin_rebase.to_map(&self.player.position).to_vec2(), cause);
+	}
+
+	/// Saves any new deaths, and handles the debug key that shows them as a heatmap over the current room.
+	fn update_death_heatmap(&mut self, _time : &FrameTime) {
+		let toggle_down = self.keyboard.is_down(Key::DEATH_HEATMAP);
+		let toggled = toggle_down && !self.death_heatmap_toggle_held;
+		self.death_heatmap_toggle_held = toggle_down;
+		let changed = self.death_log.take_dirty();
+		if changed {
+			storeSaveData(DEATH_LOG_SAVE_KEY, &self.death_log.to_save_string());
+		}
+		if toggled && !cfg!(feature = "release-lean") { // The heatmap is a debug overlay, so lean builds never show it (and drop the code that draws it).
+			self.death_heatmap.set_visible(!self.death_heatmap.is_visible());
+		}
+		if self.death_heatmap.is_visible() && (toggled || changed) {
+			self.redraw_death_heatmap();
+		}
+	}
+
+	/// Redraws the death heatmap for the current room.
+	fn redraw_death_heatmap(&mut self) {
+		if let Some(file) = self.tiled_file.get() {
+			self.death_heatmap.redraw(&self.death_log, file.get_url(), &self.origin_rebase.get_offset());
+		}
+	}
+
+	/// Starts or stops auditing collision queries (for debugging). Any frame where they take longer than the budget (in milliseconds) gets logged.
+	pub fn set_collision_audit(&mut self, enabled : bool, budget_milliseconds : f64) {
+		self.collision.set_audit(enabled, budget_milliseconds / 1000.0);
+	}
 
-		self.player.update(self.elapsed, elapsed_seconds, &self.keyboard, &self.gamepad, &self.collision, &self.tiled_geometry);
-		self.camera.track_position(&self.player.position);
+	/// Gets how full each entity pool is (as a JSON array, for debugging).
+	pub fn get_pool_stats(&self) -> String {
+		let stats = [self.projectiles.pool_stats(), self.ambient.pool_stats(), self.debris.pool_stats()];
+		serde_json::to_string(&stats).unwrap_or_else(|error| crate::fatal!("Couldn't write out pool stats: {}", error))
+	}
+
+	/// Keeps a snapshot of the simulation in memory (for debugging).
+	pub fn quick_save(&mut self) {
+		self.quick_save = self.snapshot();
+		if self.quick_save.is_none() {
+			log("Couldn't quick save, as the room is still loading");
+		}
+	}
+
+	/// Goes back to the last quick save (for debugging).
+	pub fn quick_load(&mut self) {
+		let snapshot = match self.quick_save.take() {
+			Some(snapshot) => snapshot,
+			None => { log("Nothing has been quick saved"); return; },
+		};
+		if let Err(error) = self.restore(&snapshot) {
+			log(&format!("Couldn't quick load: {}", error));
+		}
+		self.quick_save = Some(snapshot);
+	}
+
+	/// Forgets every recorded death (for debugging).
+	pub fn clear_death_log(&mut self) {
+		self.death_log.clear();
+	}
+
+	/// Shows or hides everything drawn over the game world.
+	fn set_hud_visible(&mut self, visible : bool) {
+		if visible {
+			self.description.show();
+		} else {
+			self.description.hide();
+		}
+		self.screen_effects.set_visible(visible);
+		self.kick_meter.set_visible(visible);
+		self.bullet_time_meter.set_visible(visible);
+		self.stamina_meter.set_visible(visible);
+		self.second_kick_meter.set_visible(visible);
+		for meter in &mut self.health_meters {
+			meter.set_visible(visible);
+		}
+		self.objective_display.set_visible(visible, &self.quests);
+		if !visible {
+			for prompt in &mut self.prompt_points {
+				prompt.hide();
+			}
+			self.challenge_timer.hide();
+		}
+	}
+
+	/// Changes an accessibility setting from text (see `AccessibilitySettings::set()`), saving and applying it. Logs if it's not valid.
+	pub fn set_accessibility_setting(&mut self, name : &str, value : &str) {
+		if let Err(error) = self.accessibility.set(name, value) {
+			log(&format!("Couldn't change the accessibility settings due to: {}", error));
+			return;
+		}
+		storeSaveData(ACCESSIBILITY_SAVE_KEY, &self.accessibility.to_save_string());
+		self.apply_accessibility();
+	}
+
+	/// Gets the accessibility settings.
+	pub fn get_accessibility<'a>(&'a self) -> &'a AccessibilitySettings {
+		&self.accessibility
+	}
+
+	/// Changes a gamepad calibration setting from text (see `GamepadCalibration::set()`), saving and applying it. Logs if it's not valid.
+	pub fn set_gamepad_setting(&mut self, name : &str, value : &str) {
+		let mut calibration = self.gamepad.get_calibration().clone();
+		if let Err(error) = calibration.set(name, value) {
+			log(&format!("Couldn't change the gamepad calibration due to: {}", error));
+			return;
+		}
+		self.apply_gamepad_calibration(&calibration);
+	}
+
+	/// Gets the first gamepad's calibration.
+	pub fn get_gamepad_calibration<'a>(&'a self) -> &'a GamepadCalibration {
+		self.gamepad.get_calibration()
+	}
+
+	/// Saves a gamepad calibration and uses it for the first gamepad.
+	fn apply_gamepad_calibration(&mut self, calibration : &GamepadCalibration) {
+		storeSaveData(GAMEPAD_CALIBRATION_SAVE_KEY, &calibration.to_save_string());
+		self.gamepad.set_calibration(calibration);
+	}
+
+	/// Starts measuring where the first gamepad's sticks rest.
+	fn start_gamepad_calibration(&mut self) {
+		self.calibrator = Some(GamepadCalibrator::new());
+		self.calibration_text.set_text("Calibrating: leave the sticks alone");
+		self.calibration_text.show();
+	}
+
+	/// Watches the first gamepad's sticks while calibrating, and saves where they rest once it's sure.
+	fn update_gamepad_calibration(&mut self, time : &FrameTime) {
+		let calibrator = match &mut self.calibrator {
+			Some(calibrator) => calibrator,
+			None => { return; },
+		};
+		match calibrator.update(time.real_seconds, &self.gamepad.get_raw_stick_axes()) {
+			CalibrationProgress::Measuring(progress) => {
+				self.calibration_text.set_text(&format!("Calibrating: leave the sticks alone {}%", (100.0 * progress).floor()));
+			},
+			CalibrationProgress::Moved => {
+				self.calibration_text.set_text("Calibrating: leave the sticks alone");
+			},
+			CalibrationProgress::Done(offsets) => {
+				let mut calibration = self.gamepad.get_calibration().clone();
+				for (axis, offset) in calibration.axes.iter_mut().zip(offsets.iter()) {
+					axis.offset = *offset;
+				}
+				self.apply_gamepad_calibration(&calibration);
+				self.calibrator = None;
+				self.calibration_text.hide();
+			},
+		}
+	}
+
+	/// Tints the tiles and enemies: washed out while rewinding, or otherwise however the accessibility settings want.
+	fn apply_world_tint(&mut self) {
+		if self.rewinding {
+			self.tiled_display.set_tint(&REWIND_TINT, REWIND_TINT_AMOUNT);
+			self.enemy_display.set_tint(&REWIND_TINT, REWIND_TINT_AMOUNT);
+		} else if self.accessibility.high_contrast {
+			self.tiled_display.set_tint(&HIGH_CONTRAST_TILE_TINT, HIGH_CONTRAST_TILE_AMOUNT);
+			self.enemy_display.set_tint(&HIGH_CONTRAST_ENEMY_TINT, HIGH_CONTRAST_ENEMY_AMOUNT);
+		} else {
+			self.tiled_display.set_tint(&HIGH_CONTRAST_TILE_TINT, 0.0);
+			self.enemy_display.set_tint(&HIGH_CONTRAST_ENEMY_TINT, 0.0);
+		}
+	}
+
+	/// Pushes the accessibility settings out to everything they affect.
+	fn apply_accessibility(&mut self) {
+		let settings = &self.accessibility;
+		self.player.set_flashing(!settings.reduce_flashing);
+		self.enemy_display.set_flashing(!settings.reduce_flashing);
+		self.player.set_track_hold_toggle(settings.hold_toggle);
+		if let Some(second) = &mut self.second_player {
+			second.set_flashing(!settings.reduce_flashing);
+			second.set_track_hold_toggle(settings.hold_toggle);
+		}
+
+		let palette = Palette::new(settings.palette);
+		self.description.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.objective_display.set_color(&palette.get(PaletteRole::Objective));
+		self.pickup_display.set_color(&palette.get(PaletteRole::Pickup));
+		self.kick_meter.set_color(&palette.get(PaletteRole::KickMeter));
+		self.second_kick_meter.set_color(&palette.get(PaletteRole::KickMeter));
+		for meter in &mut self.health_meters {
+			meter.set_color(&palette.get(PaletteRole::Danger));
+		}
+		self.bullet_time_meter.set_color(&palette.get(PaletteRole::BulletTimeMeter));
+		self.apply_stamina_meter_color();
+		self.screen_effects.set_vignette_color(&palette.get(PaletteRole::Danger));
+		self.enemy_display.set_shape_color(&palette.get(PaletteRole::Danger));
+		self.tiled_geometry.set_debug_palette(&palette);
+		self.ghost_display.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.challenge_timer.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.room_load_text.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.calibration_text.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.pause_menu.set_color(&palette.get(PaletteRole::HudPrimary));
+		self.apply_world_tint();
+		self.apply_ui_scale();
+	}
+
+	/// Scales the HUD and its text to match the virtual resolution and the UI scale setting. Has to be redone whenever either of those (or the screen size) changes.
+	fn apply_ui_scale(&mut self) {
+		let layout = self.camera.screen_layout();
+		let settings = &self.accessibility;
+		DisplayText::set_layout_for_all(&layout.rect, settings.ui_pixel_scale(layout.scale));
+		DisplayText::set_scale_for_all(settings.text_pixel_scale(layout.scale));
+		// The meters are drawn in the world, so they already grow with the virtual resolution.
+		self.kick_meter.set_scale(settings.ui_scale);
+		self.bullet_time_meter.set_scale(settings.ui_scale);
+		self.stamina_meter.set_scale(settings.ui_scale);
+		self.second_kick_meter.set_scale(settings.ui_scale);
+		for meter in &mut self.health_meters {
+			meter.set_scale(settings.ui_scale);
+		}
+	}
+
+	/// Sets a world flag from text ("true", "false", or an integer), logging if the value isn't valid.
+	pub fn set_world_flag(&mut self, name : &str, value : &str) {
+		match FlagValue::parse(value) {
+			Some(value) => { self.flags.set(name, value); },
+			None => { log(&format!("Couldn't set world flag {:?} to {:?} (it's not true, false, or an integer)", name, value)); },
+		}
+	}
+
+	/// Sends the camera along a camera rail (by name) over some seconds with some easing (see `Easing::from_name()`), after which it eases back to the player.
+	/// Returns whether it started.
+	pub fn play_camera_rail(&mut self, name : &str, seconds : f32, easing : &str) -> bool {
+		let easing = match Easing::from_name(easing) {
+			Some(easing) => easing,
+			None => {
+				log(&format!("Couldn't play camera rail {:?} since there's no easing named {:?}", name, easing));
+				return false;
+			},
+		};
+		match self.camera_rails.get(name) {
+			Some(rail) => {
+				self.rail_move = Some(RailMove::new(rail, seconds, easing));
+				true
+			},
+			None => {
+				log(&format!("Couldn't play camera rail {:?} since there's none by that name", name));
+				false
+			},
+		}
+	}
+
+	/// Gets the world flags.
+	pub fn get_world_flags<'a>(&'a self) -> &'a WorldFlags {
+		&self.flags
+	}
+
+	/// Describes a tile as JSON (see `map_queries::query_tile()`). Gives "null" while the map is loading.
+	pub fn query_tile(&mut self, x : usize, y : usize, layer : usize) -> String {
+		match self.tiled_file.get() {
+			Some(file) => map_queries::query_tile(&file, x, y, layer),
+			None => String::from("null"),
+		}
+	}
+
+	/// Describes the collision obstacles touching a position as JSON (see `map_queries::query_collision_at()`). The position is in map coordinates.
+	pub fn query_collision_at(&self, x : f64, y : f64) -> String {
+		let position = self.origin_rebase.from_map(&WorldVec2::new(x as WorldScalar, y as WorldScalar));
+		map_queries::query_collision_at(&self.collision, &position)
+	}
+
+	/// Describes the current map as JSON (see `map_queries::get_map_metadata()`). Gives "null" while the map is loading.
+	pub fn get_map_metadata(&mut self) -> String {
+		match self.tiled_file.get() {
+			Some(file) => map_queries::get_map_metadata(&file, &self.collision),
+			None => String::from("null"),
+		}
+	}
+
+	/// Gets the debug time controls (for pausing and stepping the simulation).
+	pub fn get_time_control<'a>(&'a mut self) -> &'a mut TimeControl {
+		&mut self.time_control
+	}
+
+	/// Gets the physics anomaly captures.
+	pub fn get_anomaly_capture<'a>(&'a mut self) -> &'a mut AnomalyCapture {
+		&mut self.anomaly_capture
+	}
+
+	/// Captures the state of the simulation (see `GameSnapshot`). Gives None while the room is loading.
+	pub fn snapshot(&mut self) -> Option<GameSnapshot> {
+		let room = self.tiled_file.get()?.get_url().to_string();
+		Some(GameSnapshot {
+			room,
+			elapsed : self.elapsed,
+			origin : self.origin_rebase.get_offset(),
+			player : self.player.snapshot(),
+			obstacles : self.collision.snapshot(),
+			tile_behaviors : self.tile_behaviors.snapshot(),
+			camera : self.camera.snapshot(),
+			enemies : self.enemies.get_enemies().clone(),
+			projectiles : self.projectiles.get_projectiles().to_vec(),
+			pickups : self.pickups.get_items().clone(),
+			platforms : self.platforms.iter().map(|platform| platform.snapshot()).collect(),
+			random : self.random.clone(),
+		})
+	}
+
+	/// Puts the simulation back the way a snapshot had it. The snapshot has to be from the room that's currently loaded.
+	pub fn restore(&mut self, snapshot : &GameSnapshot) -> Result<(), String> {
+		let room = match self.tiled_file.get() {
+			Some(file) => file.get_url().to_string(),
+			None => { return Err(String::from("The room is still loading")); },
+		};
+		if room != snapshot.room {
+			return Err(format!("The snapshot is from room {:?}, but {:?} is loaded", snapshot.room, room));
+		}
+		if snapshot.platforms.len() != self.platforms.len() {
+			return Err(format!("The snapshot has {} platforms, but the room has {}", snapshot.platforms.len(), self.platforms.len()));
+		}
+		if snapshot.tile_behaviors.len() != self.tile_behaviors.count() {
+			return Err(format!("The snapshot has {} behavior tiles, but the room has {}", snapshot.tile_behaviors.len(), self.tile_behaviors.count()));
+		}
+		// Line the world back up with the snapshot's origin first, so everything that isn't part of the snapshot (like the map itself) ends up in the same place.
+		let origin = self.origin_rebase.get_offset();
+		if origin != snapshot.origin {
+			self.shift_world(&origin.relative_to(&snapshot.origin));
+			self.origin_rebase.set_offset(&snapshot.origin);
+		}
+		self.elapsed = snapshot.elapsed;
+		self.ghost_recorder.rewind_to(self.elapsed);
+		self.player.restore(&snapshot.player);
+		self.previous_player_position = self.player.position;
+		self.camera.restore(&snapshot.camera);
+		self.pickups.restore(&snapshot.pickups);
+		self.random = snapshot.random.clone();
+		self.pickup_display.redraw(&self.pickups);
+		self.restore_movables(snapshot);
+		Ok(())
+	}
+
+	/// Puts everything in the room that moves on its own (enemies, projectiles, platforms, and behavior tiles like crumbling ones) back the way a snapshot had them. Assumes the world is lined up with the snapshot's origin, and that the snapshot has the same platforms and behavior tiles as the room.
+	fn restore_movables(&mut self, snapshot : &GameSnapshot) {
+		self.collision.restore(&snapshot.obstacles);
+		// After the obstacles, so the behavior tiles get the final say on which of theirs are solid.
+		if let Err(message) = self.tile_behaviors.restore(&snapshot.tile_behaviors, &mut self.collision) {
+			log(&format!("Couldn't restore the behavior tiles: {}", message));
+		}
+		self.show_tile_behavior_changes();
+		self.enemies.restore(&snapshot.enemies);
+		self.projectiles.restore(&snapshot.projectiles);
+		for (platform, platform_snapshot) in self.platforms.iter_mut().zip(snapshot.platforms.iter()) {
+			platform.restore(platform_snapshot);
+		}
+		self.riding_platform = None;
+		self.enemy_display.redraw(&self.enemies);
+		self.projectile_display.redraw(&self.projectiles);
+	}
+
+	/// Puts the room's moving parts back the way a snapshot of the current room had them, leaving the player (and what they've collected) alone.
+	fn reset_room(&mut self, snapshot : &GameSnapshot) {
+		if snapshot.platforms.len() != self.platforms.len() {
+			log(&format!("Couldn't reset the room, as the snapshot has {} platforms but the room has {}", snapshot.platforms.len(), self.platforms.len()));
+			return;
+		}
+		if snapshot.tile_behaviors.len() != self.tile_behaviors.count() {
+			log(&format!("Couldn't reset the room, as the snapshot has {} behavior tiles but the room has {}", snapshot.tile_behaviors.len(), self.tile_behaviors.count()));
+			return;
+		}
+		// The world may have been rebased since, so line it up with the snapshot while restoring, then move it back.
+		let origin = self.origin_rebase.get_offset();
+		let shifted = origin != snapshot.origin;
+		if shifted {
+			self.shift_world(&origin.relative_to(&snapshot.origin));
+		}
+		self.restore_movables(snapshot);
+		if shifted {
+			self.shift_world(&snapshot.origin.relative_to(&origin));
+		}
+	}
+
+	/// Gets the ghost being raced, if it's for the room the current run is in.
+	fn race_ghost(&self) -> Option<&GhostRun> {
+		let room = self.ghost_recorder.get_room()?;
+		self.ghost.as_ref().filter(|ghost| room == ghost.room)
+	}
+
+	/// Starts recording a run through a freshly loaded room, and puts the ghost (if there's one for it) at the start.
+	/// Rooms without objectives can't be finished, so they aren't recorded.
+	fn start_ghost_run(&mut self, room : &str) {
+		if self.quests.get_objectives().is_empty() {
+			self.ghost_recorder.stop();
+		} else {
+			self.ghost_recorder.start(room, self.elapsed);
+		}
+		self.show_race_ghost();
+	}
+
+	/// Shows the ghost being raced where it should be for the current run (or hides it if there isn't one).
+	fn show_race_ghost(&mut self) {
+		let steps = self.ghost_recorder.get_steps();
+		let found = self.race_ghost().map(|ghost| (ghost.get_position(steps), ghost.get_duration()));
+		match found {
+			Some((position, duration)) => {
+				if let Some(position) = position {
+					let position = self.origin_rebase.from_map(&position);
+					self.ghost_display.snap(&position);
+				}
+				self.ghost_display.set_visible(true);
+				self.ghost_display.set_text(&format!("Ghost: {:.2}s", duration));
+			},
+			None => {
+				self.ghost_display.set_visible(false);
+				self.ghost_display.set_text("");
+			},
+		}
+	}
+
+	/// Notes that an objective was completed in the current run, showing how that compares to the ghost. Finishes the run once every objective is done.
+	fn mark_ghost_split(&mut self) {
+		if !self.ghost_recorder.is_recording() {
+			return;
+		}
+		let index = self.ghost_recorder.mark_split();
+		let time = (self.ghost_recorder.get_steps() as f32) * FIXED_STEP_SECONDS;
+		let ghost_times = self.race_ghost().map(|ghost| (ghost.get_split(index), ghost.get_duration()));
+		let mut text = format!("Split {}: {:.2}s", index + 1, time);
+		if let Some((Some(split), _)) = ghost_times {
+			text.push_str(&format!(" ({})", format_time_difference(time - split)));
+		}
+		if self.quests.get_current().is_none() {
+			text = format!("Finished: {:.2}s", time);
+			if let Some((_, duration)) = ghost_times {
+				text.push_str(&format!(" ({})", format_time_difference(time - duration)));
+			}
+			log(&text);
+			self.last_run = self.ghost_recorder.finish();
+			self.ghost_display.set_visible(false);
+		}
+		self.ghost_display.set_text(&text);
+	}
+
+	/// Takes a ghost (see `GhostRun::to_bytes()`) to race against, logging if it can't be read. It's raced whenever its room is played from the start.
+	pub fn handle_ghost_loaded(&mut self, url : &str, bytes : &[u8]) {
+		match GhostRun::from_bytes(bytes) {
+			Ok(ghost) => {
+				debug_log!("Loaded ghost {:?} for room {:?} ({:.2}s)", url, ghost.room, ghost.get_duration());
+				self.ghost = Some(ghost);
+				if self.ghost_recorder.is_recording() {
+					self.show_race_ghost();
+				}
+			},
+			Err(error) => log(&format!("Couldn't read ghost {:?}: {}", url, error)),
+		}
+	}
+
+	/// Gets the last run that was finished (if any).
+	pub fn get_last_run(&self) -> Option<&GhostRun> {
+		self.last_run.as_ref()
+	}
+
+	/// Adds or removes a second, locally controlled player. They join next to the first player.
+	pub fn set_second_player(&mut self, enabled : bool) {
+		if enabled == self.second_player.is_some() {
+			return;
+		}
+		if enabled {
+			let mut second = Player::new(&self.tuning);
+			second.place(&self.player.position);
+			second.gravity_acceleration = self.player.gravity_acceleration;
+			second.set_sprite_tint(&SECOND_PLAYER_TINT, SECOND_PLAYER_TINT_AMOUNT);
+			second.set_flashing(!self.accessibility.reduce_flashing);
+			second.set_track_hold_toggle(self.accessibility.hold_toggle);
+			self.previous_second_player_position = second.position;
+			self.second_player = Some(second);
+		} else {
+			self.second_player = None;
+			self.second_kick_meter.update(None, &Vec2::zero());
+			for meter in &mut self.health_meters {
+				meter.update(None, &Vec2::zero());
+			}
+			self.second_view = None;
+			self.camera.set_viewport(&ViewRect::FULL);
+			self.camera.set_view(&self.player.position, 1.0);
+		}
+	}
+
+	/// Shows or hides the minimap (a small view of the actual world around the player, in the top right corner).
+	pub fn set_minimap_visible(&mut self, visible : bool) {
+		if visible == self.minimap.is_some() {
+			return;
+		}
+		self.minimap = if visible { Some(RenderView::new(MINIMAP_VIEW, &MINIMAP_RECT)) } else { None };
+	}
+
+	/// Turns origin rebasing on or off (it keeps the simulation precise on big maps by moving everything back toward the origin as the player gets far from it).
+	pub fn set_origin_rebasing(&mut self, enabled : bool) {
+		self.origin_rebase.set_enabled(enabled);
+	}
+
+	/// Turns camera-relative rendering on or off (it keeps things drawn far from the world origin from jittering).
+	pub fn set_relative_rendering(&mut self, enabled : bool) {
+		self.camera.set_relative_rendering(enabled);
+	}
+
+	/// Changes the virtual resolution: a fixed view size that's scaled up ("integer" or "fit") to fill the screen. A zero width or height turns it off. Logs if the scaling isn't valid.
+	pub fn set_virtual_resolution(&mut self, width : u32, height : u32, scaling : &str) {
+		if 0 == width || 0 == height {
+			self.camera.set_virtual_resolution(None);
+		} else {
+			match VirtualScaling::from_name(scaling) {
+				Some(scaling) => self.camera.set_virtual_resolution(Some(VirtualResolution::new(width, height, scaling))),
+				None => {
+					log(&format!("There's no virtual resolution scaling named {:?}", scaling));
+					return;
+				},
+			}
+		}
+		self.apply_ui_scale();
 	}
 
 	pub fn on_resize(&mut self, width : u32, height : u32) {
 		self.camera.resize(width, height);
+		self.apply_ui_scale();
 	}
 
 	pub fn on_key_down(&mut self, key : String) {
-		self.keyboard.on_down(key);
+		if self.attract_mode.note_input() {
+			return; // Only stops the demo.
+		}
+		self.input_device = InputDevice::Keyboard;
+		let time = now_seconds();
+		self.second_keyboard.queue_down(key.clone(), time);
+		self.keyboard.queue_down(key, time);
 	}
 
 	pub fn on_key_up(&mut self, key : String) {
-		self.keyboard.on_up(key);
+		if self.attract_mode.is_active() {
+			return; // The demo owns the keyboard.
+		}
+		let time = now_seconds();
+		self.second_keyboard.queue_up(key.clone(), time);
+		self.keyboard.queue_up(key, time);
 	}
 
 	pub fn on_mouse_enter(&mut self) {
@@ -135,6 +2547,9 @@ impl Game {
 	}
 
 	pub fn on_mouse_update(&mut self, x : u32, y : u32, buttons : u8) {
+		if 0 != buttons {
+			self.attract_mode.note_input();
+		}
 		self.mouse.on_mouse_update(&self.camera, x, y, buttons);
 	}
 
@@ -142,9 +2557,26 @@ impl Game {
 		self.mouse.on_leave();
 	}
 
-	pub fn on_gamepad_changed(&mut self, _valid : bool, buttons : Vec<f32>, raw_analog_sticks : Vec<f32>) {
+	/// Takes a gamepad's new state. The second gamepad (index 1) controls the second player while there is one, otherwise every gamepad controls the first.
+	pub fn on_gamepad_changed(&mut self, index : u32, _valid : bool, buttons : Vec<f32>, raw_analog_sticks : Vec<f32>) {
 		// TODO: Some sort of "disconnect pause" via `_valid`?
 		//log(&format!("Gamepad state: {:?} {:?} {:?}", valid, buttons, raw_analog_sticks));
-		self.gamepad.update(buttons, raw_analog_sticks);
+		if self.attract_mode.note_input() { // Only called when something changed.
+			return; // Only stops the demo.
+		}
+		let time = now_seconds();
+		let used = if 1 == index && self.second_player.is_some() {
+			self.second_gamepad.queue_update(buttons, raw_analog_sticks, time)
+		} else {
+			self.gamepad.queue_update(buttons, raw_analog_sticks, time)
+		};
+		if used {
+			self.input_device = InputDevice::Gamepad;
+		}
+	}
+
+	/// Gets the device the player last used.
+	pub fn get_input_device(&self) -> InputDevice {
+		self.input_device
 	}
 }
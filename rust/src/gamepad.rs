@@ -1,10 +1,12 @@
 
 use crate::geo::vec2::Vec2;
+use crate::input_events::InputQueue;
 
 /// All the virtual keys to care about.
 /// These are the keys that the game cares about.
 /// These are distinguished from real keys in that multiple real keys can map to any of these.
 #[derive(Copy, Clone)]
+#[allow(non_camel_case_types)]
 pub enum Button {
 	A = 0, // This will act like an index into a vector.
 	B,
@@ -12,18 +14,224 @@ pub enum Button {
 	Y,
 	R,
 	L,
+	DPAD_UP,
+	DPAD_DOWN,
+	DPAD_LEFT,
+	DPAD_RIGHT,
+	START,
 	/// The number of tracked buttons.
 	COUNT,
 }
 
-/// The minimum value that an analog input needs to be to register.
-/// For some reason on Firefox + Ubuntu 16.04, the sticks can get stuck at about 0.04 when flicked. So the threshold is fairly high.
+/// The minimum value that a trigger needs to be to register.
 const ANALOG_THRESHOLD : f32 = 0.05;
+/// How far a stick has to be pushed for the gamepad to count as being used (well past the deadzone, so a drifting stick doesn't).
+const ACTIVITY_THRESHOLD : f32 = 0.5;
+/// How far (in any direction) a stick has to be pushed to register, unless the calibration says otherwise.
+/// For some reason on Firefox + Ubuntu 16.04, the sticks can get stuck at about 0.04 when flicked. So this is fairly high.
+const DEFAULT_STICK_DEADZONE : f32 = 0.1;
+/// The largest the stick deadzone can be set to.
+const MAX_STICK_DEADZONE : f32 = 0.5;
+/// The furthest from the center that a stick's resting position can be corrected by. Anything further is more likely the stick being held.
+const MAX_STICK_OFFSET : f32 = 0.3;
+/// How long the sticks are watched to measure where they rest (in seconds).
+const CALIBRATION_SECONDS : f32 = 1.0;
+/// How much a stick axis can wander while it's being measured before it counts as being touched.
+const CALIBRATION_MAX_SPREAD : f32 = 0.1;
+
+/// The setting name for how big the stick deadzone is.
+pub const DEADZONE_SETTING : &str = "deadzone";
+/// The names of the stick axes, in the order they're in `GamepadCalibration::axes`. Each has an "Offset" and "Inverted" setting (like "mainYInverted").
+pub const STICK_AXIS_NAMES : [&str; 4] = ["mainX", "mainY", "secondX", "secondY"];
+
+/// The kinds of device the player can be playing with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputDevice {
+	Keyboard,
+	Gamepad,
+}
+
+impl InputDevice {
+	/// Gets the name the host page knows it by.
+	pub fn name(&self) -> &'static str {
+		match self {
+			InputDevice::Keyboard => "keyboard",
+			InputDevice::Gamepad => "gamepad",
+		}
+	}
+}
+
+/// How one axis of a stick is corrected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisCalibration {
+	/// Where the axis rests when left alone (which is taken away from what it reads).
+	pub offset : f32,
+	/// Whether the axis is flipped.
+	pub inverted : bool,
+}
+
+/// How a gamepad's sticks are corrected before they're used. Saved between visits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadCalibration {
+	/// Each stick axis (the main stick's x and y, then the second stick's; see `STICK_AXIS_NAMES`).
+	pub axes : [AxisCalibration; 4],
+	/// How far (in any direction) a stick has to be pushed to register. Past that, it's rescaled so it still goes smoothly from 0 to 1.
+	pub deadzone : f32,
+}
+
+impl GamepadCalibration {
+	/// Creates an instance that leaves the axes alone.
+	pub fn new() -> GamepadCalibration {
+		GamepadCalibration {
+			axes : [AxisCalibration { offset : 0.0, inverted : false }; 4],
+			deadzone : DEFAULT_STICK_DEADZONE,
+		}
+	}
+
+	/// Sets a single setting from text. Nothing changes if the name or value isn't valid.
+	pub fn set(&mut self, name : &str, value : &str) -> Result<(), String> {
+		let parse_number = |min : f32, max : f32| match value.parse::<f32>() {
+			Ok(number) if min <= number && number <= max => Ok(number),
+			_ => Err(format!("Setting {:?} must be a number from {} to {}, not {:?}", name, min, max, value)),
+		};
+		if DEADZONE_SETTING == name {
+			self.deadzone = parse_number(0.0, MAX_STICK_DEADZONE)?;
+			return Ok(());
+		}
+		for (index, axis_name) in STICK_AXIS_NAMES.iter().enumerate() {
+			if name.strip_suffix("Offset") == Some(axis_name) {
+				self.axes[index].offset = parse_number(-MAX_STICK_OFFSET, MAX_STICK_OFFSET)?;
+				return Ok(());
+			}
+			if name.strip_suffix("Inverted") == Some(axis_name) {
+				self.axes[index].inverted = match value {
+					"true" => true,
+					"false" => false,
+					_ => { return Err(format!("Setting {:?} must be true or false, not {:?}", name, value)); },
+				};
+				return Ok(());
+			}
+		}
+		Err(format!("There's no gamepad setting named {:?}", name))
+	}
+
+	/// Converts to text for saving (one `name=value` per line).
+	pub fn to_save_string(&self) -> String {
+		let mut lines = vec![format!("{}={}", DEADZONE_SETTING, self.deadzone)];
+		for (axis, name) in self.axes.iter().zip(STICK_AXIS_NAMES.iter()) {
+			lines.push(format!("{}Offset={}", name, axis.offset));
+			lines.push(format!("{}Inverted={}", name, axis.inverted));
+		}
+		lines.join("\n")
+	}
+
+	/// Replaces all settings with ones from `to_save_string()`. Anything not mentioned goes back to its default. If anything's wrong with the text, then nothing is changed.
+	pub fn load_save_string(&mut self, text : &str) -> Result<(), String> {
+		let mut loaded = GamepadCalibration::new();
+		for line in text.lines() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			match line.find('=') {
+				Some(split) => { loaded.set(&line[..split], &line[split+1..])?; },
+				None => { return Err(format!("Missing '=' in {:?}", line)); },
+			}
+		}
+		*self = loaded;
+		Ok(())
+	}
+
+	/// Corrects a stick's raw position, given the index of its x axis in `axes` (its y axis is the next one).
+	fn correct_stick(&self, x_axis : usize, x : f32, y : f32) -> Vec2 {
+		let correct = |index : usize, value : f32| {
+			let axis = &self.axes[index];
+			let value = value - axis.offset;
+			if axis.inverted { -value } else { value }
+		};
+		radial_deadzone(&Vec2::new(correct(x_axis, x), correct(x_axis + 1, y)), self.deadzone)
+	}
+}
+
+/// Drops a stick position that's within some distance of the center, and rescales the rest so it still goes smoothly from 0 (at the deadzone's edge) to 1.
+/// Unlike a deadzone on each axis, this doesn't snap diagonals toward the axes, and a stick drifting along one axis doesn't keep the other from working.
+pub fn radial_deadzone(stick : &Vec2, deadzone : f32) -> Vec2 {
+	let length = stick.length();
+	if length <= deadzone {
+		return Vec2::zero();
+	}
+	let scaled = (length.min(1.0) - deadzone) / (1.0 - deadzone);
+	stick * (scaled / length)
+}
+
+/// How a calibration is going.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalibrationProgress {
+	/// Still watching the sticks (with how far along it is, from 0 to 1).
+	Measuring(f32),
+	/// A stick moved too much, so it's starting over.
+	Moved,
+	/// Done, with where each stick axis rests (in the same order as `GamepadCalibration::axes`).
+	Done([f32; 4]),
+}
+
+/// Measures where a gamepad's sticks rest by watching them for a moment while they're left alone.
+pub struct GamepadCalibrator {
+	/// How long it's been watching (in seconds).
+	seconds : f32,
+	/// How many readings it's taken.
+	samples : u32,
+	/// The total of each axis' readings.
+	sums : [f32; 4],
+	/// The lowest reading of each axis.
+	lowest : [f32; 4],
+	/// The highest reading of each axis.
+	highest : [f32; 4],
+}
+
+impl GamepadCalibrator {
+	/// Creates an instance that hasn't seen anything yet.
+	pub fn new() -> GamepadCalibrator {
+		GamepadCalibrator {
+			seconds : 0.0,
+			samples : 0,
+			sums : [0.0; 4],
+			lowest : [f32::MAX; 4],
+			highest : [f32::MIN; 4],
+		}
+	}
+
+	/// Takes a reading of the stick axes (see `Gamepad::get_raw_stick_axes()`).
+	pub fn update(&mut self, elapsed_seconds : f32, axes : &[f32; 4]) -> CalibrationProgress {
+		for (index, value) in axes.iter().enumerate() {
+			self.sums[index] += value;
+			self.lowest[index] = self.lowest[index].min(*value);
+			self.highest[index] = self.highest[index].max(*value);
+		}
+		self.samples += 1;
+		self.seconds += elapsed_seconds;
+		if self.lowest.iter().zip(self.highest.iter()).any(|(lowest, highest)| CALIBRATION_MAX_SPREAD < highest - lowest) {
+			*self = GamepadCalibrator::new();
+			return CalibrationProgress::Moved;
+		}
+		if self.seconds < CALIBRATION_SECONDS {
+			return CalibrationProgress::Measuring(self.seconds / CALIBRATION_SECONDS);
+		}
+		let mut offsets = [0.0; 4];
+		for (offset, sum) in offsets.iter_mut().zip(self.sums.iter()) {
+			*offset = (sum / (self.samples as f32)).max(-MAX_STICK_OFFSET).min(MAX_STICK_OFFSET);
+		}
+		CalibrationProgress::Done(offsets)
+	}
+}
 
 /// Stores info about the current keyboard state.
 pub struct Gamepad {
 	/// The raw button values.
 	button_values : Vec<bool>,
+	/// The raw button values as of the last change that was queued (so only changes are queued).
+	queued_values : Vec<bool>,
+	/// Button changes waiting to be applied to `button_values` (see `process_until()`).
+	queue : InputQueue,
 	/// The mapping from Button enum values (as indices) to the button's specific index in button_values.
 	button_mapping : Vec<usize>,
 
@@ -33,10 +241,16 @@ pub struct Gamepad {
 	main_x_index : usize,
 	/// The index of the y-axis of the main analog stick (in direction_values).
 	main_y_index : usize,
+	/// The index of the x-axis of the second analog stick (in direction_values).
+	second_x_index : usize,
+	/// The index of the y-axis of the second analog stick (in direction_values).
+	second_y_index : usize,
 	/// The index of the right trigger (in direction_values).
 	r_trigger_index : usize,
 	/// The index of the left trigger (in direction_values).
 	l_trigger_index : usize,
+	/// How the sticks are corrected.
+	calibration : GamepadCalibration,
 }
 
 impl Gamepad {
@@ -48,36 +262,87 @@ impl Gamepad {
 		button_mapping[Button::Y as usize] = 3;
 		button_mapping[Button::L as usize] = 4;
 		button_mapping[Button::R as usize] = 5;
+		// Where the standard gamepad mapping puts the d-pad.
+		button_mapping[Button::DPAD_UP as usize] = 12;
+		button_mapping[Button::DPAD_DOWN as usize] = 13;
+		button_mapping[Button::DPAD_LEFT as usize] = 14;
+		button_mapping[Button::DPAD_RIGHT as usize] = 15;
+		button_mapping[Button::START as usize] = 9;
 		Gamepad {
 			button_values : Vec::new(),
+			queued_values : Vec::new(),
+			queue : InputQueue::new(),
 			button_mapping,
 			direction_values : Vec::new(),
 			main_x_index    : 0, // 3 for the right stick.
 			main_y_index    : 1, // 4 for the right stick.
+			second_x_index  : 3,
+			second_y_index  : 4,
 			r_trigger_index : 5,
 			l_trigger_index : 2,
+			calibration : GamepadCalibration::new(),
 		}
 	}
 
+	/// Sets how the sticks are corrected.
+	pub fn set_calibration(&mut self, calibration : &GamepadCalibration) {
+		self.calibration = calibration.clone();
+	}
+
+	/// Gets how the sticks are corrected.
+	pub fn get_calibration(&self) -> &GamepadCalibration {
+		&self.calibration
+	}
+
+	/// Gets the stick axes exactly as the gamepad reports them (in the same order as `GamepadCalibration::axes`).
+	pub fn get_raw_stick_axes(&self) -> [f32; 4] {
+		let raw = |index : usize| *self.direction_values.get(index).unwrap_or(&0.0);
+		[raw(self.main_x_index), raw(self.main_y_index), raw(self.second_x_index), raw(self.second_y_index)]
+	}
+
 	// TODO: Add a way to change and save bindings.
 
-	/// Updates the current internal state.
-	pub fn update(&mut self, button_source : Vec<f32>, analog_source : Vec<f32>) {
+	/// Updates the current internal state right away. Returns whether the gamepad was used (a button was newly pressed, or a stick was pushed well away from its center).
+	pub fn update(&mut self, button_source : Vec<f32>, analog_source : Vec<f32>) -> bool {
+		let used = self.queue_update(button_source, analog_source, f64::NEG_INFINITY);
+		self.end_step(); // Nothing's held back for a step.
+		self.process_until(f64::INFINITY);
+		used
+	}
+
+	/// Takes the gamepad's state as of some time. The sticks change right away, but the button changes wait to be applied by `process_until()`.
+	/// Returns whether the gamepad was used (like `update()`).
+	pub fn queue_update(&mut self, button_source : Vec<f32>, analog_source : Vec<f32>, time : f64) -> bool {
+		let mut used = false;
 		let button_length = button_source.len();
+		self.queued_values.resize(button_length, false);
 		self.button_values.resize(button_length, false);
 		for index in 0..button_length {
-			self.button_values[index] = 0.5f32 < button_source[index];
-		}
-
-		let analog_length = analog_source.len();
-		self.direction_values.resize(analog_length, 0.0);
-		for index in 0..analog_length {
-			let mut value = analog_source[index];
-			if value.abs() < ANALOG_THRESHOLD {
-				value = 0.0;
+			let down = 0.5f32 < button_source[index];
+			if down != self.queued_values[index] {
+				used |= down;
+				self.queue.push(index, down, time);
+				self.queued_values[index] = down;
 			}
-			self.direction_values[index] = value;
 		}
+
+		self.direction_values = analog_source; // Corrected when they're read, since each stick is corrected as a whole.
+		used || ACTIVITY_THRESHOLD < self.stick_direction().length() || ACTIVITY_THRESHOLD < self.second_direction().length()
+	}
+
+	/// Applies the queued button changes that happened by some time, in order. A button that's tapped stays down until a step has seen it (see `end_step()`).
+	pub fn process_until(&mut self, time : f64) {
+		self.queue.apply_until(time, &mut self.button_values);
+	}
+
+	/// Notes that a simulation step has seen the current button states, so the buttons that were just pressed can be let go.
+	pub fn end_step(&mut self) {
+		self.queue.end_step();
+	}
+
+	/// Gets whether the given button went down since the last simulation step (see `end_step()`). Meant for the simulation, like `Keyboard::just_pressed()`.
+	pub fn just_pressed(&self, button : Button) -> bool {
+		self.queue.was_pressed(self.button_mapping[button as usize])
 	}
 
 	/// Gets whether the given button is down.
@@ -90,21 +355,130 @@ impl Gamepad {
 		}
 	}
 
-	/// Gets the current position of the main analog stick.
+	/// Gets the direction being pushed: the d-pad along any axis it's pressed on, otherwise the main analog stick.
 	pub fn direction(&self) -> Vec2 {
+		let axis = |positive : Button, negative : Button| (self.is_down(positive) as i32 - self.is_down(negative) as i32) as f32;
+		let dpad = Vec2::new(axis(Button::DPAD_RIGHT, Button::DPAD_LEFT), axis(Button::DPAD_UP, Button::DPAD_DOWN));
+		let stick = self.stick_direction();
 		Vec2::new(
-			*self.direction_values.get(self.main_x_index).unwrap_or(&0.0),
-			-(*self.direction_values.get(self.main_y_index).unwrap_or(&0.0)), // Not using cartesian.
+			if 0.0 != dpad.x { dpad.x } else { stick.x },
+			if 0.0 != dpad.y { dpad.y } else { stick.y },
 		)
 	}
 
+	/// Gets the current position of the main analog stick (corrected by the calibration).
+	pub fn stick_direction(&self) -> Vec2 {
+		let axes = self.get_raw_stick_axes();
+		let stick = self.calibration.correct_stick(0, axes[0], axes[1]);
+		Vec2::new(stick.x, -stick.y) // Not using cartesian.
+	}
+
+	/// Gets the current position of the second (usually right) analog stick (corrected by the calibration).
+	pub fn second_direction(&self) -> Vec2 {
+		let axes = self.get_raw_stick_axes();
+		let stick = self.calibration.correct_stick(2, axes[2], axes[3]);
+		Vec2::new(stick.x, -stick.y) // Not using cartesian.
+	}
+
+	/// Gets an analog value that isn't part of a stick (like a trigger).
+	fn analog_value(&self, index : usize) -> f32 {
+		let value = *self.direction_values.get(index).unwrap_or(&0.0);
+		if value.abs() < ANALOG_THRESHOLD { 0.0 } else { value }
+	}
+
 	/// Gets the left trigger's analog value.
 	pub fn l_trigger(&self) -> f32 {
-		*self.direction_values.get(self.l_trigger_index).unwrap_or(&0.0)
+		self.analog_value(self.l_trigger_index)
 	}
 
 	/// Gets the right trigger's analog value.
 	pub fn r_trigger(&self) -> f32 {
-		*self.direction_values.get(self.r_trigger_index).unwrap_or(&0.0)
+		self.analog_value(self.r_trigger_index)
+	}
+}
+
+#[cfg(test)]
+mod tests_gamepad {
+	use super::*;
+
+	/// Makes button values with some (standard mapping) buttons pressed.
+	fn buttons(pressed : &[usize]) -> Vec<f32> {
+		(0..17).map(|index| if pressed.contains(&index) { 1.0 } else { 0.0 }).collect()
+	}
+
+	#[test]
+	fn dpad_and_activity() {
+		let mut gamepad = Gamepad::new();
+		let near = |actual : Vec2, x : f32, y : f32| (actual.x - x).abs() < 0.001 && (actual.y - y).abs() < 0.001;
+		// A slightly pushed stick moves things (rescaled past the deadzone), but doesn't count as using the gamepad.
+		assert!(!gamepad.update(buttons(&[]), vec![0.46, 0.0, -1.0, 0.0, 0.0, -1.0]));
+		assert!(near(gamepad.direction(), 0.4, 0.0));
+		// The d-pad wins along the axes it's pressed on.
+		assert!(gamepad.update(buttons(&[12]), vec![0.46, 0.0, -1.0, 0.0, 0.0, -1.0]));
+		assert!(near(gamepad.direction(), 0.4, 1.0));
+		assert!(gamepad.update(buttons(&[12, 14]), vec![0.46, 0.0, -1.0, 0.0, 0.0, -1.0]));
+		assert_eq!((gamepad.direction().x, gamepad.direction().y), (-1.0, 1.0));
+		assert!(near(gamepad.stick_direction(), 0.4, 0.0));
+		// Holding a button isn't new use, but pushing a stick is.
+		assert!(!gamepad.update(buttons(&[12, 14]), vec![0.46, 0.0, -1.0, 0.0, 0.0, -1.0]));
+		assert!(gamepad.update(buttons(&[]), vec![0.0, -0.9, -1.0, 0.0, 0.0, -1.0]));
+	}
+
+	#[test]
+	fn deadzones() {
+		let near = |actual : Vec2, x : f32, y : f32| (actual.x - x).abs() < 0.001 && (actual.y - y).abs() < 0.001;
+		// Drift near the center doesn't register, and anything past it is rescaled without changing direction.
+		assert!(near(radial_deadzone(&Vec2::new(0.08, 0.0), 0.1), 0.0, 0.0));
+		assert!(near(radial_deadzone(&Vec2::new(0.3, 0.4), 0.1), 0.3 * 0.8889, 0.4 * 0.8889));
+		assert!(near(radial_deadzone(&Vec2::new(0.0, 1.0), 0.1), 0.0, 1.0));
+		assert!(near(radial_deadzone(&Vec2::new(0.0, -1.2), 0.1), 0.0, -1.0)); // Never past the edge.
+
+		// Calibration takes away the drift and flips axes.
+		let mut gamepad = Gamepad::new();
+		let mut calibration = GamepadCalibration::new();
+		assert!(calibration.set("mainXOffset", "0.15").is_ok());
+		assert!(calibration.set("mainYInverted", "true").is_ok());
+		assert!(calibration.set("mainYOffset", "2").is_err());
+		assert!(calibration.set("thirdXOffset", "0").is_err());
+		gamepad.set_calibration(&calibration);
+		gamepad.update(buttons(&[]), vec![0.2, 0.0, -1.0, 0.0, 0.0, -1.0]);
+		assert!(near(gamepad.stick_direction(), 0.0, 0.0));
+		gamepad.update(buttons(&[]), vec![0.15, -1.0, -1.0, 0.0, 0.0, -1.0]);
+		assert!(near(gamepad.stick_direction(), 0.0, -1.0));
+		assert_eq!(gamepad.l_trigger(), -1.0);
+
+		let mut loaded = GamepadCalibration::new();
+		assert!(loaded.load_save_string(&calibration.to_save_string()).is_ok());
+		assert_eq!(&loaded, gamepad.get_calibration());
+		assert!(loaded.load_save_string("deadzone=0.2\nnonsense").is_err());
+		assert_eq!(loaded, calibration); // Unchanged.
+	}
+
+	#[test]
+	fn calibrating() {
+		let mut calibrator = GamepadCalibrator::new();
+		let resting = [0.04, -0.02, 0.0, 0.01];
+		assert_eq!(calibrator.update(0.5, &resting), CalibrationProgress::Measuring(0.5));
+		// Touching a stick starts over.
+		assert_eq!(calibrator.update(0.25, &[0.6, -0.02, 0.0, 0.01]), CalibrationProgress::Moved);
+		assert_eq!(calibrator.update(0.5, &resting), CalibrationProgress::Measuring(0.5));
+		match calibrator.update(0.5, &[0.06, -0.02, 0.0, 0.01]) {
+			CalibrationProgress::Done(offsets) => { assert!((offsets[0] - 0.05).abs() < 0.0001 && (offsets[1] + 0.02).abs() < 0.0001); },
+			progress => { panic!("Expected calibration to be done but got {:?}", progress); },
+		}
+	}
+
+	#[test]
+	fn queued_taps() {
+		let mut gamepad = Gamepad::new();
+		let sticks = vec![0.0; 6];
+		assert!(gamepad.queue_update(buttons(&[0]), sticks.clone(), 1.0));
+		assert!(!gamepad.queue_update(buttons(&[]), sticks.clone(), 1.001));
+		assert!(!gamepad.is_down(Button::A));
+		gamepad.process_until(1.01);
+		assert!(gamepad.is_down(Button::A) && gamepad.just_pressed(Button::A));
+		gamepad.end_step();
+		gamepad.process_until(1.01);
+		assert!(!gamepad.is_down(Button::A) && !gamepad.just_pressed(Button::A));
 	}
 }
@@ -5,14 +5,22 @@ pub mod common;
 pub mod asserts;
 pub mod range;
 pub mod vec2;
+pub mod world_vec2;
 pub mod bounds2;
 pub mod vec3;
 pub mod mat4;
+pub mod transform2d;
+pub mod validate;
 
 pub mod collider;
 pub mod line;
 pub mod line_segment;
 pub mod circle;
+pub mod aabb;
 pub mod polygon;
+pub mod edge_set;
 
 pub mod collision_system;
+pub mod broad_phase;
+pub mod collision_audit;
+pub mod contact_cache;
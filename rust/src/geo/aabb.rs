@@ -0,0 +1,323 @@
+use std::f32::{INFINITY, NEG_INFINITY};
+
+use generational_arena::Index;
+
+use super::consts::*;
+use super::range::*;
+use super::vec2::*;
+use super::line::*;
+use super::line_segment::*;
+use super::circle::*;
+use super::collider::*;
+
+/// A 2D axis-aligned box.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb {
+	pub center : Vec2, // The center.
+	pub half_size : Vec2, // Half of the width and height. Never negative.
+}
+
+impl Aabb {
+	pub fn new(center : &Vec2, half_size : &Vec2) -> Aabb {
+		Aabb { center: center.clone(), half_size: Vec2::new(half_size.x.abs(), half_size.y.abs()) }
+	}
+
+	/// Gets how far the box reaches from its center in some (unit length) direction.
+	pub fn reach(&self, direction : &Vec2) -> f32 {
+		self.half_size.x * direction.x.abs() + self.half_size.y * direction.y.abs()
+	}
+
+	/// Gets the sides of a convex obstacle grown by this box (given the obstacle's corners, and the normals of any of its edges that aren't along the box's axes).
+	/// The box overlaps the obstacle exactly when its center is inside all of them.
+	fn grown_sides(&self, corners : &[Vec2], edge_normals : &[Vec2]) -> Vec<Side> {
+		let mut normals = vec![Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0)];
+		for normal in edge_normals {
+			normals.push(normal.clone());
+			normals.push(normal.scale(-1.0));
+		}
+		normals.into_iter().map(|normal| {
+			let furthest = corners.iter().map(|corner| normal.dot(corner)).fold(NEG_INFINITY, f32::max);
+			Side { offset : furthest + self.reach(&normal), normal }
+		}).collect()
+	}
+}
+
+/// One side of a convex area: everything where `normal.dot(point) <= offset` is on the inside of it.
+struct Side {
+	/// Points out of the area. Must be unit length.
+	normal : Vec2,
+	/// How far along the normal the side is.
+	offset : f32,
+}
+
+/// Sweeps the box's center (as a point) into a convex area given by its sides (an obstacle grown by the box, so the center touching the area is the box touching the obstacle).
+/// Works like the Circle colliders: a start that's overlapping is pushed out through the nearest side, and contacts that don't deflect the movement are still passed back.
+fn sweep_into_sides(position : &Vec2, movement : &Vec2, sides : &[Side]) -> Option<Deflection> {
+	// Find when the center is inside all of the sides at once.
+	let mut enter = NEG_INFINITY;
+	let mut enter_normal = Vec2::zero();
+	let mut exit = INFINITY;
+	for side in sides {
+		let depth = side.offset - side.normal.dot(position); // How far inside of this side the start is.
+		let approach = side.normal.dot(movement);
+		if approach.abs() < distance_epsilon() {
+			if depth < -distance_epsilon() {
+				return None; // Moving alongside it, but outside of it.
+			}
+			continue;
+		}
+		let time = depth / approach;
+		if approach < 0.0 {
+			if enter < time {
+				enter = time;
+				enter_normal = side.normal.clone();
+			}
+		} else if time < exit {
+			exit = time;
+		}
+	}
+	if exit < enter {
+		return None;
+	}
+
+	// The side the start is least inside of (or furthest outside of).
+	let mut nearest = &sides[0];
+	let mut nearest_depth = INFINITY;
+	for side in sides {
+		let depth = side.offset - side.normal.dot(position);
+		if depth < nearest_depth {
+			nearest = side;
+			nearest_depth = depth;
+		}
+	}
+
+	let mut deflection = Deflection{
+		times: Range::empty(),
+		normal: enter_normal,
+		deflected: false, // Assume not deflected until go through that part.
+		position: position.clone(),
+		remainder: movement.clone(),
+		source: Index::from_raw_parts(0, 0), // A generic index that will be replaced by the caller.
+	};
+	if -distance_epsilon() <= nearest_depth {
+		// Starts touching or overlapping, so push it out through the nearest side and deflect from there.
+		deflection.normal = nearest.normal.clone();
+		deflection.position += nearest.normal.scale(nearest_depth.max(0.0));
+		deflection.times = Range::from_values(enter.min(0.0), exit.max(0.0));
+	} else {
+		// Starts clear, so only hits if it gets there during this movement.
+		deflection.times = Range::from_values(enter, exit);
+		if deflection.split_remainder() {
+			return None;
+		}
+	}
+
+	// Then calculate the deflection. Always return Some at this point (did contact the obstacle), but it won't always have `deflected` set to true.
+	deflection.calc_deflection();
+	Some(deflection)
+}
+
+impl<'l> Collider<'l, Vec2> for Aabb {
+	/// Deflects a collider's movement with the given obstacle.
+	fn deflect_with(&self, movement : &Vec2, obstacle : &'l Vec2) -> Option<Deflection> {
+		sweep_into_sides(&self.center, movement, &self.grown_sides(&[obstacle.clone()], &[]))
+	}
+}
+
+impl<'l> Collider<'l, LineSegment> for Aabb {
+	/// Deflects a collider's movement with the given obstacle.
+	fn deflect_with(&self, movement : &Vec2, obstacle : &'l LineSegment) -> Option<Deflection> {
+		let corners = [obstacle.start.clone(), obstacle.end.clone()];
+		let sides = if 0.0 < obstacle.length {
+			self.grown_sides(&corners, &[(&obstacle.direction).ortho()])
+		} else {
+			self.grown_sides(&corners, &[])
+		};
+		sweep_into_sides(&self.center, movement, &sides)
+	}
+}
+
+impl<'l> Collider<'l, Line> for Aabb {
+	/// Deflects a collider's movement with the given obstacle.
+	fn deflect_with(&self, movement : &Vec2, obstacle : &'l Line) -> Option<Deflection> {
+		// Lines go on forever, so grown by the box they're just a strip.
+		let normal = (&obstacle.delta).ortho();
+		let across = normal.dot(&obstacle.origin);
+		let reach = self.reach(&normal);
+		let sides = [
+			Side { normal : normal.clone(), offset : across + reach },
+			Side { normal : normal.scale(-1.0), offset : -across + reach },
+		];
+		sweep_into_sides(&self.center, movement, &sides)
+	}
+}
+
+impl<'l> Collider<'l, Circle> for Aabb {
+	/// Deflects a collider's movement with the given obstacle.
+	fn deflect_with(&self, movement : &Vec2, obstacle : &'l Circle) -> Option<Deflection> {
+		// The box grown by the circle is a rounded rectangle: two overlapping rectangles (one wider, one taller) plus a circle at each corner.
+		let center = [obstacle.center.clone()];
+		let wide = Aabb::new(&self.center, &Vec2::new(self.half_size.x + obstacle.radius, self.half_size.y));
+		let tall = Aabb::new(&self.center, &Vec2::new(self.half_size.x, self.half_size.y + obstacle.radius));
+		let mut deflections = Vec::new();
+		for sides in [wide.grown_sides(&center, &[]), tall.grown_sides(&center, &[])] {
+			if let Some(deflection) = sweep_into_sides(&self.center, movement, &sides) {
+				deflections.push(deflection);
+			}
+		}
+		let point = Circle::new(&self.center, obstacle.radius);
+		for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+			let corner = &obstacle.center + Vec2::new(x * self.half_size.x, y * self.half_size.y);
+			if let Some(deflection) = point.deflect_with(movement, &corner) {
+				deflections.push(deflection);
+			}
+		}
+		if let Some(mut total) = TotalDeflection::try_new(deflections.clone()) {
+			Some(total.deflections.remove(0))
+		} else {
+			// Nothing deflected, but still pass back if it was being skimmed (so callers can tell what's being touched).
+			deflections.into_iter().find(|deflection| deflection.times.contains(0.0))
+		}
+	}
+}
+
+#[cfg(test)]
+mod test_aabb_point_deflect {
+	use super::*;
+	use crate::{assert_vec2_about_eq, assert_about_eq};
+
+	#[test]
+	fn no_hit() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(2.0, 1.0));
+		assert!(aabb.deflect_with(&Vec2::new(1.0, 0.0), &Vec2::new(5.0, 0.0)).is_none()); // Too short.
+		assert!(aabb.deflect_with(&Vec2::new(4.0, 0.0), &Vec2::new(5.0, 1.5)).is_none()); // Passes under it.
+		assert!(aabb.deflect_with(&Vec2::new(-4.0, 0.0), &Vec2::new(5.0, 0.0)).is_none()); // Going away.
+	}
+
+	#[test]
+	fn hit_stop() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(2.0, 1.0));
+		let hit = aabb.deflect_with(&Vec2::new(6.0, 0.0), &Vec2::new(5.0, 0.5)).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.5);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+		assert_eq!(hit.deflected, true);
+		assert_vec2_about_eq!(hit.position, Vec2::new(3.0, 0.0));
+		assert_vec2_about_eq!(hit.remainder, Vec2::new(0.0, 0.0));
+	}
+
+	#[test]
+	fn start_inside() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(2.0, 1.0));
+		let hit = aabb.deflect_with(&Vec2::new(1.0, 0.0), &Vec2::new(0.5, 0.75)).unwrap();
+		assert!(hit.times.contains(0.0));
+		assert_vec2_about_eq!(hit.normal, Vec2::new(0.0, -1.0)); // Out the nearest side.
+		assert_vec2_about_eq!(hit.position, Vec2::new(0.0, -0.25));
+		assert_eq!(hit.deflected, false);
+	}
+}
+
+#[cfg(test)]
+mod test_aabb_line_segment_deflect {
+	use super::*;
+	use crate::{assert_vec2_about_eq, assert_about_eq};
+
+	#[test]
+	fn complete_miss() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 2.0), &Vec2::new(1.0, 1.0));
+		let seg = LineSegment::new(&Vec2::new(-5.0, 0.0), &Vec2::new(5.0, 0.0));
+		assert!(aabb.deflect_with(&Vec2::new(1.0, 0.5), &seg).is_none());
+	}
+
+	#[test]
+	fn land_and_slide() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 2.0), &Vec2::new(1.0, 1.0));
+		let seg = LineSegment::new(&Vec2::new(-5.0, 0.0), &Vec2::new(5.0, 0.0));
+		let hit = aabb.deflect_with(&Vec2::new(2.0, -2.0), &seg).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.5);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(0.0, 1.0));
+		assert_eq!(hit.deflected, true);
+		assert_vec2_about_eq!(hit.position, Vec2::new(1.0, 1.0));
+		assert_vec2_about_eq!(hit.remainder, Vec2::new(1.0, 0.0));
+
+		// Then walking along it just skims.
+		let standing = Aabb::new(&Vec2::new(0.0, 1.0), &Vec2::new(1.0, 1.0));
+		let skim = standing.deflect_with(&Vec2::new(2.0, 0.0), &seg).unwrap();
+		assert!(skim.times.contains(0.0));
+		assert_vec2_about_eq!(skim.normal, Vec2::new(0.0, 1.0));
+		assert_eq!(skim.deflected, false);
+	}
+
+	#[test]
+	fn hit_slope() {
+		// A 45 degree slope is hit by the box's corner.
+		let aabb = Aabb::new(&Vec2::new(0.0, 5.0), &Vec2::new(1.0, 1.0));
+		let seg = LineSegment::new(&Vec2::new(-5.0, -5.0), &Vec2::new(5.0, 5.0));
+		let hit = aabb.deflect_with(&Vec2::new(0.0, -4.0), &seg).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.75);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 1.0).norm());
+		assert_eq!(hit.deflected, true);
+		assert_vec2_about_eq!(hit.position, Vec2::new(0.0, 2.0));
+		assert_vec2_about_eq!(hit.remainder, Vec2::new(-0.5, -0.5));
+	}
+
+	#[test]
+	fn hit_end() {
+		// The box's side hits the end of a segment sticking out at it.
+		let aabb = Aabb::new(&Vec2::new(-4.0, 0.0), &Vec2::new(1.0, 1.0));
+		let seg = LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(5.0, 3.0));
+		let hit = aabb.deflect_with(&Vec2::new(4.0, 0.0), &seg).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.75);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+		assert_vec2_about_eq!(hit.position, Vec2::new(-1.0, 0.0));
+	}
+}
+
+#[cfg(test)]
+mod test_aabb_line_deflect {
+	use super::*;
+	use crate::{assert_vec2_about_eq, assert_about_eq};
+
+	#[test]
+	fn hit_deflect() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 2.0));
+		let line = Line::new(&Vec2::new(3.0, 10.0), &Vec2::new(3.0, -10.0));
+		let hit = aabb.deflect_with(&Vec2::new(4.0, 4.0), &line).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.5);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+		assert_eq!(hit.deflected, true);
+		assert_vec2_about_eq!(hit.position, Vec2::new(2.0, 2.0));
+		assert_vec2_about_eq!(hit.remainder, Vec2::new(0.0, 2.0));
+		assert!(aabb.deflect_with(&Vec2::new(1.0, 4.0), &line).is_none());
+	}
+}
+
+#[cfg(test)]
+mod test_aabb_circle_deflect {
+	use super::*;
+	use crate::{assert_vec2_about_eq, assert_about_eq};
+
+	#[test]
+	fn hit_side() {
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0));
+		let circle = Circle::new(&Vec2::new(5.0, 0.5), 2.0);
+		let hit = aabb.deflect_with(&Vec2::new(4.0, 0.0), &circle).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.5);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+		assert_vec2_about_eq!(hit.position, Vec2::new(2.0, 0.0));
+	}
+
+	#[test]
+	fn hit_corner() {
+		// Coming at it diagonally, the box's corner meets the circle's edge.
+		let aabb = Aabb::new(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0));
+		let circle = Circle::new(&Vec2::new(5.0, 5.0), 2.0_f32.sqrt());
+		let hit = aabb.deflect_with(&Vec2::new(4.0, 4.0), &circle).unwrap();
+		assert_about_eq!(hit.times.min().unwrap(), 0.75);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, -1.0).norm());
+		assert_vec2_about_eq!(hit.position, Vec2::new(3.0, 3.0));
+
+		// And can miss it entirely by going past the corner.
+		assert!(aabb.deflect_with(&Vec2::new(8.0, 0.0), &Circle::new(&Vec2::new(4.0, 3.0), 0.5)).is_none());
+	}
+}
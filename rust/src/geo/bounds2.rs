@@ -89,12 +89,13 @@ impl Bounds2 {
 	pub fn y_min(&self) -> f32 { self.y_min }
 	pub fn y_max(&self) -> f32 { self.y_max }
 
-	/// Checks if this range overlaps another. This IS NOT exact (so is to within EPSILON).
+	/// Checks if this range overlaps another. This IS NOT exact (so is to within the distance epsilon).
 	pub fn overlaps(&self, other : &Bounds2) -> bool {
-		EPSILON >= self.x_min - other.x_max &&
-		EPSILON >= other.x_min - self.x_max &&
-		EPSILON >= self.y_min - other.y_max &&
-		EPSILON >= other.y_min - self.y_max
+		let epsilon = distance_epsilon();
+		epsilon >= self.x_min - other.x_max &&
+		epsilon >= other.x_min - self.x_max &&
+		epsilon >= self.y_min - other.y_max &&
+		epsilon >= other.y_min - self.y_max
 	}
 
 	/// Checks if this overlaps a given point.
@@ -114,26 +115,26 @@ impl Bounds2 {
 		}
 		// Otherwise, much check if intersects any of the outer boundaries of the rectangle.
 		let delta = end - start;
-		if RelativePosition::Above == x_relative && -EPSILON > delta.x && end.x <= self.x_max {
+		if RelativePosition::Above == x_relative && -distance_epsilon() > delta.x && end.x <= self.x_max {
 			// Being above the x means can only intersect with the x_max wall. Check that.
 			let hit = start + delta.scale((self.x_max - start.x) / delta.x);
 			if self.y_min <= hit.y && hit.y <= self.y_max {
 				return Some(hit);
 			}
-		} else if RelativePosition::Below == x_relative && EPSILON < delta.x && end.x >= self.x_min {
+		} else if RelativePosition::Below == x_relative && distance_epsilon() < delta.x && end.x >= self.x_min {
 			// Being above the x means can only intersect with the x_max wall. Check that.
 			let hit = start + delta.scale((self.x_min - start.x) / delta.x);
 			if self.y_min <= hit.y && hit.y <= self.y_max {
 				return Some(hit);
 			}
 		}
-		if RelativePosition::Above == y_relative && -EPSILON > delta.y && end.y <= self.y_max {
+		if RelativePosition::Above == y_relative && -distance_epsilon() > delta.y && end.y <= self.y_max {
 			// Being above the x means can only intersect with the x_max wall. Check that.
 			let hit = start + delta.scale((self.y_max - start.y) / delta.y);
 			if self.x_min <= hit.x && hit.x <= self.x_max {
 				return Some(hit);
 			}
-		} else if RelativePosition::Below == y_relative && EPSILON < delta.y && end.y >= self.y_min {
+		} else if RelativePosition::Below == y_relative && distance_epsilon() < delta.y && end.y >= self.y_min {
 			// Being above the x means can only intersect with the x_max wall. Check that.
 			let hit = start + delta.scale((self.y_min - start.y) / delta.y);
 			if self.x_min <= hit.x && hit.x <= self.x_max {
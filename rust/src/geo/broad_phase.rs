@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use generational_arena::Index;
+
+use super::vec2::*;
+use super::bounds2::Bounds2;
+use super::collision_system::CircleObstacle;
+
+/// How big each cell is (in pixels). A few tiles across, so a step's query only looks at a handful of cells.
+const CELL_SIZE : f32 = 64.0;
+/// Obstacles covering more cells than this (like a huge circle) are just always checked, rather than filling thousands of cells.
+const MAX_CELLS_PER_OBSTACLE : i64 = 1024;
+
+/// The range of cells something covers (lowest and highest cell coordinates, inclusive).
+type CellSpan = ((i32, i32), (i32, i32));
+
+/// Where an obstacle is kept in the grid.
+#[derive(Debug, Clone, PartialEq)]
+enum Placement {
+	/// In every cell of a span.
+	Cells(CellSpan),
+	/// In the list that every query gets (it has no bounds, like a Line).
+	Everywhere,
+}
+
+/// A uniform grid of the collision obstacles, so queries only have to test the ones near where they are (rather than every obstacle in the map).
+///
+/// Unlike `SpatialHash` (which is refilled every step), this is kept up to date as obstacles are added, removed, and moved.
+/// Queries give back candidates: obstacles whose cells overlap the area asked about. They still need the exact test.
+pub struct ObstacleGrid {
+	/// The obstacles overlapping each cell (by cell coordinates).
+	cells : HashMap<(i32, i32), Vec<Index>>,
+	/// Obstacles that are always candidates.
+	everywhere : Vec<Index>,
+	/// Where each obstacle was put (so it can be taken back out).
+	placements : HashMap<Index, Placement>,
+}
+
+impl ObstacleGrid {
+	/// Creates an empty instance.
+	pub fn new() -> ObstacleGrid {
+		ObstacleGrid {
+			cells : HashMap::new(),
+			everywhere : Vec::new(),
+			placements : HashMap::new(),
+		}
+	}
+
+	/// Removes everything.
+	pub fn clear(&mut self) {
+		self.cells.clear();
+		self.everywhere.clear();
+		self.placements.clear();
+	}
+
+	/// Adds an obstacle (or moves it, if it's already in).
+	pub fn insert(&mut self, index : Index, obstacle : &CircleObstacle) {
+		let placement = match obstacle_bounds(obstacle).map(|bounds| cell_span(&bounds)) {
+			Some(span) if cell_count(&span) <= MAX_CELLS_PER_OBSTACLE => Placement::Cells(span),
+			_ => Placement::Everywhere,
+		};
+		if self.placements.get(&index) == Some(&placement) {
+			return; // Hasn't changed cells, which is most moves.
+		}
+		self.remove(index);
+		match &placement {
+			Placement::Cells(span) => {
+				for_each_cell(span, |cell| self.cells.entry(cell).or_default().push(index));
+			},
+			Placement::Everywhere => { self.everywhere.push(index); },
+		}
+		self.placements.insert(index, placement);
+	}
+
+	/// Takes an obstacle back out (if it's in).
+	pub fn remove(&mut self, index : Index) {
+		match self.placements.remove(&index) {
+			Some(Placement::Cells(span)) => {
+				let cells = &mut self.cells;
+				for_each_cell(&span, |cell| {
+					if let Some(bucket) = cells.get_mut(&cell) {
+						bucket.retain(|other| index != *other);
+						if bucket.is_empty() {
+							cells.remove(&cell);
+						}
+					}
+				});
+			},
+			Some(Placement::Everywhere) => { self.everywhere.retain(|other| index != *other); },
+			None => {},
+		}
+	}
+
+	/// Finds every obstacle that might overlap an area, in the order they were added to the arena (so results don't depend on the grid).
+	pub fn query(&self, bounds : &Bounds2) -> Vec<Index> {
+		let mut found = self.everywhere.clone();
+		let span = cell_span(bounds);
+		if cell_count(&span) <= MAX_CELLS_PER_OBSTACLE {
+			for_each_cell(&span, |cell| {
+				if let Some(bucket) = self.cells.get(&cell) {
+					found.extend(bucket.iter().copied());
+				}
+			});
+		} else {
+			// Asking about a huge area, so it's faster to just go through what's in it.
+			found.extend(self.placements.keys().copied());
+		}
+		found.sort_unstable_by_key(|index| index.into_raw_parts());
+		found.dedup(); // Obstacles spanning several cells show up once per cell.
+		found
+	}
+}
+
+/// Gets the area an obstacle covers. None if it goes on forever.
+fn obstacle_bounds(obstacle : &CircleObstacle) -> Option<Bounds2> {
+	match obstacle {
+		CircleObstacle::LineSegment(segment) => Some(Bounds2::from_points(&segment.start, &segment.end)),
+		CircleObstacle::Line(_)              => None,
+		CircleObstacle::Point(position)      => Some(Bounds2::from_points(position, position)),
+		CircleObstacle::Circle(circle)       => Some(Bounds2::from_centered_rect(&circle.center, 2.0 * circle.radius, 2.0 * circle.radius)),
+	}
+}
+
+/// Gets the cells an area covers.
+fn cell_span(bounds : &Bounds2) -> CellSpan {
+	let cell = |value : f32| (value / CELL_SIZE).floor() as i32; // Saturates for huge (or infinite) values.
+	(
+		(cell(bounds.x_min()), cell(bounds.y_min())),
+		(cell(bounds.x_max()), cell(bounds.y_max())),
+	)
+}
+
+/// Gets how many cells are in a span.
+fn cell_count(span : &CellSpan) -> i64 {
+	let ((x_low, y_low), (x_high, y_high)) = *span;
+	(x_high as i64 - x_low as i64 + 1) * (y_high as i64 - y_low as i64 + 1)
+}
+
+/// Runs something on every cell in a span.
+fn for_each_cell<F : FnMut((i32, i32))>(span : &CellSpan, mut action : F) {
+	let ((x_low, y_low), (x_high, y_high)) = *span;
+	for x in x_low..=x_high {
+		for y in y_low..=y_high {
+			action((x, y));
+		}
+	}
+}
+
+/// Gets the area something covers while moving, given how far it reaches from its center on each axis.
+pub fn swept_bounds(position : &Vec2, movement : &Vec2, reach : &Vec2) -> Bounds2 {
+	let end = position + movement;
+	Bounds2::from_points(
+		&Vec2::new(position.x.min(end.x) - reach.x, position.y.min(end.y) - reach.y),
+		&Vec2::new(position.x.max(end.x) + reach.x, position.y.max(end.y) + reach.y),
+	)
+}
+
+#[cfg(test)]
+mod test_obstacle_grid {
+	use super::*;
+	use crate::geo::line::Line;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::circle::Circle;
+
+	/// Makes an index (as if from an arena).
+	fn index(slot : usize) -> Index {
+		Index::from_raw_parts(slot, 0)
+	}
+
+	/// Asks about a small area around a point.
+	fn near(grid : &ObstacleGrid, x : f32, y : f32) -> Vec<Index> {
+		grid.query(&Bounds2::from_centered_rect(&Vec2::new(x, y), 2.0, 2.0))
+	}
+
+	#[test]
+	fn queries() {
+		let mut grid = ObstacleGrid::new();
+		grid.insert(index(0), &CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(200.0, 0.0))));
+		grid.insert(index(1), &CircleObstacle::Point(Vec2::new(1000.0, 1000.0)));
+		grid.insert(index(2), &CircleObstacle::Circle(Circle::new(&Vec2::new(-100.0, 0.0), 10.0)));
+		assert_eq!(grid.placements.len(), 3);
+
+		// Only what's nearby (once each, even if it spans several cells).
+		assert_eq!(near(&grid, 150.0, 1.0), vec![index(0)]);
+		assert_eq!(near(&grid, 1000.0, 1000.0), vec![index(1)]);
+		assert!(near(&grid, 500.0, 500.0).is_empty());
+		assert_eq!(grid.query(&Bounds2::from_points(&Vec2::new(-100.0, -1.0), &Vec2::new(100.0, 1.0))), vec![index(0), index(2)]);
+
+		// Lines go on forever, so they're always candidates.
+		grid.insert(index(3), &CircleObstacle::Line(Line::new(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0))));
+		assert_eq!(near(&grid, 500.0, 500.0), vec![index(3)]);
+
+		// Moving and removing update what's found.
+		grid.insert(index(1), &CircleObstacle::Point(Vec2::new(500.0, 500.0)));
+		assert_eq!(near(&grid, 500.0, 500.0), vec![index(1), index(3)]);
+		assert_eq!(near(&grid, 1000.0, 1000.0), vec![index(3)]); // Only the line is left there.
+		grid.remove(index(3));
+		grid.remove(index(3)); // Already gone, so does nothing.
+		assert_eq!(near(&grid, 500.0, 500.0), vec![index(1)]);
+		assert_eq!(grid.placements.len(), 3);
+
+		// Huge areas find everything.
+		assert_eq!(grid.query(&Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 1.0e9, 1.0e9)), vec![index(0), index(1), index(2)]);
+	}
+}
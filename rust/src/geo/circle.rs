@@ -6,9 +6,11 @@ use super::vec2::*;
 use super::line::*;
 use super::line_segment::*;
 use super::collider::*;
+use crate::debug_println;
 
 /// A 2D circle.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Circle {
 	pub center : Vec2, // The center.
 	pub radius : f32, // The radius.
@@ -31,7 +33,7 @@ impl<'l> Collider<'l, Line> for Circle {
 			remainder: movement.clone(),
 			source: Index::from_raw_parts(0, 0), // A generic index that will be replaced by the caller.
 		};
-		println!("normal: {:?}", &deflection.normal);
+		debug_println!("normal: {:?}", &deflection.normal);
 
 		// Push the start of the line out if it's too close.
 		let mut ortho = (&self.center - &obstacle.origin).ext(&obstacle.delta);
@@ -46,13 +48,13 @@ impl<'l> Collider<'l, Line> for Circle {
 		} else {
 			false
 		};
-		println!("ortho: {:?}; moved = {:?}", ortho, moved);
+		debug_println!("ortho: {:?}; moved = {:?}", ortho, moved);
 
 		// Find if/when the movement would hit.
 		let denom = movement.ext(&obstacle.delta);
-		println!("denom: {:?}", denom);
-		if denom.abs() < EPSILON && (ortho_dist - self.radius).abs() < EPSILON {
-			println!("Found skimming hit.");
+		debug_println!("denom: {:?}", denom);
+		if denom.abs() < distance_epsilon() && (ortho_dist - self.radius).abs() < distance_epsilon() {
+			debug_println!("Found skimming hit.");
 			// If start just touching and are moving parallel to the line, then it's skimming.
 			deflection.times.make_all();
 			return Some(deflection);
@@ -63,9 +65,9 @@ impl<'l> Collider<'l, Line> for Circle {
 		));
 
 		// If not time between 0.0 and 1.0, then no hit happened.
-		println!("times = {:?}", &deflection.times);
+		debug_println!("times = {:?}", &deflection.times);
 		if deflection.split_remainder() {
-			println!("Gave up: all times invalid.");
+			debug_println!("Gave up: all times invalid.");
 			// Both out of range, then there was no hit.
 			// And since pushing out the start always adds 0.0 to times, should always return 'None' here...
 			return None;
@@ -267,10 +269,10 @@ impl<'l> Collider<'l, Vec2> for Circle {
 		};
 
 		// If not time between 0.0 and 1.0, then no hit happened.
-		println!("times = {:?}", &deflection.times);
+		debug_println!("times = {:?}", &deflection.times);
 		let bounded = (&deflection.times).intersect(Range::from_values(0.0, 1.0));
 		if bounded.is_empty() {
-			println!("Gave up: all times invalid.");
+			debug_println!("Gave up: all times invalid.");
 			// Both out of range, then there was no hit.
 			// And since pushing out the start always adds 0.0 to times, should always return 'None' here...
 			return None;
@@ -406,7 +408,7 @@ fn deflect_with_line_segment_middle(circle : &Circle, movement : &Vec2, obstacle
 			remainder: movement.clone(),
 			source: Index::from_raw_parts(0, 0), // A generic index that will be replaced by the caller.
 		};
-		println!("normal: {:?}", &deflection.normal);
+		debug_println!("normal: {:?}", &deflection.normal);
 
 		// Push the start of the line out if it's too close.
 		let starting_offset = &circle.center - &obstacle.start; // Diff
@@ -423,14 +425,14 @@ fn deflect_with_line_segment_middle(circle : &Circle, movement : &Vec2, obstacle
 		} else {
 			false
 		};
-		println!("ortho: {:?}; moved = {:?}", ortho, moved);
+		debug_println!("ortho: {:?}; moved = {:?}", ortho, moved);
 
 		// TODO: Deduplicate the normal finding code. (Into where?)
 		// Find if/when the movement would hit.
 		let denom = movement.ext(&obstacle.direction);
-		println!("denom: {:?}", denom);
-		if denom.abs() < EPSILON && (ortho_dist - circle.radius).abs() < EPSILON {
-			println!("Found skimming hit.");
+		debug_println!("denom: {:?}", denom);
+		if denom.abs() < distance_epsilon() && (ortho_dist - circle.radius).abs() < distance_epsilon() {
+			debug_println!("Found skimming hit.");
 			// If start just touching and are moving parallel to the line, then it's skimming.
 			deflection.times.make_all();
 			return Some(deflection);
@@ -442,9 +444,9 @@ fn deflect_with_line_segment_middle(circle : &Circle, movement : &Vec2, obstacle
 
 		// TODO: Deduplicate the normal finding code. (Into where?)
 		// If not time between 0.0 and 1.0, then no hit happened.
-		println!("times = {:?}", &deflection.times);
+		debug_println!("times = {:?}", &deflection.times);
 		if deflection.split_remainder() {
-			println!("Gave up: all times invalid.");
+			debug_println!("Gave up: all times invalid.");
 			// Both out of range, then there was no hit.
 			// And since pushing out the start always adds 0.0 to times, should always return 'None' here...
 			return None;
@@ -454,7 +456,7 @@ fn deflect_with_line_segment_middle(circle : &Circle, movement : &Vec2, obstacle
 		distance_along = (&deflection.position - &obstacle.start).dot(&obstacle.direction);
 		if 0.0 > distance_along || distance_along > obstacle.length {
 			// Hit outside of the line segment.
-			println!("Gave up: beyond line segment edges @ {:?} vs [0.0 to {:?}].", distance_along, obstacle.direction);
+			debug_println!("Gave up: beyond line segment edges @ {:?} vs [0.0 to {:?}].", distance_along, obstacle.direction);
 			return if moved { Some(deflection) } else { None };
 		}
 
@@ -535,10 +537,11 @@ impl<'l> Collider<'l, LineSegment> for Circle {
 		if let Some(deflection) = self.deflect_with(movement, &obstacle.end) {
 			deflections.push(deflection);
 		}
-		if let Some(mut total) = TotalDeflection::try_new(deflections) {
+		if let Some(mut total) = TotalDeflection::try_new(deflections.clone()) {
 			Some(total.deflections.remove(0))
 		} else {
-			None
+			// Nothing deflected, but still pass back if it was being skimmed (so callers can tell what's being touched).
+			deflections.into_iter().find(|deflection| deflection.times.contains(0.0))
 		}
 	}
 }
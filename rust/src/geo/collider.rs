@@ -5,6 +5,7 @@ use std::f32::INFINITY;
 use super::consts::*;
 use super::range::*;
 use super::vec2::*;
+use crate::debug_println;
 
 /// The result of deflecting a collider.
 #[derive(Debug, Clone)]
@@ -56,13 +57,13 @@ impl Deflection {
 		// TODO: Special handling if it's bascially at the end?
 		// If moving in same direction as normal, then no hit happened, but skimmed, didn't hit.
 		let coincidence = (&self.remainder).dot(&self.normal);
-		if -EPSILON <= coincidence {
-			println!("Gave up: coindicence = {:?}.", coincidence);
+		if -distance_epsilon() <= coincidence {
+			debug_println!("Gave up: coindicence = {:?}.", coincidence);
 			self.deflected = false;
 			// Positive or zero coincidence means moving away from wall or perpendicular to it.
 		} else {
 			// At this point you've definitely hit and deflected.
-			println!("Deflected!");
+			debug_println!("Deflected!");
 			self.deflected = true;
 			self.remainder += (&self.normal).scale(-coincidence); // TODO: This is redundant with limit_movement_with_normals()!
 		}
@@ -81,52 +82,66 @@ pub struct TotalDeflection {
 	///
 	/// The first is always the one that's considered **the** deflection that occurred.
 	pub deflections : Vec<Deflection>,
+	/// How fast the surface of the first deflection is moving (in pixels per second). Zero unless the obstacle is moving (see `CollisionSystem::set_obstacle_offset()`).
+	pub carry_velocity : Vec2,
+}
+
+/// Reflects a movement (or velocity) off of a surface.
+/// A restitution of 0.0 just removes the part going into the surface (a pure slide), while 1.0 bounces all of it back out.
+/// Movement that's already going away from the surface is left alone.
+pub fn reflect_movement(movement : &Vec2, normal : &Vec2, restitution : f32) -> Vec2 {
+	let coincidence = movement.dot(normal);
+	if 0.0 <= coincidence {
+		return movement.clone();
+	}
+	movement - normal.scale(coincidence * (1.0 + restitution))
 }
 
 /// Limits the given input vector according to a set of normals passed in.
+///
+/// Movement that only goes into one surface slides along it. When it goes into several, it slides along whichever one leaves it going into none of the others (like rounding a convex corner), keeping the most movement if more than one does.
+/// Only if no slide works (like in a concave corner) is all the movement dropped.
 pub fn limit_movement_with_normals(movement : &Vec2, normals : &Vec<Vec2>) -> Vec2 {
-	// Try limiting the remainder vector if there's more than one normal.
-	// Do this mainly by checking if there are normals on the left and right of the remaining movement. That means drop the remaining movement.
-	let mut on_pos : bool = false;
-	let mut pos_perp : bool = false;
-	let mut on_neg : bool = false;
-	let mut neg_perp : bool = false;
-	let mut result = movement.clone();
+	let mut best : Option<Vec2> = None;
+	let mut blocked = false;
 	for normal in normals {
-		// Ignore if the normal is in the direction of movement.
+		// Ignore if the normal is in the direction of movement (or perpendicular to it).
 		let coincidence = movement.dot(normal);
-		if coincidence > EPSILON { continue; } // TODO: This specific location forced EPSILON higher (to allow circles that exactly fit to slip through a passageway. Is it especially error-prone?
-		let is_perp = coincidence > -EPSILON;
-		// Check which side it is on.
-		if 0.0 > movement.ext(normal) {
-			if is_perp {
-				pos_perp = true;
-			} else {
-				on_pos = true;
-			}
-		} else {
-			if is_perp {
-				neg_perp = true;
-			} else {
-				on_neg = true;
-			}
-		}
-		// Remove the normal from it.
-		//if !is_perp {
-		result -= normal.scale(normal.dot(&result));
-		//}
-		if (on_pos && on_neg) || (pos_perp && on_neg) || (on_pos && neg_perp) || (result.length() < EPSILON) {
-			return Vec2::new(0.0, 0.0);
+		if coincidence > -distance_epsilon() { continue; } // TODO: This specific location forced EPSILON higher (to allow circles that exactly fit to slip through a passageway. Is it especially error-prone?
+		blocked = true;
+		// Try sliding along this surface, and make sure that doesn't go into any of the others.
+		let slide = movement - normal.scale(coincidence);
+		if slide.length() < distance_epsilon() { continue; }
+		if normals.iter().any(|other| slide.dot(other) < -distance_epsilon()) { continue; }
+		let longer = match &best {
+			Some(existing) => existing.length() < slide.length(),
+			None => true,
+		};
+		if longer {
+			best = Some(slide);
 		}
 	}
-	result
+	if !blocked {
+		return movement.clone();
+	}
+	best.unwrap_or_else(Vec2::zero)
+}
+
+/// Whether two (unit length) surface normals are close enough to count as the same surface (see `normal_angle_epsilon()`).
+pub fn same_normal(first : &Vec2, second : &Vec2) -> bool {
+	(first - second).length() < normalization_epsilon() || normal_angle_epsilon().cos() <= first.dot(second)
 }
 
 impl TotalDeflection {
 	/// Combines multiple Deflections.
-	/// Always yields the nearest. If there are multiple that fit that description, chooses first.
-	/// If more than one unique normal applies at that time, then will try to apply the new ones. This will generally zero any movement toward two unique normals (not 100% sure if there's a better way).
+	/// Always yields the nearest. If there are multiple that fit that description, chooses the one with the lowest source index.
+	/// If more than one unique normal applies at that time, then the remainder is limited by all of them (see `limit_movement_with_normals()`).
+	///
+	/// The result doesn't depend on the order the items come in (which follows the obstacle Arena's insert/remove history), so ties always break the same way.
 	pub fn try_new(mut items : Vec<Deflection>) -> Option<TotalDeflection> {
+		// Put everything in source order first (a stable sort, so the same source keeps its own order).
+		items.sort_by_key(|item| item.source.into_raw_parts());
+
 		// First pass: find the Deflection with the earliest start time.
 		let mut soonest_time : f32 = INFINITY;
 		let mut soonest_index : usize = items.len();
@@ -157,16 +172,9 @@ impl TotalDeflection {
 		items.retain(|hit| {
 			let keep = hit.times.contains(soonest_time);
 			if keep {
-				let new_normal = hit.normal.clone();
-				let mut unique = true;
-				for norm in &normals {
-					if (norm - &new_normal).length() < EPSILON {
-						unique = false;
-						break;
-					}
-				}
-				if unique {
-					normals.push(new_normal);
+				// Normals that are nearly the same count as one surface (so seams don't look like corners).
+				if !normals.iter().any(|norm| same_normal(norm, &hit.normal)) {
+					normals.push(hit.normal.clone());
 				}
 			}
 			keep
@@ -183,9 +191,24 @@ impl TotalDeflection {
 			final_position: items[0].position + final_remainder,
 			normals,
 			deflections: items,
+			carry_velocity: Vec2::zero(),
 		})
 	}
 
+	/// Bounces the remaining movement back off of the (first) surface that was hit, instead of just sliding along it.
+	/// The movement must be the full movement that this was made from. See reflect_movement() for what the restitution does.
+	pub fn apply_restitution(&mut self, movement : &Vec2, restitution : f32) {
+		if 0.0 >= restitution {
+			return;
+		}
+		let deflection = &self.deflections[0];
+		let time = deflection.times.min().unwrap().max(0.0).min(1.0);
+		let coincidence = movement.scale(1.0 - time).dot(&deflection.normal);
+		if 0.0 > coincidence {
+			self.final_position += deflection.normal.scale(-coincidence * restitution);
+		}
+	}
+
 }
 
 #[cfg(test)]
@@ -362,3 +385,119 @@ mod test_combine_deflection {
 		assert_eq!(remainder.y, 1.0);
 	}
 }
+
+#[cfg(test)]
+mod test_deflection_order {
+	use super::*;
+
+	/// Makes a deflection at time 0.5 from some source.
+	fn hit(normal : Vec2, source : usize) -> Deflection {
+		Deflection {
+			times: Range::from_value(0.5),
+			normal,
+			deflected: true,
+			position:  Vec2::zero(),
+			remainder: Vec2::new(1.0, 1.0),
+			source: Index::from_raw_parts(source, 0),
+		}
+	}
+
+	#[test]
+	fn ties_break_by_source() {
+		let forward = TotalDeflection::try_new(vec![
+			hit(Vec2::new(-1.0, 0.0), 2),
+			hit(Vec2::new(0.0, -1.0), 5),
+		]).unwrap();
+		let backward = TotalDeflection::try_new(vec![
+			hit(Vec2::new(0.0, -1.0), 5),
+			hit(Vec2::new(-1.0, 0.0), 2),
+		]).unwrap();
+		for result in &[forward, backward] {
+			assert_eq!(result.deflections[0].source, Index::from_raw_parts(2, 0));
+			assert_eq!(result.deflections[1].source, Index::from_raw_parts(5, 0));
+			assert_eq!((result.normals[0].x, result.normals[0].y), (-1.0, 0.0));
+			assert_eq!((result.normals[1].x, result.normals[1].y), (0.0, -1.0));
+		}
+	}
+
+	#[test]
+	fn sooner_still_wins() {
+		let mut later = hit(Vec2::new(-1.0, 0.0), 1);
+		later.times = Range::from_value(0.9);
+		let result = TotalDeflection::try_new(vec![later, hit(Vec2::new(0.0, -1.0), 7)]).unwrap();
+		assert_eq!(result.deflections[0].source, Index::from_raw_parts(7, 0));
+		assert_eq!(result.deflections.len(), 1);
+	}
+
+	#[test]
+	fn generations_break_ties() {
+		let mut old = hit(Vec2::new(-1.0, 0.0), 3);
+		old.source = Index::from_raw_parts(3, 4);
+		let result = TotalDeflection::try_new(vec![old, hit(Vec2::new(0.0, -1.0), 3)]).unwrap();
+		assert_eq!(result.deflections[0].source, Index::from_raw_parts(3, 0));
+	}
+}
+
+#[cfg(test)]
+mod test_limit_movement {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+
+	#[test]
+	fn merges_near_normals() {
+		let tilted = Vec2::new(0.004, 1.0).norm();
+		assert!(same_normal(&Vec2::new(0.0, 1.0), &tilted));
+		assert!(!same_normal(&Vec2::new(0.0, 1.0), &Vec2::new(0.1, 1.0).norm()));
+
+		let mut seam = Deflection {
+			times: Range::from_value(0.5),
+			normal: Vec2::new(0.0, 1.0),
+			deflected: true,
+			position:  Vec2::zero(),
+			remainder: Vec2::new(1.0, -1.0),
+			source: Index::from_raw_parts(0, 0),
+		};
+		let mut other = seam.clone();
+		other.normal = Vec2::new(-0.004, 1.0).norm();
+		other.source = Index::from_raw_parts(1, 0);
+		seam.remainder = Vec2::new(1.0, -1.0);
+		let result = TotalDeflection::try_new(vec![seam, other]).unwrap();
+		assert_eq!(result.normals.len(), 1);
+		assert_vec2_about_eq!(result.final_position, Vec2::new(1.0, 0.0));
+	}
+
+	#[test]
+	fn convex_corner_slides() {
+		// Going right (and down) over a floor that's also touching the bottom of a slope that leans away.
+		let normals = vec![Vec2::new(0.0, 1.0), Vec2::new(0.6, 0.8)];
+		assert_vec2_about_eq!(limit_movement_with_normals(&Vec2::new(1.0, -1.0), &normals), Vec2::new(1.0, 0.0));
+		// Going into a slope that leans toward the movement rides up it instead of going into the floor.
+		let normals = vec![Vec2::new(0.0, 1.0), Vec2::new(-0.6, 0.8)];
+		let limited = limit_movement_with_normals(&Vec2::new(1.0, -1.0), &normals);
+		assert_vec2_about_eq!(limited, Vec2::new(0.16, 0.12));
+	}
+
+	#[test]
+	fn concave_corner_stops() {
+		let normals = vec![Vec2::new(0.0, 1.0), Vec2::new(-1.0, 0.0)];
+		assert_vec2_about_eq!(limit_movement_with_normals(&Vec2::new(1.0, -1.0), &normals), Vec2::zero());
+		// But leaving the corner is fine.
+		assert_vec2_about_eq!(limit_movement_with_normals(&Vec2::new(-1.0, 1.0), &normals), Vec2::new(-1.0, 1.0));
+		assert_vec2_about_eq!(limit_movement_with_normals(&Vec2::new(-1.0, -1.0), &normals), Vec2::new(-1.0, 0.0));
+	}
+}
+
+#[cfg(test)]
+mod test_reflect_movement {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+
+	#[test]
+	fn reflect() {
+		let normal = Vec2::new(0.0, 1.0);
+		assert_vec2_about_eq!(reflect_movement(&Vec2::new(1.0, -2.0), &normal, 0.0), Vec2::new(1.0, 0.0));
+		assert_vec2_about_eq!(reflect_movement(&Vec2::new(1.0, -2.0), &normal, 0.5), Vec2::new(1.0, 1.0));
+		assert_vec2_about_eq!(reflect_movement(&Vec2::new(1.0, -2.0), &normal, 1.0), Vec2::new(1.0, 2.0));
+		assert_vec2_about_eq!(reflect_movement(&Vec2::new(1.0, 2.0), &normal, 1.0), Vec2::new(1.0, 2.0)); // Already moving away.
+	}
+}
@@ -0,0 +1,157 @@
+use super::vec2::*;
+
+/// The default for how long all the collision queries in one frame can take before the audit gets dumped (in seconds).
+pub const DEFAULT_AUDIT_BUDGET_SECONDS : f64 = 0.004;
+
+/// A single collision query, as the audit saw it.
+#[derive(Debug, Clone)]
+pub struct AuditedQuery {
+	/// Where the circle started (in map coordinates).
+	pub position : Vec2,
+	/// The circle's radius.
+	pub radius : f32,
+	/// How many obstacles were tested against.
+	pub tested : usize,
+	/// How many of those were hit.
+	pub hit : usize,
+	/// How long the query took (in seconds).
+	pub seconds : f64,
+}
+
+/// Everything the audit saw during one frame.
+#[derive(Debug, Clone)]
+pub struct AuditFrame {
+	/// How many collision queries were made.
+	pub queries : usize,
+	/// How many obstacles were tested against (across all queries).
+	pub tested : usize,
+	/// How many obstacles were hit (across all queries).
+	pub hit : usize,
+	/// How long all the queries took (in seconds).
+	pub seconds : f64,
+	/// The slowest query.
+	pub worst : Option<AuditedQuery>,
+}
+
+impl AuditFrame {
+	/// Creates an empty instance.
+	pub fn new() -> AuditFrame {
+		AuditFrame {
+			queries : 0,
+			tested : 0,
+			hit : 0,
+			seconds : 0.0,
+			worst : None,
+		}
+	}
+
+	/// Describes the frame in a way that's easy to read in the console.
+	pub fn describe(&self) -> String {
+		let mut text = format!(
+			"Collision queries took {:.2}ms this frame: {} queries tested {} obstacles and hit {}.",
+			self.seconds * 1000.0, self.queries, self.tested, self.hit,
+		);
+		if let Some(worst) = &self.worst {
+			text += &format!(
+				" Worst was a circle (radius {}) at ({}, {}) in map coordinates, which tested {} obstacles and hit {} in {:.2}ms.",
+				worst.radius, worst.position.x, worst.position.y, worst.tested, worst.hit, worst.seconds * 1000.0,
+			);
+		}
+		text
+	}
+}
+
+/// Keeps track of how much work collision queries do each frame, so hotspots in the level geometry can be found.
+pub struct CollisionAudit {
+	/// Whether queries are being recorded.
+	enabled : bool,
+	/// How long all the queries in a frame can take before the frame gets reported (in seconds).
+	budget_seconds : f64,
+	/// The frame being recorded.
+	frame : AuditFrame,
+}
+
+impl CollisionAudit {
+	/// Creates a disabled instance.
+	pub fn new() -> CollisionAudit {
+		CollisionAudit {
+			enabled : false,
+			budget_seconds : DEFAULT_AUDIT_BUDGET_SECONDS,
+			frame : AuditFrame::new(),
+		}
+	}
+
+	/// Gets whether queries are being recorded.
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Starts or stops recording queries. Either way the current frame starts over.
+	pub fn set_enabled(&mut self, enabled : bool) {
+		self.enabled = enabled;
+		self.frame = AuditFrame::new();
+	}
+
+	/// Sets how long all the queries in a frame can take before the frame gets reported (in seconds).
+	pub fn set_budget(&mut self, budget_seconds : f64) {
+		self.budget_seconds = budget_seconds.max(0.0);
+	}
+
+	/// Records a single query. Does nothing if the audit isn't enabled.
+	pub fn record(&mut self, query : AuditedQuery) {
+		if !self.enabled {
+			return;
+		}
+		self.frame.queries += 1;
+		self.frame.tested += query.tested;
+		self.frame.hit += query.hit;
+		self.frame.seconds += query.seconds;
+		if self.frame.worst.as_ref().map_or(true, |worst| worst.seconds < query.seconds) {
+			self.frame.worst = Some(query);
+		}
+	}
+
+	/// Ends the current frame, giving it back if it went over the budget.
+	pub fn finish_frame(&mut self) -> Option<AuditFrame> {
+		let frame = std::mem::replace(&mut self.frame, AuditFrame::new());
+		if self.enabled && self.budget_seconds < frame.seconds {
+			Some(frame)
+		} else {
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_collision_audit {
+	use super::*;
+
+	fn query(x : f32, tested : usize, hit : usize, seconds : f64) -> AuditedQuery {
+		AuditedQuery { position : Vec2::new(x, 0.0), radius : 1.0, tested, hit, seconds }
+	}
+
+	#[test]
+	fn only_reports_frames_over_budget() {
+		let mut audit = CollisionAudit::new();
+		audit.record(query(0.0, 10, 1, 1.0));
+		assert!(audit.finish_frame().is_none()); // Disabled, so nothing was recorded.
+
+		audit.set_enabled(true);
+		audit.set_budget(0.01);
+		audit.record(query(0.0, 10, 1, 0.002));
+		assert!(audit.finish_frame().is_none());
+
+		audit.record(query(1.0, 10, 0, 0.004));
+		audit.record(query(2.0, 30, 2, 0.008));
+		audit.record(query(3.0, 5, 0, 0.001));
+		let frame = audit.finish_frame().unwrap();
+		assert_eq!((frame.queries, frame.tested, frame.hit), (3, 45, 2));
+		assert!((frame.seconds - 0.013).abs() < 1e-9);
+		let worst = frame.worst.unwrap();
+		assert_eq!(worst.position.x, 2.0);
+		assert_eq!(worst.tested, 30);
+
+		// Each frame starts over.
+		assert!(audit.finish_frame().is_none());
+	}
+}
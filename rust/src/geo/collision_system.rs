@@ -1,15 +1,26 @@
+use std::cell::RefCell;
+
 use generational_arena::{Arena, Index};
 
-use crate::externals::log;
+use crate::externals::{log, now_seconds};
+use crate::origin_rebase::ShiftOrigin;
 
 use super::consts::*;
 use super::vec2::*;
 use super::line::*;
 use super::line_segment::*;
 use super::circle::*;
+use super::aabb::*;
 use super::collider::*;
+use super::range::Range;
+use super::bounds2::Bounds2;
+use super::broad_phase::*;
+use super::validate::*;
+use super::collision_audit::*;
 
 /// The types of obstacles that a Circle() collider can hit.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum CircleObstacle {
 	LineSegment(LineSegment),
 	Line(Line),
@@ -17,22 +28,220 @@ pub enum CircleObstacle {
 	Circle(Circle),
 }
 
+impl CircleObstacle {
+	/// Gets the distance from a point to the closest point on the obstacle's surface.
+	pub fn distance_to(&self, point : &Vec2) -> f32 {
+		match self {
+			CircleObstacle::LineSegment(segment) => segment.shortest_distance_to_point(point),
+			CircleObstacle::Line(line)           => line.ortho_distance_to(point),
+			CircleObstacle::Point(position)      => (position - point).length(),
+			CircleObstacle::Circle(circle)       => ((&circle.center - point).length() - circle.radius).abs(),
+		}
+	}
+
+	/// Gets how far a circle would need to move to stop overlapping this obstacle (zero if it isn't overlapping).
+	/// Gives up (also returning zero) if the circle's center is right on the obstacle, as then there's no way to tell which side it should go to.
+	pub fn find_push_out(&self, center : &Vec2, radius : f32) -> Vec2 {
+		let (closest, needed) = match self {
+			CircleObstacle::LineSegment(segment) => (segment.closest_point_to(center), radius),
+			CircleObstacle::Line(line)           => (line.closest_point_to(center), radius),
+			CircleObstacle::Point(position)      => (position.clone(), radius),
+			CircleObstacle::Circle(circle)       => (circle.center.clone(), radius + circle.radius),
+		};
+		let offset = center - closest;
+		let distance = offset.length();
+		if needed <= distance || distance < distance_epsilon() {
+			return Vec2::zero();
+		}
+		offset.set_length(needed - distance)
+	}
+
+	/// Finds where a ray (with a unit length direction) first hits the obstacle's surface, if it does so within some distance.
+	/// Points are too small to hit, so they never are. The hit's source is a placeholder that the caller should replace.
+	pub fn raycast(&self, start : &Vec2, direction : &Vec2, max_distance : f32) -> Option<RaycastHit> {
+		let make_hit = |distance : f32, normal : Vec2| -> RaycastHit {
+			RaycastHit {
+				position : start + direction.scale(distance),
+				normal,
+				source : Index::from_raw_parts(0, 0), // A generic index that will be replaced by the caller.
+				distance,
+			}
+		};
+		// Rays hit lines where: start + direction * t = origin + line_direction * u.
+		let hit_line = |origin : &Vec2, line_direction : &Vec2, length : Option<f32>| -> Option<RaycastHit> {
+			let denominator = direction.ext(line_direction);
+			if denominator.abs() < normalization_epsilon() {
+				return None; // Parallel, so only grazing it at best.
+			}
+			let offset = origin - start;
+			let t = offset.ext(line_direction) / denominator;
+			let u = offset.ext(direction) / denominator;
+			if t < 0.0 || max_distance < t {
+				return None;
+			}
+			if let Some(length) = length {
+				if u < -distance_epsilon() || length + distance_epsilon() < u {
+					return None;
+				}
+			}
+			// Lines can be hit from either side, so the normal faces whichever side the ray came from.
+			let normal = line_direction.ortho().norm();
+			Some(make_hit(t, if 0.0 < normal.dot(direction) { normal.scale(-1.0) } else { normal }))
+		};
+		match self {
+			CircleObstacle::LineSegment(segment) => {
+				if 0.0 == segment.length { return None; }
+				hit_line(&segment.start, &segment.direction, Some(segment.length))
+			},
+			CircleObstacle::Line(line) => hit_line(&line.origin, &line.delta, None),
+			CircleObstacle::Point(_) => None,
+			CircleObstacle::Circle(circle) => {
+				let offset = start - &circle.center;
+				let b = direction.dot(&offset);
+				let c = offset.dot(&offset) - circle.radius * circle.radius;
+				let discriminant = b * b - c;
+				if discriminant < 0.0 {
+					return None;
+				}
+				let t = -b - discriminant.sqrt();
+				if t < 0.0 || max_distance < t {
+					return None;
+				}
+				Some(make_hit(t, (start + direction.scale(t) - &circle.center).norm()))
+			},
+		}
+	}
+}
+
+/// An obstacle as it was when a snapshot was taken (see `CollisionSystem::snapshot()`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObstacleSnapshot {
+	/// Which obstacle this is (as the raw parts of its Index, so it can be written out).
+	pub index : (usize, u64),
+	/// The obstacle itself.
+	pub obstacle : CollisionObstacle,
+}
+
 /// A general object representing a specific piece of collision geometry.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct CollisionObstacle {
 	/// The CircleObstacle that is what's collided against.
 	pub geometry : CircleObstacle,
 	/// Whether this obstacle should be collided against.
 	pub active : bool,
+	/// Arbitrary data that the creator of the obstacle can use to map collision results back to its own objects.
+	pub user_data : Option<usize>,
+	/// How bouncy the obstacle is. Zero (the default) means colliders slide along it. See reflect_movement().
+	pub restitution : f32,
+	/// If set, the obstacle is one-way: only solid to things coming at it from this (unit length) direction, like a platform that can be jumped up through and then landed on.
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub one_way : Option<Vec2>,
+	/// How far the geometry has been moved from where it was added (see `CollisionSystem::set_obstacle_offset()`).
+	#[cfg_attr(feature = "serialize", serde(default = "Vec2::zero"))]
+	pub offset : Vec2,
+	/// How fast the geometry is moving (in pixels per second), so things touching it can be carried along.
+	#[cfg_attr(feature = "serialize", serde(default = "Vec2::zero"))]
+	pub velocity : Vec2,
 }
 
-/// The max number of iterations that collisions are allowed to go through.
+impl CollisionObstacle {
+	/// Whether a collider (that started at some position and is moving) should be stopped by a deflection off of this obstacle.
+	/// One-way obstacles ignore anything that isn't coming down onto their surface side from outside of them. So a collider that's moving along the direction, touching from the other side, or already partway through (as in at the top of a jump that didn't quite clear it) passes through.
+	pub fn blocks(&self, deflection : &Deflection, position : &Vec2, movement : &Vec2) -> bool {
+		match &self.one_way {
+			None => true,
+			Some(direction) => {
+				let started_inside = deflection.times.contains(0.0) && distance_epsilon() < (deflection.position - position).length();
+				0.0 < deflection.normal.dot(direction) && distance_epsilon() >= movement.dot(direction) && !started_inside
+			},
+		}
+	}
+}
+
+/// A simple record of some collider touching a specific obstacle.
+#[derive(Debug, Clone)]
+pub struct ObstacleContact {
+	/// The obstacle that was touched.
+	pub source : Index,
+	/// The surface normal at the point of contact (pointing away from the obstacle).
+	pub normal : Vec2,
+}
+
+/// Where a ray first hit an obstacle.
+#[derive(Debug, Clone)]
+pub struct RaycastHit {
+	/// Where the ray hit the obstacle's surface.
+	pub position : Vec2,
+	/// The surface normal where it was hit (pointing back toward where the ray came from).
+	pub normal : Vec2,
+	/// The obstacle that was hit.
+	pub source : Index,
+	/// How far along the ray the hit was.
+	pub distance : f32,
+}
+
+/// The default max number of iterations that collisions are allowed to go through.
 const COLLISION_ITERATION_MAX : usize = 5;
+/// How much bigger than a query's area the broad phase looks (in pixels), so things only just touching are still tested.
+const BROAD_PHASE_MARGIN : f32 = 1.0;
+
+/// Gets the area the broad phase should look in for something with some reach (on each axis) that's moving.
+fn broad_phase_bounds(position : &Vec2, movement : &Vec2, reach : &Vec2) -> Bounds2 {
+	swept_bounds(position, movement, &(reach + Vec2::new(BROAD_PHASE_MARGIN, BROAD_PHASE_MARGIN)))
+}
+
+/// What to do when a collider runs out of iterations before using up all of its movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IterationEscape {
+	/// Keep wherever the last iteration was heading. Can leave things slightly inside the geometry.
+	KeepMoving,
+	/// Push the collider out of the geometry where the last collision happened, and drop the rest of the movement.
+	DepenetrateAndStop,
+}
+
+/// The result of colliding a circle through (possibly) several rounds of deflection.
+#[derive(Debug)]
+pub struct CircleCollision {
+	/// Each round of deflection (in order).
+	pub deflections : Vec<TotalDeflection>,
+	/// Whether the iteration budget ran out before all the movement was used up.
+	pub exhausted : bool,
+}
+
+/// Options for one round of circle collision (see `CollisionSystem::collide_circle_query()`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircleQuery {
+	/// How bouncy to treat every obstacle as. None uses each obstacle's own restitution.
+	pub restitution : Option<f32>,
+	/// Whether to keep contacts that didn't deflect the movement (like a floor being walked along) in the result. Use `contacts_only()` when just those are wanted.
+	pub include_skimming : bool,
+}
+
+impl CircleQuery {
+	/// Creates an instance with the defaults: obstacles' own restitution, and skimming contacts included.
+	pub const fn new() -> CircleQuery {
+		CircleQuery {
+			restitution : None,
+			include_skimming : true,
+		}
+	}
+}
 
 /// An easy way to collide a Circle() collider against multiple other objects.
-/// Will probably eventually also store a broad-phase collision filterer.
+/// Queries only test the obstacles the broad phase says are nearby.
 pub struct CollisionSystem {
-	/// All the obstacles being collided with.
+	/// All the obstacles being collided with. Anything that changes their geometry directly must call `rebuild_broad_phase()` afterward.
 	pub obstacles : Arena<CollisionObstacle>,
+	/// Where every obstacle is, so queries only test the nearby ones.
+	grid : ObstacleGrid,
+	/// The max number of iterations that collisions are allowed to go through.
+	iteration_budget : usize,
+	/// What to do when the iteration budget runs out.
+	escape : IterationEscape,
+	/// Records how much work collision queries do (when enabled). Queries only borrow the system, hence the RefCell.
+	audit : RefCell<CollisionAudit>,
 }
 
 impl CollisionSystem {
@@ -40,15 +249,78 @@ impl CollisionSystem {
 	pub fn new() -> CollisionSystem {
 		CollisionSystem {
 			obstacles: Arena::new(),
+			grid : ObstacleGrid::new(),
+			iteration_budget : COLLISION_ITERATION_MAX,
+			escape : IterationEscape::DepenetrateAndStop,
+			audit : RefCell::new(CollisionAudit::new()),
 		}
 	}
 
+	/// Starts or stops auditing collision queries, and sets how long they can take per frame before the frame's audit is reported (in seconds).
+	pub fn set_audit(&mut self, enabled : bool, budget_seconds : f64) {
+		let audit = self.audit.get_mut();
+		audit.set_enabled(enabled);
+		audit.set_budget(budget_seconds);
+	}
+
+	/// Moves the audit (and its settings) over from another instance, as in when the geometry is being rebuilt.
+	pub fn take_audit_from(&mut self, other : &mut CollisionSystem) {
+		std::mem::swap(self.audit.get_mut(), other.audit.get_mut());
+	}
+
+	/// Ends the current audit frame, giving it back if the queries in it went over the budget.
+	pub fn finish_audit_frame(&mut self) -> Option<AuditFrame> {
+		self.audit.get_mut().finish_frame()
+	}
+
+	/// Sets how many iterations collisions can go through, and what to do when they run out.
+	pub fn set_iteration_budget(&mut self, budget : usize, escape : IterationEscape) {
+		self.iteration_budget = budget.max(1);
+		self.escape = escape;
+	}
+
 	/// Adds the given obstacle to the collidable geometry.
 	pub fn add_obstacle(&mut self, obstacle : CircleObstacle) -> Index {
-		self.obstacles.insert(CollisionObstacle{
+		self.insert_obstacle(obstacle, None)
+	}
+
+	/// Adds the given obstacle to the collidable geometry, and tags it with some user data.
+	pub fn add_obstacle_with_user_data(&mut self, obstacle : CircleObstacle, user_data : usize) -> Index {
+		self.insert_obstacle(obstacle, Some(user_data))
+	}
+
+	/// Adds an obstacle to both the arena and the broad phase.
+	fn insert_obstacle(&mut self, obstacle : CircleObstacle, user_data : Option<usize>) -> Index {
+		let index = self.obstacles.insert(CollisionObstacle{
 			geometry : obstacle,
 			active : true,
-		})
+			user_data,
+			restitution : 0.0,
+			one_way : None,
+			offset : Vec2::zero(),
+			velocity : Vec2::zero(),
+		});
+		self.grid.insert(index, &self.obstacles[index].geometry);
+		index
+	}
+
+	/// Removes an obstacle from the collidable geometry, giving back its shape (if it existed).
+	pub fn remove_obstacle(&mut self, index : Index) -> Option<CircleObstacle> {
+		self.grid.remove(index);
+		self.obstacles.remove(index).map(|obstacle| obstacle.geometry)
+	}
+
+	/// Puts every obstacle back into the broad phase. Needed after changing obstacles' geometry directly (rather than through `set_geometry()`).
+	pub fn rebuild_broad_phase(&mut self) {
+		self.grid.clear();
+		for (index, obstacle) in &self.obstacles {
+			self.grid.insert(index, &obstacle.geometry);
+		}
+	}
+
+	/// Gets the user data associated with a given obstacle (if any).
+	pub fn get_user_data(&self, index : Index) -> Option<usize> {
+		self.obstacles.get(index).and_then(|obstacle| obstacle.user_data)
 	}
 
 	/// Let users easily enable/disable a specific obstacle.
@@ -56,57 +328,301 @@ impl CollisionSystem {
 		self.obstacles.get_mut(index).unwrap().active = enabled;
 	}
 
+	/// Replaces the shape of a specific obstacle (as in when it's moving).
+	pub fn set_geometry(&mut self, index : Index, geometry : CircleObstacle) {
+		self.grid.insert(index, &geometry);
+		self.obstacles.get_mut(index).unwrap().geometry = geometry;
+	}
+
+	/// Moves a specific obstacle's geometry so it's some offset from where it was added (as in a platform riding along a track).
+	/// Its velocity is set from how far that moved it over some time, and is reported by collisions with it so whatever's touching it can be carried along. No time means it teleported, so isn't moving.
+	pub fn set_obstacle_offset(&mut self, index : Index, offset : &Vec2, elapsed_seconds : f32) {
+		let obstacle = self.obstacles.get_mut(index).unwrap();
+		let shift = offset - obstacle.offset;
+		obstacle.geometry.shift_origin(&shift);
+		obstacle.offset = *offset;
+		obstacle.velocity = if 0.0 < elapsed_seconds { shift.scale(1.0 / elapsed_seconds) } else { Vec2::zero() };
+		self.grid.insert(index, &obstacle.geometry);
+	}
+
+	/// Sets how bouncy a specific obstacle is.
+	pub fn set_restitution(&mut self, index : Index, restitution : f32) {
+		self.obstacles.get_mut(index).unwrap().restitution = restitution;
+	}
+
+	/// Makes a specific obstacle one-way (only solid to things coming at it from a direction), or solid from every side again with None.
+	pub fn set_one_way(&mut self, index : Index, direction : Option<Vec2>) {
+		self.obstacles.get_mut(index).unwrap().one_way = direction.map(|direction| direction.norm());
+	}
+
+	/// Captures the state of every obstacle.
+	pub fn snapshot(&self) -> Vec<ObstacleSnapshot> {
+		self.obstacles.iter().map(|(index, obstacle)| ObstacleSnapshot { index : index.into_raw_parts(), obstacle : obstacle.clone() }).collect()
+	}
+
+	/// Puts obstacles back the way a snapshot had them. Obstacles are only ever added and removed when a room loads, so any that no longer exist are skipped.
+	pub fn restore(&mut self, snapshots : &[ObstacleSnapshot]) {
+		for snapshot in snapshots {
+			let index = Index::from_raw_parts(snapshot.index.0, snapshot.index.1);
+			if let Some(obstacle) = self.obstacles.get_mut(index) {
+				*obstacle = snapshot.obstacle.clone();
+				self.grid.insert(index, &obstacle.geometry);
+			}
+		}
+	}
+
+	/// Finds all active obstacles that are within some distance of a point.
+	pub fn find_obstacles_near(&self, position : &Vec2, distance : f32) -> Vec<Index> {
+		let mut found = Vec::new();
+		for (index, obstacle) in self.nearby(position, &Vec2::zero(), &Vec2::new(distance, distance)) {
+			if obstacle.active && obstacle.geometry.distance_to(position) <= distance {
+				found.push(index);
+			}
+		}
+		found
+	}
+
+	/// Gets the obstacles (active or not) the broad phase says might be within some reach (on each axis) of a moving point, in the order they were added.
+	fn nearby<'a>(&'a self, position : &Vec2, movement : &Vec2, reach : &Vec2) -> impl Iterator<Item = (Index, &'a CollisionObstacle)> + 'a {
+		let candidates = self.grid.query(&broad_phase_bounds(position, movement, reach));
+		candidates.into_iter().filter_map(move |index| self.obstacles.get(index).map(|obstacle| (index, obstacle)))
+	}
+
+	/// Finds where a ray (with a unit length direction) first hits an active obstacle, if any is within some distance.
+	/// Meant for things like line of sight and probing for the ground, where sweeping a tiny circle would be overkill.
+	pub fn raycast(&self, start : &Vec2, direction : &Vec2, max_distance : f32) -> Option<RaycastHit> {
+		let mut closest : Option<RaycastHit> = None;
+		for (index, obstacle) in self.nearby(start, &direction.scale(max_distance), &Vec2::zero()) {
+			if !obstacle.active { continue; }
+			let reach = closest.as_ref().map_or(max_distance, |hit| hit.distance);
+			if let Some(mut hit) = obstacle.geometry.raycast(start, direction, reach) {
+				if let Some(one_way) = &obstacle.one_way {
+					if 0.0 >= hit.normal.dot(one_way) || 0.0 <= direction.dot(one_way) { continue; } // Rays pass through from the other side (or along it).
+				}
+				hit.source = index;
+				closest = Some(hit);
+			}
+		}
+		closest
+	}
+
+	/// Moves a circle out of any collision geometry it's overlapping. Returns the updated position.
+	/// This is only meant for recovering from things going wrong, since normal movement through collide_circle() shouldn't ever overlap.
+	pub fn push_out_circle(&self, position : &Vec2, radius : f32) -> Vec2 {
+		let mut result = position.clone();
+		for _iteration in 0..self.iteration_budget {
+			let mut moved = false;
+			for (_index, obstacle) in self.nearby(&result.clone(), &Vec2::zero(), &Vec2::new(radius, radius)) {
+				if !obstacle.active || obstacle.one_way.is_some() { continue; } // Can't tell which side of a one-way obstacle something belongs on, so leave it be.
+				let push = obstacle.geometry.find_push_out(&result, radius);
+				if distance_epsilon() < push.length() {
+					result += push;
+					moved = true;
+				}
+			}
+			if !moved {
+				break;
+			}
+		}
+		result
+	}
+
 	/// Collides a circle with the stored collision geometry, and returns the updated movement vector.
-	pub fn collide_circle(&self, position_ : &Vec2, radius : f32, movement_ : &Vec2) -> Vec<TotalDeflection> {
+	pub fn collide_circle(&self, position : &Vec2, radius : f32, movement : &Vec2) -> Vec<TotalDeflection> {
+		self.collide_circle_checked(position, radius, movement).deflections
+	}
+
+	/// Collides a circle with the stored collision geometry, also reporting whether the iteration budget ran out.
+	/// When it does run out, the last deflection's final position is adjusted according to the escape policy.
+	pub fn collide_circle_checked(&self, position_ : &Vec2, radius : f32, movement_ : &Vec2) -> CircleCollision {
 		let mut movement = movement_.clone();
 		let mut position = position_.clone();
 		let mut result : Vec<TotalDeflection> = Vec::new();
-		for _iteration in 0..COLLISION_ITERATION_MAX {
+		for _iteration in 0..self.iteration_budget {
 			if let Some(total_deflection) = self.collide_circle_step(&position, radius, &movement) {
 				let collision = &total_deflection.deflections[0];
 				position = collision.position;
 				movement = total_deflection.final_position - collision.position;
 				result.push(total_deflection);
-				if movement.length() < EPSILON {
-					return result;
+				if movement.length() < distance_epsilon() {
+					return CircleCollision { deflections : result, exhausted : false };
 				}
 			} else {
-				return result;
+				return CircleCollision { deflections : result, exhausted : false };
 			}
 		}
 		log("Hit collision iteration max!");
-		return result;
+		if IterationEscape::DepenetrateAndStop == self.escape {
+			if let Some(last) = result.last_mut() {
+				last.final_position = self.push_out_circle(&last.deflections[0].position, radius);
+			}
+		}
+		CircleCollision { deflections : result, exhausted : true }
 	}
 
 	/// Perform one round of collision detection and send all the information to the caller.
+	/// Any bouncing uses the restitution of the obstacle that was hit.
 	pub fn collide_circle_step(&self, position : &Vec2, radius : f32, movement : &Vec2) -> Option<TotalDeflection> {
+		self.collide_circle_step_with_restitution(position, radius, movement, None)
+	}
+
+	/// Perform one round of collision detection, optionally overriding how bouncy the obstacles are.
+	/// If the restitution is None, then uses the restitution of the obstacle that was hit.
+	pub fn collide_circle_step_with_restitution(&self, position : &Vec2, radius : f32, movement : &Vec2, restitution : Option<f32>) -> Option<TotalDeflection> {
+		self.collide_circle_query(position, radius, movement, &CircleQuery { restitution, ..CircleQuery::new() })
+	}
+
+	/// Gets the surfaces a circle is touching without moving into them (the skimming contacts that `collide_circle_step()` leaves mixed in with the deflections), like the floor under something walking along it.
+	/// They're in source order.
+	pub fn contacts_only(&self, position : &Vec2, radius : f32, movement : &Vec2) -> Vec<Deflection> {
+		let mut contacts : Vec<Deflection> = self.find_hits(position, radius, movement, None).into_iter()
+			.filter(|hit| !hit.deflected && hit.times.contains(0.0))
+			.collect();
+		contacts.sort_by_key(|hit| hit.source.into_raw_parts());
+		contacts
+	}
+
+	/// Tests a moving circle against every active obstacle (or just some candidates, if given), giving every raw deflection (skimming or not).
+	fn find_hits(&self, position : &Vec2, radius : f32, movement : &Vec2, candidates : Option<&[Index]>) -> Vec<Deflection> {
 		let circle = Circle::new(position, radius);
+		let sweep = broad_phase_bounds(position, movement, &Vec2::new(radius, radius));
+		self.find_hits_with(position, radius, movement, &sweep, candidates, |geometry| match geometry {
+			CircleObstacle::LineSegment(segment) => { (&circle).deflect_with(movement, segment) },
+			CircleObstacle::Line(line)           => { (&circle).deflect_with(movement, line) },
+			CircleObstacle::Point(position)      => { (&circle).deflect_with(movement, position) },
+			CircleObstacle::Circle(obstacle) => {
+				let augmented = Circle::new(&circle.center, circle.radius + obstacle.radius);
+				(&augmented).deflect_with(movement, &obstacle.center)
+			},
+		})
+	}
+
+	/// Tests a moving box against every active obstacle, giving every raw deflection (skimming or not).
+	fn find_box_hits(&self, position : &Vec2, half_size : &Vec2, movement : &Vec2) -> Vec<Deflection> {
+		let aabb = Aabb::new(position, half_size);
+		let sweep = broad_phase_bounds(position, movement, &aabb.half_size);
+		self.find_hits_with(position, half_size.length(), movement, &sweep, None, |geometry| match geometry {
+			CircleObstacle::LineSegment(segment) => { (&aabb).deflect_with(movement, segment) },
+			CircleObstacle::Line(line)           => { (&aabb).deflect_with(movement, line) },
+			CircleObstacle::Point(position)      => { (&aabb).deflect_with(movement, position) },
+			CircleObstacle::Circle(obstacle)     => { (&aabb).deflect_with(movement, obstacle) },
+		})
+	}
+
+	/// Tests a moving collider (through a function that deflects it off of an obstacle's geometry) against every active obstacle the broad phase finds in the area it sweeps (or just some candidates, if given), giving every raw deflection that the obstacle blocks.
+	/// The radius is only for the audit (it's how big the collider is).
+	fn find_hits_with<F : Fn(&CircleObstacle) -> Option<Deflection>>(&self, position : &Vec2, radius : f32, movement : &Vec2, sweep : &Bounds2, candidates : Option<&[Index]>, deflect : F) -> Vec<Deflection> {
+		let auditing = self.audit.borrow().is_enabled();
+		let start_seconds = if auditing { now_seconds() } else { 0.0 };
+		let mut tested = 0;
 		let mut hits : Vec<Deflection> = Vec::new();
-		for (index, generic_obstacle) in &self.obstacles {
+		let nearby;
+		let indices = match candidates {
+			Some(indices) => indices,
+			None => {
+				nearby = self.grid.query(sweep);
+				&nearby[..]
+			},
+		};
+		let obstacles = indices.iter().filter_map(|index| self.obstacles.get(*index).map(|obstacle| (*index, obstacle)));
+		for (index, generic_obstacle) in obstacles {
 			if !generic_obstacle.active { continue; }
-			let maybe_deflection = match &generic_obstacle.geometry {
-				CircleObstacle::LineSegment(segment) => { (&circle).deflect_with(movement, segment) },
-				CircleObstacle::Line(line)           => { (&circle).deflect_with(movement, line) },
-				CircleObstacle::Point(position)      => { (&circle).deflect_with(movement, position) },
-				CircleObstacle::Circle(obstacle) => {
-					let augmented = Circle::new(&circle.center, circle.radius + obstacle.radius);
-					(&augmented).deflect_with(movement, &obstacle.center)
-				},
-			};
-			if let Some(mut deflection) = maybe_deflection {
+			tested += 1;
+			if let Some(mut deflection) = deflect(&generic_obstacle.geometry) {
+				if !generic_obstacle.blocks(&deflection, position, movement) { continue; }
 				deflection.source = index;
 				hits.push(deflection);
 			}
 		}
+		if auditing {
+			self.audit.borrow_mut().record(AuditedQuery {
+				position : position.clone(),
+				radius,
+				tested,
+				hit : hits.len(),
+				seconds : now_seconds() - start_seconds,
+			});
+		}
+		hits
+	}
 
-		TotalDeflection::try_new(hits)
+	/// Perform one round of collision detection with some options.
+	pub fn collide_circle_query(&self, position : &Vec2, radius : f32, movement : &Vec2, query : &CircleQuery) -> Option<TotalDeflection> {
+		self.collide_circle_query_among(position, radius, movement, query, None)
+	}
+
+	/// Perform one round of collision detection with some options, only testing some candidate obstacles if given (like the ones a ContactCache gathered nearby).
+	pub fn collide_circle_query_among(&self, position : &Vec2, radius : f32, movement : &Vec2, query : &CircleQuery, candidates : Option<&[Index]>) -> Option<TotalDeflection> {
+		let mut hits = self.find_hits(position, radius, movement, candidates);
+		if !query.include_skimming {
+			hits.retain(|hit| hit.deflected);
+		}
+		self.combine_hits(position, movement, hits, query.restitution, "CollisionSystem::collide_circle_step()")
+	}
+
+	/// Collides a box with the stored collision geometry (going through several rounds of deflection, like `collide_circle()`).
+	/// If the iteration budget runs out, the box either keeps going or stops where it last collided, depending on the escape policy.
+	pub fn collide_box(&self, position_ : &Vec2, half_size : &Vec2, movement_ : &Vec2) -> Vec<TotalDeflection> {
+		let mut movement = movement_.clone();
+		let mut position = position_.clone();
+		let mut result : Vec<TotalDeflection> = Vec::new();
+		for _iteration in 0..self.iteration_budget {
+			match self.collide_box_step(&position, half_size, &movement) {
+				Some(total_deflection) => {
+					let collision = &total_deflection.deflections[0];
+					position = collision.position;
+					movement = total_deflection.final_position - collision.position;
+					result.push(total_deflection);
+					if movement.length() < distance_epsilon() {
+						return result;
+					}
+				},
+				None => { return result; },
+			}
+		}
+		log("Hit collision iteration max!");
+		if IterationEscape::DepenetrateAndStop == self.escape {
+			if let Some(last) = result.last_mut() {
+				last.final_position = last.deflections[0].position; // Boxes are always pushed out of what they hit first, so that's a safe spot.
+			}
+		}
+		result
+	}
+
+	/// Perform one round of collision detection for a box (given its center and half of its size), and send all the information to the caller.
+	/// Any bouncing uses the restitution of the obstacle that was hit.
+	pub fn collide_box_step(&self, position : &Vec2, half_size : &Vec2, movement : &Vec2) -> Option<TotalDeflection> {
+		let hits = self.find_box_hits(position, half_size, movement);
+		self.combine_hits(position, movement, hits, None, "CollisionSystem::collide_box_step()")
+	}
+
+	/// Combines the raw deflections a collider got into the result of one round of collision, bouncing it off of the first obstacle if needed.
+	/// If that goes wrong (leaving the collider somewhere invalid), it's stopped where it started instead.
+	fn combine_hits(&self, position : &Vec2, movement : &Vec2, hits : Vec<Deflection>, restitution : Option<f32>, context : &str) -> Option<TotalDeflection> {
+		let mut result = TotalDeflection::try_new(hits);
+		if let Some(total_deflection) = &mut result {
+			total_deflection.carry_velocity = self.obstacles[total_deflection.deflections[0].source].velocity;
+			let bounciness = restitution.unwrap_or_else(|| self.obstacles[total_deflection.deflections[0].source].restitution);
+			total_deflection.apply_restitution(movement, bounciness);
+			if !total_deflection.final_position.is_finite() {
+				check_vec2(&total_deflection.final_position, context); // Just to log it (when validating).
+				// Stop the collider where it started rather than sending it somewhere invalid.
+				total_deflection.final_position = position.clone();
+				for deflection in &mut total_deflection.deflections {
+					deflection.times = Range::from_value(0.0);
+					deflection.position = position.clone();
+					deflection.remainder = Vec2::zero();
+				}
+			}
+		}
+		result
 	}
 }
 
 #[cfg(test)]
 mod test_collision_system {
 	use super::*;
-	use crate::assert_vec2_about_eq; // I have to export macros to the top-level module to be able to share them across modules in the same crate... Yeah, I can't imagine that leading to bad things in the Rust ecosystem! Why is this so difficult?
+	use crate::{assert_vec2_about_eq, assert_about_eq}; // I have to export macros to the top-level module to be able to share them across modules in the same crate... Yeah, I can't imagine that leading to bad things in the Rust ecosystem! Why is this so difficult?
 
 	#[test]
 	fn line_segment_stop() { // Make sure the line segment works.
@@ -117,6 +633,82 @@ mod test_collision_system {
 		assert_vec2_about_eq!(result[0].final_position, Vec2::new(1.0, 1.0));
 	}
 
+	#[test]
+	fn invalid_result_stops() { // Even without validation turned on.
+		let mut system = CollisionSystem::new();
+		let source = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-2.0, 0.0), &Vec2::new(2.0, 0.0))));
+		let start = Vec2::new(0.0, 2.0);
+		let hit = Deflection {
+			times : Range::from_value(0.5),
+			normal : Vec2::new(0.0, 1.0),
+			deflected : true,
+			position : Vec2::new(f32::NAN, 1.0),
+			remainder : Vec2::zero(),
+			source,
+		};
+		let result = system.combine_hits(&start, &Vec2::new(0.0, -2.0), vec![hit], Some(0.0), "test").unwrap();
+		assert!(result.final_position.is_finite()); // The about-equal check doesn't catch NaN.
+		assert_vec2_about_eq!(result.final_position, start);
+		assert_vec2_about_eq!(result.deflections[0].position, start);
+	}
+
+	#[test]
+	fn raycast() {
+		let mut system = CollisionSystem::new();
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-2.0, 0.0), &Vec2::new(2.0, 0.0))));
+		system.add_obstacle(CircleObstacle::Circle(Circle::new(&Vec2::new(10.0, 0.0), 2.0)));
+		let disabled = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-2.0, 3.0), &Vec2::new(2.0, 3.0))));
+		system.set_enabled(disabled, false);
+		let down = Vec2::new(0.0, -1.0);
+		let distance = |start : Vec2, direction : &Vec2, max_distance : f32| system.raycast(&start, direction, max_distance).map(|hit| hit.distance);
+		assert_eq!(distance(Vec2::new(1.0, 5.0), &down, 10.0), Some(5.0));
+		assert_eq!(distance(Vec2::new(1.0, 5.0), &down, 4.0), None);
+		assert_eq!(distance(Vec2::new(5.0, 5.0), &down, 10.0), None); // Past the end.
+		assert_eq!(distance(Vec2::new(10.0, 5.0), &down, 10.0), Some(3.0));
+		assert_eq!(distance(Vec2::new(1.0, -1.0), &down, 10.0), None); // Pointing away.
+	}
+
+	#[test]
+	fn raycast_hits() {
+		let mut system = CollisionSystem::new();
+		let floor = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-2.0, 0.0), &Vec2::new(2.0, 0.0))));
+		let ball = system.add_obstacle(CircleObstacle::Circle(Circle::new(&Vec2::new(10.0, 0.0), 2.0)));
+
+		// Gives back the closest hit, with the normal facing the ray.
+		let hit = system.raycast(&Vec2::new(1.0, 5.0), &Vec2::new(0.0, -1.0), 10.0).unwrap();
+		assert_eq!(hit.source, floor);
+		assert_vec2_about_eq!(hit.position, Vec2::new(1.0, 0.0));
+		assert_vec2_about_eq!(hit.normal, Vec2::new(0.0, 1.0));
+		let hit = system.raycast(&Vec2::new(1.0, -5.0), &Vec2::new(0.0, 1.0), 10.0).unwrap();
+		assert_vec2_about_eq!(hit.normal, Vec2::new(0.0, -1.0)); // From below.
+
+		// Along the floor, the ball is the only thing in the way.
+		let hit = system.raycast(&Vec2::new(3.0, 0.0), &Vec2::new(1.0, 0.0), 20.0).unwrap();
+		assert_eq!(hit.source, ball);
+		assert_about_eq!(hit.distance, 5.0);
+		assert_vec2_about_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+	}
+
+	#[test]
+	fn iteration_budget() { // Running out of iterations either keeps the last movement, or stops where the last collision was.
+		let mut system = CollisionSystem::new();
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(2.0, 4.0), &Vec2::new(2.0, -4.0))));
+		let start = Vec2::new(0.0, 1.0);
+		let movement = Vec2::new(2.0, 1.0);
+		let result = system.collide_circle_checked(&start, 1.0, &movement);
+		assert!(!result.exhausted);
+
+		system.set_iteration_budget(1, IterationEscape::KeepMoving);
+		let result = system.collide_circle_checked(&start, 1.0, &movement);
+		assert!(result.exhausted);
+		assert_vec2_about_eq!(result.deflections[0].final_position, Vec2::new(1.0, 2.0));
+
+		system.set_iteration_budget(1, IterationEscape::DepenetrateAndStop);
+		let result = system.collide_circle_checked(&start, 1.0, &movement);
+		assert!(result.exhausted);
+		assert_vec2_about_eq!(result.deflections[0].final_position, Vec2::new(1.0, 1.5));
+	}
+
 	#[test]
 	fn line_stop() { // Make sure the line works.
 		let mut system = CollisionSystem::new();
@@ -155,4 +747,195 @@ mod test_collision_system {
 		collisions = system.collide_circle(&stuck, RADIUS, &freedom);
 		assert_eq!(collisions.len(), 0);
 	}
+
+	#[test]
+	fn corner_traversal() { // Make sure sliding over seams and off of convex corners doesn't get stuck.
+		let mut system = CollisionSystem::new();
+		// Two floor tiles merged with a slight seam, then a drop off.
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(0.0, 0.0))));
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(10.0, 0.002))));
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(10.0, 0.002), &Vec2::new(10.0, -10.0))));
+		const RADIUS : f32 = 1.0;
+		let across = system.collide_circle(&Vec2::new(-2.0, 1.0), RADIUS, &Vec2::new(4.0, -0.5));
+		let position = across.last().unwrap().final_position;
+		assert!((position.x - 2.0).abs() < 0.01, "Stuck at {:?}", position);
+		assert!((position.y - 1.0).abs() < 0.01, "Stuck at {:?}", position);
+
+		let off_edge = system.collide_circle(&Vec2::new(9.0, 1.002), RADIUS, &Vec2::new(3.0, -3.0));
+		let position = off_edge.last().unwrap().final_position;
+		assert!(10.5 < position.x, "Stuck on the corner at {:?}", position);
+	}
+
+	#[test]
+	fn skimming_contacts() { // Walking along a floor into a wall: the floor is only skimmed.
+		let mut system = CollisionSystem::new();
+		let floor = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		let wall = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(3.0, 5.0), &Vec2::new(3.0, -5.0))));
+		let start = Vec2::new(0.0, 1.0);
+		let movement = Vec2::new(4.0, 0.0);
+
+		let everything = system.collide_circle_step(&start, 1.0, &movement).unwrap();
+		assert_eq!(everything.deflections.len(), 2);
+		let deflecting = system.collide_circle_query(&start, 1.0, &movement, &CircleQuery { include_skimming : false, ..CircleQuery::new() }).unwrap();
+		assert_eq!(deflecting.deflections.len(), 1);
+		assert_eq!(deflecting.deflections[0].source, wall);
+		assert_vec2_about_eq!(deflecting.final_position, everything.final_position);
+
+		let contacts = system.contacts_only(&start, 1.0, &movement);
+		assert_eq!(contacts.len(), 1);
+		assert_eq!(contacts[0].source, floor);
+		assert!(system.contacts_only(&Vec2::new(0.0, 5.0), 1.0, &movement).is_empty());
+	}
+
+	#[test]
+	fn one_way() { // Jumping up through a platform, then landing on it.
+		let mut system = CollisionSystem::new();
+		let platform = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		system.set_one_way(platform, Some(Vec2::new(0.0, 2.0)));
+		assert!(system.collide_circle(&Vec2::new(0.0, -3.0), 1.0, &Vec2::new(0.0, 5.0)).is_empty());
+		assert!(system.collide_circle(&Vec2::new(0.0, 0.5), 1.0, &Vec2::new(0.0, -1.0)).is_empty()); // Only partway up, so falls back through.
+		let landing = system.collide_circle(&Vec2::new(0.0, 3.0), 1.0, &Vec2::new(0.0, -5.0));
+		assert_eq!(landing.len(), 1);
+		assert_vec2_about_eq!(landing[0].final_position, Vec2::new(0.0, 1.0));
+		assert_eq!(system.contacts_only(&Vec2::new(0.0, 1.0), 1.0, &Vec2::new(2.0, 0.0)).len(), 1); // Standing on it.
+
+		// Rays work the same way.
+		assert!(system.raycast(&Vec2::new(0.0, -5.0), &Vec2::new(0.0, 1.0), 10.0).is_none());
+		assert_eq!(system.raycast(&Vec2::new(0.0, 5.0), &Vec2::new(0.0, -1.0), 10.0).map(|hit| hit.source), Some(platform));
+
+		// And it can be made solid again.
+		system.set_one_way(platform, None);
+		assert_eq!(system.collide_circle(&Vec2::new(0.0, -3.0), 1.0, &Vec2::new(0.0, 5.0)).len(), 1);
+	}
+
+	#[test]
+	fn moving_obstacle() { // Riding a platform up, then teleporting it.
+		let mut system = CollisionSystem::new();
+		let platform = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		let wall = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-20.0, 0.0), &Vec2::new(-20.0, 10.0))));
+		system.set_obstacle_offset(platform, &Vec2::new(0.0, 200.0), 0.5);
+		assert_vec2_about_eq!(system.obstacles[platform].velocity, Vec2::new(0.0, 400.0));
+		assert!(system.collide_circle(&Vec2::new(0.0, 3.0), 1.0, &Vec2::new(0.0, -5.0)).is_empty()); // Not where it was.
+
+		// Hitting it says how fast it's going (and hitting anything else doesn't).
+		let landing = system.collide_circle_step(&Vec2::new(0.0, 203.0), 1.0, &Vec2::new(0.0, -5.0)).unwrap();
+		assert_vec2_about_eq!(landing.final_position, Vec2::new(0.0, 201.0));
+		assert_vec2_about_eq!(landing.carry_velocity, Vec2::new(0.0, 400.0));
+		let bump = system.collide_circle_step(&Vec2::new(-17.0, 5.0), 1.0, &Vec2::new(-5.0, 0.0)).unwrap();
+		assert_eq!(bump.deflections[0].source, wall);
+		assert_vec2_about_eq!(bump.carry_velocity, Vec2::zero());
+
+		// Offsets are from where it was added, and moving it without any time passing means it isn't moving.
+		system.set_obstacle_offset(platform, &Vec2::new(0.0, 50.0), 0.0);
+		assert_vec2_about_eq!(system.obstacles[platform].velocity, Vec2::zero());
+		assert_eq!(system.raycast(&Vec2::new(0.0, 60.0), &Vec2::new(0.0, -1.0), 20.0).map(|hit| hit.distance), Some(10.0));
+	}
+
+	#[test]
+	fn find_obstacles_near() {
+		let mut system = CollisionSystem::new();
+		let near = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-2.0, 1.0), &Vec2::new(2.0, 1.0))));
+		system.add_obstacle(CircleObstacle::Point(Vec2::new(10.0, 0.0)));
+		let disabled = system.add_obstacle(CircleObstacle::Point(Vec2::new(0.0, -1.0)));
+		system.set_enabled(disabled, false);
+		assert_eq!(system.find_obstacles_near(&Vec2::new(0.0, 0.0), 1.5), vec![near]);
+	}
+
+	#[test]
+	fn restitution() {
+		let mut system = CollisionSystem::new();
+		let floor = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		let start = Vec2::new(0.0, 2.0);
+		let movement = Vec2::new(2.0, -3.0);
+
+		// Slides by default.
+		let slide = system.collide_circle_step(&start, 1.0, &movement).unwrap();
+		assert_vec2_about_eq!(slide.final_position, Vec2::new(2.0, 1.0));
+
+		// Bounces off of bouncy obstacles.
+		system.set_restitution(floor, 1.0);
+		let bounce = system.collide_circle_step(&start, 1.0, &movement).unwrap();
+		assert_vec2_about_eq!(bounce.final_position, Vec2::new(2.0, 3.0));
+
+		// Unless the call overrides it.
+		let half = system.collide_circle_step_with_restitution(&start, 1.0, &movement, Some(0.5)).unwrap();
+		assert_vec2_about_eq!(half.final_position, Vec2::new(2.0, 2.0));
+	}
+
+	#[test]
+	fn push_out_circle() {
+		let mut system = CollisionSystem::new();
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-4.0, 0.0), &Vec2::new(4.0, 0.0))));
+		system.add_obstacle(CircleObstacle::Circle(Circle::new(&Vec2::new(10.0, 10.0), 2.0)));
+		assert_vec2_about_eq!(system.push_out_circle(&Vec2::new(1.0, 0.5), 1.0), Vec2::new(1.0, 1.0));
+		assert_vec2_about_eq!(system.push_out_circle(&Vec2::new(10.0, 12.0), 1.0), Vec2::new(10.0, 13.0));
+		assert_vec2_about_eq!(system.push_out_circle(&Vec2::new(0.0, 5.0), 1.0), Vec2::new(0.0, 5.0)); // Not overlapping.
+	}
+
+	#[test]
+	fn snapshot_restores_obstacles() {
+		let mut system = CollisionSystem::new();
+		let moving = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(10.0, 0.0))));
+		let toggled = system.add_obstacle(CircleObstacle::Point(Vec2::new(5.0, 5.0)));
+		let snapshot = system.snapshot();
+
+		system.set_geometry(moving, CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 20.0), &Vec2::new(10.0, 20.0))));
+		system.set_enabled(toggled, false);
+		system.restore(&snapshot);
+		assert_eq!(1, system.find_obstacles_near(&Vec2::new(5.0, 0.0), 0.5).len());
+		assert!(system.obstacles[toggled].active);
+	}
+
+	#[test]
+	fn broad_phase_follows_changes() {
+		let mut system = CollisionSystem::new();
+		let wall = system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(5.0, -10.0), &Vec2::new(5.0, 10.0))));
+		let far = system.add_obstacle(CircleObstacle::Point(Vec2::new(5000.0, 0.0)));
+		assert_eq!(system.find_obstacles_near(&Vec2::new(4.0, 0.0), 2.0), vec![wall]);
+
+		// Moving something far away brings it into play.
+		system.set_geometry(far, CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(3.0, -10.0), &Vec2::new(3.0, 10.0))));
+		let result = system.collide_circle(&Vec2::new(0.0, 0.0), 1.0, &Vec2::new(10.0, 0.0));
+		assert_eq!(result[0].deflections[0].source, far);
+		assert_vec2_about_eq!(result[0].deflections[0].position, Vec2::new(2.0, 0.0));
+
+		// Removing it takes it back out.
+		assert!(system.remove_obstacle(far).is_some());
+		assert!(system.remove_obstacle(far).is_none());
+		let result = system.collide_circle(&Vec2::new(0.0, 0.0), 1.0, &Vec2::new(10.0, 0.0));
+		assert_eq!(result[0].deflections[0].source, wall);
+		assert_eq!(system.raycast(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 0.0), 100.0).unwrap().source, wall);
+	}
+
+	#[test]
+	fn box_slides() {
+		let mut system = CollisionSystem::new();
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		system.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(7.0, 0.0), &Vec2::new(7.0, 10.0))));
+		let half_size = Vec2::new(2.0, 1.0);
+
+		// Lands on the floor and slides along it into the wall.
+		let result = system.collide_box(&Vec2::new(0.0, 3.0), &half_size, &Vec2::new(6.0, -4.0));
+		assert_eq!(result.len(), 2);
+		assert_vec2_about_eq!(result.last().unwrap().final_position, Vec2::new(5.0, 1.0));
+
+		// Walking along the floor only touches it, so doesn't deflect.
+		assert!(system.collide_box_step(&Vec2::new(0.0, 1.0), &half_size, &Vec2::new(1.0, 0.0)).is_none());
+		assert!(system.collide_box(&Vec2::new(0.0, 1.0), &half_size, &Vec2::new(1.0, 0.0)).is_empty());
+	}
+
+	#[cfg(feature = "serialize")]
+	#[test]
+	fn snapshot_json_round_trip() {
+		let mut system = CollisionSystem::new();
+		system.add_obstacle_with_user_data(CircleObstacle::Circle(Circle::new(&Vec2::new(1.0, 2.0), 3.0)), 7);
+		let text = serde_json::to_string(&system.snapshot()).unwrap();
+		let snapshot : Vec<ObstacleSnapshot> = serde_json::from_str(&text).unwrap();
+		assert_eq!(1, snapshot.len());
+		assert_eq!(Some(7), snapshot[0].obstacle.user_data);
+		match &snapshot[0].obstacle.geometry {
+			CircleObstacle::Circle(circle) => assert_eq!((circle.center.x, circle.radius), (1.0, 3.0)),
+			_ => panic!("Wrong kind of obstacle"),
+		}
+	}
 }
@@ -1,3 +1,68 @@
+use std::cell::Cell;
 
 /// An epsilon term/factor.
 pub const EPSILON : f32  = 1e-3;
+/// The default for how far apart (in radians) surface normals can point and still count as the same surface.
+pub const NORMAL_ANGLE_EPSILON : f32 = 0.01;
+
+/// How close together the geometry code considers things to be "the same", split up by what's being compared.
+///
+/// These all default to EPSILON, which suits maps measured in pixels. Maps with a very different unit scale will want different distances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+	/// For positions, lengths, and distances (in map units).
+	pub distance : f32,
+	/// For times and fractions of a movement (which are all unitless).
+	pub time : f32,
+	/// For comparing unit-length vectors against each other (as in checking if two directions are parallel).
+	pub normalization : f32,
+	/// For merging surface normals (in radians). Seams in merged tile geometry can give the same surface slightly different normals.
+	pub normal_angle : f32,
+}
+
+impl Tolerances {
+	/// Creates an instance with the default tolerances.
+	pub const fn new() -> Tolerances {
+		Tolerances {
+			distance : EPSILON,
+			time : EPSILON,
+			normalization : EPSILON,
+			normal_angle : NORMAL_ANGLE_EPSILON,
+		}
+	}
+}
+
+thread_local! {
+	/// The tolerances currently being used. The game only runs on one thread, but keeping this per-thread stops tests from stepping on each other.
+	static TOLERANCES : Cell<Tolerances> = Cell::new(Tolerances::new());
+}
+
+/// Gets the tolerances currently being used.
+pub fn get_tolerances() -> Tolerances {
+	TOLERANCES.with(|tolerances| tolerances.get())
+}
+
+/// Changes the tolerances used by all geometry code from now on.
+pub fn set_tolerances(tolerances : &Tolerances) {
+	TOLERANCES.with(|current| current.set(*tolerances));
+}
+
+/// Gets how close positions, lengths, and distances need to be to count as the same.
+pub fn distance_epsilon() -> f32 {
+	get_tolerances().distance
+}
+
+/// Gets how close times (and fractions of a movement) need to be to count as the same.
+pub fn time_epsilon() -> f32 {
+	get_tolerances().time
+}
+
+/// Gets how close unit-length vectors (or values computed from only them) need to be to count as the same.
+pub fn normalization_epsilon() -> f32 {
+	get_tolerances().normalization
+}
+
+/// Gets how close (in radians) surface normals need to point to count as the same surface.
+pub fn normal_angle_epsilon() -> f32 {
+	get_tolerances().normal_angle
+}
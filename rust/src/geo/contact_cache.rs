@@ -0,0 +1,166 @@
+use generational_arena::Index;
+
+use super::vec2::*;
+use super::collision_system::CollisionSystem;
+
+/// How far past what a query could reach to gather nearby obstacles (so small changes in movement don't need a new gather).
+const NEARBY_MARGIN : f32 = 16.0;
+/// How closely a contact's normal has to line up with "up" for it to count as ground. Matches the 45 degree cutoff the player uses.
+const GROUND_DOT : f32 = 0.65;
+
+/// An obstacle that was touched recently.
+#[derive(Debug, Clone)]
+pub struct CachedContact {
+	/// The obstacle that was touched.
+	pub source : Index,
+	/// The surface normal the last time it was touched (pointing away from the obstacle).
+	pub normal : Vec2,
+	/// How many frames ago it was last touched (zero is this frame).
+	pub age : u32,
+}
+
+/// Remembers what a collider has been touching across frames, keyed by obstacle.
+///
+/// This keeps brief gaps in contact (like crossing a seam) from looking like leaving the surface, and gives a steady answer for what's being stood on.
+/// It also gathers the obstacles near the collider once per frame, so every collision iteration in that frame only tests those.
+pub struct ContactCache {
+	/// Everything touched within the last `max_age` frames, in source order.
+	contacts : Vec<CachedContact>,
+	/// How many frames a contact is remembered for after it was last touched.
+	max_age : u32,
+	/// The obstacles near where they were last gathered (None if they need to be gathered again).
+	nearby : Option<Vec<Index>>,
+	/// Where the nearby obstacles were gathered around.
+	nearby_center : Vec2,
+	/// How far around that they were gathered.
+	nearby_reach : f32,
+}
+
+impl ContactCache {
+	/// Creates an empty instance that remembers contacts for some number of frames after they were last touched.
+	pub fn new(max_age : u32) -> ContactCache {
+		ContactCache {
+			contacts : Vec::new(),
+			max_age,
+			nearby : None,
+			nearby_center : Vec2::zero(),
+			nearby_reach : 0.0,
+		}
+	}
+
+	/// Starts a new frame: ages every contact (forgetting ones that are too old) and drops the nearby obstacles (since obstacles can move between frames).
+	pub fn begin_frame(&mut self) {
+		let max_age = self.max_age;
+		for contact in &mut self.contacts {
+			contact.age += 1;
+		}
+		self.contacts.retain(|contact| contact.age <= max_age);
+		self.nearby = None;
+	}
+
+	/// Forgets everything (as in after teleporting).
+	pub fn clear(&mut self) {
+		self.contacts.clear();
+		self.nearby = None;
+	}
+
+	/// Records touching an obstacle this frame.
+	pub fn touch(&mut self, source : Index, normal : &Vec2) {
+		let key = source.into_raw_parts();
+		match self.contacts.binary_search_by_key(&key, |contact| contact.source.into_raw_parts()) {
+			Ok(found) => {
+				let contact = &mut self.contacts[found];
+				contact.normal = normal.clone();
+				contact.age = 0;
+			},
+			Err(position) => self.contacts.insert(position, CachedContact { source, normal : normal.clone(), age : 0 }),
+		}
+	}
+
+	/// Gets everything that's remembered, in source order.
+	pub fn get_contacts(&self) -> &[CachedContact] {
+		&self.contacts
+	}
+
+	/// Gets the ground being stood on: the most recently touched contact whose normal points (close enough to) up, preferring the flattest if several were touched at once.
+	pub fn standing_on(&self, up : &Vec2) -> Option<&CachedContact> {
+		let mut best : Option<&CachedContact> = None;
+		for contact in &self.contacts {
+			let flatness = contact.normal.dot(up);
+			if GROUND_DOT > flatness { continue; }
+			best = match best {
+				Some(existing) if existing.age < contact.age || (existing.age == contact.age && existing.normal.dot(up) >= flatness) => Some(existing),
+				_ => Some(contact),
+			};
+		}
+		best
+	}
+
+	/// Whether any ground has been touched within the remembered frames.
+	pub fn is_grounded(&self, up : &Vec2) -> bool {
+		self.standing_on(up).is_some()
+	}
+
+	/// Gets the obstacles that a circle moving from some position could hit. They're only gathered again when the query could reach past what was gathered last.
+	pub fn nearby(&mut self, collision : &CollisionSystem, position : &Vec2, radius : f32, movement : &Vec2) -> &[Index] {
+		let needed = (position - self.nearby_center).length() + radius + movement.length();
+		if self.nearby.is_none() || self.nearby_reach < needed {
+			self.nearby_center = position.clone();
+			self.nearby_reach = radius + movement.length() + NEARBY_MARGIN;
+			self.nearby = Some(collision.find_obstacles_near(position, self.nearby_reach));
+		}
+		self.nearby.as_ref().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests_contact_cache {
+	use super::*;
+	use super::super::line_segment::LineSegment;
+	use super::super::collision_system::CircleObstacle;
+
+	#[test]
+	fn ages_out() {
+		let mut cache = ContactCache::new(2);
+		let up = Vec2::new(0.0, 1.0);
+		let floor = Index::from_raw_parts(3, 0);
+		let wall = Index::from_raw_parts(1, 0);
+		cache.touch(floor, &up);
+		cache.touch(wall, &Vec2::new(-1.0, 0.0));
+		assert_eq!(cache.get_contacts()[0].source, wall); // Source order.
+		assert_eq!(cache.standing_on(&up).unwrap().source, floor);
+
+		// Missing the floor for a couple of frames doesn't count as leaving it.
+		cache.begin_frame();
+		cache.begin_frame();
+		assert!(cache.is_grounded(&up));
+		cache.begin_frame();
+		assert!(!cache.is_grounded(&up));
+		assert!(cache.get_contacts().is_empty());
+
+		// The most recent ground wins.
+		let newer = Index::from_raw_parts(7, 0);
+		cache.touch(floor, &up);
+		cache.begin_frame();
+		cache.touch(newer, &Vec2::new(0.6, 0.8));
+		assert_eq!(cache.standing_on(&up).unwrap().source, newer);
+	}
+
+	#[test]
+	fn gathers_nearby_once() {
+		let mut collision = CollisionSystem::new();
+		let near = collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-10.0, 0.0), &Vec2::new(10.0, 0.0))));
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(500.0, 0.0), &Vec2::new(510.0, 0.0))));
+		let mut cache = ContactCache::new(1);
+		let movement = Vec2::new(4.0, 0.0);
+		assert_eq!(cache.nearby(&collision, &Vec2::new(0.0, 1.0), 1.0, &movement), &[near]);
+
+		// Later iterations in the same frame use what was gathered, even if things change.
+		let added = collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 5.0), &Vec2::new(1.0, 5.0))));
+		assert_eq!(cache.nearby(&collision, &Vec2::new(2.0, 1.0), 1.0, &movement), &[near]);
+		// Unless they'd reach past it.
+		assert_eq!(cache.nearby(&collision, &Vec2::new(2.0, 1.0), 1.0, &Vec2::new(40.0, 0.0)).len(), 2);
+		cache.begin_frame();
+		assert!(cache.nearby(&collision, &Vec2::new(0.0, 1.0), 1.0, &movement).contains(&added));
+	}
+}
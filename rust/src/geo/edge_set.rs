@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use super::vec2::*;
+use super::bounds2::Bounds2;
+use super::line_segment::LineSegment;
+
+/// How finely endpoints are snapped when comparing edges (in steps per pixel). Tile geometry is on whole pixels, so this only has to absorb rounding.
+const SNAP_STEPS_PER_PIXEL : f32 = 1024.0;
+
+/// An edge's endpoints (start, then end), snapped so nearly equal edges match.
+type EdgeKey = ((i64, i64), (i64, i64));
+
+/// Gathers the edges of solid shapes, so each edge only becomes an obstacle once.
+///
+/// Neighboring shapes that share an edge add it twice, once in each direction (since both are wound the same way). That edge is between the shapes rather than on their outside, so both copies are dropped. An edge added twice in the same direction is just a duplicate, so only the first is kept.
+/// Only edges with exactly matching endpoints are found, so edges that only partly overlap are all kept.
+pub struct EdgeSet {
+	/// Every edge, in the order it was added (None if it was dropped as an inside edge).
+	edges : Vec<Option<LineSegment>>,
+	/// Where each kept edge is in `edges`.
+	lookup : HashMap<EdgeKey, usize>,
+	/// How many edges were skipped for being duplicates.
+	duplicates : usize,
+	/// How many shared inside edges were dropped (each one counts once, even though both copies are dropped).
+	inside : usize,
+}
+
+impl EdgeSet {
+	/// Creates an empty instance.
+	pub fn new() -> EdgeSet {
+		EdgeSet {
+			edges : Vec::new(),
+			lookup : HashMap::new(),
+			duplicates : 0,
+			inside : 0,
+		}
+	}
+
+	/// Adds the edges of a rectangle (going counter-clockwise).
+	pub fn add_rect(&mut self, rect : &Bounds2) {
+		self.add_polygon(&[
+			Vec2::new(rect.x_min(), rect.y_min()),
+			Vec2::new(rect.x_max(), rect.y_min()),
+			Vec2::new(rect.x_max(), rect.y_max()),
+			Vec2::new(rect.x_min(), rect.y_max()),
+		]);
+	}
+
+	/// Adds the edges of a polygon (the last point connects back to the first).
+	pub fn add_polygon(&mut self, points : &[Vec2]) {
+		for index in 0..points.len() {
+			self.add_edge(&points[index], &points[(index + 1) % points.len()]);
+		}
+	}
+
+	/// Adds a single edge. Edges with no length are skipped.
+	pub fn add_edge(&mut self, start : &Vec2, end : &Vec2) {
+		let (start_key, end_key) = (snap(start), snap(end));
+		if start_key == end_key {
+			return;
+		}
+		if self.lookup.contains_key(&(start_key, end_key)) {
+			self.duplicates += 1;
+		} else if let Some(other) = self.lookup.remove(&(end_key, start_key)) {
+			self.edges[other] = None;
+			self.inside += 1;
+		} else {
+			self.lookup.insert((start_key, end_key), self.edges.len());
+			self.edges.push(Some(LineSegment::new(start, end)));
+		}
+	}
+
+	/// Gets every edge that was kept, in the order they were added.
+	pub fn get_edges(&self) -> Vec<LineSegment> {
+		self.edges.iter().flatten().cloned().collect()
+	}
+
+	/// Gets how many edges were skipped for being duplicates.
+	pub fn get_duplicate_count(&self) -> usize {
+		self.duplicates
+	}
+
+	/// Gets how many shared inside edges were dropped.
+	pub fn get_inside_count(&self) -> usize {
+		self.inside
+	}
+}
+
+/// Snaps a point, so nearly equal points match.
+fn snap(point : &Vec2) -> (i64, i64) {
+	((point.x * SNAP_STEPS_PER_PIXEL).round() as i64, (point.y * SNAP_STEPS_PER_PIXEL).round() as i64)
+}
+
+#[cfg(test)]
+mod test_edge_set {
+	use super::*;
+
+	/// Makes a rectangle from its corners.
+	fn rect(x_min : f32, y_min : f32, x_max : f32, y_max : f32) -> Bounds2 {
+		Bounds2::from_points(&Vec2::new(x_min, y_min), &Vec2::new(x_max, y_max))
+	}
+
+	#[test]
+	fn shared_edges() {
+		let mut edges = EdgeSet::new();
+		edges.add_rect(&rect(0.0, 0.0, 16.0, 16.0));
+		edges.add_rect(&rect(16.0, 0.0, 32.0, 16.0));
+		assert_eq!(edges.get_edges().len(), 6); // The edge between them is gone.
+		assert_eq!(edges.get_inside_count(), 1);
+		assert!(edges.get_edges().iter().all(|edge| 16.0 != edge.start.x || 16.0 != edge.end.x));
+
+		// Repeating a shape only adds duplicates (even if it's a tiny bit off).
+		edges.add_rect(&rect(100.0, 0.0, 116.0, 16.0));
+		edges.add_rect(&rect(100.0, 0.0, 116.0, 16.0001));
+		assert_eq!(edges.get_edges().len(), 10);
+		assert_eq!(edges.get_duplicate_count(), 4);
+		assert_eq!(edges.get_inside_count(), 1);
+
+		// Edges that only partly overlap are all kept, and ones with no length are skipped.
+		let mut edges = EdgeSet::new();
+		edges.add_rect(&rect(0.0, 0.0, 16.0, 16.0));
+		edges.add_rect(&rect(16.0, 0.0, 32.0, 8.0));
+		edges.add_edge(&Vec2::new(5.0, 5.0), &Vec2::new(5.0, 5.0));
+		assert_eq!(edges.get_edges().len(), 8);
+		assert_eq!((edges.get_duplicate_count(), edges.get_inside_count()), (0, 0));
+	}
+}
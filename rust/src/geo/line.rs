@@ -2,7 +2,8 @@ use super::vec2::*;
 
 /// An infinite 2D line.
 /// The line is defined as: delta.x * x + delta.y * y = c
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
 	pub delta : Vec2, // The coefficients. The vector is unit-length.
 	pub c : f32, // The constant offset.
@@ -24,6 +25,11 @@ impl Line {
 	pub fn ortho_distance_to(&self, point : &Vec2) -> f32 {
 		self.delta.ext(point - &self.origin).abs()
 	}
+
+	/// Gets the point on the line that's closest to the given point.
+	pub fn closest_point_to(&self, point : &Vec2) -> Vec2 {
+		&self.origin + (&self.delta).scale(self.delta.dot(point - &self.origin))
+	}
 }
 
 #[cfg(test)]
@@ -3,9 +3,11 @@ use super::common::*;
 use super::vec2::*;
 use super::range::*;
 use super::bounds2::*;
+use crate::debug_println;
 
 /// A line segment.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineSegment {
 	pub start : Vec2, // One of the end points.
 	pub end : Vec2,   // The other end point.
@@ -36,7 +38,7 @@ impl LineSegment {
 	pub fn new(start : &Vec2, end : &Vec2) -> LineSegment {
 		let delta = end - start;
 		let mut length = delta.length();
-		if length < EPSILON { length = 0.0; }
+		if length < distance_epsilon() { length = 0.0; }
 		LineSegment {
 			start: start.clone(),
 			end: end.clone(),
@@ -63,15 +65,16 @@ impl LineSegment {
 		// One last way could be intersecting: if both lines are colinear. At that point all "sides" would be 0.
 		if 0.0 == self_start_side && 0.0 == self_end_side && 0.0 == other_start_side && 0.0 == other_end_side {
 			// At this point, use dot product to see if any of the start/end points are between the other line's.
+			let epsilon = distance_epsilon();
 			let mut along;
 			along = other.direction.dot(&other_start_to_self_start);
-			if -EPSILON < along && along - other.length < EPSILON { return true; }
+			if -epsilon < along && along - other.length < epsilon { return true; }
 			along = other.direction.dot(&other_start_to_self_end);
-			if -EPSILON < along && along - other.length < EPSILON { return true; }
+			if -epsilon < along && along - other.length < epsilon { return true; }
 			along = self.direction.dot( &self_start_to_other_start);
-			if -EPSILON < along && along - self.length < EPSILON { return true; }
+			if -epsilon < along && along - self.length < epsilon { return true; }
 			along = self.direction.dot( &self_start_to_other_end);
-			if -EPSILON < along && along - self.length < EPSILON { return true; }
+			if -epsilon < along && along - self.length < epsilon { return true; }
 		}
 		false // If all else fails, then they're not intersecting.
 	}
@@ -80,8 +83,9 @@ impl LineSegment {
 	pub fn shortest_distance_to_point(&self, point : &Vec2) -> f32 {
 		let offset = point - &self.start;
 		let along = self.direction.dot(&offset);
-		println!("along: {:?} vs {:?}", along, self.length);
-		if -EPSILON < along && along - self.length < EPSILON {
+		debug_println!("along: {:?} vs {:?}", along, self.length);
+		let epsilon = distance_epsilon();
+		if -epsilon < along && along - self.length < epsilon {
 			self.direction.ext(&offset).abs()
 		} else {
 			// Must be one of the end points.
@@ -89,6 +93,12 @@ impl LineSegment {
 		}
 	}
 
+	/// Gets the point on this line segment that's closest to the given point.
+	pub fn closest_point_to(&self, point : &Vec2) -> Vec2 {
+		let along = self.direction.dot(point - &self.start).max(0.0).min(self.length);
+		&self.start + (&self.direction).scale(along)
+	}
+
 	/// Find the intersection between two line segments (if one exists).
 	pub fn find_intersection_with_line_segment(&self, other : &LineSegment) -> LineSegmentIntersection {
 		// If the bounding boxes don't even overlap, then they definitely don't intersect.
@@ -100,9 +110,9 @@ impl LineSegment {
 		let start_perp_dist = self.direction.ext(&start_offset);
 		let perp_direction = self.direction.ext(&other.direction);
 		// If the lines are parallel, things degenerate quickly.
-		if perp_direction.abs() < EPSILON {
+		if perp_direction.abs() < normalization_epsilon() {
 			// If they're not fully colinear lines, then no intersection.
-			if EPSILON < start_perp_dist.abs() {
+			if distance_epsilon() < start_perp_dist.abs() {
 				return LineSegmentIntersection::None;
 			}
 			// Otherwise the lines are on the same infinite line, and must overlap because their bounding boxes do.
@@ -115,7 +125,7 @@ impl LineSegment {
 			let overlap = self_range.intersect(other_range);
 			let hit_start = self.direction.scale(overlap.min().unwrap()) + &self.start;
 			let hit_end   = self.direction.scale(overlap.max().unwrap()) + &self.start;
-			return if (&hit_end - &hit_start).length() < EPSILON {
+			return if (&hit_end - &hit_start).length() < distance_epsilon() {
 				LineSegmentIntersection::Point(hit_start)
 			} else {
 				LineSegmentIntersection::Many(LineSegment::new(&hit_start, &hit_end))
@@ -124,14 +134,15 @@ impl LineSegment {
 		// Otherwise, they're not parallel, and there's one (possible) point of intersection where: 0 = start_perp_dist + perp_direction * t
 		let t = -start_perp_dist / perp_direction;
 		// If the time is negative, then it's before the other line segment's start, so no intersection.
-		if t < -EPSILON {
+		if t < -distance_epsilon() {
 			return LineSegmentIntersection::None;
 		}
 		let possible = other.direction.scale(t) + &other.start;
 		// Last check to see if the point is between the start and end of both line segments.
 		let self_along  = self.direction.dot( &possible - &self.start);
 		let other_along = other.direction.dot(&possible - &other.start);
-		if -EPSILON > self_along || EPSILON < self_along - self.length || -EPSILON > other_along || EPSILON < other_along - other.length {
+		let epsilon = distance_epsilon();
+		if -epsilon > self_along || epsilon < self_along - self.length || -epsilon > other_along || epsilon < other_along - other.length {
 			return LineSegmentIntersection::None; // Past one of the end points.
 		}
 		// At this point, it's definitely a valid intersection.
@@ -151,7 +162,7 @@ impl LineSegment {
 
 	/// Gets the end point that doesn't match the one passed in.
 	pub fn get_other_end_point<'a>(&'a self, check : &Vec2) -> &'a Vec2 {
-		if (self.start - check).length() < EPSILON {
+		if (self.start - check).length() < distance_epsilon() {
 			&self.end
 		} else {
 			&self.start
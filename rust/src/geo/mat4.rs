@@ -2,6 +2,7 @@ use auto_ops::impl_op_ex;
 
 use crate::externals::*;
 use super::vec3::*;
+use super::validate::*;
 
 /// A 4x4 transform matrix.
 #[derive(Clone)]
@@ -35,6 +36,7 @@ impl Mat4 {
 
 	/// Resets this matrix to identity.
 	pub fn translate_before(&mut self, translation : &Vec3) -> &mut Self {
+		check_f32(translation.x + translation.y + translation.z, "Mat4::translate_before()");
 		self.data[ 3] = self.data[0] * translation.x + self.data[1] * translation.y + self.data[ 2] * translation.z + self.data[ 3];
 		self.data[ 7] = self.data[4] * translation.x + self.data[5] * translation.y + self.data[ 6] * translation.z + self.data[ 7];
 		self.data[11] = self.data[8] * translation.x + self.data[9] * translation.y + self.data[10] * translation.z + self.data[11];
@@ -44,6 +46,7 @@ impl Mat4 {
 	/// Rotates the matrix about the z axis by some amount.
 	/// Makes this rotation happen before the current transform stored in this matrix.
 	pub fn rotz_before(&mut self, radians : f32) -> &mut Self {
+		check_f32(radians, "Mat4::rotz_before()");
 		let sin = radians.sin();
 		let cos = radians.cos();
 		let mut x;
@@ -68,6 +71,7 @@ impl Mat4 {
 
 	/// Rescales the matrix using the given vector's values.
 	pub fn scale_before(&mut self, factor : &Vec3) -> &mut Self {
+		check_f32(factor.x + factor.y + factor.z, "Mat4::scale_before()");
 		self.data[ 0] *= factor.x; self.data[ 1] *= factor.y; self.data[ 2] *= factor.z;
 		self.data[ 4] *= factor.x; self.data[ 5] *= factor.y; self.data[ 6] *= factor.z;
 		self.data[ 8] *= factor.x; self.data[ 9] *= factor.y; self.data[10] *= factor.z;
@@ -75,6 +79,20 @@ impl Mat4 {
 		self
 	}
 
+	/// Gets how far this moves the origin.
+	pub fn get_translation(&self) -> Vec3 {
+		Vec3::new(self.data[3], self.data[7], self.data[11])
+	}
+
+	/// Changes how far this moves the origin (without touching the rest of the transform).
+	pub fn set_translation(&mut self, translation : &Vec3) -> &mut Self {
+		check_f32(translation.x + translation.y + translation.z, "Mat4::set_translation()");
+		self.data[ 3] = translation.x;
+		self.data[ 7] = translation.y;
+		self.data[11] = translation.z;
+		self
+	}
+
 	/// Creates a vec<DrawCoord> suitable for WebGL to process. (So it transposes the matrix.)
 	pub fn export(&self) -> Vec<DrawCoord> {
 		vec!(
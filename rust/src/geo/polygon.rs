@@ -3,6 +3,7 @@ use super::consts::*;
 use super::vec2::*;
 use super::line_segment::*;
 use super::circle::*;
+use crate::debug_println;
 
 /// Checks if a point is inside the given polygon.
 /// This uses the old even-odd collision counting rule.
@@ -10,26 +11,26 @@ use super::circle::*;
 /// This should work on basically any type of polygon, though it follows the "even-odd rule" when it comes to defining self-intersecting polygons.
 pub fn is_point_inside_polygon(point : &Vec2, polygon : &Vec<Vec2>) -> bool {
 	assert!(2 < polygon.len());
-	println!("=============> Start!");
+	debug_println!("=============> Start!");
 	let mut inside = false;
 	let count = polygon.len();
 	for index in 0..count {
 		let start = polygon[index];
 		let end = polygon[if index+1 < count { index+1 } else { 0 }];
-		println!("Line {:?} to {:?}", start, end);
+		debug_println!("Line {:?} to {:?}", start, end);
 		// Find the hit between the ray from point down -x, and the start-end line segment.
 		let denom = end.y - start.y;
-		if denom.abs() < EPSILON {
+		if denom.abs() < distance_epsilon() {
 			// Ingore all basically horizontal lines.
-			println!("Denom skip");
+			debug_println!("Denom skip");
 			continue;
 		}
 		let t = (point.y - start.y) / denom;
-		println!("t = {}", t);
-		if -EPSILON > t || EPSILON > 1.0 - t {
+		debug_println!("t = {}", t);
+		if -time_epsilon() > t || time_epsilon() > 1.0 - t {
 			// Ignore before the start and after (or at) the end.
 			// Ignoring at end because that prevents the end points from being double-counted.
-			println!("Skip for t");
+			debug_println!("Skip for t");
 			continue;
 		}
 		let hit_x = end.x * t + start.x * (1.0 - t);
@@ -37,12 +38,12 @@ pub fn is_point_inside_polygon(point : &Vec2, polygon : &Vec<Vec2>) -> bool {
 			// Ignore if happend on the +x side of the point.
 			continue;
 		}
-		if EPSILON > (hit_x - point.x).abs() {
+		if distance_epsilon() > (hit_x - point.x).abs() {
 			// If on a border, then that's always inside.
 			return true;
 		}
-		if EPSILON > t.abs() {
-			println!("Checking near start.");
+		if time_epsilon() > t.abs() {
+			debug_println!("Checking near start.");
 			// If at start point, then only count this as a hit if end points it connects to are on opposite sides of the line.
 			// But, there could be a bunch of horizontal lines before this one. Those should be ignored.
 			let after_side = end.y > point.y;
@@ -50,21 +51,21 @@ pub fn is_point_inside_polygon(point : &Vec2, polygon : &Vec<Vec2>) -> bool {
 			for offset in 1..(count-1) {
 				let mut prev_index = (index as i32) - (offset as i32);
 				if prev_index < 0 { prev_index += count as i32; }
-				println!("Searching for before @ {}.", prev_index);
+				debug_println!("Searching for before @ {}.", prev_index);
 				let prev = polygon[prev_index as usize];
-				if EPSILON < (prev.y - point.y).abs() {
+				if distance_epsilon() < (prev.y - point.y).abs() {
 					let before_side = prev.y > point.y;
 					ignore = before_side == after_side;
-					println!("Found prev: {:?}.", prev);
+					debug_println!("Found prev: {:?}.", prev);
 					break;
 				}
 			}
-			println!("Ignore = {:?}.", ignore);
+			debug_println!("Ignore = {:?}.", ignore);
 			if ignore { continue; }
 		}
 		// If made it this far, then it's a hit that should flip whether inside.
 		inside = !inside;
-		println!("Flipped to {:?}.", inside);
+		debug_println!("Flipped to {:?}.", inside);
 	}
 	inside
 }
@@ -425,7 +426,7 @@ pub fn does_circle_overlap_polygon(circle : &Circle, polygon : &Vec<Vec2>) -> bo
 	}
 	// Otherwise see if any of the polygon's line are within radius of the circle.
 	for segment in make_polygon_lines(&polygon) {
-		if segment.shortest_distance_to_point(&circle.center) - circle.radius < EPSILON {
+		if segment.shortest_distance_to_point(&circle.center) - circle.radius < distance_epsilon() {
 			return true;
 		}
 	}
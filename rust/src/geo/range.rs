@@ -36,11 +36,12 @@ impl Range {
 
 	/// Creates a range with end points at the zeros of a quadratic (or linear, or constant).
 	pub fn from_quadratic_zeros(a : f32, b : f32, c : f32) -> Range {
-		if a.abs() < EPSILON {
+		let epsilon = time_epsilon();
+		if a.abs() < epsilon {
 			// If a is basically zero, then this isn't quadratic.
-			if b.abs() < EPSILON {
+			if b.abs() < epsilon {
 				// If b is also basically zero, then this is a "constant equation". Just check if c is always (pretty much) zero.
-				if c.abs() < EPSILON { Range::all() } else { Range::empty() }
+				if c.abs() < epsilon { Range::all() } else { Range::empty() }
 			} else {
 				// Then it's a linear equation with one solution.
 				Range::from_value(-c / b)
@@ -49,9 +50,9 @@ impl Range {
 			// Definitely a quadratic. Do the usual formula.
 			let denom = 2.0 * a;
 			let mut det = b * b - 4.0 * a * c;
-			if det < -EPSILON {
+			if det < -epsilon {
 				Range::empty() // Negative determinite means no zeros.
-			} else if det < EPSILON {
+			} else if det < epsilon {
 				Range::from_value(-b / denom)
 			} else {
 				det = det.sqrt();
@@ -110,7 +111,8 @@ impl Range {
 
 	/// Check if the range contains a given value with some epsilon.
 	pub fn contains(&self, value : f32) -> bool {
-		!self.is_empty() && ( (self.min <= value && value <= self.max) || (self.min - value).abs() < EPSILON || (self.max - value).abs() < EPSILON )
+		let epsilon = time_epsilon();
+		!self.is_empty() && ( (self.min <= value && value <= self.max) || (self.min - value).abs() < epsilon || (self.max - value).abs() < epsilon )
 	}
 }
 
@@ -168,6 +170,18 @@ mod tests_range {
 		assert_eq!(x.min().unwrap(),-5.0, "Two solutions (a)");
 		assert_eq!(x.max().unwrap(), 5.0, "Two solutions (b)");
 	}
+
+	/// Verify that the time tolerance can be changed without touching the others.
+	#[test]
+	fn configurable_tolerance() {
+		let range = Range::from_values(0.0, 1.0);
+		assert!(!range.contains(1.01));
+		set_tolerances(&Tolerances { time : 0.1, ..Tolerances::new() });
+		assert!(range.contains(1.01));
+		assert_eq!(distance_epsilon(), EPSILON);
+		set_tolerances(&Tolerances::new());
+		assert!(!range.contains(1.01));
+	}
 }
 
 /// For the cover() operator on Ranges.
@@ -0,0 +1,85 @@
+use super::vec2::*;
+use super::vec3::*;
+use super::mat4::Mat4;
+use super::validate::*;
+
+/// Where and how a flat thing (like a sprite) is drawn: flipped, then rotated, then moved into place.
+///
+/// Flipping happens first, so it's always along the thing's own axes (a flipped sprite that's rotated still faces the other way along its slope).
+#[derive(Debug, Clone, Copy)]
+pub struct Transform2D {
+	/// Where its origin goes.
+	pub position : Vec2,
+	/// How far it's turned counter-clockwise (in radians).
+	pub rotation : f32,
+	/// Whether it's mirrored left to right.
+	pub flip_x : bool,
+	/// Whether it's mirrored top to bottom.
+	pub flip_y : bool,
+}
+
+impl Transform2D {
+	/// Creates an instance that just moves something into place.
+	pub fn new(position : &Vec2) -> Transform2D {
+		Transform2D {
+			position : *position,
+			rotation : 0.0,
+			flip_x : false,
+			flip_y : false,
+		}
+	}
+
+	/// Gets a copy turned counter-clockwise by some amount (in radians).
+	pub fn with_rotation(mut self, rotation : f32) -> Transform2D {
+		check_f32(rotation, "Transform2D::with_rotation()");
+		self.rotation = rotation;
+		self
+	}
+
+	/// Gets a copy that's mirrored on either axis.
+	pub fn with_flip(mut self, flip_x : bool, flip_y : bool) -> Transform2D {
+		self.flip_x = flip_x;
+		self.flip_y = flip_y;
+		self
+	}
+
+	/// Gets the matrix for this (as a display buffer's transform).
+	pub fn to_mat4(&self) -> Mat4 {
+		let mut transform = Mat4::new();
+		transform.translate_before(&Vec3::new(self.position.x, self.position.y, 0.0));
+		if 0.0 != self.rotation {
+			transform.rotz_before(self.rotation);
+		}
+		if self.flip_x || self.flip_y {
+			transform.scale_before(&Vec3::new(
+				if self.flip_x { -1.0 } else { 1.0 },
+				if self.flip_y { -1.0 } else { 1.0 },
+				1.0,
+			));
+		}
+		transform
+	}
+}
+
+#[cfg(test)]
+mod test_transform2d {
+	use super::*;
+
+	/// Checks that a transformed point is about where it should be.
+	fn assert_near(actual : &Vec3, x : f32, y : f32) {
+		assert!((actual.x - x).abs() < 0.0001 && (actual.y - y).abs() < 0.0001, "Expected ({}, {}) but got ({}, {})", x, y, actual.x, actual.y);
+	}
+
+	#[test]
+	fn flipping_and_rotating() {
+		let point = Vec3::new(2.0, 1.0, 0.0);
+		let position = Vec2::new(10.0, 20.0);
+		assert_near(&(Transform2D::new(&position).to_mat4() * &point), 12.0, 21.0);
+		assert_near(&(Transform2D::new(&position).with_flip(true, false).to_mat4() * &point), 8.0, 21.0);
+		assert_near(&(Transform2D::new(&position).with_flip(false, true).to_mat4() * &point), 12.0, 19.0);
+		// A quarter turn counter-clockwise.
+		assert_near(&(Transform2D::new(&position).with_rotation(std::f32::consts::FRAC_PI_2).to_mat4() * &point), 9.0, 22.0);
+		// Flipped, then turned.
+		assert_near(&(Transform2D::new(&position).with_rotation(std::f32::consts::FRAC_PI_2).with_flip(true, false).to_mat4() * &point), 9.0, 18.0);
+	}
+}
@@ -0,0 +1,77 @@
+#[cfg(feature = "validation")]
+use crate::externals::log;
+
+use super::vec2::Vec2;
+
+/// Checks that a value is finite. Logs the context and returns false if it isn't.
+///
+/// All of these checks only do anything with the "validation" feature on. Otherwise they always pass and compile down to nothing.
+/// The context should say which operation made the value, since by the time a NaN shows up somewhere visible it's usually spread everywhere.
+#[cfg(feature = "validation")]
+pub fn check_f32(value : f32, context : &str) -> bool {
+	if value.is_finite() {
+		true
+	} else {
+		log(&format!("Invalid value {} from {}", value, context));
+		false
+	}
+}
+
+/// Checks that a value is finite. Does nothing since validation is off.
+#[cfg(not(feature = "validation"))]
+#[inline(always)]
+pub fn check_f32(_value : f32, _context : &str) -> bool {
+	true
+}
+
+/// Checks that both components of a vector are finite. Logs the context and returns false if either isn't.
+#[cfg(feature = "validation")]
+pub fn check_vec2(value : &Vec2, context : &str) -> bool {
+	if value.is_finite() {
+		true
+	} else {
+		log(&format!("Invalid vector {:?} from {}", value, context));
+		false
+	}
+}
+
+/// Checks that both components of a vector are finite. Does nothing since validation is off.
+#[cfg(not(feature = "validation"))]
+#[inline(always)]
+pub fn check_vec2(_value : &Vec2, _context : &str) -> bool {
+	true
+}
+
+/// Checks that a length is usable as a divisor (as in when normalizing). Logs the context and returns false if it isn't.
+#[cfg(feature = "validation")]
+pub fn check_divisor(value : f32, context : &str) -> bool {
+	if value.is_finite() && 0.0 != value {
+		true
+	} else {
+		log(&format!("Dividing by {} in {}", value, context));
+		false
+	}
+}
+
+/// Checks that a length is usable as a divisor (as in when normalizing). Does nothing since validation is off.
+#[cfg(not(feature = "validation"))]
+#[inline(always)]
+pub fn check_divisor(_value : f32, _context : &str) -> bool {
+	true
+}
+
+#[cfg(all(test, feature = "validation"))]
+mod tests_validate {
+	use super::*;
+
+	#[test]
+	fn catches_bad_values() {
+		assert!(check_f32(1.0, "test"));
+		assert!(!check_f32(std::f32::NAN, "test"));
+		assert!(!check_f32(std::f32::INFINITY, "test"));
+		assert!(check_vec2(&Vec2::new(1.0, -1.0), "test"));
+		assert!(!check_vec2(&Vec2::new(std::f32::NAN, 0.0), "test"));
+		assert!(check_divisor(2.0, "test"));
+		assert!(!check_divisor(0.0, "test"));
+	}
+}
@@ -1,9 +1,11 @@
 use auto_ops::{impl_op, impl_op_ex};
 
 use super::consts::*;
+use super::validate::*;
 
 /// A 2D vector.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vec2 {
 	pub x : f32, // The x component.
 	pub y : f32, // The y component.
@@ -24,6 +26,11 @@ impl Vec2 {
 	pub fn length(&self) -> f32 {
 		(self.x * self.x + self.y * self.y).sqrt()
 	}
+
+	/// Whether both components are finite (i.e. not NaN or infinite).
+	pub fn is_finite(&self) -> bool {
+		self.x.is_finite() && self.y.is_finite()
+	}
 }
 
 impl_op_ex!(* |left: &Vec2, right: f32| -> Vec2 { Vec2{ x: left.x * right, y: left.y * right } } );
@@ -50,6 +57,7 @@ impl<'l> VecOp for &'l Vec2 {
 
 	fn norm(self) -> Self::Output {
 		let length = self.length();
+		check_divisor(length, "Vec2::norm()");
 		Vec2 { x: self.x / length, y: self.y / length }
 	}
 
@@ -65,6 +73,7 @@ macro_rules! impl_vec_op {
 
 			fn norm(mut self) -> Self::Output {
 				let length = self.length();
+				check_divisor(length, "Vec2::norm()");
 				self.x /= length;
 				self.y /= length;
 				self
@@ -129,6 +138,7 @@ impl<'l> VecOpScalar<f32> for &'l Vec2 {
 
 	fn set_length(self, length : f32) -> Self::Output {
 		let factor = length / self.length();
+		check_f32(factor, "Vec2::set_length()");
 		self.scale(factor)
 	}
 }
@@ -146,6 +156,7 @@ macro_rules! impl_vec_op_scalar {
 
 			fn set_length(self, length : f32) -> Self::Output {
 				let factor = length / self.length();
+				check_f32(factor, "Vec2::set_length()");
 				self.scale(factor)
 			}
 		}
@@ -281,9 +292,9 @@ macro_rules! impl_vec_op_vec_to_vec {
 impl_vec_op_vec_to_vec!( Vec2 );
 impl_vec_op_vec_to_vec!( &'r Vec2 );
 
-/// Checks if any 3 points are colinear (to within EPSILON). All points being the same counts.
+/// Checks if any 3 points are colinear (to within the distance epsilon). All points being the same counts.
 pub fn points_are_colinear(p1 : &Vec2, p2 : &Vec2, p3 : &Vec2) -> bool {
-	(p2 - p1).ext(p3 - p1).abs() < EPSILON
+	(p2 - p1).ext(p3 - p1).abs() < distance_epsilon()
 }
 
 #[cfg(test)]
@@ -2,6 +2,8 @@ use crate::externals::*;
 use std::ops;
 use auto_ops::{impl_op, impl_op_ex};
 
+use super::validate::*;
+
 /// A 3D vector suitable for drawing.
 #[derive(Clone, Debug)]
 pub struct Vec3 {
@@ -13,6 +15,9 @@ pub struct Vec3 {
 impl Vec3 {
 	/// Creates a new instance with specific coordinate values.
 	pub fn new(x : DrawCoord, y : DrawCoord, z : DrawCoord) -> Vec3 {
+		check_f32(x, "Vec3::new() x");
+		check_f32(y, "Vec3::new() y");
+		check_f32(z, "Vec3::new() z");
 		Vec3 { x, y, z }
 	}
 
@@ -37,6 +42,7 @@ impl Vec3 {
 	/// If the the current length() is too small, this could result in NaN/Infinity values.
 	pub fn set_length(&mut self, new_length : DrawCoord) -> &mut Self {
 		let rescale = new_length / self.length();
+		check_f32(rescale, "Vec3::set_length()");
 		self.x *= rescale;
 		self.y *= rescale;
 		self.z *= rescale;
@@ -0,0 +1,108 @@
+use super::vec2::*;
+
+/// The type used for positions in the world. Normally just an f32, but the "f64-world" feature makes it an f64 so big maps don't lose precision far from the origin.
+#[cfg(feature = "f64-world")]
+pub type WorldScalar = f64;
+/// The type used for positions in the world. Normally just an f32, but the "f64-world" feature makes it an f64 so big maps don't lose precision far from the origin.
+#[cfg(not(feature = "f64-world"))]
+pub type WorldScalar = f32;
+
+/// Widens an f32 to a world scalar (which is a no-op without the "f64-world" feature).
+#[cfg(feature = "f64-world")]
+fn from_f32(value : f32) -> WorldScalar {
+	value as f64
+}
+/// Widens an f32 to a world scalar (which is a no-op without the "f64-world" feature).
+#[cfg(not(feature = "f64-world"))]
+fn from_f32(value : f32) -> WorldScalar {
+	value
+}
+
+/// Narrows a world scalar to an f32 (which is a no-op without the "f64-world" feature).
+#[cfg(feature = "f64-world")]
+fn to_f32(value : WorldScalar) -> f32 {
+	value as f32
+}
+/// Narrows a world scalar to an f32 (which is a no-op without the "f64-world" feature).
+#[cfg(not(feature = "f64-world"))]
+fn to_f32(value : WorldScalar) -> f32 {
+	value
+}
+
+/// A 2D position in the world, at whatever precision WorldScalar has.
+///
+/// These only get turned into f32 values (which is what DisplayBuffers use) relative to some nearby origin, so precision isn't lost where it's visible.
+///
+/// Only the render path uses these: the render origin, how far the origin has been rebased (see `origin_rebase`), the camera, and ghost recordings.
+/// Everything that moves or collides (the players, enemies, CollisionSystem, and so on) is still in f32 Vec2s, and relies on origin rebasing to stay near the origin.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldVec2 {
+	/// The x component.
+	pub x : WorldScalar,
+	/// The y component.
+	pub y : WorldScalar,
+}
+
+impl WorldVec2 {
+	/// Creates a new instance.
+	pub fn new(x : WorldScalar, y : WorldScalar) -> WorldVec2 {
+		WorldVec2 { x, y }
+	}
+
+	/// Creates a new zero vector.
+	pub fn zero() -> WorldVec2 {
+		WorldVec2 { x : 0.0, y : 0.0 }
+	}
+
+	/// Converts a normal (f32) vector.
+	pub fn from_vec2(value : &Vec2) -> WorldVec2 {
+		WorldVec2 { x : from_f32(value.x), y : from_f32(value.y) }
+	}
+
+	/// Converts to a normal (f32) vector. Only use this when the position is known to be near the origin, otherwise use `relative_to()`.
+	pub fn to_vec2(&self) -> Vec2 {
+		Vec2::new(to_f32(self.x), to_f32(self.y))
+	}
+
+	/// Gets where this is relative to some origin, as a normal (f32) vector. The subtraction happens before the conversion, so nothing is lost as long as the two are close.
+	pub fn relative_to(&self, origin : &WorldVec2) -> Vec2 {
+		Vec2::new(to_f32(self.x - origin.x), to_f32(self.y - origin.y))
+	}
+
+	/// Gets this moved by some (small) offset.
+	pub fn offset_by(&self, offset : &Vec2) -> WorldVec2 {
+		WorldVec2 { x : self.x + from_f32(offset.x), y : self.y + from_f32(offset.y) }
+	}
+
+	/// Gets whether this is exactly at the origin.
+	pub fn is_zero(&self) -> bool {
+		0.0 == self.x && 0.0 == self.y
+	}
+}
+
+#[cfg(test)]
+mod tests_world_vec2 {
+	use super::*;
+
+	#[test]
+	fn relative_conversion() {
+		let origin = WorldVec2::new(1000.0, -2000.0);
+		let position = origin.offset_by(&Vec2::new(1.5, -0.25));
+		let relative = position.relative_to(&origin);
+		assert_eq!(relative.x, 1.5);
+		assert_eq!(relative.y, -0.25);
+		assert_eq!(WorldVec2::from_vec2(&Vec2::new(3.0, 4.0)).to_vec2().y, 4.0);
+		assert!(WorldVec2::zero().is_zero());
+		assert!(!origin.is_zero());
+	}
+
+	#[cfg(feature = "f64-world")]
+	#[test]
+	fn keeps_precision_far_away() {
+		// An f32 only has about 7 digits, so this offset would be lost entirely.
+		let origin = WorldVec2::new(100_000_000.0, 0.0);
+		let position = origin.offset_by(&Vec2::new(0.125, 0.0));
+		assert_eq!(position.relative_to(&origin).x, 0.125);
+	}
+}
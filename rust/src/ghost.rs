@@ -0,0 +1,388 @@
+use crate::byte_reader::ByteReader;
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::display_text::*;
+use crate::display_texture::DisplayTexture;
+use crate::geo::transform2d::Transform2D;
+use crate::geo::vec2::*;
+use crate::geo::vec3::Vec3;
+use crate::geo::world_vec2::*;
+use crate::interpolation::InterpolatedPosition;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The first bytes of a packed ghost.
+pub const GHOST_MAGIC : &[u8] = b"RBGH";
+/// The packed ghost format version this reads and writes.
+pub const GHOST_VERSION : u32 = 1;
+/// How finely the per-step movements are stored (units per pixel). Movements are stored as i16 values, so this also limits how far a single step can go.
+const GHOST_UNITS_PER_PIXEL : f32 = 16.0;
+/// How see-through the ghost is drawn.
+const GHOST_ALPHA : f32 = 0.4;
+/// The depth to draw the ghost at (just behind the player).
+const GHOST_DEPTH : f32 = 0.05;
+
+/// A recording of where the player was on every simulation step of a finished run, so it can be raced against later.
+///
+/// Positions are kept in map coordinates (see `origin_rebase`), so a ghost doesn't care how the world was shifted during either run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostRun {
+	/// The URL of the room the run was in.
+	pub room : String,
+	/// How long each step was (in seconds).
+	pub step_seconds : f32,
+	/// Where the player was at the end of each step.
+	pub positions : Vec<WorldVec2>,
+	/// How many steps had been recorded when each objective was completed (in order).
+	pub splits : Vec<u32>,
+}
+
+impl GhostRun {
+	/// Gets how long the run took (in seconds).
+	pub fn get_duration(&self) -> f32 {
+		(self.positions.len() as f32) * self.step_seconds
+	}
+
+	/// Gets when an objective was completed (in seconds from the start), if the run got that far.
+	pub fn get_split(&self, index : usize) -> Option<f32> {
+		self.splits.get(index).map(|steps| (*steps as f32) * self.step_seconds)
+	}
+
+	/// Gets where the player was after some number of steps (1 for the first step). Holds at the ends. None if nothing was recorded.
+	pub fn get_position(&self, steps : usize) -> Option<WorldVec2> {
+		if self.positions.is_empty() {
+			return None;
+		}
+		Some(self.positions[steps.max(1).min(self.positions.len()) - 1])
+	}
+
+	/// Packs this into bytes. Layout (all little-endian):
+	/// * The magic bytes "RBGH" and the version (u32).
+	/// * The room URL (u32 length and UTF-8 bytes) and the step length (f32).
+	/// * The step count (u32) and the first position (f64 x and y), or nothing more if there are no steps.
+	/// * Every other step's movement from the one before it, as x and y i16 values in sixteenths of a pixel.
+	/// * The splits (u32 count, then u32 step counts).
+	///
+	/// Movements are measured from where the previous step decodes to, so rounding doesn't build up over a long run.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = GHOST_MAGIC.to_vec();
+		bytes.extend_from_slice(&GHOST_VERSION.to_le_bytes());
+		bytes.extend_from_slice(&(self.room.len() as u32).to_le_bytes());
+		bytes.extend_from_slice(self.room.as_bytes());
+		bytes.extend_from_slice(&self.step_seconds.to_le_bytes());
+		bytes.extend_from_slice(&(self.positions.len() as u32).to_le_bytes());
+		if let Some(first) = self.positions.first() {
+			bytes.extend_from_slice(&(first.x as f64).to_le_bytes());
+			bytes.extend_from_slice(&(first.y as f64).to_le_bytes());
+			let mut decoded = WorldVec2::new(first.x as f64 as WorldScalar, first.y as f64 as WorldScalar);
+			for position in &self.positions[1..] {
+				let movement = position.relative_to(&decoded);
+				let x = pack_movement(movement.x);
+				let y = pack_movement(movement.y);
+				bytes.extend_from_slice(&x.to_le_bytes());
+				bytes.extend_from_slice(&y.to_le_bytes());
+				decoded = decoded.offset_by(&unpack_movement(x, y));
+			}
+		}
+		bytes.extend_from_slice(&(self.splits.len() as u32).to_le_bytes());
+		for split in &self.splits {
+			bytes.extend_from_slice(&split.to_le_bytes());
+		}
+		bytes
+	}
+
+	/// Reads an instance back in from `to_bytes()`'s output.
+	pub fn from_bytes(bytes : &[u8]) -> Result<GhostRun, String> {
+		let mut reader = ByteReader::new(bytes);
+		if GHOST_MAGIC != reader.read_bytes(GHOST_MAGIC.len(), "the header")? {
+			return Err(String::from("Not a ghost (bad header)"));
+		}
+		let version = reader.read_u32("the version")?;
+		if GHOST_VERSION != version {
+			return Err(format!("Unsupported ghost version {} (expected {})", version, GHOST_VERSION));
+		}
+		let room = reader.read_string("the room")?;
+		let step_seconds = reader.read_f32("the step length")?;
+		if !(0.0 < step_seconds) {
+			return Err(format!("Invalid step length {}", step_seconds));
+		}
+		let count = reader.read_count(4, "the step count")?;
+		let mut positions = Vec::with_capacity(count);
+		if 0 < count {
+			let x = reader.read_f64("the first x")?;
+			let y = reader.read_f64("the first y")?;
+			let mut position = WorldVec2::new(x as WorldScalar, y as WorldScalar);
+			positions.push(position);
+			for _ in 1..count {
+				let x = reader.read_u16("a movement")? as i16;
+				let y = reader.read_u16("a movement")? as i16;
+				position = position.offset_by(&unpack_movement(x, y));
+				positions.push(position);
+			}
+		}
+		let splits = reader.read_u32_list("the splits")?;
+		if !reader.is_done() {
+			return Err(format!("Unexpected data after byte {}", reader.get_offset()));
+		}
+		Ok(GhostRun { room, step_seconds, positions, splits })
+	}
+}
+
+/// Converts a movement along one axis to how it's stored.
+fn pack_movement(distance : f32) -> i16 {
+	(distance * GHOST_UNITS_PER_PIXEL).round().max(i16::MIN as f32).min(i16::MAX as f32) as i16
+}
+
+/// Converts a stored movement back.
+fn unpack_movement(x : i16, y : i16) -> Vec2 {
+	Vec2::new(x as f32 / GHOST_UNITS_PER_PIXEL, y as f32 / GHOST_UNITS_PER_PIXEL)
+}
+
+/// Formats how far ahead (negative) or behind (positive) of a ghost something was, in seconds.
+pub fn format_time_difference(seconds : f32) -> String {
+	if 0.0 > seconds {
+		format!("-{:.2}s", -seconds)
+	} else {
+		format!("+{:.2}s", seconds)
+	}
+}
+
+/// Records the player's position every step of a run, to turn into a GhostRun once it's finished.
+pub struct GhostRecorder {
+	/// The room being recorded (None when not recording).
+	room : Option<String>,
+	/// What the simulation time was when recording started.
+	start_elapsed : f32,
+	/// How long each step is.
+	step_seconds : f32,
+	/// Where the player was at the end of each step so far.
+	positions : Vec<WorldVec2>,
+	/// How many steps had been recorded when each objective was completed.
+	splits : Vec<u32>,
+}
+
+impl GhostRecorder {
+	/// Creates an instance that isn't recording.
+	pub fn new(step_seconds : f32) -> GhostRecorder {
+		GhostRecorder {
+			room : None,
+			start_elapsed : 0.0,
+			step_seconds,
+			positions : Vec::new(),
+			splits : Vec::new(),
+		}
+	}
+
+	/// Starts recording a new run (dropping anything from an unfinished one).
+	pub fn start(&mut self, room : &str, elapsed : f32) {
+		self.room = Some(room.to_string());
+		self.start_elapsed = elapsed;
+		self.positions.clear();
+		self.splits.clear();
+	}
+
+	/// Whether a run is being recorded.
+	pub fn is_recording(&self) -> bool {
+		self.room.is_some()
+	}
+
+	/// Gets the room being recorded (if any).
+	pub fn get_room(&self) -> Option<&str> {
+		self.room.as_deref()
+	}
+
+	/// Stops recording, dropping the run.
+	pub fn stop(&mut self) {
+		self.room = None;
+		self.positions.clear();
+		self.splits.clear();
+	}
+
+	/// Gets how many steps have been recorded.
+	pub fn get_steps(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// Records where the player was at the end of a step.
+	pub fn record(&mut self, position : &WorldVec2) {
+		if self.is_recording() {
+			self.positions.push(*position);
+		}
+	}
+
+	/// Marks that an objective was just completed. Gives which objective it was (counting from 0).
+	pub fn mark_split(&mut self) -> usize {
+		self.splits.push(self.positions.len() as u32);
+		self.splits.len() - 1
+	}
+
+	/// Drops every step after some simulation time (for when the game state is put back to an earlier moment).
+	/// Snapshots are taken at the start of a step, so the step they were taken in doesn't count.
+	/// Splits are kept, as completed objectives stay completed.
+	pub fn rewind_to(&mut self, elapsed : f32) {
+		let steps = ((elapsed - self.start_elapsed) / self.step_seconds).round() - 1.0;
+		self.positions.truncate(steps.max(0.0) as usize);
+	}
+
+	/// Stops recording and gives back the run (if one was being recorded).
+	pub fn finish(&mut self) -> Option<GhostRun> {
+		let room = self.room.take()?;
+		Some(GhostRun {
+			room,
+			step_seconds : self.step_seconds,
+			positions : std::mem::take(&mut self.positions),
+			splits : std::mem::take(&mut self.splits),
+		})
+	}
+}
+
+/// Shows a ghost being raced: a see-through copy of the player's sprite, and a line of timing comparisons on the HUD.
+pub struct GhostDisplay {
+	/// Where the ghost was for the last two steps.
+	position : InterpolatedPosition,
+	/// The sprite.
+	sprite : DisplayBuffer,
+	/// The texture used to draw the sprite.
+	#[allow(dead_code)] // Kept so the texture lives as long as the sprite.
+	texture : DisplayTexture,
+	/// Whether the sprite is being shown.
+	visible : bool,
+	/// The timing comparisons.
+	text : DisplayText,
+}
+
+impl GhostDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> GhostDisplay {
+		let mut texture = DisplayTexture::new();
+		texture.load_from_url("player.png");
+		let mut sprite = DisplayBuffer::new(DisplayBufferType::IMAGES);
+		{
+			let mut editor = sprite.make_editor();
+			editor.add_image(
+				&Vec2::new(0.0, 0.0),
+				&Vec2::new(16.0, 16.0),
+				&Vec3::new(-8.0, -8.0, GHOST_DEPTH),
+			);
+		}
+		sprite.set_texture(&texture);
+		sprite.set_alpha(GHOST_ALPHA);
+		sprite.hide();
+		let mut text = DisplayText::new_text_area(
+			0.05,
+			0.70,
+			0.15,
+			0.95,
+			&Color::new(255, 255, 255, 255),
+			TextAlignment::RIGHT,
+			"",
+		);
+		text.hide();
+		GhostDisplay {
+			position : InterpolatedPosition::new(&Vec2::zero()),
+			sprite,
+			texture,
+			visible : false,
+			text,
+		}
+	}
+
+	/// Shows or hides the sprite.
+	pub fn set_visible(&mut self, visible : bool) {
+		self.visible = visible;
+		if visible {
+			self.sprite.show();
+		} else {
+			self.sprite.hide();
+		}
+	}
+
+	/// Moves the ghost at the end of a step.
+	pub fn place(&mut self, position : &Vec2) {
+		self.position.push(position);
+	}
+
+	/// Jumps the ghost straight to a position.
+	pub fn snap(&mut self, position : &Vec2) {
+		self.position.snap(position);
+	}
+
+	/// Moves the sprite to where the ghost should be drawn, given how far between the last two updates to draw things (from 0 to 1).
+	pub fn sync_display(&mut self, interpolation : f32) {
+		if !self.visible {
+			return;
+		}
+		let position = self.position.get(interpolation);
+		self.sprite.set_transform(&Transform2D::new(&position).to_mat4());
+	}
+
+	/// Sets the timing comparison text (hidden when empty).
+	pub fn set_text(&mut self, text : &str) {
+		self.text.set_text(text);
+		if text.is_empty() {
+			self.text.hide();
+		} else {
+			self.text.show();
+		}
+	}
+
+	/// Sets the color of the timing comparisons.
+	pub fn set_color(&mut self, color : &Color) {
+		self.text.set_color(color);
+	}
+}
+
+impl ShiftOrigin for GhostDisplay {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position.shift_origin(shift);
+	}
+}
+
+#[cfg(test)]
+mod tests_ghost {
+	use super::*;
+
+	#[test]
+	fn packed_round_trip() {
+		let mut recorder = GhostRecorder::new(0.5);
+		recorder.record(&WorldVec2::new(1.0, 1.0)); // Not recording yet.
+		recorder.start("room.json", 10.0);
+		let mut position = WorldVec2::new(1000.0, -20.0);
+		for step in 0..6 {
+			recorder.record(&position);
+			if 3 == step {
+				assert_eq!(recorder.mark_split(), 0);
+			}
+			position = position.offset_by(&Vec2::new(1.03, -0.5));
+		}
+		recorder.rewind_to(12.5); // Snapshot at the start of the 5th step.
+		assert_eq!(recorder.get_steps(), 4);
+		recorder.record(&WorldVec2::new(-2000.0, -20.0)); // Too big a jump for one step, so it gets cut short.
+		let run = recorder.finish().unwrap();
+		assert!(!recorder.is_recording());
+		assert_eq!(run.get_duration(), 2.5);
+		assert_eq!(run.get_split(0), Some(2.0));
+		assert_eq!(run.get_split(1), None);
+
+		let bytes = run.to_bytes();
+		assert_eq!(bytes.len(), 4 + 4 + (4 + 9) + 4 + 4 + 16 + 4 * 4 + 4 + 4);
+		let read = GhostRun::from_bytes(&bytes).unwrap();
+		assert_eq!((read.room.as_str(), read.step_seconds, read.splits.clone()), ("room.json", 0.5, vec![4]));
+		assert_eq!(read.positions.len(), 5);
+		assert_eq!(read.get_position(0), Some(WorldVec2::new(1000.0, -20.0)));
+		let third = read.get_position(3).unwrap();
+		assert!((third.x - 1002.06).abs() < 0.04 && (third.y + 21.0).abs() < 0.04);
+		let fourth = read.get_position(4).unwrap();
+		assert!((read.get_position(5).unwrap().x - (fourth.x - 2048.0)).abs() < 0.001);
+		assert_eq!(read.get_position(99), read.get_position(5));
+
+		assert!(GhostRun::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(GhostRun::from_bytes(b"RBTM").is_err());
+	}
+
+	#[test]
+	fn time_differences() {
+		assert_eq!(format_time_difference(-0.404), "-0.40s");
+		assert_eq!(format_time_difference(1.5), "+1.50s");
+		assert_eq!(format_time_difference(0.0), "+0.00s");
+	}
+}
@@ -0,0 +1,140 @@
+use crate::geo::vec2::*;
+use crate::geo::consts::EPSILON;
+
+/// How quickly the smoothed normal turns toward the measured one (the fraction of the remaining angle covered per second is `1 - e^-rate`).
+const SMOOTHING_RATE : f32 = 20.0;
+/// Measured changes smaller than this (in radians) are ignored, so flickering between nearly identical seams doesn't move anything.
+const HYSTERESIS_ANGLE : f32 = 0.05;
+/// Changes bigger than this (in radians) are jumped to immediately, as they're a real change of surface (like landing on a wall) rather than jitter.
+const SNAP_ANGLE : f32 = 1.0;
+
+/// Smooths the ground normal that the player's movement is made relative to.
+///
+/// On tile seams the player can be touching two nearly identical surfaces, and which one "wins" can flip every update.
+/// So instead of using a single contact, all ground-ish contacts over an update are averaged (weighted by how "upward" they are), and then the result is eased toward.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroundNormal {
+	/// The normals gathered so far during this update (pre-weighted).
+	total : Vec2,
+	/// How many normals were gathered this update.
+	count : usize,
+	/// What the smoothed normal is turning toward. Only changes when the measured normal moves far enough away from it.
+	target : Vec2,
+	/// The current smoothed normal. Zero when not on anything.
+	smoothed : Vec2,
+}
+
+/// Gets the (unsigned) angle between two unit vectors.
+fn angle_between(first : &Vec2, second : &Vec2) -> f32 {
+	first.ext(second).atan2(first.dot(second)).abs()
+}
+
+/// Rotates a unit vector toward another by some fraction of the angle between them.
+fn turn_toward(from : &Vec2, to : &Vec2, fraction : f32) -> Vec2 {
+	let angle = from.ext(to).atan2(from.dot(to)) * fraction;
+	let (sin, cos) = angle.sin_cos();
+	Vec2::new(from.x * cos - from.y * sin, from.x * sin + from.y * cos)
+}
+
+impl GroundNormal {
+	/// Creates an instance that's not on any ground.
+	pub fn new() -> GroundNormal {
+		GroundNormal {
+			total : Vec2::zero(),
+			count : 0,
+			target : Vec2::zero(),
+			smoothed : Vec2::zero(),
+		}
+	}
+
+	/// Gets the smoothed normal. Zero when not on anything.
+	pub fn get(&self) -> Vec2 {
+		self.smoothed
+	}
+
+	/// Adds a surface normal touched during this update.
+	/// The up direction is used to weight it, so floors count for more than walls. Ones facing downward are ignored.
+	pub fn add_sample(&mut self, normal : &Vec2, up : &Vec2) {
+		let weight = normal.dot(up);
+		if 0.0 < weight {
+			self.total += normal.scale(weight);
+			self.count += 1;
+		}
+	}
+
+	/// Combines all of the samples from this update into the smoothed normal, and starts gathering for the next update.
+	pub fn finish(&mut self, elapsed_seconds : f32) -> Vec2 {
+		let count = self.count;
+		let total = std::mem::replace(&mut self.total, Vec2::zero());
+		self.count = 0;
+		if 0 == count || total.length() < EPSILON {
+			self.target = Vec2::zero();
+			self.smoothed = Vec2::zero();
+			return self.smoothed;
+		}
+		let measured = total.norm();
+		if self.smoothed.length() < EPSILON {
+			self.target = measured;
+			self.smoothed = measured;
+			return self.smoothed;
+		}
+		if HYSTERESIS_ANGLE < angle_between(&self.target, &measured) {
+			self.target = measured;
+		}
+		if SNAP_ANGLE < angle_between(&self.smoothed, &self.target) {
+			self.smoothed = self.target;
+		} else {
+			let fraction = 1.0 - (-SMOOTHING_RATE * elapsed_seconds).exp();
+			self.smoothed = turn_toward(&self.smoothed, &self.target, fraction);
+		}
+		self.smoothed
+	}
+}
+
+#[cfg(test)]
+mod tests_ground_normal {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+
+	#[test]
+	fn averages_seams() {
+		let up = Vec2::new(0.0, 1.0);
+		let mut ground = GroundNormal::new();
+		ground.add_sample(&Vec2::new(0.0, 1.0), &up);
+		ground.add_sample(&Vec2::new(0.0, -1.0), &up); // Ceilings are ignored.
+		assert_vec2_about_eq!(ground.finish(0.1), Vec2::new(0.0, 1.0));
+
+		// A slightly tilted seam doesn't make the normal flicker.
+		ground.add_sample(&Vec2::new(0.0, 1.0), &up);
+		ground.add_sample(&Vec2::new(0.05, 1.0).norm(), &up);
+		assert_vec2_about_eq!(ground.finish(0.1), Vec2::new(0.0, 1.0));
+
+		// Leaving the ground clears it.
+		assert_vec2_about_eq!(ground.finish(0.1), Vec2::zero());
+	}
+
+	#[test]
+	fn eases_onto_slopes() {
+		let up = Vec2::new(0.0, 1.0);
+		let slope = Vec2::new(-1.0, 2.0).norm();
+		let mut ground = GroundNormal::new();
+		ground.add_sample(&up, &up);
+		ground.finish(0.1);
+
+		ground.add_sample(&slope, &up);
+		let partway = ground.finish(0.01);
+		assert!(partway.x < 0.0 && slope.x < partway.x);
+		assert!((partway.length() - 1.0).abs() < EPSILON);
+		for _ in 0..100 {
+			ground.add_sample(&slope, &up);
+			ground.finish(0.01);
+		}
+		assert_vec2_about_eq!(ground.get(), slope);
+
+		// Big changes happen immediately.
+		let wall = Vec2::new(1.0, 0.1).norm();
+		ground.add_sample(&wall, &up);
+		assert_vec2_about_eq!(ground.finish(0.01), wall);
+	}
+}
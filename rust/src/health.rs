@@ -0,0 +1,106 @@
+/// Tracks how much damage something can take.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Health {
+	/// The current amount of health.
+	current : f32,
+	/// The most health there can be.
+	max : f32,
+	/// How much damage was taken since `take_damage_taken()` was last called.
+	damage_taken : f32,
+}
+
+impl Health {
+	/// Creates a new instance at full health.
+	pub fn new(max : f32) -> Health {
+		Health {
+			current : max,
+			max,
+			damage_taken : 0.0,
+		}
+	}
+
+	/// The current amount of health.
+	pub fn get_current(&self) -> f32 {
+		self.current
+	}
+
+	/// The most health there can be.
+	pub fn get_max(&self) -> f32 {
+		self.max
+	}
+
+	/// How much health is left as a value from 0 to 1.
+	pub fn get_fraction(&self) -> f32 {
+		if self.max <= 0.0 {
+			return 0.0;
+		}
+		self.current / self.max
+	}
+
+	/// Whether there's no health left.
+	pub fn is_dead(&self) -> bool {
+		self.current <= 0.0
+	}
+
+	/// Removes some health. Returns whether this killed it.
+	pub fn damage(&mut self, amount : f32) -> bool {
+		if self.is_dead() || amount <= 0.0 {
+			return false;
+		}
+		let before = self.current;
+		self.current = (self.current - amount).max(0.0);
+		self.damage_taken += before - self.current;
+		self.is_dead()
+	}
+
+	/// Adds some health back (up to the max).
+	pub fn heal(&mut self, amount : f32) {
+		if amount <= 0.0 {
+			return;
+		}
+		self.current = (self.current + amount).min(self.max);
+	}
+
+	/// Refills health completely.
+	pub fn reset(&mut self) {
+		self.current = self.max;
+		self.damage_taken = 0.0;
+	}
+
+	/// Gets how much damage has been taken since this was last called.
+	pub fn take_damage_taken(&mut self) -> f32 {
+		let damage = self.damage_taken;
+		self.damage_taken = 0.0;
+		damage
+	}
+}
+
+#[cfg(test)]
+mod tests_health {
+	use super::*;
+
+	#[test]
+	fn damage_and_heal() {
+		let mut health = Health::new(10.0);
+		assert!(!health.damage(4.0));
+		assert_eq!(health.get_current(), 6.0);
+		assert_eq!(health.get_fraction(), 0.6);
+		health.heal(100.0);
+		assert_eq!(health.get_current(), 10.0);
+		assert_eq!(health.take_damage_taken(), 4.0);
+		assert_eq!(health.take_damage_taken(), 0.0);
+	}
+
+	#[test]
+	fn dies_once() {
+		let mut health = Health::new(10.0);
+		assert!(health.damage(15.0));
+		assert!(health.is_dead());
+		assert_eq!(health.get_current(), 0.0);
+		assert!(!health.damage(1.0));
+		assert_eq!(health.take_damage_taken(), 10.0);
+		health.reset();
+		assert!(!health.is_dead());
+	}
+}
@@ -0,0 +1,94 @@
+use crate::geo::vec2::*;
+
+/// A part of an entity that interacts differently with things.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HitboxKind {
+	/// The bottom, for landing on enemies.
+	Feet,
+	/// The top, for hitting blocks from below.
+	Head,
+	/// The middle, for taking damage.
+	Body,
+}
+
+/// A circle that's part of an entity, offset along its "up" direction.
+#[derive(Debug, Clone)]
+pub struct Hitbox {
+	/// How far from the entity's center it is along "up" (negative is toward the feet).
+	pub offset : f32,
+	/// How big it is.
+	pub radius : f32,
+}
+
+impl Hitbox {
+	/// Gets where its center is for an entity at some position.
+	pub fn center(&self, position : &Vec2, up : &Vec2) -> Vec2 {
+		position + up.scale(self.offset)
+	}
+
+	/// Whether a point is inside of it (for an entity at some position).
+	pub fn contains(&self, point : &Vec2, position : &Vec2, up : &Vec2) -> bool {
+		(point - self.center(position, up)).length() <= self.radius
+	}
+}
+
+/// The sub-hitboxes of a circular entity (the player), so what touches it can be told apart by where it touches.
+///
+/// The feet reach a little below the collision circle, so landing on an enemy counts even if the circles only just meet.
+/// The head only covers the top of the circle, so bumping into something at an angle doesn't count as hitting it from below.
+#[derive(Debug, Clone)]
+pub struct Hitboxes {
+	/// The bottom.
+	pub feet : Hitbox,
+	/// The top.
+	pub head : Hitbox,
+	/// The middle (a bit smaller than the collision circle, so grazes don't hurt).
+	pub body : Hitbox,
+}
+
+impl Hitboxes {
+	/// Creates the hitboxes for a circle of some radius.
+	pub fn for_radius(radius : f32) -> Hitboxes {
+		Hitboxes {
+			feet : Hitbox { offset : -0.5 * radius, radius : 0.6 * radius },
+			head : Hitbox { offset : 0.5 * radius, radius : 0.6 * radius },
+			body : Hitbox { offset : 0.0, radius : 0.8 * radius },
+		}
+	}
+
+	/// Gets one of the hitboxes.
+	pub fn get(&self, kind : HitboxKind) -> &Hitbox {
+		match kind {
+			HitboxKind::Feet => &self.feet,
+			HitboxKind::Head => &self.head,
+			HitboxKind::Body => &self.body,
+		}
+	}
+
+	/// Gets which hitbox a point (like where an obstacle was touched) is in, checking the head and feet before the body. None if it's in none of them.
+	pub fn classify(&self, point : &Vec2, position : &Vec2, up : &Vec2) -> Option<HitboxKind> {
+		[HitboxKind::Head, HitboxKind::Feet, HitboxKind::Body].iter().copied().find(|kind| self.get(*kind).contains(point, position, up))
+	}
+}
+
+#[cfg(test)]
+mod tests_hitboxes {
+	use super::*;
+
+	#[test]
+	fn classifying() {
+		let hitboxes = Hitboxes::for_radius(8.0);
+		let position = Vec2::new(100.0, 50.0);
+		let up = Vec2::new(0.0, 1.0);
+		let on_circle = |angle : f32| position + Vec2::new(angle.cos(), angle.sin()).scale(8.0);
+		assert_eq!(hitboxes.classify(&on_circle(std::f32::consts::FRAC_PI_2), &position, &up), Some(HitboxKind::Head));
+		assert_eq!(hitboxes.classify(&on_circle(-std::f32::consts::FRAC_PI_2), &position, &up), Some(HitboxKind::Feet));
+		assert_eq!(hitboxes.classify(&on_circle(std::f32::consts::FRAC_PI_4), &position, &up), None); // An angled hit is neither.
+		assert_eq!(hitboxes.classify(&Vec2::new(104.0, 50.0), &position, &up), Some(HitboxKind::Body));
+
+		// They follow "up" around.
+		let sideways = Vec2::new(1.0, 0.0);
+		assert_eq!(hitboxes.classify(&Vec2::new(108.0, 50.0), &position, &sideways), Some(HitboxKind::Head));
+		assert!(hitboxes.get(HitboxKind::Feet).center(&position, &sideways).x < position.x);
+	}
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+/// A real key or button going down or up, and when.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+	/// Which key or button (as an index into its device's state).
+	pub index : usize,
+	/// Whether it went down (instead of up).
+	pub down : bool,
+	/// When it happened (in seconds, on the same clock as `now_seconds()`).
+	pub time : f64,
+}
+
+/// Key or button changes waiting to be applied, in the order they happened.
+///
+/// Input shows up between updates, but the simulation runs in fixed steps. So changes are held here until the step they belong to, rather than all being applied at once (which loses a press and release that both happen before the next update).
+/// Anything that goes down stays down until a step has seen it: its release waits until after the next `end_step()`. So a tap isn't lost to an update that doesn't run any steps.
+pub struct InputQueue {
+	/// The changes that haven't been applied yet (oldest first).
+	events : VecDeque<InputEvent>,
+	/// Whether each key or button went down since the last `end_step()`.
+	pressed : Vec<bool>,
+}
+
+impl InputQueue {
+	/// Creates an empty instance.
+	pub fn new() -> InputQueue {
+		InputQueue {
+			events : VecDeque::new(),
+			pressed : Vec::new(),
+		}
+	}
+
+	/// Adds a change. Changes can't be added out of order, so anything earlier than the last change counts as happening at the same time as it.
+	pub fn push(&mut self, index : usize, down : bool, time : f64) {
+		let time = match self.events.back() {
+			Some(last) if time < last.time => last.time,
+			_ => time,
+		};
+		self.events.push_back(InputEvent { index, down, time });
+	}
+
+	/// Whether there are changes waiting.
+	pub fn is_empty(&self) -> bool {
+		self.events.is_empty()
+	}
+
+	/// Drops every change that's waiting (as in when the input is taken over by something else).
+	pub fn clear(&mut self) {
+		self.events.clear();
+		for pressed in &mut self.pressed {
+			*pressed = false;
+		}
+	}
+
+	/// Applies the changes that happened by some time to the state of each key or button, in order.
+	/// Stops early at the release of anything that went down since the last `end_step()` (so a step sees it as down at least once). Returns how many changes were applied.
+	pub fn apply_until(&mut self, time : f64, state : &mut Vec<bool>) -> usize {
+		let mut applied = 0;
+		while let Some(event) = self.events.front() {
+			if time < event.time {
+				break;
+			}
+			if !event.down && self.was_pressed(event.index) {
+				break;
+			}
+			let event = *event;
+			self.events.pop_front();
+			if state.len() <= event.index {
+				state.resize(event.index + 1, false);
+			}
+			if event.down && !state[event.index] {
+				if self.pressed.len() <= event.index {
+					self.pressed.resize(event.index + 1, false);
+				}
+				self.pressed[event.index] = true;
+			}
+			state[event.index] = event.down;
+			applied += 1;
+		}
+		applied
+	}
+
+	/// Whether a key or button went down since the last `end_step()`.
+	pub fn was_pressed(&self, index : usize) -> bool {
+		*self.pressed.get(index).unwrap_or(&false)
+	}
+
+	/// Notes that a step has seen everything applied so far, so the presses are used up (and their releases can be applied).
+	pub fn end_step(&mut self) {
+		for pressed in &mut self.pressed {
+			*pressed = false;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_input_events {
+	use super::*;
+
+	#[test]
+	fn taps_last_a_step() {
+		let mut queue = InputQueue::new();
+		let mut state = vec![false; 2];
+		queue.push(0, true, 1.00);
+		queue.push(0, false, 1.01); // Let go before the next step.
+		queue.push(1, true, 1.02);
+		queue.push(1, false, 1.20); // Not until a later step.
+
+		// The tap is seen for one step. Its release (and everything after) waits for the next.
+		assert_eq!(queue.apply_until(1.05, &mut state), 1);
+		assert_eq!(state, vec![true, false]);
+		assert!(queue.was_pressed(0));
+		queue.end_step();
+
+		assert_eq!(queue.apply_until(1.05, &mut state), 2);
+		assert_eq!(state, vec![false, true]);
+		assert!(!queue.was_pressed(0) && queue.was_pressed(1));
+		queue.end_step();
+
+		// Nothing new happened by this time.
+		assert_eq!(queue.apply_until(1.10, &mut state), 0);
+		assert_eq!(state, vec![false, true]);
+		assert!(!queue.was_pressed(1));
+
+		assert_eq!(queue.apply_until(1.30, &mut state), 1);
+		assert_eq!(state, vec![false, false]);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn ordering() {
+		let mut queue = InputQueue::new();
+		let mut state = Vec::new();
+		queue.push(3, true, 2.0);
+		queue.push(3, true, 2.5); // A repeat isn't a new press.
+		queue.push(1, true, 1.0); // Late, so it's treated as happening with the one before.
+		assert_eq!(queue.apply_until(2.0, &mut state), 1);
+		assert_eq!(state, vec![false, false, false, true]);
+		queue.end_step();
+		assert_eq!(queue.apply_until(2.5, &mut state), 2);
+		assert!(!queue.was_pressed(3) && queue.was_pressed(1));
+
+		queue.push(1, false, 3.0);
+		queue.clear();
+		assert_eq!(queue.apply_until(10.0, &mut state), 0);
+		assert!(state[1]);
+	}
+
+	#[test]
+	fn taps_wait_for_a_step() {
+		let mut queue = InputQueue::new();
+		let mut state = vec![false];
+		queue.push(0, true, 1.000);
+		queue.push(0, false, 1.004); // Both inside an update that's too short to run a step.
+
+		// The update without a step sees the press, but can't let go of it.
+		assert_eq!(queue.apply_until(1.005, &mut state), 1);
+		assert!(state[0] && queue.was_pressed(0));
+
+		// The next update's step still sees it as just pressed, even though its release is long due.
+		assert_eq!(queue.apply_until(1.020, &mut state), 0);
+		assert!(state[0] && queue.was_pressed(0));
+		queue.end_step();
+
+		// Only then is it let go.
+		assert_eq!(queue.apply_until(1.020, &mut state), 1);
+		assert!(!state[0] && !queue.was_pressed(0));
+	}
+}
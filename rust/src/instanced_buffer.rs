@@ -0,0 +1,115 @@
+use crate::externals::*;
+use crate::geo::vec3::*;
+use crate::color::*;
+use crate::display_buffer::*;
+
+/// One copy of an InstancedBuffer's template.
+#[derive(Debug, Clone)]
+pub struct Instance {
+	/// How far the copy is moved from the template.
+	pub offset : Vec3,
+	/// What the copy's colors are multiplied by.
+	pub color : Color,
+}
+
+impl Instance {
+	/// Creates an instance.
+	pub fn new(offset : &Vec3, color : &Color) -> Instance {
+		Instance { offset : offset.clone(), color : color.clone() }
+	}
+}
+
+/// Flattens instances into the per-instance arrays the display wants: three offset values and four color values each.
+pub fn instance_arrays(instances : &[Instance]) -> (Vec<DrawCoord>, Vec<ColorMagnitude>) {
+	let mut offsets = Vec::with_capacity(3 * instances.len());
+	let mut colors = Vec::with_capacity(4 * instances.len());
+	for instance in instances {
+		offsets.push(instance.offset.x);
+		offsets.push(instance.offset.y);
+		offsets.push(instance.offset.z);
+		instance.color.raw_export(&mut colors);
+	}
+	(offsets, colors)
+}
+
+/// A DisplayBuffer for lots of copies of the same thing (like coins or particles).
+///
+/// The template is only sent over once, and each update just sends an offset and color per copy (which the display uses to draw them all in one instanced call).
+/// If the display can't do instancing, it falls back to building every copy into the buffer (like any other batched DisplayBuffer).
+pub struct InstancedBuffer {
+	/// Where everything's drawn.
+	display : DisplayBuffer,
+	/// What every instance is a copy of.
+	template : DisplayGeometry,
+	/// Whether the display can draw instanced (otherwise everything's batched).
+	instanced : bool,
+}
+
+impl InstancedBuffer {
+	/// Creates an instance that draws copies of some geometry. It shows nothing until it's updated.
+	pub fn new(type_ : DisplayBufferType, template : DisplayGeometry) -> InstancedBuffer {
+		let mut buffer = InstancedBuffer {
+			display : DisplayBuffer::new(type_),
+			template,
+			instanced : supportsInstancing(),
+		};
+		if buffer.instanced {
+			// The template only has to be sent over once.
+			let mut editor = buffer.display.make_editor();
+			editor.add_copy(&buffer.template, &Vec3::zero(), &Color::new(255, 255, 255, 255));
+		}
+		buffer.update(&[]);
+		buffer
+	}
+
+	/// Whether it's drawing with instancing (rather than the batched fallback).
+	pub fn is_instanced(&self) -> bool {
+		self.instanced
+	}
+
+	/// Gets the underlying DisplayBuffer, for things like views, blending, and visibility. Its contents shouldn't be edited directly.
+	pub fn get_display(&mut self) -> &mut DisplayBuffer {
+		&mut self.display
+	}
+
+	/// Sets which copies are drawn (replacing the last set).
+	pub fn update(&mut self, instances : &[Instance]) {
+		if self.instanced {
+			let (offsets, colors) = instance_arrays(instances);
+			setDisplayBufferInstances(self.display.get_id(), offsets, colors);
+		} else {
+			let mut editor = self.display.make_editor();
+			editor.clear();
+			for instance in instances {
+				editor.add_copy(&self.template, &instance.offset, &instance.color);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_instanced_buffer {
+	use super::*;
+
+	#[test]
+	fn batched_fallback() {
+		let mut template = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		template.add_triangle([Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)], &Color::new(255, 128, 255, 255));
+		let instances = vec![
+			Instance::new(&Vec3::new(10.0, 0.0, 0.0), &Color::new(255, 255, 255, 255)),
+			Instance::new(&Vec3::new(20.0, 5.0, -1.0), &Color::new(0, 255, 128, 255)),
+		];
+		// What's sent for instanced drawing.
+		let (offsets, colors) = instance_arrays(&instances);
+		assert_eq!(offsets, vec![10.0, 0.0, 0.0, 20.0, 5.0, -1.0]);
+		assert_eq!(colors, vec![255, 255, 255, 255, 0, 255, 128, 255]);
+
+		// What's built when it has to be batched instead.
+		let mut batched = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		for instance in &instances {
+			batched.add_copy(&template, &instance.offset, &instance.color);
+		}
+		assert_eq!(batched.vertex_count(), 6);
+		assert_eq!(batched.get_indices(), &vec![0, 1, 2, 3, 4, 5]);
+	}
+}
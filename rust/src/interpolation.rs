@@ -0,0 +1,79 @@
+use crate::geo::vec2::*;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How far something can move in a single simulation step before it's treated as a teleport (and not blended).
+const TELEPORT_DISTANCE : f32 = 48.0;
+
+/// Tracks where something was for the last two simulation steps, so it can be drawn part way between them.
+///
+/// The simulation runs in fixed steps that don't line up with screen refreshes, so drawing at the latest step's position stutters. Drawing at a blend of the last two (by how far the leftover time is into the next step) keeps motion smooth.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterpolatedPosition {
+	/// Where it was as of the step before last.
+	previous : Vec2,
+	/// Where it was as of the last step.
+	current : Vec2,
+}
+
+impl InterpolatedPosition {
+	/// Creates an instance sitting still at some position.
+	pub fn new(position : &Vec2) -> InterpolatedPosition {
+		InterpolatedPosition {
+			previous : position.clone(),
+			current : position.clone(),
+		}
+	}
+
+	/// Jumps straight to a position (without blending from where it was).
+	pub fn snap(&mut self, position : &Vec2) {
+		self.previous = position.clone();
+		self.current = position.clone();
+	}
+
+	/// Records the position at the end of a simulation step. Really big jumps are snapped to.
+	pub fn push(&mut self, position : &Vec2) {
+		if TELEPORT_DISTANCE < (position - self.current).length() {
+			self.snap(position);
+		} else {
+			self.previous = self.current;
+			self.current = position.clone();
+		}
+	}
+
+	/// Gets where to draw it, given how far into the next step the leftover time is (from 0 for the previous step to 1 for the latest one).
+	pub fn get(&self, interpolation : f32) -> Vec2 {
+		let amount = interpolation.max(0.0).min(1.0);
+		self.previous + (self.current - self.previous) * amount
+	}
+}
+
+impl ShiftOrigin for InterpolatedPosition {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.previous += shift;
+		self.current += shift;
+	}
+}
+
+#[cfg(test)]
+mod tests_interpolation {
+	use super::*;
+
+	#[test]
+	fn blends_and_snaps() {
+		let mut position = InterpolatedPosition::new(&Vec2::new(0.0, 0.0));
+		assert_eq!(position.get(0.5).x, 0.0);
+		position.push(&Vec2::new(4.0, 2.0));
+		assert_eq!(position.get(0.0).x, 0.0);
+		assert_eq!(position.get(0.5).x, 2.0);
+		assert_eq!(position.get(0.5).y, 1.0);
+		assert_eq!(position.get(2.0).x, 4.0);
+
+		// Teleports aren't blended.
+		position.push(&Vec2::new(500.0, 0.0));
+		assert_eq!(position.get(0.0).x, 500.0);
+		position.push(&Vec2::new(502.0, 0.0));
+		position.snap(&Vec2::new(10.0, 0.0));
+		assert_eq!(position.get(0.0).x, 10.0);
+	}
+}
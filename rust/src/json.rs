@@ -0,0 +1,46 @@
+/// Small helpers for building JSON by hand (for handing data out to JavaScript).
+
+use crate::geo::vec2::*;
+use crate::geo::collision_system::*;
+
+/// Formats a number for JSON (which has no NaN or infinity, so those become null).
+pub fn json_number(value : f32) -> String {
+	if value.is_finite() {
+		format!("{}", value)
+	} else {
+		String::from("null")
+	}
+}
+
+/// Formats a vector as a JSON array.
+pub fn json_vec2(value : &Vec2) -> String {
+	format!("[{},{}]", json_number(value.x), json_number(value.y))
+}
+
+/// Formats a string for JSON.
+pub fn json_string(value : &str) -> String {
+	let mut result = String::with_capacity(value.len() + 2);
+	result.push('"');
+	for character in value.chars() {
+		match character {
+			'"' => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			'\t' => result.push_str("\\t"),
+			c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+			c => result.push(c),
+		}
+	}
+	result.push('"');
+	result
+}
+
+/// Formats an obstacle as a JSON object.
+pub fn json_obstacle(obstacle : &CollisionObstacle) -> String {
+	match &obstacle.geometry {
+		CircleObstacle::LineSegment(segment) => format!("{{\"type\":\"segment\",\"start\":{},\"end\":{}}}", json_vec2(&segment.start), json_vec2(&segment.end)),
+		CircleObstacle::Line(line) => format!("{{\"type\":\"line\",\"origin\":{},\"delta\":{}}}", json_vec2(&line.origin), json_vec2(&line.delta)),
+		CircleObstacle::Point(point) => format!("{{\"type\":\"point\",\"position\":{}}}", json_vec2(point)),
+		CircleObstacle::Circle(circle) => format!("{{\"type\":\"circle\",\"center\":{},\"radius\":{}}}", json_vec2(&circle.center), json_number(circle.radius)),
+	}
+}
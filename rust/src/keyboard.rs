@@ -1,9 +1,12 @@
 use std::collections::{HashMap, HashSet};
 
+use crate::input_events::InputQueue;
+
 /// All the virtual keys to care about.
 /// These are the keys that the game cares about.
 /// These are distinguished from real keys in that multiple real keys can map to any of these.
 #[derive(Copy, Clone)]
+#[allow(non_camel_case_types)]
 pub enum Key {
 	NULL = 0, // A junk key that tracked real keys are bound to when they're sent into "unbind()".
 	UP,
@@ -12,6 +15,18 @@ pub enum Key {
 	RIGHT,
 	SPACE,
 	DEBUG,
+	PHOTO_MODE,
+	ZOOM_IN,
+	ZOOM_OUT,
+	STEP,
+	PAUSE,
+	DRONE_COMMAND,
+	BULLET_TIME,
+	ACCEPT,
+	DEATH_HEATMAP,
+	REWIND,
+	MENU,
+	FIRE,
 	COUNT, // Not a key. Just here to count how many exist.
 }
 
@@ -20,22 +35,29 @@ pub struct Keyboard {
 	key_mapping : HashMap<String, usize>, // Maps from keyboard event `key` strings to the index in `key_state` (if the key is tracked).
 	key_state : Vec<bool>, // The state of all tracked (real) keys.
 	bindings : Vec<HashSet<usize>>, // The (outer) Vec has one entry for each Key. The inner HashSet stores the key_state indices that that virtual key maps to.
-	reverse_bindings : Vec<Key> // The reverse of `bindings`: Every real key index has an entry here to indicate which key it's already bound to. This is to make unbinding faster.
+	reverse_bindings : Vec<Key>, // The reverse of `bindings`: Every real key index has an entry here to indicate which key it's already bound to. This is to make unbinding faster.
+	queue : InputQueue, // Real key changes waiting to be applied to `key_state` (see `process_until()`).
 }
 
 impl Keyboard {
-	/// Creates an instance. Assumes all keys are not being pressed.
-	pub fn new() -> Keyboard {
+	/// Creates an instance without any bindings.
+	fn new_unbound() -> Keyboard {
 		let mut bindings = Vec::new();
 		for _virtual_key in 0..(Key::COUNT as usize) {
 			bindings.push(HashSet::new());
 		}
-		let mut instance = Keyboard {
+		Keyboard {
 			key_mapping: HashMap::new(),
 			key_state: Vec::new(),
 			bindings,
 			reverse_bindings: Vec::new(),
-		};
+			queue: InputQueue::new(),
+		}
+	}
+
+	/// Creates an instance. Assumes all keys are not being pressed.
+	pub fn new() -> Keyboard {
+		let mut instance = Keyboard::new_unbound();
 		// Setup some default key bindings.
 		instance.bind(String::from("ArrowUp"),    Key::UP);
 		instance.bind(String::from("ArrowLeft"),  Key::LEFT);
@@ -55,6 +77,34 @@ impl Keyboard {
 		instance.bind(String::from(" "), Key::SPACE);
 
 		instance.bind(String::from("~"), Key::DEBUG);
+
+		instance.bind(String::from("p"), Key::PHOTO_MODE);
+		instance.bind(String::from("="), Key::ZOOM_IN);
+		instance.bind(String::from("+"), Key::ZOOM_IN);
+		instance.bind(String::from("-"), Key::ZOOM_OUT);
+		instance.bind(String::from("."), Key::STEP);
+		instance.bind(String::from("Pause"), Key::PAUSE);
+		instance.bind(String::from("`"), Key::PAUSE);
+
+		instance.bind(String::from("f"), Key::DRONE_COMMAND);
+		instance.bind(String::from("Shift"), Key::BULLET_TIME);
+		instance.bind(String::from("Enter"), Key::ACCEPT);
+		instance.bind(String::from("h"), Key::DEATH_HEATMAP);
+		instance.bind(String::from("r"), Key::REWIND);
+		instance.bind(String::from("Escape"), Key::MENU);
+		instance.bind(String::from("e"), Key::FIRE);
+		instance
+	}
+
+	/// Creates an instance for a second (local) player, using keys the default bindings leave alone: IJKL to move and O for the track.
+	/// Only the keys a Player reads are bound.
+	pub fn new_second_player() -> Keyboard {
+		let mut instance = Keyboard::new_unbound();
+		instance.bind(String::from("i"), Key::UP);
+		instance.bind(String::from("j"), Key::LEFT);
+		instance.bind(String::from("k"), Key::DOWN);
+		instance.bind(String::from("l"), Key::RIGHT);
+		instance.bind(String::from("o"), Key::SPACE);
 		instance
 	}
 
@@ -91,6 +141,35 @@ impl Keyboard {
 		return false;
 	}
 
+	/// Checks if the given virtual key went down since the last simulation step (see `end_step()`). Meant for the simulation: anything that runs every update (like menus) can see the same press more than once.
+	pub fn just_pressed(&self, key : Key) -> bool {
+		self.bindings[key as usize].iter().any(|real_index| self.queue.was_pressed(*real_index))
+	}
+
+	/// Signals that the given (real) key was pressed at some time, to be applied by `process_until()`.
+	pub fn queue_down(&mut self, real : String, time : f64) {
+		if let Some(real_index) = self.key_mapping.get(&real) {
+			self.queue.push(*real_index, true, time);
+		}
+	}
+
+	/// Signals that the given (real) key was released at some time, to be applied by `process_until()`.
+	pub fn queue_up(&mut self, real : String, time : f64) {
+		if let Some(real_index) = self.key_mapping.get(&real) {
+			self.queue.push(*real_index, false, time);
+		}
+	}
+
+	/// Applies the queued key changes that happened by some time, in order. A key that's tapped stays down until a step has seen it (see `end_step()`).
+	pub fn process_until(&mut self, time : f64) {
+		self.queue.apply_until(time, &mut self.key_state);
+	}
+
+	/// Notes that a simulation step has seen the current key states, so the keys that were just pressed can be let go.
+	pub fn end_step(&mut self) {
+		self.queue.end_step();
+	}
+
 	// Signals that the given (real) key has been pressed.
 	pub fn on_down(&mut self, real : String) {
 		if let Option::Some(real_index) = self.key_mapping.get(&real) {
@@ -104,6 +183,14 @@ impl Keyboard {
 			self.key_state[*real_index] = false;
 		}
 	}
+
+	/// Marks every key as released (as in when switching where the key presses come from).
+	pub fn release_all(&mut self) {
+		for state in &mut self.key_state {
+			*state = false;
+		}
+		self.queue.clear();
+	}
 }
 
 #[cfg(test)]
@@ -125,6 +212,40 @@ mod tests {
 		assert_eq!(keyboard.is_down(Key::SPACE), false);
 	}
 
+	#[test]
+	fn queued_taps() {
+		let mut keyboard = Keyboard::new();
+		keyboard.queue_down(" ".to_string(), 1.0);
+		keyboard.queue_up(" ".to_string(), 1.001);
+		keyboard.queue_down("Unbound".to_string(), 1.002);
+		assert_eq!(keyboard.is_down(Key::SPACE), false); // Nothing happens until it's processed.
+		keyboard.process_until(1.0 / 60.0 + 1.0);
+		assert_eq!(keyboard.is_down(Key::SPACE), true);
+		assert_eq!(keyboard.just_pressed(Key::SPACE), true);
+		keyboard.process_until(1.0 / 60.0 + 1.0); // No step has seen it yet.
+		assert!(keyboard.just_pressed(Key::SPACE));
+		keyboard.end_step();
+		keyboard.process_until(1.0 / 60.0 + 1.0);
+		assert_eq!(keyboard.is_down(Key::SPACE), false);
+		assert_eq!(keyboard.just_pressed(Key::SPACE), false);
+	}
+
+	#[test]
+	fn second_player_bindings() {
+		let mut first = Keyboard::new();
+		let mut second = Keyboard::new_second_player();
+		for key in &["j", "w"] {
+			first.on_down(key.to_string());
+			second.on_down(key.to_string());
+		}
+		assert_eq!(second.is_down(Key::LEFT), true);
+		assert_eq!(second.is_down(Key::UP),   false);
+		assert_eq!(first.is_down(Key::LEFT),  false);
+		assert_eq!(first.is_down(Key::UP),    true);
+		second.on_up("j".to_string());
+		assert_eq!(second.is_down(Key::LEFT), false);
+	}
+
 	#[test]
 	fn rebinding() {
 		let mut keyboard = Keyboard::new();
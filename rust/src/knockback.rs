@@ -0,0 +1,112 @@
+use crate::geo::vec2::*;
+use crate::geo::consts::EPSILON;
+
+/// How quickly knockback velocity dies off (the fraction lost per second, as an exponential rate).
+const KNOCKBACK_DECAY_RATE : f32 = 8.0;
+/// Below what speed knockback is considered over.
+const KNOCKBACK_MIN_SPEED : f32 = 1.0;
+/// How long something flashes after being hit (in seconds).
+const HIT_FLASH_SECONDS : f32 = 0.15;
+
+/// Tracks getting knocked back by a hit: a push away from whatever did the damage that quickly dies off, a stretch of time where inputs (or AI) are ignored, and a brief flash.
+///
+/// Anything that can be damaged (the player or enemies) owns one of these and adds the movement from `update()` to its own.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Knockback {
+	/// The current push velocity (in pixels per second).
+	velocity : Vec2,
+	/// How much longer inputs should be ignored (in seconds).
+	stun_remaining : f32,
+	/// How much longer to flash (in seconds).
+	flash_remaining : f32,
+}
+
+impl Knockback {
+	/// Creates an instance that isn't being knocked back.
+	pub fn new() -> Knockback {
+		Knockback {
+			velocity : Vec2::zero(),
+			stun_remaining : 0.0,
+			flash_remaining : 0.0,
+		}
+	}
+
+	/// Starts a knockback pushing the target away from the source at some speed, and stunning it for some time.
+	/// If the two are on top of each other, the push goes along the fallback direction instead.
+	pub fn apply(&mut self, source : &Vec2, target : &Vec2, fallback : &Vec2, speed : f32, stun_seconds : f32) {
+		let away = target - source;
+		let direction = if EPSILON < away.length() { away.norm() } else { fallback.norm() };
+		self.velocity = direction * speed;
+		self.stun_remaining = self.stun_remaining.max(stun_seconds);
+		self.flash_remaining = HIT_FLASH_SECONDS;
+	}
+
+	/// Moves time forward. Returns how far the knockback pushes things over that time.
+	pub fn update(&mut self, elapsed_seconds : f32) -> Vec2 {
+		self.stun_remaining = (self.stun_remaining - elapsed_seconds).max(0.0);
+		self.flash_remaining = (self.flash_remaining - elapsed_seconds).max(0.0);
+		if self.velocity.length() < KNOCKBACK_MIN_SPEED {
+			self.velocity = Vec2::zero();
+			return Vec2::zero();
+		}
+		// Integrate the exponential decay exactly, so the total push doesn't depend on the step size.
+		let remaining = (-KNOCKBACK_DECAY_RATE * elapsed_seconds).exp();
+		let movement = self.velocity * ((1.0 - remaining) / KNOCKBACK_DECAY_RATE);
+		self.velocity = self.velocity * remaining;
+		movement
+	}
+
+	/// Stops any knockback (as in when hitting a wall).
+	pub fn cancel(&mut self) {
+		self.velocity = Vec2::zero();
+	}
+
+	/// Gets the current push velocity.
+	pub fn get_velocity(&self) -> Vec2 {
+		self.velocity
+	}
+
+	/// Whether inputs (or AI) should currently be ignored.
+	pub fn is_stunned(&self) -> bool {
+		0.0 < self.stun_remaining
+	}
+
+	/// How strongly to flash (from 0 for not at all to 1 for fully), fading out over the flash time.
+	pub fn get_flash(&self) -> f32 {
+		self.flash_remaining / HIT_FLASH_SECONDS
+	}
+}
+
+#[cfg(test)]
+mod tests_knockback {
+	use super::*;
+
+	#[test]
+	fn pushes_away_and_decays() {
+		let mut knockback = Knockback::new();
+		assert_eq!(knockback.update(1.0).length(), 0.0);
+		knockback.apply(&Vec2::new(10.0, 0.0), &Vec2::new(0.0, 0.0), &Vec2::new(0.0, 1.0), 100.0, 0.3);
+		assert!(knockback.is_stunned());
+		assert_eq!(knockback.get_flash(), 1.0);
+		let mut total = Vec2::zero();
+		for _ in 0..10 {
+			total += knockback.update(0.05);
+		}
+		assert!(!knockback.is_stunned());
+		assert_eq!(knockback.get_flash(), 0.0);
+		assert!(0.0 > total.x); // Pushed away from the source.
+		assert!(total.x.abs() < 100.0 / KNOCKBACK_DECAY_RATE); // Never goes further than the full decay would allow.
+		assert!(knockback.get_velocity().length() < 100.0);
+
+		// The total push doesn't depend on the step size.
+		let mut coarse = Knockback::new();
+		coarse.apply(&Vec2::new(10.0, 0.0), &Vec2::new(0.0, 0.0), &Vec2::new(0.0, 1.0), 100.0, 0.0);
+		let coarse_total = coarse.update(0.5);
+		assert!((coarse_total.x - total.x).abs() < 0.01);
+
+		// Falls back when on top of the source.
+		knockback.apply(&Vec2::zero(), &Vec2::zero(), &Vec2::new(0.0, 2.0), 100.0, 0.0);
+		assert_eq!(knockback.get_velocity().y, 100.0);
+	}
+}
@@ -1,5 +1,6 @@
 use wasm_bindgen::prelude::*;
 
+#[cfg(not(feature = "release-lean"))]
 use std::panic;
 
 // The below modules were made public just so Rust would stop complaining about dead code.
@@ -10,28 +11,132 @@ mod externals;
 mod color;
 pub mod display_texture;
 pub mod display_buffer;
+pub mod byte_reader;
+pub mod text_layout;
+pub mod texture_atlas;
 pub mod tiled;
+pub mod tiled_json;
 pub mod tiled_display;
+pub mod tile_chunks;
 pub mod tiled_geometry;
+pub mod tile_shapes;
+pub mod tile_behavior;
+pub mod rope;
+pub mod zipline;
+pub mod moving_platform;
+pub mod palette;
+pub mod nav_grid;
+pub mod pickups;
+pub mod drone;
+pub mod enemies;
+pub mod projectiles;
+pub mod secrets;
+pub mod challenge_room;
+pub mod water;
+pub mod tween;
+pub mod random;
+pub mod animation;
+pub mod health;
+pub mod knockback;
+pub mod interpolation;
+pub mod ability_meter;
+pub mod stamina;
+pub mod tuning;
+pub mod ui_focus;
+pub mod screen_effects;
+pub mod room_transition;
+pub mod world_flags;
+pub mod scheduler;
+pub mod meter_bar;
+pub mod shadows;
+pub mod event_bus;
+pub mod objectives;
+pub mod photo_mode;
+pub mod time_control;
+pub mod json;
+pub mod accessibility;
+pub mod anomaly_capture;
+pub mod attract_mode;
+pub mod map_queries;
+pub mod ground_normal;
 pub mod player;
 mod camera;
 pub mod mouse;
+pub mod input_events;
 pub mod keyboard;
 pub mod gamepad;
 pub mod display_text;
+pub mod death_log;
+pub mod origin_rebase;
+pub mod snapshot;
+pub mod rewind;
+pub mod ghost;
+pub mod coop;
+pub mod scene;
+pub mod pause_menu;
+pub mod title;
+pub mod render_view;
+pub mod instanced_buffer;
+pub mod camera_region;
+pub mod button_prompt;
+pub mod save_slots;
+pub mod asset_manifest;
+pub mod ambient;
+pub mod sound;
+pub mod music;
+pub mod camera_rail;
+pub mod pool;
+pub mod spatial_hash;
+pub mod movement_assist;
+pub mod hitboxes;
+pub mod debris;
+pub mod diagnostics;
+pub mod work_queue;
+pub mod update_lod;
+pub mod sprite_animation;
 mod game;
 
+#[cfg(not(feature = "release-lean"))]
 use console_error_panic_hook;
 
 /// Sets up the whole game system.
 /// Must be run before anything else!
 #[wasm_bindgen]
 pub fn setup(is_little_endian : bool) {
-	panic::set_hook(Box::new(console_error_panic_hook::hook));
+	#[cfg(not(feature = "release-lean"))]
+	panic::set_hook(Box::new(console_error_panic_hook::hook)); // Lean builds skip the readable panic messages (and all the formatting they need).
 	static_singletons::set_browser_is_little_endian(is_little_endian);
 	static_singletons::create_game();
 }
 
+/// Like `setup()`, but then runs a quick self-test of the link to JavaScript (see `diagnostics::run_diagnostics()`), so integration problems are caught right away.
+/// Logs any checks that failed, and returns the full report as JSON.
+#[wasm_bindgen]
+pub fn setup_with_diagnostics(is_little_endian : bool) -> String {
+	setup(is_little_endian);
+	let report = diagnostics::run_diagnostics();
+	for check in report.checks.iter().filter(|check| !check.passed) {
+		externals::log(&format!("Self-test {:?} failed: {}", check.name, check.details));
+	}
+	report.to_json()
+}
+
+/// Reads in the asset manifest (see `asset_manifest::AssetManifest::from_json()`). Should be run before `setup()`, so the first loads use it.
+/// If it isn't valid, logs why and keeps loading everything by name.
+#[wasm_bindgen]
+pub fn set_asset_manifest(json : String) {
+	match asset_manifest::AssetManifest::from_json(&json) {
+		Ok(manifest) => static_singletons::set_asset_manifest(manifest),
+		Err(error) => externals::log(&format!("Ignoring the asset manifest due to: {}", error)),
+	}
+}
+
+/// Gets the URLs of everything to load before the game starts (for the loading screen) as a JSON array.
+#[wasm_bindgen]
+pub fn get_asset_preload_urls() -> String {
+	static_singletons::get_asset_manifest().preload_urls_json()
+}
+
 /// Updates the game according to some number of elapsed seconds.
 #[wasm_bindgen]
 pub fn update(elapsed_seconds : f32) {
@@ -44,6 +149,54 @@ pub fn on_resize(width : u32, height : u32) {
 	static_singletons::get_game().on_resize(width, height);
 }
 
+/// Turns camera-relative rendering on or off. It's on by default with the "f64-world" feature.
+#[wasm_bindgen]
+pub fn set_relative_rendering(enabled : bool) {
+	static_singletons::get_game().set_relative_rendering(enabled);
+}
+
+/// Sets a virtual resolution: a fixed view size (like 480 by 270) that's scaled up to fill the screen, with letterboxing around the rest. The scaling is "integer" (crisp pixels) or "fit". A zero size turns it off.
+#[wasm_bindgen]
+pub fn set_virtual_resolution(width : u32, height : u32, scaling : String) {
+	static_singletons::get_game().set_virtual_resolution(width, height, &scaling);
+}
+
+/// Captures the state of the simulation as JSON (see `snapshot::GameSnapshot`). Gives "null" while the room is loading.
+#[cfg(feature = "serialize")]
+#[wasm_bindgen]
+pub fn get_snapshot() -> String {
+	static_singletons::get_game().snapshot().map_or(String::from("null"), |snapshot| snapshot.to_json())
+}
+
+/// Puts the simulation back the way a snapshot (from `get_snapshot()`) had it. Returns whether that worked.
+#[cfg(feature = "serialize")]
+#[wasm_bindgen]
+pub fn restore_snapshot(json : &str) -> bool {
+	let result = snapshot::GameSnapshot::from_json(json).and_then(|snapshot| static_singletons::get_game().restore(&snapshot));
+	if let Err(error) = &result {
+		externals::log(&format!("Couldn't restore snapshot: {}", error));
+	}
+	result.is_ok()
+}
+
+/// Shows or hides the minimap.
+#[wasm_bindgen]
+pub fn set_minimap_visible(visible : bool) {
+	static_singletons::get_game().set_minimap_visible(visible);
+}
+
+/// Adds or removes a second, locally controlled player (IJKL and O on the keyboard, or the second gamepad).
+#[wasm_bindgen]
+pub fn set_second_player(enabled : bool) {
+	static_singletons::get_game().set_second_player(enabled);
+}
+
+/// Turns origin rebasing on or off (it's off by default). While on, the whole world is moved back toward the origin whenever the player gets far from it.
+#[wasm_bindgen]
+pub fn set_origin_rebasing(enabled : bool) {
+	static_singletons::get_game().set_origin_rebasing(enabled);
+}
+
 /// Notifies the game when a key is pressed.
 #[wasm_bindgen]
 pub fn on_key_down(key : String) {
@@ -74,8 +227,182 @@ pub fn on_mouse_leave() {
 	static_singletons::get_game().on_mouse_leave();
 }
 
-/// Notifies the game that the gamepad's state has changed.
+/// Notifies the game that a gamepad's state has changed. The index is which gamepad it is (as the browser numbers them).
+#[wasm_bindgen]
+pub fn on_gamepad_changed(index : u32, valid : bool, buttons : Vec<f32>, raw_analog_sticks : Vec<f32>) {
+	static_singletons::get_game().on_gamepad_changed(index, valid, buttons, raw_analog_sticks);
+}
+
+/// Gets the device the player last used ("keyboard" or "gamepad"), so prompts can show matching buttons.
+#[wasm_bindgen]
+pub fn get_input_device() -> String {
+	String::from(static_singletons::get_game().get_input_device().name())
+}
+
+/// Pauses the simulation (for debugging). Rendering and input carry on.
+#[wasm_bindgen]
+pub fn debug_pause() {
+	static_singletons::get_game().get_time_control().pause();
+}
+
+/// Lets the simulation run normally again.
+#[wasm_bindgen]
+pub fn debug_resume() {
+	static_singletons::get_game().get_time_control().resume();
+}
+
+/// Pauses the simulation (if not already) and moves it forward by some number of fixed steps (one per update).
+#[wasm_bindgen]
+pub fn debug_step(count : u32) {
+	static_singletons::get_game().get_time_control().step(count);
+}
+
+/// Gets whether the simulation is paused.
+#[wasm_bindgen]
+pub fn debug_is_paused() -> bool {
+	static_singletons::get_game().get_time_control().is_paused()
+}
+
+/// Forgets every recorded player death (which the death heatmap debug overlay shows).
+#[wasm_bindgen]
+pub fn debug_clear_deaths() {
+	static_singletons::get_game().clear_death_log();
+}
+
+/// Snapshots the simulation and keeps it in memory (see `debug_quick_load()`).
+#[wasm_bindgen]
+pub fn debug_quick_save() {
+	static_singletons::get_game().quick_save();
+}
+
+/// Puts the simulation back to the last `debug_quick_save()`. Only works in the same room.
+#[wasm_bindgen]
+pub fn debug_quick_load() {
+	static_singletons::get_game().quick_load();
+}
+
+/// Starts or stops auditing collision queries. Any frame where they take longer than the budget (in milliseconds) gets logged, along with the worst query.
+#[wasm_bindgen]
+pub fn debug_collision_audit(enabled : bool, budget_milliseconds : f64) {
+	static_singletons::get_game().set_collision_audit(enabled, budget_milliseconds);
+}
+
+/// Gets how full each entity pool is (as a JSON array of objects with "name", "capacity", "live", "peak", and "grown"). A pool that keeps growing is sized too small.
+#[wasm_bindgen]
+pub fn debug_pool_stats() -> String {
+	static_singletons::get_game().get_pool_stats()
+}
+
+/// Sets a value on an enemy definition (creating it if needed). All values are passed as strings.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn enemy_definition_set_property(definition : String, name : String, value : String) {
+	static_singletons::get_game().set_enemy_definition_property(&definition, &name, &value);
+}
+
+/// Called once every enemy definition from a file has been passed in.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn enemy_definitions_done(url : String) {
+	static_singletons::get_game().handle_enemy_definitions_loaded(&url);
+}
+
+/// Starts loading a ghost (see `ghost::GhostRun::to_bytes()`) to race against, as in one another player uploaded.
+#[wasm_bindgen]
+pub fn load_ghost(url : String) {
+	externals::startGhostLoad(&url, &asset_manifest::asset_url(&url));
+}
+
+/// Hands over a ghost that finished loading.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn ghost_loaded(url : String, bytes : Vec<u8>) {
+	static_singletons::get_game().handle_ghost_loaded(&url, &bytes);
+}
+
+/// Gets the last finished run as a packed ghost (see `ghost::GhostRun::to_bytes()`), to be shared. Empty if no run has been finished yet.
+#[wasm_bindgen]
+pub fn get_last_run_ghost() -> Vec<u8> {
+	static_singletons::get_game().get_last_run().map_or(Vec::new(), |run| run.to_bytes())
+}
+
+/// Sets a world flag (as in from dialogue). The value is "true", "false", or an integer.
+#[wasm_bindgen]
+pub fn world_flag_set(name : String, value : String) {
+	static_singletons::get_game().set_world_flag(&name, &value);
+}
+
+/// Gets a world flag's value ("true", "false", or an integer). Flags that were never set are "false".
+#[wasm_bindgen]
+pub fn world_flag_get(name : String) -> String {
+	static_singletons::get_game().get_world_flags().get(&name).map_or(String::from("false"), |value| value.to_text())
+}
+
+/// Sends the camera along a camera rail from the map (as in from dialogue) over some seconds with some easing ("linear", "quadIn", "quadOut", or "quadInOut"). Returns whether it started.
+#[wasm_bindgen]
+pub fn camera_rail_play(name : String, seconds : f32, easing : String) -> bool {
+	static_singletons::get_game().play_camera_rail(&name, seconds, &easing)
+}
+
+/// Changes an accessibility setting (see `accessibility.rs` for the names). The value is "true", "false", or a number for the text and UI scales.
+#[wasm_bindgen]
+pub fn accessibility_set(name : String, value : String) {
+	static_singletons::get_game().set_accessibility_setting(&name, &value);
+}
+
+/// Gets all the accessibility settings (as `name=value` lines).
+#[wasm_bindgen]
+pub fn accessibility_get() -> String {
+	static_singletons::get_game().get_accessibility().to_save_string()
+}
+
+/// Changes a gamepad calibration setting (see `gamepad.rs` for the names). The value is the "deadzone" size, a stick axis' resting "Offset", or whether it's "Inverted".
+#[wasm_bindgen]
+pub fn gamepad_set(name : String, value : String) {
+	static_singletons::get_game().set_gamepad_setting(&name, &value);
+}
+
+/// Gets the first gamepad's calibration (as `name=value` lines).
+#[wasm_bindgen]
+pub fn gamepad_get() -> String {
+	static_singletons::get_game().get_gamepad_calibration().to_save_string()
+}
+
+/// Switches all text to a locale's font stack and line breaking (a BCP 47 tag like "ja" or "zh-TW").
+#[wasm_bindgen]
+pub fn set_text_locale(locale : String) {
+	display_text::DisplayText::set_locale_for_all(&locale);
+}
+
+/// Describes a tile (by its column and row in a layer, counting from the top left) as JSON. Gives "null" if there's no such tile.
+#[wasm_bindgen]
+pub fn query_tile(x : u32, y : u32, layer : u32) -> String {
+	static_singletons::get_game().query_tile(x as usize, y as usize, layer as usize)
+}
+
+/// Describes all the collision obstacles touching a position (in map coordinates) as JSON.
+#[wasm_bindgen]
+pub fn query_collision_at(x : f64, y : f64) -> String {
+	static_singletons::get_game().query_collision_at(x, y)
+}
+
+/// Describes the current map (its size, layers, and object counts) as JSON. Gives "null" while it's loading.
+#[wasm_bindgen]
+pub fn get_map_metadata() -> String {
+	static_singletons::get_game().get_map_metadata()
+}
+
+/// Gets all physics anomaly captures (as a JSON array) for bug reports.
+#[wasm_bindgen]
+pub fn get_anomaly_captures() -> String {
+	static_singletons::get_game().get_anomaly_capture().to_json()
+}
+
+/// Removes all physics anomaly captures.
 #[wasm_bindgen]
-pub fn on_gamepad_changed(valid : bool, buttons : Vec<f32>, raw_analog_sticks : Vec<f32>) {
-	static_singletons::get_game().on_gamepad_changed(valid, buttons, raw_analog_sticks);
+pub fn clear_anomaly_captures() {
+	static_singletons::get_game().get_anomaly_capture().clear();
 }
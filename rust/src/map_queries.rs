@@ -0,0 +1,125 @@
+/// Read-only queries about the loaded map and collision geometry, formatted as JSON for external tooling (like debug panels and editors).
+
+use std::collections::BTreeMap;
+
+use crate::tiled::*;
+use crate::json::*;
+use crate::geo::vec2::*;
+use crate::geo::collision_system::*;
+
+/// How close an obstacle needs to be to a queried position to be included (in pixels).
+const COLLISION_QUERY_DISTANCE : f32 = 1.0;
+
+/// Describes a single tile in a layer (by its column and row, counting from the top left like Tiled does).
+/// Returns "null" if the layer or position doesn't exist.
+pub fn query_tile(file : &TiledFile, x : usize, y : usize, layer_index : usize) -> String {
+	let layer = match file.get_tile_layers().get(layer_index) {
+		Some(layer) => layer,
+		None => { return String::from("null"); },
+	};
+	if layer.get_width() <= x || layer.get_height() <= y {
+		return String::from("null");
+	}
+	let id = layer.get_tile_id(x, y);
+	if file.tile_count() <= id as usize {
+		return String::from("null");
+	}
+	let tile = file.get_tile(id);
+	let mut properties = Vec::new();
+	for property in tile.get_boolean_properties() {
+		properties.push(format!("{}:{}", json_string(&property.name), property.value));
+	}
+	for property in tile.get_string_properties() {
+		properties.push(format!("{}:{}", json_string(&property.name), json_string(&property.value)));
+	}
+	format!(
+		"{{\"layer\":{},\"x\":{},\"y\":{},\"id\":{},\"image\":{},\"source\":{},\"size\":{},\"properties\":{{{}}},\"collisionRects\":{},\"collisionPolygons\":{}}}",
+		json_string(layer.get_name()),
+		x,
+		y,
+		id,
+		json_string(tile.get_image_url()),
+		json_vec2(&tile.get_position()),
+		json_vec2(&tile.get_size()),
+		properties.join(","),
+		tile.get_collision_rectangles().len(),
+		tile.get_collision_polygons().len(),
+	)
+}
+
+/// Describes all active collision obstacles touching a position (in game coordinates).
+pub fn query_collision_at(collision : &CollisionSystem, position : &Vec2) -> String {
+	let mut obstacles = Vec::new();
+	for index in collision.find_obstacles_near(position, COLLISION_QUERY_DISTANCE) {
+		obstacles.push(json_obstacle(&collision.obstacles[index]));
+	}
+	format!("{{\"position\":{},\"obstacles\":[{}]}}", json_vec2(position), obstacles.join(","))
+}
+
+/// Counts things by their type (sorted by type so the output is stable).
+fn json_type_counts<'a, I : Iterator<Item = &'a str>>(types : I) -> String {
+	let mut counts : BTreeMap<&str, usize> = BTreeMap::new();
+	for r#type in types {
+		*counts.entry(r#type).or_insert(0) += 1;
+	}
+	let entries : Vec<String> = counts.iter().map(|(r#type, count)| format!("{}:{}", json_string(r#type), count)).collect();
+	format!("{{{}}}", entries.join(","))
+}
+
+/// Describes the map as a whole: its size, layers, and how many of each kind of object it has.
+pub fn get_map_metadata(file : &TiledFile, collision : &CollisionSystem) -> String {
+	let mut size = Vec2::zero();
+	let mut layers = Vec::with_capacity(file.get_tile_layers().len());
+	for layer in file.get_tile_layers() {
+		let offset = layer.get_offset();
+		let layer_size = layer.get_size();
+		size.x = size.x.max(offset.x + layer_size.x);
+		size.y = size.y.max(offset.y + layer_size.y);
+		layers.push(format!(
+			"{{\"name\":{},\"width\":{},\"height\":{},\"offset\":{},\"size\":{}}}",
+			json_string(layer.get_name()),
+			layer.get_width(),
+			layer.get_height(),
+			json_vec2(&offset),
+			json_vec2(&layer_size),
+		));
+	}
+	format!(
+		"{{\"url\":{},\"size\":{},\"layers\":[{}],\"tiles\":{},\"points\":{},\"polylines\":{},\"regions\":{},\"obstacles\":{}}}",
+		json_string(file.get_url()),
+		json_vec2(&size),
+		layers.join(","),
+		file.tile_count(),
+		json_type_counts(file.get_points().iter().map(|point| point.r#type.as_str())),
+		json_type_counts(file.get_polylines().iter().map(|polyline| polyline.r#type.as_str())),
+		json_type_counts(file.get_regions().iter().map(|region| region.r#type.as_str())),
+		collision.obstacles.len(),
+	)
+}
+
+#[cfg(test)]
+mod tests_map_queries {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+
+	#[test]
+	fn collision_and_metadata() {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(0.0, 0.0), &Vec2::new(10.0, 0.0))));
+		collision.add_obstacle(CircleObstacle::Point(Vec2::new(100.0, 0.0)));
+		assert_eq!(query_collision_at(&collision, &Vec2::new(5.0, 0.5)), "{\"position\":[5,0.5],\"obstacles\":[{\"type\":\"segment\",\"start\":[0,0],\"end\":[10,0]}]}");
+		assert_eq!(query_collision_at(&collision, &Vec2::new(50.0, 0.0)), "{\"position\":[50,0],\"obstacles\":[]}");
+
+		let mut file = TiledFile::new();
+		for (name, r#type) in [("a", "enemy"), ("b", "door"), ("c", "enemy")].iter() {
+			file.points.push(TiledPoint {
+				position : Vec2::zero(),
+				name : name.to_string(),
+				r#type : r#type.to_string(),
+				properties : Vec::new(),
+			});
+		}
+		assert_eq!(get_map_metadata(&file, &collision), "{\"url\":\"\",\"size\":[0,0],\"layers\":[],\"tiles\":0,\"points\":{\"door\":1,\"enemy\":2},\"polylines\":{},\"regions\":{},\"obstacles\":2}");
+		assert_eq!(query_tile(&file, 0, 0, 0), "null");
+	}
+}
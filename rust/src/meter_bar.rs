@@ -0,0 +1,90 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::mat4::*;
+
+/// How wide the bar is (in pixels).
+const BAR_WIDTH : f32 = 16.0;
+/// How tall the bar is (in pixels).
+const BAR_HEIGHT : f32 = 3.0;
+/// The depth to draw the bar at (in front of the world, behind the screen effects).
+const BAR_DEPTH : f32 = -0.8;
+
+/// A small bar drawn in the world (usually over the player's head) that shows how full something is.
+pub struct MeterBar {
+	/// The fraction last drawn (negative if hidden).
+	fraction : f32,
+	/// The color of the filled part.
+	color : Color,
+	/// How much bigger than normal the bar is drawn (the UI scale).
+	scale : f32,
+	/// What the bar is drawn in.
+	display : DisplayBuffer,
+}
+
+impl MeterBar {
+	/// Creates an instance that's not showing anything.
+	pub fn new(color : &Color) -> MeterBar {
+		MeterBar {
+			fraction : -1.0,
+			color : color.clone(),
+			scale : 1.0,
+			display : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+		}
+	}
+
+	/// Sets the color of the filled part. It's redrawn on the next update.
+	pub fn set_color(&mut self, color : &Color) {
+		self.color = color.clone();
+		self.fraction = std::f32::NAN; // Never equal to anything, so the next update redraws.
+	}
+
+	/// Sets how much bigger than normal the bar is drawn (the UI scale).
+	pub fn set_scale(&mut self, scale : f32) {
+		self.scale = scale;
+	}
+
+	/// Shows or hides the bar (regardless of what it's set to).
+	pub fn set_visible(&mut self, visible : bool) {
+		if visible {
+			self.display.show();
+		} else {
+			self.display.hide();
+		}
+	}
+
+	/// Updates the bar. The fraction is how full it is (from 0 to 1), or None to hide it. The position is where the bar's center should go.
+	pub fn update(&mut self, fraction : Option<f32>, position : &Vec2) {
+		let new_fraction = match fraction {
+			Some(value) => value.max(0.0).min(1.0),
+			None => -1.0,
+		};
+		if new_fraction != self.fraction {
+			self.fraction = new_fraction;
+			let mut editor = self.display.make_editor();
+			editor.clear();
+			if 0.0 <= self.fraction {
+				let left = -BAR_WIDTH / 2.0;
+				let right = left + BAR_WIDTH * self.fraction;
+				let bottom = -BAR_HEIGHT / 2.0;
+				let top = BAR_HEIGHT / 2.0;
+				editor.add_polygon(&vec![
+					Vec3::new(left, bottom, BAR_DEPTH + 0.01),
+					Vec3::new(-left, bottom, BAR_DEPTH + 0.01),
+					Vec3::new(-left, top, BAR_DEPTH + 0.01),
+					Vec3::new(left, top, BAR_DEPTH + 0.01),
+				], &Color::new(0, 0, 0, 160));
+				editor.add_polygon(&vec![
+					Vec3::new(left, bottom, BAR_DEPTH),
+					Vec3::new(right, bottom, BAR_DEPTH),
+					Vec3::new(right, top, BAR_DEPTH),
+					Vec3::new(left, top, BAR_DEPTH),
+				], &self.color);
+			}
+		}
+		let mut transform = Mat4::new();
+		transform.translate_before(&Vec3::new(position.x, position.y, 0.0)).scale_before(&Vec3::new(self.scale, self.scale, 1.0));
+		self.display.set_transform(&transform);
+	}
+}
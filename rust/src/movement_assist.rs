@@ -0,0 +1,177 @@
+use crate::geo::vec2::*;
+use crate::geo::consts::EPSILON;
+use crate::geo::collider::TotalDeflection;
+use crate::geo::collision_system::{CollisionSystem, CircleQuery};
+
+/// How much a surface's normal needs to point up for a step to be landed on.
+const STEP_GROUND_DOT : f32 = 0.7;
+/// How much further than it went up the step-down sweep looks for ground (in pixels), so steps that are slightly lower on the far side still count.
+const STEP_DOWN_SLACK : f32 = 1.0;
+/// Hits lower than this (in pixels above the bottom of the circle) are the floor itself rather than a lip.
+const MIN_STEP_HEIGHT : f32 = 0.05;
+/// How far apart the sideways spots tried by corner correction are (in pixels).
+const CORNER_CORRECTION_STEP : f32 = 0.5;
+/// How much a nudged circle can overlap things and still count as clear (in pixels), so resting against a wall doesn't rule a spot out.
+const CORNER_OVERLAP_TOLERANCE : f32 = 0.05;
+
+/// Sweeps a circle, only counting obstacles it actually gets pushed by (not ones it slides along).
+fn sweep(collision : &CollisionSystem, position : &Vec2, radius : f32, movement : &Vec2) -> Option<TotalDeflection> {
+	collision.collide_circle_query(position, radius, movement, &CircleQuery { include_skimming : false, ..CircleQuery::new() })
+}
+
+/// Gets how far along a sweep got before hitting something (from 0 to 1).
+fn safe_fraction(hit : &Option<TotalDeflection>) -> f32 {
+	match hit {
+		Some(hit) => hit.deflections[0].times.min().unwrap_or(0.0).max(0.0),
+		None => 1.0,
+	}
+}
+
+/// Works out whether a circle (the player) walking along the ground can step up over a small lip that's in its way, rather than being stopped by it.
+///
+/// The lip has to be hit low down (no higher than `max_height` above the bottom of the circle). Then the circle is swept up by `max_height`, forward by the horizontal part of the movement, and back down.
+/// If all of that is clear and it lands on ground, returns where it lands.
+pub fn find_step_up(collision : &CollisionSystem, position : &Vec2, radius : f32, movement : &Vec2, up : &Vec2, max_height : f32) -> Option<Vec2> {
+	if 0.0 >= max_height {
+		return None;
+	}
+	let forward = movement - up.scale(movement.dot(up));
+	if EPSILON > forward.length() {
+		return None;
+	}
+	let hit = sweep(collision, position, radius, &forward)?;
+	let blocker = &hit.deflections[0];
+	let height = radius * (1.0 - blocker.normal.dot(up)); // Where on the circle it was hit, measured up from the bottom.
+	if height < MIN_STEP_HEIGHT || max_height < height || 0.0 <= blocker.normal.dot(&forward) {
+		return None;
+	}
+
+	// Up (as far as there's room for), which has to be at least as high as the lip.
+	let rise = max_height * safe_fraction(&sweep(collision, position, radius, &up.scale(max_height)));
+	if rise < height {
+		return None;
+	}
+	let raised = position + up.scale(rise);
+
+	// Forward, which has to be completely clear (otherwise it's a wall rather than a lip).
+	if sweep(collision, &raised, radius, &forward).is_some() {
+		return None;
+	}
+	let ahead = raised + forward;
+
+	// Back down onto the top of the lip.
+	let drop = up.scale(-(rise + STEP_DOWN_SLACK));
+	let landing = sweep(collision, &ahead, radius, &drop)?;
+	if landing.deflections.iter().all(|deflection| STEP_GROUND_DOT > deflection.normal.dot(up)) {
+		return None;
+	}
+	Some(ahead + drop.scale(safe_fraction(&Some(landing))))
+}
+
+/// Works out whether a circle (the player) going up that just clipped the edge of a ceiling can be nudged sideways around it, rather than having its jump stopped.
+///
+/// Tries spots to each side, closest first (and away from what was hit first when the distance is the same), up to `window` pixels away.
+/// A spot works if the circle doesn't overlap anything there and can then make the upward part of the movement without hitting anything.
+/// The movement should only be what's left of it after the hit. Returns where the circle ends up after the upward part, and then as much of the sideways part as is clear.
+pub fn find_corner_correction(collision : &CollisionSystem, position : &Vec2, radius : f32, movement : &Vec2, up : &Vec2, hit_normal : &Vec2, window : f32) -> Option<Vec2> {
+	let rise = up.scale(movement.dot(up));
+	if 0.0 >= window || EPSILON >= movement.dot(up) {
+		return None;
+	}
+	let across = up.ortho();
+	let away = if 0.0 <= hit_normal.dot(&across) { 1.0 } else { -1.0 };
+	let steps = (window / CORNER_CORRECTION_STEP).ceil() as usize;
+	for step in 1..=steps {
+		let distance = (step as f32 * CORNER_CORRECTION_STEP).min(window);
+		for side in [away, -away] {
+			let nudged = position + across.scale(side * distance);
+			if !collision.find_obstacles_near(&nudged, radius - CORNER_OVERLAP_TOLERANCE).is_empty() {
+				continue;
+			}
+			if sweep(collision, &nudged, radius, &rise).is_none() {
+				let raised = nudged + rise;
+				let sideways = movement - rise;
+				return Some(raised + sideways.scale(safe_fraction(&sweep(collision, &raised, radius, &sideways))));
+			}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests_movement_assist {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	/// Makes a floor at y = 0 with a lip up to some height starting at x = 20.
+	fn floor_with_lip(lip : f32) -> CollisionSystem {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 0.0), &Vec2::new(20.0, 0.0))));
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(20.0, 0.0), &Vec2::new(20.0, lip))));
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(20.0, lip), &Vec2::new(100.0, lip))));
+		collision
+	}
+
+	#[test]
+	fn steps_up_small_lips() {
+		let up = Vec2::new(0.0, 1.0);
+		let start = Vec2::new(10.0, 8.0);
+		let movement = Vec2::new(12.0, 0.0);
+
+		// A 2 pixel lip is stepped onto.
+		let collision = floor_with_lip(2.0);
+		let landing = find_step_up(&collision, &start, 8.0, &movement, &up, 3.0).unwrap();
+		assert!((landing.x - 22.0).abs() < 0.01);
+		assert!((landing.y - 10.0).abs() < 0.01);
+		assert!(find_step_up(&collision, &start, 8.0, &movement, &up, 0.0).is_none()); // Turned off.
+		assert!(find_step_up(&collision, &start, 8.0, &Vec2::new(-12.0, 0.0), &up, 3.0).is_none()); // Walking away from it.
+
+		// Anything taller is a wall.
+		let collision = floor_with_lip(6.0);
+		assert!(find_step_up(&collision, &start, 8.0, &movement, &up, 3.0).is_none());
+
+		// As is a lip with no room above it.
+		let mut collision = floor_with_lip(2.0);
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 17.0), &Vec2::new(100.0, 17.0))));
+		assert!(find_step_up(&collision, &start, 8.0, &movement, &up, 3.0).is_none());
+
+		// Open floor doesn't need stepping.
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0))));
+		assert!(find_step_up(&collision, &start, 8.0, &movement, &up, 3.0).is_none());
+	}
+
+	#[test]
+	fn corrects_around_ceiling_corners() {
+		// A ceiling at y = 30 that ends at x = 20.
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 30.0), &Vec2::new(20.0, 30.0))));
+		let up = Vec2::new(0.0, 1.0);
+		let movement = Vec2::new(0.0, 4.0);
+
+		// Clipping the corner by a couple of pixels gets nudged out past it, then keeps going up.
+		let clipped = Vec2::new(26.5, 30.0 - (64.0f32 - 6.5 * 6.5).sqrt());
+		let normal = (clipped - Vec2::new(20.0, 30.0)).norm();
+		let corrected = find_corner_correction(&collision, &clipped, 8.0, &movement, &up, &normal, 4.0).unwrap();
+		assert!(28.0 <= corrected.x && corrected.x <= 28.5);
+		assert!((corrected.y - (clipped.y + 4.0)).abs() < 0.01);
+
+		// But not if the window's too small, when going down, or when it's well under the ceiling.
+		assert!(find_corner_correction(&collision, &clipped, 8.0, &movement, &up, &normal, 1.0).is_none());
+		assert!(find_corner_correction(&collision, &clipped, 8.0, &Vec2::new(0.0, -4.0), &up, &normal, 4.0).is_none());
+		assert!(find_corner_correction(&collision, &Vec2::new(10.0, 22.0), 8.0, &movement, &up, &up.scale(-1.0), 4.0).is_none());
+
+		// The sideways part of the movement carries on after the nudge, as far as it can.
+		let corrected = find_corner_correction(&collision, &clipped, 8.0, &Vec2::new(2.0, 4.0), &up, &normal, 4.0).unwrap();
+		assert!(30.0 <= corrected.x && corrected.x <= 30.5);
+		assert!((corrected.y - (clipped.y + 4.0)).abs() < 0.01);
+		let corrected = find_corner_correction(&collision, &clipped, 8.0, &Vec2::new(-2.0, 4.0), &up, &normal, 4.0).unwrap();
+		assert!(27.5 <= corrected.x && corrected.x < 28.0); // Stopped by the corner it went around.
+		assert!((corrected.y - (clipped.y + 4.0)).abs() < 0.01);
+
+		// Something in the way to the side blocks the nudge.
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(35.0, 0.0), &Vec2::new(35.0, 40.0))));
+		assert!(find_corner_correction(&collision, &clipped, 8.0, &movement, &up, &normal, 4.0).is_none());
+	}
+}
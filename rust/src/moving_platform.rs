@@ -0,0 +1,171 @@
+use generational_arena::Index;
+
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::animation::*;
+use crate::tiled::TiledPolyline;
+use crate::interpolation::InterpolatedPosition;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::mat4::*;
+use crate::geo::line_segment::LineSegment;
+use crate::geo::collision_system::{CollisionSystem, CircleObstacle};
+use crate::origin_rebase::ShiftOrigin;
+
+/// How wide platforms are (in pixels), unless the polyline has a "width" property.
+const DEFAULT_PLATFORM_WIDTH : f32 = 32.0;
+/// How tall platforms are (in pixels), unless the polyline has a "height" property.
+const DEFAULT_PLATFORM_HEIGHT : f32 = 8.0;
+/// The depth to draw platforms at (just in front of the tiles).
+const PLATFORM_DEPTH : f32 = 0.6;
+
+/// Where a platform was along its track (see `MovingPlatform::snapshot()`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlatformSnapshot {
+	/// How far into its track it was (in seconds).
+	pub time : f32,
+	/// Where its center was.
+	pub position : Vec2,
+	/// Where it was for the last two updates.
+	pub display_position : InterpolatedPosition,
+}
+
+/// A solid rectangle that moves along an animation track.
+///
+/// These come from Tiled polylines with the "platform" type. The platform's center follows the line (see `Track::from_polyline()` for the properties that control how).
+pub struct MovingPlatform {
+	/// Where the platform's center is over time.
+	motion : TrackPlayer<Vec2>,
+	/// Where the platform's center is now.
+	position : Vec2,
+	/// Where the platform's center was when its edges were added (they're moved by offsetting from there).
+	start : Vec2,
+	/// The edges' obstacles in the collision system.
+	obstacles : Vec<Index>,
+	/// Where the platform was for the last two updates (so it can be drawn smoothly between them).
+	display_position : InterpolatedPosition,
+	/// What the platform is drawn in.
+	display : DisplayBuffer,
+}
+
+impl MovingPlatform {
+	/// Creates a platform following a track, adding its edges to the collision system.
+	pub fn new(track : Track<Vec2>, size : &Vec2, collision : &mut CollisionSystem) -> MovingPlatform {
+		let motion = TrackPlayer::new(track);
+		let position = motion.value().unwrap_or_else(Vec2::zero);
+		let mut obstacles = Vec::with_capacity(4);
+		for edge in MovingPlatform::make_edges(&position, size) {
+			obstacles.push(collision.add_obstacle(edge));
+		}
+		let mut display = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		{
+			let half_width = size.x / 2.0;
+			let half_height = size.y / 2.0;
+			let mut editor = display.make_editor();
+			editor.add_polygon(&vec![
+				Vec3::new(-half_width, -half_height, PLATFORM_DEPTH),
+				Vec3::new( half_width, -half_height, PLATFORM_DEPTH),
+				Vec3::new( half_width,  half_height, PLATFORM_DEPTH),
+				Vec3::new(-half_width,  half_height, PLATFORM_DEPTH),
+			], &Color::new(120, 110, 100, 255));
+			editor.add_polygon(&vec![
+				Vec3::new(-half_width, half_height - 2.0, PLATFORM_DEPTH - 0.001),
+				Vec3::new( half_width, half_height - 2.0, PLATFORM_DEPTH - 0.001),
+				Vec3::new( half_width, half_height, PLATFORM_DEPTH - 0.001),
+				Vec3::new(-half_width, half_height, PLATFORM_DEPTH - 0.001),
+			], &Color::new(170, 160, 150, 255));
+		}
+		let mut platform = MovingPlatform {
+			motion,
+			position,
+			start : position,
+			obstacles,
+			display_position : InterpolatedPosition::new(&position),
+			display,
+		};
+		platform.sync_display(1.0);
+		platform
+	}
+
+	/// Creates a platform from a Tiled polyline. Besides the track properties, "width" and "height" set the platform's size.
+	pub fn from_polyline(polyline : &TiledPolyline, collision : &mut CollisionSystem) -> Result<MovingPlatform, String> {
+		let track = Track::from_polyline(polyline, "pingPong")?;
+		let mut size = Vec2::new(DEFAULT_PLATFORM_WIDTH, DEFAULT_PLATFORM_HEIGHT);
+		if let Some(text) = polyline.get_property("width") {
+			size.x = text.trim().parse::<f32>().map_err(|_| format!("Couldn't parse width {:?}", text))?;
+		}
+		if let Some(text) = polyline.get_property("height") {
+			size.y = text.trim().parse::<f32>().map_err(|_| format!("Couldn't parse height {:?}", text))?;
+		}
+		Ok(MovingPlatform::new(track, &size, collision))
+	}
+
+	/// Makes the edges of a rectangle centered on a position.
+	fn make_edges(center : &Vec2, size : &Vec2) -> Vec<CircleObstacle> {
+		let corners = [
+			Vec2::new(center.x - size.x / 2.0, center.y - size.y / 2.0),
+			Vec2::new(center.x + size.x / 2.0, center.y - size.y / 2.0),
+			Vec2::new(center.x + size.x / 2.0, center.y + size.y / 2.0),
+			Vec2::new(center.x - size.x / 2.0, center.y + size.y / 2.0),
+		];
+		let mut edges = Vec::with_capacity(4);
+		for index in 0..corners.len() {
+			edges.push(CircleObstacle::LineSegment(LineSegment::new(&corners[index], &corners[(index + 1) % corners.len()])));
+		}
+		edges
+	}
+
+	/// Whether an obstacle is one of this platform's edges.
+	pub fn owns(&self, obstacle : Index) -> bool {
+		self.obstacles.contains(&obstacle)
+	}
+
+	/// Moves the platform along its track (updating its collision geometry, and how fast it's moving). Returns how far it moved.
+	pub fn update(&mut self, elapsed_seconds : f32, collision : &mut CollisionSystem) -> Vec2 {
+		let previous = self.position;
+		if let Some(position) = self.motion.update(elapsed_seconds) {
+			self.position = position;
+		}
+		let offset = self.position - self.start;
+		for index in &self.obstacles {
+			collision.set_obstacle_offset(*index, &offset, elapsed_seconds);
+		}
+		self.display_position.push(&self.position);
+		self.position - previous
+	}
+
+	/// Captures where the platform is along its track.
+	pub fn snapshot(&self) -> PlatformSnapshot {
+		PlatformSnapshot {
+			time : self.motion.get_time(),
+			position : self.position,
+			display_position : self.display_position.clone(),
+		}
+	}
+
+	/// Puts the platform back where a snapshot had it. Its edges are part of the collision system's snapshot, so aren't touched here.
+	pub fn restore(&mut self, snapshot : &PlatformSnapshot) {
+		self.motion.set_time(snapshot.time);
+		self.position = snapshot.position;
+		self.display_position = snapshot.display_position.clone();
+	}
+
+	/// Moves the display to where the platform should be drawn, given how far between the last two updates to draw things (from 0 to 1).
+	pub fn sync_display(&mut self, interpolation : f32) {
+		let position = self.display_position.get(interpolation);
+		let mut transform = Mat4::new();
+		transform.translate_before(&Vec3::new(position.x, position.y, 0.0));
+		self.display.set_transform(&transform);
+	}
+}
+
+impl ShiftOrigin for MovingPlatform {
+	/// Only moves the platform's own positions. Its edges are in the collision system, so move along with it.
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.motion.shift_origin(shift);
+		self.position.shift_origin(shift);
+		self.start.shift_origin(shift);
+		self.display_position.shift_origin(shift);
+	}
+}
@@ -0,0 +1,206 @@
+use crate::event_bus::GameEvent;
+use crate::tiled::TiledFile;
+use crate::asset_manifest::asset_url;
+use crate::externals::{playMusic, stopMusic, setMusicStemVolume, setMusicVolume};
+
+/// The map property naming the music to play in it.
+pub const MUSIC_PROPERTY : &str = "music";
+/// The map property naming the intensity stem that plays on top of the music during combat (it's started in sync with the music, but silent until needed).
+pub const MUSIC_STEM_PROPERTY : &str = "musicStem";
+/// How long it takes to cross-fade from one track to the next (in seconds).
+const CROSS_FADE_SECONDS : f32 = 2.0;
+/// How long the intensity stem takes to fade in (in seconds).
+const STEM_FADE_IN_SECONDS : f32 = 0.5;
+/// How long the intensity stem takes to fade out (in seconds).
+const STEM_FADE_OUT_SECONDS : f32 = 3.0;
+/// How long the intensity stem keeps playing after the last bit of combat (in seconds).
+const COMBAT_HOLD_SECONDS : f32 = 8.0;
+/// How loud the music is while the game's paused (from 0 to 1).
+const DUCKED_VOLUME : f32 = 0.4;
+/// How long it takes to duck the music or bring it back (in seconds).
+const DUCK_FADE_SECONDS : f32 = 0.3;
+
+/// A piece of music: the main track, and optionally a stem layered on top of it for intense moments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+	/// The main track's asset name.
+	pub base : String,
+	/// The intensity stem's asset name (if it has one).
+	pub stem : Option<String>,
+}
+
+impl MusicTrack {
+	/// Gets the music a map asks for (if any).
+	pub fn from_file(file : &TiledFile) -> Option<MusicTrack> {
+		let base = file.get_property(MUSIC_PROPERTY).filter(|name| !name.is_empty())?;
+		Some(MusicTrack {
+			base : base.to_string(),
+			stem : file.get_property(MUSIC_STEM_PROPERTY).filter(|name| !name.is_empty()).map(|name| name.to_string()),
+		})
+	}
+}
+
+/// Something for the audio side to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MusicCommand {
+	/// Cross-fade to a track over some seconds (with its stem starting silent).
+	Play(MusicTrack, f32),
+	/// Fade out whatever's playing over some seconds.
+	Stop(f32),
+	/// Fade the intensity stem to some volume (from 0 to 1) over some seconds.
+	SetStemVolume(f32, f32),
+	/// Fade all music to some volume (from 0 to 1) over some seconds.
+	SetVolume(f32, f32),
+}
+
+/// Decides what music should be playing: the room's track (cross-fading when it changes), its intensity stem while there's combat, and quieter while paused.
+///
+/// This only works out what should change. The changes are taken with `take_commands()` and sent on with `send_music_command()`.
+pub struct Music {
+	/// What's playing (if anything).
+	track : Option<MusicTrack>,
+	/// How much longer the intensity stem should keep playing (in seconds).
+	combat_remaining : f32,
+	/// Whether the intensity stem is playing.
+	stem_on : bool,
+	/// Whether the music is turned down.
+	ducked : bool,
+	/// The changes that haven't been taken yet.
+	commands : Vec<MusicCommand>,
+}
+
+impl Music {
+	/// Creates an instance with nothing playing.
+	pub fn new() -> Music {
+		Music {
+			track : None,
+			combat_remaining : 0.0,
+			stem_on : false,
+			ducked : false,
+			commands : Vec::new(),
+		}
+	}
+
+	/// Gets what's playing (if anything).
+	pub fn get_track(&self) -> Option<&MusicTrack> {
+		self.track.as_ref()
+	}
+
+	/// Switches to whatever music a map asks for (see `play()`).
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.play(MusicTrack::from_file(file));
+	}
+
+	/// Cross-fades to a track (or fades out to nothing). Does nothing if it's already playing, so rooms that share music play straight through.
+	pub fn play(&mut self, track : Option<MusicTrack>) {
+		if track == self.track {
+			return;
+		}
+		self.commands.push(match &track {
+			Some(track) => MusicCommand::Play(track.clone(), CROSS_FADE_SECONDS),
+			None => MusicCommand::Stop(CROSS_FADE_SECONDS),
+		});
+		self.track = track;
+		self.stem_on = false; // New tracks start with their stem silent (it comes in on the next update if there's still combat).
+	}
+
+	/// Notes combat happening (which brings in the intensity stem).
+	pub fn handle_event(&mut self, event : &GameEvent) {
+		match event {
+			GameEvent::Combat | GameEvent::EnemyDefeated(_) => { self.combat_remaining = COMBAT_HOLD_SECONDS; },
+			_ => {},
+		}
+	}
+
+	/// Turns the music down (or back up).
+	pub fn set_ducked(&mut self, ducked : bool) {
+		if ducked == self.ducked {
+			return;
+		}
+		self.ducked = ducked;
+		self.commands.push(MusicCommand::SetVolume(if ducked { DUCKED_VOLUME } else { 1.0 }, DUCK_FADE_SECONDS));
+	}
+
+	/// Moves forward in time, fading the intensity stem in or out as needed.
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		self.combat_remaining = (self.combat_remaining - elapsed_seconds).max(0.0);
+		let has_stem = self.track.as_ref().map_or(false, |track| track.stem.is_some());
+		let want_stem = has_stem && 0.0 < self.combat_remaining;
+		if want_stem != self.stem_on {
+			self.stem_on = want_stem;
+			self.commands.push(if want_stem {
+				MusicCommand::SetStemVolume(1.0, STEM_FADE_IN_SECONDS)
+			} else {
+				MusicCommand::SetStemVolume(0.0, STEM_FADE_OUT_SECONDS)
+			});
+		}
+	}
+
+	/// Takes the changes that haven't been sent on yet (in order).
+	pub fn take_commands(&mut self) -> Vec<MusicCommand> {
+		std::mem::take(&mut self.commands)
+	}
+}
+
+/// Sends a change on to the audio side.
+pub fn send_music_command(command : &MusicCommand) {
+	match command {
+		MusicCommand::Play(track, fade_seconds) => {
+			let stem = track.stem.as_deref().unwrap_or("");
+			let stem_url = if stem.is_empty() { String::new() } else { asset_url(stem) };
+			playMusic(&track.base, &asset_url(&track.base), stem, &stem_url, *fade_seconds);
+		},
+		MusicCommand::Stop(fade_seconds) => stopMusic(*fade_seconds),
+		MusicCommand::SetStemVolume(volume, fade_seconds) => setMusicStemVolume(*volume, *fade_seconds),
+		MusicCommand::SetVolume(volume, fade_seconds) => setMusicVolume(*volume, *fade_seconds),
+	}
+}
+
+#[cfg(test)]
+mod tests_music {
+	use super::*;
+
+	fn track(base : &str, stem : Option<&str>) -> Option<MusicTrack> {
+		Some(MusicTrack { base : base.to_string(), stem : stem.map(|stem| stem.to_string()) })
+	}
+
+	#[test]
+	fn tracks_and_stems() {
+		let mut music = Music::new();
+		music.play(track("cave.ogg", Some("caveDrums.ogg")));
+		assert_eq!(music.take_commands(), vec![MusicCommand::Play(track("cave.ogg", Some("caveDrums.ogg")).unwrap(), CROSS_FADE_SECONDS)]);
+
+		// The same track keeps playing through room changes.
+		music.play(track("cave.ogg", Some("caveDrums.ogg")));
+		assert!(music.take_commands().is_empty());
+
+		// Combat brings the stem in, and it fades back out a while after the last of it.
+		music.handle_event(&GameEvent::Combat);
+		music.update(0.1);
+		assert_eq!(music.take_commands(), vec![MusicCommand::SetStemVolume(1.0, STEM_FADE_IN_SECONDS)]);
+		music.update(COMBAT_HOLD_SECONDS / 2.0);
+		music.handle_event(&GameEvent::EnemyDefeated(String::from("drone")));
+		music.update(COMBAT_HOLD_SECONDS / 2.0);
+		assert!(music.take_commands().is_empty());
+		music.update(COMBAT_HOLD_SECONDS / 2.0);
+		assert_eq!(music.take_commands(), vec![MusicCommand::SetStemVolume(0.0, STEM_FADE_OUT_SECONDS)]);
+
+		// Tracks without a stem ignore combat.
+		music.play(track("field.ogg", None));
+		music.handle_event(&GameEvent::Combat);
+		music.update(0.1);
+		assert_eq!(music.take_commands(), vec![MusicCommand::Play(track("field.ogg", None).unwrap(), CROSS_FADE_SECONDS)]);
+
+		// Pausing ducks it, and rooms without music fade it out.
+		music.set_ducked(true);
+		music.set_ducked(true);
+		music.set_ducked(false);
+		music.play(None);
+		assert_eq!(music.take_commands(), vec![
+			MusicCommand::SetVolume(DUCKED_VOLUME, DUCK_FADE_SECONDS),
+			MusicCommand::SetVolume(1.0, DUCK_FADE_SECONDS),
+			MusicCommand::Stop(CROSS_FADE_SECONDS),
+		]);
+		assert_eq!(music.get_track(), None);
+	}
+}
@@ -0,0 +1,248 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The most cells a single path search will look at before giving up.
+const MAX_SEARCHED_CELLS : usize = 4096;
+
+/// A cell waiting to be searched, ordered so the BinaryHeap pops the lowest estimated cost first.
+struct OpenCell {
+	/// The cell's index.
+	index : usize,
+	/// The cost so far plus the estimated remaining cost.
+	estimate : f32,
+}
+
+impl PartialEq for OpenCell {
+	fn eq(&self, other : &OpenCell) -> bool {
+		self.estimate == other.estimate
+	}
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+	fn partial_cmp(&self, other : &OpenCell) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for OpenCell {
+	fn cmp(&self, other : &OpenCell) -> Ordering {
+		other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+	}
+}
+
+/// A grid over the level marking which cells are open space, for flying things to find paths through.
+pub struct NavGrid {
+	/// The lower-left corner of the grid.
+	origin : Vec2,
+	/// The width and height of each cell.
+	cell_size : f32,
+	/// How many cells across the grid is.
+	width : usize,
+	/// How many cells tall the grid is.
+	height : usize,
+	/// Whether each cell is blocked (row by row, starting from the bottom).
+	blocked : Vec<bool>,
+}
+
+impl NavGrid {
+	/// Creates a grid covering some area where everything is open.
+	pub fn new(bounds : &Bounds2, cell_size : f32) -> NavGrid {
+		let width = ((bounds.x_max() - bounds.x_min()) / cell_size).ceil().max(1.0) as usize;
+		let height = ((bounds.y_max() - bounds.y_min()) / cell_size).ceil().max(1.0) as usize;
+		NavGrid {
+			origin : Vec2::new(bounds.x_min(), bounds.y_min()),
+			cell_size,
+			width,
+			height,
+			blocked : vec![false; width * height],
+		}
+	}
+
+	/// Creates an empty grid that covers nothing.
+	pub fn empty() -> NavGrid {
+		NavGrid {
+			origin : Vec2::zero(),
+			cell_size : 1.0,
+			width : 0,
+			height : 0,
+			blocked : Vec::new(),
+		}
+	}
+
+	/// Marks every cell that overlaps a rectangle as blocked.
+	pub fn block_rect(&mut self, rect : &Bounds2) {
+		if 0 == self.width || 0 == self.height {
+			return;
+		}
+		let x_start = ((rect.x_min() - self.origin.x) / self.cell_size).floor().max(0.0) as usize;
+		let y_start = ((rect.y_min() - self.origin.y) / self.cell_size).floor().max(0.0) as usize;
+		let x_end = ((rect.x_max() - self.origin.x) / self.cell_size).ceil().max(0.0) as usize;
+		let y_end = ((rect.y_max() - self.origin.y) / self.cell_size).ceil().max(0.0) as usize;
+		for y in y_start..y_end.min(self.height) {
+			for x in x_start..x_end.min(self.width) {
+				self.blocked[x + y * self.width] = true;
+			}
+		}
+	}
+
+	/// Marks every cell that overlaps a polygon's bounding box as blocked.
+	pub fn block_polygon(&mut self, polygon : &Vec<Vec2>) {
+		if polygon.is_empty() {
+			return;
+		}
+		let mut bounds = Bounds2::from_points(&polygon[0], &polygon[0]);
+		for point in polygon {
+			bounds.expand_to_x(point.x);
+			bounds.expand_to_y(point.y);
+		}
+		self.block_rect(&bounds);
+	}
+
+	/// Gets the cell that a position is in (if it's in the grid at all).
+	pub fn find_cell(&self, position : &Vec2) -> Option<(usize, usize)> {
+		let x = ((position.x - self.origin.x) / self.cell_size).floor();
+		let y = ((position.y - self.origin.y) / self.cell_size).floor();
+		if 0.0 > x || 0.0 > y || self.width as f32 <= x || self.height as f32 <= y {
+			return None;
+		}
+		Some((x as usize, y as usize))
+	}
+
+	/// Gets the center of a cell.
+	pub fn get_cell_center(&self, x : usize, y : usize) -> Vec2 {
+		Vec2::new(
+			self.origin.x + (x as f32 + 0.5) * self.cell_size,
+			self.origin.y + (y as f32 + 0.5) * self.cell_size,
+		)
+	}
+
+	/// Whether a cell can't be moved through. Everything outside the grid counts as blocked.
+	pub fn is_blocked(&self, x : usize, y : usize) -> bool {
+		self.width <= x || self.height <= y || self.blocked[x + y * self.width]
+	}
+
+	/// Finds a path between two positions (using A*), moving between neighboring cells (including diagonally, but never cutting corners).
+	///
+	/// The path is a list of cell centers, ending with the exact end position. Returns None if either end is blocked or there's no way through.
+	pub fn find_path(&self, start : &Vec2, end : &Vec2) -> Option<Vec<Vec2>> {
+		let (start_x, start_y) = self.find_cell(start)?;
+		let (end_x, end_y) = self.find_cell(end)?;
+		if self.is_blocked(start_x, start_y) || self.is_blocked(end_x, end_y) {
+			return None;
+		}
+		let start_index = start_x + start_y * self.width;
+		let end_index = end_x + end_y * self.width;
+		let estimate = |index : usize| -> f32 {
+			let dx = (index % self.width) as f32 - end_x as f32;
+			let dy = (index / self.width) as f32 - end_y as f32;
+			(dx * dx + dy * dy).sqrt()
+		};
+
+		let mut costs = vec![std::f32::INFINITY; self.blocked.len()];
+		let mut came_from = vec![usize::MAX; self.blocked.len()];
+		let mut open = BinaryHeap::new();
+		costs[start_index] = 0.0;
+		open.push(OpenCell { index : start_index, estimate : estimate(start_index) });
+		let mut searched = 0;
+		while let Some(current) = open.pop() {
+			if end_index == current.index {
+				break;
+			}
+			searched += 1;
+			if MAX_SEARCHED_CELLS < searched {
+				return None;
+			}
+			let x = (current.index % self.width) as i64;
+			let y = (current.index / self.width) as i64;
+			for dy in -1..=1i64 {
+				for dx in -1..=1i64 {
+					if 0 == dx && 0 == dy {
+						continue;
+					}
+					let next_x = x + dx;
+					let next_y = y + dy;
+					if 0 > next_x || 0 > next_y || self.is_blocked(next_x as usize, next_y as usize) {
+						continue;
+					}
+					// Don't cut corners.
+					if 0 != dx && 0 != dy && (self.is_blocked((x + dx) as usize, y as usize) || self.is_blocked(x as usize, (y + dy) as usize)) {
+						continue;
+					}
+					let next_index = next_x as usize + next_y as usize * self.width;
+					let step = if 0 != dx && 0 != dy { std::f32::consts::SQRT_2 } else { 1.0 };
+					let cost = costs[current.index] + step;
+					if cost < costs[next_index] {
+						costs[next_index] = cost;
+						came_from[next_index] = current.index;
+						open.push(OpenCell { index : next_index, estimate : cost + estimate(next_index) });
+					}
+				}
+			}
+		}
+		if start_index != end_index && usize::MAX == came_from[end_index] {
+			return None;
+		}
+
+		let mut path = vec![end.clone()];
+		let mut index = came_from[end_index];
+		while usize::MAX != index && start_index != index {
+			path.push(self.get_cell_center(index % self.width, index / self.width));
+			index = came_from[index];
+		}
+		path.reverse();
+		Some(path)
+	}
+}
+
+impl ShiftOrigin for NavGrid {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.origin.shift_origin(shift);
+	}
+}
+
+#[cfg(test)]
+mod tests_nav_grid {
+	use super::*;
+
+	#[test]
+	fn cells() {
+		let mut grid = NavGrid::new(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 50.0)), 10.0);
+		assert_eq!(grid.find_cell(&Vec2::new(15.0, 5.0)), Some((1, 0)));
+		assert_eq!(grid.find_cell(&Vec2::new(-1.0, 5.0)), None);
+		assert_eq!(grid.find_cell(&Vec2::new(15.0, 50.0)), None);
+		grid.block_rect(&Bounds2::from_points(&Vec2::new(20.0, 0.0), &Vec2::new(30.0, 20.0)));
+		assert!(!grid.is_blocked(1, 0));
+		assert!(grid.is_blocked(2, 0));
+		assert!(grid.is_blocked(2, 1));
+		assert!(!grid.is_blocked(2, 2));
+		assert!(!grid.is_blocked(3, 0));
+		assert!(grid.is_blocked(100, 0));
+	}
+
+	#[test]
+	fn path_around_wall() {
+		let mut grid = NavGrid::new(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(50.0, 50.0)), 10.0);
+		// A wall up the middle with a gap at the top.
+		grid.block_rect(&Bounds2::from_points(&Vec2::new(20.0, 0.0), &Vec2::new(30.0, 40.0)));
+		let end = Vec2::new(45.0, 5.0);
+		let path = grid.find_path(&Vec2::new(5.0, 5.0), &end).unwrap();
+		for point in &path {
+			let (x, y) = grid.find_cell(point).unwrap();
+			assert!(!grid.is_blocked(x, y));
+		}
+		assert!(path.iter().any(|point| 40.0 < point.y));
+		let last = path.last().unwrap();
+		assert_eq!(last.x, end.x);
+		assert_eq!(last.y, end.y);
+
+		// Closing the gap means there's no way through.
+		grid.block_rect(&Bounds2::from_points(&Vec2::new(20.0, 40.0), &Vec2::new(30.0, 50.0)));
+		assert!(grid.find_path(&Vec2::new(5.0, 5.0), &end).is_none());
+	}
+}
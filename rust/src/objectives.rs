@@ -0,0 +1,346 @@
+use crate::color::*;
+use crate::palette::*;
+use crate::display_buffer::*;
+use crate::display_text::*;
+use crate::event_bus::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::bounds2::Bounds2;
+use crate::tiled::TiledFile;
+use crate::origin_rebase::ShiftOrigin;
+use crate::render_view::*;
+use crate::externals::log;
+
+/// The Tiled point type that marks an objective.
+const OBJECTIVE_POINT_TYPE : &str = "objective";
+/// How close the player needs to get to an objective to complete it.
+const OBJECTIVE_REACH_DISTANCE : f32 = 16.0;
+/// How far in from the edge of the screen off-screen indicators are drawn (in pixels).
+const INDICATOR_MARGIN : f32 = 16.0;
+/// How big the off-screen indicator arrows are (in pixels).
+const INDICATOR_SIZE : f32 = 8.0;
+/// How big the on-screen marker diamonds are (in pixels).
+const MARKER_SIZE : f32 = 6.0;
+/// The depth to draw markers and indicators at.
+const MARKER_DEPTH : f32 = -0.9;
+
+/// A single place the player needs to go.
+#[derive(Debug, Clone)]
+pub struct Objective {
+	/// The objective's name (from the Tiled point's name). Also what's shown in the quest list.
+	pub name : String,
+	/// Where the objective is.
+	pub position : Vec2,
+	/// Whether it's been done.
+	pub complete : bool,
+}
+
+/// An ordered list of objectives. Only the first incomplete one is "current".
+pub struct QuestLog {
+	/// All objectives in the order they should be done.
+	objectives : Vec<Objective>,
+}
+
+impl QuestLog {
+	/// Creates an empty quest log.
+	pub fn new() -> QuestLog {
+		QuestLog {
+			objectives : Vec::new(),
+		}
+	}
+
+	/// Replaces all objectives with the ones in a Tiled file (in the order they appear).
+	/// Objectives are completed (and remembered in the world flags) by name, so unnamed ones are given a name from their place in the list, and repeated names are warned about.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.objectives.clear();
+		for point in file.get_points() {
+			if OBJECTIVE_POINT_TYPE != point.r#type {
+				continue;
+			}
+			let name = if point.name.is_empty() {
+				let name = format!("Objective {}", self.objectives.len() + 1);
+				log(&format!("Objective at {:?} has no name, so calling it {:?}", point.position, name));
+				name
+			} else {
+				point.name.clone()
+			};
+			if self.objectives.iter().any(|objective| objective.name == name) {
+				log(&format!("There's more than one objective named {:?}", name));
+			}
+			self.add(&name, &point.position);
+		}
+	}
+
+	/// Adds an objective to the end of the list.
+	pub fn add(&mut self, name : &str, position : &Vec2) {
+		self.objectives.push(Objective {
+			name : name.to_string(),
+			position : position.clone(),
+			complete : false,
+		});
+	}
+
+	/// Gets all objectives.
+	pub fn get_objectives<'a>(&'a self) -> &'a Vec<Objective> {
+		&self.objectives
+	}
+
+	/// Gets the objective that should be done next (if any are left).
+	pub fn get_current<'a>(&'a self) -> Option<&'a Objective> {
+		self.objectives.iter().find(|objective| !objective.complete)
+	}
+
+	/// Posts a completion event if the position is close enough to the current objective.
+	pub fn check_reached(&self, position : &Vec2, events : &mut EventBus) {
+		if let Some(current) = self.get_current() {
+			if (current.position - position).length() <= OBJECTIVE_REACH_DISTANCE {
+				events.post(GameEvent::ObjectiveCompleted(current.name.clone()));
+			}
+		}
+	}
+
+	/// Handles an event. Returns whether anything changed.
+	/// Only the first incomplete objective with a completed name is marked done, so objectives that share a name still have to be done one at a time.
+	pub fn handle_event(&mut self, event : &GameEvent) -> bool {
+		match event {
+			GameEvent::ObjectiveCompleted(name) => {
+				match self.objectives.iter_mut().find(|objective| !objective.complete && *name == objective.name) {
+					Some(objective) => {
+						objective.complete = true;
+						true
+					},
+					None => false,
+				}
+			},
+			_ => false,
+		}
+	}
+
+	/// Makes the (HTML) text for the quest list panel.
+	pub fn make_panel_text(&self) -> String {
+		let mut text = String::from("Objectives:");
+		for objective in &self.objectives {
+			text.push_str("<br>");
+			text.push_str(if objective.complete { "[x] " } else { "[ ] " });
+			text.push_str(&objective.name);
+		}
+		text
+	}
+}
+
+impl ShiftOrigin for QuestLog {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for objective in &mut self.objectives {
+			objective.position.shift_origin(shift);
+		}
+	}
+}
+
+/// Works out where to put an indicator for a target that's off-screen.
+///
+/// The indicator is placed on the view's edge (pulled in by the margin) along the line from the view's center to the target.
+/// Returns the indicator's position and the (unit length) direction it should point, or None if the target is on-screen.
+pub fn find_edge_indicator(view : &Bounds2, target : &Vec2, margin : f32) -> Option<(Vec2, Vec2)> {
+	if view.overlaps_point(target) {
+		return None;
+	}
+	let center = Vec2::new(
+		(view.x_min() + view.x_max()) / 2.0,
+		(view.y_min() + view.y_max()) / 2.0,
+	);
+	let half_width = ((view.x_max() - view.x_min()) / 2.0 - margin).max(0.0);
+	let half_height = ((view.y_max() - view.y_min()) / 2.0 - margin).max(0.0);
+	let offset = target - center;
+	let direction = offset.norm();
+	let mut scale = offset.length();
+	if 0.0 != direction.x {
+		scale = scale.min(half_width / direction.x.abs());
+	}
+	if 0.0 != direction.y {
+		scale = scale.min(half_height / direction.y.abs());
+	}
+	Some((center + direction * scale, direction))
+}
+
+/// Shows the objectives: markers in the world, indicators for off-screen ones, and a HUD panel with the quest list.
+pub struct ObjectiveDisplay {
+	/// The markers and indicators.
+	markers : DisplayBuffer,
+	/// The quest list panel.
+	panel : DisplayText,
+	/// Whether anything should be shown.
+	visible : bool,
+	/// The color everything is drawn in.
+	color : Color,
+}
+
+impl ObjectiveDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> ObjectiveDisplay {
+		let mut markers = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		markers.set_views(view_bit(MAIN_VIEW)); // They're placed around the edges of the camera's view.
+		ObjectiveDisplay {
+			markers,
+			panel : DisplayText::new_text_area(
+				0.05,
+				0.05,
+				0.40,
+				0.30,
+				&Palette::new(PaletteKind::Standard).get(PaletteRole::Objective),
+				TextAlignment::LEFT,
+				"",
+			),
+			visible : true,
+			color : Palette::new(PaletteKind::Standard).get(PaletteRole::Objective),
+		}
+	}
+
+	/// Sets the color everything is drawn in. The markers pick it up on their next update.
+	pub fn set_color(&mut self, color : &Color) {
+		self.color = color.clone();
+		self.panel.set_color(color);
+	}
+
+	/// Shows or hides everything.
+	pub fn set_visible(&mut self, visible : bool, quests : &QuestLog) {
+		self.visible = visible;
+		if visible {
+			self.markers.show();
+		} else {
+			self.markers.hide();
+		}
+		self.update_panel(quests);
+	}
+
+	/// Updates the quest list panel's text.
+	pub fn update_panel(&mut self, quests : &QuestLog) {
+		if !self.visible || quests.get_objectives().is_empty() {
+			self.panel.hide();
+		} else {
+			self.panel.set_text(&quests.make_panel_text());
+			self.panel.show();
+		}
+	}
+
+	/// Redraws the marker for the current objective (either in the world or as an indicator on the edge of the view).
+	pub fn update_markers(&mut self, quests : &QuestLog, view : &Bounds2) {
+		let color = self.color.clone();
+		let mut editor = self.markers.make_editor();
+		editor.clear();
+		let current = match quests.get_current() {
+			Some(objective) => objective,
+			None => return,
+		};
+		if let Some((position, direction)) = find_edge_indicator(view, &current.position, INDICATOR_MARGIN) {
+			let tip = position + direction * INDICATOR_SIZE;
+			let side = Vec2::new(-direction.y, direction.x) * (INDICATOR_SIZE / 2.0);
+			let left = position + side;
+			let right = position - side;
+			editor.add_triangle([
+				Vec3::new(tip.x, tip.y, MARKER_DEPTH),
+				Vec3::new(left.x, left.y, MARKER_DEPTH),
+				Vec3::new(right.x, right.y, MARKER_DEPTH),
+			], &color);
+		} else {
+			let center = &current.position;
+			editor.add_polygon(&vec![
+				Vec3::new(center.x, center.y + MARKER_SIZE, MARKER_DEPTH),
+				Vec3::new(center.x - MARKER_SIZE, center.y, MARKER_DEPTH),
+				Vec3::new(center.x, center.y - MARKER_SIZE, MARKER_DEPTH),
+				Vec3::new(center.x + MARKER_SIZE, center.y, MARKER_DEPTH),
+			], &color);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_quest_log {
+	use super::*;
+	use crate::tiled::TiledPoint;
+
+	#[test]
+	fn completes_in_order() {
+		let mut quests = QuestLog::new();
+		let mut events = EventBus::new();
+		quests.add("first", &Vec2::new(0.0, 0.0));
+		quests.add("second", &Vec2::new(100.0, 0.0));
+		assert_eq!(quests.get_current().unwrap().name, "first");
+
+		// Can't skip ahead.
+		quests.check_reached(&Vec2::new(100.0, 0.0), &mut events);
+		assert!(events.drain().is_empty());
+
+		quests.check_reached(&Vec2::new(1.0, 0.0), &mut events);
+		let posted = events.drain();
+		assert_eq!(posted, vec![GameEvent::ObjectiveCompleted("first".to_string())]);
+		assert!(quests.handle_event(&posted[0]));
+		assert!(!quests.handle_event(&posted[0]));
+		assert_eq!(quests.get_current().unwrap().name, "second");
+		assert_eq!(quests.make_panel_text(), "Objectives:<br>[x] first<br>[ ] second");
+
+		assert!(quests.handle_event(&GameEvent::ObjectiveCompleted("second".to_string())));
+		assert!(quests.get_current().is_none());
+	}
+
+	#[test]
+	fn unnamed_objectives() {
+		let mut file = TiledFile::new();
+		for x in [0.0, 100.0].iter() {
+			file.points.push(TiledPoint {
+				position : Vec2::new(*x, 0.0),
+				name : String::new(),
+				r#type : OBJECTIVE_POINT_TYPE.to_string(),
+				properties : Vec::new(),
+			});
+		}
+		let mut quests = QuestLog::new();
+		quests.load_from(&file);
+		let names : Vec<&str> = quests.get_objectives().iter().map(|objective| objective.name.as_str()).collect();
+		assert_eq!(names, vec!["Objective 1", "Objective 2"]);
+
+		// Reaching the first doesn't complete the second.
+		let mut events = EventBus::new();
+		quests.check_reached(&Vec2::new(0.0, 0.0), &mut events);
+		for event in events.drain() {
+			quests.handle_event(&event);
+		}
+		assert_eq!(quests.get_current().unwrap().name, "Objective 2");
+
+		// Shared names are completed one at a time.
+		let mut quests = QuestLog::new();
+		quests.add("", &Vec2::new(0.0, 0.0));
+		quests.add("", &Vec2::new(100.0, 0.0));
+		assert!(quests.handle_event(&GameEvent::ObjectiveCompleted(String::new())));
+		assert_eq!(quests.get_current().unwrap().position.x, 100.0);
+		assert!(quests.handle_event(&GameEvent::ObjectiveCompleted(String::new())));
+		assert!(quests.get_current().is_none());
+	}
+}
+
+#[cfg(test)]
+mod tests_find_edge_indicator {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+	use crate::geo::consts::EPSILON;
+
+	#[test]
+	fn on_screen() {
+		let view = Bounds2::from_points(&Vec2::new(-100.0, -50.0), &Vec2::new(100.0, 50.0));
+		assert!(find_edge_indicator(&view, &Vec2::new(10.0, 10.0), 10.0).is_none());
+	}
+
+	#[test]
+	fn off_to_the_side() {
+		let view = Bounds2::from_points(&Vec2::new(-100.0, -50.0), &Vec2::new(100.0, 50.0));
+		let (position, direction) = find_edge_indicator(&view, &Vec2::new(500.0, 0.0), 10.0).unwrap();
+		assert_vec2_about_eq!(position, Vec2::new(90.0, 0.0));
+		assert_vec2_about_eq!(direction, Vec2::new(1.0, 0.0));
+	}
+
+	#[test]
+	fn off_the_corner() {
+		let view = Bounds2::from_points(&Vec2::new(-100.0, -50.0), &Vec2::new(100.0, 50.0));
+		let (position, _direction) = find_edge_indicator(&view, &Vec2::new(-400.0, -400.0), 10.0).unwrap();
+		assert_vec2_about_eq!(position, Vec2::new(-40.0, -40.0)); // Hits the bottom edge first.
+	}
+}
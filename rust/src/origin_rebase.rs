@@ -0,0 +1,202 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::geo::line::Line;
+use crate::geo::line_segment::LineSegment;
+use crate::geo::circle::Circle;
+use crate::geo::collision_system::{CollisionSystem, CircleObstacle};
+use crate::geo::world_vec2::*;
+
+/// How far the player can get from the world origin (along either axis) before everything is shifted back toward it.
+pub const REBASE_THRESHOLD : f32 = 8192.0;
+
+/// Something with positions in the world that can be moved along with it.
+///
+/// This is how origin rebasing keeps coordinates small: every system moves everything it has by the same shift, all at once.
+pub trait ShiftOrigin {
+	/// Moves all of the world positions by some amount.
+	fn shift_origin(&mut self, shift : &Vec2);
+}
+
+impl ShiftOrigin for Vec2 {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		*self += shift;
+	}
+}
+
+impl ShiftOrigin for Bounds2 {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.translate(shift);
+	}
+}
+
+impl ShiftOrigin for Line {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.origin += shift;
+		self.c = self.delta.dot(&self.origin);
+	}
+}
+
+impl ShiftOrigin for LineSegment {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.start += shift;
+		self.end += shift;
+	}
+}
+
+impl ShiftOrigin for Circle {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.center += shift;
+	}
+}
+
+impl ShiftOrigin for CircleObstacle {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		match self {
+			CircleObstacle::LineSegment(segment) => segment.shift_origin(shift),
+			CircleObstacle::Line(line)           => line.shift_origin(shift),
+			CircleObstacle::Point(position)      => position.shift_origin(shift),
+			CircleObstacle::Circle(circle)       => circle.shift_origin(shift),
+		}
+	}
+}
+
+impl ShiftOrigin for CollisionSystem {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for (_index, obstacle) in &mut self.obstacles {
+			obstacle.geometry.shift_origin(shift);
+		}
+		self.rebuild_broad_phase();
+	}
+}
+
+impl<T : ShiftOrigin> ShiftOrigin for Vec<T> {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for item in self {
+			item.shift_origin(shift);
+		}
+	}
+}
+
+impl<T : ShiftOrigin> ShiftOrigin for Option<T> {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		if let Some(item) = self {
+			item.shift_origin(shift);
+		}
+	}
+}
+
+/// Decides when to shift the world back toward the origin, and keeps track of how far it's been shifted in total.
+///
+/// Positions in the map (i.e. the Tiled file) stay where they are. The game's world positions are the map positions minus the total offset.
+pub struct OriginRebaser {
+	/// Whether shifting is allowed.
+	enabled : bool,
+	/// How far from the origin the focus can get before a shift.
+	threshold : f32,
+	/// What shifts are rounded to (so tile chunks stay lined up with the same pixels).
+	alignment : f32,
+	/// Where the world origin is in map coordinates.
+	offset : WorldVec2,
+}
+
+impl OriginRebaser {
+	/// Creates a disabled instance.
+	pub fn new() -> OriginRebaser {
+		OriginRebaser {
+			enabled : false,
+			threshold : REBASE_THRESHOLD,
+			alignment : 1.0,
+			offset : WorldVec2::zero(),
+		}
+	}
+
+	/// Turns shifting on or off. Any shift already made stays.
+	pub fn set_enabled(&mut self, enabled : bool) {
+		self.enabled = enabled;
+	}
+
+	/// Gets whether shifting is allowed.
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Sets how far from the origin the focus can get before a shift.
+	pub fn set_threshold(&mut self, threshold : f32) {
+		self.threshold = threshold.max(self.alignment);
+	}
+
+	/// Starts over with a freshly loaded map (which is always at map coordinates), with shifts rounded to some size.
+	pub fn reset(&mut self, alignment : f32) {
+		self.alignment = alignment.max(1.0);
+		self.threshold = self.threshold.max(self.alignment);
+		self.offset = WorldVec2::zero();
+	}
+
+	/// Checks whether the focus (usually the player) is far enough out for a shift. If so, gives the shift to move everything by (and records it).
+	pub fn check(&mut self, focus : &Vec2) -> Option<Vec2> {
+		if !self.enabled || (focus.x.abs() <= self.threshold && focus.y.abs() <= self.threshold) {
+			return None;
+		}
+		let shift = Vec2::new(
+			-(focus.x / self.alignment).round() * self.alignment,
+			-(focus.y / self.alignment).round() * self.alignment,
+		);
+		self.offset = self.offset.offset_by(&(shift * -1.0));
+		Some(shift)
+	}
+
+	/// Gets where the world origin is in map coordinates.
+	pub fn get_offset(&self) -> WorldVec2 {
+		self.offset
+	}
+
+	/// Changes where the world origin is recorded as being, without shifting anything (the caller does that).
+	pub fn set_offset(&mut self, offset : &WorldVec2) {
+		self.offset = *offset;
+	}
+
+	/// Converts a world position to map coordinates.
+	pub fn to_map(&self, position : &Vec2) -> WorldVec2 {
+		self.offset.offset_by(position)
+	}
+
+	/// Converts map coordinates to a world position.
+	pub fn from_map(&self, position : &WorldVec2) -> Vec2 {
+		position.relative_to(&self.offset)
+	}
+}
+
+#[cfg(test)]
+mod tests_origin_rebase {
+	use super::*;
+
+	#[test]
+	fn shifts_in_aligned_steps() {
+		let mut rebaser = OriginRebaser::new();
+		rebaser.reset(512.0);
+		rebaser.set_threshold(1000.0);
+		assert!(rebaser.check(&Vec2::new(5000.0, 0.0)).is_none()); // Still disabled.
+
+		rebaser.set_enabled(true);
+		assert!(rebaser.check(&Vec2::new(900.0, -900.0)).is_none());
+		let shift = rebaser.check(&Vec2::new(1300.0, 20.0)).unwrap();
+		assert_eq!((shift.x, shift.y), (-1536.0, 0.0));
+		assert_eq!(rebaser.get_offset().x, 1536.0);
+
+		// Positions convert between the two spaces.
+		let map = rebaser.to_map(&Vec2::new(-236.0, 20.0));
+		assert_eq!((map.x, map.y), (1300.0, 20.0));
+		let world = rebaser.from_map(&WorldVec2::new(1300.0, 20.0));
+		assert_eq!(world.x, -236.0);
+
+		let mut bounds = Bounds2::from_points(&Vec2::new(1536.0, 0.0), &Vec2::new(1552.0, 16.0));
+		let mut segments = vec![LineSegment::new(&Vec2::new(1536.0, 0.0), &Vec2::new(1612.0, 0.0))];
+		bounds.shift_origin(&shift);
+		segments.shift_origin(&shift);
+		assert_eq!((bounds.x_min(), bounds.x_max()), (0.0, 16.0));
+		assert_eq!((segments[0].start.x, segments[0].end.x, segments[0].length), (0.0, 76.0, 76.0));
+
+		rebaser.reset(512.0);
+		assert!(rebaser.get_offset().is_zero());
+	}
+}
@@ -0,0 +1,156 @@
+use crate::color::Color;
+
+/// What a color is used for. Anything drawn with one of these should get its color from a Palette, so it can be swapped out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteRole {
+	/// Things that hurt (enemies, the damage vignette, and collision in debug overlays).
+	Danger,
+	/// Tracks in debug overlays.
+	Track,
+	/// Pneumatic pipes in debug overlays.
+	Pipe,
+	/// The main HUD text.
+	HudPrimary,
+	/// The objective panel and markers.
+	Objective,
+	/// Pickups.
+	Pickup,
+	/// The kick charge meter.
+	KickMeter,
+	/// The bullet time meter.
+	BulletTimeMeter,
+	/// The stamina meter.
+	StaminaMeter,
+}
+
+impl PaletteRole {
+	/// All of the roles.
+	pub const ALL : [PaletteRole; 9] = [
+		PaletteRole::Danger,
+		PaletteRole::Track,
+		PaletteRole::Pipe,
+		PaletteRole::HudPrimary,
+		PaletteRole::Objective,
+		PaletteRole::Pickup,
+		PaletteRole::KickMeter,
+		PaletteRole::BulletTimeMeter,
+		PaletteRole::StaminaMeter,
+	];
+}
+
+/// The sets of colors to choose from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteKind {
+	/// The original colors.
+	Standard,
+	/// Avoids telling things apart by red versus green (for deuteranopia and protanopia). Based on the Okabe-Ito colors.
+	RedGreenSafe,
+	/// Avoids telling things apart by blue versus yellow (for tritanopia).
+	BlueYellowSafe,
+}
+
+impl PaletteKind {
+	/// Gets the kind with a given name (as used in settings).
+	pub fn from_name(name : &str) -> Option<PaletteKind> {
+		match name {
+			"standard" => Some(PaletteKind::Standard),
+			"redGreenSafe" => Some(PaletteKind::RedGreenSafe),
+			"blueYellowSafe" => Some(PaletteKind::BlueYellowSafe),
+			_ => None,
+		}
+	}
+
+	/// Gets the name (as used in settings).
+	pub fn name(&self) -> &'static str {
+		match self {
+			PaletteKind::Standard => "standard",
+			PaletteKind::RedGreenSafe => "redGreenSafe",
+			PaletteKind::BlueYellowSafe => "blueYellowSafe",
+		}
+	}
+}
+
+/// Maps palette roles to colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+	/// Which set of colors is used.
+	kind : PaletteKind,
+}
+
+impl Palette {
+	/// Creates an instance using a given set of colors.
+	pub fn new(kind : PaletteKind) -> Palette {
+		Palette { kind }
+	}
+
+	/// Gets which set of colors is used.
+	pub fn get_kind(&self) -> PaletteKind {
+		self.kind
+	}
+
+	/// Gets the color for a role.
+	pub fn get(&self, role : PaletteRole) -> Color {
+		let (red, green, blue) = match self.kind {
+			PaletteKind::Standard => match role {
+				PaletteRole::Danger          => (255,   0,   0),
+				PaletteRole::Track           => (  0, 255,   0),
+				PaletteRole::Pipe            => (255,   0,   0),
+				PaletteRole::HudPrimary      => (  0, 255,   0),
+				PaletteRole::Objective       => (255, 255,   0),
+				PaletteRole::Pickup          => (255, 200,   0),
+				PaletteRole::KickMeter       => (  0, 200, 255),
+				PaletteRole::BulletTimeMeter => (200,  80, 255),
+				PaletteRole::StaminaMeter    => (120, 255,  80),
+			},
+			PaletteKind::RedGreenSafe => match role {
+				PaletteRole::Danger          => (213,  94,   0),
+				PaletteRole::Track           => (  0, 114, 178),
+				PaletteRole::Pipe            => (204, 121, 167),
+				PaletteRole::HudPrimary      => ( 86, 180, 233),
+				PaletteRole::Objective       => (240, 228,  66),
+				PaletteRole::Pickup          => (230, 159,   0),
+				PaletteRole::KickMeter       => ( 86, 180, 233),
+				PaletteRole::BulletTimeMeter => (204, 121, 167),
+				PaletteRole::StaminaMeter    => (  0, 158, 115),
+			},
+			PaletteKind::BlueYellowSafe => match role {
+				PaletteRole::Danger          => (220,  20,  60),
+				PaletteRole::Track           => (  0, 180, 180),
+				PaletteRole::Pipe            => (255, 255, 255),
+				PaletteRole::HudPrimary      => (255, 255, 255),
+				PaletteRole::Objective       => (255, 110, 180),
+				PaletteRole::Pickup          => (255, 140,   0),
+				PaletteRole::KickMeter       => (  0, 200, 200),
+				PaletteRole::BulletTimeMeter => (255, 110, 180),
+				PaletteRole::StaminaMeter    => ( 80, 220, 120),
+			},
+		};
+		Color::new(red, green, blue, 255)
+	}
+}
+
+#[cfg(test)]
+mod tests_palette {
+	use super::*;
+
+	#[test]
+	fn names() {
+		for kind in [PaletteKind::Standard, PaletteKind::RedGreenSafe, PaletteKind::BlueYellowSafe].iter() {
+			assert_eq!(PaletteKind::from_name(kind.name()), Some(*kind));
+		}
+		assert_eq!(PaletteKind::from_name("sepia"), None);
+	}
+
+	#[test]
+	fn alternates_keep_overlays_apart() {
+		// The standard palette draws danger and pipes in the same red. The alternates shouldn't.
+		for kind in [PaletteKind::RedGreenSafe, PaletteKind::BlueYellowSafe].iter() {
+			let palette = Palette::new(*kind);
+			let overlays = [palette.get(PaletteRole::Danger), palette.get(PaletteRole::Track), palette.get(PaletteRole::Pipe)];
+			for (index, color) in overlays.iter().enumerate() {
+				assert!(!overlays[index+1..].contains(color), "{:?} repeats {:?}", kind, color);
+			}
+		}
+		assert_eq!(PaletteRole::ALL.len(), 9);
+	}
+}
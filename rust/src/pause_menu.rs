@@ -0,0 +1,498 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::display_text::*;
+use crate::accessibility::*;
+use crate::palette::PaletteKind;
+use crate::keyboard::*;
+use crate::gamepad::*;
+use crate::ui_focus::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::mat4::*;
+use crate::geo::bounds2::Bounds2;
+use crate::render_view::*;
+
+/// The most items any page has.
+const MAX_ITEMS : usize = 8;
+/// Where the first item's top edge is (as a fraction of the screen, from the top).
+const ITEMS_TOP : f32 = 0.30;
+/// How far apart items are (as a fraction of the screen).
+const ITEM_SPACING : f32 = 0.07;
+/// The color of items that don't have the focus.
+const UNFOCUSED_COLOR : Color = Color { red : 150, green : 150, blue : 150, alpha : 255 };
+/// How dark the overlay over the game is.
+const OVERLAY_COLOR : Color = Color { red : 0, green : 0, blue : 0, alpha : 170 };
+/// The depth to draw the overlay at (in front of the world and the HUD meters, behind the screen effects).
+const OVERLAY_DEPTH : f32 = -0.97;
+/// The text sizes the text size option steps through.
+const TEXT_SCALES : [f32; 4] = [1.0, 1.25, 1.5, 2.0];
+/// The HUD sizes the UI size option steps through.
+const UI_SCALES : [f32; 4] = [1.0, 1.25, 1.5, 0.75];
+/// The palettes the palette option steps through.
+const PALETTES : [PaletteKind; 3] = [PaletteKind::Standard, PaletteKind::RedGreenSafe, PaletteKind::BlueYellowSafe];
+
+/// Which page of the menu is showing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuPage {
+	/// Start, continue, and options (on the title screen).
+	Title,
+	/// Resume, options, and quit.
+	Main,
+	/// The accessibility settings.
+	Options,
+	/// Picking which save slot to play (with ways to copy and delete them).
+	Slots,
+	/// Picking a slot to copy the active one over.
+	CopySlot,
+	/// Picking a slot to delete.
+	DeleteSlot,
+	/// Making sure a new game should replace the progress in the active slot.
+	ConfirmStart,
+}
+
+/// Something in the menu that can be picked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuItem {
+	Start,
+	/// Asks before starting a new game over the active slot's progress.
+	NewGame,
+	/// Starts a new game (after asking).
+	EraseAndStart,
+	Continue,
+	SaveSlots,
+	Resume,
+	Options,
+	Quit,
+	ReduceFlashing,
+	HighContrast,
+	HoldToggle,
+	TextScale,
+	UiScale,
+	Palette,
+	CalibrateGamepad,
+	/// Plays a save slot (by index).
+	Slot(usize),
+	CopySlot,
+	/// Copies the active slot over another (by index).
+	CopyTo(usize),
+	DeleteSlot,
+	/// Empties a slot (by index).
+	Delete(usize),
+	Back,
+}
+
+impl MenuItem {
+	/// Gets the save slot it's about (if any).
+	pub fn get_slot(&self) -> Option<usize> {
+		match self {
+			MenuItem::Slot(slot) | MenuItem::CopyTo(slot) | MenuItem::Delete(slot) => Some(*slot),
+			_ => None,
+		}
+	}
+}
+
+/// Something the menu needs the game to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PauseAction {
+	/// Close the menu and carry on.
+	Resume,
+	/// Leave the room for the title screen.
+	Quit,
+	/// Start a new game.
+	Start,
+	/// Go back to the saved room.
+	Continue,
+	/// Change an accessibility setting (by name, to a value as text; see `AccessibilitySettings::set()`).
+	ChangeSetting(&'static str, String),
+	/// Switch to playing a save slot.
+	SelectSlot(usize),
+	/// Copy the active save slot over another.
+	CopySlot(usize),
+	/// Empty a save slot.
+	DeleteSlot(usize),
+	/// Measure where the gamepad's sticks rest.
+	CalibrateGamepad,
+}
+
+/// Gets the items on a page, in order. Continuing is only offered if there's a save to continue from (and then starting a new game asks first, as it would replace that save).
+pub fn page_items(page : MenuPage, can_continue : bool) -> &'static [MenuItem] {
+	match page {
+		MenuPage::Title => if can_continue { &[MenuItem::NewGame, MenuItem::Continue, MenuItem::SaveSlots, MenuItem::Options] } else { &[MenuItem::Start, MenuItem::SaveSlots, MenuItem::Options] },
+		MenuPage::Main => &[MenuItem::Resume, MenuItem::Options, MenuItem::Quit],
+		MenuPage::Options => &[MenuItem::ReduceFlashing, MenuItem::HighContrast, MenuItem::HoldToggle, MenuItem::TextScale, MenuItem::UiScale, MenuItem::Palette, MenuItem::CalibrateGamepad, MenuItem::Back],
+		MenuPage::Slots => &[MenuItem::Slot(0), MenuItem::Slot(1), MenuItem::Slot(2), MenuItem::CopySlot, MenuItem::DeleteSlot, MenuItem::Back],
+		MenuPage::CopySlot => &[MenuItem::CopyTo(0), MenuItem::CopyTo(1), MenuItem::CopyTo(2), MenuItem::Back],
+		MenuPage::DeleteSlot => &[MenuItem::Delete(0), MenuItem::Delete(1), MenuItem::Delete(2), MenuItem::Back],
+		MenuPage::ConfirmStart => &[MenuItem::EraseAndStart, MenuItem::Back],
+	}
+}
+
+/// Gets the text shown for an item, given the current settings. Slot items are just the slot's name (the menu adds what's in it).
+pub fn item_label(item : MenuItem, settings : &AccessibilitySettings) -> String {
+	let on_off = |value : bool| if value { "On" } else { "Off" };
+	match item {
+		MenuItem::Start | MenuItem::NewGame => String::from("New game"),
+		MenuItem::EraseAndStart => String::from("Erase and start"),
+		MenuItem::Continue => String::from("Continue"),
+		MenuItem::SaveSlots => String::from("Save slots"),
+		MenuItem::Resume => String::from("Resume"),
+		MenuItem::Options => String::from("Options"),
+		MenuItem::Quit => String::from("Quit to title"),
+		MenuItem::ReduceFlashing => format!("Reduce flashing: {}", on_off(settings.reduce_flashing)),
+		MenuItem::HighContrast => format!("High contrast: {}", on_off(settings.high_contrast)),
+		MenuItem::HoldToggle => format!("Tap to hold: {}", on_off(settings.hold_toggle)),
+		MenuItem::TextScale => format!("Text size: {}%", (settings.text_scale * 100.0).round()),
+		MenuItem::UiScale => format!("HUD size: {}%", (settings.ui_scale * 100.0).round()),
+		MenuItem::Palette => format!("Colors: {}", settings.palette.name()),
+		MenuItem::CalibrateGamepad => String::from("Calibrate gamepad"),
+		MenuItem::Slot(slot) | MenuItem::CopyTo(slot) | MenuItem::Delete(slot) => format!("Slot {}", slot + 1),
+		MenuItem::CopySlot => String::from("Copy"),
+		MenuItem::DeleteSlot => String::from("Delete"),
+		MenuItem::Back => String::from("Back"),
+	}
+}
+
+/// Works out what picking an item does: something for the game to do, and/or a page to switch to.
+/// Going back gives the main page, which the menu swaps for whichever page it was opened on.
+pub fn activate_item(item : MenuItem, settings : &AccessibilitySettings) -> (Option<PauseAction>, Option<MenuPage>) {
+	let toggle = |name : &'static str, value : bool| (Some(PauseAction::ChangeSetting(name, (!value).to_string())), None);
+	match item {
+		MenuItem::Start | MenuItem::EraseAndStart => (Some(PauseAction::Start), None),
+		MenuItem::NewGame => (None, Some(MenuPage::ConfirmStart)),
+		MenuItem::Continue => (Some(PauseAction::Continue), None),
+		MenuItem::SaveSlots => (None, Some(MenuPage::Slots)),
+		MenuItem::Resume => (Some(PauseAction::Resume), None),
+		MenuItem::Options => (None, Some(MenuPage::Options)),
+		MenuItem::Quit => (Some(PauseAction::Quit), None),
+		MenuItem::ReduceFlashing => toggle(REDUCE_FLASHING_SETTING, settings.reduce_flashing),
+		MenuItem::HighContrast => toggle(HIGH_CONTRAST_SETTING, settings.high_contrast),
+		MenuItem::HoldToggle => toggle(HOLD_TOGGLE_SETTING, settings.hold_toggle),
+		MenuItem::TextScale => {
+			// Go to the next bigger size, wrapping back to the smallest.
+			let next = TEXT_SCALES.iter().find(|scale| settings.text_scale < **scale).unwrap_or(&TEXT_SCALES[0]);
+			(Some(PauseAction::ChangeSetting(TEXT_SCALE_SETTING, next.to_string())), None)
+		},
+		MenuItem::UiScale => {
+			// Step through the sizes in order (so smaller comes after the biggest), wrapping back to normal.
+			let index = UI_SCALES.iter().position(|scale| settings.ui_scale == *scale).map_or(0, |index| (index + 1) % UI_SCALES.len());
+			(Some(PauseAction::ChangeSetting(UI_SCALE_SETTING, UI_SCALES[index].to_string())), None)
+		},
+		MenuItem::Palette => {
+			let index = PALETTES.iter().position(|kind| settings.palette == *kind).map_or(0, |index| (index + 1) % PALETTES.len());
+			(Some(PauseAction::ChangeSetting(PALETTE_SETTING, PALETTES[index].name().to_string())), None)
+		},
+		MenuItem::CalibrateGamepad => (Some(PauseAction::CalibrateGamepad), None),
+		MenuItem::Slot(slot) => (Some(PauseAction::SelectSlot(slot)), Some(MenuPage::Main)),
+		MenuItem::CopySlot => (None, Some(MenuPage::CopySlot)),
+		MenuItem::CopyTo(slot) => (Some(PauseAction::CopySlot(slot)), Some(MenuPage::Slots)),
+		MenuItem::DeleteSlot => (None, Some(MenuPage::DeleteSlot)),
+		MenuItem::Delete(slot) => (Some(PauseAction::DeleteSlot(slot)), Some(MenuPage::Slots)),
+		MenuItem::Back => (None, Some(MenuPage::Main)),
+	}
+}
+
+/// The pause menu (which doubles as the title menu): a darkened overlay over the game, with a list of items picked with the keyboard or a gamepad.
+///
+/// It only shows things and works out what was picked. The Game does whatever that means.
+pub struct PauseMenu {
+	/// Whether it's showing.
+	open : bool,
+	/// Which page is showing.
+	page : MenuPage,
+	/// The page the menu was opened on (which going back returns to).
+	home : MenuPage,
+	/// Whether the title page offers to continue.
+	can_continue : bool,
+	/// What's in each save slot (in a few words), and which one is being played.
+	slots : (Vec<String>, usize),
+	/// Moves the focus between the items.
+	navigator : FocusNavigator,
+	/// The heading over the items.
+	heading : DisplayText,
+	/// One text per item (unused ones are hidden).
+	texts : Vec<DisplayText>,
+	/// The color of the focused item and the heading.
+	color : Color,
+	/// Whether the back button was down last update.
+	back_held : bool,
+	/// The darkened overlay.
+	overlay : DisplayBuffer,
+	/// The screen size the overlay was last drawn for.
+	overlay_size : Vec3,
+}
+
+impl PauseMenu {
+	/// Creates an instance that isn't showing.
+	pub fn new(color : &Color) -> PauseMenu {
+		let mut heading = DisplayText::new_text_area(ITEMS_TOP - 2.0 * ITEM_SPACING, 0.25, ITEMS_TOP - ITEM_SPACING, 0.75, color, TextAlignment::CENTER, "");
+		heading.hide();
+		let mut texts = Vec::with_capacity(MAX_ITEMS);
+		for index in 0..MAX_ITEMS {
+			let top = ITEMS_TOP + (index as f32) * ITEM_SPACING;
+			let mut text = DisplayText::new_text_area(top, 0.25, top + ITEM_SPACING * 0.9, 0.75, &UNFOCUSED_COLOR, TextAlignment::CENTER, "");
+			text.hide();
+			texts.push(text);
+		}
+		let mut overlay = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		overlay.set_views(view_bit(MAIN_VIEW));
+		overlay.hide();
+		PauseMenu {
+			open : false,
+			page : MenuPage::Main,
+			home : MenuPage::Main,
+			can_continue : false,
+			slots : (Vec::new(), 0),
+			navigator : FocusNavigator::new(),
+			heading,
+			texts,
+			color : color.clone(),
+			back_held : true,
+			overlay,
+			overlay_size : Vec3::zero(),
+		}
+	}
+
+	/// Whether it's showing.
+	pub fn is_open(&self) -> bool {
+		self.open
+	}
+
+	/// Shows the menu, starting on some page (see `MenuPage::Title` vs `MenuPage::Main`). Whether the title page can continue is only used on that page.
+	pub fn open(&mut self, page : MenuPage, can_continue : bool, settings : &AccessibilitySettings) {
+		self.open = true;
+		self.home = page;
+		self.can_continue = can_continue;
+		self.back_held = true; // Whatever opened it might still be down.
+		self.overlay.show();
+		self.heading.show();
+		self.show_page(page, settings);
+	}
+
+	/// Hides everything.
+	pub fn close(&mut self) {
+		self.open = false;
+		self.overlay.hide();
+		self.heading.hide();
+		for text in &mut self.texts {
+			text.hide();
+		}
+		self.navigator.clear();
+	}
+
+	/// Sets what's in each save slot (in a few words, as in from `SaveSlots::summary()`), and which one is being played. Also sets whether the title page offers to continue (which depends on the slot).
+	pub fn set_slots(&mut self, summaries : Vec<String>, active : usize, can_continue : bool, settings : &AccessibilitySettings) {
+		self.slots = (summaries, active);
+		if can_continue != self.can_continue {
+			self.can_continue = can_continue;
+			if self.open && MenuPage::Title == self.page {
+				self.show_page(MenuPage::Title, settings); // The items changed.
+				return;
+			}
+		}
+		if self.open {
+			self.refresh(settings);
+		}
+	}
+
+	/// Sets the color of the focused item and the heading.
+	pub fn set_color(&mut self, color : &Color) {
+		self.color = color.clone();
+		self.heading.set_color(color);
+		self.highlight_focused();
+	}
+
+	/// Switches to a page, focusing its first item.
+	fn show_page(&mut self, page : MenuPage, settings : &AccessibilitySettings) {
+		self.page = page;
+		self.heading.set_text(match page {
+			MenuPage::Title => "ROBO EXPLORE",
+			MenuPage::Main => "Paused",
+			MenuPage::Options => "Options",
+			MenuPage::Slots => "Save slots",
+			MenuPage::CopySlot => "Copy the current slot to...",
+			MenuPage::DeleteSlot => "Delete which slot?",
+			MenuPage::ConfirmStart => "Erase this slot's progress?",
+		});
+		self.navigator.clear();
+		let items = page_items(page, self.can_continue);
+		for (index, text) in self.texts.iter_mut().enumerate() {
+			if index < items.len() {
+				// The navigator works with y up, so the items go down from the top.
+				let center = Vec2::new(0.5, 1.0 - (ITEMS_TOP + (index as f32 + 0.5) * ITEM_SPACING));
+				self.navigator.add(index, &Bounds2::from_centered_rect(&center, 0.5, ITEM_SPACING * 0.9)).unwrap();
+				text.show();
+			} else {
+				text.hide();
+			}
+		}
+		self.refresh(settings);
+	}
+
+	/// Updates the item texts (as in after a setting changed).
+	pub fn refresh(&mut self, settings : &AccessibilitySettings) {
+		let (summaries, active) = &self.slots;
+		for (text, item) in self.texts.iter_mut().zip(page_items(self.page, self.can_continue).iter()) {
+			let mut label = item_label(*item, settings);
+			if let Some(slot) = item.get_slot() {
+				let marker = if *active == slot { " (current)" } else { "" };
+				label = format!("{}{}: {}", label, marker, summaries.get(slot).map_or("", |summary| summary.as_str()));
+			}
+			text.set_text(&label);
+		}
+		self.highlight_focused();
+	}
+
+	/// Colors the focused item differently from the rest.
+	fn highlight_focused(&mut self) {
+		let focused = self.navigator.get_focused();
+		for (index, text) in self.texts.iter_mut().enumerate() {
+			text.set_color(if Some(index) == focused { &self.color } else { &UNFOCUSED_COLOR });
+		}
+	}
+
+	/// Reads the inputs (moving the focus, picking items, and going back or resuming with B), and keeps the overlay covering the screen. Gives what the game should do (if anything).
+	pub fn update(&mut self, real_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad, settings : &AccessibilitySettings, camera_center : &Vec3, screen_size : &Vec3) -> Option<PauseAction> {
+		if !self.open {
+			return None;
+		}
+		if screen_size.x != self.overlay_size.x || screen_size.y != self.overlay_size.y {
+			self.overlay_size = screen_size.clone();
+			self.redraw_overlay();
+		}
+		let mut transform = Mat4::new();
+		transform.translate_before(&Vec3::new(camera_center.x, camera_center.y, 0.0));
+		self.overlay.set_transform(&transform);
+
+		let back_down = gamepad.is_down(Button::B);
+		let back_pressed = back_down && !self.back_held;
+		self.back_held = back_down;
+		if back_pressed {
+			if self.home != self.page {
+				self.show_page(self.home, settings);
+				return None;
+			}
+			return if MenuPage::Main == self.page { Some(PauseAction::Resume) } else { None };
+		}
+
+		let mut action = None;
+		for event in self.navigator.update(real_seconds, keyboard, gamepad) {
+			match event {
+				FocusEvent::Moved(_) => self.highlight_focused(),
+				FocusEvent::Activated(index) => {
+					let (result, page) = activate_item(page_items(self.page, self.can_continue)[index], settings);
+					if let Some(page) = page {
+						self.show_page(if MenuPage::Main == page { self.home } else { page }, settings);
+					}
+					if result.is_some() {
+						action = result;
+					}
+				},
+			}
+		}
+		action
+	}
+
+	/// Rebuilds the overlay to cover the screen.
+	fn redraw_overlay(&mut self) {
+		let mut editor = self.overlay.make_editor();
+		editor.clear();
+		let half_width = self.overlay_size.x / 2.0;
+		let half_height = self.overlay_size.y / 2.0;
+		editor.add_polygon(&vec![
+			Vec3::new(-half_width, -half_height, OVERLAY_DEPTH),
+			Vec3::new(half_width, -half_height, OVERLAY_DEPTH),
+			Vec3::new(half_width, half_height, OVERLAY_DEPTH),
+			Vec3::new(-half_width, half_height, OVERLAY_DEPTH),
+		], &OVERLAY_COLOR);
+	}
+}
+
+#[cfg(test)]
+mod tests_pause_menu {
+	use super::*;
+	use crate::save_slots::SAVE_SLOT_COUNT;
+
+	#[test]
+	fn items() {
+		let mut settings = AccessibilitySettings::new();
+		assert_eq!(activate_item(MenuItem::Resume, &settings), (Some(PauseAction::Resume), None));
+		assert_eq!(activate_item(MenuItem::Options, &settings), (None, Some(MenuPage::Options)));
+		assert_eq!(activate_item(MenuItem::HighContrast, &settings), (Some(PauseAction::ChangeSetting(HIGH_CONTRAST_SETTING, String::from("true"))), None));
+		assert_eq!(item_label(MenuItem::HighContrast, &settings), "High contrast: Off");
+
+		// Every change the menu asks for is one the settings accept, and stepping wraps around.
+		assert_eq!(page_items(MenuPage::Title, false), &[MenuItem::Start, MenuItem::SaveSlots, MenuItem::Options]);
+		assert_eq!(activate_item(MenuItem::Start, &settings), (Some(PauseAction::Start), None));
+		// A new game over a slot with a save asks first.
+		assert_eq!(page_items(MenuPage::Title, true)[0], MenuItem::NewGame);
+		assert_eq!(activate_item(MenuItem::NewGame, &settings), (None, Some(MenuPage::ConfirmStart)));
+		assert_eq!(page_items(MenuPage::ConfirmStart, true), &[MenuItem::EraseAndStart, MenuItem::Back]);
+		assert_eq!(activate_item(MenuItem::EraseAndStart, &settings), (Some(PauseAction::Start), None));
+		assert_eq!(activate_item(MenuItem::Continue, &settings), (Some(PauseAction::Continue), None));
+		for item in page_items(MenuPage::Options, false) {
+			for _ in 0..5 {
+				if let (Some(PauseAction::ChangeSetting(name, value)), _) = activate_item(*item, &settings) {
+					assert!(settings.set(name, &value).is_ok(), "{:?} asked for {}={}", item, name, value);
+				}
+			}
+		}
+		assert_eq!(item_label(MenuItem::TextScale, &settings), "Text size: 125%");
+		assert_eq!(item_label(MenuItem::UiScale, &settings), "HUD size: 125%");
+		assert_eq!(item_label(MenuItem::Palette, &settings), "Colors: blueYellowSafe");
+		assert!(settings.high_contrast);
+		assert_eq!(activate_item(MenuItem::CalibrateGamepad, &settings), (Some(PauseAction::CalibrateGamepad), None));
+
+		// Save slots.
+		assert_eq!(activate_item(MenuItem::SaveSlots, &settings), (None, Some(MenuPage::Slots)));
+		assert_eq!(activate_item(MenuItem::Slot(1), &settings), (Some(PauseAction::SelectSlot(1)), Some(MenuPage::Main)));
+		assert_eq!(activate_item(MenuItem::Delete(2), &settings), (Some(PauseAction::DeleteSlot(2)), Some(MenuPage::Slots)));
+		assert_eq!(item_label(MenuItem::CopyTo(0), &settings), "Slot 1");
+		for page in [MenuPage::Slots, MenuPage::CopySlot, MenuPage::DeleteSlot].iter() {
+			let slots = page_items(*page, false).iter().filter(|item| item.get_slot().is_some()).count();
+			assert_eq!(slots, SAVE_SLOT_COUNT);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_ui_scale {
+	use super::*;
+	use crate::camera::*;
+
+	/// Picks the UI size option, and applies whatever it asks for.
+	fn step(settings : &mut AccessibilitySettings) {
+		match activate_item(MenuItem::UiScale, settings) {
+			(Some(PauseAction::ChangeSetting(name, value)), None) => { settings.set(name, &value).unwrap(); },
+			other => panic!("Unexpected UI size action {:?}", other),
+		}
+	}
+
+	#[test]
+	fn stepping() {
+		let mut settings = AccessibilitySettings::new();
+		let mut seen = Vec::new();
+		for _ in 0..4 {
+			step(&mut settings);
+			seen.push(settings.ui_scale);
+		}
+		assert_eq!(seen, [1.25, 1.5, 0.75, 1.0]);
+		// A size the menu doesn't offer (as in from an old save) goes back to normal.
+		settings.set(UI_SCALE_SETTING, "2").unwrap();
+		step(&mut settings);
+		assert_eq!(settings.ui_scale, 1.0);
+	}
+
+	#[test]
+	fn with_virtual_resolution() {
+		let mut settings = AccessibilitySettings::new();
+		settings.set(UI_SCALE_SETTING, "1.5").unwrap();
+		settings.set(TEXT_SCALE_SETTING, "1.25").unwrap();
+		// A 480x270 view on a 1080p screen is drawn 4 times over, and the HUD grows by that on top of its own size.
+		let layout = layout_view(&ViewRect::FULL, 1920, 1080, Some(&VirtualResolution::new(480, 270, VirtualScaling::Integer)));
+		assert_eq!(settings.ui_pixel_scale(layout.scale), 6.0);
+		assert_eq!(settings.text_pixel_scale(layout.scale), 7.5);
+		// HUD pixel sizes are left to the display to scale.
+		assert_eq!(CssLength::Pixels(12.0).to_css(), "calc(12px * var(--ui-scale, 1))");
+		assert_eq!(CssLength::CharWidth(2.0).to_css(), "2em");
+	}
+}
@@ -0,0 +1,147 @@
+use crate::geo::vec2::*;
+use crate::keyboard::*;
+use crate::gamepad::*;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How fast the free camera pans (in pixels per second, at a zoom of 1.0).
+const PAN_SPEED : f32 = 300.0;
+/// How quickly the zoom changes (as a multiplier per second).
+const ZOOM_RATE : f32 = 2.0;
+/// The furthest out the camera can zoom.
+const MIN_ZOOM : f32 = 0.25;
+/// The furthest in the camera can zoom.
+const MAX_ZOOM : f32 = 8.0;
+
+/// A debug mode where the camera is detached from the player.
+///
+/// While active the camera pans with the direction keys (or the second analog stick) and zooms with the zoom keys.
+/// The Game freezes the simulation (through its TimeControl) while this is on.
+pub struct PhotoMode {
+	/// Whether photo mode is on.
+	active : bool,
+	/// Where the camera is looking.
+	center : Vec2,
+	/// The camera's zoom.
+	zoom : f32,
+	/// Whether the toggle key was down last update (so holding it doesn't flicker on and off).
+	toggle_held : bool,
+}
+
+impl PhotoMode {
+	/// Creates an inactive instance.
+	pub fn new() -> PhotoMode {
+		PhotoMode {
+			active : false,
+			center : Vec2::new(0.0, 0.0),
+			zoom : 1.0,
+			toggle_held : false,
+		}
+	}
+
+	/// Whether photo mode is on.
+	pub fn is_active(&self) -> bool {
+		self.active
+	}
+
+	/// Where the camera should look.
+	pub fn get_center(&self) -> Vec2 {
+		self.center
+	}
+
+	/// How zoomed in the camera should be.
+	pub fn get_zoom(&self) -> f32 {
+		self.zoom
+	}
+
+	/// Handles the inputs for this update. Returns whether photo mode was turned on or off.
+	/// The camera's current center is where the free camera starts from when turned on.
+	pub fn update(&mut self, elapsed_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad, camera_center : &Vec2) -> bool {
+		let toggle_down = keyboard.is_down(Key::PHOTO_MODE);
+		let toggled = toggle_down && !self.toggle_held;
+		self.toggle_held = toggle_down;
+		if toggled {
+			self.active = !self.active;
+			self.center = camera_center.clone();
+			self.zoom = 1.0;
+		}
+
+		if !self.active {
+			return toggled;
+		}
+
+		let mut pan = gamepad.second_direction();
+		if keyboard.is_down(Key::UP)    { pan.y += 1.0; }
+		if keyboard.is_down(Key::LEFT)  { pan.x -= 1.0; }
+		if keyboard.is_down(Key::DOWN)  { pan.y -= 1.0; }
+		if keyboard.is_down(Key::RIGHT) { pan.x += 1.0; }
+		if 1.0 < pan.length() {
+			pan = pan.norm();
+		}
+		self.center += pan * (PAN_SPEED * elapsed_seconds / self.zoom);
+
+		if keyboard.is_down(Key::ZOOM_IN) {
+			self.zoom *= ZOOM_RATE.powf(elapsed_seconds);
+		}
+		if keyboard.is_down(Key::ZOOM_OUT) {
+			self.zoom /= ZOOM_RATE.powf(elapsed_seconds);
+		}
+		self.zoom = self.zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+		toggled
+	}
+}
+
+impl ShiftOrigin for PhotoMode {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.center.shift_origin(shift);
+	}
+}
+
+#[cfg(test)]
+mod tests_photo_mode {
+	use super::*;
+
+	#[test]
+	fn toggle() {
+		let mut keyboard = Keyboard::new();
+		let gamepad = Gamepad::new();
+		let mut photo = PhotoMode::new();
+		let center = Vec2::new(10.0, 20.0);
+		assert!(!photo.update(0.1, &keyboard, &gamepad, &center));
+		assert!(!photo.is_active());
+
+		keyboard.on_down("p".to_string());
+		assert!(photo.update(0.1, &keyboard, &gamepad, &center));
+		assert!(photo.is_active());
+		assert_eq!(photo.get_center().x, center.x);
+		assert_eq!(photo.get_center().y, center.y);
+		assert!(!photo.update(0.1, &keyboard, &gamepad, &center)); // Holding doesn't toggle again.
+		keyboard.on_up("p".to_string());
+		photo.update(0.1, &keyboard, &gamepad, &center);
+
+		keyboard.on_down("p".to_string());
+		assert!(photo.update(0.1, &keyboard, &gamepad, &center));
+		assert!(!photo.is_active());
+	}
+
+	#[test]
+	fn pan_and_zoom() {
+		let mut keyboard = Keyboard::new();
+		let gamepad = Gamepad::new();
+		let mut photo = PhotoMode::new();
+		keyboard.on_down("p".to_string());
+		photo.update(0.0, &keyboard, &gamepad, &Vec2::new(0.0, 0.0));
+		keyboard.on_up("p".to_string());
+
+		keyboard.on_down("ArrowRight".to_string());
+		photo.update(1.0, &keyboard, &gamepad, &Vec2::new(0.0, 0.0));
+		keyboard.on_up("ArrowRight".to_string());
+		assert_eq!(photo.get_center().x, PAN_SPEED);
+		assert_eq!(photo.get_center().y, 0.0);
+
+		keyboard.on_down("=".to_string());
+		photo.update(1.0, &keyboard, &gamepad, &Vec2::new(0.0, 0.0));
+		assert_eq!(photo.get_zoom(), ZOOM_RATE);
+		photo.update(100.0, &keyboard, &gamepad, &Vec2::new(0.0, 0.0));
+		assert_eq!(photo.get_zoom(), MAX_ZOOM);
+	}
+}
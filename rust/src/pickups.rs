@@ -0,0 +1,328 @@
+use crate::color::*;
+use crate::palette::*;
+use crate::display_buffer::*;
+use crate::instanced_buffer::*;
+use crate::event_bus::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::consts::EPSILON;
+use crate::geo::collider::reflect_movement;
+use crate::geo::collision_system::CollisionSystem;
+use crate::spatial_hash::{SpatialHash, DynamicKind};
+use crate::tiled::TiledFile;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled point type that marks a pickup. The point's name is the kind of pickup.
+const PICKUP_POINT_TYPE : &str = "pickup";
+/// How big pickups are drawn (in pixels).
+const PICKUP_SIZE : f32 = 4.0;
+/// The depth to draw pickups at.
+const PICKUP_DEPTH : f32 = -0.1;
+/// How big dropped pickups are when colliding with things (in pixels).
+pub const DROP_RADIUS : f32 = 3.0;
+/// How bouncy dropped pickups are (0 doesn't bounce, 1 bounces back at full speed).
+const DROP_RESTITUTION : f32 = 0.5;
+/// How much of a dropped pickup's speed is kept after each bounce (so it eventually stops sliding).
+const DROP_BOUNCE_DAMPING : f32 = 0.8;
+/// Below what speed a dropped pickup that's touching the ground comes to rest.
+const DROP_REST_SPEED : f32 = 20.0;
+/// The most rounds of collision a dropped pickup goes through per update.
+const DROP_COLLISION_STEPS : usize = 4;
+/// How close a player needs to be for dropped pickups to fly toward them.
+const MAGNET_RADIUS : f32 = 48.0;
+/// How quickly dropped pickups speed up toward the player (in pixels per second squared).
+const MAGNET_ACCELERATION : f32 = 900.0;
+/// How fast dropped pickups can fly toward the player (in pixels per second).
+const MAGNET_MAX_SPEED : f32 = 240.0;
+/// How much a surface's normal needs to point up to count as ground.
+const GROUND_DOT : f32 = 0.7;
+
+/// Something lying around the level that can be collected.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pickup {
+	/// What kind of pickup it is.
+	pub kind : String,
+	/// Where it is.
+	pub position : Vec2,
+	/// Whether it's been collected (and so shouldn't be shown or collected again).
+	pub collected : bool,
+	/// Whether it was dropped (rather than placed in the level), and so bounces around and flies to the player.
+	pub loose : bool,
+	/// Whether it's loose but has come to rest on the ground.
+	pub resting : bool,
+	/// How fast it's moving (only used when loose).
+	pub velocity : Vec2,
+}
+
+/// All the pickups in the level.
+pub struct Pickups {
+	/// Every pickup (including collected ones, so indices stay the same).
+	items : Vec<Pickup>,
+}
+
+impl Pickups {
+	/// Creates an instance with no pickups.
+	pub fn new() -> Pickups {
+		Pickups {
+			items : Vec::new(),
+		}
+	}
+
+	/// Replaces all pickups with the ones in a Tiled file.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.items.clear();
+		for point in file.get_points() {
+			if PICKUP_POINT_TYPE == point.r#type {
+				self.add(&point.name, &point.position);
+			}
+		}
+	}
+
+	/// Adds a pickup. Returns its index.
+	pub fn add(&mut self, kind : &str, position : &Vec2) -> usize {
+		self.items.push(Pickup {
+			kind : kind.to_string(),
+			position : position.clone(),
+			collected : false,
+			loose : false,
+			resting : false,
+			velocity : Vec2::zero(),
+		});
+		self.items.len() - 1
+	}
+
+	/// Adds a loose pickup (as in when something is defeated) that bounces around starting with some velocity. Returns its index.
+	pub fn drop(&mut self, kind : &str, position : &Vec2, velocity : &Vec2) -> usize {
+		let index = self.add(kind, position);
+		let item = &mut self.items[index];
+		item.loose = true;
+		item.velocity = velocity.clone();
+		index
+	}
+
+	/// Gets all pickups.
+	pub fn get_items<'a>(&'a self) -> &'a Vec<Pickup> {
+		&self.items
+	}
+
+	/// Replaces all pickups (as in when restoring a snapshot).
+	pub fn restore(&mut self, items : &[Pickup]) {
+		self.items = items.to_vec();
+	}
+
+	/// Moves a pickup (as in when it's being carried). This stops it from moving on its own.
+	pub fn set_position(&mut self, index : usize, position : &Vec2) {
+		let item = &mut self.items[index];
+		item.position = position.clone();
+		item.loose = false;
+		item.velocity = Vec2::zero();
+	}
+
+	/// Moves all loose pickups: they fall and bounce off of things, unless a player (in the dynamic entity hash) is close enough to pull them in.
+	/// Returns whether anything moved.
+	pub fn update(&mut self, elapsed_seconds : f32, gravity : &Vec2, dynamic : &SpatialHash, collision : &CollisionSystem) -> bool {
+		let up = if EPSILON < gravity.length() { gravity.norm().scale(-1.0) } else { Vec2::new(0.0, 1.0) };
+		let mut moved = false;
+		for item in &mut self.items {
+			if item.collected || !item.loose {
+				continue;
+			}
+			if let Some(player) = dynamic.nearest(&item.position, 1, MAGNET_RADIUS, Some(DynamicKind::Player)).first() {
+				// Fly straight to the player, ignoring gravity and walls.
+				item.resting = false;
+				let to_player = player.position - item.position;
+				if EPSILON < to_player.length() {
+					item.velocity += to_player.norm() * (MAGNET_ACCELERATION * elapsed_seconds);
+				}
+				if MAGNET_MAX_SPEED < item.velocity.length() {
+					item.velocity = item.velocity.set_length(MAGNET_MAX_SPEED);
+				}
+				item.position += item.velocity * elapsed_seconds;
+				moved = true;
+				continue;
+			}
+			if item.resting {
+				continue;
+			}
+			item.velocity += gravity * elapsed_seconds;
+			let mut position = item.position;
+			let mut movement = item.velocity * elapsed_seconds;
+			let mut grounded = false;
+			for _step in 0..DROP_COLLISION_STEPS {
+				match collision.collide_circle_step_with_restitution(&position, DROP_RADIUS, &movement, Some(DROP_RESTITUTION)) {
+					Some(deflection) => {
+						let normal = deflection.deflections[0].normal;
+						grounded |= GROUND_DOT <= normal.dot(&up);
+						item.velocity = reflect_movement(&item.velocity, &normal, DROP_RESTITUTION) * DROP_BOUNCE_DAMPING;
+						position = deflection.deflections[0].position;
+						movement = deflection.final_position - position;
+						if EPSILON > movement.length() {
+							break;
+						}
+					},
+					None => {
+						position += movement;
+						break;
+					},
+				}
+			}
+			item.position = position;
+			if grounded && item.velocity.length() < DROP_REST_SPEED {
+				item.resting = true;
+				item.velocity = Vec2::zero();
+			}
+			moved = true;
+		}
+		moved
+	}
+
+	/// Finds the closest uncollected pickup within some distance of a position.
+	pub fn find_nearest(&self, position : &Vec2, max_distance : f32) -> Option<usize> {
+		let mut nearest = None;
+		let mut nearest_distance = max_distance;
+		for (index, item) in self.items.iter().enumerate() {
+			if item.collected {
+				continue;
+			}
+			let distance = (item.position - position).length();
+			if distance <= nearest_distance {
+				nearest = Some(index);
+				nearest_distance = distance;
+			}
+		}
+		nearest
+	}
+
+	/// Collects a pickup (if it hasn't been already) and posts an event about it.
+	pub fn collect(&mut self, index : usize, events : &mut EventBus) {
+		let item = &mut self.items[index];
+		if !item.collected {
+			item.collected = true;
+			events.post(GameEvent::PickupCollected(item.kind.clone()));
+		}
+	}
+
+	/// Collects everything within some distance of a position. Returns whether anything was collected.
+	pub fn collect_touching(&mut self, position : &Vec2, radius : f32, events : &mut EventBus) -> bool {
+		let mut any = false;
+		while let Some(index) = self.find_nearest(position, radius) {
+			self.collect(index, events);
+			any = true;
+		}
+		any
+	}
+}
+
+impl ShiftOrigin for Pickups {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for item in &mut self.items {
+			item.position += shift;
+		}
+	}
+}
+
+/// Draws the pickups.
+pub struct PickupDisplay {
+	/// What the pickups are drawn in (one diamond per pickup).
+	display : InstancedBuffer,
+	/// The color the pickups are drawn in.
+	color : Color,
+}
+
+impl PickupDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> PickupDisplay {
+		let mut diamond = DisplayGeometry::new(DisplayBufferType::SOLIDS);
+		diamond.add_polygon(&vec![
+			Vec3::new(0.0, PICKUP_SIZE, PICKUP_DEPTH),
+			Vec3::new(-PICKUP_SIZE, 0.0, PICKUP_DEPTH),
+			Vec3::new(0.0, -PICKUP_SIZE, PICKUP_DEPTH),
+			Vec3::new(PICKUP_SIZE, 0.0, PICKUP_DEPTH),
+		], &Color::new(255, 255, 255, 255));
+		PickupDisplay {
+			display : InstancedBuffer::new(DisplayBufferType::SOLIDS, diamond),
+			color : Palette::new(PaletteKind::Standard).get(PaletteRole::Pickup),
+		}
+	}
+
+	/// Sets the color the pickups are drawn in (on the next redraw).
+	pub fn set_color(&mut self, color : &Color) {
+		self.color = color.clone();
+	}
+
+	/// Redraws all uncollected pickups.
+	pub fn redraw(&mut self, pickups : &Pickups) {
+		let instances : Vec<Instance> = pickups.get_items().iter()
+			.filter(|item| !item.collected)
+			.map(|item| Instance::new(&Vec3::new(item.position.x, item.position.y, 0.0), &self.color))
+			.collect();
+		self.display.update(&instances);
+	}
+}
+
+#[cfg(test)]
+mod tests_pickups {
+	use super::*;
+
+	#[test]
+	fn collecting() {
+		let mut pickups = Pickups::new();
+		let mut events = EventBus::new();
+		let near = pickups.add("scrap", &Vec2::new(5.0, 0.0));
+		pickups.add("scrap", &Vec2::new(50.0, 0.0));
+		assert_eq!(pickups.find_nearest(&Vec2::new(0.0, 0.0), 100.0), Some(near));
+		assert!(pickups.collect_touching(&Vec2::new(0.0, 0.0), 10.0, &mut events));
+		assert!(pickups.get_items()[near].collected);
+		assert_eq!(events.drain(), vec![GameEvent::PickupCollected("scrap".to_string())]);
+
+		// Collecting again does nothing.
+		pickups.collect(near, &mut events);
+		assert!(events.drain().is_empty());
+		assert!(!pickups.collect_touching(&Vec2::new(0.0, 0.0), 10.0, &mut events));
+		assert_eq!(pickups.find_nearest(&Vec2::new(0.0, 0.0), 100.0), Some(1));
+	}
+
+	#[test]
+	fn drops_bounce_and_magnetize() {
+		use crate::geo::line_segment::LineSegment;
+		use crate::geo::collision_system::CircleObstacle;
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0))));
+		let gravity = Vec2::new(0.0, -800.0);
+		let mut far_away = SpatialHash::new();
+		far_away.insert(DynamicKind::Player, 0, &Vec2::new(1000.0, 1000.0), 8.0);
+		let mut pickups = Pickups::new();
+		let placed = pickups.add("scrap", &Vec2::new(-50.0, 20.0));
+		let dropped = pickups.drop("scrap", &Vec2::new(0.0, 10.0), &Vec2::new(20.0, 150.0));
+		let mut highest : f32 = 0.0;
+		let mut bounced = false;
+		let mut falling = false;
+		for _ in 0..120 {
+			let before = pickups.get_items()[dropped].velocity.y;
+			pickups.update(1.0 / 30.0, &gravity, &far_away, &collision);
+			let item = &pickups.get_items()[dropped];
+			highest = highest.max(item.position.y);
+			falling |= 0.0 > before;
+			bounced |= falling && 0.0 > before && 0.0 < item.velocity.y;
+			assert!(DROP_RADIUS - 0.5 < item.position.y);
+		}
+		assert!(20.0 < highest); // Popped up.
+		assert!(bounced);
+		assert!(pickups.get_items()[dropped].resting);
+		assert_eq!(pickups.get_items()[placed].position.y, 20.0); // Placed pickups don't fall.
+
+		// Once the player is close, it flies over to them.
+		let player = pickups.get_items()[dropped].position + Vec2::new(30.0, 10.0);
+		let mut nearby = SpatialHash::new();
+		nearby.insert(DynamicKind::Player, 0, &player, 8.0);
+		let mut events = EventBus::new();
+		let mut collected = false;
+		for _ in 0..30 {
+			pickups.update(1.0 / 30.0, &gravity, &nearby, &collision);
+			collected |= pickups.collect_touching(&player, 8.0, &mut events);
+		}
+		assert!(collected);
+		assert!(pickups.get_items()[dropped].collected);
+	}
+}
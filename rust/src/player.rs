@@ -1,24 +1,41 @@
 
-use crate::geo::mat4::Mat4;
 use crate::geo::vec2::*;
-use crate::geo::vec3::Vec3;
 use crate::geo::consts::EPSILON;
-use crate::geo::collider::limit_movement_with_normals;
+use crate::geo::collider::{Deflection, limit_movement_with_normals};
 
 use crate::externals::log;
+use crate::debug_log;
 
 use crate::display_texture::DisplayTexture;
 use crate::display_buffer::{DisplayBuffer, DisplayBufferType};
-use crate::geo::collision_system::CollisionSystem;
+use crate::sprite_animation::{Animator, AnimationMode, SpriteAnimation, SpriteFrame};
+use crate::geo::collision_system::{CollisionSystem, CircleQuery, ObstacleContact, IterationEscape};
+use crate::geo::contact_cache::ContactCache;
+use generational_arena::Index;
+use crate::tile_behavior::TileBehaviorTarget;
+use crate::health::Health;
+use crate::knockback::Knockback;
+use crate::stamina::Stamina;
+use crate::interpolation::InterpolatedPosition;
+use crate::color::Color;
+use crate::anomaly_capture::{AnomalyKind, PhysicsAnomaly};
+use crate::ground_normal::GroundNormal;
+use crate::hitboxes::{Hitboxes, HitboxKind};
+use crate::movement_assist::{find_step_up, find_corner_correction};
+use crate::tuning::Tuning;
+use crate::zipline::ZiplineRide;
+use crate::accessibility::HoldToggle;
 use crate::keyboard::*;
 use crate::gamepad::*;
 use crate::tiled_geometry::TiledGeometry;
-
-/// The max number of physics iterations the player.
-const PHYSICS_ITERATION_MAX : usize = 5;
+use crate::origin_rebase::ShiftOrigin;
 
 /// The radius of the player's (circle) collider.
 const PLAYER_RADIUS : f32 = 8.0;
+/// How far the player can overlap the collision geometry before it counts as a penetration anomaly.
+const PENETRATION_TOLERANCE : f32 = 0.5;
+/// How many frames the player remembers touching something after they stop (so brief gaps don't count as leaving the ground).
+const CONTACT_MEMORY_FRAMES : u32 = 1;
 /// How fast the player moves in pixels per second.
 const PLAYER_SPEED : f32 = 120.0;
 
@@ -38,9 +55,116 @@ const MIN_JUMP_HEIGHT : f32 = 16.0;
 /// The min jump height.
 const MAX_JUMP_HEIGHT : f32 = 64.0 + 4.0;
 
+/// How close the player's "hands" need to be to a zipline to grab it.
+const ZIPLINE_GRAB_DISTANCE : f32 = 12.0;
+/// How far below a zipline the player's center hangs.
+const ZIPLINE_HANG_DISTANCE : f32 = 10.0;
+
 /// The speed to tranvel in a pneumatic pipe.
 const PNEUMATIC_PIPE_SPEED : f32 = 200.0;
 
+/// The most the sprite tilts to match the slope it's on (in radians, about 8 degrees).
+const MAX_SPRITE_TILT : f32 = 0.15;
+/// Slopes steeper than this (in radians from up) are more like walls, so the sprite stays upright on them.
+const MAX_TILTED_SLOPE : f32 = 0.8;
+/// How fast the sprite's tilt can change (in radians per second), so it eases over bumps and seams instead of snapping.
+const SPRITE_TILT_RATE : f32 = 0.8;
+
+/// The size of each of the player's images (in pixels).
+const SPRITE_FRAME_SIZE : f32 = 16.0;
+/// How fast the player has to be going along the ground to be shown walking (in pixels per second).
+const WALK_ANIMATION_MIN_SPEED : f32 = 10.0;
+/// How long each step of the walk is shown (in seconds).
+const WALK_FRAME_SECONDS : f32 = 0.12;
+
+/// How the player was moving when they first hit a ceiling during an update (so it can be put back if they're nudged around it).
+struct CeilingClip {
+	/// Where the player was when they hit it.
+	position : Vec2,
+	/// What was left of the update's movement after the hit.
+	movement : Vec2,
+	/// The normal of the ceiling that was hit.
+	normal : Vec2,
+	/// The velocity from gravity.
+	gravity_velocity : Vec2,
+	/// The velocity from jumping.
+	jump_velocity : Vec2,
+	/// The velocity from kicking off of a track.
+	kick_start_velocity : Vec2,
+	/// Whether the jump was done.
+	jump_done : bool,
+}
+
+/// Everything about the player that the simulation depends on (so not the display handles). See `Player::snapshot()`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerSnapshot {
+	/// The player's position.
+	position : Vec2,
+	/// Whether the jump input was used up.
+	jump_input_used : bool,
+	/// Whether the track snap input was used up.
+	track_input_used : bool,
+	/// Whether the player was on the track.
+	on_track : bool,
+	/// The player's health.
+	health : Health,
+	/// The player's knockback.
+	knockback : Knockback,
+	/// The player's stamina.
+	stamina : Stamina,
+	/// Where the player would respawn.
+	respawn_position : Vec2,
+	/// The acceleration due to gravity.
+	gravity_acceleration : Vec2,
+	/// The velocity due to gravity.
+	gravity_velocity : Vec2,
+	/// Whether the player was on the ground.
+	on_ground : bool,
+	/// The smoothed ground normal.
+	ground_normal : GroundNormal,
+	/// How fast the ground was moving.
+	#[cfg_attr(feature = "serialize", serde(default = "Vec2::zero"))]
+	ground_velocity : Vec2,
+	/// The velocity carried along from moving ground that was left.
+	#[cfg_attr(feature = "serialize", serde(default = "Vec2::zero"))]
+	carry_velocity : Vec2,
+	/// The velocity due to jumping.
+	jump_velocity : Vec2,
+	/// When the current jump started.
+	jump_start_time : f32,
+	/// The starting height of the current jump.
+	jump_start_height : f32,
+	/// Whether the current jump was done.
+	jump_done : bool,
+	/// The initial velocity of the last kick.
+	kick_start_velocity : Vec2,
+	/// When the last kick happened.
+	kick_start_time : f32,
+	/// When the player started charging a kick.
+	kick_charge_start_time : f32,
+	/// Whether the player was in a pneumatic pipe.
+	in_pneumatic_pipe : bool,
+	/// Whether the player was leaving a pneumatic pipe.
+	leaving_pneumatic_pipe : bool,
+	/// The rest of the pipe the player was going through.
+	remaining_pneumatic_pipe_path : Vec<Vec2>,
+	/// The zipline being ridden.
+	zipline_ride : Option<ZiplineRide>,
+	/// Where the player was for the last two updates.
+	display_position : InterpolatedPosition,
+	/// Which way the sprite was looking.
+	aiming_right : bool,
+	/// How far the sprite was tilted to match the slope.
+	#[cfg_attr(feature = "serialize", serde(default))]
+	tilt : f32,
+	/// How far the sprite was tilted as of the update before.
+	#[cfg_attr(feature = "serialize", serde(default))]
+	last_tilt : f32,
+	/// The state of the track button's latch (if it was turned on).
+	track_toggle : Option<HoldToggle>,
+}
+
 /// The player's data.
 pub struct Player {
 	/// The player's position. This is the center of the player.
@@ -54,14 +178,38 @@ pub struct Player {
 	/// Whether the the player is on the track.
 	on_track : bool,
 
+	/// How much damage the player can take.
+	pub health : Health,
+	/// Getting pushed back (and stunned) after being hit.
+	pub knockback : Knockback,
+	/// What holding onto tracks and kicking off of them uses up.
+	pub stamina : Stamina,
+
+	/// Where the player should come back at if they respawn.
+	pub respawn_position : Vec2,
+	/// All of the obstacles the player touched during the last update.
+	contacts : Vec<ObstacleContact>,
+	/// The parts of the player that interact differently with things (stomping with the feet, bumping with the head, getting hurt in the body).
+	hitboxes : Hitboxes,
+	/// The obstacles the player hit with their head while going up during the last update.
+	head_bumps : Vec<ObstacleContact>,
+	/// What the player touched over the last few updates (and the obstacles near them this update).
+	contact_cache : ContactCache,
+	/// All of the physics problems detected during the last update.
+	anomalies : Vec<PhysicsAnomaly>,
+
 	/// Current acceleration due to gravity.
 	pub gravity_acceleration : Vec2,
 	/// The current velocity due to gravity.
 	gravity_velocity : Vec2,
 	/// Whether was on ground last update.
 	on_ground : bool,
-	/// The most "upward" surface normal available.
-	last_surface_normal : Vec2,
+	/// The (smoothed) ground normal that movement is made relative to.
+	ground_normal : GroundNormal,
+	/// How fast the ground being stood on is moving (like a moving platform).
+	ground_velocity : Vec2,
+	/// The velocity carried along from moving ground after jumping (or walking) off of it. Lasts until landing.
+	carry_velocity : Vec2,
 
 	/// The velocity due to jumping.
 	jump_velocity : Vec2,
@@ -76,6 +224,8 @@ pub struct Player {
 	kick_start_velocity : Vec2,
 	/// When the last track kick happened.
 	kick_start_time : f32,
+	/// When the player started charging a kick on the track. Negative means not charging.
+	kick_charge_start_time : f32,
 
 	/// Whether the player is currently in a pneumatic pipe.
 	in_pneumatic_pipe : bool,
@@ -84,28 +234,40 @@ pub struct Player {
 	/// The current remaining pipe for the player to go through.
 	remaining_pneumatic_pipe_path : Vec<Vec2>,
 
+	/// The zipline being ridden (if any).
+	zipline_ride : Option<ZiplineRide>,
+
+	/// Where the player was for the last two updates (so they can be drawn smoothly between them).
+	display_position : InterpolatedPosition,
 	/// The display buffer for the player.
 	display : DisplayBuffer,
+	/// Picks which of the player's images is drawn.
+	animator : Animator,
 	/// The texture used to draw the player.
 	#[allow(dead_code)] // This should be stored, so it's clear where the instructional text comes from...
 	texture : DisplayTexture,
 	/// Whether the sprite should be looking to the right.
 	aiming_right : bool,
+	/// How far the sprite is tilted (counter-clockwise, in radians) to match the slope it's on. Purely visual.
+	tilt : f32,
+	/// How far the sprite was tilted as of the last update (so it can be drawn smoothly between them).
+	last_tilt : f32,
+	/// Whether the sprite flashes when hit.
+	flashing : bool,
+	/// What the sprite is tinted toward (and how strongly) when it isn't flashing.
+	sprite_tint : (Color, f32),
+	/// Latches the track button on a tap (instead of it needing to be held), if turned on.
+	track_toggle : Option<HoldToggle>,
 }
 
 impl Player {
-	pub fn new() -> Player {
+	pub fn new(tuning : &Tuning) -> Player {
 		let mut texture = DisplayTexture::new();
 		texture.load_from_url("player.png");
 		let mut display_buffer = DisplayBuffer::new(DisplayBufferType::IMAGES);
-		{
-			let mut editor = display_buffer.make_editor();
-			editor.add_image(
-				&Vec2::new(0.0, 0.0),
-				&Vec2::new(16.0, 16.0),
-				&Vec3::new(-8.0, -8.0, 0.0),
-			);
-		}
+		let mut animator = make_animator();
+		animator.play("idle");
+		animator.draw(&mut display_buffer);
 		display_buffer.set_texture(&texture);
 		Player {
 			position : Vec2::new(0.0, 0.0),
@@ -115,10 +277,23 @@ impl Player {
 
 			on_track : false,
 
+			health : Health::new(tuning.player_max_health),
+			knockback : Knockback::new(),
+			stamina : Stamina::new(tuning),
+
+			respawn_position : Vec2::new(0.0, 0.0),
+			contacts : Vec::new(),
+			hitboxes : Hitboxes::for_radius(PLAYER_RADIUS),
+			head_bumps : Vec::new(),
+			contact_cache : ContactCache::new(CONTACT_MEMORY_FRAMES),
+			anomalies : Vec::new(),
+
 			gravity_acceleration : Vec2::new(0.0, 0.0),
 			gravity_velocity : Vec2::new(0.0, 0.0),
 			on_ground : false,
-			last_surface_normal : Vec2::new(0.0, 0.0),
+			ground_normal : GroundNormal::new(),
+			ground_velocity : Vec2::new(0.0, 0.0),
+			carry_velocity : Vec2::new(0.0, 0.0),
 
 			jump_velocity : Vec2::new(0.0, 0.0),
 			jump_start_time : -1.0,
@@ -127,14 +302,41 @@ impl Player {
 
 			kick_start_velocity : Vec2::new(0.0, 0.0),
 			kick_start_time : -1.0,
+			kick_charge_start_time : -1.0,
 
 			in_pneumatic_pipe : false,
 			leaving_pneumatic_pipe : false,
 			remaining_pneumatic_pipe_path : Vec::new(),
 
+			zipline_ride : None,
+
+			display_position : InterpolatedPosition::new(&Vec2::zero()),
 			display : display_buffer,
+			animator,
 			texture,
 			aiming_right : true,
+			tilt : 0.0,
+			last_tilt : 0.0,
+			flashing : true,
+			sprite_tint : (Color::new(255, 255, 255, 255), 0.0),
+			track_toggle : None,
+		}
+	}
+
+	/// Sets whether the sprite flashes when hit.
+	pub fn set_flashing(&mut self, flashing : bool) {
+		self.flashing = flashing;
+	}
+
+	/// Sets what the sprite is tinted toward (and how strongly, from 0 to 1) when it isn't flashing from a hit.
+	pub fn set_sprite_tint(&mut self, color : &Color, amount : f32) {
+		self.sprite_tint = (color.clone(), amount);
+	}
+
+	/// Sets whether tapping the track button acts as holding it (until it's used or tapped again).
+	pub fn set_track_hold_toggle(&mut self, enabled : bool) {
+		if enabled != self.track_toggle.is_some() {
+			self.track_toggle = if enabled { Some(HoldToggle::new()) } else { None };
 		}
 	}
 
@@ -143,8 +345,263 @@ impl Player {
 		(2.0 * self.gravity_acceleration.length() * target_height).abs().sqrt()
 	}
 
+	/// Takes all of the contacts that were made during the last update.
+	pub fn take_contacts(&mut self) -> Vec<ObstacleContact> {
+		std::mem::replace(&mut self.contacts, Vec::new())
+	}
+
+	/// Takes the obstacles the player hit with their head while going up during the last update.
+	pub fn take_head_bumps(&mut self) -> Vec<ObstacleContact> {
+		std::mem::take(&mut self.head_bumps)
+	}
+
+	/// Gets where one of the player's hitboxes is: its center and radius.
+	pub fn get_hitbox(&self, kind : HitboxKind) -> (Vec2, f32) {
+		let hitbox = self.hitboxes.get(kind);
+		(hitbox.center(&self.position, &self.get_up()), hitbox.radius)
+	}
+
+	/// Gets the obstacle the player is standing on (or was, within the last few updates), if any.
+	pub fn get_standing_on(&self) -> Option<Index> {
+		self.contact_cache.standing_on(&self.get_up()).map(|contact| contact.source)
+	}
+
+	/// Takes all of the physics problems that were detected during the last update.
+	pub fn take_anomalies(&mut self) -> Vec<PhysicsAnomaly> {
+		std::mem::take(&mut self.anomalies)
+	}
+
+	/// Gets the size of the player's (circle) collider.
+	pub fn get_radius(&self) -> f32 {
+		PLAYER_RADIUS
+	}
+
+	/// Gets the player's current velocity (combining gravity, jumping, track kicks, and what was carried off of moving ground, but not walking).
+	pub fn get_velocity(&self) -> Vec2 {
+		self.gravity_velocity + self.jump_velocity + self.kick_start_velocity + self.carry_velocity
+	}
+
+	/// Whether the player is facing right (relative to which way is up).
+	pub fn is_aiming_right(&self) -> bool {
+		self.aiming_right
+	}
+
+	/// Gets how charged the current track kick is (from 0 to 1), or None if not charging one.
+	pub fn get_kick_charge(&self, current_time : f32, tuning : &Tuning) -> Option<f32> {
+		if !self.on_track || 0.0 > self.kick_charge_start_time {
+			return None;
+		}
+		Some(kick_charge(self.kick_charge_start_time, current_time, tuning.track_kick_charge_seconds))
+	}
+
+	/// Whether the player is riding a zipline.
+	pub fn is_on_zipline(&self) -> bool {
+		self.zipline_ride.is_some()
+	}
+
+	/// Gets where the player should be drawn, given how far between the last two updates to draw things (from 0 to 1).
+	pub fn get_display_position(&self, interpolation : f32) -> Vec2 {
+		self.display_position.get(interpolation)
+	}
+
+	/// Moves the sprite to where the player should be drawn, given how far between the last two updates to draw things (from 0 to 1).
+	pub fn sync_display(&mut self, interpolation : f32) {
+		let position = self.display_position.get(interpolation);
+		let tilt = self.last_tilt + (self.tilt - self.last_tilt) * interpolation;
+		self.animator.set_flip_x(!self.aiming_right);
+		self.animator.draw(&mut self.display);
+		self.display.set_transform(&self.animator.get_transform(&position).with_rotation(tilt).to_mat4());
+		let flash = if self.flashing { self.knockback.get_flash() } else { 0.0 };
+		if 0.0 < flash || 0.0 >= self.sprite_tint.1 {
+			self.display.set_tint(&Color::new(255, 255, 255, 255), flash);
+		} else {
+			self.display.set_tint(&self.sprite_tint.0, self.sprite_tint.1);
+		}
+	}
+
+	/// Picks the animation for what the player is doing (standing, walking, or in the air), and moves it along.
+	fn update_animation(&mut self, start_position : &Vec2, elapsed_seconds : f32) {
+		let up = self.get_up();
+		let across = Vec2::new(up.y, -up.x);
+		let speed = if 0.0 < elapsed_seconds { (self.position - start_position).dot(&across).abs() / elapsed_seconds } else { 0.0 };
+		let name = if self.on_track {
+			"idle"
+		} else if !self.on_ground {
+			"jump"
+		} else if WALK_ANIMATION_MIN_SPEED < speed {
+			"walk"
+		} else {
+			"idle"
+		};
+		self.animator.play(name);
+		self.animator.update(elapsed_seconds);
+	}
+
+	/// Eases the sprite's tilt toward matching the (smoothed) ground normal, or back upright when off the ground or on something too steep.
+	fn update_tilt(&mut self, elapsed_seconds : f32) {
+		self.tilt = eased_sprite_tilt(self.tilt, &self.get_up(), &self.ground_normal.get(), self.on_ground || self.on_track, elapsed_seconds);
+	}
+
+	/// Handles riding a zipline (if on one). Returns whether the player was on one, in which case nothing else should move them this update.
+	fn update_zipline(&mut self, elapsed_seconds : f32, jump_pressed : bool, track_pressed : bool, collision : &CollisionSystem, geometry : &TiledGeometry) -> bool {
+		let mut ride = match self.zipline_ride.take() {
+			Some(ride) => ride,
+			None => return false,
+		};
+		let zipline = &geometry.get_ziplines()[ride.zipline];
+		// Letting go (with jump or the track button) drops off with whatever speed the ride had.
+		let let_go = (jump_pressed && !self.jump_input_used) || (track_pressed && !self.track_input_used);
+		let mut detach = let_go;
+		if !let_go {
+			detach = ride.update(elapsed_seconds, zipline, &self.gravity_acceleration);
+		}
+		let target = zipline.get_point(ride.distance_along) - self.get_up() * ZIPLINE_HANG_DISTANCE;
+		let movement = target - self.position;
+		if let Some(hit) = collision.collide_circle_step(&self.position, PLAYER_RADIUS, &movement) {
+			// Ran into something, so fall off where it was hit.
+			self.position = hit.deflections[0].position;
+			detach = true;
+		} else {
+			self.position = target;
+		}
+		if 0.0 > movement.x {
+			self.aiming_right = false;
+		}
+		if 0.0 < movement.x {
+			self.aiming_right = true;
+		}
+
+		if detach {
+			let velocity = ride.get_velocity(zipline);
+			self.launch(&velocity);
+		} else {
+			self.zipline_ride = Some(ride);
+		}
+		self.jump_input_used = jump_pressed;
+		self.track_input_used = track_pressed;
+		true
+	}
+
+	/// Kills all of the player's velocity (from gravity, jumping, track kicks, and moving ground).
+	fn stop(&mut self) {
+		self.gravity_velocity = Vec2::new(0.0, 0.0);
+		self.jump_velocity = Vec2::new(0.0, 0.0);
+		self.kick_start_velocity = Vec2::new(0.0, 0.0);
+		self.ground_velocity = Vec2::new(0.0, 0.0);
+		self.carry_velocity = Vec2::new(0.0, 0.0);
+		self.jump_done = true;
+	}
+
+	/// Puts the player somewhere new (as in when moving to a new room), dropping anything they were doing. That spot becomes where they respawn too.
+	pub fn place(&mut self, position : &Vec2) {
+		self.stop();
+		self.position = position.clone();
+		self.display_position.snap(position);
+		self.tilt = 0.0;
+		self.last_tilt = 0.0;
+		self.respawn_position = position.clone();
+		self.knockback.cancel();
+		self.stamina.refill();
+		self.on_ground = false;
+		self.contact_cache.clear();
+		self.on_track = false;
+		self.zipline_ride = None;
+		self.in_pneumatic_pipe = false;
+		self.leaving_pneumatic_pipe = false;
+		self.remaining_pneumatic_pipe_path.clear();
+	}
+
+	/// Captures everything about the player that the simulation depends on.
+	pub fn snapshot(&self) -> PlayerSnapshot {
+		PlayerSnapshot {
+			position : self.position,
+			jump_input_used : self.jump_input_used,
+			track_input_used : self.track_input_used,
+			on_track : self.on_track,
+			health : self.health.clone(),
+			knockback : self.knockback.clone(),
+			stamina : self.stamina.clone(),
+			respawn_position : self.respawn_position,
+			gravity_acceleration : self.gravity_acceleration,
+			gravity_velocity : self.gravity_velocity,
+			on_ground : self.on_ground,
+			ground_normal : self.ground_normal.clone(),
+			ground_velocity : self.ground_velocity,
+			carry_velocity : self.carry_velocity,
+			jump_velocity : self.jump_velocity,
+			jump_start_time : self.jump_start_time,
+			jump_start_height : self.jump_start_height,
+			jump_done : self.jump_done,
+			kick_start_velocity : self.kick_start_velocity,
+			kick_start_time : self.kick_start_time,
+			kick_charge_start_time : self.kick_charge_start_time,
+			in_pneumatic_pipe : self.in_pneumatic_pipe,
+			leaving_pneumatic_pipe : self.leaving_pneumatic_pipe,
+			remaining_pneumatic_pipe_path : self.remaining_pneumatic_pipe_path.clone(),
+			zipline_ride : self.zipline_ride.clone(),
+			display_position : self.display_position.clone(),
+			aiming_right : self.aiming_right,
+			tilt : self.tilt,
+			last_tilt : self.last_tilt,
+			track_toggle : self.track_toggle.clone(),
+		}
+	}
+
+	/// Puts the player back the way a snapshot had them. The display catches up on the next `sync_display()`.
+	pub fn restore(&mut self, snapshot : &PlayerSnapshot) {
+		self.position = snapshot.position;
+		self.jump_input_used = snapshot.jump_input_used;
+		self.track_input_used = snapshot.track_input_used;
+		self.on_track = snapshot.on_track;
+		self.health = snapshot.health.clone();
+		self.knockback = snapshot.knockback.clone();
+		self.stamina = snapshot.stamina.clone();
+		self.respawn_position = snapshot.respawn_position;
+		self.contacts.clear();
+		self.contact_cache.clear();
+		self.anomalies.clear();
+		self.gravity_acceleration = snapshot.gravity_acceleration;
+		self.gravity_velocity = snapshot.gravity_velocity;
+		self.on_ground = snapshot.on_ground;
+		self.ground_normal = snapshot.ground_normal.clone();
+		self.ground_velocity = snapshot.ground_velocity;
+		self.carry_velocity = snapshot.carry_velocity;
+		self.jump_velocity = snapshot.jump_velocity;
+		self.jump_start_time = snapshot.jump_start_time;
+		self.jump_start_height = snapshot.jump_start_height;
+		self.jump_done = snapshot.jump_done;
+		self.kick_start_velocity = snapshot.kick_start_velocity;
+		self.kick_start_time = snapshot.kick_start_time;
+		self.kick_charge_start_time = snapshot.kick_charge_start_time;
+		self.in_pneumatic_pipe = snapshot.in_pneumatic_pipe;
+		self.leaving_pneumatic_pipe = snapshot.leaving_pneumatic_pipe;
+		self.remaining_pneumatic_pipe_path = snapshot.remaining_pneumatic_pipe_path.clone();
+		self.zipline_ride = snapshot.zipline_ride.clone();
+		self.display_position = snapshot.display_position.clone();
+		self.aiming_right = snapshot.aiming_right;
+		self.tilt = snapshot.tilt;
+		self.last_tilt = snapshot.last_tilt;
+		self.track_toggle = snapshot.track_toggle.clone();
+	}
+
+	/// Launches the player away from the ground at some speed (as in when bouncing off of something).
+	pub fn bounce(&mut self, speed : f32) {
+		self.stop();
+		if EPSILON < self.gravity_acceleration.length() {
+			self.gravity_velocity = self.gravity_acceleration.norm().scale(-speed);
+		}
+	}
+
 	/// The fuction that updates the player's position and movement.
-	pub fn update(&mut self, current_time : f32, elapsed_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad, collision : &CollisionSystem, geometry : &TiledGeometry) {
+	pub fn update(&mut self, current_time : f32, elapsed_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad, collision : &CollisionSystem, geometry : &TiledGeometry, tuning : &Tuning) {
+		self.contacts.clear();
+		self.head_bumps.clear();
+		self.contact_cache.begin_frame();
+		self.anomalies.clear();
+		self.last_tilt = self.tilt;
+		let start_position = self.position.clone();
+		let knockback_movement = self.knockback.update(elapsed_seconds);
+		let stunned = self.knockback.is_stunned();
 
 		// If in a pneumatic pipe, then just don't do anything.
 		if self.in_pneumatic_pipe {
@@ -164,7 +621,7 @@ impl Player {
 					}
 				} else {
 					// No points left means you're done with this pipe.
-					log("Leaving pneumatic pipe.");
+					debug_log!("Leaving pneumatic pipe.");
 					self.in_pneumatic_pipe = false;
 					self.leaving_pneumatic_pipe = true;
 					break;
@@ -175,17 +632,40 @@ impl Player {
 			}
 
 			// Store the new position and done.
-			{
-				let mut transform = Mat4::new();
-				transform.translate_before(&Vec3::new(self.position.x, self.position.y, 0.0));
-				if !self.aiming_right {
-					transform.scale_before(&Vec3::new(-1.0, 1.0, 1.0));
-				}
-				self.display.set_transform(&transform);
+			self.display_position.push(&self.position);
+			return;
+		}
+
+		// If on a zipline, then it does all the moving.
+		// Being stunned by a hit ignores all inputs.
+		let jump_pressed = !stunned && (gamepad.is_down(Button::A) || keyboard.is_down(Key::UP));
+		let mut track_pressed = gamepad.is_down(Button::R) || keyboard.is_down(Key::SPACE);
+		if let Some(toggle) = &mut self.track_toggle {
+			if self.track_input_used {
+				toggle.release(); // The last tap was used up.
 			}
+			track_pressed = toggle.update(track_pressed);
+		}
+		let track_pressed = !stunned && track_pressed;
+		if self.update_zipline(elapsed_seconds, jump_pressed, track_pressed, collision, geometry) {
+			self.display_position.push(&self.position);
 			return;
 		}
 
+		// Grabbing a zipline takes priority over the track.
+		if track_pressed && !self.track_input_used && !self.on_track {
+			let hands = self.position + self.get_up() * ZIPLINE_HANG_DISTANCE;
+			if let Some(index) = geometry.find_closest_zipline(&hands, ZIPLINE_GRAB_DISTANCE) {
+				debug_log!("Grabbed zipline.");
+				self.zipline_ride = Some(ZiplineRide::start(index, &geometry.get_ziplines()[index], &hands, &self.get_velocity()));
+				self.stop();
+				self.on_ground = false;
+				self.track_input_used = true;
+				self.display_position.push(&self.position);
+				return;
+			}
+		}
+
 		let debug = keyboard.is_down(Key::DEBUG);
 
 		let gravity_set = EPSILON < self.gravity_acceleration.length();
@@ -210,14 +690,20 @@ impl Player {
 			input_direction.x += 1.0;
 			input_scale = 1.0;
 		}
+		if stunned {
+			input_direction = Vec2::new(0.0, 0.0);
+			input_scale = 0.0;
+		}
 		if EPSILON < input_direction.length() {
 			(&mut input_direction).norm();
 		}
 
 		// Generate a sane movement the player is trying to add to the movement based on the above input(s).
+		// Speed boost sections of track make this faster.
+		let speed = if self.on_track { PLAYER_SPEED * geometry.get_track_speed_multiplier(&self.position) } else { PLAYER_SPEED };
 		let input_movement = if 0.0 < input_direction.length() {
 			(&mut input_direction).norm();
-			input_direction.set_length(input_scale * elapsed_seconds * PLAYER_SPEED)
+			input_direction.set_length(input_scale * elapsed_seconds * speed)
 		} else {
 			Vec2::new(0.0, 0.0)
 		};
@@ -230,7 +716,6 @@ impl Player {
 		// Handle jumping.
 		// This overrides gravity.
 		let gravity_direction = if gravity_set { self.gravity_acceleration.norm() } else { Vec2::new(0.0, 0.0) };
-		let jump_pressed = gamepad.is_down(Button::A) || keyboard.is_down(Key::UP);
 		if jump_pressed && gravity_active {
 			let height = -self.position.dot(gravity_direction);
 			if self.on_ground && !self.jump_input_used {
@@ -268,8 +753,22 @@ impl Player {
 		}
 
 		// Handle track jumping.
-		let kick_velocity = if self.on_track && jump_pressed && !self.jump_input_used && gravity_set {
+		// Holding jump on a track charges up a kick, which happens when it's released. Without the stamina for it, the player just lets go.
+		if self.on_track && jump_pressed && !self.jump_input_used && gravity_set && 0.0 > self.kick_charge_start_time {
+			self.kick_charge_start_time = current_time;
+		}
+		if !self.on_track {
+			self.kick_charge_start_time = -1.0;
+		}
+		let kick_velocity = if self.on_track && !jump_pressed && 0.0 <= self.kick_charge_start_time {
+			let charge = self.get_kick_charge(current_time, tuning).unwrap_or(1.0);
+			let strength = if self.stamina.try_kick(tuning) {
+				tuning.track_kick_min_strength + (1.0 - tuning.track_kick_min_strength) * charge
+			} else {
+				0.0
+			};
 			self.on_track = false;
+			self.kick_charge_start_time = -1.0;
 			let mut kick_direction = input_direction.clone();
 			if EPSILON > kick_direction.length() {
 				kick_direction.y = 1.0; // Default to straight up if nothing else.
@@ -278,9 +777,10 @@ impl Player {
 				let vertical = kick_direction.dot(&gravity_direction);
 				let ortho = gravity_direction.ortho();
 				let horizontal = kick_direction.dot(&ortho);
-				self.kick_start_velocity =
+				self.kick_start_velocity = (
 					gravity_direction * vertical * TRACK_KICK_VERTICAL_START_SPEED +
-					ortho * horizontal * TRACK_KICK_HORIZONTAL_START_SPEED;
+					ortho * horizontal * TRACK_KICK_HORIZONTAL_START_SPEED
+				) * strength;
 			}
 			self.kick_start_time = current_time;
 			self.jump_input_used = true;
@@ -297,18 +797,34 @@ impl Player {
 			}
 		};
 
+		// Holding the track button near a track pulls the player toward it.
+		let magnet_movement = if track_pressed && !self.track_input_used && !self.on_track && !self.in_pneumatic_pipe && self.stamina.can_attach() {
+			let offset = geometry.get_closest_track_point(&self.position) - self.position;
+			let distance = offset.length();
+			if EPSILON < distance && distance <= tuning.track_magnet_distance {
+				offset.set_length(distance.min(tuning.track_magnet_speed * elapsed_seconds))
+			} else {
+				Vec2::new(0.0, 0.0)
+			}
+		} else {
+			Vec2::new(0.0, 0.0)
+		};
+
 		// Now repeatedly alternate between collision detection and responding by modifying forces.
-		let track_pressed = gamepad.is_down(Button::R) || keyboard.is_down(Key::SPACE);
 		let mut remainder_percent = 1.0;
 		let mut normals : Vec<Vec2> = Vec::new();
-		let mut next_surface_normal : Vec2 = Vec2::new(0.0, 0.0);
+		let was_on_ground = self.on_ground;
+		let left_ground_velocity = self.ground_velocity;
+		let mut ceiling_clip : Option<CeilingClip> = None;
 		self.on_ground = false; // Off the ground until proven otherwise.
-		for _iteration in 0..PHYSICS_ITERATION_MAX {
+		self.ground_velocity = Vec2::new(0.0, 0.0);
+		let iteration_budget = tuning.physics_iteration_budget.max(1);
+		for _iteration in 0..iteration_budget {
 			// First calculate the projected movement.
-			let mut total_movement = (self.gravity_velocity + self.jump_velocity + kick_velocity) * elapsed_seconds;
+			let mut total_movement = (self.gravity_velocity + self.jump_velocity + kick_velocity + self.carry_velocity) * elapsed_seconds + magnet_movement + knockback_movement;
 			if !self.on_track {
 				// Make the movements relative to the last surface normal.
-				let mut up = self.last_surface_normal;
+				let mut up = self.ground_normal.get();
 				if EPSILON > up.length() {
 					up.y = 1.0; // Default to normal up if none set yet.
 				}
@@ -321,23 +837,26 @@ impl Player {
 				total_movement += input_movement;
 			}
 			total_movement *= remainder_percent;
-			if debug { log(&format!("total_movement: {:?}", total_movement)); }
+			if debug { debug_log!("total_movement: {:?}", total_movement); }
 
 			// Remove any movement that goes against a surface normal from the previous iteration.
 			total_movement = limit_movement_with_normals(&total_movement, &normals);
 			normals.clear();
-			if debug { log(&format!("total_movement after normals: {:?}", total_movement)); }
+			if debug { debug_log!("total_movement after normals: {:?}", total_movement); }
 
 			// Give up early if (basically) no movement left.
 			if EPSILON > total_movement.length() {
 				break;
 			}
 
-			// Check how that works with collision.
-			let maybe_collision = collision.collide_circle_step(
+			// Check how that works with collision. Only what actually deflects matters for the movement (what's just being touched is gathered below).
+			// One-way platforms are left out of this while rising up through them (or when only partway up at the top of a jump), so the player only lands on them on the way back down.
+			let maybe_collision = collision.collide_circle_query_among(
 				&self.position,
 				PLAYER_RADIUS,
 				&total_movement,
+				&CircleQuery { include_skimming : false, ..CircleQuery::new() },
+				Some(self.contact_cache.nearby(collision, &self.position, PLAYER_RADIUS, &total_movement)),
 			);/*
 			let maybe_collision = {
 				let possible = collision.collide_circle_step(
@@ -358,41 +877,77 @@ impl Player {
 					possible
 				}
 			};*/
-			if debug { log(&format!("collision: {:?}", maybe_collision)); }
+			if debug { debug_log!("collision: {:?}", maybe_collision); }
+
+			// Small lips near the feet (like where merged tile rectangles don't quite line up) are stepped up onto while walking, rather than stopping the player dead.
+			if maybe_collision.is_some() && was_on_ground && gravity_active && EPSILON >= total_movement.dot(&self.get_up()) {
+				if let Some(landing) = find_step_up(collision, &self.position, PLAYER_RADIUS, &total_movement, &self.get_up(), tuning.max_step_height) {
+					if debug { debug_log!("Stepped up to: {:?}", landing); }
+					self.position = landing;
+					self.on_ground = true;
+					break;
+				}
+			}
+
+			// Also find what's being touched (without being pushed into) where any hit happened, since standing on things counts too.
+			let skimming = match &maybe_collision {
+				Some(hit) if EPSILON < hit.deflections[0].remainder.length() => collision.contacts_only(&hit.deflections[0].position, PLAYER_RADIUS, &hit.deflections[0].remainder),
+				_ => Vec::new(),
+			};
 
 			// If there is a collision, interact with it.
 			let mut safe_movement = total_movement.clone();
 			if let Some(collision) = &maybe_collision {
 				let safe_percent = 0.0f32.max(collision.deflections[0].times.min().unwrap());
-				if debug { log(&format!("Safe percent: {}", safe_percent)); }
+				if debug { debug_log!("Safe percent: {}", safe_percent); }
 				safe_movement *= safe_percent;
 				remainder_percent *= 1.0 - safe_percent;
 
 				// Save the normals.
 				normals = collision.normals.clone();
 
+				let touching : Vec<&Deflection> = collision.deflections.iter().chain(skimming.iter()).collect();
+
+				// Record what was touched (and what the head hit on the way up).
+				let up = self.get_up();
+				let rising = 0.0 < total_movement.dot(&up);
+				for deflection in &touching {
+					let contact = ObstacleContact {
+						source : deflection.source,
+						normal : deflection.normal.clone(),
+					};
+					let point = deflection.position - deflection.normal * PLAYER_RADIUS;
+					if rising && deflection.deflected && Some(HitboxKind::Head) == self.hitboxes.classify(&point, &deflection.position, &up) {
+						self.head_bumps.push(contact.clone());
+					}
+					self.contacts.push(contact);
+					self.contact_cache.touch(deflection.source, &deflection.normal);
+				}
+
 				// See how the collision might update the on_ground and hit_ceiling flags.
 				let mut on_ground = false;
 				let mut hit_ceiling = false;
+				let mut ceiling_normal = Vec2::zero();
 				let threshold = -0.65;
 				// Threshold is below "sqrt(2) / 2" (0.7071) so can handle anything within 45 degrees.
 				if gravity_set {
-					for deflection in &collision.deflections {
+					for deflection in &touching {
 						let coincidence = deflection.normal.dot(&gravity_direction);
 						if threshold > coincidence {
 							on_ground = true;
 						}
 						if -threshold < coincidence {
 							hit_ceiling = true;
+							ceiling_normal = deflection.normal;
 						}
-						// Want the most negative one.
-						if 0.0 > coincidence && next_surface_normal.dot(&gravity_direction) > coincidence {
-							next_surface_normal = deflection.normal.clone();
-						}
+						// Gather everything ground-ish (the smoother weights and filters them).
+						self.ground_normal.add_sample(&deflection.normal, &gravity_direction.scale(-1.0));
 					}
 				}
 				if on_ground {
-					if debug { log("On ground!"); }
+					if debug { debug_log!("On ground!"); }
+					self.ground_velocity = collision.carry_velocity;
+					self.carry_velocity = Vec2::new(0.0, 0.0);
 					self.gravity_velocity.x = 0.0;
 					self.gravity_velocity.y = 0.0;
 					self.jump_velocity.x = 0.0;
@@ -401,11 +956,23 @@ impl Player {
 					self.kick_start_velocity.y = 0.0;
 				}
 				if hit_ceiling {
+					if ceiling_clip.is_none() {
+						ceiling_clip = Some(CeilingClip {
+							position : self.position + safe_movement,
+							movement : total_movement - safe_movement,
+							normal : ceiling_normal,
+							gravity_velocity : self.gravity_velocity,
+							jump_velocity : self.jump_velocity,
+							kick_start_velocity : self.kick_start_velocity,
+							jump_done : self.jump_done,
+						});
+					}
 					self.gravity_velocity.x = 0.0; // Might remove this part?
 					self.gravity_velocity.y = 0.0;
 					self.jump_velocity.x = 0.0;
 					self.jump_velocity.y = 0.0;
 					self.kick_start_velocity.y = 0.0;
+					self.carry_velocity = Vec2::new(0.0, 0.0);
 					self.jump_done = true;
 				}
 				self.on_ground |= on_ground;
@@ -416,7 +983,7 @@ impl Player {
 				// If trying to leave the pipe, then don't hit it again.
 				if !self.leaving_pneumatic_pipe {
 					let (_new_position, start_at_start, pipe) = hit_info;
-					log("Starting pneumatic pipe.");
+					debug_log!("Starting pneumatic pipe.");
 					self.in_pneumatic_pipe = true;
 					self.remaining_pneumatic_pipe_path = pipe.get_path().clone();
 					if !start_at_start { self.remaining_pneumatic_pipe_path.reverse(); }
@@ -430,7 +997,9 @@ impl Player {
 			// If the player is trying to snap, then try to collide any safe movement with tracks to see if can snap.
 			// Also check if the starting position is just close enough.
 			if track_pressed && !self.track_input_used && !self.in_pneumatic_pipe {
-				if !self.on_track {
+				if !self.on_track && !self.stamina.can_attach() {
+					// Too exhausted to grab on.
+				} else if !self.on_track {
 					// Try snapping if possible.
 					let closest = geometry.get_closest_track_point(&self.position);
 					if MAX_TRACK_SNAP_DISTANCE >= (closest - self.position).length() {
@@ -441,6 +1010,7 @@ impl Player {
 						self.jump_velocity.y = 0.0;
 						self.kick_start_velocity.x = 0.0;
 						self.kick_start_velocity.y = 0.0;
+						self.carry_velocity = Vec2::new(0.0, 0.0);
 						self.track_input_used = true;
 						self.on_track = true;
 						break; // Ignore any movement after that.
@@ -454,6 +1024,7 @@ impl Player {
 						self.jump_velocity.y = 0.0;
 						self.kick_start_velocity.x = 0.0;
 						self.kick_start_velocity.y = 0.0;
+						self.carry_velocity = Vec2::new(0.0, 0.0);
 						self.track_input_used = true;
 						self.on_track = true;
 						// Don't break, allow any remaining movement to be worked out.
@@ -488,20 +1059,183 @@ impl Player {
 				break;
 			}
 
-			if PHYSICS_ITERATION_MAX-1 == _iteration {
+			if iteration_budget-1 == _iteration {
 				log("Hit player physics iteration max!");
+				self.anomalies.push(PhysicsAnomaly {
+					kind : AnomalyKind::IterationMax,
+					details : format!("{} of the movement left after {} iterations (escape: {:?})", remainder_percent, iteration_budget, tuning.iteration_escape),
+				});
+				if IterationEscape::DepenetrateAndStop == tuning.iteration_escape {
+					self.position = collision.push_out_circle(&self.position, PLAYER_RADIUS);
+					self.stop();
+				}
 			}
 		}
-		self.last_surface_normal = next_surface_normal;
 
-		// Store the new position.
-		{
-			let mut transform = Mat4::new();
-			transform.translate_before(&Vec3::new(self.position.x, self.position.y, 0.0));
-			if !self.aiming_right {
-				transform.scale_before(&Vec3::new(-1.0, 1.0, 1.0));
+		// Clipping the edge of a ceiling by a little while going up nudges the player sideways around it, rather than ending the jump.
+		// This picks up from where the ceiling was hit, with only the movement that was left then (replacing the sliding along the ceiling that happened instead).
+		if let Some(clip) = ceiling_clip {
+			if !self.on_ground && !self.on_track && !self.in_pneumatic_pipe {
+				if let Some(corrected) = find_corner_correction(collision, &clip.position, PLAYER_RADIUS, &clip.movement, &self.get_up(), &clip.normal, tuning.corner_correction_window) {
+					if debug { debug_log!("Corner corrected to: {:?}", corrected); }
+					self.position = corrected;
+					self.head_bumps.clear(); // Slipped past it, so it wasn't really hit.
+					self.gravity_velocity = clip.gravity_velocity;
+					self.jump_velocity = clip.jump_velocity;
+					self.kick_start_velocity = clip.kick_start_velocity;
+					self.jump_done = clip.jump_done;
+				}
 			}
-			self.display.set_transform(&transform);
 		}
+		self.ground_normal.finish(elapsed_seconds);
+		self.update_tilt(elapsed_seconds);
+
+		// Briefly losing contact with the ground (like crossing a seam) doesn't count as leaving it, unless moving away from it.
+		if !self.on_ground && gravity_set && 0.0 >= self.get_velocity().dot(&self.get_up()) && self.contact_cache.is_grounded(&self.get_up()) {
+			self.on_ground = true;
+			self.ground_velocity = left_ground_velocity;
+		}
+
+		// Leaving moving ground (by jumping or walking off of it) keeps going with it until landing again.
+		if was_on_ground && !self.on_ground && !self.on_track {
+			self.carry_velocity = left_ground_velocity;
+		}
+
+		// Holding onto a track uses up stamina, and running out knocks the player off. It only comes back on the ground.
+		if self.on_track && self.stamina.drain_attached(elapsed_seconds, tuning) {
+			debug_log!("Exhausted, so let go of the track.");
+			self.on_track = false;
+			self.kick_charge_start_time = -1.0;
+		}
+		self.stamina.update(elapsed_seconds, self.on_ground, tuning);
+
+		// Check if anything went wrong, and try to recover from it rather than letting it spread.
+		if !self.position.is_finite() || !self.get_velocity().is_finite() {
+			self.anomalies.push(PhysicsAnomaly {
+				kind : AnomalyKind::InvalidState,
+				details : format!("Position became {:?} with velocity {:?}", self.position, self.get_velocity()),
+			});
+			self.position = start_position;
+			self.stop();
+		} else if collision.find_obstacles_near(&self.position, PLAYER_RADIUS - PENETRATION_TOLERANCE).iter().any(|index| collision.obstacles[*index].one_way.is_none()) { // Overlapping one-way platforms is just passing through them.
+			self.anomalies.push(PhysicsAnomaly {
+				kind : AnomalyKind::Penetration,
+				details : String::from("Ended update overlapping collision geometry"),
+			});
+			self.position = collision.push_out_circle(&self.position, PLAYER_RADIUS);
+		}
+
+		self.update_animation(&start_position, elapsed_seconds);
+
+		// Store the new position.
+		self.display_position.push(&self.position);
+	}
+}
+
+/// Creates the animations that the player can be drawn with.
+///
+/// They're all made from the one image in "player.png" for now (walking just bobs it up and down), so adding real frames to it only means changing these.
+fn make_animator() -> Animator {
+	let size = Vec2::new(SPRITE_FRAME_SIZE, SPRITE_FRAME_SIZE);
+	let mut animator = Animator::new();
+	animator.add("idle", SpriteAnimation::from_strip(&Vec2::zero(), &size, 1, 1.0, AnimationMode::Loop));
+	let mut walk = SpriteAnimation::new(AnimationMode::Loop);
+	walk.add_frame(SpriteFrame::new(&Vec2::zero(), &size, WALK_FRAME_SECONDS));
+	walk.add_frame(SpriteFrame::new(&Vec2::zero(), &size, WALK_FRAME_SECONDS).with_nudge(&Vec2::new(0.0, 1.0)));
+	animator.add("walk", walk);
+	animator.add("jump", SpriteAnimation::from_strip(&Vec2::zero(), &size, 1, 1.0, AnimationMode::Once));
+	animator
+}
+
+impl TileBehaviorTarget for Player {
+	fn get_up(&self) -> Vec2 {
+		if EPSILON < self.gravity_acceleration.length() {
+			(&self.gravity_acceleration).norm().scale(-1.0)
+		} else {
+			Vec2::new(0.0, 1.0)
+		}
+	}
+
+	fn launch(&mut self, velocity : &Vec2) {
+		self.gravity_velocity = Vec2::new(0.0, 0.0);
+		self.kick_start_velocity = Vec2::new(0.0, 0.0);
+		self.jump_velocity = velocity.clone();
+		self.jump_done = true; // So holding jump doesn't override the launch.
+		self.on_ground = false;
+		self.contact_cache.clear();
+		self.on_track = false;
+	}
+
+	fn set_respawn_point(&mut self, position : &Vec2) {
+		self.respawn_position = position.clone();
+	}
+}
+
+impl ShiftOrigin for Player {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+		self.respawn_position += shift;
+		self.remaining_pneumatic_pipe_path.shift_origin(shift);
+		self.display_position.shift_origin(shift);
+		// Jump heights are measured along gravity, so only the part of the shift along it matters.
+		if EPSILON < self.gravity_acceleration.length() {
+			self.jump_start_height -= shift.dot(self.gravity_acceleration.norm());
+		}
+	}
+}
+
+/// Gets how charged a track kick is (from 0 to 1), given when charging started, the current time, and how long a full charge takes (all in seconds).
+pub fn kick_charge(start_time : f32, current_time : f32, charge_seconds : f32) -> f32 {
+	if 0.0 >= charge_seconds {
+		return 1.0;
+	}
+	((current_time - start_time) / charge_seconds).max(0.0).min(1.0)
+}
+
+/// Gets the sprite's next tilt (in radians, counter-clockwise), easing it from its current tilt toward matching the ground normal. Goes back upright when not grounded, or when the ground is too steep.
+pub fn eased_sprite_tilt(tilt : f32, up : &Vec2, ground_normal : &Vec2, grounded : bool, elapsed_seconds : f32) -> f32 {
+	let mut target = 0.0;
+	if grounded && EPSILON < ground_normal.length() {
+		let slope = up.ext(ground_normal).atan2(up.dot(ground_normal));
+		if slope.abs() <= MAX_TILTED_SLOPE {
+			target = slope.max(-MAX_SPRITE_TILT).min(MAX_SPRITE_TILT);
+		}
+	}
+	let max_change = SPRITE_TILT_RATE * elapsed_seconds;
+	tilt + (target - tilt).max(-max_change).min(max_change)
+}
+
+#[cfg(test)]
+mod tests_player {
+	use super::*;
+
+	#[test]
+	fn kick_charging() {
+		assert_eq!(kick_charge(2.0, 2.0, 0.5), 0.0);
+		assert_eq!(kick_charge(2.0, 2.25, 0.5), 0.5);
+		assert_eq!(kick_charge(2.0, 10.0, 0.5), 1.0); // Holding longer doesn't overcharge.
+		assert_eq!(kick_charge(2.0, 1.0, 0.5), 0.0); // Time going backwards (like a rewind) doesn't go negative.
+		assert_eq!(kick_charge(2.0, 2.0, 0.0), 1.0); // No charge time means always fully charged.
+	}
+
+	#[test]
+	fn sprite_tilting() {
+		let up = Vec2::new(0.0, 1.0);
+		/// The ground normal on a slope rising to the right by some angle.
+		fn slope(angle : f32) -> Vec2 { Vec2::new(-angle.sin(), angle.cos()) }
+		let approx = |value : f32, expected : f32| assert!((value - expected).abs() < 0.0001, "{} isn't {}", value, expected);
+		// Gentle slopes are matched exactly (given time to ease over).
+		approx(eased_sprite_tilt(0.0, &up, &slope(0.1), true, 10.0), 0.1);
+		approx(eased_sprite_tilt(0.0, &up, &slope(-0.1), true, 10.0), -0.1);
+		// Steeper ones only up to the limit, and wall-like ones not at all.
+		approx(eased_sprite_tilt(0.0, &up, &slope(0.5), true, 10.0), MAX_SPRITE_TILT);
+		approx(eased_sprite_tilt(0.1, &up, &slope(1.2), true, 10.0), 0.0);
+		// Back upright in the air (or without a ground normal).
+		approx(eased_sprite_tilt(0.1, &up, &slope(0.1), false, 10.0), 0.0);
+		approx(eased_sprite_tilt(0.1, &up, &Vec2::new(0.0, 0.0), true, 10.0), 0.0);
+		// It eases there rather than snapping.
+		approx(eased_sprite_tilt(0.0, &up, &slope(0.1), true, 0.05), 0.04);
+		// Works the same when gravity points another way.
+		approx(eased_sprite_tilt(0.0, &Vec2::new(1.0, 0.0), &Vec2::new(0.995, 0.0998), true, 10.0), 0.1);
 	}
 }
@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+/// How full a pool is, and how often it's had to grow (for spotting pools that are sized too small).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolStats {
+	/// What the pool holds.
+	pub name : &'static str,
+	/// How many instances it has (live or spare).
+	pub capacity : usize,
+	/// How many are in use.
+	pub live : usize,
+	/// The most that have been in use at once.
+	pub peak : usize,
+	/// How many instances had to be made after it was created (because it ran out of spares).
+	pub grown : usize,
+}
+
+impl PoolStats {
+	/// Describes the stats as a JSON object.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(self).unwrap_or_else(|error| crate::fatal!("Couldn't write out pool stats: {}", error))
+	}
+}
+
+/// A set of reusable instances: despawned ones are kept as spares and handed back out by `spawn()`, so things that come and go often (like projectiles) don't keep allocating.
+///
+/// The live instances are always at the front (in `as_slice()`), so iterating them is as cheap as a Vec. Despawning swaps the last live one into the gap, so order isn't kept.
+pub struct Pool<T> {
+	/// What the pool holds (for its stats).
+	name : &'static str,
+	/// Every instance. The first `live` of them are in use, the rest are spares.
+	items : Vec<T>,
+	/// How many are in use.
+	live : usize,
+	/// The most that have been in use at once.
+	peak : usize,
+	/// How many instances had to be made after creation.
+	grown : usize,
+	/// Makes a new (blank) instance.
+	make : fn() -> T,
+}
+
+impl<T> Pool<T> {
+	/// Creates a pool with some instances made up front.
+	pub fn new(name : &'static str, capacity : usize, make : fn() -> T) -> Pool<T> {
+		let mut items = Vec::with_capacity(capacity);
+		for _ in 0..capacity {
+			items.push(make());
+		}
+		Pool {
+			name,
+			items,
+			live : 0,
+			peak : 0,
+			grown : 0,
+			make,
+		}
+	}
+
+	/// Takes an instance to use (a spare if there is one, otherwise a new one). It still has whatever values it had when it was despawned, so the caller should set everything.
+	pub fn spawn(&mut self) -> &mut T {
+		if self.live == self.items.len() {
+			self.items.push((self.make)());
+			self.grown += 1;
+		}
+		self.live += 1;
+		self.peak = self.peak.max(self.live);
+		&mut self.items[self.live - 1]
+	}
+
+	/// Puts an instance back (by its index among the live ones). The last live instance takes its place.
+	pub fn despawn(&mut self, index : usize) {
+		crate::lean_assert!(index < self.live, "Despawned pool index {} with only {} live", index, self.live);
+		self.live -= 1;
+		self.items.swap(index, self.live);
+	}
+
+	/// Despawns every live instance the function returns false for.
+	pub fn retain<F : FnMut(&mut T) -> bool>(&mut self, mut keep : F) {
+		let mut index = 0;
+		while index < self.live {
+			if keep(&mut self.items[index]) {
+				index += 1;
+			} else {
+				self.despawn(index);
+			}
+		}
+	}
+
+	/// Despawns everything.
+	pub fn clear(&mut self) {
+		self.live = 0;
+	}
+
+	/// How many are in use.
+	pub fn len(&self) -> usize {
+		self.live
+	}
+
+	/// Whether none are in use.
+	pub fn is_empty(&self) -> bool {
+		0 == self.live
+	}
+
+	/// Gets the live instances.
+	pub fn as_slice(&self) -> &[T] {
+		&self.items[..self.live]
+	}
+
+	/// Gets the live instances (to change them).
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		&mut self.items[..self.live]
+	}
+
+	/// Gets how full it is.
+	pub fn stats(&self) -> PoolStats {
+		PoolStats {
+			name : self.name,
+			capacity : self.items.len(),
+			live : self.live,
+			peak : self.peak,
+			grown : self.grown,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_pool {
+	use super::*;
+
+	#[test]
+	fn recycling() {
+		let mut pool : Pool<Vec<u32>> = Pool::new("lists", 2, Vec::new);
+		pool.spawn().push(1);
+		pool.spawn().push(2);
+		pool.spawn().push(3);
+		assert_eq!(pool.stats(), PoolStats { name : "lists", capacity : 3, live : 3, peak : 3, grown : 1 });
+
+		// Despawning moves the last one into the gap, and keeps the instance around.
+		pool.despawn(0);
+		assert_eq!(pool.as_slice(), &[vec![3], vec![2]]);
+		pool.retain(|list| 3 != list[0]);
+		assert_eq!(pool.as_slice(), &[vec![2]]);
+
+		// Spares come back out as they were (without growing).
+		let reused = pool.spawn();
+		assert!(reused == &vec![3] || reused == &vec![1]);
+		reused.clear();
+		pool.clear();
+		assert!(pool.is_empty());
+		assert_eq!(pool.stats(), PoolStats { name : "lists", capacity : 3, live : 0, peak : 3, grown : 1 });
+		assert_eq!(pool.stats().to_json(), r#"{"name":"lists","capacity":3,"live":0,"peak":3,"grown":1}"#);
+	}
+}
@@ -0,0 +1,472 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::consts::EPSILON;
+use crate::geo::collision_system::CollisionSystem;
+use crate::origin_rebase::ShiftOrigin;
+use crate::pool::{Pool, PoolStats};
+
+/// The depth to draw projectiles at (in front of pickups).
+const PROJECTILE_DEPTH : f32 = -0.15;
+/// The depth to draw projectile trails at (just behind the projectiles).
+const TRAIL_DEPTH : f32 = -0.14;
+/// How many points make up a projectile's circle.
+const PROJECTILE_CIRCLE_POINTS : i32 = 8;
+/// How many projectiles to make room for up front (more are made if needed).
+const PROJECTILE_POOL_SIZE : usize = 64;
+
+/// How a projectile moves once fired.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProjectileMotion {
+	/// Flies in a straight line.
+	Straight,
+	/// Falls along an arc, with gravity scaled by some amount.
+	Arcing {
+		/// How strongly gravity pulls on it (1 is the same as the player).
+		gravity_scale : f32,
+	},
+	/// Turns toward its target, up to some rate.
+	Homing {
+		/// How fast it can turn (in radians per second).
+		turn_rate : f32,
+	},
+}
+
+/// Describes a kind of projectile.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProjectileConfig {
+	/// How it moves.
+	pub motion : ProjectileMotion,
+	/// How fast it starts out (in pixels per second).
+	pub speed : f32,
+	/// How big it is (in pixels).
+	pub radius : f32,
+	/// How long it lasts before disappearing (in seconds).
+	pub lifetime : f32,
+	/// How much damage it does on hitting its target.
+	pub damage : f32,
+	/// What color it's drawn in.
+	pub color : Color,
+	/// How many past positions its trail covers (0 for no trail).
+	pub trail_length : usize,
+}
+
+impl ProjectileConfig {
+	/// Gets one of the built in projectile types by name:
+	///
+	/// * "bolt": A fast shot that goes straight.
+	/// * "lob": A slow shot thrown up in an arc.
+	/// * "seeker": A slow shot that turns to follow its target.
+	/// * "shot": What the player fires. Fast and short lived.
+	pub fn from_name(name : &str) -> Option<ProjectileConfig> {
+		match name {
+			"bolt" => Some(ProjectileConfig {
+				motion : ProjectileMotion::Straight,
+				speed : 200.0,
+				radius : 2.0,
+				lifetime : 2.0,
+				damage : 10.0,
+				color : Color::new(255, 240, 120, 255),
+				trail_length : 4,
+			}),
+			"lob" => Some(ProjectileConfig {
+				motion : ProjectileMotion::Arcing { gravity_scale : 0.5 },
+				speed : 160.0,
+				radius : 3.0,
+				lifetime : 4.0,
+				damage : 15.0,
+				color : Color::new(255, 140, 40, 255),
+				trail_length : 6,
+			}),
+			"seeker" => Some(ProjectileConfig {
+				motion : ProjectileMotion::Homing { turn_rate : 2.5 },
+				speed : 90.0,
+				radius : 3.0,
+				lifetime : 5.0,
+				damage : 10.0,
+				color : Color::new(200, 80, 255, 255),
+				trail_length : 10,
+			}),
+			"shot" => Some(ProjectileConfig {
+				motion : ProjectileMotion::Straight,
+				speed : 320.0,
+				radius : 2.0,
+				lifetime : 0.8,
+				damage : 1.0,
+				color : Color::new(120, 230, 255, 255),
+				trail_length : 3,
+			}),
+			_ => None,
+		}
+	}
+
+	/// Gets the direction to fire this in to (roughly) hit a target. Arcing projectiles are aimed halfway toward up, so they lob over things.
+	pub fn aim(&self, from : &Vec2, to : &Vec2, up : &Vec2) -> Vec2 {
+		let offset = to - from;
+		let direct = if EPSILON < offset.length() { offset.norm() } else { up.clone() };
+		match self.motion {
+			ProjectileMotion::Arcing { .. } => (direct + up).norm(),
+			_ => direct,
+		}
+	}
+}
+
+/// A single projectile in flight.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Projectile {
+	/// What kind of projectile this is.
+	pub config : ProjectileConfig,
+	/// Where its center is.
+	pub position : Vec2,
+	/// How fast it's moving.
+	pub velocity : Vec2,
+	/// How long it's been flying (in seconds).
+	pub age : f32,
+	/// Where it's been recently (oldest first).
+	pub trail : Vec<Vec2>,
+	/// Whether the player fired it, so it hits enemies instead of the player.
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub friendly : bool,
+}
+
+impl Projectile {
+	/// Creates a blank instance (for the pool to hand out later).
+	fn spare() -> Projectile {
+		Projectile {
+			config : ProjectileConfig::from_name("bolt").unwrap(),
+			position : Vec2::zero(),
+			velocity : Vec2::zero(),
+			age : 0.0,
+			trail : Vec::with_capacity(16),
+			friendly : false,
+		}
+	}
+
+	/// Moves the projectile forward in time, given what it can hit (circles, as centers and radii).
+	fn advance(&mut self, elapsed_seconds : f32, gravity : &Vec2, targets : &[(Vec2, f32)], collision : &CollisionSystem) -> ProjectileFate {
+		self.age += elapsed_seconds;
+		if self.config.lifetime <= self.age {
+			return ProjectileFate::Gone;
+		}
+		match self.config.motion {
+			ProjectileMotion::Straight => {},
+			ProjectileMotion::Arcing { gravity_scale } => {
+				self.velocity += gravity * (gravity_scale * elapsed_seconds);
+			},
+			ProjectileMotion::Homing { turn_rate } => {
+				let position = self.position;
+				let nearest = targets.iter().map(|(center, _)| center).min_by(|first, second| (*first - position).length().partial_cmp(&(*second - position).length()).unwrap());
+				if let Some(target) = nearest {
+					self.steer_toward(target, turn_rate * elapsed_seconds);
+				}
+			},
+		}
+		if 0 < self.config.trail_length {
+			self.trail.push(self.position);
+			if self.config.trail_length < self.trail.len() {
+				self.trail.remove(0);
+			}
+		}
+		let movement = self.velocity * elapsed_seconds;
+		if collision.collide_circle_step(&self.position, self.config.radius, &movement).is_some() {
+			return ProjectileFate::Gone;
+		}
+		self.position += movement;
+		for (index, (center, radius)) in targets.iter().enumerate() {
+			if (center - self.position).length() < radius + self.config.radius {
+				return ProjectileFate::Hit(index, self.config.damage);
+			}
+		}
+		ProjectileFate::Flying
+	}
+
+	/// Turns the velocity toward a target by at most some angle (keeping the same speed).
+	fn steer_toward(&mut self, target : &Vec2, max_angle : f32) {
+		let speed = self.velocity.length();
+		let offset = target - self.position;
+		if EPSILON > speed || EPSILON > offset.length() {
+			return;
+		}
+		let angle = self.velocity.ext(&offset).atan2(self.velocity.dot(&offset));
+		let turn = angle.max(-max_angle).min(max_angle);
+		let (sin, cos) = turn.sin_cos();
+		self.velocity = Vec2::new(
+			self.velocity.x * cos - self.velocity.y * sin,
+			self.velocity.x * sin + self.velocity.y * cos,
+		);
+	}
+}
+
+/// What happened to a projectile during an update.
+enum ProjectileFate {
+	/// It's still going.
+	Flying,
+	/// It reached one of its targets (by index), doing some damage.
+	Hit(usize, f32),
+	/// It hit something else or ran out of time.
+	Gone,
+}
+
+impl ShiftOrigin for Projectile {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+		self.trail.shift_origin(shift);
+	}
+}
+
+/// What a projectile hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileTarget {
+	/// The player (hit by an enemy's projectile).
+	Player,
+	/// An enemy, by its index (hit by the player's projectile).
+	Enemy(usize),
+}
+
+/// A projectile reaching its target.
+#[derive(Debug, Clone)]
+pub struct ProjectileHit {
+	/// What it hit.
+	pub target : ProjectileTarget,
+	/// How much damage it does.
+	pub damage : f32,
+	/// Where the projectile was (so the target can be knocked away from it).
+	pub source : Vec2,
+}
+
+/// All projectiles in flight. They fly until they hit the collision geometry, reach a target, or run out of time.
+///
+/// Enemies' projectiles go after the player, and the player's (friendly) ones go after the enemies.
+pub struct Projectiles {
+	/// The projectiles in flight (recycled, so firing doesn't allocate).
+	projectiles : Pool<Projectile>,
+}
+
+impl Projectiles {
+	/// Creates an instance with no projectiles.
+	pub fn new() -> Projectiles {
+		Projectiles {
+			projectiles : Pool::new("projectiles", PROJECTILE_POOL_SIZE, Projectile::spare),
+		}
+	}
+
+	/// Fires a projectile (at the player) from a position in some direction.
+	pub fn fire(&mut self, config : &ProjectileConfig, position : &Vec2, direction : &Vec2) {
+		self.spawn(config, position, direction, false);
+	}
+
+	/// Fires a projectile for the player (so it hits enemies) from a position in some direction.
+	pub fn fire_friendly(&mut self, config : &ProjectileConfig, position : &Vec2, direction : &Vec2) {
+		self.spawn(config, position, direction, true);
+	}
+
+	/// Starts a projectile flying.
+	fn spawn(&mut self, config : &ProjectileConfig, position : &Vec2, direction : &Vec2, friendly : bool) {
+		let velocity = if EPSILON < direction.length() { direction.norm() * config.speed } else { Vec2::zero() };
+		let projectile = self.projectiles.spawn();
+		projectile.config = config.clone();
+		projectile.position = position.clone();
+		projectile.velocity = velocity;
+		projectile.age = 0.0;
+		projectile.trail.clear();
+		projectile.friendly = friendly;
+	}
+
+	/// Gets all projectiles in flight.
+	pub fn get_projectiles<'a>(&'a self) -> &'a [Projectile] {
+		self.projectiles.as_slice()
+	}
+
+	/// Gets where each projectile was before its last update and where it is now, given how long that update was. Skips the ones that haven't moved yet.
+	pub fn movements(&self, elapsed_seconds : f32) -> Vec<(Vec2, Vec2)> {
+		self.projectiles.as_slice().iter()
+			.filter(|projectile| 0.0 < projectile.age)
+			.map(|projectile| (projectile.position - projectile.velocity * elapsed_seconds, projectile.position))
+			.collect()
+	}
+
+	/// Gets how full the projectile pool is.
+	pub fn pool_stats(&self) -> PoolStats {
+		self.projectiles.stats()
+	}
+
+	/// Replaces all projectiles in flight (as in when restoring a snapshot).
+	pub fn restore(&mut self, projectiles : &[Projectile]) {
+		self.projectiles.clear();
+		for source in projectiles {
+			let projectile = self.projectiles.spawn();
+			projectile.config = source.config.clone();
+			projectile.position = source.position;
+			projectile.velocity = source.velocity;
+			projectile.age = source.age;
+			projectile.trail.clear();
+			projectile.trail.extend_from_slice(&source.trail);
+			projectile.friendly = source.friendly;
+		}
+	}
+
+	/// Removes all projectiles.
+	pub fn clear(&mut self) {
+		self.projectiles.clear();
+	}
+
+	/// Moves every projectile, removing the ones that are done. Returns the ones that reached the player or an enemy (circles, as centers and radii).
+	pub fn update(&mut self, elapsed_seconds : f32, gravity : &Vec2, player : &Vec2, player_radius : f32, enemies : &[(Vec2, f32)], collision : &CollisionSystem) -> Vec<ProjectileHit> {
+		let mut hits = Vec::new();
+		let player = [(*player, player_radius)];
+		self.projectiles.retain(|projectile| {
+			let targets = if projectile.friendly { enemies } else { &player[..] };
+			match projectile.advance(elapsed_seconds, gravity, targets, collision) {
+				ProjectileFate::Flying => true,
+				ProjectileFate::Hit(index, damage) => {
+					hits.push(ProjectileHit {
+						target : if projectile.friendly { ProjectileTarget::Enemy(index) } else { ProjectileTarget::Player },
+						damage,
+						source : projectile.position,
+					});
+					false
+				},
+				ProjectileFate::Gone => false,
+			}
+		});
+		hits
+	}
+}
+
+impl ShiftOrigin for Projectiles {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for projectile in self.projectiles.as_mut_slice() {
+			projectile.shift_origin(shift);
+		}
+	}
+}
+
+/// Draws the projectiles and their trails.
+pub struct ProjectileDisplay {
+	/// What the projectiles are drawn in.
+	bodies : DisplayBuffer,
+	/// What the trails are drawn in.
+	trails : DisplayBuffer,
+}
+
+impl ProjectileDisplay {
+	/// Creates an instance showing nothing.
+	pub fn new() -> ProjectileDisplay {
+		let mut trails = DisplayBuffer::new(DisplayBufferType::LINES);
+		trails.set_blend_mode(BlendMode::Additive); // So they glow.
+		ProjectileDisplay {
+			bodies : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+			trails,
+		}
+	}
+
+	/// Redraws all projectiles.
+	pub fn redraw(&mut self, projectiles : &Projectiles) {
+		let mut bodies = self.bodies.make_editor();
+		let mut trails = self.trails.make_editor();
+		bodies.clear();
+		trails.clear();
+		for projectile in projectiles.get_projectiles() {
+			let color = &projectile.config.color;
+			bodies.add_circle(Vec3::new(projectile.position.x, projectile.position.y, PROJECTILE_DEPTH), projectile.config.radius, PROJECTILE_CIRCLE_POINTS, color);
+			if !projectile.trail.is_empty() {
+				let mut points : Vec<Vec3> = projectile.trail.iter().map(|point| Vec3::new(point.x, point.y, TRAIL_DEPTH)).collect();
+				points.push(Vec3::new(projectile.position.x, projectile.position.y, TRAIL_DEPTH));
+				trails.add_lines(points, &Color::new(color.red, color.green, color.blue, 128));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_projectiles {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	#[test]
+	fn straight_and_arcing() {
+		let collision = CollisionSystem::new();
+		let gravity = Vec2::new(0.0, -800.0);
+		let far_away = Vec2::new(-1000.0, 0.0);
+		let mut projectiles = Projectiles::new();
+		let bolt = ProjectileConfig::from_name("bolt").unwrap();
+		let lob = ProjectileConfig::from_name("lob").unwrap();
+		projectiles.fire(&bolt, &Vec2::zero(), &Vec2::new(1.0, 0.0));
+		projectiles.fire(&lob, &Vec2::zero(), &lob.aim(&Vec2::zero(), &Vec2::new(100.0, 0.0), &Vec2::new(0.0, 1.0)));
+		let mut lob_highest : f32 = 0.0;
+		for _ in 0..15 {
+			projectiles.update(1.0 / 30.0, &gravity, &far_away, 4.0, &[], &collision);
+			lob_highest = lob_highest.max(projectiles.get_projectiles()[1].position.y);
+		}
+		let shots = projectiles.get_projectiles();
+		assert!((shots[0].position.y).abs() < EPSILON); // Straight doesn't fall.
+		assert!((shots[0].position.x - 100.0).abs() < 0.01);
+		assert!(shots[1].position.y < lob_highest); // Went up and came back down.
+		assert_eq!(shots[0].trail.len(), bolt.trail_length);
+		let (from, to) = projectiles.movements(1.0 / 30.0)[0];
+		assert!((to.x - from.x - bolt.speed / 30.0).abs() < 0.01); // Where it was before the last update.
+
+		// Everything runs out eventually.
+		for _ in 0..200 {
+			projectiles.update(1.0 / 30.0, &gravity, &far_away, 4.0, &[], &collision);
+		}
+		assert!(projectiles.get_projectiles().is_empty());
+	}
+
+	#[test]
+	fn homing_hits_target() {
+		let collision = CollisionSystem::new();
+		let mut projectiles = Projectiles::new();
+		let seeker = ProjectileConfig::from_name("seeker").unwrap();
+		projectiles.fire(&seeker, &Vec2::zero(), &Vec2::new(1.0, 0.0)); // Fired away from the target.
+		let target = Vec2::new(0.0, 120.0); // Outside its turning circle, so it can actually reach it.
+		let mut hits = Vec::new();
+		for _ in 0..150 {
+			hits.extend(projectiles.update(1.0 / 30.0, &Vec2::zero(), &target, 6.0, &[], &collision));
+		}
+		assert_eq!(hits.len(), 1);
+		assert_eq!(hits[0].damage, seeker.damage);
+		assert!(projectiles.get_projectiles().is_empty());
+	}
+
+	#[test]
+	fn stops_at_walls() {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(20.0, -50.0), &Vec2::new(20.0, 50.0))));
+		let mut projectiles = Projectiles::new();
+		projectiles.fire(&ProjectileConfig::from_name("bolt").unwrap(), &Vec2::zero(), &Vec2::new(1.0, 0.0));
+		let hits = projectiles.update(1.0 / 5.0, &Vec2::zero(), &Vec2::new(40.0, 0.0), 4.0, &[], &collision);
+		assert!(hits.is_empty());
+		assert!(projectiles.get_projectiles().is_empty());
+	}
+
+	#[test]
+	fn friendly_shots_hit_enemies() {
+		let collision = CollisionSystem::new();
+		let mut projectiles = Projectiles::new();
+		let shot = ProjectileConfig::from_name("shot").unwrap();
+		projectiles.fire_friendly(&shot, &Vec2::zero(), &Vec2::new(1.0, 0.0));
+		projectiles.fire(&shot, &Vec2::new(0.0, 100.0), &Vec2::new(1.0, 0.0));
+		let enemies = [(Vec2::new(0.0, 30.0), 6.0), (Vec2::new(40.0, 0.0), 6.0)];
+		let mut hits = Vec::new();
+		for _ in 0..10 {
+			// The player is right in the friendly shot's way, but only the enemy in its way gets hit.
+			hits.extend(projectiles.update(1.0 / 30.0, &Vec2::zero(), &Vec2::new(20.0, 0.0), 4.0, &enemies, &collision));
+		}
+		assert_eq!(hits.len(), 1);
+		assert_eq!(hits[0].target, ProjectileTarget::Enemy(1));
+		assert_eq!(hits[0].damage, shot.damage);
+		assert_eq!(projectiles.get_projectiles().len(), 1); // The other one's still looking for the player.
+
+		// Shots don't last long.
+		for _ in 0..30 {
+			projectiles.update(1.0 / 30.0, &Vec2::zero(), &Vec2::new(20.0, 0.0), 4.0, &enemies, &collision);
+		}
+		assert!(projectiles.get_projectiles().is_empty());
+	}
+}
@@ -0,0 +1,62 @@
+/// A small, fast pseudo-random number generator (xorshift32).
+///
+/// It's seeded explicitly so runs can be repeated exactly (which keeps physics anomaly captures reproducible).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Random {
+	/// The current state. Must never be zero.
+	state : u32,
+}
+
+impl Random {
+	/// Creates a generator from a seed.
+	pub fn new(seed : u32) -> Random {
+		Random {
+			state : if 0 == seed { 0x9E37_79B9 } else { seed },
+		}
+	}
+
+	/// Gets the next raw value.
+	pub fn next_u32(&mut self) -> u32 {
+		let mut value = self.state;
+		value ^= value << 13;
+		value ^= value >> 17;
+		value ^= value << 5;
+		self.state = value;
+		value
+	}
+
+	/// Gets a value from 0 (inclusive) to 1 (exclusive).
+	pub fn next_f32(&mut self) -> f32 {
+		((self.next_u32() >> 8) as f32) / ((1u32 << 24) as f32)
+	}
+
+	/// Gets a value from some min (inclusive) to max (exclusive).
+	pub fn range(&mut self, min : f32, max : f32) -> f32 {
+		min + (max - min) * self.next_f32()
+	}
+
+	/// Returns true with some chance (from 0 to 1).
+	pub fn chance(&mut self, chance : f32) -> bool {
+		self.next_f32() < chance
+	}
+}
+
+#[cfg(test)]
+mod tests_random {
+	use super::*;
+
+	#[test]
+	fn repeatable_and_in_range() {
+		let mut first = Random::new(42);
+		let mut second = Random::new(42);
+		for _ in 0..1000 {
+			let value = first.range(-2.0, 3.0);
+			assert_eq!(value, second.range(-2.0, 3.0));
+			assert!(-2.0 <= value && value < 3.0);
+		}
+		assert!(!first.chance(0.0));
+		assert!(first.chance(1.0));
+		assert!(0 != Random::new(0).next_u32());
+	}
+}
@@ -0,0 +1,154 @@
+use crate::externals::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::mat4::*;
+use crate::geo::bounds2::Bounds2;
+use crate::geo::world_vec2::*;
+use crate::display_buffer::get_render_origin;
+
+/// The view that the Camera draws into. It always exists.
+pub const MAIN_VIEW : RenderViewID = 0;
+/// The view showing the second player when split-screen co-op is on.
+pub const SECOND_PLAYER_VIEW : RenderViewID = 1;
+/// The minimap's view.
+pub const MINIMAP_VIEW : RenderViewID = 2;
+
+/// Which views a DisplayBuffer is drawn in, as one bit per view ID (see `view_bit()`).
+pub type ViewMask = u32;
+/// Every view (which is what DisplayBuffers start out drawn in).
+pub const ALL_VIEWS : ViewMask = !0;
+
+/// Gets the bit for a view in a ViewMask.
+pub fn view_bit(view : RenderViewID) -> ViewMask {
+	1 << view
+}
+
+/// A region of the screen, in fractions of its size (from the bottom left).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewRect {
+	/// Where the left edge is.
+	pub left : f32,
+	/// Where the bottom edge is.
+	pub bottom : f32,
+	/// How wide it is.
+	pub width : f32,
+	/// How tall it is.
+	pub height : f32,
+}
+
+impl ViewRect {
+	/// The whole screen.
+	pub const FULL : ViewRect = ViewRect { left : 0.0, bottom : 0.0, width : 1.0, height : 1.0 };
+
+	/// Creates an instance.
+	pub fn new(left : f32, bottom : f32, width : f32, height : f32) -> ViewRect {
+		ViewRect { left, bottom, width, height }
+	}
+
+	/// Gets how big it is (in whole pixels, rounded the same way the display does) on a screen of some size.
+	pub fn pixel_size(&self, screen_width : u32, screen_height : u32) -> (u32, u32) {
+		(
+			((self.width * screen_width as f32).round() as u32).max(1),
+			((self.height * screen_height as f32).round() as u32).max(1),
+		)
+	}
+
+	/// Gets where its top left corner is (in pixels from the top left of a screen of some size, like mouse positions).
+	pub fn pixel_top_left(&self, screen_width : u32, screen_height : u32) -> Vec2 {
+		Vec2::new(
+			(self.left * screen_width as f32).round(),
+			((1.0 - self.bottom - self.height) * screen_height as f32).round(),
+		)
+	}
+}
+
+/// Makes the transform that takes positions (relative to the render origin) to a view's clip space.
+/// The center is where to look (also relative to the render origin), and the width and height are the view's size in pixels.
+pub fn view_transform(center : &Vec2, depth : f32, zoom : f32, width : u32, height : u32) -> Mat4 {
+	let mut display = Mat4::new();
+	let mut translation = Vec3::new(-center.x, -center.y, -depth);
+	// Keep things pixel perfect even with odd widths/heights (by shifting half a screen pixel).
+	if 1 == width  % 2 { translation.x -= 0.5 / zoom; }
+	if 1 == height % 2 { translation.y -= 0.5 / zoom; }
+	display.scale_before(&Vec3::new(
+		2.0 * zoom / (width  as f32),
+		2.0 * zoom / (height as f32),
+		1.0,
+	)).translate_before(&translation);
+	display
+}
+
+/// An extra view of the world, drawn into a region of the screen (like a split-screen half or a minimap).
+///
+/// Which DisplayBuffers show up in it is up to them (see `DisplayBuffer::set_views()`). It's removed when dropped.
+/// It has to be updated every frame, since its transform is relative to the render origin (which moves with the Camera).
+pub struct RenderView {
+	/// The ID the display knows it by.
+	id : RenderViewID,
+	/// Where it's drawn on the screen.
+	rect : ViewRect,
+	/// The part of the world it showed at the last update.
+	bounds : Bounds2,
+}
+
+impl RenderView {
+	/// Creates an instance. It doesn't show anything until it's updated.
+	pub fn new(id : RenderViewID, rect : &ViewRect) -> RenderView {
+		crate::lean_assert!(MAIN_VIEW != id, "The main view belongs to the Camera");
+		RenderView {
+			id,
+			rect : rect.clone(),
+			bounds : Bounds2::from_centered_rect(&Vec2::zero(), 0.0, 0.0),
+		}
+	}
+
+	/// Moves where it's drawn on the screen.
+	pub fn set_rect(&mut self, rect : &ViewRect) {
+		self.rect = rect.clone();
+	}
+
+	/// Points it at a position in the world with some zoom, on a screen of some size (in pixels).
+	pub fn update(&mut self, center : &Vec2, zoom : f32, screen : &Vec3) {
+		let (width, height) = self.rect.pixel_size(screen.x as u32, screen.y as u32);
+		self.bounds = Bounds2::from_centered_rect(center, (width as f32) / zoom, (height as f32) / zoom);
+		let relative = WorldVec2::from_vec2(center).relative_to(&get_render_origin());
+		let transform = view_transform(&relative, 0.0, zoom, width, height);
+		setRenderView(self.id, self.rect.left, self.rect.bottom, self.rect.width, self.rect.height, transform.export());
+	}
+
+	/// Gets the part of the world it showed at the last update.
+	pub fn bounds(&self) -> Bounds2 {
+		self.bounds.clone()
+	}
+}
+
+impl Drop for RenderView {
+	fn drop(&mut self) {
+		removeRenderView(self.id);
+	}
+}
+
+#[cfg(test)]
+mod tests_render_view {
+	use super::*;
+
+	#[test]
+	fn transforms() {
+		let transform = view_transform(&Vec2::new(100.0, 50.0), 0.0, 2.0, 400, 300);
+		let center = &transform * &Vec3::new(100.0, 50.0, 0.0);
+		assert_eq!((center.x, center.y), (0.0, 0.0));
+		// At double zoom, the right edge is a quarter of the width away.
+		let edge = &transform * &Vec3::new(200.0, 125.0, 0.0);
+		assert_eq!((edge.x, edge.y), (1.0, 1.0));
+		// Odd sizes shift by half a pixel.
+		let odd = view_transform(&Vec2::zero(), 0.0, 1.0, 3, 2);
+		let shifted = &odd * &Vec3::zero();
+		assert!((shifted.x + 1.0 / 3.0).abs() < 1e-6 && 0.0 == shifted.y);
+
+		let half = ViewRect::new(0.5, 0.0, 0.5, 1.0);
+		assert_eq!(half.pixel_size(801, 600), (401, 600));
+		let corner = ViewRect::new(0.75, 0.75, 0.25, 0.25).pixel_top_left(800, 600);
+		assert_eq!((corner.x, corner.y), (600.0, 0.0));
+		assert_eq!(view_bit(MINIMAP_VIEW) & view_bit(MAIN_VIEW), 0);
+	}
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+use crate::anomaly_capture::InputFrame;
+
+/// How many simulation steps go by between rewind snapshots. Snapshots copy a lot, so they aren't taken every step.
+pub const REWIND_SNAPSHOT_STEPS : u32 = 2;
+
+/// A snapshot, and the inputs for every step after it (up to the next snapshot).
+struct RewindEntry<S> {
+	/// The state at the start of the entry.
+	snapshot : S,
+	/// The inputs for each step since the snapshot (oldest first).
+	inputs : Vec<InputFrame>,
+}
+
+/// A rolling history of snapshots (taken every few steps) and the inputs between them, for rolling time backwards.
+///
+/// It's generic over the snapshot type so it can be used without a whole Game (the game uses `GameSnapshot`).
+pub struct RewindHistory<S> {
+	/// How many steps go by between snapshots.
+	interval : u32,
+	/// The most entries to keep. Older ones are dropped.
+	capacity : usize,
+	/// How many steps have been recorded since the last snapshot.
+	steps_since_snapshot : u32,
+	/// The entries (oldest first).
+	entries : VecDeque<RewindEntry<S>>,
+}
+
+impl<S> RewindHistory<S> {
+	/// Creates an empty history that can go back some number of seconds, given how long each step is.
+	pub fn new(max_seconds : f32, step_seconds : f32, interval : u32) -> RewindHistory<S> {
+		let interval = interval.max(1);
+		let capacity = ((max_seconds / (step_seconds * interval as f32)).ceil() as usize).max(1);
+		RewindHistory {
+			interval,
+			capacity,
+			steps_since_snapshot : interval,
+			entries : VecDeque::with_capacity(capacity),
+		}
+	}
+
+	/// Gets how many steps go by between snapshots.
+	pub fn get_interval(&self) -> u32 {
+		self.interval
+	}
+
+	/// Whether a snapshot should be pushed before the next step is recorded.
+	pub fn is_snapshot_due(&self) -> bool {
+		self.interval <= self.steps_since_snapshot
+	}
+
+	/// Adds a snapshot of the state before the next step (dropping the oldest entry if full).
+	pub fn push(&mut self, snapshot : S) {
+		if self.capacity <= self.entries.len() {
+			self.entries.pop_front();
+		}
+		self.entries.push_back(RewindEntry { snapshot, inputs : Vec::with_capacity(self.interval as usize) });
+		self.steps_since_snapshot = 0;
+	}
+
+	/// Records the inputs for a step. Ignored until there's been a snapshot to go with them.
+	pub fn record_input(&mut self, input : InputFrame) {
+		if let Some(entry) = self.entries.back_mut() {
+			entry.inputs.push(input);
+			self.steps_since_snapshot += 1;
+		}
+	}
+
+	/// Removes the newest snapshot and gives it back (None once the history runs out).
+	/// A new snapshot is due right after, so if the state is put back to this one, recording picks up cleanly from there.
+	pub fn pop(&mut self) -> Option<S> {
+		self.steps_since_snapshot = self.interval;
+		self.entries.pop_back().map(|entry| entry.snapshot)
+	}
+
+	/// Gets every input still in the history (oldest first).
+	pub fn inputs<'a>(&'a self) -> impl Iterator<Item = &'a InputFrame> + 'a {
+		self.entries.iter().flat_map(|entry| entry.inputs.iter())
+	}
+
+	/// Gets how many snapshots are kept.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Forgets everything (as in when a new room loads).
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.steps_since_snapshot = self.interval;
+	}
+}
+
+#[cfg(test)]
+mod tests_rewind {
+	use super::*;
+	use crate::geo::vec2::Vec2;
+
+	fn input(time : f32) -> InputFrame {
+		InputFrame { time, elapsed_seconds : 0.1, direction : Vec2::zero(), jump : false, track : false }
+	}
+
+	/// Records a step the way the game does: a snapshot of the state before it (if one is due), then its inputs.
+	fn step(history : &mut RewindHistory<u32>, number : u32) {
+		if history.is_snapshot_due() {
+			history.push(number);
+		}
+		history.record_input(input(number as f32));
+	}
+
+	#[test]
+	fn keeps_a_window_of_snapshots() {
+		let mut history = RewindHistory::new(1.0, 0.1, 2); // Five snapshots.
+		history.record_input(input(-1.0)); // Nothing to go with yet.
+		assert_eq!(history.inputs().count(), 0);
+		for number in 0..=20 {
+			step(&mut history, number);
+		}
+		assert_eq!(history.len(), 5);
+		assert_eq!(history.inputs().count(), 9);
+		assert_eq!(history.inputs().next().unwrap().time, 12.0);
+
+		// Goes back two steps at a time, newest first.
+		assert_eq!(history.pop(), Some(20));
+		assert_eq!(history.pop(), Some(18));
+		assert!(history.is_snapshot_due());
+		step(&mut history, 18); // Picks up again from the restored state.
+		assert_eq!(history.len(), 4);
+		assert_eq!(history.inputs().last().unwrap().time, 18.0);
+		while history.pop().is_some() {}
+		assert_eq!(history.pop(), None);
+	}
+}
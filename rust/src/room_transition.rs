@@ -0,0 +1,327 @@
+use crate::tween::*;
+use crate::tiled::*;
+use crate::geo::vec2::*;
+use crate::world_flags::*;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The type of Tiled points that are doors to other rooms.
+pub const DOOR_POINT_TYPE : &str = "door";
+/// How close the player needs to be to a door to go through it.
+const DOOR_RADIUS : f32 = 12.0;
+/// The name of the point the player starts at when a map is entered without asking for a specific one.
+pub const DEFAULT_SPAWN_NAME : &str = "spawn_default";
+
+/// Picks the point to put the player at when entering a map: the one with the requested name, or else the one named `DEFAULT_SPAWN_NAME`, or else the first point that isn't a door (so they aren't standing in one), or else the first point.
+/// Gives None if the map has no points at all.
+pub fn find_spawn<'a>(points : &'a [TiledPoint], requested : Option<&str>) -> Option<&'a TiledPoint> {
+	requested.and_then(|name| points.iter().find(|point| name == point.name))
+		.or_else(|| points.iter().find(|point| DEFAULT_SPAWN_NAME == point.name))
+		.or_else(|| points.iter().find(|point| DOOR_POINT_TYPE != point.r#type))
+		.or_else(|| points.first())
+}
+
+/// How a room transition covers up the screen while the rooms are swapped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionStyle {
+	/// Fades to black and back.
+	Fade,
+	/// A black panel slides across the screen toward the left (and then off of it the same way).
+	SlideLeft,
+	/// A black panel slides across the screen toward the right (and then off of it the same way).
+	SlideRight,
+	/// A black panel slides across the screen upward (and then off of it the same way).
+	SlideUp,
+	/// A black panel slides across the screen downward (and then off of it the same way).
+	SlideDown,
+	/// A circle closes in on the center of the screen, then opens back up.
+	Iris,
+}
+
+impl TransitionStyle {
+	/// Looks up a style by name (as used in Tiled properties). Returns None if the name isn't known.
+	pub fn from_name(name : &str) -> Option<TransitionStyle> {
+		match name {
+			"fade" => Some(TransitionStyle::Fade),
+			"slideLeft" => Some(TransitionStyle::SlideLeft),
+			"slideRight" => Some(TransitionStyle::SlideRight),
+			"slideUp" => Some(TransitionStyle::SlideUp),
+			"slideDown" => Some(TransitionStyle::SlideDown),
+			"iris" => Some(TransitionStyle::Iris),
+			_ => None,
+		}
+	}
+}
+
+/// What part of a room transition is playing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionPhase {
+	/// The screen is being covered up.
+	Covering,
+	/// The screen is fully covered, and it's waiting on the new room to be ready.
+	Covered,
+	/// The new room is being revealed.
+	Revealing,
+	/// Finished.
+	Done,
+}
+
+/// A transition from one room (Tiled map) to another.
+///
+/// The screen is covered, then it waits (fully covered) for `finish_swap()` before revealing the new room. So whoever swaps the rooms can take as long as it needs to load things and place the player/camera, and nothing is shown until that's done.
+pub struct RoomTransition {
+	/// How the screen is covered.
+	style : TransitionStyle,
+	/// The URL of the map being moved to.
+	target_url : String,
+	/// The name of the point to put the player at in the new map (if any).
+	spawn : Option<String>,
+	/// How long each of covering and revealing take (in seconds).
+	half_seconds : f32,
+	/// The current phase.
+	phase : TransitionPhase,
+	/// How much of the screen is covered (from 0 to 1).
+	coverage : Tween,
+	/// Whether the swap has been asked for (by `take_swap_request()`) yet.
+	swap_requested : bool,
+}
+
+impl RoomTransition {
+	/// Starts a transition that takes some total time (split evenly between covering and revealing).
+	pub fn new(style : TransitionStyle, target_url : &str, spawn : Option<&str>, seconds : f32) -> RoomTransition {
+		let half_seconds = (seconds / 2.0).max(0.0);
+		RoomTransition {
+			style,
+			target_url : target_url.to_string(),
+			spawn : spawn.map(|name| name.to_string()),
+			half_seconds,
+			phase : TransitionPhase::Covering,
+			coverage : Tween::new(0.0, 1.0, half_seconds, Easing::QuadIn),
+			swap_requested : false,
+		}
+	}
+
+	/// Gets how the screen is covered.
+	pub fn get_style(&self) -> TransitionStyle {
+		self.style
+	}
+
+	/// Gets the URL of the map being moved to.
+	pub fn get_target_url<'a>(&'a self) -> &'a str {
+		&self.target_url
+	}
+
+	/// Gets the name of the point to put the player at in the new map (if any).
+	pub fn get_spawn<'a>(&'a self) -> Option<&'a str> {
+		self.spawn.as_deref()
+	}
+
+	/// Gets the current phase.
+	pub fn get_phase(&self) -> TransitionPhase {
+		self.phase
+	}
+
+	/// Gets how much of the screen is covered (from 0 to 1).
+	pub fn get_coverage(&self) -> f32 {
+		self.coverage.value()
+	}
+
+	/// Whether the game world should be held still. It's only let go once the new room starts being revealed.
+	pub fn blocks_simulation(&self) -> bool {
+		TransitionPhase::Covering == self.phase || TransitionPhase::Covered == self.phase
+	}
+
+	/// Whether the transition is over.
+	pub fn is_done(&self) -> bool {
+		TransitionPhase::Done == self.phase
+	}
+
+	/// Moves forward in time. Returns how much of the screen is covered (from 0 to 1).
+	pub fn update(&mut self, elapsed_seconds : f32) -> f32 {
+		let coverage = self.coverage.update(elapsed_seconds);
+		if self.coverage.is_done() {
+			match self.phase {
+				TransitionPhase::Covering => { self.phase = TransitionPhase::Covered; },
+				TransitionPhase::Revealing => { self.phase = TransitionPhase::Done; },
+				_ => {},
+			}
+		}
+		coverage
+	}
+
+	/// Returns true exactly once: the first time this is called once the screen is fully covered. That's when the rooms should be swapped.
+	pub fn take_swap_request(&mut self) -> bool {
+		if TransitionPhase::Covered != self.phase || self.swap_requested {
+			return false;
+		}
+		self.swap_requested = true;
+		true
+	}
+
+	/// Notes that the new room is ready (and the player and camera are in place), so it can start being revealed.
+	pub fn finish_swap(&mut self) {
+		if TransitionPhase::Covered == self.phase {
+			self.phase = TransitionPhase::Revealing;
+			self.coverage = Tween::new(1.0, 0.0, self.half_seconds, Easing::QuadOut);
+		}
+	}
+}
+
+/// A spot that moves the player to another room.
+#[derive(Debug, Clone)]
+pub struct Door {
+	/// Where the door is.
+	pub position : Vec2,
+	/// The URL of the map it leads to.
+	pub target_url : String,
+	/// The name of the point to put the player at in the new map (if any). Otherwise they go to the map's default spawn (see `find_spawn()`).
+	pub spawn : Option<String>,
+	/// How the screen is covered while going through.
+	pub style : TransitionStyle,
+	/// What needs to hold for the door to be open (if anything).
+	pub condition : Option<Condition>,
+}
+
+impl Door {
+	/// Creates a door from a Tiled point. Uses the properties:
+	/// * "target": The URL of the map to go to. Required.
+	/// * "spawn": The name of the point in the new map to put the player at.
+	/// * "transition": The name of the TransitionStyle (defaults to "fade").
+	/// * "condition": What needs to hold for it to be open (see `Condition`). It's checked every time the player touches it, so it can open up without leaving the room.
+	pub fn from_point(point : &TiledPoint) -> Result<Door, String> {
+		let target_url = match point.get_property("target") {
+			Some(url) => url.to_string(),
+			None => { return Err(String::from("Missing \"target\" property")); },
+		};
+		let style = match point.get_property("transition") {
+			Some(name) => match TransitionStyle::from_name(name) {
+				Some(style) => style,
+				None => { return Err(format!("Unknown transition {:?}", name)); },
+			},
+			None => TransitionStyle::Fade,
+		};
+		Ok(Door {
+			position : point.position,
+			target_url,
+			spawn : point.get_property("spawn").map(|name| name.to_string()),
+			style,
+			condition : Condition::from_point(point)?,
+		})
+	}
+
+	/// Whether the door can currently be gone through.
+	pub fn is_open(&self, flags : &WorldFlags) -> bool {
+		self.condition.as_ref().map_or(true, |condition| condition.holds(flags))
+	}
+
+	/// Whether a position is close enough to go through the door.
+	pub fn is_touching(&self, position : &Vec2) -> bool {
+		(position - self.position).length() < DOOR_RADIUS
+	}
+
+	/// Starts a transition through this door that takes some total time.
+	pub fn start_transition(&self, seconds : f32) -> RoomTransition {
+		RoomTransition::new(self.style, &self.target_url, self.spawn.as_deref(), seconds)
+	}
+}
+
+impl ShiftOrigin for Door {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+	}
+}
+
+#[cfg(test)]
+mod tests_room_transition {
+	use super::*;
+
+	fn make_point(properties : &[(&str, &str)]) -> TiledPoint {
+		TiledPoint {
+			position : Vec2::new(10.0, 20.0),
+			name : String::from("exit"),
+			r#type : String::from(DOOR_POINT_TYPE),
+			properties : properties.iter().map(|(name, value)| TiledStringProp { name : name.to_string(), value : value.to_string() }).collect(),
+		}
+	}
+
+	#[test]
+	fn doors_from_points() {
+		let door = Door::from_point(&make_point(&[("target", "cave.json"), ("spawn", "top"), ("transition", "slideUp")])).unwrap();
+		assert_eq!(door.target_url, "cave.json");
+		assert_eq!(door.spawn.as_deref(), Some("top"));
+		assert_eq!(door.style, TransitionStyle::SlideUp);
+		assert!(door.is_touching(&Vec2::new(15.0, 20.0)));
+		assert!(!door.is_touching(&Vec2::new(40.0, 20.0)));
+
+		let plain = Door::from_point(&make_point(&[("target", "cave.json")])).unwrap();
+		assert_eq!(plain.style, TransitionStyle::Fade);
+		assert!(plain.spawn.is_none());
+		assert_eq!(plain.start_transition(1.0).get_target_url(), "cave.json");
+
+		assert!(plain.is_open(&WorldFlags::new()));
+
+		let locked = Door::from_point(&make_point(&[("target", "cave.json"), ("condition", "lever")])).unwrap();
+		let mut flags = WorldFlags::new();
+		assert!(!locked.is_open(&flags));
+		flags.set("lever", FlagValue::Bool(true));
+		assert!(locked.is_open(&flags));
+
+		assert!(Door::from_point(&make_point(&[])).is_err());
+		assert!(Door::from_point(&make_point(&[("target", "cave.json"), ("transition", "wipe")])).is_err());
+	}
+
+	#[test]
+	fn spawn_selection() {
+		let named = |name : &str, r#type : &str| TiledPoint {
+			position : Vec2::zero(),
+			name : name.to_string(),
+			r#type : r#type.to_string(),
+			properties : Vec::new(),
+		};
+		let points = vec![
+			named("from_pipe_east", DOOR_POINT_TYPE),
+			named("rope", ""),
+			named("from_pipe_east", ""),
+			named(DEFAULT_SPAWN_NAME, ""),
+		];
+		// Named points are found even if a door shares the name (the first match wins).
+		assert_eq!(find_spawn(&points, Some("from_pipe_east")).unwrap().r#type, DOOR_POINT_TYPE);
+		assert_eq!(find_spawn(&points[1..], Some("from_pipe_east")).unwrap().name, "from_pipe_east");
+		// Falls back to the default, then the first non-door, then anything.
+		assert_eq!(find_spawn(&points, Some("missing")).unwrap().name, DEFAULT_SPAWN_NAME);
+		assert_eq!(find_spawn(&points, None).unwrap().name, DEFAULT_SPAWN_NAME);
+		assert_eq!(find_spawn(&points[..3], None).unwrap().name, "rope");
+		assert_eq!(find_spawn(&points[..1], None).unwrap().r#type, DOOR_POINT_TYPE);
+		assert!(find_spawn(&[], Some("anything")).is_none());
+	}
+
+	#[test]
+	fn waits_for_swap_before_revealing() {
+		let mut transition = RoomTransition::new(TransitionStyle::Iris, "next.json", Some("entrance"), 1.0);
+		assert_eq!(transition.get_spawn(), Some("entrance"));
+		assert!(transition.blocks_simulation());
+		assert!(!transition.take_swap_request());
+		assert!(transition.update(0.25) < 1.0);
+		transition.finish_swap(); // Too early, so ignored.
+		assert_eq!(transition.update(0.25), 1.0);
+		assert_eq!(transition.get_phase(), TransitionPhase::Covered);
+
+		// Stays covered for as long as the swap takes.
+		assert!(transition.take_swap_request());
+		assert!(!transition.take_swap_request());
+		assert_eq!(transition.update(10.0), 1.0);
+		assert!(transition.blocks_simulation());
+
+		transition.finish_swap();
+		assert!(!transition.blocks_simulation());
+		assert!(transition.update(0.25) < 1.0);
+		assert_eq!(transition.update(0.25), 0.0);
+		assert!(transition.is_done());
+	}
+
+	#[test]
+	fn style_names() {
+		assert_eq!(TransitionStyle::from_name("slideLeft"), Some(TransitionStyle::SlideLeft));
+		assert_eq!(TransitionStyle::from_name("iris"), Some(TransitionStyle::Iris));
+		assert_eq!(TransitionStyle::from_name("wipe"), None);
+	}
+}
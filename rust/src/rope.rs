@@ -0,0 +1,243 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::collision_system::CollisionSystem;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How many times per update to enforce the distance constraints. Higher means stiffer ropes.
+const ROPE_CONSTRAINT_ITERATIONS : usize = 8;
+/// How much velocity each point keeps every update (to keep ropes from swinging forever).
+const ROPE_DAMPING : f32 = 0.99;
+/// The radius each rope point uses when colliding.
+const ROPE_POINT_RADIUS : f32 = 1.0;
+/// The distance between each rope point for ropes made from the Tiled file.
+const ROPE_SEGMENT_LENGTH : f32 = 8.0;
+/// The number of segments in ropes made from the Tiled file.
+const ROPE_SEGMENT_COUNT : usize = 8;
+/// The depth to draw ropes at.
+const ROPE_DEPTH : f32 = 0.5;
+
+/// A verlet chain of points, each a fixed distance from its neighbors.
+pub struct RopeSimulation {
+	/// The current position of every point.
+	points : Vec<Vec2>,
+	/// The position of every point on the previous update. The difference from `points` is the velocity.
+	previous : Vec<Vec2>,
+	/// Whether each point is held in place.
+	pinned : Vec<bool>,
+	/// How far apart neighboring points should be.
+	segment_length : f32,
+}
+
+impl RopeSimulation {
+	/// Creates a rope that hangs straight down from an anchor (which is pinned).
+	pub fn new_hanging(anchor : &Vec2, segment_count : usize, segment_length : f32) -> RopeSimulation {
+		let mut points = Vec::with_capacity(segment_count + 1);
+		for index in 0..(segment_count + 1) {
+			points.push(Vec2::new(anchor.x, anchor.y - (index as f32) * segment_length));
+		}
+		let mut pinned = vec![false; points.len()];
+		pinned[0] = true;
+		RopeSimulation {
+			previous : points.clone(),
+			points,
+			pinned,
+			segment_length,
+		}
+	}
+
+	/// Gets the position of every point (starting from the anchor).
+	pub fn get_points<'a>(&'a self) -> &'a Vec<Vec2> {
+		&self.points
+	}
+
+	/// Pins/unpins a point.
+	pub fn set_pinned(&mut self, index : usize, pinned : bool) {
+		self.pinned[index] = pinned;
+	}
+
+	/// Moves a point directly (without giving it any velocity). Useful for dragging pinned points around.
+	pub fn move_point(&mut self, index : usize, position : &Vec2) {
+		self.points[index] = position.clone();
+		self.previous[index] = position.clone();
+	}
+
+	/// Pushes a point by some amount. Unlike `move_point()` this gives it velocity.
+	pub fn push_point(&mut self, index : usize, amount : &Vec2) {
+		if self.pinned[index] { return; }
+		self.points[index] += amount;
+	}
+
+	/// Steps the simulation forward. Returns whether any point ran out of collision iterations.
+	pub fn update(&mut self, elapsed_seconds : f32, gravity : &Vec2, collision : &CollisionSystem) -> bool {
+		let mut exhausted = false;
+		// Integrate, stopping points from moving through the collision geometry.
+		let acceleration = gravity * (elapsed_seconds * elapsed_seconds);
+		for index in 0..self.points.len() {
+			if self.pinned[index] { continue; }
+			let position = self.points[index];
+			let movement = (position - self.previous[index]) * ROPE_DAMPING + acceleration;
+			self.previous[index] = position;
+			let mut final_position = position + movement;
+			let result = collision.collide_circle_checked(&position, ROPE_POINT_RADIUS, &movement);
+			exhausted |= result.exhausted;
+			if let Some(last) = result.deflections.last() {
+				final_position = last.final_position;
+			}
+			self.points[index] = final_position;
+		}
+
+		// Then pull every pair of neighbors back to the right distance.
+		let unconstrained = self.points.clone();
+		for _iteration in 0..ROPE_CONSTRAINT_ITERATIONS {
+			for index in 1..self.points.len() {
+				self.satisfy_constraint(index - 1, index);
+			}
+		}
+
+		// The constraints can pull points into the collision geometry, so collide those corrections too.
+		for index in 0..self.points.len() {
+			if self.pinned[index] { continue; }
+			let correction = self.points[index] - unconstrained[index];
+			let result = collision.collide_circle_checked(&unconstrained[index], ROPE_POINT_RADIUS, &correction);
+			exhausted |= result.exhausted;
+			if let Some(last) = result.deflections.last() {
+				self.points[index] = last.final_position;
+			}
+		}
+		exhausted
+	}
+
+	/// Moves a pair of neighboring points so they're `segment_length` apart. Pinned points don't move.
+	fn satisfy_constraint(&mut self, first : usize, second : usize) {
+		let first_pinned = self.pinned[first];
+		let second_pinned = self.pinned[second];
+		if first_pinned && second_pinned { return; }
+		let delta = self.points[second] - self.points[first];
+		let length = delta.length();
+		if 0.0 == length { return; }
+		let correction = delta * ((length - self.segment_length) / length);
+		if first_pinned {
+			self.points[second] -= correction;
+		} else if second_pinned {
+			self.points[first] += correction;
+		} else {
+			self.points[first] += correction * 0.5;
+			self.points[second] -= correction * 0.5;
+		}
+	}
+}
+
+impl ShiftOrigin for RopeSimulation {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.points.shift_origin(shift);
+		self.previous.shift_origin(shift);
+	}
+}
+
+/// A rope (or chain or vine) in the world. These are just props that hang and swing: nothing can grab onto them.
+pub struct Rope {
+	/// The physics for the rope.
+	pub simulation : RopeSimulation,
+	/// What to draw the rope in.
+	display : DisplayBuffer,
+	/// The color to draw the rope.
+	color : Color,
+}
+
+impl Rope {
+	/// Creates a rope that hangs from an anchor.
+	pub fn new(anchor : &Vec2, color : &Color) -> Rope {
+		let mut rope = Rope {
+			simulation : RopeSimulation::new_hanging(anchor, ROPE_SEGMENT_COUNT, ROPE_SEGMENT_LENGTH),
+			display : DisplayBuffer::new(DisplayBufferType::LINES),
+			color : color.clone(),
+		};
+		rope.redraw();
+		rope
+	}
+
+	/// Updates the physics and then the display. Returns whether any point ran out of collision iterations.
+	pub fn update(&mut self, elapsed_seconds : f32, gravity : &Vec2, collision : &CollisionSystem) -> bool {
+		let exhausted = self.simulation.update(elapsed_seconds, gravity, collision);
+		self.redraw();
+		exhausted
+	}
+
+	/// Rebuilds the display buffer from the current state of the simulation.
+	fn redraw(&mut self) {
+		let points = self.simulation.get_points().iter().map(|point| Vec3::new(point.x, point.y, ROPE_DEPTH)).collect();
+		let mut editor = self.display.make_editor();
+		editor.clear();
+		editor.add_lines(points, &self.color);
+	}
+}
+
+impl ShiftOrigin for Rope {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.simulation.shift_origin(shift);
+		self.redraw();
+	}
+}
+
+#[cfg(test)]
+mod tests_rope_simulation {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	/// How far apart neighbors can be (vs the segment length) to count as "constrained".
+	const TOLERANCE : f32 = 0.1;
+
+	/// Checks that every pair of neighbors is about the right distance apart.
+	fn assert_constrained(rope : &RopeSimulation) {
+		let points = rope.get_points();
+		for index in 1..points.len() {
+			let length = (points[index] - points[index - 1]).length();
+			assert!((length - rope.segment_length).abs() < TOLERANCE, "Segment {} has length {}", index, length);
+		}
+	}
+
+	#[test]
+	fn hangs_still() {
+		let mut rope = RopeSimulation::new_hanging(&Vec2::new(0.0, 0.0), 4, 8.0);
+		let collision = CollisionSystem::new();
+		for _ in 0..60 {
+			rope.update(1.0 / 60.0, &Vec2::new(0.0, -800.0), &collision);
+		}
+		assert_constrained(&rope);
+		let points = rope.get_points();
+		assert_eq!(points[0].x, 0.0);
+		assert_eq!(points[0].y, 0.0);
+		assert!((points[4].x).abs() < TOLERANCE);
+		assert!((points[4].y + 32.0).abs() < TOLERANCE);
+	}
+
+	#[test]
+	fn swings_back_under_anchor() {
+		let mut rope = RopeSimulation::new_hanging(&Vec2::new(0.0, 0.0), 4, 8.0);
+		let collision = CollisionSystem::new();
+		rope.push_point(4, &Vec2::new(4.0, 0.0));
+		assert!(0.0 < rope.get_points()[4].x);
+		for _ in 0..600 {
+			rope.update(1.0 / 60.0, &Vec2::new(0.0, -800.0), &collision);
+		}
+		assert_constrained(&rope);
+		assert!(rope.get_points()[4].x.abs() < 1.0);
+	}
+
+	#[test]
+	fn rests_on_floor() {
+		let mut rope = RopeSimulation::new_hanging(&Vec2::new(0.0, 40.0), 4, 8.0);
+		rope.set_pinned(0, false);
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0))));
+		for _ in 0..120 {
+			rope.update(1.0 / 60.0, &Vec2::new(0.0, -800.0), &collision);
+		}
+		for point in rope.get_points() {
+			assert!(-TOLERANCE < point.y, "Point fell through floor: {:?}", point);
+		}
+	}
+}
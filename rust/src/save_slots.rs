@@ -0,0 +1,349 @@
+use crate::externals::*;
+use crate::debug_log;
+use crate::world_flags::WorldFlags;
+
+/// How many save slots there are.
+pub const SAVE_SLOT_COUNT : usize = 3;
+/// The current save format. Version 1 was from before save slots, when everything was saved under plain keys (like "worldFlags").
+pub const SAVE_FORMAT_VERSION : u32 = 2;
+/// The key a slot's progress (its world flags) is saved under.
+pub const WORLD_FLAGS_SLOT_KEY : &str = "worldFlags";
+/// The key a slot's room to continue from is saved under.
+pub const CONTINUE_SLOT_KEY : &str = "continue";
+/// The key a slot's metadata is saved under.
+const METADATA_SLOT_KEY : &str = "meta";
+/// Everything saved for each slot (which is what's copied or deleted with it).
+const SLOT_KEYS : [&str; 3] = [METADATA_SLOT_KEY, WORLD_FLAGS_SLOT_KEY, CONTINUE_SLOT_KEY];
+/// The (unslotted) key the slot being played is saved under.
+const ACTIVE_SLOT_KEY : &str = "activeSlot";
+/// How many objectives and secrets there are across every room (finding all of them is 100% completion).
+pub const COMPLETION_GOAL_COUNT : usize = 10;
+
+/// Works out how much of the game is done (from 0 to 1), from the objectives completed and secrets found.
+pub fn completion(flags : &WorldFlags) -> f32 {
+	let done = flags.count_set("objective.") + flags.count_set("secret.");
+	(done as f32 / COMPLETION_GOAL_COUNT as f32).min(1.0)
+}
+
+/// Somewhere saves are kept, as text under keys. Missing keys read as empty text, and storing empty text removes a key.
+pub trait SaveStore {
+	/// Gets what's saved under a key.
+	fn load(&self, key : &str) -> String;
+	/// Saves some text under a key.
+	fn store(&mut self, key : &str, data : &str);
+}
+
+/// The browser's storage (see `storeSaveData()` and `loadSaveData()`).
+pub struct BrowserSaveStore;
+
+impl SaveStore for BrowserSaveStore {
+	fn load(&self, key : &str) -> String {
+		loadSaveData(key)
+	}
+
+	fn store(&mut self, key : &str, data : &str) {
+		storeSaveData(key, data);
+	}
+}
+
+/// Gets the key something in a slot is saved under.
+pub fn slot_key(slot : usize, name : &str) -> String {
+	format!("slot{}.{}", slot, name)
+}
+
+/// What's shown about a save slot without loading it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotMetadata {
+	/// The save format it was written with.
+	pub version : u32,
+	/// How long it's been played for (in seconds).
+	pub playtime_seconds : f64,
+	/// How much of the game is done (from 0 to 1).
+	pub completion : f32,
+	/// When it was last saved (in seconds since 1970, UTC). Zero if that's not known.
+	pub timestamp : f64,
+}
+
+impl SlotMetadata {
+	/// Creates an instance for a new game.
+	pub fn new() -> SlotMetadata {
+		SlotMetadata {
+			version : SAVE_FORMAT_VERSION,
+			playtime_seconds : 0.0,
+			completion : 0.0,
+			timestamp : 0.0,
+		}
+	}
+
+	/// Converts to text for saving (one `name=value` per line).
+	pub fn to_save_string(&self) -> String {
+		format!("version={}\nplaytime={}\ncompletion={}\ntimestamp={}", self.version, self.playtime_seconds, self.completion, self.timestamp)
+	}
+
+	/// Reads text from `to_save_string()`. Anything not mentioned is left at its default (except the version, which is 1 if missing).
+	pub fn from_save_string(text : &str) -> Result<SlotMetadata, String> {
+		let mut metadata = SlotMetadata::new();
+		metadata.version = 1;
+		for line in text.lines() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			let split = match line.find('=') {
+				Some(split) => split,
+				None => { return Err(format!("Missing '=' in {:?}", line)); },
+			};
+			let (name, value) = (&line[..split], &line[split+1..]);
+			let bad_value = || format!("Bad {} value {:?}", name, value);
+			match name {
+				"version" => { metadata.version = value.parse().map_err(|_| bad_value())?; },
+				"playtime" => { metadata.playtime_seconds = value.parse().map_err(|_| bad_value())?; },
+				"completion" => { metadata.completion = value.parse().map_err(|_| bad_value())?; },
+				"timestamp" => { metadata.timestamp = value.parse().map_err(|_| bad_value())?; },
+				_ => { return Err(format!("Unknown slot metadata {:?}", name)); },
+			}
+		}
+		if SAVE_FORMAT_VERSION < metadata.version {
+			return Err(format!("Saved with a newer format (version {})", metadata.version));
+		}
+		Ok(metadata)
+	}
+
+	/// Describes it in a few words (like "2:05:09, 40%, saved 2024-03-01").
+	pub fn summary(&self) -> String {
+		let total = self.playtime_seconds.max(0.0) as u64;
+		let mut text = format!("{}:{:02}:{:02}, {}%", total / 3600, (total / 60) % 60, total % 60, (self.completion * 100.0).round());
+		if 0.0 < self.timestamp {
+			let (year, month, day) = civil_date((self.timestamp / 86400.0).floor() as i64);
+			text.push_str(&format!(", saved {}-{:02}-{:02}", year, month, day));
+		}
+		text
+	}
+}
+
+/// Converts a count of days since 1970-01-01 into a (year, month, day) date.
+fn civil_date(days : i64) -> (i64, u32, u32) {
+	// From Howard Hinnant's "days_from_civil" algorithms (counting in 400 year eras that start in March).
+	let shifted = days + 719468;
+	let era = (if 0 <= shifted { shifted } else { shifted - 146096 }) / 146097;
+	let day_of_era = shifted - era * 146097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+	let month = (if month_index < 10 { month_index + 3 } else { month_index - 9 }) as u32;
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year, month, day)
+}
+
+/// Moves saves from the format before slots (everything under plain keys) into the first slot. Does nothing if there's no such save, or the first slot is already used.
+fn migrate_unslotted(store : &mut dyn SaveStore) -> bool {
+	if !store.load(&slot_key(0, METADATA_SLOT_KEY)).is_empty() {
+		return false;
+	}
+	let mut moved = false;
+	for name in [WORLD_FLAGS_SLOT_KEY, CONTINUE_SLOT_KEY].iter() {
+		let data = store.load(name);
+		if !data.is_empty() {
+			store.store(&slot_key(0, name), &data);
+			store.store(name, "");
+			moved = true;
+		}
+	}
+	if moved {
+		// How long it was played and when it was saved weren't kept back then.
+		store.store(&slot_key(0, METADATA_SLOT_KEY), &SlotMetadata::new().to_save_string());
+	}
+	moved
+}
+
+/// Brings a slot up to the current format, saving its metadata if anything changed. Each future format change adds a step here (for saves older than it).
+fn migrate_slot(store : &mut dyn SaveStore, slot : usize, mut metadata : SlotMetadata) -> SlotMetadata {
+	if SAVE_FORMAT_VERSION == metadata.version {
+		return metadata;
+	}
+	// Version 1 never had slots (see `migrate_unslotted()`), so the only thing a slot claiming it needs is the new number.
+	metadata.version = SAVE_FORMAT_VERSION;
+	store.store(&slot_key(slot, METADATA_SLOT_KEY), &metadata.to_save_string());
+	metadata
+}
+
+/// The save slots: which one is being played, and what's known about each.
+///
+/// Each slot keeps its own progress (world flags) and room to continue from. Settings and the death log are shared by every slot.
+pub struct SaveSlots {
+	/// The slot being played.
+	active : usize,
+	/// What's known about each slot (None if it's empty).
+	metadata : Vec<Option<SlotMetadata>>,
+}
+
+impl SaveSlots {
+	/// Loads what's known about every slot, first bringing older saves up to the current format. Logs anything that couldn't be read (treating that slot as empty).
+	pub fn load(store : &mut dyn SaveStore) -> SaveSlots {
+		if migrate_unslotted(store) {
+			debug_log!("Moved the old save into the first save slot");
+		}
+		let mut metadata = Vec::with_capacity(SAVE_SLOT_COUNT);
+		for slot in 0..SAVE_SLOT_COUNT {
+			let text = store.load(&slot_key(slot, METADATA_SLOT_KEY));
+			metadata.push(if text.is_empty() {
+				None
+			} else {
+				match SlotMetadata::from_save_string(&text) {
+					Ok(loaded) => Some(migrate_slot(store, slot, loaded)),
+					Err(error) => {
+						log(&format!("Couldn't load save slot {} due to: {}", slot + 1, error));
+						None
+					},
+				}
+			});
+		}
+		let active = store.load(ACTIVE_SLOT_KEY).parse::<usize>().ok().filter(|slot| *slot < SAVE_SLOT_COUNT).unwrap_or(0);
+		SaveSlots { active, metadata }
+	}
+
+	/// Gets the slot being played.
+	pub fn get_active(&self) -> usize {
+		self.active
+	}
+
+	/// Switches which slot is played (and remembers it for next time).
+	pub fn set_active(&mut self, store : &mut dyn SaveStore, slot : usize) {
+		crate::lean_assert!(slot < SAVE_SLOT_COUNT, "There's no save slot {}", slot);
+		self.active = slot;
+		store.store(ACTIVE_SLOT_KEY, &slot.to_string());
+	}
+
+	/// Gets the key something in the active slot is saved under.
+	pub fn key(&self, name : &str) -> String {
+		slot_key(self.active, name)
+	}
+
+	/// Gets what's known about a slot (None if it's empty).
+	pub fn get_metadata(&self, slot : usize) -> Option<&SlotMetadata> {
+		self.metadata.get(slot).and_then(|metadata| metadata.as_ref())
+	}
+
+	/// Describes a slot in a few words.
+	pub fn summary(&self, slot : usize) -> String {
+		self.get_metadata(slot).map_or(String::from("Empty"), |metadata| metadata.summary())
+	}
+
+	/// Records playing the active slot: adds to its play time, and sets its completion and when it was saved.
+	pub fn record(&mut self, store : &mut dyn SaveStore, played_seconds : f64, completion : f32, timestamp : f64) {
+		let metadata = self.metadata[self.active].get_or_insert_with(SlotMetadata::new);
+		metadata.playtime_seconds += played_seconds;
+		metadata.completion = completion;
+		metadata.timestamp = timestamp;
+		store.store(&slot_key(self.active, METADATA_SLOT_KEY), &metadata.to_save_string());
+	}
+
+	/// Copies everything in one slot over another. Copying an empty slot empties the other.
+	pub fn copy(&mut self, store : &mut dyn SaveStore, from : usize, to : usize) {
+		if from == to {
+			return;
+		}
+		for name in SLOT_KEYS.iter() {
+			let data = store.load(&slot_key(from, name));
+			store.store(&slot_key(to, name), &data);
+		}
+		self.metadata[to] = self.metadata[from].clone();
+	}
+
+	/// Empties a slot.
+	pub fn delete(&mut self, store : &mut dyn SaveStore, slot : usize) {
+		for name in SLOT_KEYS.iter() {
+			store.store(&slot_key(slot, name), "");
+		}
+		self.metadata[slot] = None;
+	}
+}
+
+#[cfg(test)]
+mod tests_save_slots {
+	use super::*;
+	use std::collections::HashMap;
+
+	impl SaveStore for HashMap<String, String> {
+		fn load(&self, key : &str) -> String {
+			self.get(key).cloned().unwrap_or_default()
+		}
+
+		fn store(&mut self, key : &str, data : &str) {
+			if data.is_empty() {
+				self.remove(key);
+			} else {
+				self.insert(key.to_string(), data.to_string());
+			}
+		}
+	}
+
+	#[test]
+	fn metadata() {
+		let metadata = SlotMetadata { version : SAVE_FORMAT_VERSION, playtime_seconds : 7509.5, completion : 0.4, timestamp : 1709251200.0 };
+		assert_eq!(SlotMetadata::from_save_string(&metadata.to_save_string()).unwrap(), metadata);
+		assert_eq!(metadata.summary(), "2:05:09, 40%, saved 2024-03-01");
+		assert_eq!(SlotMetadata::new().summary(), "0:00:00, 0%");
+		assert_eq!(civil_date(0), (1970, 1, 1));
+		assert_eq!(civil_date(-1), (1969, 12, 31));
+		// Newer saves are refused rather than misread.
+		assert!(SlotMetadata::from_save_string("version=99").is_err());
+		assert!(SlotMetadata::from_save_string("playtime=soon").is_err());
+		assert_eq!(SlotMetadata::from_save_string("playtime=5").unwrap().version, 1);
+
+		let mut flags = WorldFlags::new();
+		assert!(flags.load_save_string("objective.door=true\nsecret.0=true\nsecret.1=false\ncollected.coin=3").is_ok());
+		assert_eq!(completion(&flags), 2.0 / COMPLETION_GOAL_COUNT as f32);
+	}
+
+	#[test]
+	fn migrates_unslotted_saves() {
+		let mut store : HashMap<String, String> = HashMap::new();
+		store.store("worldFlags", "switch.a=true");
+		store.store("continue", "room.json");
+		store.store("accessibility", "highContrast=true");
+		let slots = SaveSlots::load(&mut store);
+		assert_eq!(store.load("slot0.worldFlags"), "switch.a=true");
+		assert_eq!(store.load("slot0.continue"), "room.json");
+		assert!(store.load("worldFlags").is_empty());
+		assert_eq!(store.load("accessibility"), "highContrast=true"); // Shared by every slot, so it stays put.
+		assert_eq!(slots.get_metadata(0).unwrap().version, SAVE_FORMAT_VERSION);
+		assert!(slots.get_metadata(1).is_none());
+
+		// Slots from older formats are brought up to date.
+		store.store("slot1.meta", "playtime=5");
+		assert_eq!(SaveSlots::load(&mut store).get_metadata(1).unwrap().version, SAVE_FORMAT_VERSION);
+		assert!(store.load("slot1.meta").starts_with(&format!("version={}", SAVE_FORMAT_VERSION)));
+
+		// The old save is only moved once.
+		store.store("worldFlags", "switch.b=true");
+		SaveSlots::load(&mut store);
+		assert_eq!(store.load("slot0.worldFlags"), "switch.a=true");
+	}
+
+	#[test]
+	fn managing_slots() {
+		let mut store : HashMap<String, String> = HashMap::new();
+		let mut slots = SaveSlots::load(&mut store);
+		assert_eq!(slots.get_active(), 0);
+		assert_eq!(slots.summary(2), "Empty");
+		slots.set_active(&mut store, 2);
+		store.store(&slots.key(WORLD_FLAGS_SLOT_KEY), "keys=2");
+		slots.record(&mut store, 30.0, 0.5, 0.0);
+		slots.record(&mut store, 15.0, 0.6, 0.0);
+		assert_eq!(slots.summary(2), "0:00:45, 60%");
+
+		slots.copy(&mut store, 2, 1);
+		assert_eq!(store.load("slot1.worldFlags"), "keys=2");
+		assert_eq!(slots.get_metadata(1), slots.get_metadata(2));
+		slots.delete(&mut store, 2);
+		assert!(store.load("slot2.worldFlags").is_empty());
+		assert!(slots.get_metadata(2).is_none());
+
+		// Everything's read back the same way.
+		let reloaded = SaveSlots::load(&mut store);
+		assert_eq!(reloaded.get_active(), 2);
+		assert_eq!(reloaded.get_metadata(1), slots.get_metadata(1));
+		assert!(reloaded.get_metadata(2).is_none());
+	}
+}
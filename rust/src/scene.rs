@@ -0,0 +1,124 @@
+/// The systems turned off on the title screen: everything that reads gameplay input, and the HUD drawn over the world.
+const MENU_DISABLED_SYSTEMS : [&str; 10] = ["photo_mode", "time_control", "rewind", "objective_markers", "kick_meter", "bullet_time_meter", "stamina_meter", "coop_meters", "prompts", "challenge_timer"];
+/// The systems turned off while playing. The demo only ever starts from an idle title screen.
+const PLAYING_DISABLED_SYSTEMS : [&str; 1] = ["attract_mode"];
+/// The systems turned off under the pause menu: the same as the title screen, plus the demo.
+const PAUSED_DISABLED_SYSTEMS : [&str; 11] = ["attract_mode", "photo_mode", "time_control", "rewind", "objective_markers", "kick_meter", "bullet_time_meter", "stamina_meter", "coop_meters", "prompts", "challenge_timer"];
+/// The systems turned off while the demo plays: the same as the title screen (as the demo drives the player itself), plus anything that could change the save or leave the room.
+const DEMO_DISABLED_SYSTEMS : [&str; 12] = ["photo_mode", "time_control", "rewind", "objective_markers", "kick_meter", "bullet_time_meter", "stamina_meter", "coop_meters", "prompts", "challenge_timer", "switches", "doors"];
+
+/// A mode the game can be in, which decides whether the simulation runs and which systems are turned off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scene {
+	/// The title screen (what quitting goes back to).
+	Title,
+	/// Playing a room.
+	Playing,
+	/// The pause menu, over a room.
+	Paused,
+	/// The demo (see `attract_mode`), played over the title screen once it's been idle for a while.
+	Demo,
+}
+
+impl Scene {
+	/// Whether the simulation should move forward in this scene.
+	pub fn runs_simulation(&self) -> bool {
+		Scene::Playing == *self || Scene::Demo == *self
+	}
+
+	/// Gets the names of the systems to turn off in this scene (see `Scheduler::apply_scene()`).
+	pub fn disabled_systems(&self) -> &'static [&'static str] {
+		match self {
+			Scene::Title => &MENU_DISABLED_SYSTEMS,
+			Scene::Playing => &PLAYING_DISABLED_SYSTEMS,
+			Scene::Paused => &PAUSED_DISABLED_SYSTEMS,
+			Scene::Demo => &DEMO_DISABLED_SYSTEMS,
+		}
+	}
+}
+
+/// A stack of scenes. Only the top one is active, and the ones under it are picked back up as it's popped off.
+///
+/// The bottom scene is never removed.
+pub struct SceneStack {
+	/// The scenes, bottom first.
+	scenes : Vec<Scene>,
+}
+
+impl SceneStack {
+	/// Creates an instance with just the bottom scene.
+	pub fn new(base : Scene) -> SceneStack {
+		SceneStack {
+			scenes : vec![base],
+		}
+	}
+
+	/// Gets the active scene.
+	pub fn current(&self) -> Scene {
+		*self.scenes.last().unwrap()
+	}
+
+	/// Puts a scene on top (making it the active one).
+	pub fn push(&mut self, scene : Scene) {
+		self.scenes.push(scene);
+	}
+
+	/// Removes the active scene, going back to the one under it. Gives the removed scene, or None if only the bottom one is left.
+	pub fn pop(&mut self) -> Option<Scene> {
+		if 1 < self.scenes.len() {
+			self.scenes.pop()
+		} else {
+			None
+		}
+	}
+
+	/// Pops scenes until a given one is active. Returns false (changing nothing) if it's not in the stack.
+	pub fn unwind_to(&mut self, scene : Scene) -> bool {
+		match self.scenes.iter().rposition(|existing| scene == *existing) {
+			Some(index) => {
+				self.scenes.truncate(index + 1);
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_scene {
+	use super::*;
+
+	#[test]
+	fn stacking() {
+		let mut scenes = SceneStack::new(Scene::Title);
+		assert_eq!(scenes.pop(), None);
+		scenes.push(Scene::Playing);
+		scenes.push(Scene::Paused);
+		assert_eq!(scenes.current(), Scene::Paused);
+		assert!(!scenes.current().runs_simulation());
+		assert_eq!(scenes.pop(), Some(Scene::Paused));
+		assert!(scenes.current().runs_simulation());
+		assert!(!scenes.current().disabled_systems().contains(&"rewind"));
+
+		scenes.push(Scene::Paused);
+		assert!(scenes.unwind_to(Scene::Title));
+		assert_eq!(scenes.current(), Scene::Title);
+		assert!(!scenes.unwind_to(Scene::Paused));
+		assert_eq!(scenes.current(), Scene::Title);
+	}
+
+	#[test]
+	fn demo_only_from_title() {
+		// The demo can only start from the title screen, and has to keep running while it plays (to notice it should stop).
+		for scene in [Scene::Title, Scene::Demo].iter() {
+			assert!(!scene.disabled_systems().contains(&"attract_mode"), "{:?}", scene);
+		}
+		for scene in [Scene::Playing, Scene::Paused].iter() {
+			assert!(scene.disabled_systems().contains(&"attract_mode"), "{:?}", scene);
+		}
+		// It plays out a room, but can't leave it.
+		assert!(Scene::Demo.runs_simulation());
+		assert!(Scene::Demo.disabled_systems().contains(&"doors"));
+		assert!(!Scene::Title.runs_simulation());
+	}
+}
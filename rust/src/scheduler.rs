@@ -0,0 +1,203 @@
+/// The broad stages of an update, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+	/// Reading input and deciding on modes (every update).
+	Input,
+	/// Moving the game world forward (once per simulation step, if there is one).
+	Simulation,
+	/// Reacting to what happened during a simulation step (right after it).
+	PostSimulation,
+	/// Bringing the camera and everything drawn over the world in line with the current state (every update).
+	RenderSync,
+}
+
+/// How much time an update covers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTime {
+	/// How much real time passed (in seconds).
+	pub real_seconds : f32,
+	/// How much time the simulation moved forward (in seconds). Zero when it didn't step (as in when paused).
+	pub simulated_seconds : f32,
+	/// How far between the last two simulation steps things should be drawn (from 0 for the previous one to 1 for the latest).
+	pub interpolation : f32,
+}
+
+impl FrameTime {
+	/// Gets how much time passed on some clock (in seconds).
+	pub fn seconds(&self, clock : Clock) -> f32 {
+		match clock {
+			Clock::Simulation => self.simulated_seconds,
+			Clock::Real => self.real_seconds,
+		}
+	}
+}
+
+/// Which time an effect moves forward on.
+///
+/// Real time is the whole update's time, and so is only right for systems outside of the simulation phases (those are handed it again on every step).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+	/// The simulation's time, which stops when the game's paused (and follows slow motion).
+	Simulation,
+	/// Real time, which keeps going when the game's paused (for menus and the HUD).
+	Real,
+}
+
+impl Clock {
+	/// Looks up a clock by name (as used in Tiled properties). Returns None if the name isn't known.
+	pub fn from_name(name : &str) -> Option<Clock> {
+		match name {
+			"simulation" => Some(Clock::Simulation),
+			"real" => Some(Clock::Real),
+			_ => None,
+		}
+	}
+}
+
+/// A system: a function that updates some part of the target.
+pub type System<T> = fn(&mut T, &FrameTime);
+
+/// A single registered system.
+struct SystemEntry<T> {
+	/// The unique name it's registered under.
+	name : String,
+	/// When it runs.
+	phase : Phase,
+	/// Where it runs within its phase. Lower runs first (ties run in the order they were added).
+	priority : i32,
+	/// Whether it runs at all.
+	enabled : bool,
+	/// What it runs.
+	system : System<T>,
+}
+
+/// Decides what order systems run in during an update.
+///
+/// Systems register under a phase and a priority (instead of being called in some hand-written order), and can be turned on and off by name (as in for a specific scene).
+/// The scheduler only hands out the ordered systems; the owner runs them. That way the scheduler can be stored inside the thing the systems update.
+pub struct Scheduler<T> {
+	/// All systems, kept in run order.
+	systems : Vec<SystemEntry<T>>,
+}
+
+impl<T> Scheduler<T> {
+	/// Creates an instance without any systems.
+	pub fn new() -> Scheduler<T> {
+		Scheduler {
+			systems : Vec::new(),
+		}
+	}
+
+	/// Registers a system (enabled). Fails if the name is already taken.
+	pub fn add(&mut self, name : &str, phase : Phase, priority : i32, system : System<T>) -> Result<(), String> {
+		if self.systems.iter().any(|entry| name == entry.name) {
+			return Err(format!("A system named {:?} already exists", name));
+		}
+		// Insert after everything that should run first, so ties keep the order they were added in.
+		let index = self.systems.iter().position(|entry| (phase, priority) < (entry.phase, entry.priority)).unwrap_or(self.systems.len());
+		self.systems.insert(index, SystemEntry {
+			name : name.to_string(),
+			phase,
+			priority,
+			enabled : true,
+			system,
+		});
+		Ok(())
+	}
+
+	/// Turns a system on or off. Returns false if there's no system with that name.
+	pub fn set_enabled(&mut self, name : &str, enabled : bool) -> bool {
+		match self.systems.iter_mut().find(|entry| name == entry.name) {
+			Some(entry) => {
+				entry.enabled = enabled;
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Whether a system exists and is turned on.
+	pub fn is_enabled(&self, name : &str) -> bool {
+		self.systems.iter().any(|entry| name == entry.name && entry.enabled)
+	}
+
+	/// Turns on every system except the given ones (as in when switching to a scene that doesn't need them). Returns the names that didn't match any system.
+	pub fn apply_scene<'a>(&mut self, disabled : &[&'a str]) -> Vec<&'a str> {
+		for entry in &mut self.systems {
+			entry.enabled = !disabled.contains(&entry.name.as_str());
+		}
+		disabled.iter().filter(|name| !self.systems.iter().any(|entry| **name == entry.name)).cloned().collect()
+	}
+
+	/// Gets the names of all systems in a phase, in run order (including disabled ones).
+	pub fn get_names(&self, phase : Phase) -> Vec<&str> {
+		self.systems.iter().filter(|entry| phase == entry.phase).map(|entry| entry.name.as_str()).collect()
+	}
+
+	/// Gets the enabled systems in a phase, in the order they should run.
+	pub fn get_systems(&self, phase : Phase) -> Vec<System<T>> {
+		self.systems.iter().filter(|entry| phase == entry.phase && entry.enabled).map(|entry| entry.system).collect()
+	}
+
+	/// Runs all the enabled systems in a phase on a target that doesn't hold this scheduler.
+	pub fn run(&self, phase : Phase, target : &mut T, time : &FrameTime) {
+		for system in self.get_systems(phase) {
+			system(target, time);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_scheduler {
+	use super::*;
+
+	fn first(log : &mut Vec<&'static str>, _ : &FrameTime) { log.push("first"); }
+	fn second(log : &mut Vec<&'static str>, _ : &FrameTime) { log.push("second"); }
+	fn third(log : &mut Vec<&'static str>, _ : &FrameTime) { log.push("third"); }
+	fn input(log : &mut Vec<&'static str>, _ : &FrameTime) { log.push("input"); }
+
+	#[test]
+	fn ordering() {
+		let time = FrameTime { real_seconds : 0.1, simulated_seconds : 0.1, interpolation : 1.0 };
+		let mut scheduler : Scheduler<Vec<&'static str>> = Scheduler::new();
+		assert!(scheduler.add("third", Phase::Simulation, 10, third).is_ok());
+		assert!(scheduler.add("second", Phase::Simulation, 0, second).is_ok());
+		assert!(scheduler.add("first", Phase::Simulation, 0, first).is_ok());
+		assert!(scheduler.add("input", Phase::Input, 50, input).is_ok());
+		assert!(scheduler.add("first", Phase::Input, 0, first).is_err());
+		assert_eq!(scheduler.get_names(Phase::Simulation), vec!["second", "first", "third"]); // Ties keep the order they were added in.
+
+		let mut log = Vec::new();
+		scheduler.run(Phase::Input, &mut log, &time);
+		scheduler.run(Phase::Simulation, &mut log, &time);
+		assert_eq!(log, vec!["input", "second", "first", "third"]);
+	}
+
+	#[test]
+	fn enabling() {
+		let time = FrameTime { real_seconds : 0.1, simulated_seconds : 0.0, interpolation : 1.0 };
+		let mut scheduler : Scheduler<Vec<&'static str>> = Scheduler::new();
+		scheduler.add("first", Phase::RenderSync, 0, first).unwrap();
+		scheduler.add("second", Phase::RenderSync, 1, second).unwrap();
+		assert!(scheduler.set_enabled("first", false));
+		assert!(!scheduler.set_enabled("missing", false));
+		assert!(!scheduler.is_enabled("first"));
+
+		let mut log = Vec::new();
+		scheduler.run(Phase::RenderSync, &mut log, &time);
+		assert_eq!(log, vec!["second"]);
+
+		assert_eq!(scheduler.apply_scene(&["second", "missing"]), vec!["missing"]);
+		assert!(scheduler.is_enabled("first"));
+		assert!(!scheduler.is_enabled("second"));
+	}
+
+	#[test]
+	fn clocks() {
+		let paused = FrameTime { real_seconds : 0.1, simulated_seconds : 0.0, interpolation : 1.0 };
+		assert_eq!(paused.seconds(Clock::Simulation), 0.0);
+		assert_eq!(paused.seconds(Clock::Real), 0.1);
+		assert_eq!(Clock::from_name("real"), Some(Clock::Real));
+		assert_eq!(Clock::from_name("wall"), None);
+	}
+}
@@ -0,0 +1,287 @@
+use crate::color::*;
+use crate::palette::*;
+use crate::display_buffer::*;
+use crate::geo::vec3::*;
+use crate::geo::mat4::*;
+use crate::tuning::Tuning;
+use crate::room_transition::TransitionStyle;
+use crate::tween::*;
+use crate::render_view::*;
+use crate::scheduler::{Clock, FrameTime};
+
+/// How far in from the edges of the screen the vignette reaches (as a fraction of the screen's size).
+const VIGNETTE_REACH : f32 = 0.2;
+/// The depth to draw the vignette at (in front of everything).
+const VIGNETTE_DEPTH : f32 = -0.99;
+/// How much of the heartbeat's peak intensity the vignette keeps between beats.
+const HEARTBEAT_RESTING_FRACTION : f32 = 0.3;
+/// The depth to draw room transitions at (in front of even the vignette).
+const TRANSITION_DEPTH : f32 = -0.995;
+/// How many points make up the iris transition's circle.
+const IRIS_POINTS : usize = 48;
+
+/// Works out how strong the damage vignette should be.
+///
+/// The damage flash is on simulation time (so it freezes with everything else when paused), but the low health heartbeat is on real time so it keeps pulsing under the pause menu.
+/// Kept apart from the display so it can be tested.
+pub struct VignetteIntensity {
+	/// The flash from the most recent damage.
+	flash : Tween,
+	/// The pulse from the current heartbeat.
+	heartbeat : Tween,
+}
+
+impl VignetteIntensity {
+	/// Creates an instance with no vignette showing.
+	pub fn new() -> VignetteIntensity {
+		VignetteIntensity {
+			flash : Tween::hold(0.0),
+			heartbeat : Tween::hold(0.0).with_clock(Clock::Real),
+		}
+	}
+
+	/// Starts a new flash (as in from taking damage).
+	pub fn flash(&mut self, tuning : &Tuning) {
+		self.flash = Tween::new(tuning.vignette_intensity, 0.0, tuning.vignette_flash_seconds, Easing::QuadOut);
+	}
+
+	/// Moves forward in time. Returns the vignette's alpha (from 0 to 1).
+	pub fn update(&mut self, time : &FrameTime, health_fraction : f32, tuning : &Tuning) -> f32 {
+		let flash = self.flash.tick(time);
+		let mut pulse = 0.0;
+		if health_fraction < tuning.low_health_fraction && 0.0 < health_fraction {
+			pulse = self.heartbeat.tick(time);
+			if self.heartbeat.is_done() && 0.0 < tuning.heartbeat_rate {
+				self.heartbeat = Tween::new(tuning.vignette_intensity, tuning.vignette_intensity * HEARTBEAT_RESTING_FRACTION, 1.0 / tuning.heartbeat_rate, Easing::QuadOut).with_clock(Clock::Real);
+			}
+		} else {
+			self.heartbeat = Tween::hold(0.0).with_clock(Clock::Real);
+		}
+		flash.max(pulse).max(0.0).min(1.0)
+	}
+}
+
+/// Full-screen effects drawn over the game world.
+pub struct ScreenEffects {
+	/// How strong the vignette currently is.
+	vignette_intensity : VignetteIntensity,
+	/// The alpha the vignette was last drawn with.
+	vignette_alpha : f32,
+	/// The screen size the vignette was last drawn for.
+	vignette_size : Vec3,
+	/// What the vignette is drawn in.
+	vignette : DisplayBuffer,
+	/// The color of the vignette (its alpha is ignored).
+	vignette_color : Color,
+	/// The room transition being shown (if any).
+	transition_style : Option<TransitionStyle>,
+	/// How much of the screen the room transition covers (from 0 to 1).
+	transition_coverage : f32,
+	/// The screen size the room transition was last drawn for.
+	transition_size : Vec3,
+	/// What room transitions are drawn in.
+	transition : DisplayBuffer,
+}
+
+impl ScreenEffects {
+	/// Creates an instance with nothing showing.
+	pub fn new() -> ScreenEffects {
+		// They cover the camera's view, so they'd only be in the way in any other view.
+		let mut vignette = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		vignette.set_views(view_bit(MAIN_VIEW));
+		let mut transition = DisplayBuffer::new(DisplayBufferType::SOLIDS);
+		transition.set_views(view_bit(MAIN_VIEW));
+		ScreenEffects {
+			vignette_intensity : VignetteIntensity::new(),
+			vignette_alpha : 0.0,
+			vignette_size : Vec3::zero(),
+			vignette,
+			vignette_color : Palette::new(PaletteKind::Standard).get(PaletteRole::Danger),
+			transition_style : None,
+			transition_coverage : 0.0,
+			transition_size : Vec3::zero(),
+			transition,
+		}
+	}
+
+	/// Sets the room transition to show (if any), and how much of the screen it covers (from 0 to 1).
+	/// This is shown even when the other effects are hidden, so nothing is seen while rooms swap.
+	pub fn set_transition(&mut self, style : Option<TransitionStyle>, coverage : f32) {
+		if style != self.transition_style || coverage != self.transition_coverage {
+			self.transition_style = style;
+			self.transition_coverage = coverage;
+			self.redraw_transition();
+		}
+	}
+
+	/// Sets the color of the vignette (its alpha is ignored).
+	pub fn set_vignette_color(&mut self, color : &Color) {
+		self.vignette_color = color.clone();
+		self.redraw_vignette();
+	}
+
+	/// Flashes the damage vignette.
+	pub fn on_damage(&mut self, tuning : &Tuning) {
+		self.vignette_intensity.flash(tuning);
+	}
+
+	/// Shows or hides all effects.
+	pub fn set_visible(&mut self, visible : bool) {
+		if visible {
+			self.vignette.show();
+		} else {
+			self.vignette.hide();
+		}
+	}
+
+	/// Updates all effects.
+	/// The camera's center and screen size are needed to keep the effects covering the screen.
+	pub fn update(&mut self, time : &FrameTime, health_fraction : f32, tuning : &Tuning, camera_center : &Vec3, screen_size : &Vec3) {
+		let alpha = self.vignette_intensity.update(time, health_fraction, tuning);
+		if alpha != self.vignette_alpha || screen_size.x != self.vignette_size.x || screen_size.y != self.vignette_size.y {
+			self.vignette_alpha = alpha;
+			self.vignette_size = screen_size.clone();
+			self.redraw_vignette();
+		}
+		if screen_size.x != self.transition_size.x || screen_size.y != self.transition_size.y {
+			self.transition_size = screen_size.clone();
+			self.redraw_transition();
+		}
+		let mut transform = Mat4::new();
+		transform.translate_before(&Vec3::new(camera_center.x, camera_center.y, 0.0));
+		self.vignette.set_transform(&transform);
+		self.transition.set_transform(&transform);
+	}
+
+	/// Rebuilds the room transition's cover.
+	fn redraw_transition(&mut self) {
+		let mut editor = self.transition.make_editor();
+		editor.clear();
+		let style = match self.transition_style {
+			Some(style) => style,
+			None => { return; },
+		};
+		if 0.0 >= self.transition_coverage {
+			return;
+		}
+		let coverage = self.transition_coverage.min(1.0);
+		let half_width = self.transition_size.x / 2.0;
+		let half_height = self.transition_size.y / 2.0;
+		let black = Color::new(0, 0, 0, 255);
+		let rect = |x_min : f32, y_min : f32, x_max : f32, y_max : f32| vec![
+			Vec3::new(x_min, y_min, TRANSITION_DEPTH),
+			Vec3::new(x_max, y_min, TRANSITION_DEPTH),
+			Vec3::new(x_max, y_max, TRANSITION_DEPTH),
+			Vec3::new(x_min, y_max, TRANSITION_DEPTH),
+		];
+		let width = 2.0 * half_width * coverage;
+		let height = 2.0 * half_height * coverage;
+		match style {
+			TransitionStyle::Fade => {
+				editor.add_polygon(&rect(-half_width, -half_height, half_width, half_height), &Color::new(0, 0, 0, (coverage * 255.0) as u8));
+			},
+			TransitionStyle::SlideLeft => {
+				editor.add_polygon(&rect(half_width - width, -half_height, half_width, half_height), &black);
+			},
+			TransitionStyle::SlideRight => {
+				editor.add_polygon(&rect(-half_width, -half_height, -half_width + width, half_height), &black);
+			},
+			TransitionStyle::SlideUp => {
+				editor.add_polygon(&rect(-half_width, -half_height, half_width, -half_height + height), &black);
+			},
+			TransitionStyle::SlideDown => {
+				editor.add_polygon(&rect(-half_width, half_height - height, half_width, half_height), &black);
+			},
+			TransitionStyle::Iris => {
+				// A ring from past the screen's corners in to a hole that shrinks to nothing.
+				let outer_radius = (half_width * half_width + half_height * half_height).sqrt() + 1.0;
+				let inner_radius = outer_radius * (1.0 - coverage);
+				let mut points = Vec::with_capacity(2 * (IRIS_POINTS + 1));
+				let mut colors = Vec::with_capacity(2 * (IRIS_POINTS + 1));
+				for index in 0..=IRIS_POINTS {
+					let angle = 2.0 * std::f32::consts::PI * (index as f32) / (IRIS_POINTS as f32);
+					let (sin, cos) = angle.sin_cos();
+					points.push(Vec3::new(cos * outer_radius, sin * outer_radius, TRANSITION_DEPTH));
+					colors.push(black.clone());
+					points.push(Vec3::new(cos * inner_radius, sin * inner_radius, TRANSITION_DEPTH));
+					colors.push(black.clone());
+				}
+				editor.add_triangle_strip(&points, &colors);
+			},
+		}
+	}
+
+	/// Rebuilds the vignette as a ring from the edges of the screen (fully colored) in to a transparent inner edge.
+	fn redraw_vignette(&mut self) {
+		let mut editor = self.vignette.make_editor();
+		editor.clear();
+		if 0.0 == self.vignette_alpha {
+			return;
+		}
+		let base = &self.vignette_color;
+		let outer_color = Color::new(base.red, base.green, base.blue, (self.vignette_alpha * 255.0) as u8);
+		let inner_color = Color::new(base.red, base.green, base.blue, 0);
+		let half_width = self.vignette_size.x / 2.0;
+		let half_height = self.vignette_size.y / 2.0;
+		let inner_width = half_width * (1.0 - 2.0 * VIGNETTE_REACH);
+		let inner_height = half_height * (1.0 - 2.0 * VIGNETTE_REACH);
+		let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)];
+		let mut points = Vec::with_capacity(corners.len() * 2);
+		let mut colors = Vec::with_capacity(corners.len() * 2);
+		for (x, y) in corners.iter() {
+			points.push(Vec3::new(x * half_width, y * half_height, VIGNETTE_DEPTH));
+			colors.push(outer_color.clone());
+			points.push(Vec3::new(x * inner_width, y * inner_height, VIGNETTE_DEPTH));
+			colors.push(inner_color.clone());
+		}
+		editor.add_triangle_strip(&points, &colors);
+	}
+}
+
+#[cfg(test)]
+mod tests_vignette_intensity {
+	use super::*;
+
+	/// Makes the time for an update where the simulation moved along with real time.
+	fn running(seconds : f32) -> FrameTime {
+		FrameTime { real_seconds : seconds, simulated_seconds : seconds, interpolation : 1.0 }
+	}
+
+	/// Makes the time for an update where the game's paused.
+	fn paused(seconds : f32) -> FrameTime {
+		FrameTime { real_seconds : seconds, simulated_seconds : 0.0, interpolation : 1.0 }
+	}
+
+	#[test]
+	fn flash_fades() {
+		let tuning = Tuning::new();
+		let mut vignette = VignetteIntensity::new();
+		assert_eq!(vignette.update(&running(0.1), 1.0, &tuning), 0.0);
+		vignette.flash(&tuning);
+		let first = vignette.update(&running(0.01), 1.0, &tuning);
+		assert!(0.0 < first);
+		let second = vignette.update(&running(0.01), 1.0, &tuning);
+		assert!(second < first);
+		assert_eq!(vignette.update(&paused(0.5), 1.0, &tuning), second); // Frozen while paused.
+		assert_eq!(vignette.update(&running(tuning.vignette_flash_seconds), 1.0, &tuning), 0.0);
+	}
+
+	#[test]
+	fn heartbeat_only_when_low() {
+		let tuning = Tuning::new();
+		let mut vignette = VignetteIntensity::new();
+		let low = tuning.low_health_fraction / 2.0;
+		let mut peak : f32 = 0.0;
+		let mut trough : f32 = 1.0;
+		for _ in 0..120 {
+			let alpha = vignette.update(&paused(1.0 / 60.0), low, &tuning); // Keeps beating while paused.
+			peak = peak.max(alpha);
+			trough = trough.min(alpha);
+		}
+		assert!(tuning.vignette_intensity * 0.9 < peak);
+		assert!(trough < peak);
+
+		// And stops once healed.
+		assert_eq!(vignette.update(&running(1.0 / 60.0), 1.0, &tuning), 0.0);
+	}
+}
@@ -0,0 +1,126 @@
+use crate::event_bus::*;
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::tiled::TiledFile;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled rectangle type that marks a secret area. The rectangle's name is the tile layer that hides it.
+const SECRET_REGION_TYPE : &str = "secret";
+
+/// An area of the level that's hidden behind tiles until the player goes into it.
+#[derive(Debug, Clone)]
+pub struct SecretRegion {
+	/// The name of the tile layer that covers the secret.
+	pub layer_name : String,
+	/// The area the secret covers.
+	pub bounds : Bounds2,
+	/// Whether the player has found it yet.
+	pub found : bool,
+}
+
+/// All of the secret areas in the level.
+pub struct Secrets {
+	/// Every secret area (in the order they were loaded).
+	regions : Vec<SecretRegion>,
+}
+
+impl Secrets {
+	/// Creates an instance with no secrets.
+	pub fn new() -> Secrets {
+		Secrets {
+			regions : Vec::new(),
+		}
+	}
+
+	/// Replaces all secrets with the ones in a Tiled file.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.regions.clear();
+		for region in file.get_regions() {
+			if SECRET_REGION_TYPE == region.r#type {
+				self.add(&region.name, &region.bounds);
+			}
+		}
+	}
+
+	/// Adds a secret area covered by the given tile layer. Returns its index.
+	pub fn add(&mut self, layer_name : &str, bounds : &Bounds2) -> usize {
+		self.regions.push(SecretRegion {
+			layer_name : layer_name.to_string(),
+			bounds : bounds.clone(),
+			found : false,
+		});
+		self.regions.len() - 1
+	}
+
+	/// Gets all secret areas.
+	pub fn get_regions<'a>(&'a self) -> &'a Vec<SecretRegion> {
+		&self.regions
+	}
+
+	/// Gets the centers of all secrets that haven't been found yet.
+	pub fn get_hidden_centers(&self) -> Vec<Vec2> {
+		let mut centers = Vec::new();
+		for region in &self.regions {
+			if !region.found {
+				centers.push(Vec2::new(
+					(region.bounds.x_min() + region.bounds.x_max()) / 2.0,
+					(region.bounds.y_min() + region.bounds.y_max()) / 2.0,
+				));
+			}
+		}
+		centers
+	}
+
+	/// How many secrets have been found.
+	pub fn count_found(&self) -> usize {
+		self.regions.iter().filter(|region| region.found).count()
+	}
+
+	/// Marks any secrets the position is inside of as found, posting an event for each.
+	/// Returns the indices of the newly found secrets.
+	pub fn check_entered(&mut self, position : &Vec2, events : &mut EventBus) -> Vec<usize> {
+		let mut found = Vec::new();
+		for (index, region) in self.regions.iter_mut().enumerate() {
+			if !region.found && region.bounds.overlaps_point(position) {
+				region.found = true;
+				events.post(GameEvent::SecretFound(index));
+				found.push(index);
+			}
+		}
+		found
+	}
+}
+
+impl ShiftOrigin for Secrets {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for region in &mut self.regions {
+			region.bounds.shift_origin(shift);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_secrets {
+	use super::*;
+
+	#[test]
+	fn finding() {
+		let mut secrets = Secrets::new();
+		let mut events = EventBus::new();
+		secrets.add("cover", &Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(20.0, 10.0)));
+		let second = secrets.add("cover", &Bounds2::from_points(&Vec2::new(100.0, 0.0), &Vec2::new(120.0, 10.0)));
+		assert_eq!(secrets.get_hidden_centers().len(), 2);
+		assert!(secrets.check_entered(&Vec2::new(50.0, 5.0), &mut events).is_empty());
+		assert_eq!(secrets.check_entered(&Vec2::new(110.0, 5.0), &mut events), vec![second]);
+		assert_eq!(events.drain(), vec![GameEvent::SecretFound(second)]);
+		assert_eq!(secrets.count_found(), 1);
+		let hidden = secrets.get_hidden_centers();
+		assert_eq!(hidden.len(), 1);
+		assert_eq!(hidden[0].x, 10.0);
+		assert_eq!(hidden[0].y, 5.0);
+
+		// Only found once.
+		assert!(secrets.check_entered(&Vec2::new(110.0, 5.0), &mut events).is_empty());
+		assert!(events.drain().is_empty());
+	}
+}
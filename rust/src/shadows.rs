@@ -0,0 +1,117 @@
+use std::f32::consts::PI;
+
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::consts::EPSILON;
+use crate::geo::collision_system::CollisionSystem;
+
+/// How far below something the ground can be and still have a shadow drawn on it.
+const SHADOW_MAX_HEIGHT : f32 = 96.0;
+/// How much wider than the thing casting it a shadow is when right on the ground.
+const SHADOW_WIDTH_SCALE : f32 = 1.2;
+/// How tall a shadow is compared to how wide it is.
+const SHADOW_FLATTENING : f32 = 0.3;
+/// How small a shadow gets (as a fraction of its size on the ground) at the max height.
+const SHADOW_MIN_SCALE : f32 = 0.4;
+/// How dark a shadow is when right on the ground (its alpha, from 0 to 255).
+const SHADOW_MAX_ALPHA : f32 = 140.0;
+/// How many points make up each shadow's ellipse.
+const SHADOW_POINT_COUNT : usize = 12;
+/// The depth to draw shadows at (in front of the tiles, behind everything else).
+const SHADOW_DEPTH : f32 = 0.4;
+
+/// Where and how to draw a single shadow.
+#[derive(Debug, Clone)]
+pub struct ShadowShape {
+	/// The center of the shadow (on the ground).
+	pub center : Vec2,
+	/// The width of the shadow (along the ground).
+	pub width : f32,
+	/// The height of the shadow (away from the ground).
+	pub height : f32,
+	/// How dark the shadow is (from 0 to 1).
+	pub opacity : f32,
+}
+
+impl ShadowShape {
+	/// Finds the shadow that something (a circle at some position) casts on the ground below it.
+	/// Returns None if there's no ground close enough below.
+	pub fn find(collision : &CollisionSystem, position : &Vec2, radius : f32, down : &Vec2) -> Option<ShadowShape> {
+		if EPSILON > down.length() {
+			return None;
+		}
+		let down = down.norm();
+		let distance = collision.raycast(position, &down, radius + SHADOW_MAX_HEIGHT)?.distance;
+		let fraction = ((distance - radius).max(0.0) / SHADOW_MAX_HEIGHT).min(1.0);
+		let scale = 1.0 - (1.0 - SHADOW_MIN_SCALE) * fraction;
+		let width = 2.0 * radius * SHADOW_WIDTH_SCALE * scale;
+		Some(ShadowShape {
+			center : position + down * distance,
+			width,
+			height : width * SHADOW_FLATTENING,
+			opacity : 1.0 - fraction,
+		})
+	}
+}
+
+/// Draws the shadows of everything that moves around, all in one buffer.
+pub struct Shadows {
+	/// What the shadows are drawn in.
+	display : DisplayBuffer,
+}
+
+impl Shadows {
+	/// Creates an instance showing no shadows.
+	pub fn new() -> Shadows {
+		Shadows {
+			display : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+		}
+	}
+
+	/// Redraws all the shadows cast by some circles (each a position and radius).
+	pub fn redraw(&mut self, casters : &[(Vec2, f32)], collision : &CollisionSystem, down : &Vec2) {
+		let mut editor = self.display.make_editor();
+		editor.clear();
+		if EPSILON > down.length() {
+			return;
+		}
+		let across = Vec2::new(-down.y, down.x).norm();
+		let up = down.norm().scale(-1.0);
+		for (position, radius) in casters {
+			if let Some(shadow) = ShadowShape::find(collision, position, *radius, down) {
+				let mut points = Vec::with_capacity(SHADOW_POINT_COUNT);
+				for index in 0..SHADOW_POINT_COUNT {
+					let angle = (index as f32) * 2.0 * PI / (SHADOW_POINT_COUNT as f32);
+					let point = shadow.center + across * (angle.cos() * shadow.width / 2.0) + up * (angle.sin() * shadow.height / 2.0);
+					points.push(Vec3::new(point.x, point.y, SHADOW_DEPTH));
+				}
+				editor.add_polygon(&points, &Color::new(0, 0, 0, (SHADOW_MAX_ALPHA * shadow.opacity) as u8));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_shadows {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	#[test]
+	fn shrinks_and_fades_with_height() {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0))));
+		let down = Vec2::new(0.0, -1.0);
+		let grounded = ShadowShape::find(&collision, &Vec2::new(0.0, 8.0), 8.0, &down).unwrap();
+		assert_eq!(grounded.center.y, 0.0);
+		assert_eq!(grounded.opacity, 1.0);
+		let jumping = ShadowShape::find(&collision, &Vec2::new(0.0, 56.0), 8.0, &down).unwrap();
+		assert_eq!(jumping.center.y, 0.0);
+		assert!(jumping.width < grounded.width);
+		assert!(jumping.opacity < grounded.opacity);
+		assert!(ShadowShape::find(&collision, &Vec2::new(0.0, 200.0), 8.0, &down).is_none());
+		assert!(ShadowShape::find(&collision, &Vec2::new(200.0, 8.0), 8.0, &down).is_none());
+	}
+}
@@ -0,0 +1,56 @@
+use crate::geo::world_vec2::WorldVec2;
+use crate::geo::collision_system::ObstacleSnapshot;
+use crate::player::PlayerSnapshot;
+use crate::camera::CameraSnapshot;
+use crate::enemies::Enemy;
+use crate::projectiles::Projectile;
+use crate::pickups::Pickup;
+use crate::moving_platform::PlatformSnapshot;
+use crate::tile_behavior::TileBehaviorSnapshot;
+use crate::random::Random;
+
+/// Everything needed to put the simulation back the way it was at some moment (see `Game::snapshot()` and `Game::restore()`).
+///
+/// This only covers what changes while playing a room. The room itself (its geometry, tiles, and so on) is expected to already be loaded, so a snapshot can only be restored into the same room it came from.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameSnapshot {
+	/// The URL of the room it was taken in.
+	pub room : String,
+	/// How long the simulation had been running (in seconds).
+	pub elapsed : f32,
+	/// Where the world origin was in map coordinates (see `origin_rebase`). Every other position is relative to this.
+	pub origin : WorldVec2,
+	/// The player.
+	pub player : PlayerSnapshot,
+	/// Every collision obstacle (which covers moving platforms' edges). Behavior tiles' obstacles are switched on and off to match `tile_behaviors` after these are put back.
+	pub obstacles : Vec<ObstacleSnapshot>,
+	/// Every behavior tile (like crumbling and breakable ones).
+	#[cfg_attr(feature = "serialize", serde(default))]
+	pub tile_behaviors : Vec<TileBehaviorSnapshot>,
+	/// The camera.
+	pub camera : CameraSnapshot,
+	/// The living enemies.
+	pub enemies : Vec<Enemy>,
+	/// The projectiles in flight.
+	pub projectiles : Vec<Projectile>,
+	/// Every pickup.
+	pub pickups : Vec<Pickup>,
+	/// Where each moving platform was.
+	pub platforms : Vec<PlatformSnapshot>,
+	/// The random number generator.
+	pub random : Random,
+}
+
+#[cfg(feature = "serialize")]
+impl GameSnapshot {
+	/// Writes this out as JSON.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(self).unwrap_or_else(|error| panic!("Couldn't write out a snapshot: {}", error))
+	}
+
+	/// Reads an instance back in from `to_json()`'s output.
+	pub fn from_json(text : &str) -> Result<GameSnapshot, String> {
+		serde_json::from_str(text).map_err(|error| format!("Couldn't read snapshot: {}", error))
+	}
+}
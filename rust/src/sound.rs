@@ -0,0 +1,165 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::geo::consts::EPSILON;
+use crate::geo::collision_system::CollisionSystem;
+use crate::tiled::TiledFile;
+use crate::origin_rebase::ShiftOrigin;
+use crate::asset_manifest::asset_url;
+use crate::externals::playSound;
+
+/// The Tiled rectangle type for areas where sounds echo (like caves).
+pub const REVERB_ZONE_TYPE : &str = "reverbZone";
+/// The Tiled rectangle type for areas where sounds are muffled (like underwater).
+pub const MUFFLE_ZONE_TYPE : &str = "muffleZone";
+/// How much of a sound goes through the reverb while the listener is in a reverb zone (from 0 to 1).
+const ZONE_REVERB : f32 = 0.6;
+/// How far away sounds can be heard from (in pixels). They fade out linearly up to this.
+const HEARING_RANGE : f32 = 480.0;
+/// How much of a sound's volume makes it through a wall (from 0 to 1).
+const OCCLUDED_VOLUME : f32 = 0.35;
+
+/// What kind of effect a zone has on sounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundZoneKind {
+	/// Sounds echo.
+	Reverb,
+	/// Sounds are muffled.
+	Muffle,
+}
+
+/// An area that changes how sounds are heard while the listener is inside it.
+#[derive(Debug, Clone)]
+pub struct SoundZone {
+	/// What it does.
+	pub kind : SoundZoneKind,
+	/// The area it covers.
+	pub bounds : Bounds2,
+}
+
+/// How a single sound should be played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundMix {
+	/// How loud (from 0 to 1).
+	pub volume : f32,
+	/// How much goes through the reverb (from 0 to 1).
+	pub reverb : f32,
+	/// Whether it goes through a low pass filter.
+	pub muffled : bool,
+}
+
+/// All of the sound zones in the room, and working out how sounds are heard from where the listener is.
+pub struct SoundZones {
+	/// Every zone (in the order they were loaded).
+	zones : Vec<SoundZone>,
+}
+
+impl SoundZones {
+	/// Creates an instance with no zones.
+	pub fn new() -> SoundZones {
+		SoundZones {
+			zones : Vec::new(),
+		}
+	}
+
+	/// Replaces all zones with the ones in a Tiled file.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.zones.clear();
+		for region in file.get_regions() {
+			if REVERB_ZONE_TYPE == region.r#type {
+				self.add(SoundZoneKind::Reverb, &region.bounds);
+			} else if MUFFLE_ZONE_TYPE == region.r#type {
+				self.add(SoundZoneKind::Muffle, &region.bounds);
+			}
+		}
+	}
+
+	/// Adds a zone. Returns its index.
+	pub fn add(&mut self, kind : SoundZoneKind, bounds : &Bounds2) -> usize {
+		self.zones.push(SoundZone {
+			kind,
+			bounds : bounds.clone(),
+		});
+		self.zones.len() - 1
+	}
+
+	/// Gets all zones.
+	pub fn get_zones(&self) -> &Vec<SoundZone> {
+		&self.zones
+	}
+
+	/// Whether the listener is in any zone of some kind.
+	fn is_in(&self, kind : SoundZoneKind, listener : &Vec2) -> bool {
+		self.zones.iter().any(|zone| kind == zone.kind && zone.bounds.overlaps_point(listener))
+	}
+
+	/// Works out how a sound should be played. Sounds without a source (like the player's own) are heard at full volume.
+	///
+	/// Ones with a source fade out with distance, and if the collision geometry is in the way between them and the listener they're quieter and muffled.
+	/// The listener's zones apply to everything.
+	pub fn mix(&self, listener : &Vec2, source : Option<&Vec2>, collision : &CollisionSystem) -> SoundMix {
+		let mut mix = SoundMix {
+			volume : 1.0,
+			reverb : if self.is_in(SoundZoneKind::Reverb, listener) { ZONE_REVERB } else { 0.0 },
+			muffled : self.is_in(SoundZoneKind::Muffle, listener),
+		};
+		if let Some(source) = source {
+			let offset = listener - source;
+			let distance = offset.length();
+			mix.volume = (1.0 - distance / HEARING_RANGE).max(0.0);
+			if 0.0 < mix.volume && EPSILON < distance && collision.raycast(source, &offset.norm(), distance).is_some() {
+				mix.volume *= OCCLUDED_VOLUME;
+				mix.muffled = true;
+			}
+		}
+		mix
+	}
+}
+
+impl ShiftOrigin for SoundZones {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for zone in &mut self.zones {
+			zone.bounds.translate(shift);
+		}
+	}
+}
+
+/// Plays a sound effect (by asset name) as mixed. Does nothing if it would be silent.
+pub fn play_sound(name : &str, mix : &SoundMix) {
+	if 0.0 >= mix.volume {
+		return;
+	}
+	playSound(name, &asset_url(name), mix.volume, mix.reverb, mix.muffled);
+}
+
+#[cfg(test)]
+mod tests_sound {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	#[test]
+	fn mixing() {
+		let mut collision = CollisionSystem::new();
+		collision.add_obstacle(CircleObstacle::LineSegment(LineSegment::new(&Vec2::new(100.0, -50.0), &Vec2::new(100.0, 50.0))));
+		let mut zones = SoundZones::new();
+		zones.add(SoundZoneKind::Reverb, &Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 40.0, 40.0));
+		zones.add(SoundZoneKind::Muffle, &Bounds2::from_centered_rect(&Vec2::new(0.0, 200.0), 40.0, 40.0));
+		let listener = Vec2::new(0.0, 0.0);
+
+		// The listener's zone applies to sounds without a source.
+		assert_eq!(zones.mix(&listener, None, &collision), SoundMix { volume : 1.0, reverb : ZONE_REVERB, muffled : false });
+		assert_eq!(zones.mix(&Vec2::new(0.0, 200.0), None, &collision), SoundMix { volume : 1.0, reverb : 0.0, muffled : true });
+
+		// Sources fade with distance.
+		let open = zones.mix(&listener, Some(&Vec2::new(-240.0, 0.0)), &collision);
+		assert_eq!(open.volume, 0.5);
+		assert!(!open.muffled);
+		assert_eq!(zones.mix(&listener, Some(&Vec2::new(-1000.0, 0.0)), &collision).volume, 0.0);
+
+		// And are quieter and muffled through walls.
+		let blocked = zones.mix(&listener, Some(&Vec2::new(240.0, 0.0)), &collision);
+		assert_eq!(blocked.volume, 0.5 * OCCLUDED_VOLUME);
+		assert!(blocked.muffled);
+		assert!(zones.mix(&Vec2::new(0.0, 200.0), Some(&Vec2::new(240.0, 0.0)), &collision).muffled); // Goes around the wall, but the listener is in a muffle zone.
+	}
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::geo::vec2::*;
+
+/// How big each cell is (in pixels). About the size of the ranges things ask about, so queries only look at a handful of cells.
+const CELL_SIZE : f32 = 64.0;
+
+/// What kind of thing an entry is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DynamicKind {
+	/// A player (index 0 is the main one, 1 is the co-op one).
+	Player,
+	/// An enemy (by its index in `Enemies`).
+	Enemy,
+	/// A projectile in flight (by its index in `Projectiles`).
+	Projectile,
+	/// An uncollected pickup (by its index in `Pickups`).
+	Pickup,
+}
+
+/// Something that moves, as of when the hash was last built.
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicEntry {
+	/// What it is.
+	pub kind : DynamicKind,
+	/// Which one it is (its index in whatever holds that kind of thing).
+	pub index : usize,
+	/// Where its center is.
+	pub position : Vec2,
+	/// How big it is.
+	pub radius : f32,
+}
+
+/// A grid of buckets holding everything that moves, rebuilt every step so things can find what's near them without checking everything.
+///
+/// This is separate from the collision system's static geometry: it only holds positions and radii, and is cleared and refilled each step.
+pub struct SpatialHash {
+	/// Everything in it (cells refer to these by index).
+	entries : Vec<DynamicEntry>,
+	/// The entries overlapping each cell (by cell coordinates). Buckets are kept between rebuilds while they're in use, so refilling doesn't allocate.
+	cells : HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+	/// Creates an empty instance.
+	pub fn new() -> SpatialHash {
+		SpatialHash {
+			entries : Vec::new(),
+			cells : HashMap::new(),
+		}
+	}
+
+	/// Removes everything (to be refilled). Buckets that were empty since the last clear are dropped, so the map doesn't keep every cell ever visited.
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.cells.retain(|_, bucket| {
+			let used = !bucket.is_empty();
+			bucket.clear();
+			used
+		});
+	}
+
+	/// Adds something (a circle).
+	pub fn insert(&mut self, kind : DynamicKind, index : usize, position : &Vec2, radius : f32) {
+		let entry = self.entries.len();
+		self.entries.push(DynamicEntry {
+			kind,
+			index,
+			position : *position,
+			radius,
+		});
+		let (low, high) = cell_range(position, radius);
+		for x in low.0..=high.0 {
+			for y in low.1..=high.1 {
+				self.cells.entry((x, y)).or_default().push(entry);
+			}
+		}
+	}
+
+	/// Gets everything in it (in the order it was added).
+	pub fn get_entries(&self) -> &[DynamicEntry] {
+		&self.entries
+	}
+
+	/// Finds everything (optionally only of one kind) overlapping a circle, in the order it was added.
+	pub fn query_radius(&self, center : &Vec2, radius : f32, kind : Option<DynamicKind>) -> Vec<DynamicEntry> {
+		let mut found : Vec<usize> = Vec::new();
+		let (low, high) = cell_range(center, radius);
+		for x in low.0..=high.0 {
+			for y in low.1..=high.1 {
+				if let Some(bucket) = self.cells.get(&(x, y)) {
+					found.extend(bucket.iter().copied());
+				}
+			}
+		}
+		found.sort_unstable();
+		found.dedup(); // Things spanning several cells show up once per cell.
+		found.iter().map(|index| self.entries[*index]).filter(|entry| {
+			kind.map_or(true, |kind| kind == entry.kind) && (entry.position - center).length() <= radius + entry.radius
+		}).collect()
+	}
+
+	/// Finds up to some number of the closest things (optionally only of one kind) whose centers are within some distance of a position, closest first.
+	pub fn nearest(&self, center : &Vec2, count : usize, max_distance : f32, kind : Option<DynamicKind>) -> Vec<DynamicEntry> {
+		let mut found : Vec<(f32, DynamicEntry)> = self.query_radius(center, max_distance, kind).into_iter()
+			.map(|entry| ((entry.position - center).length(), entry))
+			.filter(|(distance, _)| *distance <= max_distance)
+			.collect();
+		found.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+		found.into_iter().take(count).map(|(_, entry)| entry).collect()
+	}
+
+	/// Whether anything (optionally only of one kind) overlaps a circle.
+	pub fn any_within(&self, center : &Vec2, radius : f32, kind : Option<DynamicKind>) -> bool {
+		!self.query_radius(center, radius, kind).is_empty()
+	}
+}
+
+/// Gets the lowest and highest cell coordinates a circle overlaps.
+fn cell_range(center : &Vec2, radius : f32) -> ((i32, i32), (i32, i32)) {
+	let cell = |value : f32| (value / CELL_SIZE).floor() as i32;
+	(
+		(cell(center.x - radius), cell(center.y - radius)),
+		(cell(center.x + radius), cell(center.y + radius)),
+	)
+}
+
+#[cfg(test)]
+mod tests_spatial_hash {
+	use super::*;
+
+	/// Gets what a query found as (kind, index) pairs (so it can be compared).
+	fn found(entries : Vec<DynamicEntry>) -> Vec<(DynamicKind, usize)> {
+		entries.iter().map(|entry| (entry.kind, entry.index)).collect()
+	}
+
+	#[test]
+	fn queries() {
+		let mut hash = SpatialHash::new();
+		hash.insert(DynamicKind::Player, 0, &Vec2::new(0.0, 0.0), 8.0);
+		hash.insert(DynamicKind::Enemy, 0, &Vec2::new(30.0, 0.0), 6.0);
+		hash.insert(DynamicKind::Enemy, 1, &Vec2::new(-10.0, 5.0), 6.0);
+		hash.insert(DynamicKind::Pickup, 3, &Vec2::new(500.0, -500.0), 3.0);
+		hash.insert(DynamicKind::Projectile, 0, &Vec2::new(63.0, 63.0), 40.0); // Spans several cells.
+
+		// Radius queries count anything overlapping (so big things are found from further away), each only once.
+		assert_eq!(found(hash.query_radius(&Vec2::new(0.0, 0.0), 20.0, None)), vec![(DynamicKind::Player, 0), (DynamicKind::Enemy, 1)]);
+		assert_eq!(found(hash.query_radius(&Vec2::new(60.0, 0.0), 30.0, None)), vec![(DynamicKind::Enemy, 0), (DynamicKind::Projectile, 0)]);
+		assert!(hash.any_within(&Vec2::new(497.0, -500.0), 1.0, Some(DynamicKind::Pickup)));
+		assert!(!hash.any_within(&Vec2::new(497.0, -500.0), 1.0, Some(DynamicKind::Enemy)));
+
+		// Nearest queries sort by distance (to centers), and stop at the limit.
+		assert_eq!(found(hash.nearest(&Vec2::new(40.0, 0.0), 2, 100.0, Some(DynamicKind::Enemy))), vec![(DynamicKind::Enemy, 0), (DynamicKind::Enemy, 1)]);
+		assert_eq!(found(hash.nearest(&Vec2::new(40.0, 0.0), 2, 100.0, None)), vec![(DynamicKind::Enemy, 0), (DynamicKind::Player, 0)]);
+		assert!(hash.nearest(&Vec2::new(40.0, 0.0), 1, 5.0, Some(DynamicKind::Player)).is_empty());
+
+		// Rebuilding starts over.
+		hash.clear();
+		assert!(hash.get_entries().is_empty());
+		assert!(hash.query_radius(&Vec2::new(0.0, 0.0), 1000.0, None).is_empty());
+		hash.insert(DynamicKind::Player, 1, &Vec2::new(-200.0, 0.0), 8.0);
+		assert_eq!(found(hash.nearest(&Vec2::new(0.0, 0.0), 1, 250.0, Some(DynamicKind::Player))), vec![(DynamicKind::Player, 1)]);
+	}
+}
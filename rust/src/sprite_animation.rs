@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use crate::geo::vec2::*;
+use crate::geo::vec3::Vec3;
+use crate::geo::transform2d::Transform2D;
+use crate::display_buffer::DisplayBuffer;
+
+/// One image in a sprite animation.
+#[derive(Debug, Clone)]
+pub struct SpriteFrame {
+	/// Where the image is in the texture (its bottom left corner, in pixels).
+	pub source_position : Vec2,
+	/// The image's size (in pixels).
+	pub size : Vec2,
+	/// Where the image's bottom left corner is drawn, relative to the sprite's origin.
+	pub offset : Vec2,
+	/// How long the frame is shown (in seconds).
+	pub seconds : f32,
+}
+
+impl SpriteFrame {
+	/// Creates a frame that's centered on the sprite's origin.
+	pub fn new(source_position : &Vec2, size : &Vec2, seconds : f32) -> SpriteFrame {
+		SpriteFrame {
+			source_position : *source_position,
+			size : *size,
+			offset : size * -0.5,
+			seconds,
+		}
+	}
+
+	/// Gets a copy that's drawn moved from where it would be by some amount (like to bob up and down without needing another image).
+	pub fn with_nudge(mut self, nudge : &Vec2) -> SpriteFrame {
+		self.offset += nudge;
+		self
+	}
+}
+
+/// What happens once an animation's last frame is done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationMode {
+	/// Start over from the first frame.
+	Loop,
+	/// Hold the last frame.
+	Once,
+}
+
+/// A series of frames, each shown for a while.
+#[derive(Debug, Clone)]
+pub struct SpriteAnimation {
+	/// The frames, in order.
+	frames : Vec<SpriteFrame>,
+	/// What happens after the last frame.
+	mode : AnimationMode,
+}
+
+impl SpriteAnimation {
+	/// Creates an animation with no frames.
+	pub fn new(mode : AnimationMode) -> SpriteAnimation {
+		SpriteAnimation {
+			frames : Vec::new(),
+			mode,
+		}
+	}
+
+	/// Creates an animation from images that are side by side in the texture (going right from the first one), each shown for the same time.
+	pub fn from_strip(first : &Vec2, size : &Vec2, count : usize, seconds : f32, mode : AnimationMode) -> SpriteAnimation {
+		let mut animation = SpriteAnimation::new(mode);
+		for index in 0..count {
+			animation.add_frame(SpriteFrame::new(&(first + Vec2::new(size.x * (index as f32), 0.0)), size, seconds));
+		}
+		animation
+	}
+
+	/// Adds a frame to the end.
+	pub fn add_frame(&mut self, frame : SpriteFrame) {
+		self.frames.push(frame);
+	}
+
+	/// Gets all of the frames.
+	pub fn get_frames(&self) -> &[SpriteFrame] {
+		&self.frames
+	}
+
+	/// Gets what happens after the last frame.
+	pub fn get_mode(&self) -> AnimationMode {
+		self.mode
+	}
+
+	/// Gets how long it takes to go through every frame once (in seconds).
+	pub fn get_duration(&self) -> f32 {
+		self.frames.iter().map(|frame| frame.seconds).sum()
+	}
+
+	/// Gets which frame is shown some time after starting (or None if there are no frames).
+	pub fn frame_at(&self, time : f32) -> Option<usize> {
+		if self.frames.is_empty() {
+			return None;
+		}
+		let duration = self.get_duration();
+		let mut time = time.max(0.0);
+		if duration <= time {
+			match self.mode {
+				AnimationMode::Loop => { time %= duration; },
+				AnimationMode::Once => { return Some(self.frames.len() - 1); },
+			}
+		}
+		for (index, frame) in self.frames.iter().enumerate() {
+			if time < frame.seconds {
+				return Some(index);
+			}
+			time -= frame.seconds;
+		}
+		Some(self.frames.len() - 1) // Only reachable through rounding.
+	}
+}
+
+/// Plays a sprite's animations (by name) onto an IMAGES type DisplayBuffer, one at a time.
+///
+/// The simulation picks what to play (and moves time along), and the buffer is only rewritten when the frame being shown changes.
+pub struct Animator {
+	/// Every animation that can be played.
+	animations : HashMap<String, SpriteAnimation>,
+	/// What's playing.
+	current : Option<String>,
+	/// How long the current animation has been playing (in seconds).
+	time : f32,
+	/// Whether the sprite is mirrored left to right.
+	flip_x : bool,
+	/// What was last drawn (the animation and frame), so it isn't redrawn when nothing changed.
+	drawn : Option<(String, usize)>,
+}
+
+impl Animator {
+	/// Creates an instance without any animations.
+	pub fn new() -> Animator {
+		Animator {
+			animations : HashMap::new(),
+			current : None,
+			time : 0.0,
+			flip_x : false,
+			drawn : None,
+		}
+	}
+
+	/// Adds (or replaces) an animation.
+	pub fn add(&mut self, name : &str, animation : SpriteAnimation) {
+		self.animations.insert(name.to_string(), animation);
+	}
+
+	/// Switches to an animation, starting it from the beginning. Asking for the one that's already playing leaves it be (so this can be called every update).
+	/// Returns false (and changes nothing) if there's no animation by that name.
+	pub fn play(&mut self, name : &str) -> bool {
+		if self.current.as_deref() == Some(name) {
+			return true;
+		}
+		self.restart(name)
+	}
+
+	/// Starts an animation from the beginning, even if it's already playing (like for a one shot animation that should happen again).
+	/// Returns false (and changes nothing) if there's no animation by that name.
+	pub fn restart(&mut self, name : &str) -> bool {
+		if !self.animations.contains_key(name) {
+			return false;
+		}
+		self.current = Some(name.to_string());
+		self.time = 0.0;
+		true
+	}
+
+	/// Gets the name of what's playing.
+	pub fn get_current(&self) -> Option<&str> {
+		self.current.as_deref()
+	}
+
+	/// Moves the current animation along.
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		self.time += elapsed_seconds;
+		// Keep looping animations' time small, so it doesn't lose precision after playing for a long time.
+		if let Some(animation) = self.current.as_ref().and_then(|name| self.animations.get(name)) {
+			let duration = animation.get_duration();
+			if AnimationMode::Loop == animation.mode && 0.0 < duration && duration <= self.time {
+				self.time %= duration;
+			}
+		}
+	}
+
+	/// Whether the current animation is a one shot that has reached its end (or nothing is playing).
+	pub fn is_done(&self) -> bool {
+		match self.current.as_ref().and_then(|name| self.animations.get(name)) {
+			Some(animation) => AnimationMode::Once == animation.mode && animation.get_duration() <= self.time,
+			None => true,
+		}
+	}
+
+	/// Gets which frame is showing (by index in the current animation).
+	pub fn get_frame_index(&self) -> Option<usize> {
+		self.current.as_ref().and_then(|name| self.animations.get(name)).and_then(|animation| animation.frame_at(self.time))
+	}
+
+	/// Gets the frame that's showing.
+	pub fn get_frame(&self) -> Option<&SpriteFrame> {
+		let animation = self.animations.get(self.current.as_ref()?)?;
+		animation.frames.get(animation.frame_at(self.time)?)
+	}
+
+	/// Sets whether the sprite is mirrored left to right.
+	pub fn set_flip_x(&mut self, flip_x : bool) {
+		self.flip_x = flip_x;
+	}
+
+	/// Whether the sprite is mirrored left to right.
+	pub fn get_flip_x(&self) -> bool {
+		self.flip_x
+	}
+
+	/// Gets where and how to draw the sprite so it's at a position (and mirrored if it should be). Can have more added to it (like rotation) before it's used.
+	pub fn get_transform(&self, position : &Vec2) -> Transform2D {
+		Transform2D::new(position).with_flip(self.flip_x, false)
+	}
+
+	/// Puts the frame that's showing into a buffer (replacing what was in it), if it's changed since the last time. Returns whether it was redrawn.
+	pub fn draw(&mut self, buffer : &mut DisplayBuffer) -> bool {
+		let showing = match (&self.current, self.get_frame_index()) {
+			(Some(name), Some(index)) => Some((name.clone(), index)),
+			_ => None,
+		};
+		if showing == self.drawn {
+			return false;
+		}
+		let mut editor = buffer.make_editor();
+		editor.clear();
+		if let Some(frame) = self.get_frame() {
+			editor.add_image(&frame.source_position, &frame.size, &Vec3::new(frame.offset.x, frame.offset.y, 0.0));
+		}
+		drop(editor);
+		self.drawn = showing;
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests_sprite_animation {
+	use super::*;
+
+	/// Makes an animation with a frame for each of some durations (the frames' images going right from the origin).
+	fn animation(durations : &[f32], mode : AnimationMode) -> SpriteAnimation {
+		let mut animation = SpriteAnimation::new(mode);
+		for (index, seconds) in durations.iter().enumerate() {
+			animation.add_frame(SpriteFrame::new(&Vec2::new(16.0 * (index as f32), 0.0), &Vec2::new(16.0, 16.0), *seconds));
+		}
+		animation
+	}
+
+	#[test]
+	fn frames() {
+		let looping = animation(&[0.1, 0.2, 0.1], AnimationMode::Loop);
+		assert!((looping.get_duration() - 0.4).abs() < 0.0001);
+		let frames : Vec<Option<usize>> = [0.0, 0.05, 0.15, 0.35, 0.45, 0.55].iter().map(|time| looping.frame_at(*time)).collect();
+		assert_eq!(frames, vec![Some(0), Some(0), Some(1), Some(2), Some(0), Some(1)]);
+
+		let once = animation(&[0.1, 0.2, 0.1], AnimationMode::Once);
+		assert_eq!(once.frame_at(0.35), Some(2));
+		assert_eq!(once.frame_at(10.0), Some(2)); // Holds the last frame.
+		assert_eq!(SpriteAnimation::new(AnimationMode::Loop).frame_at(0.0), None);
+
+		let strip = SpriteAnimation::from_strip(&Vec2::new(0.0, 16.0), &Vec2::new(16.0, 16.0), 3, 0.1, AnimationMode::Loop);
+		assert_eq!(strip.get_frames().len(), 3);
+		assert_eq!(strip.get_frames()[2].source_position.x, 32.0);
+		assert_eq!(strip.get_frames()[2].source_position.y, 16.0);
+		assert_eq!(strip.get_frames()[2].offset.x, -8.0); // Centered.
+	}
+
+	#[test]
+	fn playing() {
+		let mut animator = Animator::new();
+		animator.add("walk", animation(&[0.1, 0.1], AnimationMode::Loop));
+		animator.add("land", animation(&[0.1, 0.1], AnimationMode::Once));
+		assert!(animator.is_done());
+		assert_eq!(animator.get_frame_index(), None);
+		assert!(!animator.play("swim"));
+		assert_eq!(animator.get_current(), None);
+
+		assert!(animator.play("walk"));
+		animator.update(0.15);
+		assert_eq!(animator.get_frame_index(), Some(1));
+		animator.play("walk"); // Already playing, so it keeps going.
+		assert_eq!(animator.get_frame_index(), Some(1));
+		animator.update(0.1);
+		assert_eq!(animator.get_frame_index(), Some(0));
+		assert!(!animator.is_done()); // Loops never finish.
+
+		animator.play("land");
+		assert_eq!(animator.get_frame_index(), Some(0));
+		animator.update(0.5);
+		assert!(animator.is_done());
+		assert_eq!(animator.get_frame_index(), Some(1));
+		animator.restart("land");
+		assert!(!animator.is_done());
+
+		assert!(!animator.get_transform(&Vec2::zero()).flip_x);
+		animator.set_flip_x(true);
+		assert!(animator.get_transform(&Vec2::zero()).flip_x);
+	}
+}
@@ -0,0 +1,118 @@
+use crate::ability_meter::*;
+use crate::tuning::Tuning;
+
+/// The player's stamina: holding onto a track drains it, kicking off of one costs a chunk of it, and it only refills while standing on the ground.
+///
+/// Running out leaves the player exhausted (knocked off of any track), and they stay that way until it refills past `Tuning::stamina_recover_fraction`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stamina {
+	/// How much is left.
+	meter : AbilityMeter,
+	/// Whether it ran out and hasn't refilled enough yet.
+	exhausted : bool,
+}
+
+impl Stamina {
+	/// Creates a full instance.
+	pub fn new(tuning : &Tuning) -> Stamina {
+		Stamina {
+			meter : AbilityMeter::new(tuning.stamina_max_seconds, tuning.stamina_regen_rate).with_regen_delay(tuning.stamina_regen_delay),
+			exhausted : false,
+		}
+	}
+
+	/// Gets the underlying meter (for showing it).
+	pub fn get_meter(&self) -> &AbilityMeter {
+		&self.meter
+	}
+
+	/// Whether it ran out and hasn't refilled enough yet.
+	pub fn is_exhausted(&self) -> bool {
+		self.exhausted
+	}
+
+	/// Whether there's enough to grab onto a track.
+	pub fn can_attach(&self) -> bool {
+		!self.exhausted && !self.meter.is_empty()
+	}
+
+	/// Drains it for some time spent holding onto a track. Returns whether that ran it out (so the player should be knocked off).
+	pub fn drain_attached(&mut self, elapsed_seconds : f32, tuning : &Tuning) -> bool {
+		self.meter.drain(tuning.stamina_track_drain_rate * elapsed_seconds);
+		self.check_events();
+		self.exhausted
+	}
+
+	/// Spends what a kick off of a track costs, but only if there's enough. Returns whether it was spent.
+	pub fn try_kick(&mut self, tuning : &Tuning) -> bool {
+		if self.exhausted || !self.meter.try_consume(tuning.stamina_kick_cost) {
+			return false;
+		}
+		self.check_events();
+		true
+	}
+
+	/// Moves forward in time. It only refills while on the ground.
+	pub fn update(&mut self, elapsed_seconds : f32, grounded : bool, tuning : &Tuning) {
+		if grounded {
+			self.meter.update(elapsed_seconds);
+		}
+		self.check_events();
+		if self.exhausted && tuning.stamina_recover_fraction <= self.meter.get_fraction() {
+			self.exhausted = false;
+		}
+	}
+
+	/// Fills it back up (as in after respawning).
+	pub fn refill(&mut self) {
+		self.meter.refill();
+		self.meter.take_events();
+		self.exhausted = false;
+	}
+
+	/// Notes running out.
+	fn check_events(&mut self) {
+		for event in self.meter.take_events() {
+			if AbilityMeterEvent::Emptied == event {
+				self.exhausted = true;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_stamina {
+	use super::*;
+
+	#[test]
+	fn exhaustion() {
+		let mut tuning = Tuning::new();
+		tuning.stamina_max_seconds = 2.0;
+		tuning.stamina_track_drain_rate = 1.0;
+		tuning.stamina_kick_cost = 0.5;
+		tuning.stamina_regen_rate = 1.0;
+		tuning.stamina_regen_delay = 0.0;
+		tuning.stamina_recover_fraction = 0.5;
+		let mut stamina = Stamina::new(&tuning);
+		assert!(stamina.try_kick(&tuning));
+		assert!(!stamina.drain_attached(1.0, &tuning));
+		assert_eq!(stamina.get_meter().get_current(), 0.5);
+		assert!(stamina.drain_attached(1.0, &tuning));
+		assert!(stamina.is_exhausted());
+		assert!(!stamina.can_attach());
+		assert!(!stamina.try_kick(&tuning));
+
+		// Only refills on the ground, and needs to get past the recover fraction before it can be used again.
+		stamina.update(1.0, false, &tuning);
+		assert_eq!(stamina.get_meter().get_current(), 0.0);
+		stamina.update(0.5, true, &tuning);
+		assert!(stamina.is_exhausted());
+		stamina.update(0.5, true, &tuning);
+		assert!(stamina.can_attach());
+
+		stamina.drain_attached(10.0, &tuning);
+		stamina.refill();
+		assert!(stamina.can_attach() && stamina.get_meter().is_full());
+	}
+}
@@ -4,6 +4,7 @@
 
 use crate::game::Game;
 use crate::tiled::TiledGenerator;
+use crate::asset_manifest::AssetManifest;
 
 use std::ptr;
 
@@ -57,3 +58,27 @@ pub fn get_tiled_generator() -> &'static mut TiledGenerator {
 		return &mut *TILED_FILE_GENERATOR;
 	}
 }
+
+/// The asset manifest (see `asset_manifest`).
+static mut ASSET_MANIFEST : *mut AssetManifest = ptr::null_mut();
+
+/// Replaces the asset manifest.
+pub fn set_asset_manifest(manifest : AssetManifest) {
+	unsafe {
+		if !ASSET_MANIFEST.is_null() {
+			drop(Box::from_raw(ASSET_MANIFEST));
+		}
+		ASSET_MANIFEST = Box::into_raw(Box::new(manifest));
+	}
+}
+
+/// Gets the asset manifest.
+/// Will create an empty one if none was set.
+pub fn get_asset_manifest() -> &'static AssetManifest {
+	unsafe {
+		if ASSET_MANIFEST.is_null() {
+			ASSET_MANIFEST = Box::into_raw(Box::new(AssetManifest::new()));
+		}
+		return &*ASSET_MANIFEST;
+	}
+}
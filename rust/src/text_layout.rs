@@ -0,0 +1,121 @@
+/// The font stack used for locales without one of their own.
+pub const DEFAULT_FONT_STACK : &str = "sans-serif";
+/// Font stacks for locales whose scripts need specific fonts, keyed by language subtag (or language and script for Chinese).
+const LOCALE_FONT_STACKS : [(&str, &str); 6] = [
+	("ja", "\"Hiragino Sans\", \"Yu Gothic\", \"Noto Sans CJK JP\", \"Noto Sans JP\", sans-serif"),
+	("ko", "\"Apple SD Gothic Neo\", \"Malgun Gothic\", \"Noto Sans CJK KR\", \"Noto Sans KR\", sans-serif"),
+	("zh-Hans", "\"PingFang SC\", \"Microsoft YaHei\", \"Noto Sans CJK SC\", \"Noto Sans SC\", sans-serif"),
+	("zh-Hant", "\"PingFang TC\", \"Microsoft JhengHei\", \"Noto Sans CJK TC\", \"Noto Sans TC\", sans-serif"),
+	("ru", "\"Segoe UI\", Roboto, \"Noto Sans\", \"DejaVu Sans\", sans-serif"),
+	("uk", "\"Segoe UI\", Roboto, \"Noto Sans\", \"DejaVu Sans\", sans-serif"),
+];
+
+/// Gets the font stack (a CSS `font-family` value) for a locale (a BCP 47 tag like "ja" or "zh-TW").
+pub fn font_stack_for_locale(locale : &str) -> &'static str {
+	let mut parts = locale.split(|c| '-' == c || '_' == c);
+	let language = parts.next().unwrap_or("").to_ascii_lowercase();
+	let key = if "zh" == language {
+		// Traditional characters are used in Taiwan, Hong Kong, and Macau (unless the script says otherwise).
+		let rest : Vec<String> = parts.map(|part| part.to_ascii_lowercase()).collect();
+		let traditional = rest.iter().any(|part| "hant" == part) || (
+			!rest.iter().any(|part| "hans" == part) &&
+			rest.iter().any(|part| "tw" == part || "hk" == part || "mo" == part)
+		);
+		if traditional { String::from("zh-Hant") } else { String::from("zh-Hans") }
+	} else if ["be", "bg", "kk", "sr"].contains(&language.as_str()) {
+		String::from("ru") // Other Cyrillic scripts work with the same fonts.
+	} else {
+		language
+	};
+	LOCALE_FONT_STACKS.iter().find(|(name, _)| key == *name).map_or(DEFAULT_FONT_STACK, |(_, stack)| stack)
+}
+
+/// Whether a character can be broken before or after without a space (as in most CJK characters).
+fn breaks_anywhere(c : char) -> bool {
+	let code = c as u32;
+	(0x3040 <= code && code <= 0x30FF) || // Hiragana and Katakana.
+	(0x3400 <= code && code <= 0x4DBF) || // CJK Extension A.
+	(0x4E00 <= code && code <= 0x9FFF) || // CJK Unified Ideographs.
+	(0xF900 <= code && code <= 0xFAFF) || // CJK Compatibility Ideographs.
+	(0xFF00 <= code && code <= 0xFFEF)    // Full-width forms.
+}
+
+/// Whether a line shouldn't start with a character (closing punctuation and small kana, following the usual CJK rules).
+fn avoids_line_start(c : char) -> bool {
+	"、。，．・：；？！）」』】〕〉》ーぁぃぅぇぉっゃゅょァィゥェォッャュョ,.!?)".contains(c)
+}
+
+/// Splits text into lines that are no wider than a given width, using a function to measure how wide some text is.
+///
+/// Latin and Cyrillic text breaks at spaces. CJK text breaks between characters (keeping closing punctuation off the start of lines). Anything that doesn't fit on a line by itself gets its own line.
+pub fn wrap_text<F : Fn(&str) -> f32>(text : &str, max_width : f32, measure : F) -> Vec<String> {
+	// First split into pieces that can't be broken up.
+	let mut pieces : Vec<String> = Vec::new();
+	let mut current = String::new();
+	for c in text.chars() {
+		if c.is_whitespace() {
+			current.push(c);
+			pieces.push(std::mem::take(&mut current));
+		} else if avoids_line_start(c) {
+			if current.is_empty() && !pieces.is_empty() {
+				current = pieces.pop().unwrap();
+			}
+			current.push(c);
+		} else if breaks_anywhere(c) {
+			if !current.is_empty() {
+				pieces.push(std::mem::take(&mut current));
+			}
+			current.push(c);
+		} else {
+			if current.chars().last().map_or(false, breaks_anywhere) {
+				pieces.push(std::mem::take(&mut current));
+			}
+			current.push(c);
+		}
+	}
+	if !current.is_empty() {
+		pieces.push(current);
+	}
+
+	// Then fill up the lines.
+	let mut lines : Vec<String> = Vec::new();
+	let mut line = String::new();
+	for piece in pieces {
+		let candidate = format!("{}{}", line, piece);
+		if line.is_empty() || measure(candidate.trim_end()) <= max_width {
+			line = candidate;
+		} else {
+			lines.push(line.trim_end().to_string());
+			line = piece;
+		}
+	}
+	if !line.trim_end().is_empty() {
+		lines.push(line.trim_end().to_string());
+	}
+	lines
+}
+
+#[cfg(test)]
+mod tests_text_layout {
+	use super::*;
+
+	#[test]
+	fn font_stacks() {
+		assert_eq!(font_stack_for_locale("en-US"), DEFAULT_FONT_STACK);
+		assert!(font_stack_for_locale("ja").contains("JP"));
+		assert!(font_stack_for_locale("zh-CN").contains("SC"));
+		assert!(font_stack_for_locale("zh_TW").contains("TC"));
+		assert!(font_stack_for_locale("zh-Hans-HK").contains("SC"));
+		assert_eq!(font_stack_for_locale("bg"), font_stack_for_locale("ru"));
+	}
+
+	#[test]
+	fn wrapping() {
+		let measure = |text : &str| text.chars().count() as f32;
+		assert_eq!(wrap_text("the quick brown fox", 10.0, measure), vec!["the quick", "brown fox"]);
+		assert_eq!(wrap_text("быстрая лиса", 8.0, measure), vec!["быстрая", "лиса"]);
+		assert_eq!(wrap_text("extraordinarily big", 5.0, measure), vec!["extraordinarily", "big"]);
+		assert_eq!(wrap_text("日本語の文章です。", 4.0, measure), vec!["日本語の", "文章で", "す。"]); // The period sticks to the character before it.
+		assert_eq!(wrap_text("", 4.0, measure), Vec::<String>::new());
+	}
+}
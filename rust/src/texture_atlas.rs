@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::externals::*;
+use crate::geo::vec2::*;
+use crate::display_texture::DisplayTexture;
+use crate::asset_manifest::asset_url;
+
+/// How wide and tall atlas textures are (in pixels).
+pub const ATLAS_SIZE : u32 = 1024;
+/// How much empty space to leave between packed regions (in pixels), so neighbors don't bleed into each other.
+const ATLAS_PADDING : u32 = 1;
+
+/// A part of an atlas (in pixels, measured from the bottom left like all other texture positions).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasRegion {
+	/// The left edge.
+	pub x : u32,
+	/// The bottom edge.
+	pub y : u32,
+	/// The width.
+	pub width : u32,
+	/// The height.
+	pub height : u32,
+}
+
+impl AtlasRegion {
+	/// Gets the bottom left corner (for `DisplayBufferEditor::add_image()`).
+	pub fn get_position(&self) -> Vec2 {
+		Vec2::new(self.x as f32, self.y as f32)
+	}
+
+	/// Gets the size.
+	pub fn get_size(&self) -> Vec2 {
+		Vec2::new(self.width as f32, self.height as f32)
+	}
+}
+
+/// A single row of regions in a `ShelfPacker`.
+struct Shelf {
+	/// The bottom edge.
+	y : u32,
+	/// How tall it is (set by the first region put in it).
+	height : u32,
+	/// How much of the width is used up.
+	used_width : u32,
+}
+
+/// Packs rectangles into a fixed area, row by row ("shelves"). Simple, and good enough for sprites that are mostly similar sizes.
+pub struct ShelfPacker {
+	/// The width of the whole area.
+	width : u32,
+	/// The height of the whole area.
+	height : u32,
+	/// The space to leave between regions.
+	padding : u32,
+	/// The rows so far (bottom to top).
+	shelves : Vec<Shelf>,
+}
+
+impl ShelfPacker {
+	/// Creates an instance with nothing packed.
+	pub fn new(width : u32, height : u32, padding : u32) -> ShelfPacker {
+		ShelfPacker {
+			width,
+			height,
+			padding,
+			shelves : Vec::new(),
+		}
+	}
+
+	/// Finds a place for a rectangle. Returns None if there's no room left.
+	pub fn pack(&mut self, width : u32, height : u32) -> Option<AtlasRegion> {
+		let padded_width = width + self.padding;
+		let padded_height = height + self.padding;
+		// Use the existing shelf that wastes the least height.
+		let mut best : Option<usize> = None;
+		for (index, shelf) in self.shelves.iter().enumerate() {
+			if padded_height <= shelf.height && shelf.used_width + padded_width <= self.width {
+				if best.map_or(true, |best| shelf.height < self.shelves[best].height) {
+					best = Some(index);
+				}
+			}
+		}
+		let index = match best {
+			Some(index) => index,
+			None => {
+				let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+				if self.height < y + padded_height || self.width < padded_width {
+					return None;
+				}
+				self.shelves.push(Shelf { y, height : padded_height, used_width : 0 });
+				self.shelves.len() - 1
+			},
+		};
+		let shelf = &mut self.shelves[index];
+		let region = AtlasRegion { x : shelf.used_width, y : shelf.y, width, height };
+		shelf.used_width += padded_width;
+		Some(region)
+	}
+}
+
+/// A single texture that parts of other images get copied into (on the JavaScript side), so things drawn from different images can share one buffer (and one draw call).
+pub struct TextureAtlas {
+	/// The texture everything is copied into.
+	texture : DisplayTexture,
+	/// Decides where things go.
+	packer : ShelfPacker,
+	/// Where each copied part is, keyed by the image URL and the part's position and size in that image.
+	regions : HashMap<(String, u32, u32, u32, u32), AtlasRegion>,
+}
+
+impl TextureAtlas {
+	/// Creates an empty (transparent) atlas.
+	pub fn new(size : u32) -> TextureAtlas {
+		let mut texture = DisplayTexture::new();
+		texture.set_blank(size, size);
+		TextureAtlas {
+			texture,
+			packer : ShelfPacker::new(size, size, ATLAS_PADDING),
+			regions : HashMap::new(),
+		}
+	}
+
+	/// Gets where part of an image is in the atlas, copying it in if it isn't there yet. Returns None if it doesn't fit.
+	///
+	/// The region is usable right away, though it stays transparent until the image finishes loading.
+	pub fn add_region(&mut self, url : &str, source_position : &Vec2, size : &Vec2) -> Option<AtlasRegion> {
+		let key = (url.to_string(), source_position.x as u32, source_position.y as u32, size.x as u32, size.y as u32);
+		if let Some(region) = self.regions.get(&key) {
+			return Some(region.clone());
+		}
+		let region = self.packer.pack(key.3, key.4)?;
+		assert!(
+			blitImageIntoTexture(self.texture.get_id(), &asset_url(url), key.1, key.2, key.3, key.4, region.x, region.y),
+			"Couldn't copy {:?} into atlas texture {}", url, self.texture.get_id()
+		);
+		self.regions.insert(key, region.clone());
+		Some(region)
+	}
+
+	/// Gets the texture (for `DisplayBuffer::set_texture()`).
+	pub fn get_texture<'a>(&'a self) -> &'a DisplayTexture {
+		&self.texture
+	}
+}
+
+#[cfg(test)]
+mod tests_texture_atlas {
+	use super::*;
+
+	#[test]
+	fn shelf_packing() {
+		let mut packer = ShelfPacker::new(64, 64, 1);
+		assert_eq!(packer.pack(16, 16), Some(AtlasRegion { x : 0, y : 0, width : 16, height : 16 }));
+		assert_eq!(packer.pack(16, 8), Some(AtlasRegion { x : 17, y : 0, width : 16, height : 8 })); // Shorter things share the row.
+		assert_eq!(packer.pack(40, 16).map(|region| (region.x, region.y)), Some((0, 17))); // Too wide for what's left, so starts a new row.
+		assert_eq!(packer.pack(8, 8).map(|region| (region.x, region.y)), Some((34, 0)));
+		assert_eq!(packer.pack(8, 40), None); // Too tall for what's left.
+		assert_eq!(packer.pack(65, 1), None);
+	}
+}
@@ -0,0 +1,908 @@
+use std::collections::HashMap;
+
+use generational_arena::Index;
+
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::geo::collision_system::{CollisionSystem, ObstacleContact};
+use crate::tiled_geometry::BehaviorTile;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How closely a contact's normal must match the entity's "up" direction to count as landing on the tile.
+/// Same as the threshold the Player uses to decide whether it's on the ground.
+const LAND_THRESHOLD : f32 = 0.65;
+
+/// How fast a spring tile launches whatever lands on it.
+const SPRING_LAUNCH_SPEED : f32 = 400.0;
+
+/// How long something must stand on a crumbling tile (without stepping off) before it starts to shake (in seconds).
+const CRUMBLE_STAND_TIME : f32 = 0.3;
+/// How long a crumbling tile shakes before it falls away (in seconds).
+const CRUMBLE_SHAKE_TIME : f32 = 0.4;
+/// How far a crumbling tile shakes side-to-side (in pixels).
+const CRUMBLE_SHAKE_DISTANCE : f32 = 1.0;
+/// How quickly a crumbling tile shakes (in radians per second).
+const CRUMBLE_SHAKE_SPEED : f32 = 60.0;
+/// How long a crumbled tile stays gone before coming back (in seconds).
+const CRUMBLE_RESPAWN_TIME : f32 = 3.0;
+
+/// How long a bumped tile takes to pop up and settle back down (in seconds).
+const BUMP_TIME : f32 = 0.2;
+/// How far a bumped tile pops up (in pixels).
+const BUMP_HEIGHT : f32 = 4.0;
+/// The kind of pickup a prize tile gives out.
+const PRIZE_KIND : &str = "coin";
+/// How far above a prize tile its pickup appears (in pixels), so it doesn't start inside of the tile.
+const PRIZE_CLEARANCE : f32 = 4.0;
+
+/// Anything that can touch a tile and be affected by its behavior.
+pub trait TileBehaviorTarget {
+	/// The direction that's "up" for this entity (i.e. away from gravity). Must be unit length.
+	fn get_up(&self) -> Vec2;
+
+	/// Launches the entity with the given velocity. This replaces any jumping/falling that was happening.
+	fn launch(&mut self, velocity : &Vec2);
+
+	/// Sets where the entity should come back at if it respawns.
+	fn set_respawn_point(&mut self, position : &Vec2);
+}
+
+/// The parts of a tile that a behavior is allowed to change.
+#[derive(Debug, Clone)]
+pub struct TileBehaviorState {
+	/// Whether the tile's collision geometry is active.
+	pub solid : bool,
+	/// Whether the tile is drawn.
+	pub visible : bool,
+	/// How far the tile is drawn from where it actually is. Doesn't affect collision.
+	pub offset : Vec2,
+	/// What the tile wants to happen outside of itself. These are collected up by `TileBehaviors` (see `take_effects()`), so this is always empty after a callback is handled.
+	pub effects : Vec<TileEffect>,
+}
+
+impl TileBehaviorState {
+	/// Creates the state that every tile starts in.
+	pub fn new() -> TileBehaviorState {
+		TileBehaviorState {
+			solid : true,
+			visible : true,
+			offset : Vec2::new(0.0, 0.0),
+			effects : Vec::new(),
+		}
+	}
+}
+
+/// Something a tile's behavior wants the rest of the game to do, as it can't reach outside of its own tile.
+#[derive(Debug, Clone)]
+pub enum TileEffect {
+	/// Drop a pickup, popping it up (along "up") out of a spot.
+	Drop { kind : String, position : Vec2, up : Vec2 },
+	/// The tile broke, so throw debris out of the area it covered (up being along "up").
+	Shatter { bounds : Bounds2, up : Vec2 },
+}
+
+/// A behavior attached to a specific tile.
+///
+/// All callbacks do nothing by default, so behaviors only need to implement what they care about.
+pub trait TileBehavior {
+	/// Called when an entity lands on top of the tile. Only called on the first update it's standing on it.
+	fn on_land(&mut self, _state : &mut TileBehaviorState, _target : &mut dyn TileBehaviorTarget) {}
+
+	/// Called every update that an entity is standing on top of the tile. This happens before `on_update()`.
+	fn on_stand(&mut self, _state : &mut TileBehaviorState, _target : &mut dyn TileBehaviorTarget) {}
+
+	/// Called every update that an entity is touching the tile (from any side).
+	fn on_touch(&mut self, _state : &mut TileBehaviorState, _target : &mut dyn TileBehaviorTarget) {}
+
+	/// Called when an entity hits the tile from below with its head (see `TileBehaviors::dispatch_bumps()`). Only called once per update no matter how many of its obstacles were hit.
+	fn on_bump(&mut self, _state : &mut TileBehaviorState, _target : &mut dyn TileBehaviorTarget) {}
+
+	/// Called every update that an entity overlaps the area the tile covers (whether or not the tile is solid). This happens before `on_update()`.
+	fn on_occupied(&mut self) {}
+
+	/// Called once every update, whether or not anything is touching the tile.
+	fn on_update(&mut self, _state : &mut TileBehaviorState, _elapsed_seconds : f32) {}
+
+	/// Called when the world origin moves (see `origin_rebase`). Behaviors holding onto world positions must move them.
+	fn on_shift_origin(&mut self, _shift : &Vec2) {}
+
+	/// Captures whatever the behavior keeps track of (as plain numbers, so snapshots can be written out), for `load()` to put back. Behaviors without any state can leave this as is.
+	fn save(&self) -> Vec<f32> { Vec::new() }
+
+	/// Puts back what `save()` captured.
+	fn load(&mut self, _saved : &[f32]) {}
+}
+
+/// A behavior tile as it was when a snapshot was taken (see `TileBehaviors::snapshot()`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileBehaviorSnapshot {
+	/// Whether the tile was solid.
+	pub solid : bool,
+	/// Whether the tile was drawn.
+	pub visible : bool,
+	/// How far the tile was drawn from where it actually is.
+	pub offset : Vec2,
+	/// What the behavior itself was keeping track of (see `TileBehavior::save()`).
+	pub saved : Vec<f32>,
+}
+
+/// Creates a TileBehavior instance given the area that the tile covers.
+pub type TileBehaviorFactory = fn(&Bounds2) -> Box<dyn TileBehavior>;
+
+/// Maps behavior names (the `behavior` property in Tiled) to the code that creates them.
+pub struct TileBehaviorRegistry {
+	/// The factory functions by name.
+	factories : HashMap<String, TileBehaviorFactory>,
+}
+
+impl TileBehaviorRegistry {
+	/// Creates an empty registry.
+	pub fn new() -> TileBehaviorRegistry {
+		TileBehaviorRegistry {
+			factories : HashMap::new(),
+		}
+	}
+
+	/// Creates a registry with all of the built-in behaviors.
+	pub fn with_defaults() -> TileBehaviorRegistry {
+		let mut registry = TileBehaviorRegistry::new();
+		registry.register("spring", |_bounds| Box::new(SpringBehavior{}));
+		registry.register("crumble", |_bounds| Box::new(CrumbleBehavior::new()));
+		registry.register("bump", |bounds| Box::new(BumpBehavior::new(BumpReaction::Bounce, bounds)));
+		registry.register("prize", |bounds| Box::new(BumpBehavior::new(BumpReaction::Prize, bounds)));
+		registry.register("breakable", |bounds| Box::new(BumpBehavior::new(BumpReaction::Break, bounds)));
+		registry.register("checkpoint", |bounds| Box::new(CheckpointBehavior{
+			position : Vec2::new(
+				(bounds.x_min() + bounds.x_max()) / 2.0,
+				(bounds.y_min() + bounds.y_max()) / 2.0,
+			),
+		}));
+		registry
+	}
+
+	/// Registers (or replaces) the behavior with the given name.
+	pub fn register(&mut self, name : &str, factory : TileBehaviorFactory) {
+		self.factories.insert(name.to_string(), factory);
+	}
+
+	/// Creates a new instance of the named behavior. Returns `None` if the name isn't registered.
+	pub fn create(&self, name : &str, bounds : &Bounds2) -> Option<Box<dyn TileBehavior>> {
+		self.factories.get(name).map(|factory| factory(bounds))
+	}
+}
+
+/// A single tile's behavior along with everything needed to apply its state.
+struct TileBehaviorInstance {
+	/// The behavior itself.
+	behavior : Box<dyn TileBehavior>,
+	/// The current state of the tile.
+	state : TileBehaviorState,
+	/// The state last applied to the tile's obstacles.
+	applied : TileBehaviorState,
+	/// The collision obstacles that make up the tile.
+	obstacles : Vec<Index>,
+	/// The area the tile covers.
+	bounds : Bounds2,
+	/// The index of the layer the tile is in.
+	layer_index : usize,
+	/// The x position in the layer (in tiles).
+	x : usize,
+	/// The y position in the layer (in tiles).
+	y : usize,
+	/// Whether an entity was standing on the tile last update.
+	landed : bool,
+}
+
+/// All of the tile behaviors in the current map.
+///
+/// The obstacles for a behavior tile are tagged (via user data) with the ID this hands out, which is how collision results get routed back to the right behavior.
+pub struct TileBehaviors {
+	/// The available behavior types.
+	registry : TileBehaviorRegistry,
+	/// The behavior instances. Their index is their ID.
+	instances : Vec<TileBehaviorInstance>,
+	/// The IDs of all instances whose visuals changed since `take_display_changes()` was last called.
+	display_changes : Vec<usize>,
+	/// What the behaviors asked for since `take_effects()` was last called.
+	effects : Vec<TileEffect>,
+}
+
+impl TileBehaviors {
+	/// Creates an instance with the default behavior registry.
+	pub fn new() -> TileBehaviors {
+		TileBehaviors {
+			registry : TileBehaviorRegistry::with_defaults(),
+			instances : Vec::new(),
+			display_changes : Vec::new(),
+			effects : Vec::new(),
+		}
+	}
+
+	/// Gets the registry so new behavior types can be added.
+	pub fn get_registry<'a>(&'a mut self) -> &'a mut TileBehaviorRegistry {
+		&mut self.registry
+	}
+
+	/// Removes all behavior instances.
+	pub fn clear(&mut self) {
+		self.instances.clear();
+		self.display_changes.clear();
+		self.effects.clear();
+	}
+
+	/// Creates a new behavior instance for a tile. Returns its ID (to use as the user data for its obstacles), or `None` if the behavior isn't known.
+	pub fn add(&mut self, tile : &BehaviorTile) -> Option<usize> {
+		let behavior = self.registry.create(&tile.behavior, &tile.bounds)?;
+		self.instances.push(TileBehaviorInstance {
+			behavior,
+			state : TileBehaviorState::new(),
+			applied : TileBehaviorState::new(),
+			obstacles : Vec::new(),
+			bounds : tile.bounds.clone(),
+			layer_index : tile.layer_index,
+			x : tile.x,
+			y : tile.y,
+			landed : false,
+		});
+		Some(self.instances.len() - 1)
+	}
+
+	/// Links a collision obstacle to a behavior instance, so it can be enabled/disabled with the tile.
+	pub fn add_obstacle(&mut self, id : usize, obstacle : Index) {
+		self.instances[id].obstacles.push(obstacle);
+	}
+
+	/// Gets how many behavior instances there are.
+	pub fn count(&self) -> usize {
+		self.instances.len()
+	}
+
+	/// Gets the current state of a behavior instance.
+	pub fn get_state<'a>(&'a self, id : usize) -> &'a TileBehaviorState {
+		&self.instances[id].state
+	}
+
+	/// Gets the location of a behavior instance's tile as (layer index, x, y).
+	pub fn get_location(&self, id : usize) -> (usize, usize, usize) {
+		let instance = &self.instances[id];
+		(instance.layer_index, instance.x, instance.y)
+	}
+
+	/// Takes the IDs of all instances whose visuals (visibility or offset) have changed since the last call.
+	pub fn take_display_changes(&mut self) -> Vec<usize> {
+		std::mem::take(&mut self.display_changes)
+	}
+
+	/// Takes everything the behaviors asked the rest of the game to do since the last call (in the order they asked).
+	pub fn take_effects(&mut self) -> Vec<TileEffect> {
+		std::mem::take(&mut self.effects)
+	}
+
+	/// Sends the contacts an entity made this update to the behaviors of the tiles it touched.
+	pub fn dispatch_contacts(&mut self, contacts : &[ObstacleContact], collision : &mut CollisionSystem, target : &mut dyn TileBehaviorTarget) {
+		let count = self.instances.len();
+		let mut touched = vec![false; count];
+		let mut landed = vec![false; count];
+		let up = target.get_up();
+		for contact in contacts {
+			if let Some(id) = collision.get_user_data(contact.source) {
+				if id >= count { continue; }
+				touched[id] = true;
+				if LAND_THRESHOLD < contact.normal.dot(&up) {
+					landed[id] = true;
+				}
+			}
+		}
+		for (id, instance) in self.instances.iter_mut().enumerate() {
+			if touched[id] {
+				if landed[id] && !instance.landed {
+					instance.behavior.on_land(&mut instance.state, target);
+				}
+				if landed[id] {
+					instance.behavior.on_stand(&mut instance.state, target);
+				}
+				instance.behavior.on_touch(&mut instance.state, target);
+			}
+			instance.landed = landed[id];
+			TileBehaviors::apply_state(id, instance, collision, &mut self.display_changes, &mut self.effects);
+		}
+	}
+
+	/// Sends the obstacles an entity hit with its head (going up) this update to the behaviors of those tiles.
+	pub fn dispatch_bumps(&mut self, bumps : &[ObstacleContact], collision : &mut CollisionSystem, target : &mut dyn TileBehaviorTarget) {
+		let mut bumped : Vec<usize> = Vec::new();
+		for bump in bumps {
+			if let Some(id) = collision.get_user_data(bump.source) {
+				if id < self.instances.len() && !bumped.contains(&id) {
+					bumped.push(id);
+				}
+			}
+		}
+		for id in bumped {
+			let instance = &mut self.instances[id];
+			instance.behavior.on_bump(&mut instance.state, target);
+			TileBehaviors::apply_state(id, instance, collision, &mut self.display_changes, &mut self.effects);
+		}
+	}
+
+	/// Updates all behaviors by some amount of elapsed time, given the (circle) colliders of every entity as their center and radius.
+	pub fn update(&mut self, elapsed_seconds : f32, occupants : &[(Vec2, f32)], collision : &mut CollisionSystem) {
+		for (id, instance) in self.instances.iter_mut().enumerate() {
+			if occupants.iter().any(|(center, radius)| circle_overlaps_bounds(center, *radius, &instance.bounds)) {
+				instance.behavior.on_occupied();
+			}
+			instance.behavior.on_update(&mut instance.state, elapsed_seconds);
+			TileBehaviors::apply_state(id, instance, collision, &mut self.display_changes, &mut self.effects);
+		}
+	}
+
+	/// Captures the state of every behavior tile.
+	pub fn snapshot(&self) -> Vec<TileBehaviorSnapshot> {
+		self.instances.iter().map(|instance| TileBehaviorSnapshot {
+			solid : instance.state.solid,
+			visible : instance.state.visible,
+			offset : instance.state.offset,
+			saved : instance.behavior.save(),
+		}).collect()
+	}
+
+	/// Puts every behavior tile back the way a snapshot had it, forcing its obstacles and visuals to match (whatever the collision system's own snapshot had them as). The snapshot has to be from the same room.
+	pub fn restore(&mut self, snapshots : &[TileBehaviorSnapshot], collision : &mut CollisionSystem) -> Result<(), String> {
+		if snapshots.len() != self.instances.len() {
+			return Err(format!("The snapshot has {} behavior tiles, but the room has {}", snapshots.len(), self.instances.len()));
+		}
+		for (id, (instance, snapshot)) in self.instances.iter_mut().zip(snapshots.iter()).enumerate() {
+			instance.behavior.load(&snapshot.saved);
+			instance.state.solid = snapshot.solid;
+			instance.state.visible = snapshot.visible;
+			instance.state.offset = snapshot.offset;
+			instance.state.effects.clear();
+			instance.landed = false;
+			for obstacle in &instance.obstacles {
+				collision.set_enabled(*obstacle, snapshot.solid);
+			}
+			if !self.display_changes.contains(&id) {
+				self.display_changes.push(id);
+			}
+			instance.applied = instance.state.clone();
+		}
+		self.effects.clear();
+		Ok(())
+	}
+
+	/// Pushes any changes in an instance's state out to its obstacles, records if its visuals need updating, and collects any effects it asked for.
+	fn apply_state(id : usize, instance : &mut TileBehaviorInstance, collision : &mut CollisionSystem, display_changes : &mut Vec<usize>, effects : &mut Vec<TileEffect>) {
+		effects.append(&mut instance.state.effects);
+		let state = &instance.state;
+		let applied = &instance.applied;
+		if state.solid != applied.solid {
+			for obstacle in &instance.obstacles {
+				collision.set_enabled(*obstacle, state.solid);
+			}
+		}
+		let visuals_changed = state.visible != applied.visible || state.offset.x != applied.offset.x || state.offset.y != applied.offset.y;
+		if visuals_changed && !display_changes.contains(&id) {
+			display_changes.push(id);
+		}
+		instance.applied = instance.state.clone();
+	}
+}
+
+impl ShiftOrigin for TileBehaviors {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		for instance in &mut self.instances {
+			instance.bounds.shift_origin(shift);
+			instance.behavior.on_shift_origin(shift);
+		}
+	}
+}
+
+/// Whether a circle overlaps an area (just touching its edge doesn't count, so standing next to or on top of a tile isn't being inside it).
+fn circle_overlaps_bounds(center : &Vec2, radius : f32, bounds : &Bounds2) -> bool {
+	let closest = Vec2::new(
+		center.x.max(bounds.x_min()).min(bounds.x_max()),
+		center.y.max(bounds.y_min()).min(bounds.y_max()),
+	);
+	(center - closest).length() < radius
+}
+
+/// Launches anything that lands on it straight up.
+struct SpringBehavior {}
+
+impl TileBehavior for SpringBehavior {
+	fn on_land(&mut self, _state : &mut TileBehaviorState, target : &mut dyn TileBehaviorTarget) {
+		let velocity = target.get_up() * SPRING_LAUNCH_SPEED;
+		target.launch(&velocity);
+	}
+}
+
+/// Sets the respawn point of anything that touches it.
+struct CheckpointBehavior {
+	/// Where to respawn.
+	position : Vec2,
+}
+
+impl TileBehavior for CheckpointBehavior {
+	fn on_touch(&mut self, _state : &mut TileBehaviorState, target : &mut dyn TileBehaviorTarget) {
+		target.set_respawn_point(&self.position);
+	}
+
+	fn on_shift_origin(&mut self, shift : &Vec2) {
+		self.position.shift_origin(shift);
+	}
+}
+
+/// What a tile does when it's hit from below.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BumpReaction {
+	/// Just pops up.
+	Bounce,
+	/// Pops up, and gives out a pickup the first time.
+	Prize,
+	/// Breaks apart (for good).
+	Break,
+}
+
+/// Reacts to being hit from below (see `TileBehavior::on_bump()`).
+struct BumpBehavior {
+	/// What it does.
+	reaction : BumpReaction,
+	/// The area the tile covers.
+	bounds : Bounds2,
+	/// Which way it pops up (the "up" of whatever last bumped it).
+	up : Vec2,
+	/// How far through popping up it is (in seconds), if it is.
+	bouncing : Option<f32>,
+	/// Whether it has already given out its prize.
+	emptied : bool,
+}
+
+impl BumpBehavior {
+	fn new(reaction : BumpReaction, bounds : &Bounds2) -> BumpBehavior {
+		BumpBehavior {
+			reaction,
+			bounds : bounds.clone(),
+			up : Vec2::new(0.0, 1.0),
+			bouncing : None,
+			emptied : false,
+		}
+	}
+
+	/// Gets the middle of the tile's side that faces "up".
+	fn get_top(&self, up : &Vec2) -> Vec2 {
+		let half_width = (self.bounds.x_max() - self.bounds.x_min()) / 2.0;
+		let half_height = (self.bounds.y_max() - self.bounds.y_min()) / 2.0;
+		let center = Vec2::new(self.bounds.x_min() + half_width, self.bounds.y_min() + half_height);
+		center + up.scale(up.x.abs() * half_width + up.y.abs() * half_height)
+	}
+}
+
+impl TileBehavior for BumpBehavior {
+	fn on_bump(&mut self, state : &mut TileBehaviorState, target : &mut dyn TileBehaviorTarget) {
+		self.up = target.get_up();
+		match self.reaction {
+			BumpReaction::Break => {
+				state.solid = false;
+				state.visible = false;
+				state.offset = Vec2::new(0.0, 0.0);
+				state.effects.push(TileEffect::Shatter { bounds : self.bounds.clone(), up : self.up });
+				return;
+			},
+			BumpReaction::Prize => {
+				if !self.emptied {
+					self.emptied = true;
+					state.effects.push(TileEffect::Drop {
+						kind : PRIZE_KIND.to_string(),
+						position : self.get_top(&self.up) + self.up.scale(PRIZE_CLEARANCE),
+						up : self.up,
+					});
+				}
+			},
+			BumpReaction::Bounce => {},
+		}
+		self.bouncing = Some(0.0);
+	}
+
+	fn on_update(&mut self, state : &mut TileBehaviorState, elapsed_seconds : f32) {
+		if let Some(timer) = self.bouncing {
+			let timer = timer + elapsed_seconds;
+			if BUMP_TIME <= timer {
+				self.bouncing = None;
+				state.offset = Vec2::new(0.0, 0.0);
+			} else {
+				self.bouncing = Some(timer);
+				state.offset = self.up.scale((std::f32::consts::PI * timer / BUMP_TIME).sin() * BUMP_HEIGHT);
+			}
+		}
+	}
+
+	fn on_shift_origin(&mut self, shift : &Vec2) {
+		self.bounds.shift_origin(shift);
+	}
+
+	/// Saves which way it pops up, whether it's emptied, and then how far through popping up it is (only if it is).
+	fn save(&self) -> Vec<f32> {
+		let mut saved = vec![self.up.x, self.up.y, if self.emptied { 1.0 } else { 0.0 }];
+		saved.extend(self.bouncing);
+		saved
+	}
+
+	fn load(&mut self, saved : &[f32]) {
+		if saved.len() < 3 {
+			return;
+		}
+		self.up = Vec2::new(saved[0], saved[1]);
+		self.emptied = 0.0 != saved[2];
+		self.bouncing = saved.get(3).copied();
+	}
+}
+
+/// The stages a crumbling tile goes through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CrumbleStage {
+	/// Sitting still. Counts how long it has been stood on (without stepping off).
+	Stable,
+	/// About to fall away.
+	Shaking,
+	/// Fallen away, waiting to come back.
+	Gone,
+}
+
+/// Shakes and then falls away after being stood on for a bit. Comes back after a delay, once nothing is in the way.
+struct CrumbleBehavior {
+	/// What it's currently doing.
+	stage : CrumbleStage,
+	/// How long it has been in the current stage (or been stood on, if stable).
+	timer : f32,
+	/// Whether something stood on it this update.
+	stood_on : bool,
+	/// Whether something was in the tile's area this update.
+	occupied : bool,
+}
+
+impl CrumbleBehavior {
+	fn new() -> CrumbleBehavior {
+		CrumbleBehavior {
+			stage : CrumbleStage::Stable,
+			timer : 0.0,
+			stood_on : false,
+			occupied : false,
+		}
+	}
+}
+
+impl TileBehavior for CrumbleBehavior {
+	fn on_stand(&mut self, _state : &mut TileBehaviorState, _target : &mut dyn TileBehaviorTarget) {
+		self.stood_on = true;
+	}
+
+	fn on_occupied(&mut self) {
+		self.occupied = true;
+	}
+
+	fn on_update(&mut self, state : &mut TileBehaviorState, elapsed_seconds : f32) {
+		match self.stage {
+			CrumbleStage::Stable => {
+				if self.stood_on {
+					self.timer += elapsed_seconds;
+				} else {
+					self.timer = 0.0;
+				}
+				if CRUMBLE_STAND_TIME <= self.timer {
+					self.stage = CrumbleStage::Shaking;
+					self.timer = 0.0;
+				}
+			},
+			CrumbleStage::Shaking => {
+				self.timer += elapsed_seconds;
+				if CRUMBLE_SHAKE_TIME <= self.timer {
+					self.stage = CrumbleStage::Gone;
+					self.timer = 0.0;
+					state.solid = false;
+					state.visible = false;
+					state.offset = Vec2::new(0.0, 0.0);
+				} else {
+					state.offset = Vec2::new((self.timer * CRUMBLE_SHAKE_SPEED).sin() * CRUMBLE_SHAKE_DISTANCE, 0.0);
+				}
+			},
+			CrumbleStage::Gone => {
+				self.timer += elapsed_seconds;
+				// Don't come back inside of anything.
+				if CRUMBLE_RESPAWN_TIME <= self.timer && !self.occupied {
+					self.stage = CrumbleStage::Stable;
+					self.timer = 0.0;
+					state.solid = true;
+					state.visible = true;
+				}
+			},
+		}
+		self.stood_on = false;
+		self.occupied = false;
+	}
+
+	/// Saves the stage (by its order) and timer.
+	fn save(&self) -> Vec<f32> {
+		let stage = match self.stage {
+			CrumbleStage::Stable => 0.0,
+			CrumbleStage::Shaking => 1.0,
+			CrumbleStage::Gone => 2.0,
+		};
+		vec![stage, self.timer]
+	}
+
+	fn load(&mut self, saved : &[f32]) {
+		if saved.len() < 2 {
+			return;
+		}
+		self.stage = match saved[0] as i32 {
+			1 => CrumbleStage::Shaking,
+			2 => CrumbleStage::Gone,
+			_ => CrumbleStage::Stable,
+		};
+		self.timer = saved[1];
+		self.stood_on = false;
+		self.occupied = false;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::geo::line_segment::LineSegment;
+	use crate::geo::collision_system::CircleObstacle;
+
+	/// A stand-in for the Player.
+	struct FakeTarget {
+		launched : Option<Vec2>,
+		respawn : Option<Vec2>,
+	}
+
+	impl TileBehaviorTarget for FakeTarget {
+		fn get_up(&self) -> Vec2 { Vec2::new(0.0, 1.0) }
+		fn launch(&mut self, velocity : &Vec2) { self.launched = Some(velocity.clone()); }
+		fn set_respawn_point(&mut self, position : &Vec2) { self.respawn = Some(position.clone()); }
+	}
+
+	/// Makes a tile at the origin with the given behavior.
+	fn make_tile(name : &str) -> BehaviorTile {
+		BehaviorTile {
+			behavior : name.to_string(),
+			layer_index : 0,
+			x : 0,
+			y : 0,
+			bounds : Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0)),
+			collision_rects : Vec::new(),
+			collision_polygons : Vec::new(),
+		}
+	}
+
+	/// Makes a behavior instance with a single (flat) obstacle.
+	fn setup(name : &str) -> (TileBehaviors, CollisionSystem, Index) {
+		let mut behaviors = TileBehaviors::new();
+		let mut collision = CollisionSystem::new();
+		let id = behaviors.add(&make_tile(name)).unwrap();
+		let obstacle = collision.add_obstacle_with_user_data(CircleObstacle::LineSegment(LineSegment::new(
+			&Vec2::new(0.0, 16.0),
+			&Vec2::new(16.0, 16.0),
+		)), id);
+		behaviors.add_obstacle(id, obstacle);
+		(behaviors, collision, obstacle)
+	}
+
+	#[test]
+	fn unknown_behavior() {
+		let mut behaviors = TileBehaviors::new();
+		assert!(behaviors.add(&make_tile("nonsense")).is_none());
+	}
+
+	#[test]
+	fn spring_only_launches_on_landing() {
+		let (mut behaviors, mut collision, obstacle) = setup("spring");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let side = vec![ObstacleContact { source : obstacle, normal : Vec2::new(1.0, 0.0) }];
+		behaviors.dispatch_contacts(&side, &mut collision, &mut target);
+		assert!(target.launched.is_none());
+
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		assert_eq!(target.launched.unwrap().y, SPRING_LAUNCH_SPEED);
+
+		// Still standing on it shouldn't re-trigger.
+		target.launched = None;
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		assert!(target.launched.is_none());
+	}
+
+	#[test]
+	fn checkpoint_sets_respawn() {
+		let (mut behaviors, mut collision, obstacle) = setup("checkpoint");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		behaviors.dispatch_contacts(&[], &mut collision, &mut target);
+		assert!(target.respawn.is_none());
+		let contacts = vec![ObstacleContact { source : obstacle, normal : Vec2::new(-1.0, 0.0) }];
+		behaviors.dispatch_contacts(&contacts, &mut collision, &mut target);
+		let respawn = target.respawn.unwrap();
+		assert_eq!(respawn.x, 8.0);
+		assert_eq!(respawn.y, 8.0);
+	}
+
+	#[test]
+	fn crumble_cycle() {
+		let (mut behaviors, mut collision, obstacle) = setup("crumble");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+
+		// Not stood on long enough yet.
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		behaviors.update(CRUMBLE_STAND_TIME / 2.0, &[], &mut collision);
+		assert!(behaviors.get_state(0).solid);
+
+		// Then starts shaking.
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		behaviors.update(CRUMBLE_STAND_TIME / 2.0, &[], &mut collision);
+		behaviors.update(CRUMBLE_SHAKE_TIME / 2.0, &[], &mut collision);
+		assert!(behaviors.get_state(0).solid);
+		assert!(0.0 != behaviors.get_state(0).offset.x);
+		assert_eq!(behaviors.take_display_changes(), vec![0]);
+
+		// Then falls away.
+		behaviors.update(CRUMBLE_SHAKE_TIME / 2.0, &[], &mut collision);
+		assert!(!behaviors.get_state(0).solid);
+		assert!(!behaviors.get_state(0).visible);
+		assert!(!collision.obstacles.get(obstacle).unwrap().active);
+
+		// Then comes back.
+		behaviors.update(CRUMBLE_RESPAWN_TIME, &[], &mut collision);
+		assert!(behaviors.get_state(0).solid);
+		assert!(behaviors.get_state(0).visible);
+		assert!(collision.obstacles.get(obstacle).unwrap().active);
+	}
+
+	#[test]
+	fn crumble_needs_steady_standing() {
+		let (mut behaviors, mut collision, obstacle) = setup("crumble");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+
+		// Lots of short touches (stepping off in between) never add up.
+		for _ in 0..10 {
+			behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+			behaviors.update(CRUMBLE_STAND_TIME / 2.0, &[], &mut collision);
+			behaviors.dispatch_contacts(&[], &mut collision, &mut target);
+			behaviors.update(CRUMBLE_STAND_TIME / 2.0, &[], &mut collision);
+		}
+		behaviors.update(CRUMBLE_SHAKE_TIME, &[], &mut collision);
+		assert!(behaviors.get_state(0).solid);
+		assert_eq!(behaviors.get_state(0).offset.x, 0.0);
+	}
+
+	#[test]
+	fn crumble_waits_to_respawn() {
+		let (mut behaviors, mut collision, obstacle) = setup("crumble");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		behaviors.update(CRUMBLE_STAND_TIME, &[], &mut collision);
+		behaviors.update(CRUMBLE_SHAKE_TIME, &[], &mut collision);
+		assert!(!behaviors.get_state(0).solid);
+
+		// Something is where the tile was, so it stays gone.
+		let inside = [(Vec2::new(8.0, 8.0), 4.0)];
+		behaviors.update(CRUMBLE_RESPAWN_TIME, &inside, &mut collision);
+		assert!(!behaviors.get_state(0).solid);
+		assert!(!collision.obstacles.get(obstacle).unwrap().active);
+
+		// Resting right on top of where it was isn't in the way.
+		let on_top = [(Vec2::new(8.0, 20.0), 4.0)];
+		behaviors.update(0.0, &on_top, &mut collision);
+		assert!(behaviors.get_state(0).solid);
+		assert!(behaviors.get_state(0).visible);
+		assert!(collision.obstacles.get(obstacle).unwrap().active);
+	}
+
+	#[test]
+	fn bump_bounces() {
+		let (mut behaviors, mut collision, obstacle) = setup("bump");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let bump = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, -1.0) }];
+		behaviors.dispatch_bumps(&bump, &mut collision, &mut target);
+		behaviors.update(BUMP_TIME / 2.0, &[], &mut collision);
+		assert!((behaviors.get_state(0).offset.y - BUMP_HEIGHT).abs() < 0.01);
+		assert_eq!(behaviors.take_display_changes(), vec![0]);
+
+		// Settles back down, without anything else happening.
+		behaviors.update(BUMP_TIME / 2.0, &[], &mut collision);
+		assert_eq!(behaviors.get_state(0).offset.y, 0.0);
+		assert!(behaviors.get_state(0).solid);
+		assert!(behaviors.take_effects().is_empty());
+	}
+
+	#[test]
+	fn prize_only_gives_once() {
+		let (mut behaviors, mut collision, obstacle) = setup("prize");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let bump = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, -1.0) }];
+		behaviors.dispatch_bumps(&bump, &mut collision, &mut target);
+		let effects = behaviors.take_effects();
+		assert_eq!(effects.len(), 1);
+		match &effects[0] {
+			TileEffect::Drop { kind, position, .. } => {
+				assert_eq!(kind, PRIZE_KIND);
+				assert_eq!(position.x, 8.0);
+				assert_eq!(position.y, 16.0 + PRIZE_CLEARANCE);
+			},
+			other => panic!("Expected a drop, got {:?}", other),
+		}
+		assert!(behaviors.get_state(0).effects.is_empty());
+
+		// Still bounces after that, but gives nothing more.
+		behaviors.update(BUMP_TIME, &[], &mut collision);
+		behaviors.dispatch_bumps(&bump, &mut collision, &mut target);
+		behaviors.update(BUMP_TIME / 2.0, &[], &mut collision);
+		assert!(0.0 < behaviors.get_state(0).offset.y);
+		assert!(behaviors.take_effects().is_empty());
+	}
+
+	#[test]
+	fn breakable_shatters() {
+		let (mut behaviors, mut collision, obstacle) = setup("breakable");
+		let mut target = FakeTarget { launched : None, respawn : None };
+
+		// Landing on it does nothing.
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		assert!(behaviors.get_state(0).solid);
+
+		// But hitting it from below breaks it for good.
+		let bump = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, -1.0) }];
+		behaviors.dispatch_bumps(&bump, &mut collision, &mut target);
+		assert!(!behaviors.get_state(0).solid);
+		assert!(!behaviors.get_state(0).visible);
+		assert!(!collision.obstacles.get(obstacle).unwrap().active);
+		assert!(matches!(behaviors.take_effects()[..], [TileEffect::Shatter { .. }]));
+		behaviors.update(CRUMBLE_RESPAWN_TIME * 10.0, &[], &mut collision);
+		assert!(!behaviors.get_state(0).solid);
+	}
+
+	#[test]
+	fn crumble_rewinds() {
+		let (mut behaviors, mut collision, obstacle) = setup("crumble");
+		let mut target = FakeTarget { launched : None, respawn : None };
+		let top = vec![ObstacleContact { source : obstacle, normal : Vec2::new(0.0, 1.0) }];
+		let whole = (collision.snapshot(), behaviors.snapshot());
+		behaviors.dispatch_contacts(&top, &mut collision, &mut target);
+		behaviors.update(CRUMBLE_STAND_TIME, &[], &mut collision);
+		behaviors.update(CRUMBLE_SHAKE_TIME, &[], &mut collision);
+		behaviors.update(CRUMBLE_RESPAWN_TIME / 2.0, &[], &mut collision);
+		let gone = (collision.snapshot(), behaviors.snapshot());
+		behaviors.take_display_changes();
+
+		// Rewinding to before it crumbled brings it back, solid and shown.
+		collision.restore(&whole.0);
+		behaviors.restore(&whole.1, &mut collision).unwrap();
+		assert!(behaviors.get_state(0).solid);
+		assert!(behaviors.get_state(0).visible);
+		assert!(collision.obstacles.get(obstacle).unwrap().active);
+		assert_eq!(behaviors.take_display_changes(), vec![0]);
+		behaviors.update(CRUMBLE_SHAKE_TIME, &[], &mut collision); // Nobody's standing on it anymore.
+		assert!(behaviors.get_state(0).solid);
+
+		// Rewinding to while it was gone takes it away again, and it still comes back on time.
+		collision.restore(&gone.0);
+		behaviors.restore(&gone.1, &mut collision).unwrap();
+		assert!(!behaviors.get_state(0).solid);
+		assert!(!behaviors.get_state(0).visible);
+		assert!(!collision.obstacles.get(obstacle).unwrap().active);
+		behaviors.update(CRUMBLE_RESPAWN_TIME / 2.0, &[], &mut collision);
+		assert!(behaviors.get_state(0).solid);
+		assert!(behaviors.get_state(0).visible);
+		assert!(collision.obstacles.get(obstacle).unwrap().active);
+
+		// The collision system's own snapshot doesn't get the final say.
+		collision.restore(&gone.0);
+		behaviors.restore(&whole.1, &mut collision).unwrap();
+		assert!(collision.obstacles.get(obstacle).unwrap().active);
+		assert!(behaviors.restore(&[], &mut collision).is_err());
+	}
+}
@@ -0,0 +1,322 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How many tiles wide and tall each chunk of a layer is.
+pub const CHUNK_TILES : usize = 32;
+/// How far outside of the view a chunk can be and still get built (in pixels).
+const LOAD_MARGIN : f32 = 128.0;
+/// How far outside of the view a chunk has to be before it's dropped (in pixels). Bigger than `LOAD_MARGIN` so chunks near the edge don't keep getting rebuilt.
+const UNLOAD_MARGIN : f32 = 384.0;
+/// How far ahead (in seconds) the view's movement is followed to guess where it's going.
+const PREDICT_SECONDS : f32 = 0.5;
+/// How quickly the tracked velocity follows the view's actual movement (the fraction per second, roughly).
+const VELOCITY_SMOOTHING : f32 = 8.0;
+/// The view moving faster than this (in pixels per second) is taken as a cut (like respawning), so it isn't followed.
+const MAX_TRACKED_SPEED : f32 = 3000.0;
+
+/// How a tile layer is split up into square chunks of tiles.
+#[derive(Debug, Clone)]
+pub struct ChunkLayout {
+	/// Where the layer's bottom left corner is.
+	offset : Vec2,
+	/// How much space each tile gets.
+	tile_space : Vec2,
+	/// The layer's width (in tiles).
+	width : usize,
+	/// The layer's height (in tiles).
+	height : usize,
+	/// How many tiles wide and tall each chunk is.
+	chunk_tiles : usize,
+}
+
+impl ChunkLayout {
+	/// Creates an instance for a layer (with its size in tiles).
+	pub fn new(offset : &Vec2, tile_space : &Vec2, width : usize, height : usize, chunk_tiles : usize) -> ChunkLayout {
+		ChunkLayout {
+			offset : offset.clone(),
+			tile_space : tile_space.clone(),
+			width,
+			height,
+			chunk_tiles : chunk_tiles.max(1),
+		}
+	}
+
+	/// Gets how many chunks wide the layer is.
+	pub fn get_columns(&self) -> usize {
+		(self.width + self.chunk_tiles - 1) / self.chunk_tiles
+	}
+
+	/// Gets how many chunks tall the layer is.
+	pub fn get_rows(&self) -> usize {
+		(self.height + self.chunk_tiles - 1) / self.chunk_tiles
+	}
+
+	/// Gets the total number of chunks.
+	pub fn chunk_count(&self) -> usize {
+		self.get_columns() * self.get_rows()
+	}
+
+	/// Gets the index of the chunk holding a tile (by its column and row in the layer, counting from the top left).
+	pub fn chunk_of(&self, x : usize, y : usize) -> usize {
+		(x / self.chunk_tiles) + (y / self.chunk_tiles) * self.get_columns()
+	}
+
+	/// Gets the area a chunk covers.
+	pub fn chunk_bounds(&self, index : usize) -> Bounds2 {
+		let columns = self.get_columns();
+		let x_start = (index % columns) * self.chunk_tiles;
+		let y_start = (index / columns) * self.chunk_tiles;
+		let x_end = (x_start + self.chunk_tiles).min(self.width);
+		let y_end = (y_start + self.chunk_tiles).min(self.height);
+		// Rows count down from the top, while positions count up from the bottom.
+		Bounds2::from_points(
+			&(self.offset + Vec2::new(x_start as f32 * self.tile_space.x, (self.height - y_end) as f32 * self.tile_space.y)),
+			&(self.offset + Vec2::new(x_end as f32 * self.tile_space.x, (self.height - y_start) as f32 * self.tile_space.y)),
+		)
+	}
+
+	/// Gets the indices of all chunks that overlap an area (expanded by some margin).
+	pub fn chunks_near(&self, area : &Bounds2, margin : f32) -> Vec<usize> {
+		let expanded = Bounds2::from_points(
+			&Vec2::new(area.x_min() - margin, area.y_min() - margin),
+			&Vec2::new(area.x_max() + margin, area.y_max() + margin),
+		);
+		(0..self.chunk_count()).filter(|index| self.chunk_bounds(*index).overlaps(&expanded)).collect()
+	}
+}
+
+impl ShiftOrigin for ChunkLayout {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.offset.shift_origin(shift);
+	}
+}
+
+/// What changed about which chunks should exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkChanges {
+	/// The chunks that need to be built.
+	pub load : Vec<usize>,
+	/// The chunks that aren't needed yet, but are where the view's heading (so should be built if there's time).
+	pub prefetch : Vec<usize>,
+	/// The chunks that can be dropped.
+	pub unload : Vec<usize>,
+}
+
+/// Tracks which chunks of a layer are built, as the view moves around.
+pub struct ChunkStreamer {
+	/// How the layer is split up.
+	layout : ChunkLayout,
+	/// Whether each chunk is currently built.
+	loaded : Vec<bool>,
+}
+
+impl ChunkStreamer {
+	/// Creates an instance with nothing built.
+	pub fn new(layout : ChunkLayout) -> ChunkStreamer {
+		let count = layout.chunk_count();
+		ChunkStreamer {
+			layout,
+			loaded : vec![false; count],
+		}
+	}
+
+	/// Gets the layout.
+	pub fn get_layout<'a>(&'a self) -> &'a ChunkLayout {
+		&self.layout
+	}
+
+	/// Whether a chunk is built.
+	pub fn is_loaded(&self, index : usize) -> bool {
+		self.loaded.get(index).cloned().unwrap_or(false)
+	}
+
+	/// Gets how many chunks are built.
+	pub fn loaded_count(&self) -> usize {
+		self.loaded.iter().filter(|loaded| **loaded).count()
+	}
+
+	/// Works out which chunks to build and drop for the current view, and records them as done.
+	pub fn update(&mut self, view : &Bounds2) -> ChunkChanges {
+		self.update_ahead(view, view, 0)
+	}
+
+	/// Like `update()`, but also picks up to `budget` chunks near where the view's heading to build early (closest to it first).
+	/// Chunks near either area are kept, so the ones built early aren't dropped before the view gets there.
+	pub fn update_ahead(&mut self, view : &Bounds2, ahead : &Bounds2, budget : usize) -> ChunkChanges {
+		let mut changes = ChunkChanges { load : Vec::new(), prefetch : Vec::new(), unload : Vec::new() };
+		for index in self.layout.chunks_near(view, LOAD_MARGIN) {
+			if !self.loaded[index] {
+				self.loaded[index] = true;
+				changes.load.push(index);
+			}
+		}
+		if 0 < budget {
+			let center = bounds_center(ahead);
+			let mut wanted : Vec<usize> = self.layout.chunks_near(ahead, LOAD_MARGIN).into_iter().filter(|index| !self.loaded[*index]).collect();
+			wanted.sort_by(|first, second| {
+				let distance = |index : usize| (bounds_center(&self.layout.chunk_bounds(index)) - center).length();
+				distance(*first).partial_cmp(&distance(*second)).unwrap()
+			});
+			for index in wanted.into_iter().take(budget) {
+				self.loaded[index] = true;
+				changes.prefetch.push(index);
+			}
+		}
+		let mut keep = self.layout.chunks_near(view, UNLOAD_MARGIN);
+		keep.extend(self.layout.chunks_near(ahead, UNLOAD_MARGIN));
+		for index in 0..self.loaded.len() {
+			if self.loaded[index] && !keep.contains(&index) {
+				self.loaded[index] = false;
+				changes.unload.push(index);
+			}
+		}
+		changes
+	}
+}
+
+impl ShiftOrigin for ChunkStreamer {
+	/// Which chunks are built doesn't change, since they all move together.
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.layout.shift_origin(shift);
+	}
+}
+
+/// Follows how the view is moving, to guess where it'll be soon.
+pub struct ViewPredictor {
+	/// The center of the view as of the last update (None until there's been one).
+	last_center : Option<Vec2>,
+	/// How fast the view's been moving (smoothed, in pixels per second).
+	velocity : Vec2,
+}
+
+impl ViewPredictor {
+	/// Creates an instance that hasn't seen the view yet.
+	pub fn new() -> ViewPredictor {
+		ViewPredictor {
+			last_center : None,
+			velocity : Vec2::zero(),
+		}
+	}
+
+	/// Forgets how the view was moving (as in when a new map is loaded).
+	pub fn reset(&mut self) {
+		self.last_center = None;
+		self.velocity = Vec2::zero();
+	}
+
+	/// Gets how fast the view's been moving (in pixels per second).
+	pub fn get_velocity(&self) -> Vec2 {
+		self.velocity
+	}
+
+	/// Takes in where the view is now, and gives back where it's guessed to be in a little while.
+	pub fn update(&mut self, view : &Bounds2, elapsed_seconds : f32) -> Bounds2 {
+		let center = bounds_center(view);
+		if let Some(last) = self.last_center {
+			if 0.0 < elapsed_seconds {
+				let measured = (center - last) * (1.0 / elapsed_seconds);
+				if MAX_TRACKED_SPEED < measured.length() {
+					self.velocity = Vec2::zero(); // Jumped somewhere, so there's nothing to follow.
+				} else {
+					self.velocity += (measured - self.velocity) * (elapsed_seconds * VELOCITY_SMOOTHING).min(1.0);
+				}
+			}
+		}
+		self.last_center = Some(center);
+		let mut ahead = view.clone();
+		ahead.translate(&(self.velocity * PREDICT_SECONDS));
+		ahead
+	}
+}
+
+impl ShiftOrigin for ViewPredictor {
+	/// The view moves with everything else, which shouldn't count as it moving.
+	fn shift_origin(&mut self, shift : &Vec2) {
+		if let Some(last) = &mut self.last_center {
+			last.shift_origin(shift);
+		}
+	}
+}
+
+/// Gets the middle of an area.
+fn bounds_center(bounds : &Bounds2) -> Vec2 {
+	Vec2::new(0.5 * (bounds.x_min() + bounds.x_max()), 0.5 * (bounds.y_min() + bounds.y_max()))
+}
+
+#[cfg(test)]
+mod tests_tile_chunks {
+	use super::*;
+
+	#[test]
+	fn layout() {
+		let layout = ChunkLayout::new(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0), 70, 40, 32);
+		assert_eq!((layout.get_columns(), layout.get_rows()), (3, 2));
+		assert_eq!(layout.chunk_of(33, 0), 1);
+		assert_eq!(layout.chunk_of(69, 39), 5);
+		// The top left chunk is at the top of the layer.
+		let top_left = layout.chunk_bounds(0);
+		assert_eq!((top_left.x_min(), top_left.x_max()), (0.0, 512.0));
+		assert_eq!((top_left.y_min(), top_left.y_max()), (128.0, 640.0));
+		// Edge chunks are cut short.
+		let bottom_right = layout.chunk_bounds(5);
+		assert_eq!((bottom_right.x_min(), bottom_right.x_max()), (1024.0, 1120.0));
+		assert_eq!((bottom_right.y_min(), bottom_right.y_max()), (0.0, 128.0));
+	}
+
+	#[test]
+	fn streaming() {
+		let layout = ChunkLayout::new(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0), 320, 32, 32); // A row of ten 512 pixel chunks.
+		let mut streamer = ChunkStreamer::new(layout);
+		let view = |x : f32| Bounds2::from_centered_rect(&Vec2::new(x, 256.0), 256.0, 256.0);
+
+		assert_eq!(streamer.update(&view(200.0)), ChunkChanges { load : vec![0], prefetch : vec![], unload : vec![] });
+		assert_eq!(streamer.update(&view(200.0)), ChunkChanges { load : vec![], prefetch : vec![], unload : vec![] });
+		// Nearing the next chunk builds it before it's on screen.
+		assert_eq!(streamer.update(&view(300.0)).load, vec![1]);
+		// Chunks are only dropped once they're well out of view.
+		let changes = streamer.update(&view(900.0));
+		assert!(changes.unload.is_empty());
+		assert_eq!(streamer.update(&view(1400.0)).unload, vec![0]);
+		assert!(!streamer.is_loaded(0));
+		assert_eq!(streamer.loaded_count(), 3);
+	}
+
+	#[test]
+	fn prefetching() {
+		let layout = ChunkLayout::new(&Vec2::new(0.0, 0.0), &Vec2::new(16.0, 16.0), 320, 32, 32); // A row of ten 512 pixel chunks.
+		let mut streamer = ChunkStreamer::new(layout);
+		let view = |x : f32| Bounds2::from_centered_rect(&Vec2::new(x, 256.0), 256.0, 256.0);
+
+		// What's needed now is always built, and then what's ahead (closest first) as the budget allows.
+		let changes = streamer.update_ahead(&view(200.0), &view(1300.0), 1);
+		assert_eq!((changes.load, changes.prefetch), (vec![0], vec![2]));
+		assert_eq!(streamer.update_ahead(&view(200.0), &view(1300.0), 5).prefetch, vec![3]);
+		// Built early chunks are kept while they're still ahead, and dropped once they aren't.
+		assert!(streamer.update_ahead(&view(200.0), &view(1300.0), 5).unload.is_empty());
+		assert_eq!(streamer.update(&view(200.0)).unload, vec![2, 3]);
+	}
+
+	#[test]
+	fn predicting() {
+		let mut predictor = ViewPredictor::new();
+		let view = |x : f32| Bounds2::from_centered_rect(&Vec2::new(x, 0.0), 100.0, 100.0);
+
+		// Nothing to go on at first.
+		assert_eq!(predictor.update(&view(0.0), 0.1).x_min(), -50.0);
+		// Moving steadily gets followed (smoothly), and predicted ahead.
+		for step in 1..=20 {
+			predictor.update(&view(step as f32 * 10.0), 0.1);
+		}
+		assert!((predictor.get_velocity().x - 100.0).abs() < 1.0);
+		let ahead = predictor.update(&view(210.0), 0.1);
+		assert!((ahead.x_min() - (160.0 + 100.0 * PREDICT_SECONDS)).abs() < 1.0);
+		// Shifting the origin isn't movement.
+		predictor.shift_origin(&Vec2::new(-1000.0, 0.0));
+		predictor.update(&view(-780.0), 0.1);
+		assert!((predictor.get_velocity().x - 100.0).abs() < 1.0);
+		// Neither is jumping somewhere else.
+		predictor.update(&view(5000.0), 0.1);
+		assert_eq!(predictor.get_velocity().length(), 0.0);
+	}
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::geo::vec2::Vec2;
+use crate::tiled::{TiledFile, TiledTileId};
+use crate::origin_rebase::ShiftOrigin;
+
+/// The collision shape type that is shared between tile instances.
+const SHARED_SHAPE_TYPE : &str = "collision";
+
+/// The collision polygons from a tileset, stored once per tile (rather than once per placed tile).
+///
+/// Placed tiles only store a `TileShapeInstance`, which is resolved against this when the world-space points are needed. Swapping in a different tileset's shapes changes every placed tile at once.
+pub struct TileShapeLibrary {
+	/// The collision polygons for each tile that has any (relative to the tile's bottom left corner).
+	shapes : HashMap<TiledTileId, Vec<Vec<Vec2>>>,
+}
+
+impl TileShapeLibrary {
+	/// Creates an empty instance.
+	pub fn new() -> TileShapeLibrary {
+		TileShapeLibrary {
+			shapes : HashMap::new(),
+		}
+	}
+
+	/// Replaces all shapes with the ones from the given file's tiles.
+	pub fn load_from(&mut self, file : &TiledFile) {
+		self.shapes.clear();
+		for (id, tile) in file.get_tiles().iter().enumerate() {
+			let polygons : Vec<Vec<Vec2>> = tile.get_collision_polygons().iter()
+				.filter(|polygon| SHARED_SHAPE_TYPE == polygon.r#type)
+				.map(|polygon| polygon.points.clone())
+				.collect();
+			if !polygons.is_empty() {
+				self.shapes.insert(id as TiledTileId, polygons);
+			}
+		}
+	}
+
+	/// Whether a tile has any shapes.
+	pub fn has_shapes(&self, id : TiledTileId) -> bool {
+		self.shapes.contains_key(&id)
+	}
+
+	/// Gets a tile's shapes (relative to the tile). Empty if it has none.
+	pub fn get_shapes<'a>(&'a self, id : TiledTileId) -> &'a [Vec<Vec2>] {
+		match self.shapes.get(&id) {
+			Some(polygons) => polygons,
+			None => &[],
+		}
+	}
+
+	/// Gets how many tiles have shapes.
+	pub fn tile_count(&self) -> usize {
+		self.shapes.len()
+	}
+}
+
+/// A placed tile whose collision polygons live in a `TileShapeLibrary`.
+#[derive(Debug, Clone)]
+pub struct TileShapeInstance {
+	/// The tile whose shapes to use.
+	pub tile_id : TiledTileId,
+	/// Where the tile's bottom left corner is in the world.
+	pub offset : Vec2,
+}
+
+impl TileShapeInstance {
+	/// Gets the world-space polygons for this instance.
+	pub fn resolve<'a>(&'a self, library : &'a TileShapeLibrary) -> impl Iterator<Item = Vec<Vec2>> + 'a {
+		library.get_shapes(self.tile_id).iter().map(move |polygon| polygon.iter().map(|point| point + self.offset).collect())
+	}
+}
+
+impl ShiftOrigin for TileShapeInstance {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.offset.shift_origin(shift);
+	}
+}
+
+#[cfg(test)]
+mod tests_tile_shapes {
+	use super::*;
+
+	/// Makes a map with a single tile (ID 1) with the given polygon corners.
+	fn make_file(corners : &str) -> TiledFile {
+		let mut file = TiledFile::new();
+		file.read_json(&format!(
+			"{{\"tilesets\":[{{\"firstgid\":1,\"image\":\"a.png\",\"tilewidth\":8,\"tileheight\":8,\"columns\":1,\"tilecount\":1,\"tiles\":[{{\"id\":0,\"objectgroup\":{{\"objects\":[{{\"type\":\"collision\",\"x\":0,\"y\":0,\"width\":0,\"height\":0,\"polygon\":{}}}]}}}}]}}]}}",
+			corners,
+		)).unwrap();
+		file
+	}
+
+	#[test]
+	fn sharing_and_swapping() {
+		let mut library = TileShapeLibrary::new();
+		library.load_from(&make_file("[{\"x\":0,\"y\":8},{\"x\":8,\"y\":8},{\"x\":8,\"y\":0}]"));
+		assert_eq!(library.tile_count(), 1);
+		assert!(!library.has_shapes(0));
+		assert!(library.has_shapes(1));
+
+		let instances = vec![
+			TileShapeInstance { tile_id : 1, offset : Vec2::new(0.0, 0.0) },
+			TileShapeInstance { tile_id : 1, offset : Vec2::new(8.0, 16.0) },
+		];
+		let polygons : Vec<Vec<Vec2>> = instances[1].resolve(&library).collect();
+		assert_eq!(polygons.len(), 1);
+		assert_eq!((polygons[0][2].x, polygons[0][2].y), (16.0, 24.0)); // Tiled's (8,0) is the tile's top right.
+
+		// Swapping the tileset changes every instance.
+		library.load_from(&make_file("[{\"x\":0,\"y\":8},{\"x\":4,\"y\":4},{\"x\":8,\"y\":8}]"));
+		for instance in &instances {
+			let polygon = instance.resolve(&library).next().unwrap();
+			assert_eq!(polygon[1].x - instance.offset.x, 4.0);
+			assert_eq!(polygon[1].y - instance.offset.y, 4.0);
+		}
+	}
+}
@@ -4,12 +4,23 @@ use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 
 use crate::externals::*;
+use crate::debug_log;
 use crate::static_singletons::{get_tiled_generator, get_game};
 use crate::geo::vec2::*;
 use crate::geo::bounds2::Bounds2;
+use crate::byte_reader::ByteReader;
+use crate::display_texture::COLOR_KEY_PROPERTY;
+use crate::tiled_json::*;
+use crate::asset_manifest::asset_url;
+use serde_json::Value;
 
 pub type TiledTileId = u32;
 
+/// The bytes that every packed map buffer starts with.
+pub const PACKED_MAP_MAGIC : &[u8; 4] = b"RBTM";
+/// The version of the packed map format that can be read.
+pub const PACKED_MAP_VERSION : u32 = 3;
+
 /// All relevant data in a given TiledFile.
 pub struct TiledFile {
 	/// Whether this file is being loaded.
@@ -22,6 +33,14 @@ pub struct TiledFile {
 	tile_layers : Vec<TiledTileLayer>,
 	/// Important points.
 	pub points : Vec<TiledPoint>,
+	/// Lines made up of multiple points (from polyline objects).
+	pub polylines : Vec<TiledPolyline>,
+	/// Named areas (from rectangle objects).
+	pub regions : Vec<TiledRegion>,
+	/// Properties for each tileset image (from the tileset's custom properties), keyed by the image's URL.
+	image_properties : HashMap<String, Vec<TiledStringProp>>,
+	/// The map's own custom properties (as in its music).
+	properties : Vec<TiledStringProp>,
 	/// The max y value from any piece of the file.
 	/// Used to convert cartesian coordinates to non-cartesian.
 	max_y : f32,
@@ -36,6 +55,10 @@ impl TiledFile {
 			tiles : Vec::new(),
 			tile_layers : Vec::new(),
 			points : Vec::new(),
+			polylines : Vec::new(),
+			regions : Vec::new(),
+			image_properties : HashMap::new(),
+			properties : Vec::new(),
 			max_y : 0.0,
 		}
 	}
@@ -47,6 +70,14 @@ impl TiledFile {
 		for point in &self.points {
 			max_y = max_y.max(point.position.y);
 		}
+		for polyline in &self.polylines {
+			for point in &polyline.points {
+				max_y = max_y.max(point.y);
+			}
+		}
+		for region in &self.regions {
+			max_y = max_y.max(region.bounds.y_max());
+		}
 		for layer in &self.tile_layers {
 			let mut max_tile_height : f32 = 0.0;
 			for tile_id in &layer.tile_data {
@@ -65,6 +96,385 @@ impl TiledFile {
 		for point in &mut self.points {
 			point.flip_y(max_y);
 		}
+		for polyline in &mut self.polylines {
+			polyline.flip_y(max_y);
+		}
+		for region in &mut self.regions {
+			region.flip_y(max_y);
+		}
+	}
+
+	/// Reads in everything from a packed map buffer (see `tiledFileLoader.ts` for the writer).
+	///
+	/// All values are little-endian. Strings and lists are prefixed by their length (as a u32). The layout is:
+	/// * The magic bytes "RBTM" and the version (u32).
+	/// * Tiles (u32 count): image URL, x, y, width, height (u16 each), boolean properties (name, u8), string properties (name, value), collision rectangles (type, x1, y1, x2, y2 as f32), and collision polygons (type, f32 list of x/y pairs).
+	/// * Tile layers (u32 count): name, x and y offset (f32), width, height, pixel width, pixel height (u32 each), and tile IDs (u32 list).
+	/// * Points (u32 count): name, type, x, y (f32), and properties (name, value).
+	/// * Polylines (u32 count): name, type, f32 list of x/y pairs, and properties (name, value).
+	/// * Regions (u32 count): name, type, x1, y1, x2, y2 (f32).
+	/// * Image properties (u32 count): image URL, name, value.
+	/// * Map properties (name, value).
+	///
+	/// Nothing is changed if the buffer can't be read.
+	pub fn read_packed(&mut self, bytes : &[u8]) -> Result<(), String> {
+		let mut reader = ByteReader::new(bytes);
+		if PACKED_MAP_MAGIC != reader.read_bytes(PACKED_MAP_MAGIC.len(), "the header")? {
+			return Err(String::from("Not a packed map (bad header)"));
+		}
+		let version = reader.read_u32("the version")?;
+		if PACKED_MAP_VERSION != version {
+			return Err(format!("Unsupported packed map version {} (expected {})", version, PACKED_MAP_VERSION));
+		}
+
+		let mut tiles = Vec::new();
+		for _ in 0..reader.read_count(12, "the tile count")? {
+			let mut tile = TiledTile {
+				image_url : reader.read_string("a tile's image")?,
+				position : Vec2::zero(),
+				size : Vec2::zero(),
+				boolean_properties : Vec::new(),
+				string_properties : Vec::new(),
+				collision_rects : Vec::new(),
+				collision_polygons : Vec::new(),
+			};
+			tile.position.x = reader.read_u16("a tile's x")? as f32;
+			tile.position.y = reader.read_u16("a tile's y")? as f32;
+			tile.size.x = reader.read_u16("a tile's width")? as f32;
+			tile.size.y = reader.read_u16("a tile's height")? as f32;
+			for _ in 0..reader.read_count(5, "a tile's boolean property count")? {
+				tile.boolean_properties.push(TiledBoolProp {
+					name : reader.read_string("a tile's boolean property name")?,
+					value : reader.read_bool("a tile's boolean property value")?,
+				});
+			}
+			tile.string_properties = read_packed_properties(&mut reader)?;
+			for _ in 0..reader.read_count(20, "a tile's collision rectangle count")? {
+				let r#type = reader.read_string("a collision rectangle's type")?;
+				let x1 = reader.read_f32("a collision rectangle's x1")?;
+				let y1 = reader.read_f32("a collision rectangle's y1")?;
+				let x2 = reader.read_f32("a collision rectangle's x2")?;
+				let y2 = reader.read_f32("a collision rectangle's y2")?;
+				tile.collision_rects.push(TiledRect {
+					r#type,
+					position : Bounds2::from_points(&Vec2::new(x1, y1), &Vec2::new(x2, y2)),
+				});
+			}
+			for _ in 0..reader.read_count(8, "a tile's collision polygon count")? {
+				let r#type = reader.read_string("a collision polygon's type")?;
+				let points = read_packed_points(&mut reader, "a collision polygon's points")?;
+				tile.collision_polygons.push(TiledPolygon { r#type, points });
+			}
+			tiles.push(tile);
+		}
+
+		let mut tile_layers = Vec::new();
+		for _ in 0..reader.read_count(36, "the tile layer count")? {
+			let name = reader.read_string("a layer's name")?;
+			let x_offset = reader.read_f32("a layer's x offset")?;
+			let y_offset = reader.read_f32("a layer's y offset")?;
+			let width = reader.read_u32("a layer's width")? as usize;
+			let height = reader.read_u32("a layer's height")? as usize;
+			let pixel_width = reader.read_u32("a layer's pixel width")?;
+			let pixel_height = reader.read_u32("a layer's pixel height")?;
+			let tile_data = reader.read_u32_list("a layer's tiles")?;
+			if tile_data.len() != width * height {
+				return Err(format!("Layer {:?} has {} tiles but is {}x{}", name, tile_data.len(), width, height));
+			}
+			if let Some(id) = tile_data.iter().find(|id| tiles.len() <= **id as usize) {
+				return Err(format!("Layer {:?} uses tile {} but there are only {} tiles", name, id, tiles.len()));
+			}
+			tile_layers.push(TiledTileLayer {
+				name,
+				offset : Vec2::new(x_offset, y_offset),
+				width,
+				height,
+				size : Vec2::new(pixel_width as f32, pixel_height as f32),
+				tile_data,
+			});
+		}
+
+		let mut points = Vec::new();
+		for _ in 0..reader.read_count(20, "the point count")? {
+			let name = reader.read_string("a point's name")?;
+			let r#type = reader.read_string("a point's type")?;
+			let x = reader.read_f32("a point's x")?;
+			let y = reader.read_f32("a point's y")?;
+			points.push(TiledPoint {
+				position : Vec2::new(x, y),
+				name,
+				r#type,
+				properties : read_packed_properties(&mut reader)?,
+			});
+		}
+
+		let mut polylines = Vec::new();
+		for _ in 0..reader.read_count(16, "the polyline count")? {
+			let name = reader.read_string("a polyline's name")?;
+			let r#type = reader.read_string("a polyline's type")?;
+			polylines.push(TiledPolyline {
+				points : read_packed_points(&mut reader, "a polyline's points")?,
+				name,
+				r#type,
+				properties : read_packed_properties(&mut reader)?,
+			});
+		}
+
+		let mut regions = Vec::new();
+		for _ in 0..reader.read_count(24, "the region count")? {
+			let name = reader.read_string("a region's name")?;
+			let r#type = reader.read_string("a region's type")?;
+			let x1 = reader.read_f32("a region's x1")?;
+			let y1 = reader.read_f32("a region's y1")?;
+			let x2 = reader.read_f32("a region's x2")?;
+			let y2 = reader.read_f32("a region's y2")?;
+			regions.push(TiledRegion {
+				bounds : Bounds2::from_points(&Vec2::new(x1, y1), &Vec2::new(x2, y2)),
+				name,
+				r#type,
+			});
+		}
+
+		let mut image_properties = Vec::new();
+		for _ in 0..reader.read_count(12, "the image property count")? {
+			let url = reader.read_string("an image property's image")?;
+			let name = reader.read_string("an image property's name")?;
+			let value = reader.read_string("an image property's value")?;
+			image_properties.push((url, TiledStringProp { name, value }));
+		}
+
+		let properties = read_packed_properties(&mut reader)?;
+
+		if !reader.is_done() {
+			return Err(format!("Unexpected data after byte {}", reader.get_offset()));
+		}
+		self.tiles.extend(tiles);
+		self.tile_layers.extend(tile_layers);
+		self.points.extend(points);
+		self.polylines.extend(polylines);
+		self.regions.extend(regions);
+		for (url, property) in image_properties {
+			self.add_image_property(&url, property);
+		}
+		for property in properties {
+			self.add_property(property);
+		}
+		Ok(())
+	}
+
+	/// Reads in everything from the text of Tiled's JSON map export (with the tilesets embedded). This follows the same rules as the TypeScript loader.
+	///
+	/// Fails (without changing anything) if the text isn't a valid map. Otherwise gives descriptions of any parts that had to be skipped.
+	pub fn read_json(&mut self, text : &str) -> Result<Vec<String>, String> {
+		let map : JsonMap = match serde_json::from_str(text) {
+			Ok(map) => map,
+			Err(error) => { return Err(format!("Invalid map JSON: {}", error)); },
+		};
+		let mut warnings = Vec::new();
+
+		// First map all the tile IDs to the data.
+		let mut tiles : HashMap<TiledTileId, TiledTile> = HashMap::new();
+		let mut max_id : TiledTileId = 0;
+		for (tileset_index, tileset) in map.tilesets.iter().enumerate() {
+			let (tile_width, tile_height, columns, count, image_url, id_offset, tileset_tiles) = match (tileset.tilewidth, tileset.tileheight, tileset.columns, tileset.tilecount, &tileset.image, tileset.firstgid, &tileset.tiles) {
+				(Some(width), Some(height), Some(columns), Some(count), Some(image), Some(first), Some(tiles)) if 0 < columns => (width, height, columns, count, image, first, tiles),
+				_ => {
+					warnings.push(format!("Tileset #{} is missing one of \"tilewidth\", \"tileheight\", \"columns\", \"tilecount\", \"image\", \"firstgid\", or \"tiles\"", tileset_index));
+					continue;
+				},
+			};
+			let rows = count as f32 / columns as f32;
+			for property in &tileset.properties {
+				if let (Some(name), Some(value)) = (&property.name, property.value_to_string()) {
+					self.add_image_property(image_url, TiledStringProp { name : name.clone(), value });
+				}
+			}
+			if let Some(color) = &tileset.transparentcolor {
+				// Tiled's own transparent color setting works the same as the custom property (which takes priority).
+				if self.get_image_property(image_url, COLOR_KEY_PROPERTY).is_none() {
+					self.add_image_property(image_url, TiledStringProp { name : COLOR_KEY_PROPERTY.to_string(), value : color.clone() });
+				}
+			}
+			for json_tile in tileset_tiles {
+				let mut tile = TiledTile {
+					image_url : image_url.clone(),
+					position : Vec2::new(
+						(json_tile.id % columns) as f32 * tile_width,
+						(rows - (json_tile.id / columns) as f32 - 1.0) * tile_height,
+					),
+					size : Vec2::new(tile_width, tile_height),
+					boolean_properties : Vec::new(),
+					string_properties : Vec::new(),
+					collision_rects : Vec::new(),
+					collision_polygons : Vec::new(),
+				};
+				for property in &json_tile.properties {
+					let name = match &property.name { Some(name) => name.clone(), None => { continue; } };
+					match (property.r#type.as_deref(), &property.value) {
+						(Some("bool"), Some(Value::Bool(value))) => tile.boolean_properties.push(TiledBoolProp { name, value : *value }),
+						(Some("string"), Some(Value::String(value))) => tile.string_properties.push(TiledStringProp { name, value : value.clone() }),
+						_ => {},
+					}
+				}
+				// Then get the collision information (flipped to be relative to the bottom of the tile).
+				if let Some(group) = &json_tile.objectgroup {
+					for object in &group.objects {
+						let (r#type, x, y, width, height) = match (&object.r#type, object.x, object.y, object.width, object.height) {
+							(Some(r#type), Some(x), Some(y), Some(width), Some(height)) => (r#type.clone(), x, y, width, height),
+							_ => { continue; },
+						};
+						if let Some(corners) = &object.polygon {
+							tile.collision_polygons.push(TiledPolygon {
+								r#type,
+								points : corners.iter().map(|corner| Vec2::new(x + corner.x, tile_height - (y + corner.y))).collect(),
+							});
+						} else {
+							tile.collision_rects.push(TiledRect {
+								r#type,
+								position : Bounds2::from_points(
+									&Vec2::new(x, tile_height - y),
+									&Vec2::new(x + width, tile_height - (y + height)),
+								),
+							});
+						}
+					}
+				}
+				let id = id_offset + json_tile.id;
+				max_id = max_id.max(id);
+				tiles.insert(id, tile);
+			}
+		}
+
+		// Then lay out the layers.
+		let mut tile_layers = Vec::new();
+		let mut points = Vec::new();
+		let mut polylines = Vec::new();
+		let mut regions = Vec::new();
+		for (layer_index, layer) in map.layers.iter().enumerate() {
+			let name = layer.name.clone().unwrap_or_default();
+			match layer.r#type.as_deref() {
+				Some("tilelayer") => {
+					let (width, height, data) = match (layer.width, layer.height, &layer.data) {
+						(Some(width), Some(height), Some(data)) => (width, height, data),
+						_ => {
+							warnings.push(format!("Layer {} (index={}) is missing one of \"width\", \"height\", or \"data\"", name, layer_index));
+							continue;
+						},
+					};
+					if data.len() != width * height {
+						warnings.push(format!("Layer {} (index={}) has {} tiles but is {}x{}", name, layer_index, data.len(), width, height));
+						continue;
+					}
+					// Find the resulting total dimensions.
+					let mut max_tile_size = Vec2::zero();
+					for id in data {
+						if let Some(tile) = tiles.get(id) {
+							max_tile_size.x = max_tile_size.x.max(tile.size.x);
+							max_tile_size.y = max_tile_size.y.max(tile.size.y);
+						}
+					}
+					max_id = data.iter().fold(max_id, |max, id| max.max(*id));
+					tile_layers.push(TiledTileLayer {
+						name,
+						offset : Vec2::new(layer.offsetx.unwrap_or(0.0), layer.offsety.unwrap_or(0.0)),
+						width,
+						height,
+						size : Vec2::new(width as f32 * max_tile_size.x, height as f32 * max_tile_size.y),
+						tile_data : data.clone(),
+					});
+				},
+				Some("objectgroup") => {
+					for (object_index, object) in layer.objects.iter().enumerate() {
+						let r#type = object.r#type.clone().unwrap_or_default();
+						let (x, y) = match (object.x, object.y) {
+							(Some(x), Some(y)) => (x, y),
+							_ => {
+								warnings.push(format!("Object #{} in layer #{} has no position", object_index, layer_index));
+								continue;
+							},
+						};
+						let properties : Vec<TiledStringProp> = object.properties.iter().filter_map(|property| Some(TiledStringProp {
+							name : property.name.clone()?,
+							value : property.value_to_string()?, // All types are converted to strings.
+						})).collect();
+						if object.point {
+							let name = match &object.name {
+								Some(name) => name.clone(),
+								None => {
+									warnings.push(format!("Point #{} in layer #{} has no \"name\"", object_index, layer_index));
+									continue;
+								},
+							};
+							points.push(TiledPoint { position : Vec2::new(x, y), name, r#type, properties });
+						} else if let Some(line) = &object.polyline {
+							polylines.push(TiledPolyline {
+								points : line.iter().map(|point| Vec2::new(x + point.x, y + point.y)).collect(),
+								name : object.name.clone().unwrap_or_default(),
+								r#type,
+								properties,
+							});
+						} else if let (Some(width), Some(height), None, None) = (object.width, object.height, object.ellipse, &object.polygon) {
+							regions.push(TiledRegion {
+								bounds : Bounds2::from_points(&Vec2::new(x, y), &Vec2::new(x + width, y + height)),
+								name : object.name.clone().unwrap_or_default(),
+								r#type,
+							});
+						} else {
+							warnings.push(format!("Object #{} in layer #{} has an unrecognized type", object_index, layer_index));
+						}
+					}
+				},
+				other => {
+					warnings.push(format!("Unsure of how to deal with Tiled layer type {:?}", other.unwrap_or("")));
+				},
+			}
+		}
+
+		// Every ID up to the largest one gets a tile (using defaults for any that weren't described).
+		for id in 0..=max_id {
+			self.tiles.push(tiles.remove(&id).unwrap_or_else(|| TiledTile {
+				image_url : String::new(),
+				position : Vec2::zero(),
+				size : Vec2::new(1.0, 1.0),
+				boolean_properties : Vec::new(),
+				string_properties : Vec::new(),
+				collision_rects : Vec::new(),
+				collision_polygons : Vec::new(),
+			}));
+		}
+		self.tile_layers.extend(tile_layers);
+		self.points.extend(points);
+		self.polylines.extend(polylines);
+		self.regions.extend(regions);
+		for property in &map.properties {
+			if let (Some(name), Some(value)) = (&property.name, property.value_to_string()) {
+				self.add_property(TiledStringProp { name : name.clone(), value });
+			}
+		}
+		Ok(warnings)
+	}
+
+	/// Stores one of the map's own properties (replacing any with the same name).
+	fn add_property(&mut self, property : TiledStringProp) {
+		self.properties.retain(|existing| existing.name != property.name);
+		self.properties.push(property);
+	}
+
+	/// Gets one of the map's own properties (if it has it).
+	pub fn get_property<'a>(&'a self, name : &str) -> Option<&'a str> {
+		self.properties.iter().find(|property| property.name == name).map(|property| property.value.as_str())
+	}
+
+	/// Stores a property for a tileset image (replacing any with the same name).
+	fn add_image_property(&mut self, image_url : &str, property : TiledStringProp) {
+		let properties = self.image_properties.entry(image_url.to_string()).or_insert_with(Vec::new);
+		properties.retain(|existing| existing.name != property.name);
+		properties.push(property);
+	}
+
+	/// Gets the value of a property on a tileset image (if it exists).
+	pub fn get_image_property<'a>(&'a self, image_url : &str, name : &str) -> Option<&'a str> {
+		self.image_properties.get(image_url)?.iter().find(|property| name == property.name).map(|property| property.value.as_str())
 	}
 
 	/// Gets the URL this was loaded from.
@@ -95,7 +505,7 @@ impl TiledFile {
 	/// Gets the number of layers.
 	pub fn layer_count(&self) -> usize {
 		for layer in &self.tile_layers {
-			log(&format!("Have layer {:?}", layer.name));
+			debug_log!("Have layer {:?}", layer.name);
 		}
 		self.tile_layers.len()
 	}
@@ -104,6 +514,42 @@ impl TiledFile {
 	pub fn get_points<'a>(&'a self) -> &'a Vec<TiledPoint> {
 		&self.points
 	}
+
+	/// Removes all points that the given function returns false for.
+	pub fn retain_points<F : FnMut(&TiledPoint) -> bool>(&mut self, keep : F) {
+		self.points.retain(keep);
+	}
+
+	/// Gets a ref to the polylines.
+	pub fn get_polylines<'a>(&'a self) -> &'a Vec<TiledPolyline> {
+		&self.polylines
+	}
+
+	/// Gets a ref to the regions.
+	pub fn get_regions<'a>(&'a self) -> &'a Vec<TiledRegion> {
+		&self.regions
+	}
+}
+
+/// Reads a list of string properties (name and value pairs) from a packed map buffer.
+fn read_packed_properties(reader : &mut ByteReader) -> Result<Vec<TiledStringProp>, String> {
+	let mut properties = Vec::new();
+	for _ in 0..reader.read_count(8, "a property count")? {
+		properties.push(TiledStringProp {
+			name : reader.read_string("a property name")?,
+			value : reader.read_string("a property value")?,
+		});
+	}
+	Ok(properties)
+}
+
+/// Reads a list of points (stored as x/y pairs) from a packed map buffer.
+fn read_packed_points(reader : &mut ByteReader, what : &str) -> Result<Vec<Vec2>, String> {
+	let values = reader.read_f32_list(what)?;
+	if 0 != values.len() % 2 {
+		return Err(format!("Odd number of values in {}", what));
+	}
+	Ok(values.chunks(2).map(|pair| Vec2::new(pair[0], pair[1])).collect())
 }
 
 /// A specific tile's info.
@@ -116,6 +562,8 @@ pub struct TiledTile {
 	size : Vec2,
 	/// The boolean properties.
 	boolean_properties : Vec<TiledBoolProp>,
+	/// The string properties.
+	string_properties : Vec<TiledStringProp>,
 	/// The collision geometry.
 	collision_rects : Vec<TiledRect>,
 	/// The collision polygons.
@@ -143,6 +591,21 @@ impl TiledTile {
 		&self.boolean_properties
 	}
 
+	/// Gets the string properties from this specific tile.
+	pub fn get_string_properties<'a>(&'a self) -> &'a Vec<TiledStringProp> {
+		&self.string_properties
+	}
+
+	/// Gets the value of a specific string property (if this tile has it).
+	pub fn get_string_property<'a>(&'a self, name : &str) -> Option<&'a str> {
+		for property in &self.string_properties {
+			if name == property.name {
+				return Some(&property.value);
+			}
+		}
+		None
+	}
+
 	/// Gets the collision rectangles from this specific tile.
 	pub fn get_collision_rectangles<'a>(&'a self) -> &'a Vec<TiledRect> {
 		&self.collision_rects
@@ -213,15 +676,79 @@ pub struct TiledPoint {
 	pub position : Vec2,
 	/// The point's name.
 	pub name : String,
+	/// The point's type (empty if none was given).
+	pub r#type : String,
+	/// The point's custom properties (all converted to strings).
+	pub properties : Vec<TiledStringProp>,
 }
 
 impl TiledPoint {
+	/// Gets the value of a property (if it exists).
+	pub fn get_property<'a>(&'a self, name : &str) -> Option<&'a str> {
+		for property in &self.properties {
+			if name == property.name {
+				return Some(&property.value);
+			}
+		}
+		None
+	}
+
 	/// Flips the y coordinate of all items inside this (converting from Cartesian coordinates to non-Cartesian).
 	fn flip_y(&mut self, max_y : f32) {
 		self.position.y = max_y - self.position.y;
 	}
 }
 
+/// A simple structure for storing a named line (made of multiple points) from a geometry layer.
+pub struct TiledPolyline {
+	/// The points along the line (in order).
+	pub points : Vec<Vec2>,
+	/// The line's name.
+	pub name : String,
+	/// The line's type (empty if none was given).
+	pub r#type : String,
+	/// The line's custom properties (all converted to strings).
+	pub properties : Vec<TiledStringProp>,
+}
+
+impl TiledPolyline {
+	/// Gets the value of a property (if it exists).
+	pub fn get_property<'a>(&'a self, name : &str) -> Option<&'a str> {
+		for property in &self.properties {
+			if name == property.name {
+				return Some(&property.value);
+			}
+		}
+		None
+	}
+
+	/// Flips the y coordinate of all items inside this (converting from Cartesian coordinates to non-Cartesian).
+	fn flip_y(&mut self, max_y : f32) {
+		for point in &mut self.points {
+			point.y = max_y - point.y;
+		}
+	}
+}
+
+/// A simple structure for storing a named area (from a rectangle object) in a geometry layer.
+pub struct TiledRegion {
+	/// The area covered.
+	pub bounds : Bounds2,
+	/// The region's name.
+	pub name : String,
+	/// The region's type (empty if none was given).
+	pub r#type : String,
+}
+
+impl TiledRegion {
+	/// Flips the y coordinate of all items inside this (converting from Cartesian coordinates to non-Cartesian).
+	fn flip_y(&mut self, max_y : f32) {
+		self.bounds = Bounds2::from_points(
+			&Vec2::new(self.bounds.x_min(), max_y - self.bounds.y_min()),
+			&Vec2::new(self.bounds.x_max(), max_y - self.bounds.y_max()),
+		);
+	}
+}
 
 /// A structure for storing an axis-aligned rectangle from Tiled.
 pub struct TiledRect {
@@ -247,6 +774,14 @@ pub struct TiledBoolProp {
 	pub value : bool,
 }
 
+/// A structure for storing string properties in Tiled.
+pub struct TiledStringProp {
+	/// The property's name.
+	pub name : String,
+	/// The property's value.
+	pub value : String,
+}
+
 /// The main way TiledFile objects are loaded in.
 ///
 /// This is basically a Rc<RefCell<TiledFile>>, with a little extra to make it easier to work with.
@@ -328,18 +863,18 @@ impl TiledGenerator {
 			file.url = url.to_string();
 		}
 		self.current.insert(url.to_string(), shared.clone());
-		startTiledFileLoad(url);
+		startTiledFileLoad(url, &asset_url(url));
 		Ok(())
 	}
 
 	fn borrow_file(&self, url : &str) -> RefMut<'_, TiledFile> {
-		assert!(self.current.contains_key(url), "Attempting to update Tiled file {:?} that is no longer stored in the generator!", url);
+		crate::lean_assert!(self.current.contains_key(url), "Attempting to update Tiled file {:?} that is no longer stored in the generator!", url);
 		self.current.get(url).unwrap().file.borrow_mut()
 	}
 
 	/// Concludes a callback for the given URL using the current file.
 	fn conclude(&mut self, url : &str) {
-		log(&format!("Concluding {:?}", url));
+		debug_log!("Concluding {:?}", url);
 		let completed = self.current.remove(url).unwrap();
 		{
 			let mut file = completed.file.borrow_mut();
@@ -362,6 +897,7 @@ pub fn tiled_generate_add_tile(file_url : String, image_url : String, x : u16, y
 		position: Vec2::new(x as f32, y as f32),
 		size: Vec2::new(width as f32, height as f32),
 		boolean_properties : Vec::new(),
+		string_properties : Vec::new(),
 		collision_rects : Vec::new(),
 		collision_polygons : Vec::new(),
 	});
@@ -380,6 +916,19 @@ pub fn tiled_generate_add_tile_boolean_property(file_url : String, name : String
 	);
 }
 
+/// Called to add a string property to the latest tile that was added.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_tile_string_property(file_url : String, name : String, value : String) {
+	get_tiled_generator().borrow_file(&file_url).tiles.last_mut().unwrap().string_properties.push(
+		TiledStringProp{
+			name,
+			value,
+		}
+	);
+}
+
 /// Called to add a collision rectangle to the latest tile that was added.
 ///
 /// This should only be called by external JavaScript code!
@@ -413,15 +962,95 @@ pub fn tiled_generate_add_tile_collision_polygon(file_url : String, type_ : Stri
 	);
 }
 
+/// Called to add a property to a tileset image (as in a color key).
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_image_property(file_url : String, image_url : String, name : String, value : String) {
+	get_tiled_generator().borrow_file(&file_url).add_image_property(&image_url, TiledStringProp { name, value });
+}
+
+/// Called to add one of the map's own properties.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_map_property(file_url : String, name : String, value : String) {
+	get_tiled_generator().borrow_file(&file_url).add_property(TiledStringProp { name, value });
+}
+
 /// Called to add a point of interest.
 ///
 /// This should only be called by external JavaScript code!
 #[wasm_bindgen]
-pub fn tiled_generate_add_point(file_url : String, name : String, x : f32, y : f32) {
+pub fn tiled_generate_add_point(file_url : String, name : String, type_ : String, x : f32, y : f32) {
 	get_tiled_generator().borrow_file(&file_url).points.push(
 		TiledPoint{
 			name,
+			r#type : type_,
 			position: Vec2::new(x, y),
+			properties : Vec::new(),
+		}
+	);
+}
+
+/// Called to add a custom property to the latest point that was added.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_point_property(file_url : String, name : String, value : String) {
+	get_tiled_generator().borrow_file(&file_url).points.last_mut().unwrap().properties.push(
+		TiledStringProp{
+			name,
+			value,
+		}
+	);
+}
+
+/// Called to add a polyline. The values are the (absolute) x and y coordinates of each point, one after another.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_polyline(file_url : String, name : String, type_ : String, values : Vec<f32>) {
+	let mut points : Vec<Vec2> = Vec::new();
+	for index in (0..values.len()).step_by(2) {
+		points.push(Vec2::new(values[index], values[index+1]));
+	}
+	get_tiled_generator().borrow_file(&file_url).polylines.push(
+		TiledPolyline{
+			points,
+			name,
+			r#type : type_,
+			properties : Vec::new(),
+		}
+	);
+}
+
+/// Called to add a custom property to the latest polyline that was added.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_polyline_property(file_url : String, name : String, value : String) {
+	get_tiled_generator().borrow_file(&file_url).polylines.last_mut().unwrap().properties.push(
+		TiledStringProp{
+			name,
+			value,
+		}
+	);
+}
+
+/// Called to add a region (a rectangle object). The corners are in Tiled's coordinates.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_add_region(file_url : String, name : String, type_ : String, x1 : f32, y1 : f32, x2 : f32, y2 : f32) {
+	get_tiled_generator().borrow_file(&file_url).regions.push(
+		TiledRegion{
+			bounds : Bounds2::from_points(
+				&Vec2::new(x1, y1),
+				&Vec2::new(x2, y2),
+			),
+			name,
+			r#type : type_,
 		}
 	);
 }
@@ -440,6 +1069,35 @@ pub fn tiled_generate_add_tile_layer(file_url : String, name : String, x_offset
 	});
 }
 
+/// Adds everything in a packed map buffer (see `TiledFile::read_packed()`) and then signals that loading is done. Much faster than calling all the functions above for every item.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_from_buffer(file_url : String, bytes : Vec<u8>) {
+	let result = get_tiled_generator().borrow_file(&file_url).read_packed(&bytes);
+	if let Err(message) = result {
+		log(&format!("Failed reading packed map {:?}: {}", file_url, message));
+	}
+	get_tiled_generator().conclude(&file_url);
+}
+
+/// Parses the text of Tiled's JSON map export (see `TiledFile::read_json()`) and then signals that loading is done. Avoids nearly all the back and forth with JavaScript.
+///
+/// This should only be called by external JavaScript code!
+#[wasm_bindgen]
+pub fn tiled_generate_from_json(file_url : String, text : String) {
+	let result = get_tiled_generator().borrow_file(&file_url).read_json(&text);
+	match result {
+		Ok(warnings) => {
+			for warning in warnings {
+				log(&format!("{} in file {}", warning, file_url));
+			}
+		},
+		Err(message) => log(&format!("Failed reading map {:?}: {}", file_url, message)),
+	}
+	get_tiled_generator().conclude(&file_url);
+}
+
 /// Signals that loading of a Tiled file is done.
 ///
 /// This should only be called by external JavaScript code!
@@ -447,3 +1105,294 @@ pub fn tiled_generate_add_tile_layer(file_url : String, name : String, x_offset
 pub fn tiled_generation_done(url : &str) {
 	get_tiled_generator().conclude(url);
 }
+
+/// Builds a TiledFile entirely in Rust, so the code that loads maps can be tested with small made-up ones.
+///
+/// Works like the generator functions above: tile properties and shapes go on the latest tile, point properties on the latest point, and so on. Tile zero starts out as Tiled's empty tile, so added tiles count up from one. Tile shapes are in the tile's own (already flipped) coordinates, while everything else is in Tiled's coordinates until `build()` flips it.
+#[cfg(test)]
+pub struct TiledFileBuilder {
+	/// The file being built.
+	file : TiledFile,
+}
+
+#[cfg(test)]
+impl TiledFileBuilder {
+	/// Creates an instance with nothing but the empty tile.
+	pub fn new(url : &str) -> TiledFileBuilder {
+		let mut file = TiledFile::new();
+		file.url = url.to_string();
+		TiledFileBuilder { file }.tile("", 0.0, 0.0, 0.0, 0.0)
+	}
+
+	/// Adds a tile (with the next ID).
+	pub fn tile(mut self, image_url : &str, x : f32, y : f32, width : f32, height : f32) -> TiledFileBuilder {
+		self.file.tiles.push(TiledTile {
+			image_url : image_url.to_string(),
+			position : Vec2::new(x, y),
+			size : Vec2::new(width, height),
+			boolean_properties : Vec::new(),
+			string_properties : Vec::new(),
+			collision_rects : Vec::new(),
+			collision_polygons : Vec::new(),
+		});
+		self
+	}
+
+	/// Adds a boolean property to the latest tile.
+	pub fn tile_bool(mut self, name : &str, value : bool) -> TiledFileBuilder {
+		self.file.tiles.last_mut().unwrap().boolean_properties.push(TiledBoolProp { name : name.to_string(), value });
+		self
+	}
+
+	/// Adds a string property to the latest tile.
+	pub fn tile_string(mut self, name : &str, value : &str) -> TiledFileBuilder {
+		self.file.tiles.last_mut().unwrap().string_properties.push(TiledStringProp { name : name.to_string(), value : value.to_string() });
+		self
+	}
+
+	/// Adds a collision rectangle to the latest tile.
+	pub fn tile_rect(mut self, type_ : &str, x1 : f32, y1 : f32, x2 : f32, y2 : f32) -> TiledFileBuilder {
+		self.file.tiles.last_mut().unwrap().collision_rects.push(TiledRect {
+			r#type : type_.to_string(),
+			position : Bounds2::from_points(&Vec2::new(x1, y1), &Vec2::new(x2, y2)),
+		});
+		self
+	}
+
+	/// Adds a collision polygon to the latest tile.
+	pub fn tile_polygon(mut self, type_ : &str, points : &[(f32, f32)]) -> TiledFileBuilder {
+		self.file.tiles.last_mut().unwrap().collision_polygons.push(TiledPolygon {
+			r#type : type_.to_string(),
+			points : points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect(),
+		});
+		self
+	}
+
+	/// Adds a tile layer (at no offset), given its tile IDs row by row from the top.
+	pub fn layer(mut self, name : &str, tile_width : f32, tile_height : f32, rows : &[&[TiledTileId]]) -> TiledFileBuilder {
+		let height = rows.len();
+		let width = rows.first().map_or(0, |row| row.len());
+		assert!(rows.iter().all(|row| width == row.len()), "Every row of layer {:?} must be the same length", name);
+		self.file.tile_layers.push(TiledTileLayer {
+			name : name.to_string(),
+			offset : Vec2::new(0.0, 0.0),
+			width, height,
+			size : Vec2::new(tile_width * width as f32, tile_height * height as f32),
+			tile_data : rows.concat(),
+		});
+		self
+	}
+
+	/// Adds a point of interest.
+	pub fn point(mut self, name : &str, type_ : &str, x : f32, y : f32) -> TiledFileBuilder {
+		self.file.points.push(TiledPoint {
+			position : Vec2::new(x, y),
+			name : name.to_string(),
+			r#type : type_.to_string(),
+			properties : Vec::new(),
+		});
+		self
+	}
+
+	/// Adds a custom property to the latest point.
+	pub fn point_property(mut self, name : &str, value : &str) -> TiledFileBuilder {
+		self.file.points.last_mut().unwrap().properties.push(TiledStringProp { name : name.to_string(), value : value.to_string() });
+		self
+	}
+
+	/// Adds a polyline.
+	pub fn polyline(mut self, name : &str, type_ : &str, points : &[(f32, f32)]) -> TiledFileBuilder {
+		self.file.polylines.push(TiledPolyline {
+			points : points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect(),
+			name : name.to_string(),
+			r#type : type_.to_string(),
+			properties : Vec::new(),
+		});
+		self
+	}
+
+	/// Adds a region.
+	pub fn region(mut self, name : &str, type_ : &str, x1 : f32, y1 : f32, x2 : f32, y2 : f32) -> TiledFileBuilder {
+		self.file.regions.push(TiledRegion {
+			bounds : Bounds2::from_points(&Vec2::new(x1, y1), &Vec2::new(x2, y2)),
+			name : name.to_string(),
+			r#type : type_.to_string(),
+		});
+		self
+	}
+
+	/// Adds one of the map's own properties.
+	pub fn property(mut self, name : &str, value : &str) -> TiledFileBuilder {
+		self.file.add_property(TiledStringProp { name : name.to_string(), value : value.to_string() });
+		self
+	}
+
+	/// Finishes the file, flipping it just like a finished load does.
+	pub fn build(mut self) -> TiledFile {
+		self.file.flip_y();
+		self.file
+	}
+}
+
+#[cfg(test)]
+mod tests_tiled {
+	use super::*;
+
+	/// Appends a length-prefixed string.
+	fn push_string(bytes : &mut Vec<u8>, text : &str) {
+		bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+		bytes.extend_from_slice(text.as_bytes());
+	}
+
+	/// Appends a list of u32 values, prefixed by how many there are.
+	fn push_u32s(bytes : &mut Vec<u8>, values : &[u32]) {
+		bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+		for value in values {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+	}
+
+	#[test]
+	fn packed_map() {
+		let mut bytes = PACKED_MAP_MAGIC.to_vec();
+		bytes.extend_from_slice(&PACKED_MAP_VERSION.to_le_bytes());
+		// One tile with a boolean property and a collision rectangle.
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "tiles.png");
+		for value in &[16u16, 0, 8, 8] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "solid");
+		bytes.push(1);
+		bytes.extend_from_slice(&0u32.to_le_bytes());
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "wall");
+		for value in &[0.0f32, 8.0, 8.0, 0.0] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		bytes.extend_from_slice(&0u32.to_le_bytes());
+		// A 2x1 layer.
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "ground");
+		bytes.extend_from_slice(&0f32.to_le_bytes());
+		bytes.extend_from_slice(&0f32.to_le_bytes());
+		for value in &[2u32, 1, 16, 8] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		push_u32s(&mut bytes, &[0, 0]);
+		// One point with a property, and a polyline.
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "spawn");
+		push_string(&mut bytes, "start");
+		bytes.extend_from_slice(&4f32.to_le_bytes());
+		bytes.extend_from_slice(&2f32.to_le_bytes());
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "facing");
+		push_string(&mut bytes, "left");
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "path");
+		push_string(&mut bytes, "");
+		push_u32s(&mut bytes, &[1f32.to_bits(), 2f32.to_bits(), 3f32.to_bits(), 4f32.to_bits()]);
+		bytes.extend_from_slice(&0u32.to_le_bytes());
+		// No regions, and one image property.
+		bytes.extend_from_slice(&0u32.to_le_bytes());
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "tiles.png");
+		push_string(&mut bytes, "colorKey");
+		push_string(&mut bytes, "#ff00ff");
+		// And a map property.
+		bytes.extend_from_slice(&1u32.to_le_bytes());
+		push_string(&mut bytes, "music");
+		push_string(&mut bytes, "cave.ogg");
+
+		let mut file = TiledFile::new();
+		assert_eq!(file.read_packed(&bytes), Ok(()));
+		assert_eq!(file.tile_count(), 1);
+		assert_eq!(file.get_tile(0).get_image_url(), "tiles.png");
+		assert_eq!(file.get_tile(0).get_position().x, 16.0);
+		assert!(file.get_tile(0).get_boolean_properties()[0].value);
+		assert_eq!(file.get_tile(0).get_collision_rectangles()[0].r#type, "wall");
+		assert_eq!(file.get_tile_layers()[0].get_width(), 2);
+		assert_eq!(file.get_points()[0].get_property("facing"), Some("left"));
+		assert_eq!(file.get_polylines()[0].points[1].y, 4.0);
+		assert_eq!(file.get_image_property("tiles.png", "colorKey"), Some("#ff00ff"));
+		assert_eq!(file.get_property("music"), Some("cave.ogg"));
+
+		// Broken buffers change nothing.
+		let mut file = TiledFile::new();
+		assert!(file.read_packed(&bytes[..bytes.len() - 1]).is_err());
+		assert!(file.read_packed(b"nope").is_err());
+		bytes.push(0);
+		assert!(file.read_packed(&bytes).is_err());
+		assert_eq!(file.tile_count(), 0);
+	}
+
+	#[test]
+	fn json_map() {
+		let mut file = TiledFile::new();
+		let warnings = file.read_json(include_str!("../../site/room.json")).unwrap();
+		assert!(warnings.is_empty(), "{:?}", warnings);
+		file.flip_y();
+		assert_eq!(file.tile_count(), 49); // Up to the largest ID that is described or used (plus the empty tile zero).
+		assert_eq!(file.get_tile_layers().len(), 2);
+		let layer = &file.get_tile_layers()[0];
+		assert_eq!(layer.get_name(), "base");
+		assert_eq!((layer.get_width(), layer.get_height()), (50, 60));
+		assert_eq!(layer.get_size().x, 800.0);
+		let tile = file.get_tile(2);
+		assert_eq!(tile.get_image_url(), "roomTiles.png");
+		assert_eq!(tile.get_size().x, 16.0);
+		assert_eq!((tile.get_position().x, tile.get_position().y), (16.0, 112.0));
+		assert_eq!(tile.get_collision_rectangles()[0].r#type, "collision");
+		assert_eq!(file.get_points()[0].name, "playerStart");
+		assert_eq!(file.get_image_property("roomTiles.png", "colorKey"), None);
+
+		// Tileset properties apply to the image, with Tiled's transparent color as a fallback color key.
+		let mut file = TiledFile::new();
+		file.read_json("{\"tilesets\": [{\"firstgid\": 1, \"image\": \"a.png\", \"tilewidth\": 8, \"tileheight\": 8, \"columns\": 1, \"tilecount\": 1, \"tiles\": [], \"transparentcolor\": \"#ff00ff\", \"properties\": [{\"name\": \"premultiplyAlpha\", \"type\": \"bool\", \"value\": true}]}]}").unwrap();
+		assert_eq!(file.get_image_property("a.png", "colorKey"), Some("#ff00ff"));
+		assert_eq!(file.get_image_property("a.png", "premultiplyAlpha"), Some("true"));
+
+		// And the map's own properties are kept.
+		let mut file = TiledFile::new();
+		file.read_json("{\"properties\": [{\"name\": \"music\", \"type\": \"file\", \"value\": \"cave.ogg\"}, {\"name\": \"tempo\", \"type\": \"int\", \"value\": 120}]}").unwrap();
+		assert_eq!(file.get_property("music"), Some("cave.ogg"));
+		assert_eq!(file.get_property("tempo"), Some("120"));
+		assert_eq!(file.get_property("missing"), None);
+
+		// Broken maps change nothing.
+		let mut file = TiledFile::new();
+		assert!(file.read_json("{\"layers\": 5}").is_err());
+		assert_eq!(file.tile_count(), 0);
+		let warnings = file.read_json("{\"layers\": [{\"type\": \"imagelayer\"}, {\"type\": \"objectgroup\", \"objects\": [{\"name\": \"a\", \"x\": 1, \"y\": 2, \"ellipse\": true, \"width\": 3, \"height\": 3, \"properties\": [{\"name\": \"n\", \"type\": \"int\", \"value\": 3}]}]}]}").unwrap();
+		assert_eq!(warnings.len(), 2);
+	}
+
+	#[test]
+	fn builder() {
+		let file = TiledFileBuilder::new("test.json")
+			.tile("tiles.png", 16.0, 0.0, 8.0, 8.0).tile_bool("solid", true).tile_rect("collision", 0.0, 0.0, 8.0, 4.0)
+			.layer("ground", 8.0, 8.0, &[&[1, 0], &[1, 1]])
+			.point("spawn", "start", 4.0, 2.0).point_property("facing", "left")
+			.polyline("path", "zipline", &[(0.0, 20.0), (8.0, 10.0)])
+			.region("exit", "door", 0.0, 0.0, 4.0, 4.0)
+			.property("music", "cave.ogg")
+			.build();
+		assert_eq!(file.get_url(), "test.json");
+		assert_eq!(file.tile_count(), 2); // The empty tile, then the one added.
+		assert_eq!(file.get_tile(1).get_collision_rectangles()[0].position.y_max(), 4.0); // Tile shapes are left alone.
+		let layer = &file.get_tile_layers()[0];
+		assert_eq!((layer.get_width(), layer.get_height()), (2, 2));
+		assert_eq!(layer.get_tile_id(1, 0), 0);
+		assert_eq!(layer.get_size().y, 16.0);
+		assert_eq!(file.get_property("music"), Some("cave.ogg"));
+		assert_eq!(file.get_points()[0].get_property("facing"), Some("left"));
+
+		// Everything else is flipped, using the highest thing in the file (the polyline here).
+		assert_eq!(file.get_points()[0].position.y, 18.0);
+		assert_eq!(file.get_polylines()[0].points[0].y, 0.0);
+		assert_eq!(layer.get_offset().y, 4.0);
+		assert_eq!((file.get_regions()[0].bounds.y_min(), file.get_regions()[0].bounds.y_max()), (16.0, 20.0));
+	}
+}
@@ -1,16 +1,66 @@
 use std::collections::HashMap;
 
+use crate::geo::vec2::*;
 use crate::geo::vec3::*;
+use crate::geo::mat4::*;
 use crate::display_buffer::*;
 use crate::display_texture::*;
 use crate::tiled::*;
+use crate::tween::*;
+use crate::secrets::Secrets;
+use crate::geo::bounds2::Bounds2;
+use crate::tile_chunks::*;
+use crate::externals::log;
+use crate::color::Color;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How long it takes the tiles covering a secret to fade out once it's found (in seconds).
+const SECRET_REVEAL_SECONDS : f32 = 0.75;
+/// How many chunks (across all layers) can be built early each update, ahead of where the view's heading. Kept low so prefetching never causes the hitch it's meant to avoid.
+const PREFETCH_CHUNKS_PER_UPDATE : usize = 1;
+
+/// The tiles covering a single secret area, kept in their own buffer so they can be faded out together.
+struct SecretCover {
+	/// The index of the secret (in Secrets).
+	secret : usize,
+	/// The covering tiles.
+	buffer : DisplayBuffer,
+	/// The covering tiles that haven't been put in the buffer yet (they're all added at once, at the end of loading).
+	pending : Vec<ImageQuad>,
+	/// How opaque the covering tiles are (None until the secret is found).
+	fade : Option<Tween>,
+}
+
+/// A tile layer, split into chunks that only get a buffer while they're near the view.
+///
+/// Building one buffer per layer means uploading the whole layer at once (and drawing all of it all the time), which doesn't scale to big maps.
+struct ChunkedLayer {
+	/// Tracks which chunks should be built.
+	streamer : ChunkStreamer,
+	/// The tiles in each chunk (as they're drawn).
+	tiles : Vec<Vec<ImageQuad>>,
+	/// The built chunks, keyed by chunk index.
+	buffers : HashMap<usize, DisplayBuffer>,
+	/// The URL of the texture that the layer's tiles come from.
+	texture_url : String,
+}
 
 /// A way to display a TiledFile using DisplayBuffers and DisplayTextures.
 pub struct TiledDisplay {
 	/// A mapping from display texture URLs to the DisplayTexture objects.
 	textures : HashMap<String, DisplayTexture>,
-	/// The display buffers in display order (back to front).
-	buffers : Vec<DisplayBuffer>
+	/// The tile layers in display order (back to front).
+	layers : Vec<ChunkedLayer>,
+	/// Tiles that can change at runtime (i.e. ones with a `behavior` property), each in their own buffer. Keyed by (layer index, x, y).
+	detached : HashMap<(usize, usize, usize), DisplayBuffer>,
+	/// The tiles covering secret areas.
+	covers : Vec<SecretCover>,
+	/// The color all tiles are blended toward, and how much.
+	tint : (Color, f32),
+	/// How far everything has been moved since it was loaded (see `origin_rebase`). The detached tiles' transforms need it, since they're reset whenever a tile moves.
+	origin_shift : Vec2,
+	/// Guesses where the view's heading, so chunks there can be built before they're needed.
+	predictor : ViewPredictor,
 }
 
 impl TiledDisplay {
@@ -18,58 +68,253 @@ impl TiledDisplay {
 	pub fn new() -> TiledDisplay {
 		TiledDisplay {
 			textures : HashMap::new(),
-			buffers : Vec::new(),
+			layers : Vec::new(),
+			detached : HashMap::new(),
+			covers : Vec::new(),
+			tint : (Color::new(0, 0, 0, 255), 0.0),
+			origin_shift : Vec2::zero(),
+			predictor : ViewPredictor::new(),
+		}
+	}
+
+	/// Sets the color all tiles are blended toward, and how much (from 0 to 1). Chunks built later get it too.
+	pub fn set_tint(&mut self, color : &Color, amount : f32) {
+		self.tint = (color.clone(), amount);
+		let buffers = self.layers.iter_mut().flat_map(|layer| layer.buffers.values_mut())
+			.chain(self.detached.values_mut())
+			.chain(self.covers.iter_mut().map(|cover| &mut cover.buffer));
+		for buffer in buffers {
+			buffer.set_tint(color, amount);
 		}
 	}
 
 	/// Loads in all data from a TiledFile instance.
-	pub fn load_from(&mut self, file : &TiledFile) {
+	/// Tiles in a layer that covers a secret get grouped into their own buffer (per secret) so they can be revealed later.
+	/// Everything else is split into chunks, which aren't built until `update_view()` brings them near the view.
+	pub fn load_from(&mut self, file : &TiledFile, secrets : &Secrets) {
 		self.textures.clear();
-		self.buffers.clear();
+		self.layers.clear();
+		self.detached.clear();
+		self.covers.clear();
+		self.origin_shift = Vec2::zero();
+		self.predictor.reset();
 		for tile in file.get_tiles() {
 			let url = tile.get_image_url();
 			if 0 == url.len() { continue; }
 			if !self.textures.contains_key(url) {
+				let options = match TextureOptions::from_properties(|name| file.get_image_property(url, name)) {
+					Ok(options) => options,
+					Err(error) => {
+						log(&format!("Ignoring the texture options for {:?} in map {:?} due to: {}", url, file.get_url(), error));
+						TextureOptions::new()
+					},
+				};
 				let mut texture = DisplayTexture::new();
-				texture.load_from_url(url);
+				texture.load_with_options(url, &options);
 				self.textures.insert(url.to_string(), texture);
 			}
 		}
 
 		for (layer_index, layer) in file.get_tile_layers().iter().enumerate() {
-			let mut buffer = DisplayBuffer::new(DisplayBufferType::IMAGES);
 			let mut tile_url = String::new();
-			{
-				let mut editor = buffer.make_editor();
-				let width = layer.get_width();
-				let height = layer.get_height();
-				let offset = layer.get_offset();
-				let depth = 1.0 - (layer_index as f32) / 100.0;
-				let mut tile_space = layer.get_size(); // How much space to give the tile. It may not use it all.
-				tile_space.x /= width as f32;
-				tile_space.y /= height as f32;
-				for y in 0..height {
-					for x in 0..width {
-						let tile = file.get_tile(layer.get_tile_id(x, y));
-						let current_url = tile.get_image_url();
-						if 0 == tile_url.len() && 0 < current_url.len() {
-							tile_url = current_url.to_string();
-						}
-						let position = Vec3::new(
-							offset.x + (x as f32) * tile_space.x,
-							offset.y + ((height - y - 1) as f32) * tile_space.y,
-							depth,
-						);
-						editor.add_image(
+			let mut layer_covers : Vec<SecretCover> = Vec::new();
+			for (secret, region) in secrets.get_regions().iter().enumerate() {
+				if layer.get_name() == region.layer_name {
+					layer_covers.push(SecretCover {
+						secret,
+						buffer : DisplayBuffer::new(DisplayBufferType::IMAGES),
+						pending : Vec::new(),
+						fade : None,
+					});
+				}
+			}
+			let width = layer.get_width();
+			let height = layer.get_height();
+			let offset = layer.get_offset();
+			let depth = 1.0 - (layer_index as f32) / 100.0;
+			let mut tile_space = layer.get_size(); // How much space to give the tile. It may not use it all.
+			tile_space.x /= width as f32;
+			tile_space.y /= height as f32;
+			let layout = ChunkLayout::new(&offset, &tile_space, width, height, CHUNK_TILES);
+			let mut chunk_tiles : Vec<Vec<ImageQuad>> = (0..layout.chunk_count()).map(|_| Vec::new()).collect();
+			for y in 0..height {
+				for x in 0..width {
+					let tile = file.get_tile(layer.get_tile_id(x, y));
+					let current_url = tile.get_image_url();
+					if 0 == tile_url.len() && 0 < current_url.len() {
+						tile_url = current_url.to_string();
+					}
+					let position = Vec3::new(
+						offset.x + (x as f32) * tile_space.x,
+						offset.y + ((height - y - 1) as f32) * tile_space.y,
+						depth,
+					);
+					if tile.get_string_property("behavior").is_some() && 0 < current_url.len() {
+						let mut tile_buffer = DisplayBuffer::new(DisplayBufferType::IMAGES);
+						tile_buffer.make_editor().add_image(
 							&tile.get_position(),
 							&tile.get_size(),
 							&position,
 						);
+						tile_buffer.set_texture(self.textures.get(current_url).unwrap());
+						tile_buffer.set_tint(&self.tint.0, self.tint.1);
+						tile_buffer.freeze(); // Only ever moved around, shown, or hidden.
+						self.detached.insert((layer_index, x, y), tile_buffer);
+						continue;
+					}
+					let center = Vec2::new(position.x + tile_space.x / 2.0, position.y + tile_space.y / 2.0);
+					if let Some(cover) = layer_covers.iter_mut().find(|cover| secrets.get_regions()[cover.secret].bounds.overlaps_point(&center)) {
+						cover.pending.push(ImageQuad {
+							source_position : tile.get_position(),
+							size : tile.get_size(),
+							destination : position,
+						});
+						continue;
 					}
+					chunk_tiles[layout.chunk_of(x, y)].push(ImageQuad {
+						source_position : tile.get_position(),
+						size : tile.get_size(),
+						destination : position,
+					});
+				}
+			}
+			self.layers.push(ChunkedLayer {
+				streamer : ChunkStreamer::new(layout),
+				tiles : chunk_tiles,
+				buffers : HashMap::new(),
+				texture_url : tile_url.clone(),
+			});
+			for mut cover in layer_covers {
+				cover.buffer.make_editor().add_images(&cover.pending);
+				cover.pending = Vec::new();
+				cover.buffer.set_texture(self.textures.get(&tile_url).unwrap());
+				cover.buffer.set_tint(&self.tint.0, self.tint.1);
+				cover.buffer.freeze();
+				self.covers.push(cover);
+			}
+		}
+	}
+
+	/// Builds the chunks that are coming into view, and drops the ones that are far away.
+	///
+	/// Also builds a few chunks early where the view's heading (going by how it's been moving over the last `elapsed_seconds`), so crossing into a new area doesn't have to build several at once.
+	pub fn update_view(&mut self, view : &Bounds2, elapsed_seconds : f32) {
+		let ahead = self.predictor.update(view, elapsed_seconds);
+		let mut budget = PREFETCH_CHUNKS_PER_UPDATE;
+		for layer in &mut self.layers {
+			let changes = layer.streamer.update_ahead(view, &ahead, budget);
+			budget -= changes.prefetch.len();
+			for index in changes.unload {
+				layer.buffers.remove(&index);
+			}
+			for index in changes.load.into_iter().chain(changes.prefetch) {
+				if let Some(buffer) = build_chunk(layer, index, &self.textures, &self.tint) {
+					layer.buffers.insert(index, buffer);
+				}
+			}
+		}
+	}
+
+	/// Gets how many chunk buffers are currently built (across all layers).
+	pub fn built_chunk_count(&self) -> usize {
+		self.layers.iter().map(|layer| layer.buffers.len()).sum()
+	}
+
+	/// Starts fading out the tiles covering a secret.
+	pub fn reveal_secret(&mut self, secret : usize) {
+		for cover in &mut self.covers {
+			if secret == cover.secret && cover.fade.is_none() {
+				cover.fade = Some(Tween::new(1.0, 0.0, SECRET_REVEAL_SECONDS, Easing::QuadOut));
+			}
+		}
+	}
+
+	/// Moves any fading secret covers forward in time.
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		for cover in &mut self.covers {
+			if let Some(fade) = &mut cover.fade {
+				if fade.is_done() {
+					continue;
 				}
+				let alpha = fade.update(elapsed_seconds);
+				cover.buffer.set_alpha(alpha);
+				if fade.is_done() {
+					cover.buffer.hide();
+				}
+			}
+		}
+	}
+
+	/// Shows or hides a tile that has a behavior. Does nothing for any other tile.
+	pub fn set_tile_visible(&mut self, layer_index : usize, x : usize, y : usize, visible : bool) {
+		if let Some(buffer) = self.detached.get_mut(&(layer_index, x, y)) {
+			if visible {
+				buffer.show();
+			} else {
+				buffer.hide();
 			}
-			buffer.set_texture(self.textures.get(&tile_url).unwrap());
-			self.buffers.push(buffer);
+		}
+	}
+
+	/// Moves where a tile that has a behavior is drawn. Does nothing for any other tile.
+	pub fn set_tile_offset(&mut self, layer_index : usize, x : usize, y : usize, offset : &Vec2) {
+		if let Some(buffer) = self.detached.get_mut(&(layer_index, x, y)) {
+			let mut transform = Mat4::new();
+			transform.translate_before(&Vec3::new(self.origin_shift.x + offset.x, self.origin_shift.y + offset.y, 0.0));
+			buffer.set_transform(&transform);
+		}
+	}
+}
+
+/// Builds the buffer for a chunk of a layer. None if it has no tiles.
+fn build_chunk(layer : &ChunkedLayer, index : usize, textures : &HashMap<String, DisplayTexture>, tint : &(Color, f32)) -> Option<DisplayBuffer> {
+	let tiles = &layer.tiles[index];
+	if tiles.is_empty() {
+		return None;
+	}
+	// Tiles are stored relative to their chunk's corner, and moved into place by the transform (so they keep their precision when rendering relative to the camera).
+	let bounds = layer.streamer.get_layout().chunk_bounds(index);
+	let corner = Vec3::new(bounds.x_min(), bounds.y_min(), 0.0);
+	let mut buffer = DisplayBuffer::with_capacity(DisplayBufferType::IMAGES, tiles.len());
+	{
+		let mut editor = buffer.make_editor();
+		for tile in tiles {
+			editor.add_image(&tile.source_position, &tile.size, &(&tile.destination - &corner));
+		}
+	}
+	let mut transform = Mat4::new();
+	transform.translate_before(&corner);
+	buffer.set_transform(&transform);
+	if let Some(texture) = textures.get(&layer.texture_url) {
+		buffer.set_texture(texture);
+	}
+	buffer.set_tint(&tint.0, tint.1);
+	buffer.freeze();
+	Some(buffer)
+}
+
+impl ShiftOrigin for TiledDisplay {
+	/// Built chunks and the other buffers are just moved, so nothing needs to be rebuilt.
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.origin_shift.shift_origin(shift);
+		self.predictor.shift_origin(shift);
+		for layer in &mut self.layers {
+			layer.streamer.shift_origin(shift);
+			for tiles in &mut layer.tiles {
+				for tile in tiles {
+					tile.destination.x += shift.x;
+					tile.destination.y += shift.y;
+				}
+			}
+			for buffer in layer.buffers.values_mut() {
+				buffer.shift_origin(shift);
+			}
+		}
+		for buffer in self.detached.values_mut() {
+			buffer.shift_origin(shift);
+		}
+		for cover in &mut self.covers {
+			cover.buffer.shift_origin(shift);
 		}
 	}
 }
@@ -5,12 +5,18 @@ use crate::externals::log;
 
 use crate::geo::bounds2::Bounds2;
 use crate::geo::vec2::Vec2;
-use crate::geo::vec3::Vec3;
-use crate::color::Color;
+#[cfg(not(feature = "release-lean"))]
+use crate::geo::vec3::Vec3; // Only for the debug drawing.
+use crate::palette::*;
 
 use crate::tiled::{TiledFile, TiledTileLayer};
+use crate::tile_shapes::{TileShapeLibrary, TileShapeInstance};
+use crate::zipline::Zipline;
 
-use crate::display_buffer::{DisplayBuffer, DisplayBufferType};
+use crate::display_buffer::DisplayBuffer;
+#[cfg(not(feature = "release-lean"))]
+use crate::display_buffer::DisplayBufferType; // Only for the debug drawing.
+use crate::origin_rebase::ShiftOrigin;
 
 /// A way to store a pneumatic pipe between two locations.
 pub struct PneumaticPipe {
@@ -29,6 +35,34 @@ impl PneumaticPipe {
 	}
 }
 
+impl ShiftOrigin for PneumaticPipe {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.start_collision.shift_origin(shift);
+		self.end_collision.shift_origin(shift);
+		self.path.shift_origin(shift);
+	}
+}
+
+/// A single tile with a `behavior` property.
+///
+/// Its collision geometry is kept separate (rather than merged with its neighbors) so it can be changed on its own.
+pub struct BehaviorTile {
+	/// The name of the behavior (i.e. the `behavior` property's value).
+	pub behavior : String,
+	/// The index of the layer the tile is in.
+	pub layer_index : usize,
+	/// The x position in the layer (in tiles).
+	pub x : usize,
+	/// The y position in the layer (in tiles).
+	pub y : usize,
+	/// The area the tile covers.
+	pub bounds : Bounds2,
+	/// The rectangles to collide with.
+	pub collision_rects : Vec<Bounds2>,
+	/// The polygons to collide with.
+	pub collision_polygons : Vec<Vec<Vec2>>,
+}
+
 // A way to store directions in a single u8.
 const DIR_UP    : u8 = 0b0001;
 const DIR_LEFT  : u8 = 0b0010;
@@ -39,37 +73,115 @@ const DIR_RIGHT : u8 = 0b1000;
 pub struct TiledGeometry {
 	/// The rectangles that represent "tracks".
 	tracks : Vec<Bounds2>,
+	/// The parts of the tracks that change how fast the player moves along them (with the speed multiplier).
+	track_speeds : Vec<(Bounds2, f32)>,
 	/// The rectangles to collide with.
 	collision_rects : Vec<Bounds2>,
-	/// The polygons to collide with.
-	collision_polygons : Vec<Vec<Vec2>>,
+	/// The tiles with polygons to collide with (the polygons themselves are in `tile_shapes`).
+	collision_polygons : Vec<TileShapeInstance>,
+	/// The collision polygons for each tile (shared by every placed copy of that tile).
+	tile_shapes : TileShapeLibrary,
+	/// The rectangles that are filled with water.
+	water_rects : Vec<Bounds2>,
+	/// The rectangles whose tops are one-way platforms (only solid when landed on from above).
+	platform_rects : Vec<Bounds2>,
 	/// All of the level's penumatic pipes.
 	pneumatic_pipes : Vec<PneumaticPipe>,
-	/// A debugging buffer to show all the geometry with.
-	pub debug_buffer : DisplayBuffer,
+	/// All of the level's ziplines.
+	ziplines : Vec<Zipline>,
+	/// All tiles that have a behavior attached.
+	behavior_tiles : Vec<BehaviorTile>,
+	/// A debugging buffer to show all the geometry with (only made once something is drawn in it).
+	debug_buffer : Option<DisplayBuffer>,
+	/// The colors the debugging buffer is drawn with.
+	debug_palette : Palette,
 }
 
 impl TiledGeometry {
 	pub fn new() -> TiledGeometry {
 		TiledGeometry {
 			tracks : Vec::new(),
+			track_speeds : Vec::new(),
 			collision_rects : Vec::new(),
 			collision_polygons : Vec::new(),
+			tile_shapes : TileShapeLibrary::new(),
+			water_rects : Vec::new(),
+			platform_rects : Vec::new(),
 			pneumatic_pipes : Vec::new(),
-			debug_buffer : DisplayBuffer::new(DisplayBufferType::LINES),
+			ziplines : Vec::new(),
+			behavior_tiles : Vec::new(),
+			debug_buffer : None,
+			debug_palette : Palette::new(PaletteKind::Standard),
 		}
 	}
 
+	/// Sets the colors the debugging buffer is drawn with (the next time something is loaded).
+	pub fn set_debug_palette(&mut self, palette : &Palette) {
+		self.debug_palette = palette.clone();
+	}
+
 	/// The collision rectangle geometry.
 	pub fn get_collision_rects<'a>(&'a self) -> &'a Vec<Bounds2> {
 		&self.collision_rects
 	}
 
-	/// The collision polygon geometry.
-	pub fn get_collision_polygons<'a>(&'a self) -> &'a Vec<Vec<Vec2>> {
+	/// The collision polygon geometry (resolved to world-space as it's iterated over).
+	pub fn get_collision_polygons<'a>(&'a self) -> impl Iterator<Item = Vec<Vec2>> + 'a {
+		let library = &self.tile_shapes;
+		self.collision_polygons.iter().flat_map(move |instance| instance.resolve(library))
+	}
+
+	/// The placed tiles that have collision polygons.
+	pub fn get_collision_polygon_instances<'a>(&'a self) -> &'a Vec<TileShapeInstance> {
 		&self.collision_polygons
 	}
 
+	/// The shared collision polygons for each tile.
+	pub fn get_tile_shapes<'a>(&'a self) -> &'a TileShapeLibrary {
+		&self.tile_shapes
+	}
+
+	/// Replaces the shared collision polygons with the ones from another file's tiles (as in when hot-swapping a tileset). Every placed tile picks up the new shapes.
+	///
+	/// Anything built from the old polygons (like collision obstacles) needs to be rebuilt after this.
+	pub fn swap_tile_shapes(&mut self, file : &TiledFile) {
+		self.tile_shapes.load_from(file);
+	}
+
+	/// The areas filled with water.
+	pub fn get_water_rects<'a>(&'a self) -> &'a Vec<Bounds2> {
+		&self.water_rects
+	}
+
+	/// The one-way platforms (only their tops are solid, and only from above).
+	pub fn get_platform_rects<'a>(&'a self) -> &'a Vec<Bounds2> {
+		&self.platform_rects
+	}
+
+	/// The tiles with behaviors attached.
+	pub fn get_behavior_tiles<'a>(&'a self) -> &'a Vec<BehaviorTile> {
+		&self.behavior_tiles
+	}
+
+	/// The ziplines.
+	pub fn get_ziplines<'a>(&'a self) -> &'a Vec<Zipline> {
+		&self.ziplines
+	}
+
+	/// Finds the closest zipline within some distance of a position (if any).
+	pub fn find_closest_zipline(&self, position : &Vec2, max_distance : f32) -> Option<usize> {
+		let mut closest = None;
+		let mut closest_distance = max_distance;
+		for (index, zipline) in self.ziplines.iter().enumerate() {
+			let distance = zipline.distance_to(position);
+			if distance <= closest_distance {
+				closest = Some(index);
+				closest_distance = distance;
+			}
+		}
+		closest
+	}
+
 	/// Finds the closest point inside the tracts.
 	pub fn get_closest_track_point(&self, position : &Vec2) -> Vec2 {
 		let mut closest = Vec2::new(0.0, 0.0);
@@ -88,6 +200,18 @@ impl TiledGeometry {
 		closest
 	}
 
+	/// Gets how much faster (or slower) the player should move along the track at a given position.
+	/// Speed boost sections come from the `trackSpeed` property on track tiles. Everywhere else is 1.0.
+	pub fn get_track_speed_multiplier(&self, position : &Vec2) -> f32 {
+		let mut multiplier = 1.0;
+		for (rect, speed) in &self.track_speeds {
+			if rect.overlaps_point(position) {
+				multiplier = *speed;
+			}
+		}
+		multiplier
+	}
+
 	/// Finds the closest point on a track that intersects with a given moving point.
 	pub fn collide_moving_point_with_track(&self, position : &Vec2, movement : &Vec2) -> Option<Vec2> {
 		let end = position + movement;
@@ -242,27 +366,59 @@ impl TiledGeometry {
 
 	/// Loads in all data from a TiledFile instance.
 	pub fn load_from(&mut self, file : &TiledFile) {
+		self.tile_shapes.load_from(file);
 		// First pass: Extract all collision information from the map.
-		for layer in file.get_tile_layers() {
+		for (layer_index, layer) in file.get_tile_layers().iter().enumerate() {
 			let layer_width = layer.get_width();
 			let layer_height = layer.get_height();
 			let mut used_pipe_entrance_positions : Vec<usize> = Vec::new();
 			for y in 0..layer_height {
 				for x in 0..layer_width {
 					let int_position = x + y * layer_width;
-					let tile = file.get_tile(layer.get_tile_id(x, y));
+					let tile_id = layer.get_tile_id(x, y);
+					let tile = file.get_tile(tile_id);
 					let tile_offset = TiledGeometry::get_tile_offset(layer, x, y);
+					let mut behavior_tile = tile.get_string_property("behavior").map(|behavior| BehaviorTile {
+						behavior : behavior.to_string(),
+						layer_index,
+						x, y,
+						bounds : Bounds2::from_points(&tile_offset, &(tile_offset + tile.get_size())),
+						collision_rects : Vec::new(),
+						collision_polygons : Vec::new(),
+					});
 					for rect in tile.get_collision_rectangles() {
 						if "collision" == rect.r#type {
 							let mut final_copy = rect.position.clone();
 							final_copy.translate(&tile_offset);
-							self.collision_rects.push(final_copy);
+							if let Some(behavior) = &mut behavior_tile {
+								behavior.collision_rects.push(final_copy);
+							} else {
+								self.collision_rects.push(final_copy);
+							}
 						}
 						if "track" == rect.r#type {
 							let mut final_copy = rect.position.clone();
 							final_copy.translate(&tile_offset);
+							if let Some(speed) = tile.get_string_property("trackSpeed") {
+								match speed.parse::<f32>() {
+									// Anything that's not above zero would freeze (or reverse) the player, so it's left at normal speed.
+									Ok(multiplier) if 0.0 < multiplier && multiplier.is_finite() => { self.track_speeds.push((final_copy.clone(), multiplier)); },
+									Ok(_) => { log(&format!("Ignoring trackSpeed {:?} at {},{} in map {:?} as it must be above 0", speed, x, y, file.get_url())); },
+									Err(_) => { log(&format!("Couldn't parse trackSpeed {:?} at {},{} in map {:?}", speed, x, y, file.get_url())); },
+								}
+							}
 							self.tracks.push(final_copy);
 						}
+						if "water" == rect.r#type {
+							let mut final_copy = rect.position.clone();
+							final_copy.translate(&tile_offset);
+							self.water_rects.push(final_copy);
+						}
+						if "platform" == rect.r#type {
+							let mut final_copy = rect.position.clone();
+							final_copy.translate(&tile_offset);
+							self.platform_rects.push(final_copy);
+						}
 						if "pipeEnter" == rect.r#type {
 							let mut make_new = true;
 							for used in &used_pipe_entrance_positions {
@@ -279,35 +435,56 @@ impl TiledGeometry {
 							}
 						}
 					}
-					for polygon in tile.get_collision_polygons() {
-						if "collision" == polygon.r#type {
-							let mut final_copy = Vec::with_capacity(polygon.points.len());
-							for point in &polygon.points {
-								final_copy.push(point + tile_offset);
-							}
-							self.collision_polygons.push(final_copy);
-						}
+					if let Some(behavior) = &mut behavior_tile {
+						// Behavior tiles get their own copies, so they can be changed on their own.
+						let instance = TileShapeInstance { tile_id, offset : tile_offset };
+						behavior.collision_polygons.extend(instance.resolve(&self.tile_shapes));
+					} else if self.tile_shapes.has_shapes(tile_id) {
+						self.collision_polygons.push(TileShapeInstance { tile_id, offset : tile_offset });
 					}
 					for property in tile.get_boolean_properties() {
 						if "solid" == property.name && property.value {
-							self.collision_rects.push(Bounds2::from_points(
+							let solid = Bounds2::from_points(
 								&tile_offset,
 								&(tile_offset + tile.get_size()),
-							));
+							);
+							if let Some(behavior) = &mut behavior_tile {
+								behavior.collision_rects.push(solid);
+							} else {
+								self.collision_rects.push(solid);
+							}
 							break;
 						}
 					}
+					if let Some(behavior) = behavior_tile {
+						self.behavior_tiles.push(behavior);
+					}
+				}
+			}
+		}
+		for polyline in file.get_polylines() {
+			if "zipline" == polyline.r#type {
+				if polyline.points.len() < 2 {
+					log(&format!("Zipline {:?} in map {:?} needs at least two points", polyline.name, file.get_url()));
+					continue;
+				}
+				if 2 < polyline.points.len() {
+					log(&format!("Zipline {:?} in map {:?} has more than two points, so only using the first and last", polyline.name, file.get_url()));
 				}
+				self.ziplines.push(Zipline::new(&polyline.points[0], polyline.points.last().unwrap()));
 			}
 		}
 		self.collision_rects = simplify_rects(&mut self.collision_rects);
 		self.tracks = simplify_rects(&mut self.tracks);
+		self.water_rects = simplify_rects(&mut self.water_rects);
+		self.platform_rects = simplify_rects(&mut self.platform_rects);
 		// For debugging: draw all the rectangles.
+		#[cfg(not(feature = "release-lean"))]
 		if false {
-			let mut editor = self.debug_buffer.make_editor();
+			let mut editor = self.debug_buffer.get_or_insert_with(|| DisplayBuffer::new(DisplayBufferType::LINES)).make_editor();
 			editor.clear();
 			if false {
-				let color = Color::new(255, 0, 0, 255);
+				let color = self.debug_palette.get(PaletteRole::Danger);
 				let z : f32 = -0.8;
 				for rect in &self.collision_rects {
 					editor.add_polygon(
@@ -320,9 +497,10 @@ impl TiledGeometry {
 						&color,
 					);
 				}
-				for polygon in &self.collision_polygons {
+				let library = &self.tile_shapes;
+				for polygon in self.collision_polygons.iter().flat_map(|instance| instance.resolve(library)) {
 					let mut points : Vec<Vec3> = Vec::with_capacity(polygon.len());
-					for source in polygon {
+					for source in &polygon {
 						points.push(Vec3::new(source.x, source.y, z));
 					}
 					editor.add_polygon(
@@ -332,7 +510,7 @@ impl TiledGeometry {
 				}
 			}
 			{
-				let color = Color::new(255, 0, 0, 255);
+				let color = self.debug_palette.get(PaletteRole::Pipe);
 				let z : f32 = -0.75;
 				for pipe in &self.pneumatic_pipes {
 					for index in 0..(pipe.path.len()-1) {
@@ -365,7 +543,7 @@ impl TiledGeometry {
 				}
 			}
 			if false {
-				let color = Color::new(0, 255, 0, 255);
+				let color = self.debug_palette.get(PaletteRole::Track);
 				let z : f32 = -0.85;
 				for rect in &self.tracks {
 					editor.add_polygon(
@@ -383,6 +561,31 @@ impl TiledGeometry {
 	}
 }
 
+impl ShiftOrigin for BehaviorTile {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.bounds.shift_origin(shift);
+		self.collision_rects.shift_origin(shift);
+		self.collision_polygons.shift_origin(shift);
+	}
+}
+
+impl ShiftOrigin for TiledGeometry {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.tracks.shift_origin(shift);
+		for (bounds, _speed) in &mut self.track_speeds {
+			bounds.shift_origin(shift);
+		}
+		self.collision_rects.shift_origin(shift);
+		self.collision_polygons.shift_origin(shift);
+		self.water_rects.shift_origin(shift);
+		self.platform_rects.shift_origin(shift);
+		self.pneumatic_pipes.shift_origin(shift);
+		self.ziplines.shift_origin(shift);
+		self.behavior_tiles.shift_origin(shift);
+		self.debug_buffer.shift_origin(shift);
+	}
+}
+
 /// Iterates through every unique 2-pair of items, and passes them to a lambda function.
 /// The iterator returns whether the right item should be skipped by future iterations.
 /// The passed in skip_indices is updated to include any newly skipped items.
@@ -452,4 +655,106 @@ fn simplify_rects(source : &mut Vec<Bounds2>) -> Vec<Bounds2> {
 		}
 	}
 	updated
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests_tiled_geometry {
+	use super::*;
+	use crate::tiled::TiledFileBuilder;
+
+	/// Gets a rectangle's corners, so they can be compared.
+	fn corners(bounds : &Bounds2) -> (f32, f32, f32, f32) {
+		(bounds.x_min(), bounds.y_min(), bounds.x_max(), bounds.y_max())
+	}
+
+	/// Gets a list of points as pairs, so they can be compared.
+	fn pairs(points : &[Vec2]) -> Vec<(f32, f32)> {
+		points.iter().map(|point| (point.x, point.y)).collect()
+	}
+
+	#[test]
+	fn simplifying() {
+		// A 2x2 block plus one more below its right side: the columns merge first, then the block's halves.
+		let mut rects : Vec<Bounds2> = [(0.0, 16.0), (16.0, 16.0), (0.0, 0.0), (16.0, 0.0), (32.0, 0.0)].iter()
+			.map(|(x, y)| Bounds2::from_points(&Vec2::new(*x, *y), &Vec2::new(x + 16.0, y + 16.0)))
+			.collect();
+		let simplified : Vec<_> = simplify_rects(&mut rects).iter().map(corners).collect();
+		assert_eq!(simplified, vec![(0.0, 0.0, 32.0, 32.0), (32.0, 0.0, 48.0, 16.0)]);
+	}
+
+	#[test]
+	fn loading() {
+		let file = TiledFileBuilder::new("test.json")
+			.tile("tiles.png", 0.0, 0.0, 16.0, 16.0).tile_bool("solid", true)
+			.tile("tiles.png", 16.0, 0.0, 16.0, 16.0).tile_rect("water", 0.0, 0.0, 16.0, 8.0)
+			.tile("tiles.png", 32.0, 0.0, 16.0, 16.0).tile_bool("solid", true).tile_string("behavior", "crumble")
+			.tile("tiles.png", 48.0, 0.0, 16.0, 16.0).tile_rect("collision", 0.0, 0.0, 16.0, 4.0)
+			.tile("tiles.png", 64.0, 0.0, 16.0, 16.0).tile_rect("platform", 0.0, 12.0, 16.0, 16.0)
+			.layer("ground", 16.0, 16.0, &[
+				&[0, 2, 2, 5],
+				&[1, 1, 3, 4],
+			])
+			.polyline("ride", "zipline", &[(8.0, 24.0), (56.0, 8.0)])
+			.build();
+		let mut geometry = TiledGeometry::new();
+		geometry.load_from(&file);
+
+		// The bottom row is at the bottom, with the solid tiles merged together (leaving out the behavior tile and the partial one).
+		let collisions : Vec<_> = geometry.get_collision_rects().iter().map(corners).collect();
+		assert_eq!(collisions, vec![(0.0, 0.0, 32.0, 16.0), (48.0, 0.0, 64.0, 4.0)]);
+		let water : Vec<_> = geometry.get_water_rects().iter().map(corners).collect();
+		assert_eq!(water, vec![(16.0, 16.0, 48.0, 24.0)]);
+		let platforms : Vec<_> = geometry.get_platform_rects().iter().map(corners).collect();
+		assert_eq!(platforms, vec![(48.0, 28.0, 64.0, 32.0)]);
+		let behaviors = geometry.get_behavior_tiles();
+		assert_eq!(behaviors.len(), 1);
+		assert_eq!((behaviors[0].behavior.as_str(), behaviors[0].x, behaviors[0].y), ("crumble", 2, 1));
+		assert_eq!(behaviors[0].collision_rects.iter().map(corners).collect::<Vec<_>>(), vec![(32.0, 0.0, 48.0, 16.0)]);
+
+		// The zipline is flipped along with the rest of the map.
+		let zipline = &geometry.get_ziplines()[0];
+		assert_eq!((zipline.get_start().x, zipline.get_start().y), (8.0, 8.0));
+		assert_eq!((zipline.get_end().x, zipline.get_end().y), (56.0, 24.0));
+	}
+
+	#[test]
+	fn track_speeds() {
+		let mut builder = TiledFileBuilder::new("test.json");
+		for speed in ["2", "0", "-1", "fast"].iter() {
+			builder = builder.tile("tracks.png", 0.0, 0.0, 16.0, 16.0).tile_rect("track", 0.0, 0.0, 16.0, 16.0).tile_string("trackSpeed", speed);
+		}
+		let file = builder
+			.tile("tracks.png", 0.0, 0.0, 16.0, 16.0).tile_rect("track", 0.0, 0.0, 16.0, 16.0)
+			.layer("tracks", 16.0, 16.0, &[&[1, 2, 3, 4, 5]])
+			.build();
+		let mut geometry = TiledGeometry::new();
+		geometry.load_from(&file);
+
+		// Only the valid boost is used. Zero, negative, and unreadable speeds are left at normal speed.
+		let speeds : Vec<f32> = [8.0, 24.0, 40.0, 56.0, 72.0].iter().map(|x| geometry.get_track_speed_multiplier(&Vec2::new(*x, 8.0))).collect();
+		assert_eq!(speeds, vec![2.0, 1.0, 1.0, 1.0, 1.0]);
+		assert_eq!(geometry.get_track_speed_multiplier(&Vec2::new(200.0, 8.0)), 1.0); // Off of the track.
+	}
+
+	#[test]
+	fn pipes() {
+		// A pipe entered on the left, running through a straight piece, and coming out on the right.
+		let file = TiledFileBuilder::new("test.json")
+			.tile("pipes.png", 0.0, 0.0, 16.0, 16.0).tile_rect("pipeEnter", 0.0, 0.0, 4.0, 16.0).tile_rect("pipeTravel", 4.0, 4.0, 16.0, 12.0)
+			.tile("pipes.png", 16.0, 0.0, 16.0, 16.0).tile_rect("pipeTravel", 0.0, 4.0, 16.0, 12.0)
+			.tile("pipes.png", 32.0, 0.0, 16.0, 16.0).tile_rect("pipeTravel", 0.0, 4.0, 12.0, 12.0).tile_rect("pipeEnter", 12.0, 0.0, 16.0, 16.0)
+			.layer("pipes", 16.0, 16.0, &[
+				&[0, 0, 0],
+				&[1, 2, 3],
+			])
+			.build();
+		let mut geometry = TiledGeometry::new();
+		geometry.load_from(&file);
+
+		// Only one pipe, even though both ends can be entered.
+		assert_eq!(geometry.pneumatic_pipes.len(), 1);
+		let pipe = &geometry.pneumatic_pipes[0];
+		assert_eq!(pairs(pipe.get_path()), vec![(8.0, 8.0), (24.0, 8.0), (40.0, 8.0)]);
+		assert_eq!(corners(&pipe.start_collision), (0.0, 0.0, 4.0, 16.0));
+		assert_eq!(corners(&pipe.end_collision), (44.0, 0.0, 48.0, 16.0));
+	}
+}
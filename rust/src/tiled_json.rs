@@ -0,0 +1,152 @@
+/// The parts of Tiled's JSON map export that are used (read in with serde). Anything else in the file is ignored.
+///
+/// Most fields are optional so that missing ones can be reported (or defaulted) the same way the TypeScript loader does. See `TiledFile::read_json()` for how these become a `TiledFile`.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A whole map.
+#[derive(Deserialize)]
+pub struct JsonMap {
+	/// The tilesets (which must be embedded in the map).
+	#[serde(default)]
+	pub tilesets : Vec<JsonTileset>,
+	/// All layers (tile layers and object groups).
+	#[serde(default)]
+	pub layers : Vec<JsonLayer>,
+	/// The map's own custom properties.
+	#[serde(default)]
+	pub properties : Vec<JsonProperty>,
+}
+
+/// An embedded tileset (a single image cut into a grid of tiles).
+#[derive(Deserialize)]
+pub struct JsonTileset {
+	/// The ID of the first tile in this set (IDs count up across the grid from there).
+	pub firstgid : Option<u32>,
+	/// The image the tiles come from.
+	pub image : Option<String>,
+	/// The width of each tile (in pixels).
+	pub tilewidth : Option<f32>,
+	/// The height of each tile (in pixels).
+	pub tileheight : Option<f32>,
+	/// How many tiles there are in each row of the image.
+	pub columns : Option<u32>,
+	/// How many tiles there are in total.
+	pub tilecount : Option<u32>,
+	/// The tiles that have extra information (properties and collision geometry).
+	pub tiles : Option<Vec<JsonTile>>,
+	/// A color in the image to treat as transparent (like "#ff00ff").
+	pub transparentcolor : Option<String>,
+	/// The custom properties (which apply to the tileset's image).
+	#[serde(default)]
+	pub properties : Vec<JsonProperty>,
+}
+
+/// Extra information about a specific tile in a tileset.
+#[derive(Deserialize)]
+pub struct JsonTile {
+	/// The ID within the tileset.
+	pub id : u32,
+	/// The custom properties.
+	#[serde(default)]
+	pub properties : Vec<JsonProperty>,
+	/// The collision geometry.
+	pub objectgroup : Option<JsonObjectGroup>,
+}
+
+/// A group of objects attached to a tile.
+#[derive(Deserialize)]
+pub struct JsonObjectGroup {
+	/// The objects.
+	#[serde(default)]
+	pub objects : Vec<JsonObject>,
+}
+
+/// A custom property.
+#[derive(Deserialize)]
+pub struct JsonProperty {
+	/// The name.
+	pub name : Option<String>,
+	/// The type ("bool", "string", "int", etc).
+	#[serde(rename = "type")]
+	pub r#type : Option<String>,
+	/// The value (of whatever JSON type matches the property's type).
+	pub value : Option<Value>,
+}
+
+impl JsonProperty {
+	/// Gets the value as a string, the same way JavaScript's `String()` would (so whole numbers don't get a ".0").
+	pub fn value_to_string(&self) -> Option<String> {
+		match self.value.as_ref()? {
+			Value::String(text) => Some(text.clone()),
+			Value::Number(number) => match number.as_f64() {
+				Some(value) => Some(format!("{}", value)),
+				None => Some(number.to_string()),
+			},
+			other => Some(other.to_string()),
+		}
+	}
+}
+
+/// A single layer.
+#[derive(Deserialize)]
+pub struct JsonLayer {
+	/// What kind of layer this is ("tilelayer", "objectgroup", etc).
+	#[serde(rename = "type")]
+	pub r#type : Option<String>,
+	/// The name.
+	pub name : Option<String>,
+	/// The horizontal offset (in pixels).
+	pub offsetx : Option<f32>,
+	/// The vertical offset (in pixels).
+	pub offsety : Option<f32>,
+	/// The width (in tiles, for tile layers).
+	pub width : Option<usize>,
+	/// The height (in tiles, for tile layers).
+	pub height : Option<usize>,
+	/// The tile IDs, in row-major order (for tile layers).
+	pub data : Option<Vec<u32>>,
+	/// The objects (for object groups).
+	#[serde(default)]
+	pub objects : Vec<JsonObject>,
+}
+
+/// A single object (a point, rectangle, polygon, polyline, or ellipse).
+#[derive(Deserialize)]
+pub struct JsonObject {
+	/// The name.
+	pub name : Option<String>,
+	/// The type (for telling objects apart in game code).
+	#[serde(rename = "type")]
+	pub r#type : Option<String>,
+	/// The x position (in pixels).
+	pub x : Option<f32>,
+	/// The y position (in pixels).
+	pub y : Option<f32>,
+	/// The width (in pixels).
+	pub width : Option<f32>,
+	/// The height (in pixels).
+	pub height : Option<f32>,
+	/// Whether this is a point.
+	#[serde(default)]
+	pub point : bool,
+	/// Set if this is an ellipse.
+	pub ellipse : Option<bool>,
+	/// The corners if this is a polygon (relative to the position).
+	pub polygon : Option<Vec<JsonPoint>>,
+	/// The points if this is a polyline (relative to the position).
+	pub polyline : Option<Vec<JsonPoint>>,
+	/// The custom properties.
+	#[serde(default)]
+	pub properties : Vec<JsonProperty>,
+}
+
+/// A point in a polygon or polyline.
+#[derive(Deserialize)]
+pub struct JsonPoint {
+	/// The x position (in pixels).
+	pub x : f32,
+	/// The y position (in pixels).
+	pub y : f32,
+}
@@ -0,0 +1,149 @@
+use crate::keyboard::*;
+
+/// How much time a single simulation step (or debug step) moves the simulation forward (in seconds).
+pub const FIXED_STEP_SECONDS : f32 = 1.0 / 60.0;
+/// The most fixed steps a single update can run, so falling behind (as in after a hitch) doesn't snowball.
+const MAX_STEPS_PER_UPDATE : u32 = 5;
+
+/// Decides how much time the simulation should move forward each update.
+///
+/// Normally that's however many fixed steps fit into the time that really passed (with the leftovers carried over to the next update), but for debugging the simulation can be paused and then moved forward one fixed step at a time.
+/// Pausing only freezes the simulation; rendering and input handling carry on as normal.
+pub struct TimeControl {
+	/// Whether the simulation is paused.
+	paused : bool,
+	/// How many fixed steps are waiting to be run (only used while paused).
+	pending_steps : u32,
+	/// Whether the pause key was down last update (so holding it doesn't flicker).
+	pause_held : bool,
+	/// Whether the step key was down last update (so holding it only steps once).
+	step_held : bool,
+	/// The time that's passed but hasn't been simulated yet (in seconds). Always less than a fixed step after `take_steps()`.
+	accumulator : f32,
+}
+
+impl TimeControl {
+	/// Creates an instance that isn't paused.
+	pub fn new() -> TimeControl {
+		TimeControl {
+			paused : false,
+			pending_steps : 0,
+			pause_held : false,
+			step_held : false,
+			accumulator : 0.0,
+		}
+	}
+
+	/// Whether the simulation is paused.
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	/// Pauses the simulation.
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+
+	/// Lets the simulation run normally again. Drops any steps that haven't run yet.
+	pub fn resume(&mut self) {
+		self.paused = false;
+		self.pending_steps = 0;
+		self.accumulator = 0.0;
+	}
+
+	/// Pauses (if not already) and queues up some number of fixed steps.
+	pub fn step(&mut self, count : u32) {
+		self.paused = true;
+		self.pending_steps += count;
+	}
+
+	/// Handles the debug keys: the pause key toggles pausing, and the step key queues a single step.
+	pub fn handle_input(&mut self, keyboard : &Keyboard) {
+		let pause_down = keyboard.is_down(Key::PAUSE);
+		if pause_down && !self.pause_held {
+			if self.paused {
+				self.resume();
+			} else {
+				self.pause();
+			}
+		}
+		self.pause_held = pause_down;
+
+		let step_down = keyboard.is_down(Key::STEP);
+		if step_down && !self.step_held {
+			self.step(1);
+		}
+		self.step_held = step_down;
+	}
+
+	/// Gets how many fixed steps (of `FIXED_STEP_SECONDS`) to move the simulation forward this update.
+	/// While paused, this only gives out the steps that were asked for (one per update).
+	pub fn take_steps(&mut self, elapsed_seconds : f32) -> u32 {
+		if self.paused {
+			// Show the latest step as-is.
+			self.accumulator = FIXED_STEP_SECONDS;
+			if 0 < self.pending_steps {
+				self.pending_steps -= 1;
+				return 1;
+			}
+			return 0;
+		}
+		self.accumulator += elapsed_seconds.max(0.0);
+		let steps = ((self.accumulator / FIXED_STEP_SECONDS) as u32).min(MAX_STEPS_PER_UPDATE);
+		self.accumulator = (self.accumulator - (steps as f32) * FIXED_STEP_SECONDS).min(FIXED_STEP_SECONDS * 0.999);
+		steps
+	}
+
+	/// Gets how far the leftover time is into the next fixed step (from 0 to 1). Things should be drawn this far between their last two steps.
+	pub fn get_interpolation(&self) -> f32 {
+		(self.accumulator / FIXED_STEP_SECONDS).max(0.0).min(1.0)
+	}
+}
+
+#[cfg(test)]
+mod tests_time_control {
+	use super::*;
+
+	#[test]
+	fn fixed_steps() {
+		let mut time = TimeControl::new();
+		assert_eq!(time.take_steps(FIXED_STEP_SECONDS * 0.5), 0);
+		assert!((time.get_interpolation() - 0.5).abs() < 0.001);
+		assert_eq!(time.take_steps(FIXED_STEP_SECONDS * 2.0), 2);
+		assert!((time.get_interpolation() - 0.5).abs() < 0.001);
+		assert_eq!(time.take_steps(10.0), MAX_STEPS_PER_UPDATE); // Doesn't try to catch up on a long hitch.
+		assert!(time.get_interpolation() < 1.0);
+
+		time.step(1);
+		assert_eq!(time.take_steps(1.0), 1);
+		assert_eq!(time.take_steps(1.0), 0);
+		assert_eq!(time.get_interpolation(), 1.0);
+		time.resume();
+		assert_eq!(time.take_steps(FIXED_STEP_SECONDS * 0.25), 0);
+		assert!((time.get_interpolation() - 0.25).abs() < 0.001);
+	}
+
+	#[test]
+	fn keys() {
+		let mut keyboard = Keyboard::new();
+		let mut time = TimeControl::new();
+		keyboard.on_down("Pause".to_string());
+		time.handle_input(&keyboard);
+		time.handle_input(&keyboard); // Holding shouldn't toggle it back.
+		assert!(time.is_paused());
+		assert_eq!(time.take_steps(0.1), 0);
+		keyboard.on_up("Pause".to_string());
+		time.handle_input(&keyboard);
+
+		keyboard.on_down(".".to_string());
+		time.handle_input(&keyboard);
+		time.handle_input(&keyboard);
+		assert_eq!(time.take_steps(0.1), 1);
+		assert_eq!(time.take_steps(0.1), 0);
+		keyboard.on_up(".".to_string());
+
+		keyboard.on_down("Pause".to_string());
+		time.handle_input(&keyboard);
+		assert!(!time.is_paused());
+	}
+}
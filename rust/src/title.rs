@@ -0,0 +1,68 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::tween::Easing;
+use crate::animation::*;
+use crate::tiled::TiledFile;
+
+/// The map shown behind the title menu. The starting room is small enough to double as it.
+pub const TITLE_MAP_URL : &str = "room.json";
+/// Where a new game starts.
+pub const START_ROOM_URL : &str = "room.json";
+/// The Tiled polyline type for a custom title camera path (see `title_pan()`).
+pub const TITLE_CAMERA_TYPE : &str = "titleCamera";
+/// How fast the default title pan moves (in pixels per second).
+const TITLE_PAN_SPEED : f32 = 40.0;
+/// How long the default title pan waits at each end (in seconds).
+const TITLE_PAN_WAIT : f32 = 2.0;
+
+/// Makes the default title camera path: slowly back and forth across the middle half of a map, at half its height.
+pub fn default_title_pan(map : &Bounds2) -> Track<Vec2> {
+	let quarter = (map.x_max() - map.x_min()) / 4.0;
+	let y = (map.y_min() + map.y_max()) / 2.0;
+	let left = Vec2::new(map.x_min() + quarter, y);
+	let right = Vec2::new(map.x_max() - quarter, y);
+	Track::from_path(&vec![left, right, left], TITLE_PAN_SPEED, &vec![TITLE_PAN_WAIT, TITLE_PAN_WAIT], Easing::QuadInOut, TrackEnd::Loop)
+}
+
+/// Gets the camera path for the title screen from a map: the first polyline with the `TITLE_CAMERA_TYPE` type (read like any camera path, but ping-ponging by default), or else the default pan across the tile layers.
+/// Returns None if the map has neither.
+pub fn title_pan(file : &TiledFile) -> Result<Option<Track<Vec2>>, String> {
+	if let Some(polyline) = file.get_polylines().iter().find(|polyline| TITLE_CAMERA_TYPE == polyline.r#type) {
+		return Track::from_polyline(polyline, "pingPong").map(Some);
+	}
+	let mut bounds : Option<Bounds2> = None;
+	for layer in file.get_tile_layers() {
+		let offset = layer.get_offset();
+		let layer_bounds = Bounds2::from_points(&offset, &(offset + layer.get_size()));
+		bounds = Some(match bounds {
+			Some(mut existing) => {
+				existing.expand_to_x(layer_bounds.x_min());
+				existing.expand_to_x(layer_bounds.x_max());
+				existing.expand_to_y(layer_bounds.y_min());
+				existing.expand_to_y(layer_bounds.y_max());
+				existing
+			},
+			None => layer_bounds,
+		});
+	}
+	Ok(bounds.map(|bounds| default_title_pan(&bounds)))
+}
+
+#[cfg(test)]
+mod tests_title {
+	use super::*;
+
+	#[test]
+	fn default_pan() {
+		let track = default_title_pan(&Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(800.0, 200.0)));
+		assert_eq!(track.get_end(), TrackEnd::Loop);
+		let start = track.sample(0.0).unwrap();
+		assert_eq!((start.x, start.y), (200.0, 100.0));
+		// 400 pixels across at 40 per second, after the first wait.
+		let across = track.sample(TITLE_PAN_WAIT + 10.0).unwrap();
+		assert!((across.x - 600.0).abs() < 0.01);
+		// Then back again, and around.
+		let back = track.sample(2.0 * TITLE_PAN_WAIT + 20.0).unwrap();
+		assert!((back.x - 200.0).abs() < 0.01);
+	}
+}
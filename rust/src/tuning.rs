@@ -0,0 +1,119 @@
+use crate::geo::collision_system::IterationEscape;
+use crate::geo::consts::Tolerances;
+
+/// Values for tweaking how the game looks and feels, all in one place.
+///
+/// Everything here has a sensible default, so only the values being experimented with need to be changed.
+#[derive(Debug, Clone)]
+pub struct Tuning {
+	/// How much health the player starts with.
+	pub player_max_health : f32,
+	/// Below what fraction of max health the player counts as "low" (from 0 to 1).
+	pub low_health_fraction : f32,
+	/// How strong the damage vignette is at its peak (its alpha, from 0 to 1).
+	pub vignette_intensity : f32,
+	/// How long the vignette flash lasts after taking damage (in seconds).
+	pub vignette_flash_seconds : f32,
+	/// How many heartbeats per second when at low health.
+	pub heartbeat_rate : f32,
+	/// How close to a track the player needs to be for holding the track button to pull them onto it (in pixels).
+	pub track_magnet_distance : f32,
+	/// How fast the pull toward a track is (in pixels per second).
+	pub track_magnet_speed : f32,
+	/// How long the jump needs to be held on a track to fully charge a kick (in seconds).
+	pub track_kick_charge_seconds : f32,
+	/// How strong an uncharged kick is (as a fraction of a fully charged one).
+	pub track_kick_min_strength : f32,
+	/// How many seconds the player can hold onto a track on a full stamina meter.
+	pub stamina_max_seconds : f32,
+	/// How much stamina holding onto a track uses per second.
+	pub stamina_track_drain_rate : f32,
+	/// How much stamina kicking off of a track costs.
+	pub stamina_kick_cost : f32,
+	/// How much stamina comes back per second while on the ground.
+	pub stamina_regen_rate : f32,
+	/// How long after using stamina before it starts refilling (in seconds on the ground).
+	pub stamina_regen_delay : f32,
+	/// How full the stamina meter has to get before an exhausted player can use it again (from 0 to 1).
+	pub stamina_recover_fraction : f32,
+	/// How fast the player gets pushed away from whatever hurt them (in pixels per second).
+	pub player_knockback_speed : f32,
+	/// How long the player's inputs are ignored after getting hurt (in seconds).
+	pub player_hit_stun_seconds : f32,
+	/// How much damage landing on an enemy does to it.
+	pub stomp_damage : f32,
+	/// How fast the player bounces off of an enemy they land on (in pixels per second).
+	pub stomp_bounce_speed : f32,
+	/// How long the player has to wait between shots (in seconds).
+	pub player_shot_cooldown : f32,
+	/// How fast loot pops up out of a defeated enemy (in pixels per second).
+	pub loot_pop_speed : f32,
+	/// How fast loot can be thrown sideways out of a defeated enemy (in pixels per second).
+	pub loot_pop_spread : f32,
+	/// How many seconds of bullet time the meter holds.
+	pub bullet_time_max_seconds : f32,
+	/// How many seconds of bullet time come back per second.
+	pub bullet_time_regen_rate : f32,
+	/// How long after using bullet time before the meter starts refilling (in seconds).
+	pub bullet_time_regen_delay : f32,
+	/// How fast the simulation runs during bullet time (1 is normal speed).
+	pub bullet_time_scale : f32,
+	/// How far back rewinding can go (in seconds).
+	pub rewind_max_seconds : f32,
+	/// How long moving between rooms takes, covering and revealing the screen together (in seconds).
+	pub room_transition_seconds : f32,
+	/// The most times the player's physics can alternate between moving and colliding in a single update.
+	pub physics_iteration_budget : usize,
+	/// The most rounds of deflection a single collision check can go through.
+	pub collision_iteration_budget : usize,
+	/// The tallest lip the player steps up onto automatically when walking into it (in pixels). 0 turns stepping up off.
+	pub max_step_height : f32,
+	/// How far sideways the player can be nudged to get around the edge of a ceiling they clip while going up (in pixels). 0 turns corner correction off.
+	pub corner_correction_window : f32,
+	/// What to do when either of the iteration budgets runs out.
+	pub iteration_escape : IterationEscape,
+	/// How close the geometry code considers things to be "the same". Only needs changing for maps with a very different unit scale.
+	pub geometry_tolerances : Tolerances,
+}
+
+impl Tuning {
+	/// Creates an instance with all the default values.
+	pub fn new() -> Tuning {
+		Tuning {
+			player_max_health : 100.0,
+			low_health_fraction : 0.25,
+			vignette_intensity : 0.6,
+			vignette_flash_seconds : 0.4,
+			heartbeat_rate : 1.2,
+			track_magnet_distance : 12.0,
+			track_magnet_speed : 90.0,
+			track_kick_charge_seconds : 0.6,
+			track_kick_min_strength : 0.5,
+			stamina_max_seconds : 4.0,
+			stamina_track_drain_rate : 1.0,
+			stamina_kick_cost : 1.0,
+			stamina_regen_rate : 2.0,
+			stamina_regen_delay : 0.25,
+			stamina_recover_fraction : 0.5,
+			player_knockback_speed : 220.0,
+			player_hit_stun_seconds : 0.35,
+			stomp_damage : 1.0,
+			stomp_bounce_speed : 250.0,
+			player_shot_cooldown : 0.25,
+			loot_pop_speed : 180.0,
+			loot_pop_spread : 60.0,
+			bullet_time_max_seconds : 3.0,
+			bullet_time_regen_rate : 0.5,
+			bullet_time_regen_delay : 1.0,
+			bullet_time_scale : 0.3,
+			rewind_max_seconds : 5.0,
+			room_transition_seconds : 0.6,
+			physics_iteration_budget : 5,
+			collision_iteration_budget : 5,
+			max_step_height : 3.0,
+			corner_correction_window : 4.0,
+			iteration_escape : IterationEscape::DepenetrateAndStop,
+			geometry_tolerances : Tolerances::new(),
+		}
+	}
+}
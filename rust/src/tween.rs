@@ -0,0 +1,169 @@
+use crate::scheduler::{Clock, FrameTime};
+
+/// The shape of the curve a Tween follows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+	/// Constant speed.
+	Linear,
+	/// Starts slow and speeds up.
+	QuadIn,
+	/// Starts fast and slows down.
+	QuadOut,
+	/// Starts slow, speeds up, then slows down again.
+	QuadInOut,
+}
+
+impl Easing {
+	/// Looks up an easing by name (as used in Tiled properties). Returns None if the name isn't known.
+	pub fn from_name(name : &str) -> Option<Easing> {
+		match name {
+			"linear" => Some(Easing::Linear),
+			"quadIn" => Some(Easing::QuadIn),
+			"quadOut" => Some(Easing::QuadOut),
+			"quadInOut" => Some(Easing::QuadInOut),
+			_ => None,
+		}
+	}
+
+	/// Maps a linear progress value (from 0 to 1) onto the curve.
+	pub fn apply(&self, progress : f32) -> f32 {
+		let t = progress.max(0.0).min(1.0);
+		match self {
+			Easing::Linear => t,
+			Easing::QuadIn => t * t,
+			Easing::QuadOut => t * (2.0 - t),
+			Easing::QuadInOut => {
+				if t < 0.5 {
+					2.0 * t * t
+				} else {
+					-1.0 + (4.0 - 2.0 * t) * t
+				}
+			},
+		}
+	}
+}
+
+/// Smoothly moves a value from one number to another over time.
+#[derive(Debug, Clone)]
+pub struct Tween {
+	/// The value at the start.
+	start : f32,
+	/// The value at the end.
+	end : f32,
+	/// How long it takes to get from start to end (in seconds).
+	duration : f32,
+	/// How long it has been running (in seconds).
+	elapsed : f32,
+	/// The curve to follow.
+	easing : Easing,
+	/// Which time it moves forward on when ticked (see `tick()`).
+	clock : Clock,
+}
+
+impl Tween {
+	/// Creates a new tween that starts immediately.
+	pub fn new(start : f32, end : f32, duration : f32, easing : Easing) -> Tween {
+		Tween {
+			start,
+			end,
+			duration,
+			elapsed : 0.0,
+			easing,
+			clock : Clock::Simulation,
+		}
+	}
+
+	/// Makes it move forward on some clock when ticked (it's on simulation time otherwise).
+	pub fn with_clock(mut self, clock : Clock) -> Tween {
+		self.clock = clock;
+		self
+	}
+
+	/// Gets which time it moves forward on when ticked.
+	pub fn get_clock(&self) -> Clock {
+		self.clock
+	}
+
+	/// Creates a tween that's already finished (and so just holds a value).
+	pub fn hold(value : f32) -> Tween {
+		Tween::new(value, value, 0.0, Easing::Linear)
+	}
+
+	/// Moves the tween forward in time. Returns the new value.
+	pub fn update(&mut self, elapsed_seconds : f32) -> f32 {
+		self.elapsed = (self.elapsed + elapsed_seconds).min(self.duration);
+		self.value()
+	}
+
+	/// Moves the tween forward by however much time passed on its clock. Returns the new value.
+	pub fn tick(&mut self, time : &FrameTime) -> f32 {
+		self.update(time.seconds(self.clock))
+	}
+
+	/// Starts the tween over from the beginning.
+	pub fn restart(&mut self) {
+		self.elapsed = 0.0;
+	}
+
+	/// Gets the current value.
+	pub fn value(&self) -> f32 {
+		if self.duration <= 0.0 {
+			return self.end;
+		}
+		self.start + (self.end - self.start) * self.easing.apply(self.elapsed / self.duration)
+	}
+
+	/// Whether the tween has reached its end.
+	pub fn is_done(&self) -> bool {
+		self.duration <= self.elapsed
+	}
+}
+
+#[cfg(test)]
+mod tests_tween {
+	use super::*;
+
+	#[test]
+	fn easing_endpoints() {
+		for easing in &[Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut] {
+			assert_eq!(easing.apply(0.0), 0.0);
+			assert_eq!(easing.apply(1.0), 1.0);
+			assert_eq!(easing.apply(-1.0), 0.0);
+			assert_eq!(easing.apply(2.0), 1.0);
+		}
+		assert_eq!(Easing::QuadInOut.apply(0.5), 0.5);
+		assert!(Easing::QuadIn.apply(0.5) < 0.5);
+		assert!(0.5 < Easing::QuadOut.apply(0.5));
+	}
+
+	#[test]
+	fn linear_progress() {
+		let mut tween = Tween::new(2.0, 4.0, 1.0, Easing::Linear);
+		assert_eq!(tween.value(), 2.0);
+		assert_eq!(tween.update(0.25), 2.5);
+		assert!(!tween.is_done());
+		assert_eq!(tween.update(10.0), 4.0);
+		assert!(tween.is_done());
+		tween.restart();
+		assert_eq!(tween.value(), 2.0);
+	}
+
+	#[test]
+	fn clocks() {
+		let paused = FrameTime { real_seconds : 0.5, simulated_seconds : 0.0, interpolation : 1.0 };
+		let mut game = Tween::new(0.0, 1.0, 1.0, Easing::Linear);
+		let mut menu = Tween::new(0.0, 1.0, 1.0, Easing::Linear).with_clock(Clock::Real);
+		assert_eq!(game.tick(&paused), 0.0);
+		assert_eq!(menu.tick(&paused), 0.5);
+		assert_eq!(menu.get_clock(), Clock::Real);
+		menu.restart();
+		assert_eq!(menu.get_clock(), Clock::Real); // Restarting keeps the clock.
+	}
+
+	#[test]
+	fn hold() {
+		let mut tween = Tween::hold(3.0);
+		assert!(tween.is_done());
+		assert_eq!(tween.update(1.0), 3.0);
+	}
+}
@@ -0,0 +1,266 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+use crate::keyboard::*;
+use crate::gamepad::*;
+
+/// How far the analog stick needs to be pushed to count as a direction.
+const STICK_THRESHOLD : f32 = 0.5;
+/// How long a direction needs to be held before it starts repeating (in seconds).
+const REPEAT_DELAY : f32 = 0.4;
+/// How often a held direction repeats after that (in seconds).
+const REPEAT_PERIOD : f32 = 0.15;
+/// How much being off to the side counts against an element, compared to being further away in the direction moved.
+const SIDEWAYS_WEIGHT : f32 = 2.0;
+
+/// A direction to move the focus in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavDirection {
+	Up,
+	Down,
+	Left,
+	Right,
+}
+
+impl NavDirection {
+	/// Gets a unit vector pointing this way (with y up).
+	pub fn to_vec2(&self) -> Vec2 {
+		match self {
+			NavDirection::Up => Vec2::new(0.0, 1.0),
+			NavDirection::Down => Vec2::new(0.0, -1.0),
+			NavDirection::Left => Vec2::new(-1.0, 0.0),
+			NavDirection::Right => Vec2::new(1.0, 0.0),
+		}
+	}
+}
+
+/// Something that happened to the focus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusEvent {
+	/// The focus moved to the element with this ID.
+	Moved(usize),
+	/// The focused element (with this ID) was activated.
+	Activated(usize),
+}
+
+/// A single element that can be focused.
+struct Focusable {
+	/// The ID it was registered with.
+	id : usize,
+	/// Where it is on the screen (in any coordinates, as long as y is up and every element uses the same ones).
+	bounds : Bounds2,
+}
+
+impl Focusable {
+	/// Gets the center of its bounds.
+	fn center(&self) -> Vec2 {
+		Vec2::new((self.bounds.x_min() + self.bounds.x_max()) / 2.0, (self.bounds.y_min() + self.bounds.y_max()) / 2.0)
+	}
+}
+
+/// Moves a focus between UI elements (as in menu items drawn with DisplayText), so menus can be used with a gamepad or keyboard instead of the mouse.
+///
+/// Elements are registered with their bounds, and moving picks the closest element in that direction (wrapping around to the far side if there's nothing there).
+pub struct FocusNavigator {
+	/// Everything that can be focused, in the order registered.
+	elements : Vec<Focusable>,
+	/// The index of the focused element (in `elements`).
+	focused : Option<usize>,
+	/// The direction being held last update (if any).
+	held_direction : Option<NavDirection>,
+	/// How long until the held direction repeats (in seconds).
+	repeat_timer : f32,
+	/// Whether the activate input was down last update (so holding it only activates once).
+	activate_held : bool,
+}
+
+impl FocusNavigator {
+	/// Creates an instance with nothing to focus.
+	pub fn new() -> FocusNavigator {
+		FocusNavigator {
+			elements : Vec::new(),
+			focused : None,
+			held_direction : None,
+			repeat_timer : 0.0,
+			activate_held : false,
+		}
+	}
+
+	/// Registers an element. The first one registered gets the focus. Fails if the ID is already taken.
+	pub fn add(&mut self, id : usize, bounds : &Bounds2) -> Result<(), String> {
+		if self.elements.iter().any(|element| id == element.id) {
+			return Err(format!("A focusable element with ID {} already exists", id));
+		}
+		self.elements.push(Focusable { id, bounds : bounds.clone() });
+		if self.focused.is_none() {
+			self.focused = Some(self.elements.len() - 1);
+		}
+		Ok(())
+	}
+
+	/// Moves an element (as in when the screen resizes). Returns false if there's no element with that ID.
+	pub fn set_bounds(&mut self, id : usize, bounds : &Bounds2) -> bool {
+		match self.elements.iter_mut().find(|element| id == element.id) {
+			Some(element) => {
+				element.bounds = bounds.clone();
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Removes all elements (as in when leaving a menu).
+	pub fn clear(&mut self) {
+		self.elements.clear();
+		self.focused = None;
+	}
+
+	/// Gets the ID of the focused element (if any).
+	pub fn get_focused(&self) -> Option<usize> {
+		self.focused.map(|index| self.elements[index].id)
+	}
+
+	/// Focuses the element with a given ID. Returns false if there's no such element.
+	pub fn focus(&mut self, id : usize) -> bool {
+		match self.elements.iter().position(|element| id == element.id) {
+			Some(index) => {
+				self.focused = Some(index);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Moves the focus to the closest element in a direction, wrapping around if there's nothing that way. Returns the newly focused ID if it changed.
+	pub fn navigate(&mut self, direction : NavDirection) -> Option<usize> {
+		let current = self.focused?;
+		let origin = self.elements[current].center();
+		let forward = direction.to_vec2();
+		let sideways = forward.ortho();
+		let mut best : Option<(usize, f32)> = None;
+		let mut wrap : Option<(usize, f32)> = None;
+		for (index, element) in self.elements.iter().enumerate() {
+			if current == index {
+				continue;
+			}
+			let offset = element.center() - origin;
+			let ahead = offset.dot(&forward);
+			let aside = offset.dot(&sideways).abs();
+			if 0.0 < ahead {
+				let score = ahead + SIDEWAYS_WEIGHT * aside;
+				if best.map_or(true, |(_, best_score)| score < best_score) {
+					best = Some((index, score));
+				}
+			} else {
+				// Wrapping goes to whatever's furthest back, staying as in line as possible.
+				let score = ahead + SIDEWAYS_WEIGHT * aside;
+				if wrap.map_or(true, |(_, wrap_score)| score < wrap_score) {
+					wrap = Some((index, score));
+				}
+			}
+		}
+		let (index, _) = best.or(wrap)?;
+		self.focused = Some(index);
+		self.get_focused()
+	}
+
+	/// Reads the d-pad, the main stick, and the arrow keys (held directions repeat), and the activate inputs (A or Enter).
+	pub fn update(&mut self, elapsed_seconds : f32, keyboard : &Keyboard, gamepad : &Gamepad) -> Vec<FocusEvent> {
+		let mut events = Vec::new();
+
+		let direction = read_direction(keyboard, gamepad);
+		let mut step = false;
+		if direction != self.held_direction {
+			self.held_direction = direction;
+			self.repeat_timer = REPEAT_DELAY;
+			step = direction.is_some();
+		} else if direction.is_some() {
+			self.repeat_timer -= elapsed_seconds;
+			if self.repeat_timer <= 0.0 {
+				self.repeat_timer += REPEAT_PERIOD;
+				step = true;
+			}
+		}
+		if step {
+			if let Some(id) = self.navigate(direction.unwrap()) {
+				events.push(FocusEvent::Moved(id));
+			}
+		}
+
+		let activate_down = gamepad.is_down(Button::A) || keyboard.is_down(Key::ACCEPT);
+		if activate_down && !self.activate_held {
+			if let Some(id) = self.get_focused() {
+				events.push(FocusEvent::Activated(id));
+			}
+		}
+		self.activate_held = activate_down;
+		events
+	}
+}
+
+/// Works out which direction is being pushed (if any). Only one direction counts at a time, with the d-pad and keys winning over the stick.
+fn read_direction(keyboard : &Keyboard, gamepad : &Gamepad) -> Option<NavDirection> {
+	if gamepad.is_down(Button::DPAD_UP) || keyboard.is_down(Key::UP) { return Some(NavDirection::Up); }
+	if gamepad.is_down(Button::DPAD_DOWN) || keyboard.is_down(Key::DOWN) { return Some(NavDirection::Down); }
+	if gamepad.is_down(Button::DPAD_LEFT) || keyboard.is_down(Key::LEFT) { return Some(NavDirection::Left); }
+	if gamepad.is_down(Button::DPAD_RIGHT) || keyboard.is_down(Key::RIGHT) { return Some(NavDirection::Right); }
+	let stick = gamepad.direction();
+	if stick.x.abs().max(stick.y.abs()) < STICK_THRESHOLD {
+		return None;
+	}
+	Some(if stick.x.abs() < stick.y.abs() {
+		if 0.0 < stick.y { NavDirection::Up } else { NavDirection::Down }
+	} else {
+		if 0.0 < stick.x { NavDirection::Right } else { NavDirection::Left }
+	})
+}
+
+#[cfg(test)]
+mod tests_ui_focus {
+	use super::*;
+
+	/// Makes a 2x2 grid of elements (IDs 0 and 1 on top, 2 and 3 below) plus a wide one (4) under them.
+	fn make_grid() -> FocusNavigator {
+		let mut navigator = FocusNavigator::new();
+		navigator.add(0, &Bounds2::from_centered_rect(&Vec2::new(0.0, 10.0), 8.0, 4.0)).unwrap();
+		navigator.add(1, &Bounds2::from_centered_rect(&Vec2::new(10.0, 10.0), 8.0, 4.0)).unwrap();
+		navigator.add(2, &Bounds2::from_centered_rect(&Vec2::new(0.0, 0.0), 8.0, 4.0)).unwrap();
+		navigator.add(3, &Bounds2::from_centered_rect(&Vec2::new(10.0, 0.0), 8.0, 4.0)).unwrap();
+		navigator.add(4, &Bounds2::from_centered_rect(&Vec2::new(5.0, -10.0), 18.0, 4.0)).unwrap();
+		navigator
+	}
+
+	#[test]
+	fn navigation() {
+		let mut navigator = make_grid();
+		assert!(navigator.add(4, &Bounds2::from_centered_rect(&Vec2::zero(), 1.0, 1.0)).is_err());
+		assert_eq!(navigator.get_focused(), Some(0));
+		assert_eq!(navigator.navigate(NavDirection::Right), Some(1));
+		assert_eq!(navigator.navigate(NavDirection::Down), Some(3));
+		assert_eq!(navigator.navigate(NavDirection::Down), Some(4));
+		assert_eq!(navigator.navigate(NavDirection::Down), Some(0)); // Wraps to the top (preferring the closer column).
+		assert_eq!(navigator.navigate(NavDirection::Left), Some(1)); // Wraps to the right side.
+		assert!(navigator.focus(2));
+		assert_eq!(navigator.navigate(NavDirection::Up), Some(0));
+		assert!(!navigator.focus(7));
+
+		navigator.clear();
+		assert_eq!(navigator.get_focused(), None);
+		assert_eq!(navigator.navigate(NavDirection::Up), None);
+	}
+
+	#[test]
+	fn input() {
+		let mut navigator = make_grid();
+		let mut keyboard = Keyboard::new();
+		let gamepad = Gamepad::new();
+		assert_eq!(navigator.update(0.1, &keyboard, &gamepad), vec![]);
+		keyboard.on_down(String::from("ArrowDown"));
+		assert_eq!(navigator.update(0.1, &keyboard, &gamepad), vec![FocusEvent::Moved(2)]);
+		assert_eq!(navigator.update(0.3, &keyboard, &gamepad), vec![]); // Not repeating yet.
+		assert_eq!(navigator.update(0.15, &keyboard, &gamepad), vec![FocusEvent::Moved(4)]);
+		keyboard.on_up(String::from("ArrowDown"));
+		keyboard.on_down(String::from("Enter"));
+		assert_eq!(navigator.update(0.1, &keyboard, &gamepad), vec![FocusEvent::Activated(4)]);
+		assert_eq!(navigator.update(0.1, &keyboard, &gamepad), vec![]); // Holding only activates once.
+	}
+}
@@ -0,0 +1,203 @@
+use crate::geo::vec2::*;
+use crate::geo::bounds2::Bounds2;
+
+/// How often something is updated, from most to least often.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum UpdateLevel {
+	/// Every simulation step.
+	Full,
+	/// Once every few steps (for all the time since the last one).
+	Reduced,
+	/// Not at all. Everything about it is kept as it was, and the time that passes is dropped.
+	Frozen,
+}
+
+/// How far out of view something of some kind has to be before it's updated less often.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct LodSettings {
+	/// How far outside of the view (in pixels) it has to be to be updated less often. None to always update fully.
+	pub reduced_distance : Option<f32>,
+	/// How far outside of the view (in pixels) it has to be to stop updating. None to never freeze.
+	pub frozen_distance : Option<f32>,
+	/// How many simulation steps apart the reduced updates are.
+	pub reduced_interval : u32,
+	/// How much closer than a distance it has to come to go back to updating more often, so things right at a distance don't keep switching.
+	pub hysteresis : f32,
+}
+
+impl LodSettings {
+	/// Creates settings that always update fully.
+	pub fn always() -> LodSettings {
+		LodSettings {
+			reduced_distance : None,
+			frozen_distance : None,
+			reduced_interval : 4,
+			hysteresis : 64.0,
+		}
+	}
+
+	/// Creates settings that reduce and freeze at some distances (None to never do that).
+	pub fn new(reduced_distance : Option<f32>, frozen_distance : Option<f32>) -> LodSettings {
+		LodSettings {
+			reduced_distance,
+			frozen_distance,
+			..LodSettings::always()
+		}
+	}
+
+	/// Reads the settings from a property: "full" (always update), or the reduced and frozen distances separated by a comma (either can be "none").
+	pub fn from_property(value : &str) -> Result<LodSettings, String> {
+		if "full" == value.trim() {
+			return Ok(LodSettings::always());
+		}
+		let mut distances = Vec::new();
+		for piece in value.split(',').map(str::trim) {
+			distances.push(match piece {
+				"none" => None,
+				_ => Some(piece.parse::<f32>().map_err(|_| format!("Couldn't parse LOD distance {:?}", piece))?),
+			});
+		}
+		if 2 != distances.len() {
+			return Err(format!("Expected 2 LOD distances but got {:?}", value));
+		}
+		Ok(LodSettings::new(distances[0], distances[1]))
+	}
+
+	/// Gets the level for something some distance out of view (ignoring where it was before).
+	fn level_at(&self, distance : f32) -> UpdateLevel {
+		if self.frozen_distance.map_or(false, |frozen| frozen < distance) {
+			UpdateLevel::Frozen
+		} else if self.reduced_distance.map_or(false, |reduced| reduced < distance) {
+			UpdateLevel::Reduced
+		} else {
+			UpdateLevel::Full
+		}
+	}
+}
+
+/// Where a single thing is in its update LOD (level of detail): how often it's being updated, and the time it still needs to catch up on.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpdateLod {
+	/// How often it's being updated.
+	level : UpdateLevel,
+	/// The time since its last update (in seconds), while it's being updated less often.
+	pending_seconds : f32,
+	/// How many more steps until its next reduced update.
+	countdown : u32,
+}
+
+impl UpdateLod {
+	/// Creates an instance that's being fully updated.
+	pub fn new() -> UpdateLod {
+		UpdateLod {
+			level : UpdateLevel::Full,
+			pending_seconds : 0.0,
+			countdown : 0,
+		}
+	}
+
+	/// Gets how often it's being updated.
+	pub fn get_level(&self) -> UpdateLevel {
+		self.level
+	}
+
+	/// Works out how often it should be updated now that it's some distance out of view (see `distance_outside()`), and then whether it's updated this step.
+	/// Returns how much time to update it by (which can cover several steps), or None if it's skipped.
+	pub fn step(&mut self, settings : &LodSettings, distance : f32, elapsed_seconds : f32) -> Option<f32> {
+		let farther = settings.level_at(distance);
+		let nearer = settings.level_at(distance + settings.hysteresis);
+		let level = if self.level < farther {
+			farther
+		} else if nearer < self.level {
+			nearer
+		} else {
+			self.level
+		};
+		if level != self.level {
+			self.level = level;
+			self.countdown = settings.reduced_interval;
+		}
+		match self.level {
+			UpdateLevel::Full => Some(self.take_pending(elapsed_seconds)),
+			UpdateLevel::Reduced => {
+				self.countdown = self.countdown.saturating_sub(1);
+				if 0 < self.countdown {
+					self.pending_seconds += elapsed_seconds;
+					return None;
+				}
+				self.countdown = settings.reduced_interval;
+				Some(self.take_pending(elapsed_seconds))
+			},
+			UpdateLevel::Frozen => {
+				self.pending_seconds = 0.0;
+				None
+			},
+		}
+	}
+
+	/// Gets all the time to update by (including what was skipped), and starts counting again.
+	fn take_pending(&mut self, elapsed_seconds : f32) -> f32 {
+		let seconds = self.pending_seconds + elapsed_seconds;
+		self.pending_seconds = 0.0;
+		seconds
+	}
+}
+
+/// Gets how far a position is outside of the view (0 if it's inside).
+pub fn distance_outside(view : &Bounds2, position : &Vec2) -> f32 {
+	let x = (view.x_min() - position.x).max(position.x - view.x_max()).max(0.0);
+	let y = (view.y_min() - position.y).max(position.y - view.y_max()).max(0.0);
+	Vec2::new(x, y).length()
+}
+
+#[cfg(test)]
+mod tests_update_lod {
+	use super::*;
+
+	#[test]
+	fn levels() {
+		let settings = LodSettings::new(Some(200.0), Some(1000.0));
+		let mut lod = UpdateLod::new();
+		assert_eq!(lod.step(&settings, 0.0, 0.1), Some(0.1));
+
+		// Far enough away only updates every few steps, catching up on the time it missed.
+		let updates : Vec<Option<f32>> = (0..4).map(|_| lod.step(&settings, 300.0, 0.1)).collect();
+		assert_eq!(lod.get_level(), UpdateLevel::Reduced);
+		assert_eq!(updates.iter().flatten().count(), 1);
+		assert!((updates[3].unwrap() - 0.4).abs() < 0.0001);
+
+		// Coming back just inside the distance doesn't switch back (yet).
+		assert_eq!(lod.step(&settings, 180.0, 0.1), None);
+		assert_eq!(lod.get_level(), UpdateLevel::Reduced);
+		assert!((lod.step(&settings, 100.0, 0.1).unwrap() - 0.2).abs() < 0.0001);
+		assert_eq!(lod.get_level(), UpdateLevel::Full);
+
+		// Frozen things don't catch up on time.
+		assert_eq!(lod.step(&settings, 5000.0, 0.1), None);
+		assert_eq!(lod.get_level(), UpdateLevel::Frozen);
+		assert_eq!(lod.step(&settings, 5000.0, 0.1), None);
+		assert_eq!(lod.step(&settings, 0.0, 0.1), Some(0.1));
+
+		// Settings without distances always update.
+		assert_eq!(lod.step(&LodSettings::always(), 1.0e6, 0.1), Some(0.1));
+	}
+
+	#[test]
+	fn properties() {
+		assert_eq!(LodSettings::from_property("full"), Ok(LodSettings::always()));
+		assert_eq!(LodSettings::from_property("256, none"), Ok(LodSettings::new(Some(256.0), None)));
+		assert!(LodSettings::from_property("256").is_err());
+		assert!(LodSettings::from_property("near,far").is_err());
+	}
+
+	#[test]
+	fn distances() {
+		let view = Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 100.0));
+		assert_eq!(distance_outside(&view, &Vec2::new(50.0, 50.0)), 0.0);
+		assert_eq!(distance_outside(&view, &Vec2::new(-30.0, 50.0)), 30.0);
+		assert_eq!(distance_outside(&view, &Vec2::new(130.0, 140.0)), 50.0);
+	}
+}
@@ -0,0 +1,238 @@
+use crate::color::*;
+use crate::display_buffer::*;
+use crate::geo::vec2::*;
+use crate::geo::vec3::*;
+use crate::geo::bounds2::Bounds2;
+use crate::origin_rebase::ShiftOrigin;
+
+/// How wide each column of the water's surface is (in pixels).
+const WATER_COLUMN_WIDTH : f32 = 4.0;
+/// How strongly each column is pulled back to its resting height.
+const WATER_STIFFNESS : f32 = 120.0;
+/// How quickly each column's movement dies off.
+const WATER_DAMPING : f32 = 4.0;
+/// How strongly each column pulls on its neighbors.
+const WATER_SPREAD : f32 = 400.0;
+/// How many times per update the neighbor coupling is applied. Higher means waves travel further per update.
+const WATER_SPREAD_PASSES : usize = 4;
+/// How much of an entering/exiting object's vertical speed turns into surface speed.
+const WATER_SPLASH_SCALE : f32 = 0.25;
+/// How far from a splash (in pixels) the columns are affected.
+const WATER_SPLASH_RADIUS : f32 = 8.0;
+/// The color of the top of the water.
+const WATER_TOP_COLOR : (u8, u8, u8, u8) = (96, 160, 255, 160);
+/// The color of the bottom of the water.
+const WATER_BOTTOM_COLOR : (u8, u8, u8, u8) = (16, 48, 128, 200);
+/// The depth to draw water at (in front of the tiles so things in it look submerged).
+const WATER_DEPTH : f32 = -0.5;
+
+/// A 1D spring simulation of a water surface.
+///
+/// The surface is split into evenly spaced columns, each a spring pulled toward its resting height and coupled to its neighbors.
+pub struct WaterSurface {
+	/// How far each column is from its resting height.
+	heights : Vec<f32>,
+	/// How fast each column's height is changing.
+	velocities : Vec<f32>,
+}
+
+impl WaterSurface {
+	/// Creates a flat surface with the given number of columns.
+	pub fn new(column_count : usize) -> WaterSurface {
+		WaterSurface {
+			heights : vec![0.0; column_count],
+			velocities : vec![0.0; column_count],
+		}
+	}
+
+	/// Gets how far each column is from its resting height.
+	pub fn get_heights<'a>(&'a self) -> &'a Vec<f32> {
+		&self.heights
+	}
+
+	/// Adds some speed to a single column.
+	pub fn disturb(&mut self, column : usize, speed : f32) {
+		if column < self.velocities.len() {
+			self.velocities[column] += speed;
+		}
+	}
+
+	/// Steps the simulation forward.
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		for index in 0..self.heights.len() {
+			let acceleration = -WATER_STIFFNESS * self.heights[index] - WATER_DAMPING * self.velocities[index];
+			self.velocities[index] += acceleration * elapsed_seconds;
+			self.heights[index] += self.velocities[index] * elapsed_seconds;
+		}
+
+		let count = self.heights.len();
+		let spread = (WATER_SPREAD * elapsed_seconds).min(0.5); // Don't let it overshoot on long updates.
+		let mut deltas = vec![0.0; count];
+		for _pass in 0..WATER_SPREAD_PASSES {
+			for index in 0..count {
+				deltas[index] = 0.0;
+				if 0 < index {
+					deltas[index] += spread * (self.heights[index - 1] - self.heights[index]);
+				}
+				if index + 1 < count {
+					deltas[index] += spread * (self.heights[index + 1] - self.heights[index]);
+				}
+			}
+			for index in 0..count {
+				self.velocities[index] += deltas[index];
+				self.heights[index] += deltas[index] * elapsed_seconds;
+			}
+		}
+	}
+}
+
+/// A rectangle of water with a moving surface.
+pub struct Water {
+	/// The area the water fills (when calm).
+	bounds : Bounds2,
+	/// The surface simulation.
+	surface : WaterSurface,
+	/// What the water is drawn in.
+	display : DisplayBuffer,
+}
+
+impl Water {
+	/// Creates water that fills a rectangle.
+	pub fn new(bounds : &Bounds2) -> Water {
+		let width = bounds.x_max() - bounds.x_min();
+		let column_count = ((width / WATER_COLUMN_WIDTH).ceil() as usize).max(1) + 1;
+		let mut water = Water {
+			bounds : bounds.clone(),
+			surface : WaterSurface::new(column_count),
+			display : DisplayBuffer::new(DisplayBufferType::SOLIDS),
+		};
+		water.redraw();
+		water
+	}
+
+	/// Gets the area the water fills (when calm).
+	pub fn get_bounds<'a>(&'a self) -> &'a Bounds2 {
+		&self.bounds
+	}
+
+	/// Disturbs the surface around an x position. Meant for when something enters/exits the water.
+	/// The speed should be the vertical speed of whatever is causing the splash.
+	pub fn splash(&mut self, x : f32, speed : f32) {
+		let start = ((x - WATER_SPLASH_RADIUS - self.bounds.x_min()) / WATER_COLUMN_WIDTH).floor().max(0.0) as usize;
+		let end = ((x + WATER_SPLASH_RADIUS - self.bounds.x_min()) / WATER_COLUMN_WIDTH).ceil().max(0.0) as usize;
+		for column in start..(end + 1) {
+			self.surface.disturb(column, speed * WATER_SPLASH_SCALE);
+		}
+	}
+
+	/// Splashes wherever something crossed the surface, given where each thing was (before moving) and where it is now.
+	pub fn splash_crossings(&mut self, movements : &[(Vec2, Vec2)], elapsed_seconds : f32) {
+		for (x, speed) in find_crossings(&self.bounds, movements, elapsed_seconds) {
+			self.splash(x, speed);
+		}
+	}
+
+	/// Updates the surface and then the display.
+	pub fn update(&mut self, elapsed_seconds : f32) {
+		self.surface.update(elapsed_seconds);
+		self.redraw();
+	}
+
+	/// Rebuilds the display buffer from the current state of the surface.
+	fn redraw(&mut self) {
+		let top_color = Color::new(WATER_TOP_COLOR.0, WATER_TOP_COLOR.1, WATER_TOP_COLOR.2, WATER_TOP_COLOR.3);
+		let bottom_color = Color::new(WATER_BOTTOM_COLOR.0, WATER_BOTTOM_COLOR.1, WATER_BOTTOM_COLOR.2, WATER_BOTTOM_COLOR.3);
+		let heights = self.surface.get_heights();
+		let mut points = Vec::with_capacity(heights.len() * 2);
+		let mut colors = Vec::with_capacity(heights.len() * 2);
+		for (index, height) in heights.iter().enumerate() {
+			let x = (self.bounds.x_min() + (index as f32) * WATER_COLUMN_WIDTH).min(self.bounds.x_max());
+			points.push(Vec3::new(x, self.bounds.y_min(), WATER_DEPTH));
+			colors.push(bottom_color.clone());
+			points.push(Vec3::new(x, self.bounds.y_max() + height, WATER_DEPTH));
+			colors.push(top_color.clone());
+		}
+		let mut editor = self.display.make_editor();
+		editor.clear();
+		editor.add_triangle_strip(&points, &colors);
+	}
+
+	/// Checks whether a position is in the water (using the calm surface).
+	pub fn contains(&self, position : &Vec2) -> bool {
+		self.bounds.overlaps_point(position)
+	}
+}
+
+/// Finds where movements (from a position to another) went into or out of some water, as x positions and vertical speeds (see `Water::splash()`).
+fn find_crossings(bounds : &Bounds2, movements : &[(Vec2, Vec2)], elapsed_seconds : f32) -> Vec<(f32, f32)> {
+	if 0.0 >= elapsed_seconds {
+		return Vec::new();
+	}
+	movements.iter()
+		.filter(|(from, to)| bounds.overlaps_point(from) != bounds.overlaps_point(to))
+		.map(|(from, to)| (to.x, (to.y - from.y) / elapsed_seconds))
+		.collect()
+}
+
+impl ShiftOrigin for Water {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.bounds.shift_origin(shift);
+		self.redraw();
+	}
+}
+
+#[cfg(test)]
+mod tests_water_surface {
+	use super::*;
+
+	#[test]
+	fn calm_stays_calm() {
+		let mut surface = WaterSurface::new(10);
+		for _ in 0..60 {
+			surface.update(1.0 / 60.0);
+		}
+		for height in surface.get_heights() {
+			assert_eq!(*height, 0.0);
+		}
+	}
+
+	#[test]
+	fn ripples_spread_and_settle() {
+		let mut surface = WaterSurface::new(21);
+		surface.disturb(10, -100.0);
+		for _ in 0..10 {
+			surface.update(1.0 / 60.0);
+		}
+		assert!(surface.get_heights()[10] < 0.0);
+		assert!(0.0 != surface.get_heights()[12]); // Should have moved the neighbors too.
+		for _ in 0..600 {
+			surface.update(1.0 / 60.0);
+		}
+		for height in surface.get_heights() {
+			assert!(height.abs() < 0.1, "Surface didn't settle: {}", height);
+		}
+	}
+
+	#[test]
+	fn crossings() {
+		let bounds = Bounds2::from_points(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 50.0));
+		let movements = [
+			(Vec2::new(10.0, 60.0), Vec2::new(10.0, 40.0)), // Falling in.
+			(Vec2::new(20.0, 10.0), Vec2::new(20.0, 20.0)), // Staying under.
+			(Vec2::new(30.0, 45.0), Vec2::new(35.0, 55.0)), // Jumping out.
+			(Vec2::new(-10.0, 60.0), Vec2::new(-10.0, 40.0)), // Passing by.
+		];
+		assert_eq!(find_crossings(&bounds, &movements, 0.5), vec![(10.0, -40.0), (35.0, 20.0)]);
+		assert!(find_crossings(&bounds, &movements, 0.0).is_empty());
+	}
+
+	#[test]
+	fn disturb_out_of_range() {
+		let mut surface = WaterSurface::new(4);
+		surface.disturb(4, 100.0);
+		surface.update(1.0 / 60.0);
+		for height in surface.get_heights() {
+			assert_eq!(*height, 0.0);
+		}
+	}
+}
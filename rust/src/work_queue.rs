@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+
+/// A piece of work. Returns whether it's finished; if not, it's run again (before anything after it) the next time there's time.
+pub type WorkStep<C> = Box<dyn FnMut(&mut C) -> bool>;
+
+/// Work that's too much to do in one update, split into steps that are run a little at a time (so the page doesn't freeze).
+///
+/// Steps run in the order they were added, each on whatever it's given to work on. A step that isn't done (like going through a long list in batches) keeps its place at the front.
+pub struct WorkQueue<C> {
+	/// What's left, with names (for logging).
+	steps : VecDeque<(&'static str, WorkStep<C>)>,
+	/// How many steps have been added since the queue was last empty.
+	added : usize,
+	/// How many of those have finished.
+	finished : usize,
+	/// Told how far along the work is after each run (from 0 to 1, and exactly 1 once it's all done).
+	on_progress : Option<fn(&mut C, f32)>,
+}
+
+impl<C> WorkQueue<C> {
+	/// Creates an empty instance.
+	pub fn new() -> WorkQueue<C> {
+		WorkQueue {
+			steps : VecDeque::new(),
+			added : 0,
+			finished : 0,
+			on_progress : None,
+		}
+	}
+
+	/// Sets what's told how far along the work is.
+	pub fn with_progress(mut self, on_progress : fn(&mut C, f32)) -> WorkQueue<C> {
+		self.on_progress = Some(on_progress);
+		self
+	}
+
+	/// Adds a step to the end.
+	pub fn push<F : FnMut(&mut C) -> bool + 'static>(&mut self, name : &'static str, step : F) {
+		self.steps.push_back((name, Box::new(step)));
+		self.added += 1;
+	}
+
+	/// Adds a step that always finishes in one go.
+	pub fn push_once<F : FnOnce(&mut C) + 'static>(&mut self, name : &'static str, step : F) {
+		let mut step = Some(step);
+		self.push(name, move |context| {
+			if let Some(step) = step.take() {
+				step(context);
+			}
+			true
+		});
+	}
+
+	/// Drops everything that's left (without running it).
+	pub fn clear(&mut self) {
+		self.steps.clear();
+		self.added = 0;
+		self.finished = 0;
+	}
+
+	/// Whether there's nothing left to do.
+	pub fn is_empty(&self) -> bool {
+		self.steps.is_empty()
+	}
+
+	/// Gets the name of the step that will run next (if any).
+	pub fn get_next_name(&self) -> Option<&'static str> {
+		self.steps.front().map(|(name, _)| *name)
+	}
+
+	/// Gets how far along the work is (from 0 to 1). Each step counts the same, however long it takes.
+	pub fn get_progress(&self) -> f32 {
+		if 0 == self.added {
+			1.0
+		} else {
+			(self.finished as f32) / (self.added as f32)
+		}
+	}
+
+	/// Runs steps until the time budget's used up (or there's nothing left). At least one step is always run, so the work finishes even if every step takes longer than the budget.
+	///
+	/// The clock is passed in (in seconds) so it can be faked. Returns how many steps finished.
+	pub fn run(&mut self, context : &mut C, budget_seconds : f64, now : &dyn Fn() -> f64) -> usize {
+		if self.steps.is_empty() {
+			return 0;
+		}
+		let start = now();
+		let mut finished = 0;
+		while let Some((_, step)) = self.steps.front_mut() {
+			if step(context) {
+				self.steps.pop_front();
+				self.finished += 1;
+				finished += 1;
+			}
+			if budget_seconds <= now() - start {
+				break;
+			}
+		}
+		let progress = self.get_progress();
+		if self.steps.is_empty() {
+			self.added = 0;
+			self.finished = 0;
+		}
+		if let Some(on_progress) = self.on_progress {
+			on_progress(context, progress);
+		}
+		finished
+	}
+}
+
+#[cfg(test)]
+mod tests_work_queue {
+	use super::*;
+	use std::cell::Cell;
+
+	/// What the steps work on.
+	struct Context {
+		log : Vec<String>,
+		progress : Vec<f32>,
+	}
+
+	#[test]
+	fn budgeted() {
+		let clock = Cell::new(0.0);
+		let now = || { let time = clock.get(); clock.set(time + 1.0); time }; // Every look at the clock takes a second.
+		let mut queue = WorkQueue::new().with_progress(|context : &mut Context, progress| context.progress.push(progress));
+		let mut context = Context { log : Vec::new(), progress : Vec::new() };
+		queue.push_once("first", |context : &mut Context| context.log.push(String::from("first")));
+		let mut left = 5;
+		queue.push("batches", move |context : &mut Context| {
+			let batch = left.min(2);
+			left -= batch;
+			context.log.push(format!("batch of {}", batch));
+			0 == left
+		});
+		queue.push_once("last", |context : &mut Context| context.log.push(String::from("last")));
+		assert_eq!(queue.get_progress(), 0.0);
+
+		// A budget that's smaller than any step still runs one.
+		assert_eq!(queue.run(&mut context, 0.5, &now), 1);
+		assert_eq!(context.log, vec!["first"]);
+		assert_eq!(queue.get_next_name(), Some("batches"));
+
+		// Unfinished steps keep their place.
+		assert_eq!(queue.run(&mut context, 2.0, &now), 0);
+		assert_eq!(context.log, vec!["first", "batch of 2", "batch of 2"]);
+		assert_eq!(queue.get_next_name(), Some("batches"));
+
+		assert_eq!(queue.run(&mut context, 100.0, &now), 2);
+		assert_eq!(context.log, vec!["first", "batch of 2", "batch of 2", "batch of 1", "last"]);
+		assert!(queue.is_empty());
+		assert_eq!(context.progress, vec![1.0 / 3.0, 1.0 / 3.0, 1.0]);
+		assert_eq!(queue.run(&mut context, 100.0, &now), 0); // Nothing left, so not even progress.
+		assert_eq!(context.progress.len(), 3);
+
+		// Clearing drops the rest, and starts the progress over.
+		queue.push_once("dropped", |context : &mut Context| context.log.push(String::from("dropped")));
+		queue.clear();
+		assert!(queue.is_empty());
+		assert_eq!(queue.get_progress(), 1.0);
+	}
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use crate::tiled::TiledPoint;
+use crate::event_bus::GameEvent;
+use crate::geo::vec2::*;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The Tiled property holding the condition for an object to be spawned/enabled.
+pub const CONDITION_PROPERTY : &str = "condition";
+/// The type of Tiled points that set a flag when touched.
+pub const SWITCH_POINT_TYPE : &str = "switch";
+/// How close the player needs to be to a switch to press it.
+const SWITCH_RADIUS : f32 = 10.0;
+
+/// The value of a single world flag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagValue {
+	/// Just on or off.
+	Bool(bool),
+	/// A count (or some other number).
+	Int(i32),
+}
+
+impl FlagValue {
+	/// Parses a value: either "true", "false", or an integer. Returns None if it's none of those.
+	pub fn parse(text : &str) -> Option<FlagValue> {
+		match text.trim() {
+			"true" => Some(FlagValue::Bool(true)),
+			"false" => Some(FlagValue::Bool(false)),
+			other => other.parse::<i32>().ok().map(FlagValue::Int),
+		}
+	}
+
+	/// Gets the value as a boolean. Integers are true when they're not zero.
+	pub fn as_bool(&self) -> bool {
+		match self {
+			FlagValue::Bool(value) => *value,
+			FlagValue::Int(value) => 0 != *value,
+		}
+	}
+
+	/// Gets the value as an integer. Booleans are 1 when true and 0 when false.
+	pub fn as_int(&self) -> i32 {
+		match self {
+			FlagValue::Bool(value) => if *value { 1 } else { 0 },
+			FlagValue::Int(value) => *value,
+		}
+	}
+
+	/// Converts the value into the same form `parse()` takes.
+	pub fn to_text(&self) -> String {
+		match self {
+			FlagValue::Bool(value) => value.to_string(),
+			FlagValue::Int(value) => value.to_string(),
+		}
+	}
+}
+
+/// Global state that sticks around between rooms (and between play sessions, through saves): which switches were pressed, what's been collected or defeated, and so on.
+///
+/// Flags that were never set count as false (or zero).
+/// Names can't contain line breaks (as the save format is one flag per line).
+pub struct WorldFlags {
+	/// All flags that have been set.
+	values : HashMap<String, FlagValue>,
+	/// Whether anything changed since the last `take_dirty()`.
+	dirty : bool,
+}
+
+impl WorldFlags {
+	/// Creates an instance with no flags set.
+	pub fn new() -> WorldFlags {
+		WorldFlags {
+			values : HashMap::new(),
+			dirty : false,
+		}
+	}
+
+	/// Sets a flag.
+	pub fn set(&mut self, name : &str, value : FlagValue) {
+		if self.values.get(name) != Some(&value) {
+			self.values.insert(name.to_string(), value);
+			self.dirty = true;
+		}
+	}
+
+	/// Adds to a flag's count. Returns the new count.
+	pub fn add(&mut self, name : &str, amount : i32) -> i32 {
+		let total = self.get_int(name) + amount;
+		self.set(name, FlagValue::Int(total));
+		total
+	}
+
+	/// Gets a flag's value (if it was ever set).
+	pub fn get(&self, name : &str) -> Option<FlagValue> {
+		self.values.get(name).cloned()
+	}
+
+	/// Gets a flag as a boolean.
+	pub fn get_bool(&self, name : &str) -> bool {
+		self.values.get(name).map_or(false, |value| value.as_bool())
+	}
+
+	/// Gets a flag as an integer.
+	pub fn get_int(&self, name : &str) -> i32 {
+		self.values.get(name).map_or(0, |value| value.as_int())
+	}
+
+	/// Counts the flags starting with some prefix (like "secret.") that are set to true (or a non-zero count).
+	pub fn count_set(&self, prefix : &str) -> usize {
+		self.values.iter().filter(|(name, value)| name.starts_with(prefix) && value.as_bool()).count()
+	}
+
+	/// Updates the flags that track game events:
+	/// * "objective.NAME", "secret.INDEX", and "challenge.NAME" are set to true when those are completed/found.
+	/// * "collected.KIND" and "defeated.DEFINITION" count pickups collected and enemies defeated.
+	pub fn handle_event(&mut self, event : &GameEvent) {
+		match event {
+			GameEvent::ObjectiveCompleted(name) => { self.set(&format!("objective.{}", name), FlagValue::Bool(true)); },
+			GameEvent::SecretFound(index) => { self.set(&format!("secret.{}", index), FlagValue::Bool(true)); },
+			GameEvent::PickupCollected(kind) => { self.add(&format!("collected.{}", kind), 1); },
+			GameEvent::EnemyDefeated(name) => { self.add(&format!("defeated.{}", name), 1); },
+			GameEvent::ChallengeCompleted(name) => { self.set(&crate::challenge_room::challenge_flag(name), FlagValue::Bool(true)); },
+			GameEvent::Combat => {},
+		}
+	}
+
+	/// Returns whether any flag changed since the last call (so it's known when to save).
+	pub fn take_dirty(&mut self) -> bool {
+		std::mem::replace(&mut self.dirty, false)
+	}
+
+	/// Converts all flags into text for saving: one "name=value" per line (sorted by name).
+	pub fn to_save_string(&self) -> String {
+		let mut names : Vec<&String> = self.values.keys().collect();
+		names.sort();
+		let mut lines = Vec::with_capacity(names.len());
+		for name in names {
+			lines.push(format!("{}={}", name, self.values[name].to_text()));
+		}
+		lines.join("\n")
+	}
+
+	/// Replaces all flags with ones from `to_save_string()`. If anything's wrong with the text, then nothing is changed.
+	pub fn load_save_string(&mut self, text : &str) -> Result<(), String> {
+		let mut values = HashMap::new();
+		for line in text.lines() {
+			if line.trim().is_empty() {
+				continue;
+			}
+			let split = match line.rfind('=') {
+				Some(split) => split,
+				None => { return Err(format!("Missing '=' in {:?}", line)); },
+			};
+			match FlagValue::parse(&line[split+1..]) {
+				Some(value) => { values.insert(line[..split].to_string(), value); },
+				None => { return Err(format!("Bad value in {:?}", line)); },
+			}
+		}
+		self.values = values;
+		self.dirty = false;
+		Ok(())
+	}
+}
+
+/// How a single part of a condition tests its flag.
+#[derive(Debug, Clone, PartialEq)]
+enum FlagTest {
+	/// Whether the flag is true (or false).
+	Is(bool),
+	/// The flag's count is equal to a value.
+	Equal(i32),
+	/// The flag's count isn't equal to a value.
+	NotEqual(i32),
+	/// The flag's count is less than a value.
+	Less(i32),
+	/// The flag's count is at most a value.
+	LessOrEqual(i32),
+	/// The flag's count is more than a value.
+	Greater(i32),
+	/// The flag's count is at least a value.
+	GreaterOrEqual(i32),
+}
+
+/// The comparison operators conditions can use, paired with how to build their tests. Longer ones come first so "<=" isn't read as "<".
+const COMPARISONS : [(&str, fn(i32) -> FlagTest); 6] = [
+	("==", FlagTest::Equal),
+	("!=", FlagTest::NotEqual),
+	("<=", FlagTest::LessOrEqual),
+	(">=", FlagTest::GreaterOrEqual),
+	("<", FlagTest::Less),
+	(">", FlagTest::Greater),
+];
+
+/// A test against the world flags, as written in a Tiled `condition` property.
+///
+/// It's any number of clauses joined by "&&" (all of which must hold). Each clause is one of:
+/// * "NAME": The flag is true.
+/// * "!NAME": The flag is false (or was never set).
+/// * "NAME OP NUMBER": The flag's count compared with one of ==, !=, <, <=, >, or >=.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+	/// The flag names and their tests.
+	clauses : Vec<(String, FlagTest)>,
+}
+
+impl Condition {
+	/// Parses a condition.
+	pub fn parse(text : &str) -> Result<Condition, String> {
+		let mut clauses = Vec::new();
+		for part in text.split("&&") {
+			let part = part.trim();
+			if part.is_empty() {
+				return Err(format!("Empty clause in {:?}", text));
+			}
+			let mut clause = None;
+			for (operator, make_test) in COMPARISONS.iter() {
+				if let Some(split) = part.find(operator) {
+					let name = part[..split].trim();
+					let value = part[split+operator.len()..].trim();
+					let number = value.parse::<i32>().map_err(|_| format!("Can't compare {:?} against {:?} (it's not an integer)", name, value))?;
+					clause = Some((name.to_string(), make_test(number)));
+					break;
+				}
+			}
+			let (name, test) = match clause {
+				Some(clause) => clause,
+				None => {
+					if let Some(name) = part.strip_prefix('!') {
+						(name.trim().to_string(), FlagTest::Is(false))
+					} else {
+						(part.to_string(), FlagTest::Is(true))
+					}
+				},
+			};
+			if name.is_empty() {
+				return Err(format!("Missing flag name in {:?}", part));
+			}
+			clauses.push((name, test));
+		}
+		Ok(Condition { clauses })
+	}
+
+	/// Reads the condition off of a Tiled point (if it has one).
+	pub fn from_point(point : &TiledPoint) -> Result<Option<Condition>, String> {
+		match point.get_property(CONDITION_PROPERTY) {
+			Some(text) => Ok(Some(Condition::parse(text)?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Whether the condition currently holds.
+	pub fn holds(&self, flags : &WorldFlags) -> bool {
+		self.clauses.iter().all(|(name, test)| {
+			let count = flags.get_int(name);
+			match test {
+				FlagTest::Is(expected) => flags.get_bool(name) == *expected,
+				FlagTest::Equal(value) => count == *value,
+				FlagTest::NotEqual(value) => count != *value,
+				FlagTest::Less(value) => count < *value,
+				FlagTest::LessOrEqual(value) => count <= *value,
+				FlagTest::Greater(value) => count > *value,
+				FlagTest::GreaterOrEqual(value) => count >= *value,
+			}
+		})
+	}
+}
+
+/// A spot that sets a flag when the player touches it.
+#[derive(Debug, Clone)]
+pub struct FlagSwitch {
+	/// Where the switch is.
+	pub position : Vec2,
+	/// The flag it sets.
+	pub flag : String,
+	/// What it sets the flag to.
+	pub value : FlagValue,
+}
+
+impl FlagSwitch {
+	/// Creates a switch from a Tiled point. Uses the properties:
+	/// * "flag": The flag to set. Required.
+	/// * "value": What to set it to (defaults to true).
+	pub fn from_point(point : &TiledPoint) -> Result<FlagSwitch, String> {
+		let flag = match point.get_property("flag") {
+			Some(flag) => flag.to_string(),
+			None => { return Err(String::from("Missing \"flag\" property")); },
+		};
+		let value = match point.get_property("value") {
+			Some(text) => match FlagValue::parse(text) {
+				Some(value) => value,
+				None => { return Err(format!("Bad value {:?}", text)); },
+			},
+			None => FlagValue::Bool(true),
+		};
+		Ok(FlagSwitch {
+			position : point.position,
+			flag,
+			value,
+		})
+	}
+
+	/// Sets the flag if the position is touching the switch. Returns whether it was.
+	pub fn press_if_touching(&self, position : &Vec2, flags : &mut WorldFlags) -> bool {
+		if SWITCH_RADIUS <= (position - self.position).length() {
+			return false;
+		}
+		flags.set(&self.flag, self.value);
+		true
+	}
+}
+
+impl ShiftOrigin for FlagSwitch {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.position += shift;
+	}
+}
+
+#[cfg(test)]
+mod tests_world_flags {
+	use super::*;
+
+	#[test]
+	fn setting_and_saving() {
+		let mut flags = WorldFlags::new();
+		assert!(!flags.get_bool("gate"));
+		assert!(!flags.take_dirty());
+		flags.set("gate", FlagValue::Bool(true));
+		flags.handle_event(&GameEvent::EnemyDefeated(String::from("crawler")));
+		flags.handle_event(&GameEvent::EnemyDefeated(String::from("crawler")));
+		assert!(flags.take_dirty());
+		assert!(!flags.take_dirty());
+		flags.set("gate", FlagValue::Bool(true)); // Not a change.
+		assert!(!flags.take_dirty());
+		assert_eq!(flags.get_int("defeated.crawler"), 2);
+		assert_eq!(flags.count_set("defeated."), 1);
+
+		let saved = flags.to_save_string();
+		assert_eq!(saved, "defeated.crawler=2\ngate=true");
+		let mut loaded = WorldFlags::new();
+		assert!(loaded.load_save_string(&saved).is_ok());
+		assert_eq!(loaded.get("gate"), Some(FlagValue::Bool(true)));
+		assert_eq!(loaded.get_int("defeated.crawler"), 2);
+		assert!(loaded.load_save_string("gate=maybe").is_err());
+		assert!(loaded.get_bool("gate")); // Unchanged by the bad load.
+	}
+
+	#[test]
+	fn conditions() {
+		let mut flags = WorldFlags::new();
+		flags.set("switch.a", FlagValue::Bool(true));
+		flags.set("keys", FlagValue::Int(2));
+		assert!(Condition::parse("switch.a").unwrap().holds(&flags));
+		assert!(!Condition::parse("!switch.a").unwrap().holds(&flags));
+		assert!(Condition::parse("!never").unwrap().holds(&flags));
+		assert!(Condition::parse("switch.a && keys >= 2").unwrap().holds(&flags));
+		assert!(!Condition::parse("switch.a && keys > 2").unwrap().holds(&flags));
+		assert!(Condition::parse("keys<=2&&keys!=1").unwrap().holds(&flags));
+		assert!(Condition::parse("keys == two").is_err());
+		assert!(Condition::parse("a && && b").is_err());
+		assert!(Condition::parse("== 2").is_err());
+	}
+
+	#[test]
+	fn switches() {
+		let point = TiledPoint {
+			position : Vec2::new(0.0, 0.0),
+			name : String::from("lever"),
+			r#type : String::from(SWITCH_POINT_TYPE),
+			properties : vec![crate::tiled::TiledStringProp { name : String::from("flag"), value : String::from("door.open") }],
+		};
+		let switch = FlagSwitch::from_point(&point).unwrap();
+		let mut flags = WorldFlags::new();
+		assert!(!switch.press_if_touching(&Vec2::new(50.0, 0.0), &mut flags));
+		assert!(!flags.get_bool("door.open"));
+		assert!(switch.press_if_touching(&Vec2::new(5.0, 0.0), &mut flags));
+		assert!(flags.get_bool("door.open"));
+	}
+}
@@ -0,0 +1,145 @@
+use crate::geo::vec2::*;
+use crate::geo::line_segment::LineSegment;
+use crate::origin_rebase::ShiftOrigin;
+
+/// The slowest a rider can go along a zipline (in pixels per second). Keeps riders from getting stuck on flat (or uphill) lines.
+const MIN_RIDE_SPEED : f32 = 40.0;
+/// The fastest a rider can go along a zipline (in pixels per second).
+const MAX_RIDE_SPEED : f32 = 600.0;
+
+/// A line that can be ridden from its start to its end.
+///
+/// These come from Tiled polylines with the "zipline" type. The first point is the start, and the last is the end.
+pub struct Zipline {
+	/// The line itself.
+	line : LineSegment,
+}
+
+impl Zipline {
+	/// Creates a zipline running from the start to the end.
+	pub fn new(start : &Vec2, end : &Vec2) -> Zipline {
+		Zipline {
+			line : LineSegment::new(start, end),
+		}
+	}
+
+	/// Where the ride starts.
+	pub fn get_start(&self) -> Vec2 {
+		self.line.start
+	}
+
+	/// Where the ride ends.
+	pub fn get_end(&self) -> Vec2 {
+		self.line.end
+	}
+
+	/// The (unit length) direction that riders travel in.
+	pub fn get_direction(&self) -> Vec2 {
+		self.line.direction
+	}
+
+	/// How long the zipline is.
+	pub fn get_length(&self) -> f32 {
+		self.line.length
+	}
+
+	/// Gets how far along the zipline the closest point to a position is.
+	pub fn find_distance_along(&self, position : &Vec2) -> f32 {
+		self.line.direction.dot(position - &self.line.start).max(0.0).min(self.line.length)
+	}
+
+	/// Gets the distance from a position to the zipline.
+	pub fn distance_to(&self, position : &Vec2) -> f32 {
+		(self.line.closest_point_to(position) - position).length()
+	}
+
+	/// Gets the point some distance along the zipline.
+	pub fn get_point(&self, distance_along : f32) -> Vec2 {
+		self.line.start + self.line.direction * distance_along
+	}
+}
+
+impl ShiftOrigin for Zipline {
+	fn shift_origin(&mut self, shift : &Vec2) {
+		self.line.shift_origin(shift);
+	}
+}
+
+/// Something riding along a zipline.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZiplineRide {
+	/// The index of the zipline being ridden.
+	pub zipline : usize,
+	/// How far along the zipline the rider is.
+	pub distance_along : f32,
+	/// How fast the rider is moving along the zipline.
+	pub speed : f32,
+}
+
+impl ZiplineRide {
+	/// Starts riding a zipline, carrying over whatever part of the velocity is along it.
+	pub fn start(index : usize, zipline : &Zipline, position : &Vec2, velocity : &Vec2) -> ZiplineRide {
+		ZiplineRide {
+			zipline : index,
+			distance_along : zipline.find_distance_along(position),
+			speed : velocity.dot(zipline.get_direction()).max(MIN_RIDE_SPEED).min(MAX_RIDE_SPEED),
+		}
+	}
+
+	/// Moves along the zipline, speeding up with whatever part of gravity pulls along it.
+	/// Returns whether the end was reached.
+	pub fn update(&mut self, elapsed_seconds : f32, zipline : &Zipline, gravity : &Vec2) -> bool {
+		let acceleration = gravity.dot(zipline.get_direction());
+		self.speed = (self.speed + acceleration * elapsed_seconds).max(MIN_RIDE_SPEED).min(MAX_RIDE_SPEED);
+		self.distance_along += self.speed * elapsed_seconds;
+		if zipline.get_length() <= self.distance_along {
+			self.distance_along = zipline.get_length();
+			true
+		} else {
+			false
+		}
+	}
+
+	/// The rider's velocity (for carrying it over when detaching).
+	pub fn get_velocity(&self, zipline : &Zipline) -> Vec2 {
+		zipline.get_direction() * self.speed
+	}
+}
+
+#[cfg(test)]
+mod tests_zipline {
+	use super::*;
+	use crate::assert_vec2_about_eq;
+	use crate::geo::consts::EPSILON;
+
+	#[test]
+	fn closest_point() {
+		let zipline = Zipline::new(&Vec2::new(0.0, 100.0), &Vec2::new(100.0, 0.0));
+		assert!((zipline.find_distance_along(&Vec2::new(50.0, 50.0)) - 50.0 * 2.0f32.sqrt()).abs() < EPSILON);
+		assert_eq!(zipline.find_distance_along(&Vec2::new(-50.0, 200.0)), 0.0);
+		assert!((zipline.distance_to(&Vec2::new(0.0, 0.0)) - 50.0 * 2.0f32.sqrt()).abs() < EPSILON);
+	}
+
+	#[test]
+	fn rides_downhill() {
+		let zipline = Zipline::new(&Vec2::new(0.0, 100.0), &Vec2::new(100.0, 0.0));
+		let gravity = Vec2::new(0.0, -800.0);
+		let mut ride = ZiplineRide::start(0, &zipline, &Vec2::new(0.0, 100.0), &Vec2::new(0.0, 0.0));
+		assert_eq!(ride.speed, MIN_RIDE_SPEED);
+		assert!(!ride.update(0.1, &zipline, &gravity));
+		assert!(MIN_RIDE_SPEED < ride.speed);
+		let mut done = false;
+		for _ in 0..100 {
+			if ride.update(0.1, &zipline, &gravity) {
+				done = true;
+				break;
+			}
+		}
+		assert!(done);
+		assert_eq!(ride.distance_along, zipline.get_length());
+		let velocity = ride.get_velocity(&zipline);
+		assert!(0.0 < velocity.x && velocity.y < 0.0);
+		assert_vec2_about_eq!(zipline.get_point(ride.distance_along), zipline.get_end());
+	}
+}
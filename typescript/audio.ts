@@ -0,0 +1,224 @@
+namespace ExampleProject {
+	/// A piece of music that's playing: its main track and intensity stem, started together so they stay in sync.
+	interface _MusicVoice {
+		/// The asset name of the main track (to tell if it's changed).
+		name : string;
+		/// Everything playing (the main track, and the stem if there is one).
+		sources : AudioBufferSourceNode[];
+		/// The volume of this piece (for fading it in and out).
+		gain : GainNode;
+		/// The volume of the stem.
+		stemGain : GainNode;
+	}
+
+	/// Plays sound effects through Web Audio, optionally through a reverb or a low pass filter (see the Rust sound module for when those are used).
+	/// Also plays looping music with cross-fades and a stem layered on top (see the Rust music module for when those change).
+	export class AudioMixer {
+		/// How long the generated reverb tail is (in seconds).
+		private readonly _REVERB_SECONDS = 2.0;
+		/// The low pass cutoff for muffled sounds (in Hz).
+		private readonly _MUFFLE_FREQUENCY = 700.0;
+
+		/// The audio context. Null until the first sound plays (browsers only allow audio after the page has been interacted with).
+		private _context : AudioContext = null;
+		/// Where everything ends up (before the speakers).
+		private _master : GainNode = null;
+		/// The shared reverb everything with some reverb is sent to.
+		private _reverb : ConvolverNode = null;
+		/// Where all music goes (for turning it down while paused).
+		private _musicGain : GainNode = null;
+		/// The music that's playing (or loading). Null when there's none.
+		private _music : _MusicVoice = null;
+		/// The volume the intensity stem should be at (so it's right when a track finishes loading).
+		private _stemVolume : number = 0.0;
+		/// Decoded sounds (or loads in progress) by URL. Null for ones that failed to load, so they aren't retried every time.
+		private _buffers : Map<string, Promise<AudioBuffer>> = new Map();
+
+		/// Gets the audio context, creating it (and the shared nodes) if needed.
+		private _getContext() : AudioContext {
+			if (null === this._context) {
+				this._context = new AudioContext();
+				this._master = this._context.createGain();
+				this._master.connect(this._context.destination);
+				this._reverb = this._context.createConvolver();
+				this._reverb.buffer = this._makeImpulse(this._context);
+				this._reverb.connect(this._master);
+				this._musicGain = this._context.createGain();
+				this._musicGain.connect(this._master);
+			}
+			if ("suspended" === this._context.state) {
+				this._context.resume();
+			}
+			return this._context;
+		}
+
+		/// Makes a simple reverb impulse: noise that dies away.
+		private _makeImpulse(context : AudioContext) : AudioBuffer {
+			const length = Math.floor(context.sampleRate * this._REVERB_SECONDS);
+			const impulse = context.createBuffer(2, length, context.sampleRate);
+			for (let channel = 0; channel < impulse.numberOfChannels; channel++) {
+				const data = impulse.getChannelData(channel);
+				for (let index = 0; index < length; index++) {
+					data[index] = (2.0 * Math.random() - 1.0) * Math.pow(1.0 - index / length, 3.0);
+				}
+			}
+			return impulse;
+		}
+
+		/// Gets a sound's decoded audio, starting to load it if it hasn't been yet.
+		private _getBuffer(url : string) : Promise<AudioBuffer> {
+			let buffer = this._buffers.get(url);
+			if (undefined === buffer) {
+				const context = this._getContext();
+				buffer = fetch(url).then(
+					(response) => response.ok ? response.arrayBuffer() : Promise.reject(response.statusText)
+				).then(
+					(data) => context.decodeAudioData(data)
+				).catch((error) => {
+					console.error(`Failed loading ${url} due to:`, error);
+					return null;
+				});
+				this._buffers.set(url, buffer);
+			}
+			return buffer;
+		}
+
+		/**
+		 * Plays a sound effect once.
+		 * @param name The sound's asset name (for error messages).
+		 * @param url Where to load it from.
+		 * @param volume How loud it is (from 0 to 1).
+		 * @param reverb How much of it goes through the reverb (from 0 to 1).
+		 * @param muffled Whether it goes through a low pass filter (as in underwater or through a wall).
+		 */
+		public playSound(name : string, url : string, volume : number, reverb : number, muffled : boolean) {
+			let context : AudioContext;
+			try {
+				context = this._getContext();
+			} catch (error) {
+				console.error(`Can't play ${name} due to:`, error);
+				return;
+			}
+			this._getBuffer(url).then((buffer) => {
+				if (null === buffer) {
+					return;
+				}
+				const source = context.createBufferSource();
+				source.buffer = buffer;
+				let output : AudioNode = source;
+				if (muffled) {
+					const filter = context.createBiquadFilter();
+					filter.type = "lowpass";
+					filter.frequency.value = this._MUFFLE_FREQUENCY;
+					output.connect(filter);
+					output = filter;
+				}
+				const gain = context.createGain();
+				gain.gain.value = volume;
+				output.connect(gain);
+				gain.connect(this._master);
+				if (0.0 < reverb) {
+					const send = context.createGain();
+					send.gain.value = volume * reverb;
+					output.connect(send);
+					send.connect(this._reverb);
+				}
+				source.start();
+			});
+		}
+
+		/// Ramps a volume to some value over some seconds (from wherever it currently is).
+		private _fade(gain : GainNode, volume : number, seconds : number) {
+			const now = this._context.currentTime;
+			gain.gain.cancelScheduledValues(now);
+			gain.gain.setValueAtTime(gain.gain.value, now);
+			gain.gain.linearRampToValueAtTime(volume, now + Math.max(seconds, 0.01));
+		}
+
+		/// Fades out a piece of music, and stops it once it's silent.
+		private _fadeOutVoice(voice : _MusicVoice, seconds : number) {
+			this._fade(voice.gain, 0.0, seconds);
+			for (let source of voice.sources) {
+				source.stop(this._context.currentTime + seconds);
+			}
+		}
+
+		/**
+		 * Cross-fades to a looping piece of music.
+		 * @param name The main track's asset name.
+		 * @param url Where to load the main track from.
+		 * @param stemName The intensity stem's asset name (empty for none).
+		 * @param stemUrl Where to load the stem from (empty for none).
+		 * @param fadeSeconds How long the cross-fade takes.
+		 */
+		public playMusic(name : string, url : string, stemName : string, stemUrl : string, fadeSeconds : number) {
+			let context : AudioContext;
+			try {
+				context = this._getContext();
+			} catch (error) {
+				console.error(`Can't play ${name} due to:`, error);
+				return;
+			}
+			if (null !== this._music) {
+				this._fadeOutVoice(this._music, fadeSeconds);
+			}
+			const voice : _MusicVoice = {
+				name,
+				sources : [],
+				gain : context.createGain(),
+				stemGain : context.createGain(),
+			};
+			voice.gain.gain.value = 0.0;
+			voice.gain.connect(this._musicGain);
+			voice.stemGain.gain.value = 0.0;
+			voice.stemGain.connect(voice.gain);
+			this._music = voice;
+			this._stemVolume = 0.0;
+			const loads = [this._getBuffer(url), "" === stemUrl ? Promise.resolve(null) : this._getBuffer(stemUrl)];
+			Promise.all(loads).then(([base, stem]) => {
+				if (this._music !== voice || null === base) {
+					return; // Something else started playing while this loaded (or it didn't load).
+				}
+				// Both parts start at the same moment, so the stem lines up with the main track.
+				const start = context.currentTime + 0.05;
+				for (let [buffer, output] of [[base, voice.gain], [stem, voice.stemGain]] as [AudioBuffer, GainNode][]) {
+					if (null === buffer) { continue; }
+					const source = context.createBufferSource();
+					source.buffer = buffer;
+					source.loop = true;
+					source.connect(output);
+					source.start(start);
+					voice.sources.push(source);
+				}
+				voice.stemGain.gain.value = this._stemVolume;
+				this._fade(voice.gain, 1.0, fadeSeconds);
+			});
+		}
+
+		/// Fades out whatever music is playing over some seconds.
+		public stopMusic(fadeSeconds : number) {
+			if (null === this._music) {
+				return;
+			}
+			this._fadeOutVoice(this._music, fadeSeconds);
+			this._music = null;
+		}
+
+		/// Fades the intensity stem (of whatever music is playing) to some volume over some seconds.
+		public setMusicStemVolume(volume : number, fadeSeconds : number) {
+			this._stemVolume = volume;
+			if (null === this._music) {
+				return;
+			}
+			this._fade(this._music.stemGain, volume, fadeSeconds);
+		}
+
+		/// Fades all music to some volume over some seconds (as in turning it down while paused).
+		public setMusicVolume(volume : number, fadeSeconds : number) {
+			if (null === this._context) {
+				return; // Nothing's played yet, and it starts at full volume.
+			}
+			this._fade(this._musicGain, volume, fadeSeconds);
+		}
+	}
+}
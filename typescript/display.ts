@@ -6,6 +6,38 @@ namespace ExampleProject {
 		IMAGES = 2, // for images (composed of triangles).
 	}
 
+	/// How a display buffer's pixels are combined with what's already drawn.
+	export enum BlendMode {
+		ALPHA = 0, // Covers what's under it (by its alpha).
+		ADDITIVE = 1, // Adds to what's under it (for glows).
+		MULTIPLY = 2, // Darkens what's under it (for lighting).
+	}
+
+	/// The view that the main camera draws into (which always exists).
+	const MAIN_VIEW = 0;
+
+	/**
+	 * A region of the canvas that some of the display buffers are drawn into, with its own transform.
+	 * Properties are public as this is an internal class.
+	 */
+	class _RenderView {
+		/// Where the left edge is (as a fraction of the canvas width).
+		public left : number = 0.0;
+		/// Where the bottom edge is (as a fraction of the canvas height).
+		public bottom : number = 0.0;
+		/// How wide it is (as a fraction of the canvas width).
+		public width : number = 1.0;
+		/// How tall it is (as a fraction of the canvas height).
+		public height : number = 1.0;
+		/// The perspective transform used for everything drawn in it.
+		public transform : Float32Array = new Float32Array([
+			1.0, 0.0, 0.0, 0.0,
+			0.0, 1.0, 0.0, 0.0,
+			0.0, 0.0, 1.0, 0.0,
+			0.0, 0.0, 0.0, 1.0,
+		]);
+	}
+
 	/**
 	 * A class for storing a single object's display info.
 	 * Properties are public as this is an internal class.
@@ -21,6 +53,8 @@ namespace ExampleProject {
 		public readonly indices : WebGLBuffer;
 		/// The number of values in the indices buffer.
 		public count : number = 0;
+		/// Whether the indices buffer holds 32 bit values (for buffers with more vertices than 16 bits can index).
+		public wideIndices : boolean = false;
 		/// The transform matrix. Should have 16 elements in OpenGL's (confusing) internal memory layout.
 		public transform : Float32Array;
 		/// The rendering type used by WebGL.
@@ -29,6 +63,26 @@ namespace ExampleProject {
 		private _useTexture : boolean;
 		/// Whether the buffer should be drawn.
 		public visible : boolean = true;
+		/// How opaque to draw the buffer (multiplied into every pixel's alpha).
+		public alpha : number = 1.0;
+		/// The color to blend every pixel toward (RGB from 0 to 1), with how much to blend as the last value.
+		public tint : Float32Array = new Float32Array([0.0, 0.0, 0.0, 0.0]);
+		/// Whether the contents are not expected to change again (so they can be uploaded for static drawing).
+		public frozen : boolean = false;
+		/// How the pixels are combined with what's already drawn.
+		public blendMode : BlendMode = BlendMode.ALPHA;
+		/// Whether the depth buffer can hide this buffer's pixels.
+		public depthTest : boolean = true;
+		/// Which render views it's drawn in (one bit per view ID).
+		public views : number = 0xFFFFFFFF;
+		/// Whether the contents are drawn once per instance (rather than just once).
+		public instanced : boolean = false;
+		/// How many instances there are.
+		public instanceCount : number = 0;
+		/// The per-instance offsets (only created once the buffer is instanced).
+		public instanceOffsets : WebGLBuffer = null;
+		/// The per-instance colors (only created once the buffer is instanced).
+		public instanceColors : WebGLBuffer = null;
 
 		/// Creates an instance.
 		constructor(context : WebGL2RenderingContext, type : DisplayBufferType) {
@@ -44,6 +98,11 @@ namespace ExampleProject {
 			this.setType(context, type);
 		}
 
+		/// Whether this is drawn after all the normal buffers (as it blends onto, or ignores the depth of, whatever's under it).
+		public drawsLate() : boolean {
+			return BlendMode.ALPHA !== this.blendMode || !this.depthTest;
+		}
+
 		/// Sets the DisplayBufferType. Doesn't do anything else, as this isn't the part of the system that handles updating buffer internals.
 		public setType(context : WebGL2RenderingContext, type : DisplayBufferType) {
 			switch (type) {
@@ -84,6 +143,12 @@ namespace ExampleProject {
 		public width : number = 1;
 		/// The height in pixels.
 		public height : number = 1;
+		/// A color (RGB from 0 to 255) to make transparent when an image is loaded, or null for none.
+		public colorKey : number[] = null;
+		/// How far off (per channel) a pixel can be from the color key and still be made transparent.
+		public colorKeyTolerance : number = 0;
+		/// Whether the colors are premultiplied by the alpha when an image is loaded.
+		public premultiplied : boolean = false;
 
 		/// Creates a one pixel red texture.
 		constructor(context : WebGL2RenderingContext) {
@@ -109,20 +174,84 @@ namespace ExampleProject {
 			context.texParameteri(context.TEXTURE_2D, context.TEXTURE_MIN_FILTER, context.NEAREST);
 		}
 
-		/// Updates the texture to an image.
+		/// Updates the texture to an image (applying the color key and premultiplying, if set).
 		public setImage(context : WebGL2RenderingContext, image : HTMLImageElement) {
+			let source : HTMLImageElement | ImageData = image;
+			if (null !== this.colorKey) {
+				source = this._applyColorKey(image);
+			}
 			context.bindTexture(context.TEXTURE_2D, this.texture);
+			context.pixelStorei(context.UNPACK_PREMULTIPLY_ALPHA_WEBGL, this.premultiplied);
 			context.texImage2D(
 				context.TEXTURE_2D,
 				0, // No mipmaps
 				context.RGBA, // Store RGBA in WebGL.
 				context.RGBA, // The passed in data is RGBA too.
 				context.UNSIGNED_BYTE, // Each passed in component channel is one unsigned byte.
-				image,
+				source,
 			);
+			context.pixelStorei(context.UNPACK_PREMULTIPLY_ALPHA_WEBGL, false);
 			this.width = image.naturalWidth;
 			this.height = image.naturalHeight;
 		}
+
+		/// Resizes the texture, making it fully transparent (for copying parts of other images into).
+		public setBlank(context : WebGL2RenderingContext, width : number, height : number) {
+			context.bindTexture(context.TEXTURE_2D, this.texture);
+			context.texImage2D(
+				context.TEXTURE_2D,
+				0, // No mipmaps
+				context.RGBA, // Store RGBA in WebGL.
+				width, height,
+				0, // border?
+				context.RGBA,
+				context.UNSIGNED_BYTE,
+				null, // WebGL fills it with zeros (transparent black).
+			);
+			this.width = width;
+			this.height = height;
+		}
+
+		/// Copies part of an image into part of this texture. Positions are in pixels from the bottom left (like everything on the WASM side).
+		public blit(context : WebGL2RenderingContext, image : HTMLImageElement, sourceX : number, sourceY : number, width : number, height : number, destinationX : number, destinationY : number) {
+			// Crop through a canvas, since texSubImage2D() can only take a whole image.
+			const canvas = document.createElement("canvas");
+			canvas.width = width;
+			canvas.height = height;
+			const canvasContext = canvas.getContext("2d");
+			canvasContext.drawImage(image, sourceX, image.naturalHeight - (sourceY + height), width, height, 0, 0, width, height);
+			context.bindTexture(context.TEXTURE_2D, this.texture);
+			context.texSubImage2D(
+				context.TEXTURE_2D,
+				0, // No mipmaps
+				destinationX, this.height - (destinationY + height), // Images are stored top row first.
+				context.RGBA,
+				context.UNSIGNED_BYTE,
+				canvas,
+			);
+		}
+
+		/// Gets an image's pixels with everything close enough to the color key made fully transparent.
+		private _applyColorKey(image : HTMLImageElement) : ImageData {
+			const canvas = document.createElement("canvas");
+			canvas.width = image.naturalWidth;
+			canvas.height = image.naturalHeight;
+			const context = canvas.getContext("2d");
+			context.drawImage(image, 0, 0);
+			const data = context.getImageData(0, 0, canvas.width, canvas.height);
+			const pixels = data.data;
+			const [red, green, blue] = this.colorKey;
+			for (let index = 0;index < pixels.length;index += 4) {
+				if (
+					Math.abs(pixels[index    ] - red  ) <= this.colorKeyTolerance &&
+					Math.abs(pixels[index + 1] - green) <= this.colorKeyTolerance &&
+					Math.abs(pixels[index + 2] - blue ) <= this.colorKeyTolerance
+				) {
+					pixels[index + 3] = 0;
+				}
+			}
+			return data;
+		}
 	}
 
 	/// The type for a resize callback.
@@ -148,12 +277,16 @@ namespace ExampleProject {
 
 			in vec3 position;
 			in vec4 color;
+			in vec3 instance_offset; // Per instance (or zero when not instancing).
+			in vec4 instance_color; // Per instance (or white when not instancing).
 
 			out vec4 color_source;
+			out vec4 instance_tint;
 
 			void main() {
 				color_source = color;
-				gl_Position = perspective * transform * vec4(position, 1.0); // NOTE: GLSL normalizes based on the w term!
+				instance_tint = instance_color / 255.0;
+				gl_Position = perspective * transform * vec4(position + instance_offset, 1.0); // NOTE: GLSL normalizes based on the w term!
 			}
 		`;
 		/// The source for the fragment shader.
@@ -164,8 +297,12 @@ namespace ExampleProject {
 			uniform float use_texture;
 			uniform sampler2D texture_sampler;
 			uniform vec2 texture_size;
+			uniform float alpha;
+			uniform vec4 tint;
+			uniform float premultiplied;
 
 			in vec4 color_source;
+			in vec4 instance_tint;
 
 			out vec4 color;
 
@@ -177,6 +314,11 @@ namespace ExampleProject {
 					texture(texture_sampler, texture_position),
 					use_texture
 				);
+				color *= instance_tint;
+				// Premultiplied colors need the tint and alpha applied to the color channels too.
+				color.xyz = mix(color.xyz, mix(tint.xyz, tint.xyz * color.w, premultiplied), tint.w);
+				color.xyz *= mix(1.0, alpha, premultiplied);
+				color.w *= alpha;
 				// To prevent depth testing from killing transparency, don't set pixels if they're basically transparent.
 				if (color.w < 1e-6) {
 					discard;
@@ -188,6 +330,10 @@ namespace ExampleProject {
 		private _vertexBufferPosition : number;
 		/// The vertex color position buffer.
 		private _colorBufferPosition : number;
+		/// The position of the per-instance offset.
+		private _instanceOffsetPosition : number;
+		/// The position of the per-instance color.
+		private _instanceColorPosition : number;
 		/// The position of the transform matrix.
 		private readonly _transformPosition : WebGLUniformLocation;
 		/// The position of the perspective matrix.
@@ -198,6 +344,12 @@ namespace ExampleProject {
 		private readonly _texturePosition : WebGLUniformLocation;
 		/// The size of the texture.
 		private readonly _textureSizePosition : WebGLUniformLocation;
+		/// The position of the buffer-wide alpha.
+		private readonly _alphaPosition : WebGLUniformLocation;
+		/// The position of the buffer-wide tint.
+		private readonly _tintPosition : WebGLUniformLocation;
+		/// The position of whether the texture's colors are premultiplied.
+		private readonly _premultipliedPosition : WebGLUniformLocation;
 
 		/// A default texture to use.
 		private readonly _defaultTexture : _DisplayTexture;
@@ -210,11 +362,15 @@ namespace ExampleProject {
 		private readonly _deleted : _DisplayBuffer[] = [];
 		/// All of the buffers to draw, in the order they should be drawn.
 		private readonly _drawOrder : _DisplayBuffer[] = [];
+		/// The regions of the canvas to draw into, by ID (drawn in ID order).
+		private readonly _views : Map<number, _RenderView> = new Map([[MAIN_VIEW, new _RenderView()]]);
 
 		/// The next ID to give a new _DisplayTexture.
 		private _nextTextureID = 1;
 		/// All of the textures mapped from their ids. Deleted textures will be removed.
 		private readonly _textures : Map<number, _DisplayTexture> = new Map();
+		/// The images that have been (or are being) loaded to copy parts of into textures, by URL.
+		private readonly _blitImages : Map<string, Promise<HTMLImageElement>> = new Map();
 
 		/// A function to be called whenever the canvas resizes.
 		private _resizeCallback : DisplayResizeCallback = null;
@@ -264,11 +420,18 @@ namespace ExampleProject {
 			// Setup the shader inputs.
 			this._vertexBufferPosition = ctx.getAttribLocation(program, "position");
 			this._colorBufferPosition = ctx.getAttribLocation(program, "color");
+			this._instanceOffsetPosition = ctx.getAttribLocation(program, "instance_offset");
+			this._instanceColorPosition = ctx.getAttribLocation(program, "instance_color");
+			ctx.vertexAttribDivisor(this._instanceOffsetPosition, 1);
+			ctx.vertexAttribDivisor(this._instanceColorPosition, 1);
 			this._transformPosition = ctx.getUniformLocation(program, "transform");
 			this._perspectivePosition = ctx.getUniformLocation(program, "perspective");
 			this._useTexturePosition = ctx.getUniformLocation(program, "use_texture");
 			this._texturePosition = ctx.getUniformLocation(program, "texture_sampler");
 			this._textureSizePosition = ctx.getUniformLocation(program, "texture_size");
+			this._alphaPosition = ctx.getUniformLocation(program, "alpha");
+			this._tintPosition = ctx.getUniformLocation(program, "tint");
+			this._premultipliedPosition = ctx.getUniformLocation(program, "premultiplied");
 
 			/// Always start with a unit perspective.
 			this.perspectiveTransform = new Float32Array([
@@ -324,6 +487,14 @@ namespace ExampleProject {
 			this._buffers.set(id, buffer);
 			this._drawOrder.push(buffer);
 			if (doReset) {
+				buffer.alpha = 1.0;
+				buffer.tint = new Float32Array([0.0, 0.0, 0.0, 0.0]);
+				buffer.frozen = false;
+				buffer.blendMode = BlendMode.ALPHA;
+				buffer.depthTest = true;
+				buffer.views = 0xFFFFFFFF;
+				buffer.instanced = false;
+				buffer.instanceCount = 0;
 				this.setBuffer(id, new Float32Array([]), new Uint8Array([]), new Uint16Array([]));
 				this.setBufferTransform(id, new Float32Array([
 					1.0, 0.0, 0.0, 0.0,
@@ -346,17 +517,19 @@ namespace ExampleProject {
 			return true;
 		}
 
-		/// Sets the contents of a display buffer.
-		public setBuffer(id : number, vertices : Float32Array, colors : Uint8Array, indices : Uint16Array) : boolean {
+		/// Sets the contents of a display buffer. The indices can be 32 bit if there are too many vertices for 16 bit ones.
+		public setBuffer(id : number, vertices : Float32Array, colors : Uint8Array, indices : Uint16Array | Uint32Array) : boolean {
 			if (!this._buffers.has(id)) { return false; }
 			const buffer = this._buffers.get(id);
 			const ctx = this._context;
+			const usage = buffer.frozen ? ctx.STATIC_DRAW : ctx.DYNAMIC_DRAW;
 			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.vertices);
-			ctx.bufferData(ctx.ARRAY_BUFFER, vertices, ctx.STATIC_DRAW); // Maybe make this "dynamic draw" someday?
+			ctx.bufferData(ctx.ARRAY_BUFFER, vertices, usage);
 			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.colors);
-			ctx.bufferData(ctx.ARRAY_BUFFER, colors, ctx.STATIC_DRAW); // Maybe make this "dynamic draw" someday?
+			ctx.bufferData(ctx.ARRAY_BUFFER, colors, usage);
 			ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, buffer.indices);
-			ctx.bufferData(ctx.ELEMENT_ARRAY_BUFFER, new Uint16Array(indices), ctx.STATIC_DRAW); // Maybe make this "dynamic draw" someday?
+			buffer.wideIndices = indices instanceof Uint32Array;
+			ctx.bufferData(ctx.ELEMENT_ARRAY_BUFFER, buffer.wideIndices ? new Uint32Array(indices) : new Uint16Array(indices), usage);
 			buffer.count = indices.length;
 			return true;
 		}
@@ -377,13 +550,99 @@ namespace ExampleProject {
 			return true;
 		}
 
-		/// The overall perspective transform.
+		/// Sets how opaque a display buffer is drawn.
+		public setBufferAlpha(id : number, alpha : number) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.alpha = alpha;
+			return true;
+		}
+
+		/// Sets whether a display buffer's contents are frozen. Only affects how its contents are uploaded from then on.
+		public setBufferFrozen(id : number, frozen : boolean) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.frozen = frozen;
+			return true;
+		}
+
+		/// Sets the color a display buffer is blended toward (RGB from 0 to 1), and how much (from 0 to 1).
+		public setBufferTint(id : number, red : number, green : number, blue : number, amount : number) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.tint = new Float32Array([red, green, blue, amount]);
+			return true;
+		}
+
+		/// Sets how a display buffer's pixels are combined with what's already drawn.
+		public setBufferBlendMode(id : number, mode : BlendMode) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.blendMode = mode;
+			return true;
+		}
+
+		/// Sets whether the depth buffer can hide a display buffer's pixels.
+		public setBufferDepthTest(id : number, enabled : boolean) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.depthTest = enabled;
+			return true;
+		}
+
+		/// Whether buffers can be drawn instanced (see setBufferInstances()).
+		public supportsInstancing() : boolean {
+			return typeof this._context.drawElementsInstanced === "function";
+		}
+
+		/// Sets a display buffer to be drawn once per instance, each moved by an offset (3 floats) and multiplied by a color (4 bytes).
+		public setBufferInstances(id : number, offsets : Float32Array, colors : Uint8Array) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			const ctx = this._context;
+			if (null === buffer.instanceOffsets) {
+				buffer.instanceOffsets = ctx.createBuffer();
+				buffer.instanceColors = ctx.createBuffer();
+			}
+			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.instanceOffsets);
+			ctx.bufferData(ctx.ARRAY_BUFFER, offsets, ctx.DYNAMIC_DRAW);
+			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.instanceColors);
+			ctx.bufferData(ctx.ARRAY_BUFFER, colors, ctx.DYNAMIC_DRAW);
+			buffer.instanced = true;
+			buffer.instanceCount = offsets.length / 3;
+			return true;
+		}
+
+		/// Sets which render views a display buffer is drawn in (one bit per view ID).
+		public setBufferViews(id : number, views : number) : boolean {
+			if (!this._buffers.has(id)) { return false; }
+			const buffer = this._buffers.get(id);
+			buffer.views = views;
+			return true;
+		}
+
+		/// The main view's perspective transform.
 		set perspectiveTransform(matrix : Float32Array) {
-			this._context.uniformMatrix4fv(
-				this._perspectivePosition,
-				false,
-				matrix,
-			);
+			this._views.get(MAIN_VIEW).transform = matrix;
+		}
+
+		/// Creates or updates a render view: a region of the canvas (in fractions of its size, from the bottom left) drawn with its own perspective transform.
+		public setView(id : number, left : number, bottom : number, width : number, height : number, matrix : Float32Array) {
+			if (!this._views.has(id)) {
+				this._views.set(id, new _RenderView());
+			}
+			const view = this._views.get(id);
+			view.left = left;
+			view.bottom = bottom;
+			view.width = width;
+			view.height = height;
+			view.transform = matrix;
+		}
+
+		/// Removes a render view. The main one can't be removed.
+		public removeView(id : number) : boolean {
+			if (MAIN_VIEW === id) { return false; }
+			return this._views.delete(id);
 		}
 
 		/// Creates a texture and returns its handle.
@@ -420,6 +679,44 @@ namespace ExampleProject {
 			return true;
 		}
 
+		/// Resizes a texture to be fully transparent (for copying parts of other images into).
+		public setTextureSize(id : number, width : number, height : number) : boolean {
+			if (!this._textures.has(id)) { return false; }
+			this._textures.get(id).setBlank(this._context, width, height);
+			return true;
+		}
+
+		/// Copies part of an image (from a URL) into part of a texture once the image loads. Each image is only downloaded once.
+		public blitImage(id : number, url : string, sourceX : number, sourceY : number, width : number, height : number, destinationX : number, destinationY : number) : boolean {
+			if (!this._textures.has(id)) { return false; }
+			const texture = this._textures.get(id);
+			if (!this._blitImages.has(url)) {
+				this._blitImages.set(url, new Promise(function(resolve, reject) {
+					const image = new Image();
+					image.addEventListener("load", () => resolve(image));
+					image.addEventListener("error", reject);
+					console.log(`Starting to loading image for copying: ${url}`);
+					image.src = url;
+				}));
+			}
+			this._blitImages.get(url).then(function(image : HTMLImageElement) {
+				texture.blit(this._context, image, sourceX, sourceY, width, height, destinationX, destinationY);
+			}.bind(this)).catch(
+				(error) => console.error(`Failed copying ${url} into texture ${id} due to:`, error)
+			);
+			return true;
+		}
+
+		/// Sets how an image gets changed as it's loaded into a texture (must be called before starting to load it).
+		public setTextureOptions(id : number, useColorKey : boolean, keyRed : number, keyGreen : number, keyBlue : number, keyTolerance : number, premultiplyAlpha : boolean) : boolean {
+			if (!this._textures.has(id)) { return false; }
+			const texture = this._textures.get(id);
+			texture.colorKey = useColorKey ? [keyRed, keyGreen, keyBlue] : null;
+			texture.colorKeyTolerance = keyTolerance;
+			texture.premultiplied = premultiplyAlpha;
+			return true;
+		}
+
 		/// Sets the display buffer's texture.
 		public setBufferTexture(bufferId : number, textureId : number) {
 			if (!this._buffers.has(bufferId)) { return false; }
@@ -434,60 +731,131 @@ namespace ExampleProject {
 			const ctx = this._context;
 			// Assuming the program and buffers haven't changed, this is pretty trivial.
 
+			ctx.disable(ctx.SCISSOR_TEST);
+			ctx.depthMask(true); // The last buffer drawn may have turned off depth writes, which would stop the depth from clearing.
 			ctx.clear(ctx.COLOR_BUFFER_BIT | ctx.DEPTH_BUFFER_BIT); // Clear the color and depth of the display.
 
-			for (let buffer of this._drawOrder) {
-				if (!buffer.visible) { continue; }
-				ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.vertices);
-				ctx.vertexAttribPointer(
-					this._vertexBufferPosition,
-					3, // There are 3 floats per vertex.
-					ctx.FLOAT, // Using a Float32Array() to pass the buffer's data.
-					false, // Whether to normalize input values to some specified range. (Not needed here.)
-					0, // Don't skip any consecutive bytes when reading the buffer.
-					0, // Don't start at some byte offset when reading the buffer.
-				);
-				ctx.enableVertexAttribArray(this._vertexBufferPosition);
-
-				ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.colors);
-				ctx.vertexAttribPointer(
-					this._colorBufferPosition,
-					(buffer.useTexture) ? (2) : (4), // Textures are packed as 2 16-bit values. Colors are 4 8-bit values.
-					(buffer.useTexture) ? (ctx.UNSIGNED_SHORT) : (ctx.UNSIGNED_BYTE), // Using a Uint8Array() or a Uint16Array() to pass the buffer's data.
-					false, // Whether to normalize input values to some specified range. (Not needed here.)
-					0, // Don't skip any consecutive bytes when reading the buffer.
-					0, // Don't start at some byte offset when reading the buffer.
-				);
-				ctx.enableVertexAttribArray(this._colorBufferPosition);
+			// Buffers that blend onto (or ignore the depth of) what's under them go last, so everything they cover is already there. Otherwise it's creation order.
+			const order = this._drawOrder.filter((buffer) => !buffer.drawsLate()).concat(this._drawOrder.filter((buffer) => buffer.drawsLate()));
+			const ids = Array.from(this._views.keys()).sort((first, second) => first - second);
+			for (const id of ids) {
+				const view = this._views.get(id);
+				const left = Math.round(view.left * this._canvas.width);
+				const bottom = Math.round(view.bottom * this._canvas.height);
+				const width = Math.round(view.width * this._canvas.width);
+				const height = Math.round(view.height * this._canvas.height);
+				ctx.viewport(left, bottom, width, height);
+				if (MAIN_VIEW !== id) {
+					// Every other view starts from a blank region, so it isn't mixed into whatever's under it.
+					ctx.enable(ctx.SCISSOR_TEST);
+					ctx.scissor(left, bottom, width, height);
+					ctx.depthMask(true);
+					ctx.clear(ctx.COLOR_BUFFER_BIT | ctx.DEPTH_BUFFER_BIT);
+				}
+				ctx.uniformMatrix4fv(this._perspectivePosition, false, view.transform);
+				for (let buffer of order) {
+					if (!buffer.visible || 0 === ((buffer.views >>> id) & 1)) { continue; }
+					this._drawBuffer(buffer);
+				}
+			}
+			ctx.disable(ctx.SCISSOR_TEST);
+			ctx.viewport(0, 0, this._canvas.width, this._canvas.height);
+		}
 
-				ctx.uniformMatrix4fv(
-					this._transformPosition,
-					false,
-					buffer.transform,
-				);
+		/// Draws a single buffer (into whatever view is set up).
+		private _drawBuffer(buffer : _DisplayBuffer) {
+			const ctx = this._context;
+			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.vertices);
+			ctx.vertexAttribPointer(
+				this._vertexBufferPosition,
+				3, // There are 3 floats per vertex.
+				ctx.FLOAT, // Using a Float32Array() to pass the buffer's data.
+				false, // Whether to normalize input values to some specified range. (Not needed here.)
+				0, // Don't skip any consecutive bytes when reading the buffer.
+				0, // Don't start at some byte offset when reading the buffer.
+			);
+			ctx.enableVertexAttribArray(this._vertexBufferPosition);
 
-				// Load in the texture information.
-				ctx.uniform1f(
-					this._useTexturePosition,
-					(buffer.useTexture) ? (1.0) : (0.0),
-				)
-				let texture = this._defaultTexture;
-				if (null !== buffer.texture && this._textures.has(buffer.texture)) {
-					texture = this._textures.get(buffer.texture);
-				}
-				ctx.bindTexture(ctx.TEXTURE_2D, texture.texture);
-				ctx.uniform2f(
-					this._textureSizePosition,
-					texture.width,
-					texture.height,
-				);
+			ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.colors);
+			ctx.vertexAttribPointer(
+				this._colorBufferPosition,
+				(buffer.useTexture) ? (2) : (4), // Textures are packed as 2 16-bit values. Colors are 4 8-bit values.
+				(buffer.useTexture) ? (ctx.UNSIGNED_SHORT) : (ctx.UNSIGNED_BYTE), // Using a Uint8Array() or a Uint16Array() to pass the buffer's data.
+				false, // Whether to normalize input values to some specified range. (Not needed here.)
+				0, // Don't skip any consecutive bytes when reading the buffer.
+				0, // Don't start at some byte offset when reading the buffer.
+			);
+			ctx.enableVertexAttribArray(this._colorBufferPosition);
 
-				ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, buffer.indices);
-				// Then draw everything.
+			ctx.uniformMatrix4fv(
+				this._transformPosition,
+				false,
+				buffer.transform,
+			);
+			ctx.uniform1f(this._alphaPosition, buffer.alpha);
+			ctx.uniform4fv(this._tintPosition, buffer.tint);
+
+			// Load in the texture information.
+			ctx.uniform1f(
+				this._useTexturePosition,
+				(buffer.useTexture) ? (1.0) : (0.0),
+			)
+			let texture = this._defaultTexture;
+			if (null !== buffer.texture && this._textures.has(buffer.texture)) {
+				texture = this._textures.get(buffer.texture);
+			}
+			ctx.bindTexture(ctx.TEXTURE_2D, texture.texture);
+			const premultiplied = buffer.useTexture && texture.premultiplied;
+			ctx.uniform1f(this._premultipliedPosition, premultiplied ? 1.0 : 0.0);
+			const source = premultiplied ? ctx.ONE : ctx.SRC_ALPHA;
+			switch (buffer.blendMode) {
+				case BlendMode.ADDITIVE:
+					ctx.blendFunc(source, ctx.ONE);
+					break;
+				case BlendMode.MULTIPLY:
+					ctx.blendFunc(ctx.DST_COLOR, ctx.ONE_MINUS_SRC_ALPHA);
+					break;
+				default:
+					ctx.blendFunc(source, ctx.ONE_MINUS_SRC_ALPHA);
+			}
+			if (buffer.depthTest) {
+				ctx.enable(ctx.DEPTH_TEST);
+			} else {
+				ctx.disable(ctx.DEPTH_TEST);
+			}
+			// Only normal buffers hide what's behind them.
+			ctx.depthMask(BlendMode.ALPHA === buffer.blendMode);
+			ctx.uniform2f(
+				this._textureSizePosition,
+				texture.width,
+				texture.height,
+			);
+
+			if (buffer.instanced) {
+				ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.instanceOffsets);
+				ctx.vertexAttribPointer(this._instanceOffsetPosition, 3, ctx.FLOAT, false, 0, 0);
+				ctx.enableVertexAttribArray(this._instanceOffsetPosition);
+				ctx.bindBuffer(ctx.ARRAY_BUFFER, buffer.instanceColors);
+				ctx.vertexAttribPointer(this._instanceColorPosition, 4, ctx.UNSIGNED_BYTE, false, 0, 0);
+				ctx.enableVertexAttribArray(this._instanceColorPosition);
+			} else {
+				// Without arrays, every vertex gets the same (do nothing) values.
+				ctx.disableVertexAttribArray(this._instanceOffsetPosition);
+				ctx.vertexAttrib3f(this._instanceOffsetPosition, 0.0, 0.0, 0.0);
+				ctx.disableVertexAttribArray(this._instanceColorPosition);
+				ctx.vertexAttrib4f(this._instanceColorPosition, 255.0, 255.0, 255.0, 255.0);
+			}
+
+			ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, buffer.indices);
+			// Then draw everything.
+			const indexType = (buffer.wideIndices) ? (ctx.UNSIGNED_INT) : (ctx.UNSIGNED_SHORT); // The type used to encode the index buffer in bufferData().
+			if (buffer.instanced) {
+				ctx.drawElementsInstanced(buffer.glType, buffer.count, indexType, 0, buffer.instanceCount);
+			} else {
 				ctx.drawElements(
 					buffer.glType,
 					buffer.count, // Number of vertices in the index buffer.
-					ctx.UNSIGNED_SHORT, // The type used to encode the index buffer in bufferData().
+					indexType,
 					0, // Byte offset into the index buffer to start at.
 				);
 			}
@@ -14,6 +14,13 @@ namespace ExampleProject {
 		private _input : Input;
 		/// Manages loading Tiled files.
 		private readonly _tiled : TiledFileLoader = new TiledFileLoader();
+		/// Plays sounds.
+		private readonly _audio : AudioMixer = new AudioMixer();
+
+		/// Where the asset manifest is (see the Rust asset_manifest module).
+		private readonly _ASSET_MANIFEST_URL = "manifest.json";
+		/// How much of the preload list has loaded, from 0 to 1.
+		private _preloadProgress : number = 0.0;
 
 		/// The WASM function to call whenever things resize.
 		private _resizeCallback : (width : number, height : number) => void = null;
@@ -35,13 +42,48 @@ namespace ExampleProject {
 
 		/// Run once the document is setup. This is where the WASM is loaded in.
 		public setup() {
+			// Fetch the asset manifest alongside the WASM, as everything else loaded is looked up in it.
+			// It's fine if it's missing (assets are then loaded by name), so that failure just gets logged.
+			const manifest = fetch(this._ASSET_MANIFEST_URL, {cache: "no-cache"}).then(
+				(response) => response.ok ? response.text() : Promise.reject(response.statusText)
+			).catch(function(error) {
+				console.error(`Failed loading ${this._ASSET_MANIFEST_URL} due to:`, error);
+				return null;
+			}.bind(this));
 			// Once GAME is set, try loading in the WASM.
 			// The wasm_bindgen() method requires that GAME exists to link against it.
-			wasm_bindgen("rust.wasm").then(function(wasm) {
+			Promise.all([wasm_bindgen("rust.wasm"), manifest]).then(function([wasm, manifestText]) {
 				// The "wasm" argument is the raw WASM object. It has all the same methods as wasm_bindgen, except that they have no JS interfacing setup...
 				// So use wasm_bindgen's "namespace" instead.
-				this._start();
-			}.bind(this));
+				if (null !== manifestText) {
+					wasm_bindgen.set_asset_manifest(manifestText);
+				}
+				return this._preload();
+			}.bind(this)).then(
+				() => this._start()
+			);
+		}
+
+		/// Loads everything the manifest says to preload (so it's in the browser's cache before the game asks for it), keeping track of the progress for the loading screen.
+		/// Failures are only logged, as the game will just try again when it needs them.
+		private _preload() : Promise<void> {
+			const urls : string[] = JSON.parse(wasm_bindgen.get_asset_preload_urls());
+			this._preloadProgress = (0 === urls.length) ? 1.0 : 0.0;
+			let done = 0;
+			return Promise.all(urls.map((url) => fetch(url).then(
+				(response) => response.blob()
+			).catch(
+				(error) => console.error(`Failed preloading ${url} due to:`, error)
+			).then(() => {
+				done += 1;
+				this._preloadProgress = done / urls.length;
+				console.log(`Preloaded ${done} of ${urls.length}: ${url}`);
+			}))).then(() => undefined);
+		}
+
+		/// How much of the preload list has loaded, from 0 to 1 (for the loading screen).
+		get preloadProgress() : number {
+			return this._preloadProgress;
 		}
 
 		/// Checks if this machine is little-endian.
@@ -63,11 +105,20 @@ namespace ExampleProject {
 			this._tiled.setup(
 				wasm_bindgen.tiled_generate_add_tile,
 				wasm_bindgen.tiled_generate_add_tile_boolean_property,
+				wasm_bindgen.tiled_generate_add_tile_string_property,
 				wasm_bindgen.tiled_generate_add_tile_collision_rectangle,
 				wasm_bindgen.tiled_generate_add_tile_collision_polygon,
 				wasm_bindgen.tiled_generate_add_point,
+				wasm_bindgen.tiled_generate_add_point_property,
+				wasm_bindgen.tiled_generate_add_polyline,
+				wasm_bindgen.tiled_generate_add_polyline_property,
+				wasm_bindgen.tiled_generate_add_region,
 				wasm_bindgen.tiled_generate_add_tile_layer,
 				wasm_bindgen.tiled_generation_done,
+				wasm_bindgen.tiled_generate_from_buffer,
+				wasm_bindgen.tiled_generate_from_json,
+				wasm_bindgen.tiled_generate_add_image_property,
+				wasm_bindgen.tiled_generate_add_map_property,
 			);
 
 			wasm_bindgen.setup(this._isLittleEndian());
@@ -87,6 +138,126 @@ namespace ExampleProject {
 			setInterval(this._update.bind(this), this._UPDATE_PERIOD);
 		}
 
+		/// Pauses the simulation (for debugging from the console).
+		public debugPause() {
+			wasm_bindgen.debug_pause();
+		}
+
+		/// Resumes the simulation (for debugging from the console).
+		public debugResume() {
+			wasm_bindgen.debug_resume();
+		}
+
+		/// Pauses the simulation and moves it forward some number of fixed steps (for debugging from the console).
+		public debugStep(count : number = 1) {
+			wasm_bindgen.debug_step(count);
+		}
+
+		/// Forgets every recorded player death (for debugging from the console). Press H in game to show them as a heatmap.
+		public debugClearDeaths() {
+			wasm_bindgen.debug_clear_deaths();
+		}
+
+		/// Snapshots the simulation and keeps it in memory (for debugging from the console).
+		public debugQuickSave() {
+			wasm_bindgen.debug_quick_save();
+		}
+
+		/// Puts the simulation back to the last quick save (for debugging from the console). Only works in the same room.
+		public debugQuickLoad() {
+			wasm_bindgen.debug_quick_load();
+		}
+
+		/// Starts or stops auditing collision queries (for debugging from the console). Frames where they take longer than the budget get logged, with where the worst one was.
+		public debugCollisionAudit(enabled : boolean = true, budgetMilliseconds : number = 4) {
+			wasm_bindgen.debug_collision_audit(enabled, budgetMilliseconds);
+		}
+
+		/// Gets all the physics anomaly captures (for debugging from the console).
+		public debugAnomalyCaptures() : any[] {
+			return JSON.parse(wasm_bindgen.get_anomaly_captures());
+		}
+
+		/// Describes a tile by its column and row in a layer (for host tooling). Gives null if there's no such tile.
+		public queryTile(x : number, y : number, layer : number = 0) : any {
+			return JSON.parse(wasm_bindgen.query_tile(x, y, layer));
+		}
+
+		/// Describes the collision obstacles touching a position in map coordinates (for host tooling).
+		public queryCollisionAt(x : number, y : number) : any {
+			return JSON.parse(wasm_bindgen.query_collision_at(x, y));
+		}
+
+		/// Describes the current map (for host tooling). Gives null while it's loading.
+		public mapMetadata() : any {
+			return JSON.parse(wasm_bindgen.get_map_metadata());
+		}
+
+		/// Changes an accessibility setting (reduceFlashing, highContrast, holdToggle, textScale, uiScale, or palette). It's saved between visits.
+		public setAccessibility(name : string, value : boolean | number | string) {
+			wasm_bindgen.accessibility_set(name, String(value));
+		}
+
+		/// Gets all the accessibility settings.
+		public accessibilitySettings() : string {
+			return wasm_bindgen.accessibility_get();
+		}
+
+		/// Switches all text to a locale's fonts and line breaking (a tag like "ja" or "zh-TW").
+		public setTextLocale(locale : string) {
+			wasm_bindgen.set_text_locale(locale);
+		}
+
+		/// Turns camera-relative rendering on or off (it keeps big maps from jittering far from the origin).
+		public setRelativeRendering(enabled : boolean) {
+			wasm_bindgen.set_relative_rendering(enabled);
+		}
+
+		/// Sets a virtual resolution (like 480 by 270) that's scaled up to fill the screen, with letterboxing around the rest. The scaling is "integer" (crisp pixels) or "fit". A zero size turns it off.
+		public setVirtualResolution(width : number, height : number, scaling : string = "integer") {
+			wasm_bindgen.set_virtual_resolution(width, height, scaling);
+		}
+
+		/// Gets the state of the simulation as JSON (only in builds with the "serialize" feature).
+		public getSnapshot() : any {
+			return JSON.parse(wasm_bindgen.get_snapshot());
+		}
+
+		/// Puts the simulation back the way a snapshot from getSnapshot() had it (only in builds with the "serialize" feature). Returns whether that worked.
+		public restoreSnapshot(snapshot : any) : boolean {
+			return wasm_bindgen.restore_snapshot(JSON.stringify(snapshot));
+		}
+
+		/// Turns origin rebasing on or off (it keeps physics precise on big maps by moving the world back toward the origin as the player travels).
+		public setOriginRebasing(enabled : boolean) {
+			wasm_bindgen.set_origin_rebasing(enabled);
+		}
+
+		/// Gets the device the player last used ("keyboard" or "gamepad").
+		public inputDevice() : string {
+			return wasm_bindgen.get_input_device();
+		}
+
+		/// Shows or hides the minimap.
+		public setMinimapVisible(visible : boolean) {
+			wasm_bindgen.set_minimap_visible(visible);
+		}
+
+		/// Adds or removes a second, local player (IJKL and O on the keyboard, or the second gamepad).
+		public setSecondPlayer(enabled : boolean) {
+			wasm_bindgen.set_second_player(enabled);
+		}
+
+		/// Starts loading a ghost (as from getLastRunGhost(), possibly another player's) to race against. It shows up whenever its room is played.
+		public loadGhost(url : string) {
+			wasm_bindgen.load_ghost(url);
+		}
+
+		/// Gets the last finished run as a packed ghost, for sharing with other players. Empty if no run has been finished yet.
+		public getLastRunGhost() : Uint8Array {
+			return wasm_bindgen.get_last_run_ghost();
+		}
+
 		/// An example exported method.
 		public exportExample(value : number) {
 			console.log(`WASM requested that this print ${value}`);
@@ -110,6 +281,7 @@ namespace ExampleProject {
 			const gamepad = this._input.getChangedGamepadState();
 			if (gamepad) {
 				wasm_bindgen.on_gamepad_changed(
+					gamepad.index,
 					gamepad.valid,
 					new Float32Array(gamepad.buttons),
 					new Float32Array(gamepad.analogSticks),
@@ -122,6 +294,16 @@ namespace ExampleProject {
 			requestAnimationFrame(this._draw.bind(this));
 		}
 
+		/// Whether a function exists on this (dotted names are looked up on child objects, like "text.setText"). Used by the WASM's startup self-test.
+		public hasGameFunction(name : string) : boolean {
+			let owner : any = this;
+			const parts = name.split(".");
+			for (const part of parts.slice(0, -1)) {
+				owner = owner?.[part];
+			}
+			return "function" === typeof owner?.[parts[parts.length - 1]];
+		}
+
 		/// Creates a buffer and returns its ID.
 		public createDrawBuffer(type_ : DisplayBufferType) : number {
 			return this._display.createBuffer(type_);
@@ -137,6 +319,11 @@ namespace ExampleProject {
 			return this._display.setBuffer(id, vertices, colors, indices);
 		}
 
+		/// Sets the contents of a display buffer that has too many vertices for 16 bit indices.
+		public setDisplayBufferWide(id : number, vertices : Float32Array, colors : Uint8Array, indices : Uint32Array) : boolean {
+			return this._display.setBuffer(id, vertices, colors, indices);
+		}
+
 		/// Sets the transform on a display buffer.
 		public setDisplayBufferTransform(id : number, matrix : Float32Array) : boolean {
 			return this._display.setBufferTransform(id, matrix);
@@ -147,11 +334,61 @@ namespace ExampleProject {
 			this._display.perspectiveTransform = matrix;
 		}
 
+		/// Creates or updates a render view (a region of the canvas with its own transform).
+		public setRenderView(id : number, left : number, bottom : number, width : number, height : number, matrix : Float32Array) {
+			this._display.setView(id, left, bottom, width, height, matrix);
+		}
+
+		/// Removes a render view.
+		public removeRenderView(id : number) : boolean {
+			return this._display.removeView(id);
+		}
+
+		/// Whether display buffers can be drawn instanced.
+		public supportsInstancing() : boolean {
+			return this._display.supportsInstancing();
+		}
+
+		/// Sets a display buffer to be drawn once per instance.
+		public setDisplayBufferInstances(id : number, offsets : Float32Array, colors : Uint8Array) : boolean {
+			return this._display.setBufferInstances(id, offsets, colors);
+		}
+
+		/// Sets which render views a display buffer is drawn in (one bit per view ID).
+		public setDisplayBufferViews(id : number, views : number) : boolean {
+			return this._display.setBufferViews(id, views);
+		}
+
 		/// Sets whether a display buffer is visible.
 		public setDisplayBufferVisibility(id : number, visible : boolean) : boolean {
 			return this._display.setBufferVisibility(id, visible);
 		}
 
+		/// Sets how opaque a display buffer is drawn.
+		public setDisplayBufferAlpha(id : number, alpha : number) : boolean {
+			return this._display.setBufferAlpha(id, alpha);
+		}
+
+		/// Sets whether a display buffer's contents are frozen (uploaded for static drawing).
+		public setDisplayBufferFrozen(id : number, frozen : boolean) : boolean {
+			return this._display.setBufferFrozen(id, frozen);
+		}
+
+		/// Sets the color a display buffer is blended toward, and how much.
+		public setDisplayBufferTint(id : number, red : number, green : number, blue : number, amount : number) : boolean {
+			return this._display.setBufferTint(id, red, green, blue, amount);
+		}
+
+		/// Sets how a display buffer's pixels are combined with what's already drawn.
+		public setDisplayBufferBlendMode(id : number, mode : BlendMode) : boolean {
+			return this._display.setBufferBlendMode(id, mode);
+		}
+
+		/// Sets whether the depth buffer can hide a display buffer's pixels.
+		public setDisplayBufferDepthTest(id : number, enabled : boolean) : boolean {
+			return this._display.setBufferDepthTest(id, enabled);
+		}
+
 		/// Creates a texture for the Display and returns it's new ID.
 		public createDrawTexture() : number {
 			return this._display.createTexture();
@@ -167,6 +404,21 @@ namespace ExampleProject {
 			return this._display.setTextureWithURL(id, url);
 		}
 
+		/// Resizes a Display texture to be fully transparent (for copying parts of images into). Returns if that worked.
+		public setDrawTextureSize(id : number, width : number, height : number) : boolean {
+			return this._display.setTextureSize(id, width, height);
+		}
+
+		/// Copies part of an image (from a URL) into part of a Display texture once it loads. Returns if this started without issue.
+		public blitImageIntoTexture(id : number, url : string, sourceX : number, sourceY : number, width : number, height : number, destinationX : number, destinationY : number) : boolean {
+			return this._display.blitImage(id, url, sourceX, sourceY, width, height, destinationX, destinationY);
+		}
+
+		/// Sets how an image gets changed as it's loaded into a Display texture (color keying and premultiplying alpha).
+		public setDrawTextureOptions(id : number, useColorKey : boolean, keyRed : number, keyGreen : number, keyBlue : number, keyTolerance : number, premultiplyAlpha : boolean) : boolean {
+			return this._display.setTextureOptions(id, useColorKey, keyRed, keyGreen, keyBlue, keyTolerance, premultiplyAlpha);
+		}
+
 		/// Links a Display buffer to a given texture.
 		public setDrawBufferTexture(bufferId : number, textureId : number) : boolean {
 			return this._display.setBufferTexture(bufferId, textureId);
@@ -177,8 +429,61 @@ namespace ExampleProject {
 			return this._display?.text;
 		}
 
-		public startTiledFileLoad(url : string) {
-			this._tiled.startLoading(url);
+		/// The sound management object.
+		get audio() : AudioMixer {
+			return this._audio;
+		}
+
+		/// Starts loading a Tiled file from a URL. The name is what it's reported back to WASM as (the URL may have a version on it, see the asset manifest).
+		public startTiledFileLoad(name : string, url : string) {
+			this._tiled.startLoading(name, url);
+		}
+
+		/// Starts loading enemy definitions from a JSON file (an object mapping definition names to their properties).
+		public startEnemyDefinitionsLoad(name : string, url : string) {
+			fetch(url).then(
+				(response) => response.json()
+			).then(function(json : any) {
+				for (let definition of Object.keys(json)) {
+					const properties = json[definition];
+					for (let name of Object.keys(properties)) {
+						wasm_bindgen.enemy_definition_set_property(definition, name, String(properties[name])); // All types are passed as strings (so lists become comma separated).
+					}
+				}
+				wasm_bindgen.enemy_definitions_done(name);
+			}).catch(
+				(error) => console.error(`Failed loading ${url} due to:`, error)
+			);
+		}
+
+		/// Fetches a packed ghost and hands it to the game (see loadGhost()).
+		public startGhostLoad(name : string, url : string) {
+			fetch(url).then(
+				(response) => response.arrayBuffer()
+			).then(function(buffer : ArrayBuffer) {
+				wasm_bindgen.ghost_loaded(name, new Uint8Array(buffer));
+			}).catch(
+				(error) => console.error(`Failed loading ${url} due to:`, error)
+			);
+		}
+
+		/// Stores some saved data (in local storage, so it's kept between visits).
+		public storeSaveData(key : string, data : string) {
+			try {
+				window.localStorage.setItem(key, data);
+			} catch (error) {
+				console.error(`Failed saving ${key} due to:`, error);
+			}
+		}
+
+		/// Gets some saved data. Returns an empty string if nothing was saved.
+		public loadSaveData(key : string) : string {
+			try {
+				return window.localStorage.getItem(key) ?? "";
+			} catch (error) {
+				console.error(`Failed loading ${key} due to:`, error);
+				return "";
+			}
 		}
 
 		/**
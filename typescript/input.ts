@@ -12,6 +12,8 @@ namespace ExampleProject {
 	export class GamepadState {
 		/// If this actually exists. Gets set to true once know that an actual Gamepad object was used to construct this.
 		public valid : boolean = false;
+		/// Which gamepad this is (the index the DOM gives it).
+		public index : number = 0;
 		/// The button states.
 		public readonly buttons : number[] = [];
 		/// The state of any analog sticks.
@@ -176,6 +178,7 @@ namespace ExampleProject {
 			for (let index = 0;index < gamepads.length;index += 1) {
 				// Store the data in a way that's easy to check.
 				const current = new GamepadState(gamepads[index]);
+				current.index = index;
 				// Compare it to the known state (if there was one).
 				// If find **any** change, then this is the right gamepad.
 				if (!this._gamepads[index] || !this._gamepads[index].equals(current)) {
@@ -4,10 +4,58 @@ namespace ExampleProject {
 		/// All the text elements ordered according to ID.
 		/// Elements are never removed, just replaced with null.
 		private _elements : HTMLDivElement[] = [];
+		/// Where all the text goes: the part of the container the game is drawn in.
+		private _area : HTMLDivElement;
+		/// A canvas context set to the current font, for measuring text. Null until needed (or when the font changes).
+		private _measureContext : CanvasRenderingContext2D = null;
 
 		/// Creates an instance using the given element to store text in.
 		constructor(private readonly _container : HTMLElement) {
-			//
+			this._area = document.createElement("div");
+			this._area.style.position = "absolute";
+			this._area.style.pointerEvents = "none";
+			this.setLayout(0.0, 0.0, 1.0, 1.0, 1.0);
+			this._container.appendChild(this._area);
+		}
+
+		/// Sets how big all text is drawn (1 is normal). Sizes given in "em" or "ex" grow with it.
+		public setTextScale(scale : number) {
+			this._area.style.fontSize = `${100.0 * scale}%`;
+			this._measureContext = null; // The font size changed.
+		}
+
+		/**
+		 * Fits all text to the part of the container the game is drawn in (as when it's letterboxed).
+		 * @param left Where the left edge is (as a fraction of the container width).
+		 * @param bottom Where the bottom edge is (as a fraction of the container height).
+		 * @param width How wide it is (as a fraction of the container width).
+		 * @param height How tall it is (as a fraction of the container height).
+		 * @param uiScale How much text point positions and pixel sizes are multiplied by.
+		 */
+		public setLayout(left : number, bottom : number, width : number, height : number, uiScale : number) {
+			this._area.style.left = `${100.0 * left}%`;
+			this._area.style.top = `${100.0 * (1.0 - bottom - height)}%`;
+			this._area.style.width = `${100.0 * width}%`;
+			this._area.style.height = `${100.0 * height}%`;
+			this._area.style.setProperty("--ui-scale", String(uiScale));
+		}
+
+		/// Switches all text to a locale's font stack (a CSS font-family value). The lang attribute gets the browser to use that locale's line breaking rules.
+		public setFontStack(locale : string, fontStack : string) {
+			this._area.lang = locale;
+			this._area.style.fontFamily = fontStack;
+			this._area.style.overflowWrap = "anywhere"; // So long runs without spaces still wrap.
+			this._measureContext = null; // The font changed.
+		}
+
+		/// Measures how wide some (plain) text is in the current font (in pixels).
+		public measureTextWidth(text : string) : number {
+			if (null === this._measureContext) {
+				this._measureContext = document.createElement("canvas").getContext("2d");
+				const style = window.getComputedStyle(this._area);
+				this._measureContext.font = `${style.fontSize} ${style.fontFamily}`;
+			}
+			return this._measureContext.measureText(text).width;
 		}
 
 		/// Creates and stores a text box element and returns its id.
@@ -31,8 +79,8 @@ namespace ExampleProject {
 
 		/**
 		 * Adds a text box using a specific point. Returns the handle to the element.
-		 * @param x The x position in pixels. Zero is the center of the screen. The left half is negative.
-		 * @param y The y position in pixels. Zero is the center of the screen. The bottom half is negative.
+		 * @param x The x position in pixels. Zero is the center of the game area, and it grows with the UI scale. The left half is negative.
+		 * @param y The y position in pixels. Zero is the center of the game area, and it grows with the UI scale. The bottom half is negative.
 		 * @param hoirzontal The horizontal position of the (x, y) coordinate relative to the text. 0.0 puts the point on the left. 1.0 puts it on the right.
 		 * @param vertical The vertical position of the (x, y) coordinate relative to the text. 0.0 puts the point at the top. 1.0 puts it at the bottom.
 		 * @param width The width of the text box in CSS units (I'd recommend "em").
@@ -48,15 +96,15 @@ namespace ExampleProject {
 			this.positionTextPoint(id, x, y, horizontal, vertical, width, height);
 			this.setText(id, color, alignment, text);
 
-			this._container.appendChild(this._getTextBox(id));
+			this._area.appendChild(this._getTextBox(id));
 			return id;
 		}
 
 		/**
 		 * Sets the position of a text box using a specific point.
 		 * @param id The text box's ID.
-		 * @param x The x position in pixels. Zero is the center of the screen. The left half is negative.
-		 * @param y The y position in pixels. Zero is the center of the screen. The bottom half is negative.
+		 * @param x The x position in pixels. Zero is the center of the game area, and it grows with the UI scale. The left half is negative.
+		 * @param y The y position in pixels. Zero is the center of the game area, and it grows with the UI scale. The bottom half is negative.
 		 * @param hoirzontal The horizontal position of the (x, y) coordinate relative to the text. 0.0 puts the point on the left. 1.0 puts it on the right.
 		 * @param vertical The vertical position of the (x, y) coordinate relative to the text. 0.0 puts the point at the top. 1.0 puts it at the bottom.
 		 * @param width The width of the text box in CSS units (I'd recommend "em").
@@ -64,18 +112,18 @@ namespace ExampleProject {
 		 */
 		public positionTextPoint(id : number, x : number, y : number, horizontal : number, vertical : number, width : string, height : string) {
 			const element = this._getTextBox(id);
-			element.style.top  = `calc(50% - ${y}px - ${vertical} * ${height} )`;
-			element.style.left = `calc(50% + ${x}px - ${horizontal} * ${width} )`;
+			element.style.top  = `calc(50% - ${y}px * var(--ui-scale, 1) - ${vertical} * ${height} )`;
+			element.style.left = `calc(50% + ${x}px * var(--ui-scale, 1) - ${horizontal} * ${width} )`;
 			element.style.width  = width;
 			element.style.height = height;
 		}
 
 		/**
 		 * Adds a text box using view size percentages. Returns the handle to the element.
-		 * @param top The position of the top edge of the text box. Always a percentage (0.0 = top of screen, 1.0 = bottom of screen).
-		 * @param left The position of the left edge of the text box. Always a percentage (0.0 = left side of screen, 1.0 = right side of screen).
-		 * @param bottom The position of the bottom edge of the text box. Always a percentage (0.0 = top of screen, 1.0 = bottom of screen).
-		 * @param right The position of the right edge of the text box. Always a percentage (0.0 = left side of screen, 1.0 = right side of screen).
+		 * @param top The position of the top edge of the text box. Always a percentage (0.0 = top of the game area, 1.0 = bottom of it).
+		 * @param left The position of the left edge of the text box. Always a percentage (0.0 = left side of the game area, 1.0 = right side of it).
+		 * @param bottom The position of the bottom edge of the text box. Always a percentage (0.0 = top of the game area, 1.0 = bottom of it).
+		 * @param right The position of the right edge of the text box. Always a percentage (0.0 = left side of the game area, 1.0 = right side of it).
 		 * @param color A CSS color value.
 		 * @param alignment The CSS "text-align" value to use.
 		 * @param text The (HTML) text to fill it with.
@@ -87,17 +135,17 @@ namespace ExampleProject {
 			this.positionTextArea(id, top, left, bottom, right);
 			this.setText(id, color, alignment, text);
 
-			this._container.appendChild(this._getTextBox(id));
+			this._area.appendChild(this._getTextBox(id));
 			return id;
 		}
 
 		/**
 		 * Positions the given text box using view size percentages.
 		 * @param id The text box's ID.
-		 * @param top The position of the top edge of the text box. Always a percentage (0.0 = top of screen, 1.0 = bottom of screen).
-		 * @param left The position of the left edge of the text box. Always a percentage (0.0 = left side of screen, 1.0 = right side of screen).
-		 * @param bottom The position of the bottom edge of the text box. Always a percentage (0.0 = top of screen, 1.0 = bottom of screen).
-		 * @param right The position of the right edge of the text box. Always a percentage (0.0 = left side of screen, 1.0 = right side of screen).
+		 * @param top The position of the top edge of the text box. Always a percentage (0.0 = top of the game area, 1.0 = bottom of it).
+		 * @param left The position of the left edge of the text box. Always a percentage (0.0 = left side of the game area, 1.0 = right side of it).
+		 * @param bottom The position of the bottom edge of the text box. Always a percentage (0.0 = top of the game area, 1.0 = bottom of it).
+		 * @param right The position of the right edge of the text box. Always a percentage (0.0 = left side of the game area, 1.0 = right side of it).
 		 */
 		public positionTextArea(id : number, top : number, left : number, bottom : number, right : number) {
 			const element = this._getTextBox(id);
@@ -29,6 +29,13 @@ namespace ExampleProject {
 		}
 	}
 
+	/// A class for storing a string property.
+	class _StringProperty {
+		constructor(public name : string, public value : string) {
+			//
+		}
+	}
+
 	/// A class for storing partial information about a tile.
 	class _PartialTileInfo {
 		/// The image to get the tile from.
@@ -42,7 +49,7 @@ namespace ExampleProject {
 		/// The height.
 		public height : number = 1;
 		/// The properties.
-		public properties : _BooleanProperty[] = []; // TODO: Add more property types.
+		public properties : (_BooleanProperty | _StringProperty)[] = []; // TODO: Add more property types.
 		/// The collision rectangle info.
 		public collisionRectangles : _CollisionRect[] = [];
 		/// The collision polygon info.
@@ -51,11 +58,282 @@ namespace ExampleProject {
 
 	type AddTileFunc = (url : string, imageUrl : string, x : number, y : number, width : number, height : number) => void;
 	type AddTileBooleanPropertyFunc = (url : string, name : string, value : boolean) => void;
+	type AddTileStringPropertyFunc = (url : string, name : string, value : string) => void;
 	type AddTileCollisionRectangleFunc = (url : string, type : string, x1 : number, y1 : number, x2 : number, y2 : number) => void;
 	type AddTileCollisionPolygonFunc = (url : string, type : string, values : Float32Array) => void;
-	type AddTilePointFunc = (url : string, name : string, x : number, y : number) => void;
+	type AddTilePointFunc = (url : string, name : string, type : string, x : number, y : number) => void;
+	type AddPointPropertyFunc = (url : string, name : string, value : string) => void;
+	type AddPolylineFunc = (url : string, name : string, type : string, values : Float32Array) => void;
+	type AddPolylinePropertyFunc = (url : string, name : string, value : string) => void;
+	type AddRegionFunc = (url : string, name : string, type : string, x1 : number, y1 : number, x2 : number, y2 : number) => void;
 	type AddTileLayerFunc = (url : string, name : string, xOffset : number, yOffset : number, width : number, height : number, pixelWidth : number, pixelHeight : number, data : Uint32Array) => void;
 	type OnDoneFunc = (url : string) => void;
+	type FromBufferFunc = (url : string, bytes : Uint8Array) => void;
+	type FromJsonFunc = (url : string, text : string) => void;
+	type AddImagePropertyFunc = (url : string, imageUrl : string, name : string, value : string) => void;
+	type AddMapPropertyFunc = (url : string, name : string, value : string) => void;
+
+	/// Something that takes in the parts of a map as they're parsed (from the JSON export).
+	interface _MapSink {
+		addTile(imageUrl : string, x : number, y : number, width : number, height : number) : void;
+		addTileBooleanProperty(name : string, value : boolean) : void;
+		addTileStringProperty(name : string, value : string) : void;
+		addTileCollisionRectangle(type : string, x1 : number, y1 : number, x2 : number, y2 : number) : void;
+		addTileCollisionPolygon(type : string, values : Float32Array) : void;
+		addPoint(name : string, type : string, x : number, y : number) : void;
+		addPointProperty(name : string, value : string) : void;
+		addPolyline(name : string, type : string, values : Float32Array) : void;
+		addPolylineProperty(name : string, value : string) : void;
+		addRegion(name : string, type : string, x1 : number, y1 : number, x2 : number, y2 : number) : void;
+		addTileLayer(name : string, xOffset : number, yOffset : number, width : number, height : number, pixelWidth : number, pixelHeight : number, data : Uint32Array) : void;
+		addImageProperty(imageUrl : string, name : string, value : string) : void;
+		addMapProperty(name : string, value : string) : void;
+		/// Called once everything has been added.
+		finish() : void;
+	}
+
+	/// Builds up a packed map buffer (see `TiledFile::read_packed()` on the Rust side for the layout), so the whole map can be handed to WASM in one call.
+	class _PackedMapWriter implements _MapSink {
+		/// The bytes that every packed map buffer starts with.
+		private static readonly _MAGIC = "RBTM";
+		/// The version of the packed map format being written.
+		private static readonly _VERSION = 3;
+
+		/// The tiles (each with their properties and collision geometry).
+		private _tiles : _PartialTileInfo[] = [];
+		/// The tile layers.
+		private _layers : { name : string, xOffset : number, yOffset : number, width : number, height : number, pixelWidth : number, pixelHeight : number, data : Uint32Array }[] = [];
+		/// The points (with their properties).
+		private _points : { name : string, type : string, x : number, y : number, properties : _StringProperty[] }[] = [];
+		/// The polylines (with their properties).
+		private _polylines : { name : string, type : string, values : Float32Array, properties : _StringProperty[] }[] = [];
+		/// The regions.
+		private _regions : { name : string, type : string, x1 : number, y1 : number, x2 : number, y2 : number }[] = [];
+		/// The tileset image properties.
+		private _imageProperties : { imageUrl : string, name : string, value : string }[] = [];
+		/// The map's own properties.
+		private _mapProperties : _StringProperty[] = [];
+
+		/// The bytes written so far.
+		private _bytes : Uint8Array = new Uint8Array(1024);
+		/// A view for writing numbers into the bytes.
+		private _view : DataView = new DataView(this._bytes.buffer);
+		/// How many bytes have been written.
+		private _length : number = 0;
+		/// For turning strings into UTF-8.
+		private readonly _encoder = new TextEncoder();
+
+		constructor(private readonly _url : string, private readonly _fromBuffer : FromBufferFunc) {
+			//
+		}
+
+		public addTile(imageUrl : string, x : number, y : number, width : number, height : number) {
+			const tile = new _PartialTileInfo();
+			tile.url = imageUrl;
+			tile.x = x;
+			tile.y = y;
+			tile.width = width;
+			tile.height = height;
+			this._tiles.push(tile);
+		}
+
+		public addTileBooleanProperty(name : string, value : boolean) {
+			this._tiles[this._tiles.length - 1].properties.push(new _BooleanProperty(name, value));
+		}
+
+		public addTileStringProperty(name : string, value : string) {
+			this._tiles[this._tiles.length - 1].properties.push(new _StringProperty(name, value));
+		}
+
+		public addTileCollisionRectangle(type : string, x1 : number, y1 : number, x2 : number, y2 : number) {
+			const rectangle = new _CollisionRect();
+			rectangle.type = type;
+			rectangle.x1 = x1;
+			rectangle.y1 = y1;
+			rectangle.x2 = x2;
+			rectangle.y2 = y2;
+			this._tiles[this._tiles.length - 1].collisionRectangles.push(rectangle);
+		}
+
+		public addTileCollisionPolygon(type : string, values : Float32Array) {
+			const polygon = new _CollisionPolygon();
+			polygon.type = type;
+			polygon.values = Array.from(values);
+			this._tiles[this._tiles.length - 1].collisionPolygons.push(polygon);
+		}
+
+		public addPoint(name : string, type : string, x : number, y : number) {
+			this._points.push({ name, type, x, y, properties : [] });
+		}
+
+		public addPointProperty(name : string, value : string) {
+			this._points[this._points.length - 1].properties.push(new _StringProperty(name, value));
+		}
+
+		public addPolyline(name : string, type : string, values : Float32Array) {
+			this._polylines.push({ name, type, values, properties : [] });
+		}
+
+		public addPolylineProperty(name : string, value : string) {
+			this._polylines[this._polylines.length - 1].properties.push(new _StringProperty(name, value));
+		}
+
+		public addRegion(name : string, type : string, x1 : number, y1 : number, x2 : number, y2 : number) {
+			this._regions.push({ name, type, x1, y1, x2, y2 });
+		}
+
+		public addTileLayer(name : string, xOffset : number, yOffset : number, width : number, height : number, pixelWidth : number, pixelHeight : number, data : Uint32Array) {
+			this._layers.push({ name, xOffset, yOffset, width, height, pixelWidth, pixelHeight, data });
+		}
+
+		public addImageProperty(imageUrl : string, name : string, value : string) {
+			this._imageProperties.push({ imageUrl, name, value });
+		}
+
+		public addMapProperty(name : string, value : string) {
+			this._mapProperties.push(new _StringProperty(name, value));
+		}
+
+		/// Packs everything up and hands it to WASM.
+		public finish() {
+			this._writeRaw(this._encoder.encode(_PackedMapWriter._MAGIC));
+			this._writeU32(_PackedMapWriter._VERSION);
+			this._writeU32(this._tiles.length);
+			for (let tile of this._tiles) {
+				this._writeString(tile.url);
+				this._writeU16(tile.x);
+				this._writeU16(tile.y);
+				this._writeU16(tile.width);
+				this._writeU16(tile.height);
+				const booleans = tile.properties.filter((property) => property instanceof _BooleanProperty) as _BooleanProperty[];
+				this._writeU32(booleans.length);
+				for (let property of booleans) {
+					this._writeString(property.name);
+					this._writeU8(property.value ? 1 : 0);
+				}
+				this._writeProperties(tile.properties.filter((property) => property instanceof _StringProperty) as _StringProperty[]);
+				this._writeU32(tile.collisionRectangles.length);
+				for (let rectangle of tile.collisionRectangles) {
+					this._writeString(rectangle.type);
+					this._writeF32(rectangle.x1);
+					this._writeF32(rectangle.y1);
+					this._writeF32(rectangle.x2);
+					this._writeF32(rectangle.y2);
+				}
+				this._writeU32(tile.collisionPolygons.length);
+				for (let polygon of tile.collisionPolygons) {
+					this._writeString(polygon.type);
+					this._writeF32List(polygon.values);
+				}
+			}
+			this._writeU32(this._layers.length);
+			for (let layer of this._layers) {
+				this._writeString(layer.name);
+				this._writeF32(layer.xOffset);
+				this._writeF32(layer.yOffset);
+				this._writeU32(layer.width);
+				this._writeU32(layer.height);
+				this._writeU32(layer.pixelWidth);
+				this._writeU32(layer.pixelHeight);
+				this._writeU32(layer.data.length);
+				for (let id of layer.data) {
+					this._writeU32(id);
+				}
+			}
+			this._writeU32(this._points.length);
+			for (let point of this._points) {
+				this._writeString(point.name);
+				this._writeString(point.type);
+				this._writeF32(point.x);
+				this._writeF32(point.y);
+				this._writeProperties(point.properties);
+			}
+			this._writeU32(this._polylines.length);
+			for (let polyline of this._polylines) {
+				this._writeString(polyline.name);
+				this._writeString(polyline.type);
+				this._writeF32List(polyline.values);
+				this._writeProperties(polyline.properties);
+			}
+			this._writeU32(this._regions.length);
+			for (let region of this._regions) {
+				this._writeString(region.name);
+				this._writeString(region.type);
+				this._writeF32(region.x1);
+				this._writeF32(region.y1);
+				this._writeF32(region.x2);
+				this._writeF32(region.y2);
+			}
+			this._writeU32(this._imageProperties.length);
+			for (let property of this._imageProperties) {
+				this._writeString(property.imageUrl);
+				this._writeString(property.name);
+				this._writeString(property.value);
+			}
+			this._writeProperties(this._mapProperties);
+			this._fromBuffer(this._url, this._bytes.subarray(0, this._length));
+		}
+
+		/// Makes sure there's room for some more bytes.
+		private _reserve(count : number) {
+			if (this._length + count <= this._bytes.length) { return; }
+			const bytes = new Uint8Array(Math.max(this._bytes.length * 2, this._length + count));
+			bytes.set(this._bytes.subarray(0, this._length));
+			this._bytes = bytes;
+			this._view = new DataView(bytes.buffer);
+		}
+
+		private _writeRaw(bytes : Uint8Array) {
+			this._reserve(bytes.length);
+			this._bytes.set(bytes, this._length);
+			this._length += bytes.length;
+		}
+
+		private _writeU8(value : number) {
+			this._reserve(1);
+			this._view.setUint8(this._length, value);
+			this._length += 1;
+		}
+
+		private _writeU16(value : number) {
+			this._reserve(2);
+			this._view.setUint16(this._length, value, true);
+			this._length += 2;
+		}
+
+		private _writeU32(value : number) {
+			this._reserve(4);
+			this._view.setUint32(this._length, value, true);
+			this._length += 4;
+		}
+
+		private _writeF32(value : number) {
+			this._reserve(4);
+			this._view.setFloat32(this._length, value, true);
+			this._length += 4;
+		}
+
+		private _writeString(text : string) {
+			const bytes = this._encoder.encode(text);
+			this._writeU32(bytes.length);
+			this._writeRaw(bytes);
+		}
+
+		private _writeF32List(values : ArrayLike<number>) {
+			this._writeU32(values.length);
+			for (let index = 0;index < values.length;index += 1) {
+				this._writeF32(values[index]);
+			}
+		}
+
+		private _writeProperties(properties : _StringProperty[]) {
+			this._writeU32(properties.length);
+			for (let property of properties) {
+				this._writeString(property.name);
+				this._writeString(property.value);
+			}
+		}
+	}
 
 	/**
 	 * A class for loading in JSON exports from Tiled.
@@ -63,29 +341,83 @@ namespace ExampleProject {
 	export class TiledFileLoader {
 		private _addTile : AddTileFunc = null;
 		private _addTileBooleanProperty : AddTileBooleanPropertyFunc = null;
+		private _addTileStringProperty : AddTileStringPropertyFunc = null;
 		private _addTileCollisionRectangle : AddTileCollisionRectangleFunc = null;
 		private _addTileCollisionPolygon : AddTileCollisionPolygonFunc = null;
 		private _addPoint : AddTilePointFunc = null;
+		private _addPointProperty : AddPointPropertyFunc = null;
+		private _addPolyline : AddPolylineFunc = null;
+		private _addPolylineProperty : AddPolylinePropertyFunc = null;
+		private _addRegion : AddRegionFunc = null;
 		private _addTileLayer : AddTileLayerFunc = null;
 		private _onDone : OnDoneFunc = null;
+		private _fromBuffer : FromBufferFunc = null;
+		private _fromJson : FromJsonFunc = null;
+		private _addImageProperty : AddImagePropertyFunc = null;
+		private _addMapProperty : AddMapPropertyFunc = null;
 
 		/// Stores callbacks useful for loading tile info.
-		public setup(addTile : AddTileFunc, addTileBooleanProperty : AddTileBooleanPropertyFunc, addTileCollisionRectangle : AddTileCollisionRectangleFunc, addTileCollisionPolygon : AddTileCollisionPolygonFunc, addPoint : AddTilePointFunc, addTileLayer : AddTileLayerFunc, onDone : OnDoneFunc) {
+		public setup(addTile : AddTileFunc, addTileBooleanProperty : AddTileBooleanPropertyFunc, addTileStringProperty : AddTileStringPropertyFunc, addTileCollisionRectangle : AddTileCollisionRectangleFunc, addTileCollisionPolygon : AddTileCollisionPolygonFunc, addPoint : AddTilePointFunc, addPointProperty : AddPointPropertyFunc, addPolyline : AddPolylineFunc, addPolylineProperty : AddPolylinePropertyFunc, addRegion : AddRegionFunc, addTileLayer : AddTileLayerFunc, onDone : OnDoneFunc, fromBuffer : FromBufferFunc = null, fromJson : FromJsonFunc = null, addImageProperty : AddImagePropertyFunc = null, addMapProperty : AddMapPropertyFunc = null) {
 			this._addTile = addTile;
 			this._addTileBooleanProperty = addTileBooleanProperty;
+			this._addTileStringProperty = addTileStringProperty;
 			this._addTileCollisionRectangle = addTileCollisionRectangle;
 			this._addTileCollisionPolygon = addTileCollisionPolygon;
 			this._addPoint = addPoint;
+			this._addPointProperty = addPointProperty;
+			this._addPolyline = addPolyline;
+			this._addPolylineProperty = addPolylineProperty;
+			this._addRegion = addRegion;
 			this._addTileLayer = addTileLayer;
 			this._onDone = onDone;
+			this._fromBuffer = fromBuffer;
+			this._fromJson = fromJson;
+			this._addImageProperty = addImageProperty;
+			this._addMapProperty = addMapProperty;
 		}
 
-		/// Starts loading the file at the given URL.
-		public startLoading(url : string) {
-			let sourceUrl = url;
+		/// Creates the thing to send a map's parts to. Packs everything into one buffer when possible (which is much faster than a call per item), otherwise uses the individual callbacks.
+		private _createSink(url : string) : _MapSink {
+			if (this._fromBuffer) {
+				return new _PackedMapWriter(url, this._fromBuffer);
+			}
+			return {
+				addTile : (imageUrl, x, y, width, height) => this._addTile(url, imageUrl, x, y, width, height),
+				addTileBooleanProperty : (name, value) => this._addTileBooleanProperty(url, name, value),
+				addTileStringProperty : (name, value) => this._addTileStringProperty(url, name, value),
+				addTileCollisionRectangle : (type, x1, y1, x2, y2) => this._addTileCollisionRectangle(url, type, x1, y1, x2, y2),
+				addTileCollisionPolygon : (type, values) => this._addTileCollisionPolygon(url, type, values),
+				addPoint : (name, type, x, y) => this._addPoint(url, name, type, x, y),
+				addPointProperty : (name, value) => this._addPointProperty(url, name, value),
+				addPolyline : (name, type, values) => this._addPolyline(url, name, type, values),
+				addPolylineProperty : (name, value) => this._addPolylineProperty(url, name, value),
+				addRegion : (name, type, x1, y1, x2, y2) => this._addRegion(url, name, type, x1, y1, x2, y2),
+				addTileLayer : (name, xOffset, yOffset, width, height, pixelWidth, pixelHeight, data) => this._addTileLayer(url, name, xOffset, yOffset, width, height, pixelWidth, pixelHeight, data),
+				addImageProperty : (imageUrl, name, value) => this._addImageProperty?.(url, imageUrl, name, value),
+				addMapProperty : (name, value) => this._addMapProperty?.(url, name, value),
+				finish : () => this._onDone(url),
+			};
+		}
+
+		/// Starts loading the file at the given URL. Everything is reported back to WASM under the given name.
+		public startLoading(name : string, url : string) {
+			let sourceUrl = name;
+			if (this._fromJson) {
+				// Let WASM parse the whole thing.
+				fetch(url).then(
+					(response) => response.text()
+				).then(
+					(text) => this._fromJson(sourceUrl, text)
+				).catch(
+					(error) => console.error(`Failed loading ${sourceUrl} due to:`, error)
+				);
+				return;
+			}
+			// Otherwise parse it here, and hand the parts over.
 			fetch(url).then(
 				(response) => response.json()
 			).then(function(json : any){
+				const sink = this._createSink(sourceUrl);
 				// First map all the tile IDs to the data.
 				const tileIdToInfo : Map<number, _PartialTileInfo> = new Map();
 				let maxId : number = 0;
@@ -129,6 +461,16 @@ namespace ExampleProject {
 						console.error(`Tileset #${tilesetIndex} has no "tiles" in file ${sourceUrl}`);
 						continue;
 					}
+					// The tileset's own properties apply to its image (as in color keys), with Tiled's transparent color as a fallback.
+					const tilesetProperties : any[] = tileset["properties"] ?? [];
+					for (let property of tilesetProperties) {
+						if (undefined === property["name"] || undefined === property["value"]) { continue; }
+						sink.addImageProperty(imageUrl, property["name"], String(property["value"])); // All types are passed as strings.
+					}
+					const transparentColor : string = tileset["transparentcolor"];
+					if (undefined !== transparentColor && !tilesetProperties.some((property) => "colorKey" === property["name"])) {
+						sink.addImageProperty(imageUrl, "colorKey", transparentColor);
+					}
 					for (let tileIndex = 0;tileIndex < tiles.length;tileIndex += 1) {
 						const tile : any = tiles[tileIndex];
 						const tileSetId : number = tile["id"];
@@ -139,17 +481,20 @@ namespace ExampleProject {
 						tileInfo.y = (tileColumnCount - Math.floor(tileSetId / tileRowCount) - 1) * tileHeight;
 						tileInfo.width = tileWidth;
 						tileInfo.height = tileHeight;
-						// Add the boolean property information.
+						// Add the boolean and string property information.
 						const properties : any[] = tile["properties"];
 						if (properties) {
 							for (let property of properties) {
+								const name = property["name"];
+								if (undefined === name) { continue; }
+								const value = property["value"];
+								if (undefined === value) { continue; }
 								if ("bool" === property["type"]) {
-									const name = property["name"];
-									if (undefined === name) { continue; }
-									const value = property["value"];
-									if (undefined === value) { continue; }
 									tileInfo.properties.push(new _BooleanProperty(name, value));
 								}
+								if ("string" === property["type"]) {
+									tileInfo.properties.push(new _StringProperty(name, value));
+								}
 							}
 						}
 						// Then get the collision information.
@@ -208,15 +553,17 @@ namespace ExampleProject {
 					if (!info) {
 						info = new _PartialTileInfo(); // Resort to the defaults.
 					}
-					this._addTile(sourceUrl, info.url, info.x, info.y, info.width, info.height);
+					sink.addTile(info.url, info.x, info.y, info.width, info.height);
 					for (let property of info.properties) {
 						if (property instanceof _BooleanProperty) {
-							this._addTileBooleanProperty(sourceUrl, property.name, property.value);
+							sink.addTileBooleanProperty(property.name, property.value);
+						}
+						if (property instanceof _StringProperty) {
+							sink.addTileStringProperty(property.name, property.value);
 						}
 					}
 					for (let rectangle of info.collisionRectangles) {
-						this._addTileCollisionRectangle(
-							sourceUrl,
+						sink.addTileCollisionRectangle(
 							rectangle.type,
 							rectangle.x1,
 							rectangle.y1,
@@ -225,14 +572,18 @@ namespace ExampleProject {
 						);
 					}
 					for (let polygon of info.collisionPolygons) {
-						this._addTileCollisionPolygon(
-							sourceUrl,
+						sink.addTileCollisionPolygon(
 							polygon.type,
 							new Float32Array(polygon.values),
 						);
 					}
 				}
 				// Then add all the layers.
+				// The map's own properties (as in its music).
+				for (let property of json["properties"] ?? []) {
+					if (undefined === property["name"] || undefined === property["value"]) { continue; }
+					sink.addMapProperty(property["name"], String(property["value"])); // All types are passed as strings.
+				}
 				const layers : any[] = json["layers"];
 				for (let layerIndex = 0;layerIndex < layers.length;layerIndex += 1) {
 					const layer : any = layers[layerIndex];
@@ -271,7 +622,7 @@ namespace ExampleProject {
 						const pixelWidth  = width  * maxTileWidth;
 						const pixelHeight = height * maxTileHeight;
 						// Then store it.
-						this._addTileLayer(sourceUrl, name, xOffset, yOffset, width, height, pixelWidth, pixelHeight, new Uint32Array(data));
+						sink.addTileLayer(name, xOffset, yOffset, width, height, pixelWidth, pixelHeight, new Uint32Array(data));
 					} else if ("objectgroup" === layer["type"]) {
 						// Handle the geometry layers.
 						// Mostly just extract a few useful bits of information.
@@ -294,7 +645,76 @@ namespace ExampleProject {
 									console.error(`Object #${objectIndex} in layer #{layerIndex} has no "y" in file ${sourceUrl}`);
 									continue;
 								}
-								this._addPoint(sourceUrl, name, x, y);
+								let type : string = object["type"];
+								if (undefined === type) {
+									type = "";
+								}
+								sink.addPoint(name, type, x, y);
+								const properties : any[] = object["properties"];
+								if (properties) {
+									for (let property of properties) {
+										const propertyName = property["name"];
+										if (undefined === propertyName) { continue; }
+										const value = property["value"];
+										if (undefined === value) { continue; }
+										sink.addPointProperty(propertyName, String(value)); // All types are passed as strings.
+									}
+								}
+							} else if (undefined !== object["polyline"]) {
+								let name : string = object["name"];
+								if (undefined === name) {
+									name = "";
+								}
+								let type : string = object["type"];
+								if (undefined === type) {
+									type = "";
+								}
+								const x : number = object["x"];
+								const y : number = object["y"];
+								if (undefined === x || undefined === y) {
+									console.error(`Polyline #${objectIndex} in layer #{layerIndex} has no position in file ${sourceUrl}`);
+									continue;
+								}
+								const values : number[] = [];
+								let broken = false;
+								for (let point of object["polyline"]) {
+									const point_x = point["x"];
+									if (undefined === point_x) { broken = true; break; }
+									const point_y = point["y"];
+									if (undefined === point_y) { broken = true; break; }
+									values.push(x + point_x, y + point_y);
+								}
+								if (broken) {
+									console.error(`Polyline #${objectIndex} in layer #{layerIndex} has a broken point in file ${sourceUrl}`);
+									continue;
+								}
+								sink.addPolyline(name, type, new Float32Array(values));
+								const properties : any[] = object["properties"];
+								if (properties) {
+									for (let property of properties) {
+										const propertyName = property["name"];
+										if (undefined === propertyName) { continue; }
+										const value = property["value"];
+										if (undefined === value) { continue; }
+										sink.addPolylineProperty(propertyName, String(value)); // All types are passed as strings.
+									}
+								}
+							} else if (undefined !== object["width"] && undefined !== object["height"] && undefined === object["ellipse"] && undefined === object["polygon"]) {
+								let name : string = object["name"];
+								if (undefined === name) {
+									name = "";
+								}
+								let type : string = object["type"];
+								if (undefined === type) {
+									type = "";
+								}
+								const x : number = object["x"];
+								const y : number = object["y"];
+								if (undefined === x || undefined === y) {
+									console.error(`Rectangle #${objectIndex} in layer #{layerIndex} has no position in file ${sourceUrl}`);
+									continue;
+								}
+								sink.addRegion(name, type, x, y, x + object["width"], y + object["height"]);
 							} else {
 								console.warn(`Object #${objectIndex} in layer #{layerIndex} has an recognized type  in file ${sourceUrl}`);
 							}
@@ -303,7 +723,7 @@ namespace ExampleProject {
 						console.warn(`Unsure of how to deal with Tiled layer type ${layer["type"]} in file ${sourceUrl}`);
 					}
 				}
-				this._onDone(sourceUrl);
+				sink.finish();
 			}.bind(this)).catch(
 				(error) => console.error(`Failed loading ${sourceUrl} due to:`, error)
 			);